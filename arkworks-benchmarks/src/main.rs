@@ -6,13 +6,15 @@ use ark_groth16::Groth16;
 use ark_marlin::Marlin;
 use ark_poly::univariate::DensePolynomial;
 use ark_poly_commit::{ipa_pc::InnerProductArgPC, marlin_pc::MarlinKZG10, sonic_pc::SonicKZG10};
+use ark_r1cs_std::{alloc::AllocVar, fields::fp::FpVar};
+use ark_relations::r1cs::ConstraintSystem;
 use ark_std::{self, test_rng, time::Instant, vec::Vec};
 use arkworks_native_gadgets::{
 	merkle_tree::SparseMerkleTree,
 	poseidon::{FieldHasher, Poseidon},
 };
 use arkworks_r1cs_circuits::anchor::AnchorCircuit;
-use arkworks_r1cs_gadgets::poseidon::PoseidonGadget;
+use arkworks_r1cs_gadgets::{poseidon::PoseidonGadget, uniqueness::UniquenessGadget};
 
 use arkworks_utils::utils::common::{setup_params_x5_3, setup_params_x5_4};
 use blake2::Blake2s;
@@ -205,6 +207,37 @@ fn benchmark_marlin_ipa_pc(nc: usize, nv: usize, num_iter: u32) {
 	benchmark_marlin!(MarlinSetup, EdBlsFr, "Marlin_IPA_PC", nc, nv, num_iter);
 }
 
+fn benchmark_nullifier_uniqueness() {
+	let rng = &mut test_rng();
+	for n in [2, 16, 64, 256, 1024, 2048] {
+		let nullifiers = (0..n).map(|_| BlsFr::rand(rng)).collect::<Vec<_>>();
+
+		let cs = ConstraintSystem::<BlsFr>::new_ref();
+		let vars = Vec::<FpVar<BlsFr>>::new_input(cs.clone(), || Ok(nullifiers.clone())).unwrap();
+		let start = Instant::now();
+		UniquenessGadget::new(vars).enforce_unique_sorted().unwrap();
+		println!(
+			"Uniqueness (sorted) of {} nullifiers: {} constraints in {:?}",
+			n,
+			cs.num_constraints(),
+			start.elapsed()
+		);
+
+		let cs = ConstraintSystem::<BlsFr>::new_ref();
+		let vars = Vec::<FpVar<BlsFr>>::new_input(cs.clone(), || Ok(nullifiers)).unwrap();
+		let start = Instant::now();
+		UniquenessGadget::new(vars)
+			.enforce_unique_pairwise()
+			.unwrap();
+		println!(
+			"Uniqueness (pairwise) of {} nullifiers: {} constraints in {:?}",
+			n,
+			cs.num_constraints(),
+			start.elapsed()
+		);
+	}
+}
+
 fn main() {
 	let nc = 65536;
 	let nv = 65536;
@@ -218,4 +251,6 @@ fn main() {
 	benchmark_marlin_sonic(nc, nv, num_iter);
 	// IPA
 	benchmark_marlin_ipa_pc(nc, nv, num_iter);
+	// Constraint counts
+	benchmark_nullifier_uniqueness();
 }
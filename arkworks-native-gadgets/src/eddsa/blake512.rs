@@ -0,0 +1,188 @@
+// This file is part of Webb.
+//
+// Copyright (C) 2021 Webb Technologies Inc.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The BLAKE-512 hash function, as submitted to the SHA-3 competition.
+//!
+//! circomlib derives EdDSA keys and nonces with BLAKE-512 (not BLAKE2), so it
+//! is implemented here to stay compatible with keys produced by circomlib.
+
+use ark_std::vec::Vec;
+
+const IV: [u64; 8] = [
+	0x6a09e667f3bcc908,
+	0xbb67ae8584caa73b,
+	0x3c6ef372fe94f82b,
+	0xa54ff53a5f1d36f1,
+	0x510e527fade682d1,
+	0x9b05688c2b3e6c1f,
+	0x1f83d9abfb41bd6b,
+	0x5be0cd19137e2179,
+];
+
+const C: [u64; 16] = [
+	0x243f6a8885a308d3,
+	0x13198a2e03707344,
+	0xa4093822299f31d0,
+	0x082efa98ec4e6c89,
+	0x452821e638d01377,
+	0xbe5466cf34e90c6c,
+	0xc0ac29b7c97c50dd,
+	0x3f84d5b5b5470917,
+	0x9216d5d98979fb1b,
+	0xd1310ba698dfb5ac,
+	0x2ffd72dbd01adfb7,
+	0xb8e1afed6a267e96,
+	0xba7c9045f12c7f99,
+	0x24a19947b3916cf7,
+	0x0801f2e2858efc16,
+	0x636920d871574e69,
+];
+
+const SIGMA: [[usize; 16]; 10] = [
+	[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+	[14, 10, 4, 8, 9, 15, 13, 6, 1, 12, 0, 2, 11, 7, 5, 3],
+	[11, 8, 12, 0, 5, 2, 15, 13, 10, 14, 3, 6, 7, 1, 9, 4],
+	[7, 9, 3, 1, 13, 12, 11, 14, 2, 6, 5, 10, 4, 0, 15, 8],
+	[9, 0, 5, 7, 2, 4, 10, 15, 14, 1, 11, 12, 6, 8, 3, 13],
+	[2, 12, 6, 10, 0, 11, 8, 3, 4, 13, 7, 5, 15, 14, 1, 9],
+	[12, 5, 1, 15, 14, 13, 4, 10, 0, 7, 6, 3, 9, 2, 8, 11],
+	[13, 11, 7, 14, 12, 1, 3, 9, 5, 0, 15, 4, 8, 6, 2, 10],
+	[6, 15, 14, 9, 11, 3, 0, 8, 12, 2, 13, 7, 1, 4, 10, 5],
+	[10, 2, 8, 4, 7, 6, 1, 5, 15, 11, 9, 14, 3, 12, 13, 0],
+];
+
+const ROUNDS: usize = 16;
+const BLOCK_BYTES: usize = 128;
+
+fn compress(h: &mut [u64; 8], block: &[u8], counter: u128) {
+	let mut m = [0u64; 16];
+	for (i, word) in m.iter_mut().enumerate() {
+		let mut bytes = [0u8; 8];
+		bytes.copy_from_slice(&block[8 * i..8 * i + 8]);
+		*word = u64::from_be_bytes(bytes);
+	}
+
+	let t0 = counter as u64;
+	let t1 = (counter >> 64) as u64;
+	let mut v = [0u64; 16];
+	v[..8].copy_from_slice(h);
+	v[8..12].copy_from_slice(&C[..4]);
+	v[12] = t0 ^ C[4];
+	v[13] = t0 ^ C[5];
+	v[14] = t1 ^ C[6];
+	v[15] = t1 ^ C[7];
+
+	for r in 0..ROUNDS {
+		let s = &SIGMA[r % 10];
+		let mut g = |a: usize, b: usize, c: usize, d: usize, i: usize| {
+			v[a] = v[a]
+				.wrapping_add(v[b])
+				.wrapping_add(m[s[2 * i]] ^ C[s[2 * i + 1]]);
+			v[d] = (v[d] ^ v[a]).rotate_right(32);
+			v[c] = v[c].wrapping_add(v[d]);
+			v[b] = (v[b] ^ v[c]).rotate_right(25);
+			v[a] = v[a]
+				.wrapping_add(v[b])
+				.wrapping_add(m[s[2 * i + 1]] ^ C[s[2 * i]]);
+			v[d] = (v[d] ^ v[a]).rotate_right(16);
+			v[c] = v[c].wrapping_add(v[d]);
+			v[b] = (v[b] ^ v[c]).rotate_right(11);
+		};
+		g(0, 4, 8, 12, 0);
+		g(1, 5, 9, 13, 1);
+		g(2, 6, 10, 14, 2);
+		g(3, 7, 11, 15, 3);
+		g(0, 5, 10, 15, 4);
+		g(1, 6, 11, 12, 5);
+		g(2, 7, 8, 13, 6);
+		g(3, 4, 9, 14, 7);
+	}
+
+	for i in 0..8 {
+		h[i] ^= v[i] ^ v[i + 8];
+	}
+}
+
+/// Hashes `msg` with BLAKE-512 and returns the 64 byte digest
+pub fn blake512(msg: &[u8]) -> [u8; 64] {
+	let msg_bits = (msg.len() as u128) * 8;
+
+	// Padding: a one bit, zeros, a one bit, and the 128-bit message length,
+	// so that the padded message is a multiple of the block size.
+	let mut padded: Vec<u8> = msg.to_vec();
+	padded.push(0x80);
+	while padded.len() % BLOCK_BYTES != BLOCK_BYTES - 16 {
+		padded.push(0);
+	}
+	let last = padded.len() - 1;
+	padded[last] |= 0x01;
+	padded.extend_from_slice(&msg_bits.to_be_bytes());
+
+	let mut h = IV;
+	for (i, block) in padded.chunks(BLOCK_BYTES).enumerate() {
+		// The counter holds the number of message bits hashed so far, and is
+		// zero for a block that only contains padding.
+		let start_bits = (i * BLOCK_BYTES * 8) as u128;
+		let counter = if msg_bits > start_bits {
+			msg_bits.min(start_bits + (BLOCK_BYTES * 8) as u128)
+		} else {
+			0
+		};
+		compress(&mut h, block, counter);
+	}
+
+	let mut digest = [0u8; 64];
+	for (i, word) in h.iter().enumerate() {
+		digest[8 * i..8 * i + 8].copy_from_slice(&word.to_be_bytes());
+	}
+	digest
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn to_hex(bytes: &[u8]) -> Vec<u8> {
+		bytes
+			.iter()
+			.flat_map(|b| {
+				let digits = b"0123456789abcdef";
+				vec![digits[(b >> 4) as usize], digits[(b & 0x0f) as usize]]
+			})
+			.collect()
+	}
+
+	#[test]
+	fn should_match_reference_digests() {
+		// Test vectors from the BLAKE submission to the SHA-3 competition
+		let one_block = blake512(&[0u8]);
+		assert_eq!(
+			to_hex(&one_block),
+			b"97961587f6d970faba6d2478045de6d1fabd09b61ae50932054d52bc29d31be4\
+			ff9102b9f69e2bbdb83be13d4b9c06091e5fa0b48bd081b634058be0ec49beb3"
+				.to_vec()
+		);
+
+		let two_blocks = blake512(&[0u8; 144]);
+		assert_eq!(
+			to_hex(&two_blocks),
+			b"313717d608e9cf758dcb1eb0f0c3cf9fc150b2d500fb33f51c52afc99d358a2f\
+			1374b8a38bba7974e7f6ef79cab16f22ce1e649d6e01ad9589c213045d545dde"
+				.to_vec()
+		);
+	}
+}
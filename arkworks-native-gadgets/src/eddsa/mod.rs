@@ -0,0 +1,362 @@
+// This file is part of Webb.
+//
+// Copyright (C) 2021 Webb Technologies Inc.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A native implementation of EdDSA-Poseidon signatures over Baby Jubjub
+//! (`ed_on_bn254`), compatible with circomlib's `signPoseidon` and
+//! `verifyPoseidon`.
+//!
+//! # Coordinates
+//! circomlib uses the twisted Edwards form `168700 x^2 + y^2 = 1 + 168696 x^2
+//! y^2`, while arkworks uses the isomorphic form `x^2 + y^2 = 1 + d x^2 y^2`
+//! with `d = 168696 / 168700`. A point `(x, y)` in circomlib coordinates is
+//! the point `(x * sqrt(168700), y)` in arkworks coordinates. Everything that
+//! is hashed or exposed by this module (public keys, the `R8` point of a
+//! signature) uses circomlib coordinates, the arkworks form is only used for
+//! the group arithmetic.
+//!
+//! # Scheme
+//! With `B8` the generator of the prime order subgroup and `L` its order:
+//! - the private key is 32 bytes `k`. `h = blake512(k)`, the scalar `s` is the
+//!   pruned first half of `h` and the public key is `A = (s >> 3) * B8`,
+//! - a signature of a field element `msg` is `(R8, S)` where `r =
+//!   blake512(h[32..64] || msg) mod L`, `R8 = r * B8`, `hm = Poseidon(R8, A,
+//!   msg)` and `S = r + hm * s mod L`,
+//! - a signature is valid iff `S * B8 == R8 + (8 * hm) * A`.
+//!
+//! `hm` is computed with the Poseidon hash of width 6 over the BN254 scalar
+//! field, so the hasher given to this module must use those parameters.
+//!
+//! Only the holder of the private key can produce a signature, so a prover
+//! that receives a public key and a signature can show that a spend was
+//! authorized without ever learning the private key.
+
+use crate::{poseidon::FieldHasher, Error};
+use ark_ec::{AffineCurve, ProjectiveCurve};
+use ark_ed_on_bn254::{EdwardsAffine, Fq, Fr};
+use ark_ff::{field_new, BigInteger, Field, PrimeField, Zero};
+use ark_std::{rand::Rng, string::ToString, vec::Vec};
+
+pub mod blake512;
+
+use blake512::blake512;
+
+/// x coordinate of the base point `B8`, in circomlib coordinates
+pub const BASE8_X: Fq = field_new!(
+	Fq,
+	"5299619240641551281634865583518297030282874472190772894086521144482721001553"
+);
+/// y coordinate of the base point `B8`, in circomlib coordinates
+pub const BASE8_Y: Fq = field_new!(
+	Fq,
+	"16950150798460657717958625567821834550301663161624707787222815936182638968203"
+);
+/// Square root of circomlib's curve coefficient `a = 168700`. Scaling the x
+/// coordinate by it maps circomlib coordinates to arkworks coordinates.
+pub const SQRT_A: Fq = field_new!(
+	Fq,
+	"7214280148105020021932206872019688659210616427216992810330019057549499971851"
+);
+
+#[derive(Debug)]
+pub enum EdDSAError {
+	/// Thrown if coordinates are not a point of the prime order subgroup
+	InvalidPoint,
+}
+
+impl core::fmt::Display for EdDSAError {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		let msg = match self {
+			EdDSAError::InvalidPoint => "point is not in the prime order subgroup".to_string(),
+		};
+		write!(f, "{}", msg)
+	}
+}
+
+impl ark_std::error::Error for EdDSAError {}
+
+/// Maps a point given in circomlib coordinates to arkworks coordinates.
+/// Fails if the point is not on the curve or not in the prime order subgroup.
+pub fn from_circom_coordinates(x: Fq, y: Fq) -> Result<EdwardsAffine, EdDSAError> {
+	let point = EdwardsAffine::new(x * SQRT_A, y);
+	if !point.is_on_curve() || !point.is_in_correct_subgroup_assuming_on_curve() {
+		return Err(EdDSAError::InvalidPoint);
+	}
+	Ok(point)
+}
+
+/// Maps a point in arkworks coordinates to circomlib coordinates
+pub fn to_circom_coordinates(point: &EdwardsAffine) -> (Fq, Fq) {
+	// SQRT_A is a non-zero constant, so the inverse always exists
+	let sqrt_a_inv = SQRT_A.inverse().unwrap();
+	(point.x * sqrt_a_inv, point.y)
+}
+
+/// The base point `B8`, in arkworks coordinates
+pub fn base8() -> EdwardsAffine {
+	EdwardsAffine::new(BASE8_X * SQRT_A, BASE8_Y)
+}
+
+/// Reduces a field element of the base field into the scalar field
+fn to_scalar(value: &Fq) -> Fr {
+	Fr::from_le_bytes_mod_order(&value.into_repr().to_bytes_le())
+}
+
+/// Embeds a scalar into the base field, which is larger than the scalar
+/// field, so circuits over the base field can take scalars as inputs
+pub fn to_base_field(scalar: &Fr) -> Fq {
+	Fq::from_le_bytes_mod_order(&scalar.into_repr().to_bytes_le())
+}
+
+/// The private key of a signer, i.e. the 32 bytes circomlib expects
+#[derive(Clone)]
+pub struct SecretKey([u8; 32]);
+
+impl SecretKey {
+	pub fn new(bytes: [u8; 32]) -> Self {
+		Self(bytes)
+	}
+
+	pub fn rand<R: Rng>(rng: &mut R) -> Self {
+		let mut bytes = [0u8; 32];
+		rng.fill_bytes(&mut bytes);
+		Self(bytes)
+	}
+
+	pub fn to_bytes(&self) -> [u8; 32] {
+		self.0
+	}
+
+	/// Expands the private key into the signing scalar `s` and the prefix
+	/// used to derive nonces.
+	fn expand(&self) -> (Fr, [u8; 32]) {
+		let h = blake512(&self.0);
+		let mut scalar = [0u8; 32];
+		scalar.copy_from_slice(&h[..32]);
+		scalar[0] &= 0xf8;
+		scalar[31] &= 0x7f;
+		scalar[31] |= 0x40;
+
+		let mut prefix = [0u8; 32];
+		prefix.copy_from_slice(&h[32..]);
+		(Fr::from_le_bytes_mod_order(&scalar), prefix)
+	}
+
+	/// The scalar `s >> 3`, such that the public key is `scalar * B8`
+	pub fn scalar(&self) -> Fr {
+		let (s, _) = self.expand();
+		// The pruned scalar is a multiple of 8, so `s >> 3` is `s / 8`
+		let eight_inv = Fr::from(8u64).inverse().unwrap();
+		s * eight_inv
+	}
+
+	pub fn public_key(&self) -> PublicKey {
+		let point = base8().mul(self.scalar().into_repr()).into_affine();
+		let (x, y) = to_circom_coordinates(&point);
+		PublicKey { x, y }
+	}
+
+	/// Computes the ECDH shared point `scalar * public_key`, in circomlib
+	/// coordinates. Both parties get the same point, which can be used as a
+	/// key for `poseidon::encryption`.
+	pub fn shared_key(&self, public_key: &PublicKey) -> Result<(Fq, Fq), Error> {
+		let point = from_circom_coordinates(public_key.x, public_key.y)?;
+		let shared = point.mul(self.scalar().into_repr()).into_affine();
+		Ok(to_circom_coordinates(&shared))
+	}
+
+	/// Signs `msg`. `hasher` must be the Poseidon hash of width 6.
+	pub fn sign<H: FieldHasher<Fq>>(&self, msg: Fq, hasher: &H) -> Result<Signature, Error> {
+		let (s, prefix) = self.expand();
+		let public_key = self.public_key();
+
+		let mut nonce_input: Vec<u8> = prefix.to_vec();
+		nonce_input.extend_from_slice(&msg.into_repr().to_bytes_le());
+		let r = Fr::from_le_bytes_mod_order(&blake512(&nonce_input));
+		let (r8_x, r8_y) = to_circom_coordinates(&base8().mul(r.into_repr()).into_affine());
+
+		let hm = hasher.hash(&[r8_x, r8_y, public_key.x, public_key.y, msg])?;
+		let s = r + to_scalar(&hm) * s;
+
+		Ok(Signature { r8_x, r8_y, s })
+	}
+}
+
+/// A public key, in circomlib coordinates
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PublicKey {
+	pub x: Fq,
+	pub y: Fq,
+}
+
+impl PublicKey {
+	/// Creates a public key from circomlib coordinates, checking that they
+	/// are a point of the prime order subgroup other than the identity.
+	pub fn new(x: Fq, y: Fq) -> Result<Self, Error> {
+		let point = from_circom_coordinates(x, y)?;
+		if point.mul_by_cofactor().is_zero() {
+			return Err(EdDSAError::InvalidPoint.into());
+		}
+		Ok(Self { x, y })
+	}
+
+	/// Verifies a signature of `msg`. `hasher` must be the Poseidon hash of
+	/// width 6. Signatures whose `R8` is not a point of the prime order
+	/// subgroup are rejected, and so is every signature under a key whose
+	/// multiple by the cofactor is the identity, which would otherwise be
+	/// satisfied by `S * B8 == R8`.
+	pub fn verify<H: FieldHasher<Fq>>(
+		&self,
+		msg: Fq,
+		signature: &Signature,
+		hasher: &H,
+	) -> Result<bool, Error> {
+		let (a, r8) = match (
+			from_circom_coordinates(self.x, self.y),
+			from_circom_coordinates(signature.r8_x, signature.r8_y),
+		) {
+			(Ok(a), Ok(r8)) => (a, r8),
+			_ => return Ok(false),
+		};
+		let a8 = a.mul_by_cofactor();
+		if a8.is_zero() {
+			return Ok(false);
+		}
+
+		let hm = hasher.hash(&[signature.r8_x, signature.r8_y, self.x, self.y, msg])?;
+		let lhs = base8().mul(signature.s.into_repr());
+		let rhs = r8.into_projective() + a8.mul(to_scalar(&hm).into_repr());
+		Ok(lhs == rhs)
+	}
+}
+
+/// An EdDSA signature, with the `R8` point in circomlib coordinates
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Signature {
+	pub r8_x: Fq,
+	pub r8_y: Fq,
+	pub s: Fr,
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::poseidon::{test::setup_params, Poseidon};
+	use ark_std::test_rng;
+	use arkworks_utils::{bytes_vec_to_f, parse_vec, Curve};
+
+	fn parse<F: PrimeField>(hex: &str) -> F {
+		bytes_vec_to_f(&parse_vec(vec![hex]).unwrap())[0]
+	}
+
+	#[test]
+	fn should_match_circomlib_signature() {
+		let hasher = Poseidon::new(setup_params::<Fq>(Curve::Bn254, 5, 6));
+
+		// Vector from circomlib's eddsa test:
+		// ```js
+		// const prvKey = Buffer.from("0001020304050607080900010203040506070809000102030405060708090001", "hex");
+		// const msg = Scalar.fromRprLE(Buffer.from("000102030405060708090000", "hex"), 0);
+		// const signature = eddsa.signPoseidon(prvKey, msg);
+		// ```
+		let mut prv = [0u8; 32];
+		for (i, byte) in prv.iter_mut().enumerate() {
+			*byte = (i % 10) as u8;
+		}
+		let secret_key = SecretKey::new(prv);
+		let msg: Fq = parse("0x0000000000000000000000000000000000000000000009080706050403020100");
+
+		let public_key = secret_key.public_key();
+		assert_eq!(
+			public_key.x,
+			parse("0x1d5ac1f31407018b7d413a4f52c8f74463b30e6ac2238220ad8b254de4eaa3a2")
+		);
+		assert_eq!(
+			public_key.y,
+			parse("0x1e1de8a908826c3f9ac2e0ceee929ecd0caf3b99b3ef24523aaab796a6f733c4")
+		);
+
+		let signature = secret_key.sign(msg, &hasher).unwrap();
+		assert_eq!(
+			signature.r8_x,
+			parse("0x192b4e51adf302c8139d356d0e08e2404b5ace440ef41fc78f5c4f2428df0765")
+		);
+		assert_eq!(
+			signature.r8_y,
+			parse("0x2202bebcf57b820863e0acc88970b6ca7d987a0d513c2ddeb42e3f5d31b4eddf")
+		);
+		assert_eq!(
+			signature.s,
+			parse("0x03b2c1e53da70d9a17a6c1c7c1273a7d7a10c040b6adc0bf828f2a56ce3e049d")
+		);
+
+		assert!(public_key.verify(msg, &signature, &hasher).unwrap());
+	}
+
+	#[test]
+	fn should_reject_invalid_signatures() {
+		let rng = &mut test_rng();
+		let hasher = Poseidon::new(setup_params::<Fq>(Curve::Bn254, 5, 6));
+
+		let secret_key = SecretKey::rand(rng);
+		let public_key = secret_key.public_key();
+		let msg = Fq::from(42u64);
+		let signature = secret_key.sign(msg, &hasher).unwrap();
+		assert!(public_key.verify(msg, &signature, &hasher).unwrap());
+
+		// Different message
+		assert!(!public_key
+			.verify(Fq::from(43u64), &signature, &hasher)
+			.unwrap());
+
+		// Different signer
+		let other_key = SecretKey::rand(rng).public_key();
+		assert!(!other_key.verify(msg, &signature, &hasher).unwrap());
+
+		// Tampered scalar
+		let mut tampered = signature;
+		tampered.s += Fr::from(1u64);
+		assert!(!public_key.verify(msg, &tampered, &hasher).unwrap());
+
+		// R8 outside of the curve
+		let mut tampered = signature;
+		tampered.r8_x += Fq::from(1u64);
+		assert!(!public_key.verify(msg, &tampered, &hasher).unwrap());
+	}
+
+	#[test]
+	fn should_derive_the_same_shared_key() {
+		let rng = &mut test_rng();
+		let alice = SecretKey::rand(rng);
+		let bob = SecretKey::rand(rng);
+
+		let alice_shared = alice.shared_key(&bob.public_key()).unwrap();
+		let bob_shared = bob.shared_key(&alice.public_key()).unwrap();
+		assert_eq!(alice_shared, bob_shared);
+
+		let carol = SecretKey::rand(rng);
+		assert_ne!(alice_shared, carol.shared_key(&bob.public_key()).unwrap());
+	}
+
+	#[test]
+	fn should_convert_between_coordinates() {
+		let (x, y) = to_circom_coordinates(&base8());
+		assert_eq!((x, y), (BASE8_X, BASE8_Y));
+		assert!(from_circom_coordinates(BASE8_X, BASE8_Y).is_ok());
+		assert!(from_circom_coordinates(BASE8_X + Fq::from(1u64), BASE8_Y).is_err());
+		assert!(PublicKey::new(Fq::zero(), Fq::from(1u64)).is_err());
+	}
+}
@@ -6,9 +6,13 @@ use ark_std::{boxed::Box, vec::Vec};
 #[macro_use]
 pub extern crate ark_std;
 
+pub mod eddsa;
 pub mod merkle_tree;
 pub mod mimc;
+pub mod pedersen;
 pub mod poseidon;
+pub mod rln;
+pub mod tornado;
 
 pub type Error = Box<dyn ark_std::error::Error>;
 
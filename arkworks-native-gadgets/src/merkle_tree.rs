@@ -109,6 +109,8 @@ pub enum MerkleError {
 	InvalidLeaf,
 	/// Thrown when the merkle path is invalid.
 	InvalidPathNodes,
+	/// Thrown when the tree depth is out of bounds.
+	InvalidDepth,
 }
 
 impl core::fmt::Display for MerkleError {
@@ -116,6 +118,7 @@ impl core::fmt::Display for MerkleError {
 		let msg = match self {
 			MerkleError::InvalidLeaf => "Invalid leaf".to_owned(),
 			MerkleError::InvalidPathNodes => "Path nodes are not consistent".to_owned(),
+			MerkleError::InvalidDepth => "Invalid tree depth".to_owned(),
 		};
 		write!(f, "{}", msg)
 	}
@@ -164,6 +167,33 @@ impl<F: PrimeField, H: FieldHasher<F>, const N: usize> Path<F, H, N> {
 		Ok(prev)
 	}
 
+	/// Calculates the root of the tree of height `depth` that holds the leaf,
+	/// ignoring the levels of the path above `depth`.
+	///
+	/// For a tree filled sequentially, the node at level `depth` on the path
+	/// of a tree of height `N` is the root of the tree of height `depth` with
+	/// the same leaves, so a path of the largest supported height can be
+	/// used for trees of any smaller height.
+	pub fn calculate_root_at_depth(&self, leaf: &F, depth: usize, hasher: &H) -> Result<F, Error> {
+		if depth == 0 || depth > N {
+			return Err(MerkleError::InvalidDepth.into());
+		}
+		if *leaf != self.path[0].0 && *leaf != self.path[0].1 {
+			return Err(MerkleError::InvalidLeaf.into());
+		}
+
+		let mut prev = *leaf;
+		// Check levels between leaf level and depth
+		for &(ref left_hash, ref right_hash) in &self.path[..depth] {
+			if &prev != left_hash && &prev != right_hash {
+				return Err(MerkleError::InvalidPathNodes.into());
+			}
+			prev = hasher.hash_two(left_hash, right_hash)?;
+		}
+
+		Ok(prev)
+	}
+
 	/// Given leaf data determine what the index of this leaf must be
 	/// in the Merkle tree it belongs to.  Before doing so check that the leaf
 	/// does indeed belong to a tree with the given `root_hash`
@@ -326,6 +356,66 @@ impl<F: PrimeField, H: FieldHasher<F>, const N: usize> SparseMerkleTree<F, H, N>
 	}
 }
 
+/// Builds the root tree of a bridge from the roots of its edges.
+///
+/// The edge roots are inserted as sequential leaves, so the path proving that
+/// an edge root belongs to the set is
+/// `generate_membership_proof(index_of_edge_root)`. The root of the returned
+/// tree is the "root of roots" used as the single public root-set input of
+/// the root tree Anchor and VAnchor circuits.
+pub fn build_root_tree<F: PrimeField, H: FieldHasher<F>, const K: usize>(
+	edge_roots: &[F],
+	hasher: &H,
+	empty_leaf: &[u8],
+) -> Result<SparseMerkleTree<F, H, K>, Error> {
+	SparseMerkleTree::new_sequential(edge_roots, hasher, empty_leaf)
+}
+
+/// Builds an association set tree from the leaves of the approved deposits.
+///
+/// An association set is a subset of the deposits of a pool, e.g. the ones
+/// vetted by a compliance provider. The leaves are inserted as sequential
+/// leaves, and the root of the returned tree is the public association root
+/// used by the association set Anchor circuit. The path of a deposit is given
+/// by `association_path`.
+pub fn build_association_tree<F: PrimeField, H: FieldHasher<F>, const K: usize>(
+	approved_leaves: &[F],
+	hasher: &H,
+	empty_leaf: &[u8],
+) -> Result<SparseMerkleTree<F, H, K>, Error> {
+	SparseMerkleTree::new_sequential(approved_leaves, hasher, empty_leaf)
+}
+
+/// Generates the path proving that `leaf` belongs to the association set
+/// tree built from `approved_leaves` with `build_association_tree`.
+///
+/// Returns `MerkleError::InvalidLeaf` if the leaf is not in the set.
+pub fn association_path<F: PrimeField, H: FieldHasher<F>, const K: usize>(
+	tree: &SparseMerkleTree<F, H, K>,
+	approved_leaves: &[F],
+	leaf: &F,
+) -> Result<Path<F, H, K>, Error> {
+	let index = approved_leaves
+		.iter()
+		.position(|x| x == leaf)
+		.ok_or(MerkleError::InvalidLeaf)?;
+	Ok(tree.generate_membership_proof(index as u64))
+}
+
+/// Hashes a batch of leaves into a single value, by chaining `hash_two`
+/// starting from zero. This is the public commitment to the leaves of a
+/// batch insertion proof.
+pub fn batch_leaves_hash<F: PrimeField, H: FieldHasher<F>>(
+	leaves: &[F],
+	hasher: &H,
+) -> Result<F, Error> {
+	let mut hash = F::zero();
+	for leaf in leaves {
+		hash = hasher.hash_two(&hash, leaf)?;
+	}
+	Ok(hash)
+}
+
 /// A function to generate empty hashes with a given `default_leaf`.
 ///
 /// Given a `FieldHasher`, generate a list of `N` hashes consisting
@@ -413,7 +503,10 @@ fn parent(index: u64) -> Option<u64> {
 
 #[cfg(test)]
 mod test {
-	use super::{gen_empty_hashes, SparseMerkleTree};
+	use super::{
+		association_path, build_association_tree, build_root_tree, gen_empty_hashes,
+		SparseMerkleTree,
+	};
 	use crate::poseidon::{test::setup_params, FieldHasher, Poseidon};
 	use ark_ed_on_bls12_381::Fq;
 	use ark_ff::{BigInteger, PrimeField, UniformRand};
@@ -515,6 +608,115 @@ mod test {
 		assert_eq!(res, desired_res);
 	}
 
+	#[test]
+	fn should_calculate_root_at_depth() {
+		let rng = &mut test_rng();
+		let curve = Curve::Bls381;
+
+		let params = setup_params(curve, 5, 3);
+		let poseidon = Poseidon::new(params);
+		let default_leaf = [0u8; 32];
+		let leaves = [Fq::rand(rng), Fq::rand(rng), Fq::rand(rng)];
+		const HEIGHT: usize = 5;
+		const SMALL_HEIGHT: usize = 3;
+		let smt =
+			create_merkle_tree::<Fq, BLSHash, HEIGHT>(poseidon.clone(), &leaves, &default_leaf);
+		let small_smt = create_merkle_tree::<Fq, BLSHash, SMALL_HEIGHT>(
+			poseidon.clone(),
+			&leaves,
+			&default_leaf,
+		);
+
+		let proof = smt.generate_membership_proof(2);
+
+		let root = proof
+			.calculate_root_at_depth(&leaves[2], SMALL_HEIGHT, &poseidon)
+			.unwrap();
+		assert_eq!(root, small_smt.root());
+		let root = proof
+			.calculate_root_at_depth(&leaves[2], HEIGHT, &poseidon)
+			.unwrap();
+		assert_eq!(root, smt.root());
+
+		assert!(proof
+			.calculate_root_at_depth(&leaves[2], 0, &poseidon)
+			.is_err());
+		assert!(proof
+			.calculate_root_at_depth(&leaves[2], HEIGHT + 1, &poseidon)
+			.is_err());
+	}
+
+	#[test]
+	fn should_prove_edge_root_in_root_tree() {
+		let rng = &mut test_rng();
+		let curve = Curve::Bls381;
+
+		let params = setup_params(curve, 5, 3);
+		let poseidon = Poseidon::new(params);
+		let default_leaf = [0u8; 32];
+		const HEIGHT: usize = 3;
+		const ROOT_HEIGHT: usize = 3;
+
+		// Roots of the trees on each edge of the bridge
+		let edge_roots = (0..3)
+			.map(|_| {
+				let leaves = [Fq::rand(rng), Fq::rand(rng)];
+				create_merkle_tree::<Fq, BLSHash, HEIGHT>(poseidon.clone(), &leaves, &default_leaf)
+					.root()
+			})
+			.collect::<Vec<_>>();
+
+		let root_tree =
+			build_root_tree::<Fq, BLSHash, ROOT_HEIGHT>(&edge_roots, &poseidon, &default_leaf)
+				.unwrap();
+		let root_of_roots = root_tree.root();
+
+		for (i, edge_root) in edge_roots.iter().enumerate() {
+			let root_path = root_tree.generate_membership_proof(i as u64);
+			let res = root_path
+				.check_membership(&root_of_roots, edge_root, &poseidon)
+				.unwrap();
+			assert!(res);
+		}
+
+		// A root that is not an edge root can't be proven to be in the set
+		let root_path = root_tree.generate_membership_proof(0);
+		let res = root_path.check_membership(&root_of_roots, &Fq::rand(rng), &poseidon);
+		assert!(res.is_err());
+	}
+
+	#[test]
+	fn should_prove_leaf_in_association_set() {
+		let rng = &mut test_rng();
+		let curve = Curve::Bls381;
+
+		let params = setup_params(curve, 5, 3);
+		let poseidon = Poseidon::new(params);
+		let default_leaf = [0u8; 32];
+		const ASSOCIATION_HEIGHT: usize = 3;
+
+		// The approved deposits are a subset of the deposits of the pool
+		let deposits = (0..6).map(|_| Fq::rand(rng)).collect::<Vec<_>>();
+		let approved = vec![deposits[1], deposits[4], deposits[5]];
+		let tree = build_association_tree::<Fq, BLSHash, ASSOCIATION_HEIGHT>(
+			&approved,
+			&poseidon,
+			&default_leaf,
+		)
+		.unwrap();
+
+		for leaf in approved.iter() {
+			let path = association_path(&tree, &approved, leaf).unwrap();
+			let res = path
+				.check_membership(&tree.root(), leaf, &poseidon)
+				.unwrap();
+			assert!(res);
+		}
+
+		// A deposit that is not approved has no path
+		assert!(association_path(&tree, &approved, &deposits[0]).is_err());
+	}
+
 	#[test]
 	fn compare_with_solidity_empty_hashes() {
 		// These are taken from protocol-solidity/contracts/trees/MerkleTreePoseidon.sol
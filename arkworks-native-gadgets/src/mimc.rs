@@ -1,4 +1,7 @@
-use crate::ark_std::string::ToString;
+use crate::{
+	ark_std::string::ToString,
+	poseidon::{FieldHasher, PoseidonError},
+};
 use ark_crypto_primitives::{crh::TwoToOneCRH, Error, CRH as CRHTrait};
 use ark_ff::{fields::PrimeField, BigInteger};
 use ark_std::{error::Error as ArkError, marker::PhantomData, rand::Rng, vec::Vec};
@@ -22,7 +25,7 @@ impl core::fmt::Display for MiMCError {
 
 impl ArkError for MiMCError {}
 
-#[derive(Default, Clone)]
+#[derive(Default, Clone, Debug)]
 pub struct MiMCParameters<F> {
 	pub k: F,
 	pub rounds: usize,
@@ -214,12 +217,82 @@ impl<F: PrimeField, P: Rounds> TwoToOneCRH for CRH<F, P> {
 	}
 }
 
+/// The MiMCSponge hash of circomlib, used by the Merkle trees of Tornado
+/// Cash.
+///
+/// Unlike `CRH`, the round constants are used as given: round `i` adds
+/// `round_keys[i]`, so `round_keys` must hold `rounds` constants, starting
+/// and ending with zero as circomlib's do. The permutation is the Feistel
+/// network `MiMCFeistel` with the key `k`, the inputs are absorbed by adding
+/// them to the left half of the state and the output is the left half of the
+/// state.
+#[derive(Default, Clone, Debug)]
+pub struct MiMCSponge<F: PrimeField> {
+	pub params: MiMCParameters<F>,
+}
+
+impl<F: PrimeField> MiMCSponge<F> {
+	pub fn new(params: MiMCParameters<F>) -> Self {
+		Self { params }
+	}
+
+	/// Applies the Feistel permutation to the state `(x_l, x_r)`
+	pub fn feistel(&self, left: F, right: F) -> Result<(F, F), MiMCError> {
+		let params = &self.params;
+		if params.rounds == 0 || params.round_keys.len() != params.rounds {
+			return Err(MiMCError::InvalidInputs);
+		}
+
+		let mut x_l = left;
+		let mut x_r = right;
+		for (i, c) in params.round_keys.iter().enumerate() {
+			let t = if i == 0 {
+				params.k + x_l
+			} else {
+				params.k + x_l + c
+			};
+			let t2 = t * t;
+			let t5 = t2 * t2 * t;
+
+			if i < params.rounds - 1 {
+				let temp_x_l = x_l;
+				x_l = x_r + t5;
+				x_r = temp_x_l;
+			} else {
+				x_r += t5;
+			}
+		}
+
+		Ok((x_l, x_r))
+	}
+}
+
+impl<F: PrimeField> FieldHasher<F> for MiMCSponge<F> {
+	fn hash(&self, inputs: &[F]) -> Result<F, PoseidonError> {
+		let mut x_l = F::zero();
+		let mut x_r = F::zero();
+		for input in inputs {
+			let (l, r) = self
+				.feistel(x_l + input, x_r)
+				.map_err(|_| PoseidonError::InvalidInputs)?;
+			x_l = l;
+			x_r = r;
+		}
+
+		Ok(x_l)
+	}
+
+	fn hash_two(&self, left: &F, right: &F) -> Result<F, PoseidonError> {
+		self.hash(&[*left, *right])
+	}
+}
+
 #[cfg(test)]
-mod test {
+pub mod test {
 	use super::*;
 	use ark_ed_on_bn254::Fq;
 	use ark_ff::{to_bytes, Zero};
-	use arkworks_utils::{bytes_vec_to_f, mimc_params::setup_mimc_params, Curve};
+	use arkworks_utils::{bytes_vec_to_f, mimc_params::setup_mimc_params, parse_vec, Curve};
 
 	#[derive(Default, Clone)]
 	struct MiMCRounds220;
@@ -256,4 +329,32 @@ mod test {
 		let mimc_res = <MiMC220 as CRHTrait>::evaluate(&params, &inp).unwrap();
 		println!("{:?}", mimc_res);
 	}
+
+	pub fn setup_mimc_sponge<F: PrimeField>(curve: Curve) -> MiMCSponge<F> {
+		let mimc_data = setup_mimc_params(curve, 220, 3).unwrap();
+		let round_keys = bytes_vec_to_f(&mimc_data.constants);
+
+		MiMCSponge::new(MiMCParameters::new(
+			F::zero(),
+			mimc_data.rounds as usize,
+			2,
+			1,
+			round_keys,
+		))
+	}
+
+	#[test]
+	fn test_mimc_sponge_circomlib_vector() {
+		let sponge = setup_mimc_sponge::<Fq>(Curve::Bn254);
+
+		// MiMCSponge(1, 2) with k = 0, from the tests of circomlib
+		let res = sponge.hash_two(&Fq::from(1u64), &Fq::from(2u64)).unwrap();
+		let expected = bytes_vec_to_f(
+			&parse_vec(vec![
+				"0x2bcea035a1251603f1ceaf73cd4ae89427c47075bb8e3a944039ff1e3d6d2a6f",
+			])
+			.unwrap(),
+		)[0];
+		assert_eq!(res, expected);
+	}
 }
@@ -0,0 +1,214 @@
+// This file is part of Webb.
+//
+// Copyright (C) 2021 Webb Technologies Inc.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A native implementation of circomlib's Pedersen hash over Baby Jubjub
+//! (`ed_on_bn254`), the commitment scheme of Tornado Cash.
+//!
+//! The input bits are split into segments of 200 bits, each segment having
+//! its own base point `B_s`, and every segment into windows of 4 bits. The
+//! window `j` of a segment, with bits `(b0, b1, b2, b3)`, encodes the scalar
+//! `(1 + b0 + 2 * b1 + 4 * b2) * (-1)^b3 * 32^j`, the last window being
+//! padded with zeros. The hash is the sum over the segments of their scalar
+//! times `B_s`, and its x coordinate is what circomlib's `Pedersen` outputs.
+//!
+//! Like the `eddsa` module, points are given and exposed in circomlib
+//! coordinates and the group arithmetic uses the arkworks form.
+
+use crate::eddsa::{to_circom_coordinates, SQRT_A};
+use ark_ec::{AffineCurve, ProjectiveCurve};
+use ark_ed_on_bn254::{EdwardsAffine, EdwardsProjective, Fq, Fr};
+use ark_ff::{field_new, PrimeField, Zero};
+use ark_std::{string::ToString, vec::Vec};
+
+/// Number of bits of a window
+pub const WINDOW_SIZE: usize = 4;
+/// Number of windows of a segment
+pub const WINDOWS_PER_SEGMENT: usize = 50;
+/// Number of bits of a segment
+pub const BITS_PER_SEGMENT: usize = WINDOW_SIZE * WINDOWS_PER_SEGMENT;
+
+/// The base points of the first segments, in circomlib coordinates. These are
+/// the first entries of `BASE` in circomlib's `pedersen.circom`.
+pub const BASE_POINTS: [(Fq, Fq); 3] = [
+	(
+		field_new!(
+			Fq,
+			"10457101036533406547632367118273992217979173478358440826365724437999023779287"
+		),
+		field_new!(
+			Fq,
+			"19824078218392094440610104313265183977899662750282163392862422243483260492317"
+		),
+	),
+	(
+		field_new!(
+			Fq,
+			"2671756056509184035029146175565761955751135805354291559563293617232983272177"
+		),
+		field_new!(
+			Fq,
+			"2663205510731142763556352975002641716101654201788071096152948830924149045094"
+		),
+	),
+	(
+		field_new!(
+			Fq,
+			"5802099305472655231388284418920769829666717045250560929368476121199858275951"
+		),
+		field_new!(
+			Fq,
+			"5980429700218124965372158798884772646841287887664001482443826541541529227896"
+		),
+	),
+];
+
+/// Largest number of bits that can be hashed with `BASE_POINTS`
+pub const MAX_BITS: usize = BITS_PER_SEGMENT * BASE_POINTS.len();
+
+#[derive(Debug)]
+pub enum PedersenError {
+	/// Thrown if there are no bits to hash
+	EmptyInput,
+	/// Thrown if there are more than `MAX_BITS` bits to hash
+	TooManyBits,
+}
+
+impl core::fmt::Display for PedersenError {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		let msg = match self {
+			PedersenError::EmptyInput => "no bits to hash".to_string(),
+			PedersenError::TooManyBits => "too many bits to hash".to_string(),
+		};
+		write!(f, "{}", msg)
+	}
+}
+
+impl ark_std::error::Error for PedersenError {}
+
+/// The base point of the segment `segment`, in arkworks coordinates
+pub fn base_point(segment: usize) -> EdwardsAffine {
+	let (x, y) = BASE_POINTS[segment];
+	EdwardsAffine::new(x * SQRT_A, y)
+}
+
+/// The point `32^window * B_segment` that the scalar of the window `window`
+/// of the segment `segment` multiplies, in arkworks coordinates
+pub fn window_base(segment: usize, window: usize) -> EdwardsAffine {
+	let mut base = base_point(segment).into_projective();
+	for _ in 0..window * (WINDOW_SIZE + 1) {
+		base.double_in_place();
+	}
+	base.into_affine()
+}
+
+/// The multiples `1..8` of the base of the window `window` of the segment
+/// `segment`, the points the first 3 bits of the window select among
+pub fn window_multiples(segment: usize, window: usize) -> Vec<EdwardsAffine> {
+	let base = window_base(segment, window).into_projective();
+	let mut multiple = base;
+	let mut multiples = Vec::with_capacity(8);
+	for _ in 0..8 {
+		multiples.push(multiple.into_affine());
+		multiple += base;
+	}
+	multiples
+}
+
+/// Splits bytes into bits, least significant bit of every byte first, like
+/// circomlib's `buffer2bits`
+pub fn bytes_to_bits_le(bytes: &[u8]) -> Vec<bool> {
+	bytes
+		.iter()
+		.flat_map(|byte| (0..8).map(move |i| (byte >> i) & 1 == 1))
+		.collect()
+}
+
+/// Computes the Pedersen hash of `bits`, in arkworks coordinates
+pub fn pedersen_hash(bits: &[bool]) -> Result<EdwardsAffine, PedersenError> {
+	if bits.is_empty() {
+		return Err(PedersenError::EmptyInput);
+	}
+	if bits.len() > MAX_BITS {
+		return Err(PedersenError::TooManyBits);
+	}
+
+	let window_factor = Fr::from(1u64 << (WINDOW_SIZE + 1));
+	let mut hash = EdwardsProjective::zero();
+	for (segment, segment_bits) in bits.chunks(BITS_PER_SEGMENT).enumerate() {
+		let mut scalar = Fr::zero();
+		let mut exp = Fr::from(1u64);
+		for window in segment_bits.chunks(WINDOW_SIZE) {
+			// The missing bits of the last window are zeros
+			let bit = |i: usize| window.get(i).copied().unwrap_or(false);
+			let mut value = Fr::from(1 + bit(0) as u64 + 2 * bit(1) as u64 + 4 * bit(2) as u64);
+			if bit(3) {
+				value = -value;
+			}
+			scalar += value * exp;
+			exp *= window_factor;
+		}
+		hash += base_point(segment).mul(scalar.into_repr());
+	}
+
+	Ok(hash.into_affine())
+}
+
+/// Computes the Pedersen hash of `bytes`, and returns its x coordinate in
+/// circomlib coordinates, like Tornado Cash's `pedersenHash`
+pub fn pedersen_hash_bytes(bytes: &[u8]) -> Result<Fq, PedersenError> {
+	let hash = pedersen_hash(&bytes_to_bits_le(bytes))?;
+	Ok(to_circom_coordinates(&hash).0)
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn base_points_should_be_in_the_subgroup() {
+		for segment in 0..BASE_POINTS.len() {
+			let base = base_point(segment);
+			assert!(base.is_on_curve());
+			assert!(base.is_in_correct_subgroup_assuming_on_curve());
+		}
+	}
+
+	#[test]
+	fn should_match_window_bases() {
+		// Summing the window scalars times their window bases must give the
+		// same point as the segment scalar times the segment base
+		let bits = bytes_to_bits_le(&[0xa5, 0x3c, 0xff]);
+		let mut expected = EdwardsProjective::zero();
+		for (window, chunk) in bits.chunks(WINDOW_SIZE).enumerate() {
+			let value = 1 + chunk[0] as u64 + 2 * chunk[1] as u64 + 4 * chunk[2] as u64;
+			let point = window_base(0, window).mul(Fr::from(value).into_repr());
+			if chunk[3] {
+				expected -= point;
+			} else {
+				expected += point;
+			}
+		}
+		assert_eq!(pedersen_hash(&bits).unwrap(), expected.into_affine());
+	}
+
+	#[test]
+	fn should_reject_invalid_lengths() {
+		assert!(pedersen_hash(&[]).is_err());
+		assert!(pedersen_hash(&vec![true; MAX_BITS + 1]).is_err());
+		assert!(pedersen_hash(&vec![true; MAX_BITS]).is_ok());
+	}
+}
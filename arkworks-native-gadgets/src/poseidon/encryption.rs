@@ -0,0 +1,236 @@
+// This file is part of Webb.
+//
+// Copyright (C) 2021 Webb Technologies Inc.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Authenticated symmetric encryption of field elements with the Poseidon
+//! permutation, as described in [Encryption with Poseidon](https://dusk.network/uploads/Encryption-with-Poseidon.pdf)
+//! by Khovratovich. It is the construction used by circomlib-based projects
+//! such as MACI, so ciphertexts can be produced or checked inside a circuit.
+//!
+//! The permutation has width 4: one capacity element and a rate of three
+//! elements. For a key `(k0, k1)`, a nonce `n < 2^128` and a message of
+//! length `l`:
+//! - the state starts as `[0, k0, k1, n + l * 2^128]`,
+//! - the message is padded with zeros to a multiple of three elements,
+//! - for every chunk of three elements, the state is permuted, the chunk is
+//!   added to the rate and the rate is output as ciphertext,
+//! - the state is permuted once more and its second element is output as an
+//!   authentication tag.
+//!
+//! The key is usually the shared point of an ECDH over Baby Jubjub, see
+//! `eddsa::SecretKey::shared_key`. A key must not be used twice with the same
+//! nonce.
+
+use super::Poseidon;
+use crate::Error;
+use ark_ff::PrimeField;
+use ark_std::{string::ToString, vec::Vec};
+
+/// Number of message elements absorbed per permutation
+pub const RATE: usize = 3;
+/// Width of the Poseidon permutation used by the encryption
+pub const WIDTH: usize = RATE + 1;
+/// Number of bits of a nonce
+pub const NONCE_BITS: usize = 128;
+
+#[derive(Debug)]
+pub enum PoseidonEncryptionError {
+	/// Thrown if the hasher does not have width 4
+	InvalidWidth,
+	/// Thrown if the nonce does not fit in 128 bits
+	InvalidNonce,
+	/// Thrown if the ciphertext length does not match the message length
+	InvalidCiphertextLength,
+	/// Thrown if the authentication tag or the padding does not match
+	DecryptionFailed,
+}
+
+impl core::fmt::Display for PoseidonEncryptionError {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		let msg = match self {
+			PoseidonEncryptionError::InvalidWidth => "hasher width must be 4".to_string(),
+			PoseidonEncryptionError::InvalidNonce => "nonce must fit in 128 bits".to_string(),
+			PoseidonEncryptionError::InvalidCiphertextLength => {
+				"invalid ciphertext length".to_string()
+			}
+			PoseidonEncryptionError::DecryptionFailed => "decryption failed".to_string(),
+		};
+		write!(f, "{}", msg)
+	}
+}
+
+impl ark_std::error::Error for PoseidonEncryptionError {}
+
+/// Length of the ciphertext of a message of `msg_len` elements, including the
+/// authentication tag
+pub fn ciphertext_len(msg_len: usize) -> usize {
+	(msg_len + RATE - 1) / RATE * RATE + 1
+}
+
+/// `2^128`, which separates the nonce from the message length in the initial
+/// state
+pub fn length_shift<F: PrimeField>() -> F {
+	F::from(2u64).pow(&[NONCE_BITS as u64])
+}
+
+fn initial_state<F: PrimeField>(
+	hasher: &Poseidon<F>,
+	key: &(F, F),
+	nonce: F,
+	msg_len: usize,
+) -> Result<Vec<F>, Error> {
+	if hasher.params.width as usize != WIDTH {
+		return Err(PoseidonEncryptionError::InvalidWidth.into());
+	}
+	if nonce >= length_shift() {
+		return Err(PoseidonEncryptionError::InvalidNonce.into());
+	}
+	let domain = nonce + F::from(msg_len as u64) * length_shift::<F>();
+	Ok(vec![F::zero(), key.0, key.1, domain])
+}
+
+/// Encrypts `msg` under `key` and `nonce`. `hasher` must be the Poseidon
+/// hash of width 4.
+pub fn encrypt<F: PrimeField>(
+	hasher: &Poseidon<F>,
+	msg: &[F],
+	key: &(F, F),
+	nonce: F,
+) -> Result<Vec<F>, Error> {
+	let mut state = initial_state(hasher, key, nonce, msg.len())?;
+
+	let mut padded = msg.to_vec();
+	padded.resize(ciphertext_len(msg.len()) - 1, F::zero());
+
+	let mut ciphertext = Vec::with_capacity(padded.len() + 1);
+	for chunk in padded.chunks(RATE) {
+		state = hasher.permute(state)?;
+		for (i, m) in chunk.iter().enumerate() {
+			state[i + 1] += m;
+			ciphertext.push(state[i + 1]);
+		}
+	}
+
+	state = hasher.permute(state)?;
+	ciphertext.push(state[1]);
+	Ok(ciphertext)
+}
+
+/// Decrypts a ciphertext of a message of `msg_len` elements, checking the
+/// authentication tag. `hasher` must be the Poseidon hash of width 4.
+pub fn decrypt<F: PrimeField>(
+	hasher: &Poseidon<F>,
+	ciphertext: &[F],
+	key: &(F, F),
+	nonce: F,
+	msg_len: usize,
+) -> Result<Vec<F>, Error> {
+	if ciphertext.len() != ciphertext_len(msg_len) {
+		return Err(PoseidonEncryptionError::InvalidCiphertextLength.into());
+	}
+	let mut state = initial_state(hasher, key, nonce, msg_len)?;
+
+	let (body, tag) = ciphertext.split_at(ciphertext.len() - 1);
+	let mut msg = Vec::with_capacity(body.len());
+	for chunk in body.chunks(RATE) {
+		state = hasher.permute(state)?;
+		for (i, c) in chunk.iter().enumerate() {
+			msg.push(*c - state[i + 1]);
+			state[i + 1] = *c;
+		}
+	}
+
+	state = hasher.permute(state)?;
+	let padding_is_zero = msg[msg_len..].iter().all(|m| m.is_zero());
+	if state[1] != tag[0] || !padding_is_zero {
+		return Err(PoseidonEncryptionError::DecryptionFailed.into());
+	}
+
+	msg.truncate(msg_len);
+	Ok(msg)
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::poseidon::test::setup_params;
+	use ark_ed_on_bn254::Fq;
+	use ark_std::{test_rng, UniformRand};
+	use arkworks_utils::Curve;
+
+	fn setup_hasher() -> Poseidon<Fq> {
+		Poseidon::new(setup_params(Curve::Bn254, 5, 4))
+	}
+
+	#[test]
+	fn should_encrypt_and_decrypt() {
+		let rng = &mut test_rng();
+		let hasher = setup_hasher();
+		let key = (Fq::rand(rng), Fq::rand(rng));
+		let nonce = Fq::from(7u64);
+
+		for msg_len in 1..8 {
+			let msg = (0..msg_len).map(|_| Fq::rand(rng)).collect::<Vec<_>>();
+			let ciphertext = encrypt(&hasher, &msg, &key, nonce).unwrap();
+			assert_eq!(ciphertext.len(), ciphertext_len(msg_len));
+
+			let decrypted = decrypt(&hasher, &ciphertext, &key, nonce, msg_len).unwrap();
+			assert_eq!(decrypted, msg);
+		}
+	}
+
+	#[test]
+	fn should_reject_tampered_ciphertexts() {
+		let rng = &mut test_rng();
+		let hasher = setup_hasher();
+		let key = (Fq::rand(rng), Fq::rand(rng));
+		let nonce = Fq::from(7u64);
+		let msg = vec![
+			Fq::from(1u64),
+			Fq::from(2u64),
+			Fq::from(3u64),
+			Fq::from(4u64),
+		];
+		let ciphertext = encrypt(&hasher, &msg, &key, nonce).unwrap();
+
+		// Tampered ciphertext element
+		let mut tampered = ciphertext.clone();
+		tampered[0] += Fq::from(1u64);
+		assert!(decrypt(&hasher, &tampered, &key, nonce, msg.len()).is_err());
+
+		// Wrong key
+		let other_key = (key.0, key.1 + Fq::from(1u64));
+		assert!(decrypt(&hasher, &ciphertext, &other_key, nonce, msg.len()).is_err());
+
+		// Wrong nonce
+		assert!(decrypt(&hasher, &ciphertext, &key, Fq::from(8u64), msg.len()).is_err());
+
+		// Wrong length, with the same number of chunks
+		assert!(decrypt(&hasher, &ciphertext, &key, nonce, msg.len() + 1).is_err());
+	}
+
+	#[test]
+	fn should_reject_invalid_parameters() {
+		let hasher = setup_hasher();
+		let key = (Fq::from(1u64), Fq::from(2u64));
+		let msg = vec![Fq::from(1u64)];
+
+		assert!(encrypt(&hasher, &msg, &key, length_shift()).is_err());
+
+		let wrong_width = Poseidon::new(setup_params(Curve::Bn254, 5, 3));
+		assert!(encrypt(&wrong_width, &msg, &key, Fq::from(0u64)).is_err());
+	}
+}
@@ -62,6 +62,7 @@ use sbox::PoseidonSbox;
 
 use super::{from_field_elements, to_field_elements};
 
+pub mod encryption;
 pub mod sbox;
 
 #[derive(Debug)]
@@ -268,39 +269,18 @@ impl<F: PrimeField> Poseidon<F> {
 	pub fn new(params: PoseidonParameters<F>) -> Self {
 		Poseidon { params }
 	}
-}
-
-/// A field hasher over a prime field `F` is any cryptographic hash function
-/// that takes in a vector of elements of `F` and outputs a single element
-/// of `F`.
-pub trait FieldHasher<F: PrimeField> {
-	fn hash(&self, inputs: &[F]) -> Result<F, PoseidonError>;
 
-	/// With this method we separate the special case when the length of the
-	/// input vector is 2, since hashing together two field elements is
-	/// particularly useful in Merkle trees.
-	fn hash_two(&self, left: &F, right: &F) -> Result<F, PoseidonError>;
-}
-
-/// The Poseidon hash algorithm.
-impl<F: PrimeField> FieldHasher<F> for Poseidon<F> {
-	fn hash(&self, inputs: &[F]) -> Result<F, PoseidonError> {
+	/// Applies the Poseidon permutation to a full state vector, whose length
+	/// must be the `width` parameter.
+	pub fn permute(&self, mut state: Vec<F>) -> Result<Vec<F>, PoseidonError> {
 		// Casting params to usize
 		let width = self.params.width as usize;
 		let partial_rounds = self.params.partial_rounds as usize;
 		let full_rounds = self.params.full_rounds as usize;
 
-		// Populate a state vector with 0 and then inputs, pad with zeros if necessary
-		if inputs.len() > width - 1 {
+		if state.len() != width {
 			return Err(PoseidonError::InvalidInputs);
 		}
-		let mut state = vec![F::zero()];
-		for f in inputs {
-			state.push(*f);
-		}
-		while state.len() < width {
-			state.push(F::zero());
-		}
 
 		let nr = full_rounds + partial_rounds;
 		for r in 0..nr {
@@ -337,6 +317,40 @@ impl<F: PrimeField> FieldHasher<F> for Poseidon<F> {
 				.collect();
 		}
 
+		Ok(state)
+	}
+}
+
+/// A field hasher over a prime field `F` is any cryptographic hash function
+/// that takes in a vector of elements of `F` and outputs a single element
+/// of `F`.
+pub trait FieldHasher<F: PrimeField> {
+	fn hash(&self, inputs: &[F]) -> Result<F, PoseidonError>;
+
+	/// With this method we separate the special case when the length of the
+	/// input vector is 2, since hashing together two field elements is
+	/// particularly useful in Merkle trees.
+	fn hash_two(&self, left: &F, right: &F) -> Result<F, PoseidonError>;
+}
+
+/// The Poseidon hash algorithm.
+impl<F: PrimeField> FieldHasher<F> for Poseidon<F> {
+	fn hash(&self, inputs: &[F]) -> Result<F, PoseidonError> {
+		let width = self.params.width as usize;
+
+		// Populate a state vector with 0 and then inputs, pad with zeros if necessary
+		if inputs.len() > width - 1 {
+			return Err(PoseidonError::InvalidInputs);
+		}
+		let mut state = vec![F::zero()];
+		for f in inputs {
+			state.push(*f);
+		}
+		while state.len() < width {
+			state.push(F::zero());
+		}
+
+		let state = self.permute(state)?;
 		Ok(state[0])
 	}
 
@@ -543,6 +557,33 @@ pub mod test {
 		);
 	}
 
+	#[test]
+	fn test_width_6_circom_bn_254() {
+		let curve = Curve::Bn254;
+
+		let params = setup_params(curve, 5, 6);
+		let poseidon = PoseidonHasher::new(params);
+
+		// output from circomlib, and here is the code.
+		// ```js
+		// const { poseidon } = require('circomlib');
+		// console.log(poseidon([3, 4, 5, 10, 23]).toString(16));
+		// ```
+		let res: Vec<Fq> = bytes_vec_to_f(
+			&parse_vec(vec![
+				"0x1cd139bdaaf7450acd086f9aa725241c0e6e2ef2d7ec9e3fa28e90a407417261",
+			])
+			.unwrap(),
+		);
+		let inputs = [3u64, 4, 5, 10, 23]
+			.iter()
+			.map(|x| Fq::from(*x))
+			.collect::<Vec<Fq>>();
+		let poseidon_res = poseidon.hash(&inputs).unwrap();
+
+		assert_eq!(res[0], poseidon_res, "{} != {}", res[0], poseidon_res);
+	}
+
 	#[test]
 	fn test_parameter_to_and_from_bytes() {
 		let curve = Curve::Bn254;
@@ -0,0 +1,128 @@
+// This file is part of Webb.
+//
+// Copyright (C) 2021 Webb Technologies Inc.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Native helpers of the Rate-Limiting Nullifier (RLN) construction.
+//!
+//! A member of the group is identified by the commitment `Hash(sk)` of its
+//! secret key `sk`. Each message sent in an epoch reveals a point `(x, y)` of
+//! the line `y = sk + a1 * x`, where `x` is the hash of the message and `a1 =
+//! Hash(sk, epoch)`. The epoch nullifier `Hash(a1)` is the same for every
+//! message of an epoch, so two messages in one epoch are detected and their
+//! shares are enough to recover the secret key with `recover_secret`.
+//!
+//! The identity commitment and the epoch nullifier are computed with a hasher
+//! of width 2, `a1` with a hasher of width 3, the same as the one of the tree.
+
+use crate::{poseidon::FieldHasher, Error};
+use ark_ff::PrimeField;
+use ark_std::string::ToString;
+
+#[derive(Debug)]
+pub enum RlnError {
+	/// Thrown if two shares have the same `x`, so the line can't be recovered
+	SameShareX,
+}
+
+impl core::fmt::Display for RlnError {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		let msg = match self {
+			RlnError::SameShareX => "Shares have the same x".to_string(),
+		};
+		write!(f, "{}", msg)
+	}
+}
+
+impl ark_std::error::Error for RlnError {}
+
+/// A point of the line of a member for an epoch
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Share<F: PrimeField> {
+	pub x: F,
+	pub y: F,
+}
+
+/// Computes the identity commitment `Hash(sk)` stored in the tree
+pub fn identity_commitment<F: PrimeField, H: FieldHasher<F>>(
+	secret: &F,
+	hasher2: &H,
+) -> Result<F, Error> {
+	Ok(hasher2.hash(&[*secret])?)
+}
+
+/// Computes the share of the message hashed to `x` in `epoch` and the epoch
+/// nullifier
+pub fn create_share<F: PrimeField, H: FieldHasher<F>>(
+	secret: &F,
+	epoch: &F,
+	x: &F,
+	hasher2: &H,
+	hasher3: &H,
+) -> Result<(Share<F>, F), Error> {
+	let a1 = hasher3.hash_two(secret, epoch)?;
+	let y = *secret + a1 * x;
+	let nullifier = hasher2.hash(&[a1])?;
+	Ok((Share { x: *x, y }, nullifier))
+}
+
+/// Recovers the secret key from two shares of the same epoch, i.e. the value
+/// at zero of the line through both points
+pub fn recover_secret<F: PrimeField>(a: &Share<F>, b: &Share<F>) -> Result<F, Error> {
+	let dx_inv = (b.x - a.x).inverse().ok_or(RlnError::SameShareX)?;
+	let a1 = (b.y - a.y) * dx_inv;
+	Ok(a.y - a1 * a.x)
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::poseidon::{test::setup_params, Poseidon};
+	use ark_bn254::Fr;
+	use ark_std::{test_rng, UniformRand};
+	use arkworks_utils::Curve;
+
+	#[test]
+	fn should_recover_secret_from_two_shares() {
+		let rng = &mut test_rng();
+		let hasher2 = Poseidon::new(setup_params::<Fr>(Curve::Bn254, 5, 2));
+		let hasher3 = Poseidon::new(setup_params::<Fr>(Curve::Bn254, 5, 3));
+
+		let secret = Fr::rand(rng);
+		let epoch = Fr::from(42u64);
+		let (share_a, nullifier_a) =
+			create_share(&secret, &epoch, &Fr::rand(rng), &hasher2, &hasher3).unwrap();
+		let (share_b, nullifier_b) =
+			create_share(&secret, &epoch, &Fr::rand(rng), &hasher2, &hasher3).unwrap();
+
+		// Both messages of the epoch are linked by the nullifier
+		assert_eq!(nullifier_a, nullifier_b);
+		assert_eq!(recover_secret(&share_a, &share_b).unwrap(), secret);
+
+		// A message in another epoch has another nullifier and line
+		let (share_c, nullifier_c) = create_share(
+			&secret,
+			&Fr::from(43u64),
+			&Fr::rand(rng),
+			&hasher2,
+			&hasher3,
+		)
+		.unwrap();
+		assert_ne!(nullifier_a, nullifier_c);
+		assert_ne!(recover_secret(&share_a, &share_c).unwrap(), secret);
+
+		assert!(recover_secret(&share_a, &share_a).is_err());
+	}
+}
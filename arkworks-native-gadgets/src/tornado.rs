@@ -0,0 +1,362 @@
+// This file is part of Webb.
+//
+// Copyright (C) 2021 Webb Technologies Inc.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Native support for legacy Tornado Cash deposits, so they can be imported
+//! into the Mixer or the VAnchor.
+//!
+//! A Tornado note `tornado-<currency>-<amount>-<netId>-0x<preimage>` holds
+//! the 62 bytes preimage `nullifier || secret`, both 31 bytes little-endian
+//! integers. The deposit is the leaf `Pedersen(nullifier || secret)` of a
+//! MiMCSponge Merkle tree of height 20 whose empty leaf is
+//! `keccak256("tornado") mod p`, and it is withdrawn by revealing
+//! `Pedersen(nullifier)`.
+//!
+//! The hasher given to this module must be circomlib's MiMCSponge with 220
+//! rounds over the BN254 scalar field, see `mimc::MiMCSponge`.
+
+use crate::{
+	merkle_tree::{Path, SparseMerkleTree},
+	mimc::MiMCSponge,
+	pedersen::pedersen_hash_bytes,
+	Error,
+};
+use ark_ed_on_bn254::Fq;
+use ark_ff::{field_new, BigInteger, PrimeField};
+use ark_std::{
+	fmt,
+	rand::Rng,
+	string::{String, ToString},
+	vec::Vec,
+};
+
+/// Height of the Tornado Cash Merkle trees
+pub const TORNADO_TREE_HEIGHT: usize = 20;
+/// Number of bytes of the nullifier and of the secret of a note
+pub const NOTE_SECRET_BYTES: usize = 31;
+/// The empty leaf of the Tornado Cash Merkle trees, `keccak256("tornado")`
+/// reduced modulo the BN254 scalar field
+pub const ZERO_LEAF: Fq = field_new!(
+	Fq,
+	"21663839004416932945382355908790599225266501822907911457504978515578255421292"
+);
+
+pub type TornadoTree = SparseMerkleTree<Fq, MiMCSponge<Fq>, TORNADO_TREE_HEIGHT>;
+pub type TornadoPath = Path<Fq, MiMCSponge<Fq>, TORNADO_TREE_HEIGHT>;
+
+#[derive(Debug)]
+pub enum TornadoError {
+	/// Thrown if a note is not of the form
+	/// `tornado-<currency>-<amount>-<netId>-0x<preimage>`
+	InvalidNoteFormat,
+	/// Thrown if the preimage of a note is not 62 bytes of hex
+	InvalidPreimage,
+	/// Thrown if a nullifier or a secret does not fit in 31 bytes
+	ValueTooLarge,
+	/// Thrown if the commitment of a note is not a leaf of the tree
+	DepositNotFound,
+}
+
+impl fmt::Display for TornadoError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let msg = match self {
+			TornadoError::InvalidNoteFormat => "invalid note format".to_string(),
+			TornadoError::InvalidPreimage => "invalid note preimage".to_string(),
+			TornadoError::ValueTooLarge => "value does not fit in 31 bytes".to_string(),
+			TornadoError::DepositNotFound => "deposit not found in the tree".to_string(),
+		};
+		write!(f, "{}", msg)
+	}
+}
+
+impl ark_std::error::Error for TornadoError {}
+
+/// A Tornado Cash deposit note
+#[derive(Debug, Clone, PartialEq)]
+pub struct TornadoNote {
+	/// Currency of the pool, e.g. `eth`
+	pub currency: String,
+	/// Denomination of the pool, as written in the note, e.g. `0.1`
+	pub amount: String,
+	/// Id of the network of the pool
+	pub net_id: u64,
+	nullifier: Fq,
+	secret: Fq,
+}
+
+impl TornadoNote {
+	pub fn new(
+		currency: String,
+		amount: String,
+		net_id: u64,
+		nullifier: Fq,
+		secret: Fq,
+	) -> Result<Self, Error> {
+		if !fits_in_note(&nullifier) || !fits_in_note(&secret) {
+			return Err(TornadoError::ValueTooLarge.into());
+		}
+		Ok(Self {
+			currency,
+			amount,
+			net_id,
+			nullifier,
+			secret,
+		})
+	}
+
+	/// Creates a note with a random nullifier and secret, like Tornado
+	/// Cash's `createDeposit`
+	pub fn generate<R: Rng>(currency: String, amount: String, net_id: u64, rng: &mut R) -> Self {
+		let nullifier: [u8; NOTE_SECRET_BYTES] = rng.gen();
+		let secret: [u8; NOTE_SECRET_BYTES] = rng.gen();
+		Self {
+			currency,
+			amount,
+			net_id,
+			nullifier: Fq::from_le_bytes_mod_order(&nullifier),
+			secret: Fq::from_le_bytes_mod_order(&secret),
+		}
+	}
+
+	/// Parses a note of the form
+	/// `tornado-<currency>-<amount>-<netId>-0x<preimage>`
+	pub fn parse(note: &str) -> Result<Self, Error> {
+		let parts: Vec<&str> = note.trim().split('-').collect();
+		if parts.len() != 5 || parts[0] != "tornado" {
+			return Err(TornadoError::InvalidNoteFormat.into());
+		}
+		let (currency, amount, net_id, preimage) = (parts[1], parts[2], parts[3], parts[4]);
+
+		let is_word = |c: char| c.is_ascii_alphanumeric() || c == '_';
+		if currency.is_empty() || !currency.chars().all(is_word) {
+			return Err(TornadoError::InvalidNoteFormat.into());
+		}
+		if amount.is_empty() || !amount.chars().all(|c| c.is_ascii_digit() || c == '.') {
+			return Err(TornadoError::InvalidNoteFormat.into());
+		}
+		if net_id.is_empty() || !net_id.chars().all(|c| c.is_ascii_digit()) {
+			return Err(TornadoError::InvalidNoteFormat.into());
+		}
+		let net_id = net_id
+			.parse::<u64>()
+			.map_err(|_| TornadoError::InvalidNoteFormat)?;
+
+		let preimage = preimage
+			.strip_prefix("0x")
+			.ok_or(TornadoError::InvalidNoteFormat)?;
+		let preimage = decode_hex(preimage)?;
+		if preimage.len() != 2 * NOTE_SECRET_BYTES {
+			return Err(TornadoError::InvalidPreimage.into());
+		}
+		let (nullifier, secret) = preimage.split_at(NOTE_SECRET_BYTES);
+
+		Ok(Self {
+			currency: currency.to_string(),
+			amount: amount.to_string(),
+			net_id,
+			nullifier: Fq::from_le_bytes_mod_order(nullifier),
+			secret: Fq::from_le_bytes_mod_order(secret),
+		})
+	}
+
+	pub fn nullifier(&self) -> Fq {
+		self.nullifier
+	}
+
+	pub fn secret(&self) -> Fq {
+		self.secret
+	}
+
+	/// The 62 bytes `nullifier || secret`, both little-endian
+	pub fn preimage(&self) -> Vec<u8> {
+		let mut preimage = to_note_bytes(&self.nullifier);
+		preimage.extend_from_slice(&to_note_bytes(&self.secret));
+		preimage
+	}
+
+	/// The commitment `Pedersen(nullifier || secret)`, the leaf of the
+	/// deposit in the Tornado Cash tree
+	pub fn commitment(&self) -> Result<Fq, Error> {
+		Ok(pedersen_hash_bytes(&self.preimage())?)
+	}
+
+	/// The nullifier hash `Pedersen(nullifier)` revealed on withdrawal
+	pub fn nullifier_hash(&self) -> Result<Fq, Error> {
+		Ok(pedersen_hash_bytes(&to_note_bytes(&self.nullifier))?)
+	}
+}
+
+impl fmt::Display for TornadoNote {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(
+			f,
+			"tornado-{}-{}-{}-0x",
+			self.currency, self.amount, self.net_id
+		)?;
+		for byte in self.preimage() {
+			write!(f, "{:02x}", byte)?;
+		}
+		Ok(())
+	}
+}
+
+/// Rebuilds a Tornado Cash tree from its commitments, in deposit order
+pub fn build_tree(commitments: &[Fq], hasher: &MiMCSponge<Fq>) -> Result<TornadoTree, Error> {
+	let zero_leaf = ZERO_LEAF.into_repr().to_bytes_be();
+	TornadoTree::new_sequential(commitments, hasher, &zero_leaf)
+}
+
+/// Rebuilds a Tornado Cash tree from its commitments and returns its root
+/// along with the path of the deposit of `note`
+pub fn deposit_path(
+	commitments: &[Fq],
+	note: &TornadoNote,
+	hasher: &MiMCSponge<Fq>,
+) -> Result<(Fq, TornadoPath), Error> {
+	let commitment = note.commitment()?;
+	let index = commitments
+		.iter()
+		.position(|leaf| *leaf == commitment)
+		.ok_or(TornadoError::DepositNotFound)?;
+
+	let tree = build_tree(commitments, hasher)?;
+	Ok((tree.root(), tree.generate_membership_proof(index as u64)))
+}
+
+fn fits_in_note(value: &Fq) -> bool {
+	value.into_repr().num_bits() as usize <= NOTE_SECRET_BYTES * 8
+}
+
+fn to_note_bytes(value: &Fq) -> Vec<u8> {
+	let mut bytes = value.into_repr().to_bytes_le();
+	bytes.truncate(NOTE_SECRET_BYTES);
+	bytes
+}
+
+fn decode_hex(hex: &str) -> Result<Vec<u8>, TornadoError> {
+	if hex.len() % 2 != 0 {
+		return Err(TornadoError::InvalidPreimage);
+	}
+	(0..hex.len())
+		.step_by(2)
+		.map(|i| {
+			hex.get(i..i + 2)
+				.and_then(|byte| u8::from_str_radix(byte, 16).ok())
+				.ok_or(TornadoError::InvalidPreimage)
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::{mimc::test::setup_mimc_sponge, poseidon::FieldHasher};
+	use ark_ff::Field;
+	use ark_std::test_rng;
+	use arkworks_utils::Curve;
+
+	// The values below were computed with an independent implementation of
+	// circomlib's `pedersenHash` and `MiMCSponge`
+	const NOTE: &str = "tornado-eth-0.1-1-0x0102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f202122232425262728292a2b2c2d2e2f303132333435363738393a3b3c3d3e";
+	const COMMITMENT: Fq = field_new!(
+		Fq,
+		"12586749863153184864925885664262035978966482588559049325782590101023831672229"
+	);
+	const NULLIFIER_HASH: Fq = field_new!(
+		Fq,
+		"8900415171344073390802788145013945835304806373489649092413952449106704923705"
+	);
+	// Root of the tree holding only the commitment above
+	const ROOT: Fq = field_new!(
+		Fq,
+		"648289624649595634125937099306742813737983021200451218248220960647909972271"
+	);
+
+	#[test]
+	fn should_parse_note() {
+		let note = TornadoNote::parse(NOTE).unwrap();
+		assert_eq!(note.currency, "eth");
+		assert_eq!(note.amount, "0.1");
+		assert_eq!(note.net_id, 1);
+		assert_eq!(note.nullifier().into_repr().to_bytes_le()[0], 0x01);
+		assert_eq!(note.secret().into_repr().to_bytes_le()[0], 0x20);
+		assert_eq!(note.to_string(), NOTE);
+	}
+
+	#[test]
+	fn should_reject_invalid_notes() {
+		let invalid = [
+			"tornado-eth-0.1-1-0x0102",
+			"tornado-eth-0.1-0x0102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f202122232425262728292a2b2c2d2e2f303132333435363738393a3b3c3d3e",
+			"webb-eth-0.1-1-0x0102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f202122232425262728292a2b2c2d2e2f303132333435363738393a3b3c3d3e",
+			"tornado-eth-0.1-1-0x0102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f202122232425262728292a2b2c2d2e2f303132333435363738393a3b3c3d3g",
+		];
+		for note in invalid.iter() {
+			assert!(TornadoNote::parse(note).is_err());
+		}
+	}
+
+	#[test]
+	fn should_derive_commitment_and_nullifier_hash() {
+		let note = TornadoNote::parse(NOTE).unwrap();
+		assert_eq!(note.commitment().unwrap(), COMMITMENT);
+		assert_eq!(note.nullifier_hash().unwrap(), NULLIFIER_HASH);
+	}
+
+	#[test]
+	fn should_rebuild_tree() {
+		let hasher = setup_mimc_sponge::<Fq>(Curve::Bn254);
+		let note = TornadoNote::parse(NOTE).unwrap();
+
+		// Taken from the `zeros` of Tornado Cash's MerkleTreeWithHistory.sol
+		let zero_1 = hasher.hash_two(&ZERO_LEAF, &ZERO_LEAF).unwrap();
+		let zero_2 = hasher.hash_two(&zero_1, &zero_1).unwrap();
+		assert_eq!(
+			zero_1,
+			field_new!(
+				Fq,
+				"16923532097304556005972200564242292693309333953544141029519619077135960040221"
+			)
+		);
+		assert_eq!(
+			zero_2,
+			field_new!(
+				Fq,
+				"7833458610320835472520144237082236871909694928684820466656733259024982655488"
+			)
+		);
+
+		let (root, path) = deposit_path(&[COMMITMENT], &note, &hasher).unwrap();
+		assert_eq!(root, ROOT);
+		assert!(path.check_membership(&root, &COMMITMENT, &hasher).unwrap());
+	}
+
+	#[test]
+	fn should_generate_valid_notes() {
+		let rng = &mut test_rng();
+		let note = TornadoNote::generate("eth".to_string(), "1".to_string(), 5, rng);
+		let parsed = TornadoNote::parse(&note.to_string()).unwrap();
+		assert_eq!(parsed, note);
+		assert!(TornadoNote::new(
+			"eth".to_string(),
+			"1".to_string(),
+			5,
+			Fq::from(2u64).pow([NOTE_SECRET_BYTES as u64 * 8]),
+			note.secret(),
+		)
+		.is_err());
+	}
+}
@@ -21,10 +21,14 @@ use ark_ff::PrimeField;
 use arkworks_native_gadgets::merkle_tree::Path;
 use arkworks_plonk_gadgets::{
 	add_public_input_variable, add_public_input_variables, merkle_tree::PathGadget,
-	poseidon::FieldHasherGadget, set::SetGadget,
+	poseidon::FieldHasherGadget, set::SetGadget, uniqueness::UniquenessGadget,
 };
 use plonk_core::{circuit::Circuit, constraint_system::StandardComposer, error::Error};
 
+/// Above this number of inputs the nullifiers are checked to be unique with a
+/// single product of differences rather than an equality check per pair.
+pub const NULLIFIER_PRODUCT_THRESHOLD: usize = 2;
+
 pub struct VariableAnchorCircuit<
 	F: PrimeField,
 	P: TEModelParameters<BaseField = F>,
@@ -221,12 +225,11 @@ where
 		// Check all the nullifiers are unique to prevent double-spending
 		// TODO: Investigate checking nullifier uniqueness this check to the application
 		// side
-		for i in 0..INS {
-			for j in (i + 1)..INS {
-				let result =
-					composer.is_eq_with_output(nullifier_hash_vars[i], nullifier_hash_vars[j]);
-				composer.assert_equal(result, composer.zero_var());
-			}
+		let uniqueness_gadget = UniquenessGadget::<F, P>::new(nullifier_hash_vars);
+		if INS > NULLIFIER_PRODUCT_THRESHOLD {
+			uniqueness_gadget.assert_unique(composer);
+		} else {
+			uniqueness_gadget.assert_unique_pairwise(composer);
 		}
 
 		for i in 0..OUTS {
@@ -6,6 +6,7 @@ pub extern crate ark_std;
 pub mod merkle_tree;
 pub mod poseidon;
 pub mod set;
+pub mod uniqueness;
 
 use ark_ec::models::TEModelParameters;
 use ark_ff::PrimeField;
@@ -0,0 +1,141 @@
+// This file is part of Webb.
+//
+// Copyright (C) 2021 Webb Technologies Inc.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A Plonk gadget to check that all elements of a list are distinct.
+//!
+//! Instead of an equality check per pair of elements, the gadget takes the
+//! difference of every pair, accumulates the product of all those
+//! differences and checks once that the product is not zero. The product is
+//! zero if and only if two of the elements are equal.
+//!
+//! For example, one could check that the nullifier hashes of a transaction
+//! with many inputs are all different.
+
+use ark_ec::models::TEModelParameters;
+use ark_ff::PrimeField;
+use ark_std::{marker::PhantomData, vec::Vec};
+use plonk_core::{constraint_system::StandardComposer, prelude::Variable};
+
+pub struct UniquenessGadget<F: PrimeField, P: TEModelParameters<BaseField = F>> {
+	items: Vec<Variable>,
+	te: PhantomData<P>,
+}
+
+impl<F: PrimeField, P: TEModelParameters<BaseField = F>> UniquenessGadget<F, P> {
+	pub fn new(items: Vec<Variable>) -> Self {
+		Self {
+			items,
+			te: PhantomData,
+		}
+	}
+
+	/// A function whose output is 1 if two of the items are equal
+	/// and 0 otherwise.  Contraints are added to a StandardComposer
+	/// and the output is added as a variable to the StandardComposer.
+	pub fn check_duplicates(&self, composer: &mut StandardComposer<F, P>) -> Variable {
+		// Accumulate the product of the differences of all pairs
+		let mut accumulator = composer.add_witness_to_circuit_description(F::one());
+		for i in 0..self.items.len() {
+			for j in (i + 1)..self.items.len() {
+				let (a, b) = (self.items[i], self.items[j]);
+				let diff = composer
+					.arithmetic_gate(|gate| gate.witness(a, b, None).add(F::one(), -F::one()));
+				accumulator = composer
+					.arithmetic_gate(|gate| gate.witness(accumulator, diff, None).mul(F::one()));
+			}
+		}
+
+		composer.is_zero_with_output(accumulator)
+	}
+
+	/// Constrains all items to be distinct with a single zero check on the
+	/// product of the pairwise differences.
+	pub fn assert_unique(&self, composer: &mut StandardComposer<F, P>) {
+		let has_duplicates = self.check_duplicates(composer);
+		composer.assert_equal(has_duplicates, composer.zero_var());
+	}
+
+	/// Constrains all items to be distinct with an equality check per pair
+	/// of items.  Intended for a small number of items.
+	pub fn assert_unique_pairwise(&self, composer: &mut StandardComposer<F, P>) {
+		for i in 0..self.items.len() {
+			for j in (i + 1)..self.items.len() {
+				let result = composer.is_eq_with_output(self.items[i], self.items[j]);
+				composer.assert_equal(result, composer.zero_var());
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use ark_ed_on_bn254::{EdwardsParameters as JubjubParameters, Fq};
+
+	#[test]
+	fn test_verify_unique_items() {
+		let items = vec![Fq::from(1u32), Fq::from(2u32), Fq::from(3u32)];
+		let mut composer = StandardComposer::<Fq, JubjubParameters>::new();
+
+		let vars = items.into_iter().map(|x| composer.add_input(x)).collect();
+		let uniqueness_gadget = UniquenessGadget::new(vars);
+
+		let zero = composer.zero_var();
+		let result = uniqueness_gadget.check_duplicates(&mut composer);
+		composer.assert_equal(result, zero);
+		composer.check_circuit_satisfied();
+	}
+
+	#[test]
+	fn test_detect_duplicate_items() {
+		let items = vec![Fq::from(1u32), Fq::from(2u32), Fq::from(1u32)];
+		let mut composer = StandardComposer::<Fq, JubjubParameters>::new();
+
+		let vars = items.into_iter().map(|x| composer.add_input(x)).collect();
+		let uniqueness_gadget = UniquenessGadget::new(vars);
+
+		let one = composer.add_input(Fq::from(1u32));
+		let result = uniqueness_gadget.check_duplicates(&mut composer);
+		composer.assert_equal(result, one);
+		composer.check_circuit_satisfied();
+	}
+
+	#[test]
+	fn test_product_uses_fewer_gates_than_pairwise() {
+		for n in [2u32, 4, 16] {
+			let items = (0..n).map(Fq::from).collect::<Vec<_>>();
+
+			let mut composer = StandardComposer::<Fq, JubjubParameters>::new();
+			let vars = items.iter().map(|x| composer.add_input(*x)).collect();
+			let start = composer.circuit_size();
+			UniquenessGadget::new(vars).assert_unique(&mut composer);
+			let product_gates = composer.circuit_size() - start;
+			composer.check_circuit_satisfied();
+
+			let mut composer = StandardComposer::<Fq, JubjubParameters>::new();
+			let vars = items.iter().map(|x| composer.add_input(*x)).collect();
+			let start = composer.circuit_size();
+			UniquenessGadget::new(vars).assert_unique_pairwise(&mut composer);
+			let pairwise_gates = composer.circuit_size() - start;
+			composer.check_circuit_satisfied();
+
+			if n > 2 {
+				assert!(product_gates < pairwise_gates);
+			}
+		}
+	}
+}
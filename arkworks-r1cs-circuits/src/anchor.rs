@@ -51,7 +51,7 @@ pub struct AnchorCircuit<F: PrimeField, HG: FieldHasherGadget<F>, const N: usize
 	nullifier: F,
 	// source chain_id
 	chain_id: F,
-	// Merkle root set to use on one-of-many proof
+	// Merkle root set to use on one-of-many proof, unused slots are zero
 	root_set: [F; M],
 	// Merkle path to transaction
 	path: Path<F, HG::Native, N>,
@@ -136,8 +136,8 @@ where
 			hasher4_gadget.hash(&[chain_id_var, nullifier_var.clone(), secret_var])?;
 		let anchor_nullifier = hasher3_gadget.hash_two(&nullifier_var, &nullifier_var)?;
 		let root_var = path_var.root_hash(&anchor_leaf, &hasher3_gadget)?;
-		// Check if target root is in set
-		let set_gadget = SetGadget::new(roots_var);
+		// Check if target root is in set, ignoring the unused (zero) slots
+		let set_gadget = SetGadget::new_with_zero_slots_disabled(roots_var)?;
 		let is_set_member = set_gadget.check_membership(&root_var)?;
 		// Constraining arbitrary inputs
 		let _ = &arbitrary_input_var * &arbitrary_input_var;
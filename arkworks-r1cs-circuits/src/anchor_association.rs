@@ -0,0 +1,187 @@
+// This file is part of Webb.
+
+// Copyright (C) 2021 Webb Technologies Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The association set variant of the Anchor, in the spirit of Privacy Pools.
+//!
+//! On top of the usual Anchor statement, the prover can show that its deposit
+//! also belongs to an association set: a second Merkle tree holding a subset of
+//! the deposits of the pool, e.g. the ones approved by a compliance provider.
+//! The association root is a public input, so the verifier decides which set
+//! the proof is checked against.
+//!
+//! The association check is selected per proof with a public boolean input.
+//! When it is disabled, the association root and path are ignored and the
+//! circuit behaves like the plain `AnchorCircuit`.
+use ark_ff::fields::PrimeField;
+use ark_r1cs_std::{eq::EqGadget, fields::fp::FpVar, prelude::*};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+use ark_std::vec::Vec;
+use arkworks_native_gadgets::merkle_tree::Path;
+use arkworks_r1cs_gadgets::{merkle_tree::PathVar, poseidon::FieldHasherGadget, set::SetGadget};
+
+/// Defines an `AnchorAssociationCircuit` struct that hold all the information
+/// thats needed to verify the following statements:
+/// * Alice knows a witness tuple `(secret, nullifier, merklePath)`
+/// and a commitment `Hash(chain_id, nullifier, secret)` stored in one of the
+/// Anchor merkle trees,
+/// * If the association mode is enabled, Alice knows a second `merklePath`
+/// proving that the same commitment is in the association set tree with root
+/// `association_root`,
+/// * The Anchor smart contract / end-user application hasn't seen this
+///   `nullifier_hash` before.
+///
+/// `N` is the height of the Anchor trees, `M` the size of the root set and `A`
+/// the height of the association set tree.
+#[derive(Clone)]
+pub struct AnchorAssociationCircuit<
+	F: PrimeField,
+	HG: FieldHasherGadget<F>,
+	const N: usize,
+	const M: usize,
+	const A: usize,
+> {
+	// Represents the hash of
+	// recepient + relayer + fee + refunds + commitment
+	arbitrary_input: F,
+	// secret
+	secret: F,
+	// nullifier to prevent double spending
+	nullifier: F,
+	// source chain_id
+	chain_id: F,
+	// Merkle root set to use on one-of-many proof, unused slots are zero
+	root_set: [F; M],
+	// Merkle path to transaction
+	path: Path<F, HG::Native, N>,
+	nullifier_hash: F,
+	// Root of the association set tree
+	association_root: F,
+	// Whether the membership in the association set is enforced
+	association_enabled: bool,
+	// Merkle path to the commitment in the association set tree
+	association_path: Path<F, HG::Native, A>,
+	// 3 input hasher
+	hasher3: HG::Native,
+	// 4 input hasher
+	hasher4: HG::Native,
+}
+
+/// A constructor for the `AnchorAssociationCircuit`
+impl<F, HG, const N: usize, const M: usize, const A: usize> AnchorAssociationCircuit<F, HG, N, M, A>
+where
+	F: PrimeField,
+	HG: FieldHasherGadget<F>,
+{
+	#[allow(clippy::too_many_arguments)]
+	pub fn new(
+		arbitrary_input: F,
+		secret: F,
+		nullifier: F,
+		chain_id: F,
+		root_set: [F; M],
+		path: Path<F, HG::Native, N>,
+		nullifier_hash: F,
+		association_root: F,
+		association_enabled: bool,
+		association_path: Path<F, HG::Native, A>,
+		hasher3: HG::Native,
+		hasher4: HG::Native,
+	) -> Self {
+		Self {
+			arbitrary_input,
+			secret,
+			nullifier,
+			chain_id,
+			root_set,
+			path,
+			nullifier_hash,
+			association_root,
+			association_enabled,
+			association_path,
+			hasher3,
+			hasher4,
+		}
+	}
+}
+
+/// Implementation of the `ConstraintSynthesizer` trait for the
+/// `AnchorAssociationCircuit`
+impl<F, HG, const N: usize, const M: usize, const A: usize> ConstraintSynthesizer<F>
+	for AnchorAssociationCircuit<F, HG, N, M, A>
+where
+	F: PrimeField,
+	HG: FieldHasherGadget<F>,
+{
+	fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+		let arbitrary_input = self.arbitrary_input;
+		let secret = self.secret;
+		let nullifier = self.nullifier;
+		let chain_id = self.chain_id;
+		let root_set = self.root_set;
+		let path = self.path;
+		let nullifier_hash = self.nullifier_hash;
+		let association_root = self.association_root;
+		let association_enabled = self.association_enabled;
+		let association_path = self.association_path;
+
+		// Generating vars
+		// Public inputs
+		let nullifier_hash_var = FpVar::<F>::new_input(cs.clone(), || Ok(nullifier_hash))?;
+		let arbitrary_input_var = FpVar::<F>::new_input(cs.clone(), || Ok(arbitrary_input))?;
+		let chain_id_var = FpVar::<F>::new_input(cs.clone(), || Ok(chain_id))?;
+		let roots_var = Vec::<FpVar<F>>::new_input(cs.clone(), || Ok(root_set))?;
+		let association_root_var = FpVar::<F>::new_input(cs.clone(), || Ok(association_root))?;
+		let association_enabled_var = Boolean::new_input(cs.clone(), || Ok(association_enabled))?;
+
+		// Hashers
+		let hasher3_gadget: HG =
+			FieldHasherGadget::<F>::from_native(&mut cs.clone(), self.hasher3)?;
+		let hasher4_gadget: HG =
+			FieldHasherGadget::<F>::from_native(&mut cs.clone(), self.hasher4)?;
+
+		// Private inputs
+		let secret_var = FpVar::<F>::new_witness(cs.clone(), || Ok(secret))?;
+		let nullifier_var = FpVar::<F>::new_witness(cs.clone(), || Ok(nullifier))?;
+		let path_var = PathVar::<F, HG, N>::new_witness(cs.clone(), || Ok(path))?;
+		let association_path_var = PathVar::<F, HG, A>::new_witness(cs, || Ok(association_path))?;
+
+		// Creating the leaf and checking the membership inside the tree
+		let anchor_leaf =
+			hasher4_gadget.hash(&[chain_id_var, nullifier_var.clone(), secret_var])?;
+		let anchor_nullifier = hasher3_gadget.hash_two(&nullifier_var, &nullifier_var)?;
+		let root_var = path_var.root_hash(&anchor_leaf, &hasher3_gadget)?;
+		// Check if target root is in set, ignoring the unused (zero) slots
+		let set_gadget = SetGadget::new_with_zero_slots_disabled(roots_var)?;
+		let is_set_member = set_gadget.check_membership(&root_var)?;
+		// Check if the leaf is in the association set, only enforced when enabled
+		let is_association_member = association_path_var.check_membership(
+			&association_root_var,
+			&anchor_leaf,
+			&hasher3_gadget,
+		)?;
+		let association_satisfied = is_association_member.or(&association_enabled_var.not())?;
+		// Constraining arbitrary inputs
+		let _ = &arbitrary_input_var * &arbitrary_input_var;
+
+		// Enforcing constraints
+		is_set_member.enforce_equal(&Boolean::TRUE)?;
+		association_satisfied.enforce_equal(&Boolean::TRUE)?;
+		anchor_nullifier.enforce_equal(&nullifier_hash_var)?;
+
+		Ok(())
+	}
+}
@@ -0,0 +1,189 @@
+// This file is part of Webb.
+
+// Copyright (C) 2021 Webb Technologies Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Migration of an Anchor deposit into a VAnchor pool.
+//!
+//! The circuit spends an Anchor note like the `AnchorCircuit` and creates, in
+//! the same proof, a VAnchor output commitment holding the denomination of
+//! the Anchor. The funds move from the Anchor to the VAnchor pool without
+//! being withdrawn to a public address, and the commitment stays unlinkable
+//! to the Anchor deposit.
+use ark_ff::fields::PrimeField;
+use ark_r1cs_std::{eq::EqGadget, fields::fp::FpVar, prelude::*};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+use ark_std::vec::Vec;
+use arkworks_native_gadgets::merkle_tree::Path;
+use arkworks_r1cs_gadgets::{merkle_tree::PathVar, poseidon::FieldHasherGadget, set::SetGadget};
+
+/// Defines an `AnchorMigrationCircuit` struct that hold all the information
+/// thats needed to verify the following statements:
+/// * Alice knows a witness tuple `(secret, nullifier, merklePath)`
+/// and a commitment `Hash(chain_id, nullifier, secret)` stored in one of the
+/// Anchor merkle trees,
+/// * The Anchor smart contract / end-user application hasn't seen this
+///   `nullifier_hash` before,
+/// * The output commitment is the VAnchor commitment `Hash(out_chain_id,
+///   denomination, out_pubkey, out_blinding)` of a note holding exactly the
+///   public denomination.
+///
+/// Needs to implement `ConstraintSynthesizer` and a
+/// constructor to generate proper constraints
+#[derive(Clone)]
+pub struct AnchorMigrationCircuit<
+	F: PrimeField,
+	HG: FieldHasherGadget<F>,
+	const N: usize,
+	const M: usize,
+> {
+	// Amount of every deposit of the Anchor
+	denomination: F,
+	// secret
+	secret: F,
+	// nullifier to prevent double spending
+	nullifier: F,
+	// source chain_id
+	chain_id: F,
+	// Merkle root set to use on one-of-many proof, unused slots are zero
+	root_set: [F; M],
+	// Merkle path to transaction
+	path: Path<F, HG::Native, N>,
+	nullifier_hash: F,
+	// VAnchor output note
+	out_commitment: F,
+	out_chain_id: F,
+	out_pubkey: F,
+	out_blinding: F,
+	// 3 input hasher
+	hasher3: HG::Native,
+	// 4 input hasher
+	hasher4: HG::Native,
+	// 5 input hasher, of the VAnchor commitments
+	hasher5: HG::Native,
+}
+
+/// A constructor for the `AnchorMigrationCircuit`
+impl<F, HG, const N: usize, const M: usize> AnchorMigrationCircuit<F, HG, N, M>
+where
+	F: PrimeField,
+	HG: FieldHasherGadget<F>,
+{
+	#[allow(clippy::too_many_arguments)]
+	pub fn new(
+		denomination: F,
+		secret: F,
+		nullifier: F,
+		chain_id: F,
+		root_set: [F; M],
+		path: Path<F, HG::Native, N>,
+		nullifier_hash: F,
+		out_commitment: F,
+		out_chain_id: F,
+		out_pubkey: F,
+		out_blinding: F,
+		hasher3: HG::Native,
+		hasher4: HG::Native,
+		hasher5: HG::Native,
+	) -> Self {
+		Self {
+			denomination,
+			secret,
+			nullifier,
+			chain_id,
+			root_set,
+			path,
+			nullifier_hash,
+			out_commitment,
+			out_chain_id,
+			out_pubkey,
+			out_blinding,
+			hasher3,
+			hasher4,
+			hasher5,
+		}
+	}
+}
+
+/// Implementation of the `ConstraintSynthesizer` trait for the
+/// `AnchorMigrationCircuit`
+impl<F, HG, const N: usize, const M: usize> ConstraintSynthesizer<F>
+	for AnchorMigrationCircuit<F, HG, N, M>
+where
+	F: PrimeField,
+	HG: FieldHasherGadget<F>,
+{
+	fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+		let denomination = self.denomination;
+		let secret = self.secret;
+		let nullifier = self.nullifier;
+		let chain_id = self.chain_id;
+		let root_set = self.root_set;
+		let path = self.path;
+		let nullifier_hash = self.nullifier_hash;
+		let out_commitment = self.out_commitment;
+		let out_chain_id = self.out_chain_id;
+		let out_pubkey = self.out_pubkey;
+		let out_blinding = self.out_blinding;
+
+		// Generating vars
+		// Public inputs
+		let nullifier_hash_var = FpVar::<F>::new_input(cs.clone(), || Ok(nullifier_hash))?;
+		let denomination_var = FpVar::<F>::new_input(cs.clone(), || Ok(denomination))?;
+		let chain_id_var = FpVar::<F>::new_input(cs.clone(), || Ok(chain_id))?;
+		let roots_var = Vec::<FpVar<F>>::new_input(cs.clone(), || Ok(root_set))?;
+		let out_commitment_var = FpVar::<F>::new_input(cs.clone(), || Ok(out_commitment))?;
+
+		// Hashers
+		let hasher3_gadget: HG =
+			FieldHasherGadget::<F>::from_native(&mut cs.clone(), self.hasher3)?;
+		let hasher4_gadget: HG =
+			FieldHasherGadget::<F>::from_native(&mut cs.clone(), self.hasher4)?;
+		let hasher5_gadget: HG =
+			FieldHasherGadget::<F>::from_native(&mut cs.clone(), self.hasher5)?;
+
+		// Private inputs
+		let secret_var = FpVar::<F>::new_witness(cs.clone(), || Ok(secret))?;
+		let nullifier_var = FpVar::<F>::new_witness(cs.clone(), || Ok(nullifier))?;
+		let path_var = PathVar::<F, HG, N>::new_witness(cs.clone(), || Ok(path))?;
+		let out_chain_id_var = FpVar::<F>::new_witness(cs.clone(), || Ok(out_chain_id))?;
+		let out_pubkey_var = FpVar::<F>::new_witness(cs.clone(), || Ok(out_pubkey))?;
+		let out_blinding_var = FpVar::<F>::new_witness(cs, || Ok(out_blinding))?;
+
+		// Creating the leaf and checking the membership inside the tree
+		let anchor_leaf =
+			hasher4_gadget.hash(&[chain_id_var, nullifier_var.clone(), secret_var])?;
+		let anchor_nullifier = hasher3_gadget.hash_two(&nullifier_var, &nullifier_var)?;
+		let root_var = path_var.root_hash(&anchor_leaf, &hasher3_gadget)?;
+		// Check if target root is in set, ignoring the unused (zero) slots
+		let set_gadget = SetGadget::new_with_zero_slots_disabled(roots_var)?;
+		let is_set_member = set_gadget.check_membership(&root_var)?;
+
+		// Creating the VAnchor commitment of the denomination
+		let vanchor_leaf = hasher5_gadget.hash(&[
+			out_chain_id_var,
+			denomination_var,
+			out_pubkey_var,
+			out_blinding_var,
+		])?;
+
+		// Enforcing constraints
+		is_set_member.enforce_equal(&Boolean::TRUE)?;
+		anchor_nullifier.enforce_equal(&nullifier_hash_var)?;
+		vanchor_leaf.enforce_equal(&out_commitment_var)?;
+
+		Ok(())
+	}
+}
@@ -0,0 +1,169 @@
+// This file is part of Webb.
+
+// Copyright (C) 2021 Webb Technologies Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The Anchor with a Merkle-committed root set.
+//!
+//! Instead of allocating every edge root as a public input, the edge roots
+//! are committed to in a small Merkle tree (see
+//! `arkworks_native_gadgets::merkle_tree::build_root_tree`) and only the
+//! root of that tree, the "root of roots", is public. The circuit
+//! reconstructs the root of the deposit tree and proves with a second path
+//! that it is a leaf of the root tree.
+//!
+//! The number of public inputs no longer grows with the number of edges,
+//! which keeps Groth16 verification cheap for large bridges.
+//!
+//! This is the Groth16 setup implementation of the root tree Anchor
+use ark_ff::fields::PrimeField;
+use ark_r1cs_std::{eq::EqGadget, fields::fp::FpVar, prelude::*};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+use arkworks_native_gadgets::merkle_tree::Path;
+use arkworks_r1cs_gadgets::{merkle_tree::PathVar, poseidon::FieldHasherGadget};
+
+/// Defines an `AnchorRootTreeCircuit` struct that hold all the information
+/// thats needed to verify the following statements:
+/// * Alice knows a witness tuple `(secret, nullifier, merklePath)`
+/// and a commitment `Hash(chain_id, nullifier, secret)` stored in one of the
+/// Anchor merkle trees,
+/// * The root of that merkle tree is a leaf of the root tree committed to by
+///   the public `root_of_roots`,
+/// * The Anchor smart contract / end-user application hasn't seen this
+///   `nullifier_hash` before.
+///
+/// `N` is the height of the deposit trees and `K` is the height of the root
+/// tree, which can hold up to `2^K` edge roots.
+///
+/// Needs to implement `ConstraintSynthesizer` and a
+/// constructor to generate proper constraints
+#[derive(Clone)]
+pub struct AnchorRootTreeCircuit<
+	F: PrimeField,
+	HG: FieldHasherGadget<F>,
+	const N: usize,
+	const K: usize,
+> {
+	// Represents the hash of
+	// recepient + relayer + fee + refunds + commitment
+	arbitrary_input: F,
+	// secret
+	secret: F,
+	// nullifier to prevent double spending
+	nullifier: F,
+	// source chain_id
+	chain_id: F,
+	// Root of the tree of edge roots
+	root_of_roots: F,
+	// Merkle path to transaction
+	path: Path<F, HG::Native, N>,
+	// Merkle path from the edge root to the root of roots
+	root_path: Path<F, HG::Native, K>,
+	nullifier_hash: F,
+	// 3 input hasher
+	hasher3: HG::Native,
+	// 4 input hasher
+	hasher4: HG::Native,
+}
+
+/// A constructor for the `AnchorRootTreeCircuit`
+impl<F, HG, const N: usize, const K: usize> AnchorRootTreeCircuit<F, HG, N, K>
+where
+	F: PrimeField,
+	HG: FieldHasherGadget<F>,
+{
+	#[allow(clippy::too_many_arguments)]
+	pub fn new(
+		arbitrary_input: F,
+		secret: F,
+		nullifier: F,
+		chain_id: F,
+		root_of_roots: F,
+		path: Path<F, HG::Native, N>,
+		root_path: Path<F, HG::Native, K>,
+		nullifier_hash: F,
+		hasher3: HG::Native,
+		hasher4: HG::Native,
+	) -> Self {
+		Self {
+			arbitrary_input,
+			secret,
+			nullifier,
+			chain_id,
+			root_of_roots,
+			path,
+			root_path,
+			nullifier_hash,
+			hasher3,
+			hasher4,
+		}
+	}
+}
+
+/// Implementation of the `ConstraintSynthesizer` trait for the
+/// `AnchorRootTreeCircuit`
+impl<F, HG, const N: usize, const K: usize> ConstraintSynthesizer<F>
+	for AnchorRootTreeCircuit<F, HG, N, K>
+where
+	F: PrimeField,
+	HG: FieldHasherGadget<F>,
+{
+	fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+		let arbitrary_input = self.arbitrary_input;
+		let secret = self.secret;
+		let nullifier = self.nullifier;
+		let chain_id = self.chain_id;
+		let root_of_roots = self.root_of_roots;
+		let path = self.path;
+		let root_path = self.root_path;
+		let nullifier_hash = self.nullifier_hash;
+
+		// Generating vars
+		// Public inputs
+		let nullifier_hash_var = FpVar::<F>::new_input(cs.clone(), || Ok(nullifier_hash))?;
+		let arbitrary_input_var = FpVar::<F>::new_input(cs.clone(), || Ok(arbitrary_input))?;
+		let chain_id_var = FpVar::<F>::new_input(cs.clone(), || Ok(chain_id))?;
+		let root_of_roots_var = FpVar::<F>::new_input(cs.clone(), || Ok(root_of_roots))?;
+
+		// Hashers
+		let hasher3_gadget: HG =
+			FieldHasherGadget::<F>::from_native(&mut cs.clone(), self.hasher3)?;
+		let hasher4_gadget: HG =
+			FieldHasherGadget::<F>::from_native(&mut cs.clone(), self.hasher4)?;
+
+		// Private inputs
+		let secret_var = FpVar::<F>::new_witness(cs.clone(), || Ok(secret))?;
+		let nullifier_var = FpVar::<F>::new_witness(cs.clone(), || Ok(nullifier))?;
+		let path_var = PathVar::<F, HG, N>::new_witness(cs.clone(), || Ok(path))?;
+		let root_path_var = PathVar::<F, HG, K>::new_witness(cs, || Ok(root_path))?;
+
+		// Creating the leaf and checking the membership inside the tree
+		let anchor_leaf =
+			hasher4_gadget.hash(&[chain_id_var, nullifier_var.clone(), secret_var])?;
+		let anchor_nullifier = hasher3_gadget.hash_two(&nullifier_var, &nullifier_var)?;
+		let root_var = path_var.root_hash(&anchor_leaf, &hasher3_gadget)?;
+		// Check if target root is in the root tree
+		let is_root_member =
+			root_path_var.check_membership(&root_of_roots_var, &root_var, &hasher3_gadget)?;
+		// Constraining arbitrary inputs
+		let _ = &arbitrary_input_var * &arbitrary_input_var;
+
+		// Enforcing constraints
+		is_root_member.enforce_equal(&Boolean::TRUE)?;
+		anchor_nullifier.enforce_equal(&nullifier_hash_var)?;
+
+		Ok(())
+	}
+}
@@ -0,0 +1,209 @@
+// This file is part of Webb.
+
+// Copyright (C) 2021 Webb Technologies Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Proof that a set of VAnchor Utxos holds at least a public amount, without
+//! spending them, e.g. for voting weights or proofs of funds.
+//!
+//! The inputs have the same shape as the inputs of the `VAnchorCircuit`, but
+//! they all belong to a single key. Instead of the spend nullifiers, the
+//! circuit outputs one nullifier per Utxo bound to an application scope, so
+//! the same Utxo can't be counted twice in that scope and the proof can't be
+//! linked to a later spend.
+use crate::vanchor::enforce_unique_nullifiers;
+use ark_ff::fields::PrimeField;
+use ark_r1cs_std::{eq::EqGadget, fields::fp::FpVar, prelude::*};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+use ark_std::vec::Vec;
+use arkworks_native_gadgets::merkle_tree::Path;
+use arkworks_r1cs_gadgets::{
+	merkle_tree::PathVar, poseidon::FieldHasherGadget, range::RangeGadget, set::SetGadget,
+};
+
+/// Defines a `BalanceThresholdCircuit` struct that hold all the information
+/// thats needed to verify the following statements:
+/// * Alice knows a private key and, for each input, a witness tuple `(amount,
+///   blinding, merklePath)` such that the commitment `Hash(chain_id, amount,
+///   Hash(private_key), blinding)` is stored in one of the VAnchor merkle
+///   trees. Inputs with a zero amount are padding and aren't checked to be in
+///   the trees.
+/// * The scoped nullifier of each input is `Hash(private_key, scope,
+///   commitment)` and the scoped nullifiers are unique. The order of the inputs
+///   differs from the spend signature `Hash(private_key, commitment, index)`,
+///   so a scope equal to an index doesn't reveal the signature.
+/// * Every amount fits in `amount_bits` bits and their sum is at least the
+///   `threshold`.
+#[derive(Clone)]
+pub struct BalanceThresholdCircuit<
+	F: PrimeField,
+	HG: FieldHasherGadget<F>,
+	const HEIGHT: usize,
+	const N_INS: usize,
+	const ANCHOR_CT: usize,
+> {
+	threshold: F,
+	scope: F,
+	scoped_nullifiers: Vec<F>,
+
+	in_amounts: Vec<F>,
+	in_blindings: Vec<F>,
+	private_key: F,
+	in_chain_id: F,
+	root_set: [F; ANCHOR_CT],
+	paths: Vec<Path<F, HG::Native, HEIGHT>>,
+
+	tree_hasher: HG::Native,
+	keypair_hasher: HG::Native,
+	leaf_hasher: HG::Native,
+	nullifier_hasher: HG::Native,
+
+	// Bit width of the amounts, checked to prevent overflows in the field
+	amount_bits: usize,
+}
+
+/// Constructor for BalanceThresholdCircuit
+impl<F, HG, const HEIGHT: usize, const N_INS: usize, const ANCHOR_CT: usize>
+	BalanceThresholdCircuit<F, HG, HEIGHT, N_INS, ANCHOR_CT>
+where
+	F: PrimeField,
+	HG: FieldHasherGadget<F>,
+{
+	#[allow(clippy::too_many_arguments)]
+	pub fn new(
+		threshold: F,
+		scope: F,
+		scoped_nullifiers: Vec<F>,
+		in_amounts: Vec<F>,
+		in_blindings: Vec<F>,
+		private_key: F,
+		in_chain_id: F,
+		root_set: [F; ANCHOR_CT],
+		paths: Vec<Path<F, HG::Native, HEIGHT>>,
+		tree_hasher: HG::Native,
+		keypair_hasher: HG::Native,
+		leaf_hasher: HG::Native,
+		nullifier_hasher: HG::Native,
+		amount_bits: usize,
+	) -> Self {
+		Self {
+			threshold,
+			scope,
+			scoped_nullifiers,
+			in_amounts,
+			in_blindings,
+			private_key,
+			in_chain_id,
+			root_set,
+			paths,
+			tree_hasher,
+			keypair_hasher,
+			leaf_hasher,
+			nullifier_hasher,
+			amount_bits,
+		}
+	}
+
+	/// Bit width of the sum of `N_INS` amounts of `amount_bits` bits
+	pub fn sum_bits(amount_bits: usize) -> usize {
+		amount_bits + (usize::BITS - N_INS.leading_zeros()) as usize
+	}
+}
+
+impl<F, HG, const HEIGHT: usize, const N_INS: usize, const ANCHOR_CT: usize>
+	ConstraintSynthesizer<F> for BalanceThresholdCircuit<F, HG, HEIGHT, N_INS, ANCHOR_CT>
+where
+	F: PrimeField,
+	HG: FieldHasherGadget<F>,
+{
+	fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+		let threshold = self.threshold;
+		let scope = self.scope;
+		let scoped_nullifiers = self.scoped_nullifiers;
+		let in_amounts = self.in_amounts;
+		let in_blindings = self.in_blindings;
+		let private_key = self.private_key;
+		let in_chain_id = self.in_chain_id;
+		let root_set = self.root_set;
+		let paths = self.paths;
+
+		// Generating vars
+		// Public inputs
+		let threshold_var = FpVar::<F>::new_input(cs.clone(), || Ok(threshold))?;
+		let scope_var = FpVar::<F>::new_input(cs.clone(), || Ok(scope))?;
+		let scoped_nullifiers_var =
+			Vec::<FpVar<F>>::new_input(cs.clone(), || Ok(scoped_nullifiers))?;
+		let in_chain_id_var = FpVar::<F>::new_input(cs.clone(), || Ok(in_chain_id))?;
+		let root_set_var = Vec::<FpVar<F>>::new_input(cs.clone(), || Ok(root_set))?;
+
+		// Hashers
+		let tree_hasher = HG::from_native(&mut cs.clone(), self.tree_hasher)?;
+		let keypair_hasher = HG::from_native(&mut cs.clone(), self.keypair_hasher)?;
+		let leaf_hasher = HG::from_native(&mut cs.clone(), self.leaf_hasher)?;
+		let nullifier_hasher = HG::from_native(&mut cs.clone(), self.nullifier_hasher)?;
+
+		// Private inputs
+		let in_amounts_var = Vec::<FpVar<F>>::new_witness(cs.clone(), || Ok(in_amounts))?;
+		let in_blindings_var = Vec::<FpVar<F>>::new_witness(cs.clone(), || Ok(in_blindings))?;
+		let private_key_var = FpVar::<F>::new_witness(cs.clone(), || Ok(private_key))?;
+		let in_path_elements_var = Vec::<PathVar<F, HG, HEIGHT>>::new_witness(cs, || Ok(paths))?;
+
+		// Unused (zero) slots of the root set are ignored
+		let set_gadget = SetGadget::new_with_zero_slots_disabled(root_set_var)?;
+		let range_gadget = RangeGadget::new(self.amount_bits);
+		let sum_range_gadget = RangeGadget::new(Self::sum_bits(self.amount_bits));
+
+		// All the inputs are owned by the same key
+		let pub_key = keypair_hasher.hash(&[private_key_var.clone()])?;
+
+		let mut sum_ins_var = FpVar::<F>::zero();
+		for tx in 0..N_INS {
+			let in_leaf = leaf_hasher.hash(&[
+				in_chain_id_var.clone(),
+				in_amounts_var[tx].clone(),
+				pub_key.clone(),
+				in_blindings_var[tx].clone(),
+			])?;
+
+			let scoped_nullifier = nullifier_hasher.hash(&[
+				private_key_var.clone(),
+				scope_var.clone(),
+				in_leaf.clone(),
+			])?;
+			scoped_nullifier.enforce_equal(&scoped_nullifiers_var[tx])?;
+
+			let roothash = in_path_elements_var[tx].root_hash(&in_leaf, &tree_hasher)?;
+			let in_amount_tx = &in_amounts_var[tx];
+
+			// Check membership if in_amount is non zero
+			let check = set_gadget.check_membership_enabled(&roothash, in_amount_tx)?;
+			check.enforce_equal(&Boolean::TRUE)?;
+
+			// Check that amount is within the amount range (to prevent overflow)
+			range_gadget.enforce_in_range(in_amount_tx)?;
+
+			sum_ins_var += in_amount_tx;
+		}
+
+		// The same Utxo can't be counted twice
+		enforce_unique_nullifiers(&scoped_nullifiers_var)?;
+
+		// The threshold is range checked so the comparison is meaningful
+		sum_range_gadget.enforce_in_range(&threshold_var)?;
+		sum_range_gadget.enforce_less_or_equal(&threshold_var, &sum_ins_var)?;
+
+		Ok(())
+	}
+}
@@ -0,0 +1,159 @@
+// This file is part of Webb.
+
+// Copyright (C) 2021 Webb Technologies Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The batch insertion circuit moves the insertion of leaves off-chain.
+//! A relayer appends a batch of `K` deposits to a Merkle tree and submits a
+//! single proof that the tree went from `old_root` to `new_root`, instead of
+//! inserting every leaf on-chain.
+//!
+//! The leaves are inserted at consecutive indices starting at `start_index`,
+//! and every slot is checked to be empty before the insertion. The leaves
+//! themselves are committed to by a public hash, which the contract compares
+//! with the hash of the queued deposits.
+//!
+//! This is the Groth16 setup implementation of the batch insertion
+use ark_ff::fields::PrimeField;
+use ark_r1cs_std::{eq::EqGadget, fields::fp::FpVar, prelude::*};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+use ark_std::vec::Vec;
+use arkworks_native_gadgets::merkle_tree::Path;
+use arkworks_r1cs_gadgets::{
+	merkle_tree::{BatchInsertGadget, PathVar},
+	poseidon::FieldHasherGadget,
+};
+
+/// Defines a `BatchInsertCircuit` struct that hold all the information thats
+/// needed to verify the following statements:
+/// * Inserting `leaves` at indices `start_index..start_index + K` of the tree
+///   with root `old_root` results in the tree with root `new_root`,
+/// * All of these slots were empty before the insertion,
+/// * `leaves_hash` is the hash of the inserted leaves.
+///
+/// Needs to implement `ConstraintSynthesizer` and a
+/// constructor to generate proper constraints
+#[derive(Clone)]
+pub struct BatchInsertCircuit<
+	F: PrimeField,
+	HG: FieldHasherGadget<F>,
+	const N: usize,
+	const K: usize,
+> {
+	// Merkle root before the insertion
+	old_root: F,
+	// Merkle root after the insertion
+	new_root: F,
+	// Index of the first inserted leaf
+	start_index: F,
+	// Hash of the inserted leaves
+	leaves_hash: F,
+	// Leaves to insert
+	leaves: [F; K],
+	// Merkle paths to the slots of the leaves, each taken from the tree
+	// holding the leaves inserted before it
+	paths: Vec<Path<F, HG::Native, N>>,
+	// Value of the empty slots of the tree
+	empty_leaf: F,
+	// Hasher to be used inside the circuit
+	hasher: HG::Native,
+}
+
+/// Constructor for BatchInsertCircuit
+impl<F, HG, const N: usize, const K: usize> BatchInsertCircuit<F, HG, N, K>
+where
+	F: PrimeField,
+	HG: FieldHasherGadget<F>,
+{
+	#[allow(clippy::too_many_arguments)]
+	pub fn new(
+		old_root: F,
+		new_root: F,
+		start_index: F,
+		leaves_hash: F,
+		leaves: [F; K],
+		paths: Vec<Path<F, HG::Native, N>>,
+		empty_leaf: F,
+		hasher: HG::Native,
+	) -> Self {
+		Self {
+			old_root,
+			new_root,
+			start_index,
+			leaves_hash,
+			leaves,
+			paths,
+			empty_leaf,
+			hasher,
+		}
+	}
+}
+
+/// Implementation of the `ConstraintSynthesizer` trait for the
+/// `BatchInsertCircuit`
+impl<F, HG, const N: usize, const K: usize> ConstraintSynthesizer<F>
+	for BatchInsertCircuit<F, HG, N, K>
+where
+	F: PrimeField,
+	HG: FieldHasherGadget<F>,
+{
+	fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+		let old_root = self.old_root;
+		let new_root = self.new_root;
+		let start_index = self.start_index;
+		let leaves_hash = self.leaves_hash;
+		let leaves = self.leaves;
+		let paths = self.paths;
+
+		// Generating vars
+		// Public inputs
+		let old_root_var = FpVar::<F>::new_input(cs.clone(), || Ok(old_root))?;
+		let new_root_var = FpVar::<F>::new_input(cs.clone(), || Ok(new_root))?;
+		let start_index_var = FpVar::<F>::new_input(cs.clone(), || Ok(start_index))?;
+		let leaves_hash_var = FpVar::<F>::new_input(cs.clone(), || Ok(leaves_hash))?;
+
+		// Hashers
+		let hasher: HG = FieldHasherGadget::<F>::from_native(&mut cs.clone(), self.hasher)?;
+
+		// Constants
+		let empty_leaf_var = FpVar::<F>::new_constant(cs.clone(), self.empty_leaf)?;
+
+		// Private inputs
+		let leaves_var = Vec::<FpVar<F>>::new_witness(cs.clone(), || Ok(leaves))?;
+		let mut paths_var = Vec::new();
+		for path in paths {
+			paths_var.push(PathVar::<F, HG, N>::new_witness(cs.clone(), || Ok(path))?);
+		}
+
+		// Inserting the leaves and hashing them
+		let batch_insert_gadget = BatchInsertGadget::new(paths_var);
+		let is_valid_insert = batch_insert_gadget.check_batch_insert(
+			&old_root_var,
+			&new_root_var,
+			&start_index_var,
+			&leaves_var,
+			&empty_leaf_var,
+			&hasher,
+		)?;
+		let computed_leaves_hash =
+			BatchInsertGadget::<F, HG, N>::hash_leaves(&leaves_var, &hasher)?;
+
+		// Enforcing constraints
+		is_valid_insert.enforce_equal(&Boolean::TRUE)?;
+		computed_leaves_hash.enforce_equal(&leaves_hash_var)?;
+
+		Ok(())
+	}
+}
@@ -0,0 +1,241 @@
+// This file is part of Webb.
+
+// Copyright (C) 2021 Webb Technologies Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Anonymous credentials with predicates on their attributes.
+//!
+//! An issuer inserts credentials in a registry tree as leaves
+//! `Hash(holder_pk, attr_1, .., attr_k, issuer_salt)`, where `holder_pk =
+//! Hash(holder_sk)`. The holder proves that it owns a credential of the
+//! registry, without revealing which one, and that its attributes satisfy a
+//! set of predicates. The predicates are fixed by a descriptor when the
+//! circuit is set up, one per attribute, and take their operands from the
+//! public inputs, so a verifying key checks one kind of statement, e.g. "the
+//! age is at least `x` and the country is one of `s`", for any `x` and `s`.
+//! Every attribute has `SET_SIZE` operand inputs, whatever its predicate, and
+//! the ones the predicate doesn't use must be zero.
+//!
+//! Attributes compared with a bound are range checked to
+//! `ATTRIBUTE_BITS` bits, along with the bound.
+use ark_ff::fields::PrimeField;
+use ark_r1cs_std::{eq::EqGadget, fields::fp::FpVar, prelude::*};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+use ark_std::vec::Vec;
+use arkworks_native_gadgets::merkle_tree::Path;
+use arkworks_r1cs_gadgets::{
+	merkle_tree::PathVar, poseidon::FieldHasherGadget, range::RangeGadget, set::SetGadget,
+};
+
+/// Bit width of the attributes compared with a bound
+pub const ATTRIBUTE_BITS: usize = 64;
+
+/// A predicate on one attribute of a credential
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Predicate {
+	/// The attribute is not constrained
+	None,
+	/// The attribute equals a public value
+	Equal,
+	/// The attribute is at least a public bound
+	GreaterOrEqual,
+	/// The attribute is at most a public bound
+	LessOrEqual,
+	/// The attribute is one of `SET_SIZE` public values. Unused slots can
+	/// repeat a value of the set.
+	InSet,
+}
+
+impl Predicate {
+	/// Number of operands used by the predicate, out of the `set_size` public
+	/// inputs of its attribute
+	pub fn num_operands(&self, set_size: usize) -> usize {
+		match self {
+			Predicate::None => 0,
+			Predicate::Equal | Predicate::GreaterOrEqual | Predicate::LessOrEqual => 1,
+			Predicate::InSet => set_size,
+		}
+	}
+}
+
+/// Defines a `CredentialCircuit` struct that hold all the information thats
+/// needed to verify the following statements:
+/// * Alice knows a witness tuple `(holder_sk, attributes, issuer_salt,
+///   merklePath)` and a credential `Hash(Hash(holder_sk), attributes,
+///   issuer_salt)` stored in the registry merkle tree,
+/// * Every attribute satisfies its predicate, with the operands given by the
+///   public inputs.
+///
+/// Needs to implement `ConstraintSynthesizer` and a
+/// constructor to generate proper constraints
+#[derive(Clone)]
+pub struct CredentialCircuit<
+	F: PrimeField,
+	HG: FieldHasherGadget<F>,
+	const N: usize,
+	const N_ATTRS: usize,
+	const SET_SIZE: usize,
+> {
+	// Predicate descriptor, one predicate per attribute
+	predicates: [Predicate; N_ATTRS],
+	// Operands of the predicates, only the first `num_operands` values of
+	// every attribute are used and the others are zero
+	operands: [[F; SET_SIZE]; N_ATTRS],
+	// Root of the registry
+	root: F,
+	// Private key of the holder
+	holder_sk: F,
+	attributes: [F; N_ATTRS],
+	issuer_salt: F,
+	// Merkle path to the credential
+	path: Path<F, HG::Native, N>,
+	// 2 input hasher, of the holder key
+	hasher2: HG::Native,
+	// 3 input hasher, of the registry tree
+	hasher3: HG::Native,
+	// Hasher of the credentials, taking `N_ATTRS + 2` inputs
+	leaf_hasher: HG::Native,
+}
+
+/// A constructor for the `CredentialCircuit`
+impl<F, HG, const N: usize, const N_ATTRS: usize, const SET_SIZE: usize>
+	CredentialCircuit<F, HG, N, N_ATTRS, SET_SIZE>
+where
+	F: PrimeField,
+	HG: FieldHasherGadget<F>,
+{
+	#[allow(clippy::too_many_arguments)]
+	pub fn new(
+		predicates: [Predicate; N_ATTRS],
+		operands: [[F; SET_SIZE]; N_ATTRS],
+		root: F,
+		holder_sk: F,
+		attributes: [F; N_ATTRS],
+		issuer_salt: F,
+		path: Path<F, HG::Native, N>,
+		hasher2: HG::Native,
+		hasher3: HG::Native,
+		leaf_hasher: HG::Native,
+	) -> Self {
+		Self {
+			predicates,
+			operands,
+			root,
+			holder_sk,
+			attributes,
+			issuer_salt,
+			path,
+			hasher2,
+			hasher3,
+			leaf_hasher,
+		}
+	}
+
+	// Enforces `predicate` on `attribute`, with the operands `operands`
+	fn enforce_predicate(
+		predicate: Predicate,
+		attribute: &FpVar<F>,
+		operands: &[FpVar<F>],
+	) -> Result<(), SynthesisError> {
+		let range = RangeGadget::<F>::new(ATTRIBUTE_BITS);
+		match predicate {
+			Predicate::None => {}
+			Predicate::Equal => attribute.enforce_equal(&operands[0])?,
+			Predicate::GreaterOrEqual => {
+				range.enforce_in_range(attribute)?;
+				range.enforce_in_range(&operands[0])?;
+				range.enforce_less_or_equal(&operands[0], attribute)?;
+			}
+			Predicate::LessOrEqual => {
+				range.enforce_in_range(attribute)?;
+				range.enforce_in_range(&operands[0])?;
+				range.enforce_less_or_equal(attribute, &operands[0])?;
+			}
+			Predicate::InSet => {
+				let set_gadget = SetGadget::new(operands.to_vec());
+				let is_set_member = set_gadget.check_membership(attribute)?;
+				is_set_member.enforce_equal(&Boolean::TRUE)?;
+			}
+		}
+		Ok(())
+	}
+}
+
+/// Implementation of the `ConstraintSynthesizer` trait for the
+/// `CredentialCircuit`
+impl<F, HG, const N: usize, const N_ATTRS: usize, const SET_SIZE: usize> ConstraintSynthesizer<F>
+	for CredentialCircuit<F, HG, N, N_ATTRS, SET_SIZE>
+where
+	F: PrimeField,
+	HG: FieldHasherGadget<F>,
+{
+	fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+		let predicates = self.predicates;
+		let operands = self.operands;
+		let root = self.root;
+		let holder_sk = self.holder_sk;
+		let attributes = self.attributes;
+		let issuer_salt = self.issuer_salt;
+		let path = self.path;
+
+		// Generating vars
+		// Public inputs
+		let root_var = FpVar::<F>::new_input(cs.clone(), || Ok(root))?;
+		let mut operands_var = Vec::with_capacity(N_ATTRS);
+		for values in operands.iter() {
+			operands_var.push(Vec::<FpVar<F>>::new_input(cs.clone(), || Ok(*values))?);
+		}
+
+		// Hashers
+		let hasher2_gadget: HG =
+			FieldHasherGadget::<F>::from_native(&mut cs.clone(), self.hasher2)?;
+		let hasher3_gadget: HG =
+			FieldHasherGadget::<F>::from_native(&mut cs.clone(), self.hasher3)?;
+		let leaf_hasher_gadget: HG =
+			FieldHasherGadget::<F>::from_native(&mut cs.clone(), self.leaf_hasher)?;
+
+		// Private inputs
+		let holder_sk_var = FpVar::<F>::new_witness(cs.clone(), || Ok(holder_sk))?;
+		let attributes_var = Vec::<FpVar<F>>::new_witness(cs.clone(), || Ok(attributes))?;
+		let issuer_salt_var = FpVar::<F>::new_witness(cs.clone(), || Ok(issuer_salt))?;
+		let path_var = PathVar::<F, HG, N>::new_witness(cs, || Ok(path))?;
+
+		// Creating the credential and checking the membership inside the registry
+		let holder_pk = hasher2_gadget.hash(&[holder_sk_var])?;
+		let mut leaf_inputs = vec![holder_pk];
+		leaf_inputs.extend(attributes_var.iter().cloned());
+		leaf_inputs.push(issuer_salt_var);
+		let credential = leaf_hasher_gadget.hash(&leaf_inputs)?;
+		let is_member = path_var.check_membership(&root_var, &credential, &hasher3_gadget)?;
+
+		// Enforcing constraints
+		is_member.enforce_equal(&Boolean::TRUE)?;
+		for ((predicate, attribute), operands) in predicates
+			.iter()
+			.zip(attributes_var.iter())
+			.zip(operands_var.iter())
+		{
+			// The unused operands are zero, so a proof has a single valid set of
+			// public inputs
+			let (used, unused) = operands.split_at(predicate.num_operands(SET_SIZE));
+			for operand in unused {
+				operand.enforce_equal(&FpVar::zero())?;
+			}
+			Self::enforce_predicate(*predicate, attribute, used)?;
+		}
+
+		Ok(())
+	}
+}
@@ -0,0 +1,232 @@
+// This file is part of Webb.
+
+// Copyright (C) 2021 Webb Technologies Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Import of a legacy Tornado Cash deposit into a Mixer or a VAnchor pool.
+//!
+//! Works like the `MixerCircuit` over the scalar field of BN254, except that
+//! the spent deposit is a Tornado Cash one: its leaf is the Pedersen
+//! commitment `Pedersen(nullifier || secret)` over Baby Jubjub, stored in a
+//! MiMCSponge Merkle tree, and it is nullified by revealing
+//! `Pedersen(nullifier)`, as in Tornado Cash. In the same proof, the circuit
+//! re-commits the deposit as a Mixer note or as a VAnchor note holding the
+//! denomination of the pool, so the funds move without being withdrawn to a
+//! public address and the new note stays unlinkable to the deposit.
+//!
+//! The legacy nullifier hash is the one of Tornado Cash, so the importing
+//! contract should also reject the nullifier hashes already spent on the
+//! Tornado Cash pool.
+use ark_ed_on_bn254::Fq;
+use ark_ff::Zero;
+use ark_r1cs_std::{eq::EqGadget, fields::fp::FpVar, prelude::*};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+use ark_std::vec::Vec;
+use arkworks_native_gadgets::{
+	mimc::MiMCSponge,
+	tornado::{TornadoPath, NOTE_SECRET_BYTES, TORNADO_TREE_HEIGHT},
+};
+use arkworks_r1cs_gadgets::{
+	merkle_tree::PathVar, mimc::MiMCSpongeGadget, pedersen::PedersenHashGadget,
+	poseidon::FieldHasherGadget,
+};
+
+/// Number of bits of the nullifier and of the secret of a Tornado Cash note
+pub const NOTE_BITS: usize = NOTE_SECRET_BYTES * 8;
+
+/// The note a legacy deposit is re-committed as
+#[derive(Clone)]
+pub enum LegacyImportOutput {
+	/// A Mixer note, with the commitment `Hash(secret, nullifier)`
+	Mixer { secret: Fq, nullifier: Fq },
+	/// A VAnchor note, with the commitment `Hash(chain_id, denomination,
+	/// pubkey, blinding)`
+	VAnchor {
+		chain_id: Fq,
+		pubkey: Fq,
+		blinding: Fq,
+	},
+}
+
+impl LegacyImportOutput {
+	/// The chain the note is created on, which is a public input. Mixer notes
+	/// aren't bound to a chain, so it is zero for them.
+	pub fn chain_id(&self) -> Fq {
+		match self {
+			LegacyImportOutput::Mixer { .. } => Fq::zero(),
+			LegacyImportOutput::VAnchor { chain_id, .. } => *chain_id,
+		}
+	}
+}
+
+/// Defines a `LegacyImportCircuit` struct that hold all the information
+/// thats needed to verify the following statements:
+/// * Alice knows a witness tuple `(secret, nullifier, merklePath)`, both 248
+///   bits integers, and a commitment `Pedersen(nullifier || secret)` stored in
+///   the Tornado Cash merkle tree with the public root,
+/// * The public `nullifier_hash` is `Pedersen(nullifier)`,
+/// * The output commitment is a Mixer note, or a VAnchor note holding exactly
+///   the public denomination on the public chain ID, so the pool can check that
+///   the note is created on its own chain.
+/// * The public `arbitrary_input`, the hash of the recipient, relayer, fee and
+///   refund, is bound to the proof so the import can't be front-run.
+///
+/// Needs to implement `ConstraintSynthesizer` and a
+/// constructor to generate proper constraints
+#[derive(Clone)]
+pub struct LegacyImportCircuit<HG: FieldHasherGadget<Fq>> {
+	// Hash of the recipient, relayer, fee and refund of the import
+	arbitrary_input: Fq,
+	// Amount of every deposit of the Tornado Cash pool
+	denomination: Fq,
+	// Tornado Cash secret
+	secret: Fq,
+	// Tornado Cash nullifier to prevent double spending
+	nullifier: Fq,
+	// Root of the Tornado Cash tree
+	root: Fq,
+	// Merkle path to the deposit
+	path: TornadoPath,
+	nullifier_hash: Fq,
+	// Output note
+	output: LegacyImportOutput,
+	out_commitment: Fq,
+	// Hasher of the Tornado Cash tree
+	mimc: MiMCSponge<Fq>,
+	// Hasher of the output commitment, of width 3 for a Mixer note and of
+	// width 5 for a VAnchor note
+	hasher: HG::Native,
+}
+
+/// A constructor for the `LegacyImportCircuit`
+impl<HG: FieldHasherGadget<Fq>> LegacyImportCircuit<HG> {
+	#[allow(clippy::too_many_arguments)]
+	pub fn new(
+		arbitrary_input: Fq,
+		denomination: Fq,
+		secret: Fq,
+		nullifier: Fq,
+		root: Fq,
+		path: TornadoPath,
+		nullifier_hash: Fq,
+		output: LegacyImportOutput,
+		out_commitment: Fq,
+		mimc: MiMCSponge<Fq>,
+		hasher: HG::Native,
+	) -> Self {
+		Self {
+			arbitrary_input,
+			denomination,
+			secret,
+			nullifier,
+			root,
+			path,
+			nullifier_hash,
+			output,
+			out_commitment,
+			mimc,
+			hasher,
+		}
+	}
+
+	// Decomposes `value` into `NOTE_BITS` little-endian bits, enforcing that
+	// it fits in them like Tornado Cash's `Num2Bits(248)`
+	fn to_note_bits(value: &FpVar<Fq>) -> Result<Vec<Boolean<Fq>>, SynthesisError> {
+		let mut bits = value.to_bits_le()?;
+		for bit in &bits[NOTE_BITS..] {
+			bit.enforce_equal(&Boolean::FALSE)?;
+		}
+		bits.truncate(NOTE_BITS);
+		Ok(bits)
+	}
+}
+
+/// Implementation of the `ConstraintSynthesizer` trait for the
+/// `LegacyImportCircuit`
+impl<HG: FieldHasherGadget<Fq>> ConstraintSynthesizer<Fq> for LegacyImportCircuit<HG> {
+	fn generate_constraints(self, cs: ConstraintSystemRef<Fq>) -> Result<(), SynthesisError> {
+		let arbitrary_input = self.arbitrary_input;
+		let denomination = self.denomination;
+		let secret = self.secret;
+		let nullifier = self.nullifier;
+		let root = self.root;
+		let path = self.path;
+		let nullifier_hash = self.nullifier_hash;
+		let out_commitment = self.out_commitment;
+		let out_chain_id = self.output.chain_id();
+
+		// Generating vars
+		// Public inputs
+		let nullifier_hash_var = FpVar::<Fq>::new_input(cs.clone(), || Ok(nullifier_hash))?;
+		let root_var = FpVar::<Fq>::new_input(cs.clone(), || Ok(root))?;
+		let arbitrary_input_var = FpVar::<Fq>::new_input(cs.clone(), || Ok(arbitrary_input))?;
+		let denomination_var = FpVar::<Fq>::new_input(cs.clone(), || Ok(denomination))?;
+		let out_commitment_var = FpVar::<Fq>::new_input(cs.clone(), || Ok(out_commitment))?;
+		let out_chain_id_var = FpVar::<Fq>::new_input(cs.clone(), || Ok(out_chain_id))?;
+
+		// Hashers
+		let mimc_gadget = MiMCSpongeGadget::from_native(&mut cs.clone(), self.mimc)?;
+		let hasher_gadget: HG = FieldHasherGadget::<Fq>::from_native(&mut cs.clone(), self.hasher)?;
+
+		// Private inputs
+		let secret_var = FpVar::<Fq>::new_witness(cs.clone(), || Ok(secret))?;
+		let nullifier_var = FpVar::<Fq>::new_witness(cs.clone(), || Ok(nullifier))?;
+		let path_var = PathVar::<Fq, MiMCSpongeGadget<Fq>, TORNADO_TREE_HEIGHT>::new_witness(
+			cs.clone(),
+			|| Ok(path),
+		)?;
+
+		// Creating the Tornado Cash leaf and checking the membership inside the tree
+		let nullifier_bits = Self::to_note_bits(&nullifier_var)?;
+		let secret_bits = Self::to_note_bits(&secret_var)?;
+		let preimage_bits = [nullifier_bits.clone(), secret_bits].concat();
+		let legacy_leaf = PedersenHashGadget::hash(&preimage_bits)?;
+		let legacy_nullifier_hash = PedersenHashGadget::hash(&nullifier_bits)?;
+		let is_member = path_var.check_membership(&root_var, &legacy_leaf, &mimc_gadget)?;
+		// Constraining arbitrary inputs
+		let _ = &arbitrary_input_var * &arbitrary_input_var;
+
+		// Creating the output commitment
+		let out_leaf = match self.output {
+			LegacyImportOutput::Mixer { secret, nullifier } => {
+				let out_secret_var = FpVar::<Fq>::new_witness(cs.clone(), || Ok(secret))?;
+				let out_nullifier_var = FpVar::<Fq>::new_witness(cs, || Ok(nullifier))?;
+				// Mixer notes don't hold an amount, constraining the denomination
+				let _ = &denomination_var * &denomination_var;
+				out_chain_id_var.enforce_equal(&FpVar::<Fq>::zero())?;
+				hasher_gadget.hash_two(&out_secret_var, &out_nullifier_var)?
+			}
+			LegacyImportOutput::VAnchor {
+				pubkey, blinding, ..
+			} => {
+				let out_pubkey_var = FpVar::<Fq>::new_witness(cs.clone(), || Ok(pubkey))?;
+				let out_blinding_var = FpVar::<Fq>::new_witness(cs, || Ok(blinding))?;
+				hasher_gadget.hash(&[
+					out_chain_id_var,
+					denomination_var,
+					out_pubkey_var,
+					out_blinding_var,
+				])?
+			}
+		};
+
+		// Enforcing constraints
+		is_member.enforce_equal(&Boolean::TRUE)?;
+		legacy_nullifier_hash.enforce_equal(&nullifier_hash_var)?;
+		out_leaf.enforce_equal(&out_commitment_var)?;
+
+		Ok(())
+	}
+}
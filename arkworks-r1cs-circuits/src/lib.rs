@@ -23,10 +23,33 @@ pub extern crate ark_std;
 use ark_std::boxed::Box;
 
 pub mod anchor;
+pub mod anchor_association;
+pub mod anchor_chain_bound;
+pub mod anchor_dynamic_depth;
+pub mod anchor_migration;
+pub mod anchor_root_tree;
+pub mod balance_threshold;
 pub mod basic;
+pub mod batch_insert;
+pub mod credential;
+#[cfg(feature = "babyjubjub")]
+pub mod legacy_import;
+pub mod masp;
 pub mod mixer;
+pub mod mixer_dynamic_depth;
+pub mod ownership;
 pub mod poseidon;
+pub mod rln;
+pub mod semaphore;
+pub mod swap;
 pub mod vanchor;
+#[cfg(feature = "babyjubjub")]
+pub mod vanchor_encrypted;
+#[cfg(feature = "babyjubjub")]
+pub mod vanchor_multisig;
+pub mod vanchor_root_tree;
+pub mod vanchor_timelock;
+pub mod voting;
 
 pub type Error = Box<dyn ark_std::error::Error>;
 
@@ -0,0 +1,147 @@
+// This file is part of Webb.
+
+// Copyright (C) 2021 Webb Technologies Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Proof of ownership of an Anchor deposit that doesn't spend it.
+//!
+//! The prover shows that it knows the `(secret, nullifier)` behind a leaf of
+//! one of the Anchor trees in the root set, e.g. to claim an airdrop or build
+//! up reputation. Instead of the spend nullifier `Hash(nullifier, nullifier)`
+//! the circuit outputs the scoped nullifier `Hash(nullifier, scope)`, so an
+//! application can prevent a deposit from being claimed twice in its scope
+//! without learning which deposit will later be withdrawn.
+use ark_ff::fields::PrimeField;
+use ark_r1cs_std::{eq::EqGadget, fields::fp::FpVar, prelude::*};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+use ark_std::vec::Vec;
+use arkworks_native_gadgets::merkle_tree::Path;
+use arkworks_r1cs_gadgets::{merkle_tree::PathVar, poseidon::FieldHasherGadget, set::SetGadget};
+
+/// Defines an `OwnershipCircuit` struct that hold all the information thats
+/// needed to verify the following statements:
+/// * Alice knows a witness tuple `(secret, nullifier, merklePath)`
+/// and a commitment `Hash(chain_id, nullifier, secret)` stored in one of the
+/// Anchor merkle trees,
+/// * The `scoped_nullifier` is `Hash(nullifier, scope)`.
+///
+/// Needs to implement `ConstraintSynthesizer` and a
+/// constructor to generate proper constraints
+#[derive(Clone)]
+pub struct OwnershipCircuit<F: PrimeField, HG: FieldHasherGadget<F>, const N: usize, const M: usize>
+{
+	// Application scope, e.g. the id of an airdrop
+	scope: F,
+	// secret
+	secret: F,
+	// nullifier of the deposit
+	nullifier: F,
+	// source chain_id
+	chain_id: F,
+	// Merkle root set to use on one-of-many proof, unused slots are zero
+	root_set: [F; M],
+	// Merkle path to transaction
+	path: Path<F, HG::Native, N>,
+	// Nullifier bound to the scope
+	scoped_nullifier: F,
+	// 3 input hasher
+	hasher3: HG::Native,
+	// 4 input hasher
+	hasher4: HG::Native,
+}
+
+/// A constructor for the `OwnershipCircuit`
+impl<F, HG, const N: usize, const M: usize> OwnershipCircuit<F, HG, N, M>
+where
+	F: PrimeField,
+	HG: FieldHasherGadget<F>,
+{
+	#[allow(clippy::too_many_arguments)]
+	pub fn new(
+		scope: F,
+		secret: F,
+		nullifier: F,
+		chain_id: F,
+		root_set: [F; M],
+		path: Path<F, HG::Native, N>,
+		scoped_nullifier: F,
+		hasher3: HG::Native,
+		hasher4: HG::Native,
+	) -> Self {
+		Self {
+			scope,
+			secret,
+			nullifier,
+			chain_id,
+			root_set,
+			path,
+			scoped_nullifier,
+			hasher3,
+			hasher4,
+		}
+	}
+}
+
+/// Implementation of the `ConstraintSynthesizer` trait for the
+/// `OwnershipCircuit`
+impl<F, HG, const N: usize, const M: usize> ConstraintSynthesizer<F>
+	for OwnershipCircuit<F, HG, N, M>
+where
+	F: PrimeField,
+	HG: FieldHasherGadget<F>,
+{
+	fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+		let scope = self.scope;
+		let secret = self.secret;
+		let nullifier = self.nullifier;
+		let chain_id = self.chain_id;
+		let root_set = self.root_set;
+		let path = self.path;
+		let scoped_nullifier = self.scoped_nullifier;
+
+		// Generating vars
+		// Public inputs
+		let scoped_nullifier_var = FpVar::<F>::new_input(cs.clone(), || Ok(scoped_nullifier))?;
+		let scope_var = FpVar::<F>::new_input(cs.clone(), || Ok(scope))?;
+		let chain_id_var = FpVar::<F>::new_input(cs.clone(), || Ok(chain_id))?;
+		let roots_var = Vec::<FpVar<F>>::new_input(cs.clone(), || Ok(root_set))?;
+
+		// Hashers
+		let hasher3_gadget: HG =
+			FieldHasherGadget::<F>::from_native(&mut cs.clone(), self.hasher3)?;
+		let hasher4_gadget: HG =
+			FieldHasherGadget::<F>::from_native(&mut cs.clone(), self.hasher4)?;
+
+		// Private inputs
+		let secret_var = FpVar::<F>::new_witness(cs.clone(), || Ok(secret))?;
+		let nullifier_var = FpVar::<F>::new_witness(cs.clone(), || Ok(nullifier))?;
+		let path_var = PathVar::<F, HG, N>::new_witness(cs, || Ok(path))?;
+
+		// Creating the leaf and checking the membership inside the tree
+		let anchor_leaf =
+			hasher4_gadget.hash(&[chain_id_var, nullifier_var.clone(), secret_var])?;
+		let computed_scoped_nullifier = hasher3_gadget.hash_two(&nullifier_var, &scope_var)?;
+		let root_var = path_var.root_hash(&anchor_leaf, &hasher3_gadget)?;
+		// Check if target root is in set, ignoring the unused (zero) slots
+		let set_gadget = SetGadget::new_with_zero_slots_disabled(roots_var)?;
+		let is_set_member = set_gadget.check_membership(&root_var)?;
+
+		// Enforcing constraints
+		is_set_member.enforce_equal(&Boolean::TRUE)?;
+		computed_scoped_nullifier.enforce_equal(&scoped_nullifier_var)?;
+
+		Ok(())
+	}
+}
@@ -0,0 +1,146 @@
+// This file is part of Webb.
+
+// Copyright (C) 2021 Webb Technologies Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Rate-Limiting Nullifier (RLN) signalling.
+//!
+//! Members of a group register an identity commitment `Hash(sk)` as a leaf of
+//! a merkle tree. With every message sent in an epoch, a member publishes a
+//! share `(x, y)` of the line `y = sk + a1 * x`, where `x` is the hash of the
+//! message and `a1 = Hash(sk, epoch)`, together with the epoch nullifier
+//! `Hash(a1)`.
+//!
+//! A member sending a single message per epoch stays anonymous. Two messages
+//! in the same epoch have the same epoch nullifier, and their shares are two
+//! points of the same line, from which anyone can recover `sk` (see
+//! `arkworks_native_gadgets::rln::recover_secret`) and e.g. slash the member.
+use ark_ff::fields::PrimeField;
+use ark_r1cs_std::{eq::EqGadget, fields::fp::FpVar, prelude::*};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+use arkworks_native_gadgets::merkle_tree::Path;
+use arkworks_r1cs_gadgets::{merkle_tree::PathVar, poseidon::FieldHasherGadget};
+
+/// Defines an `RlnCircuit` struct that hold all the information thats needed
+/// to verify the following statements:
+/// * Alice knows a witness tuple `(sk, merklePath)` and an identity commitment
+///   `Hash(sk)` that is stored in the merkle tree.
+/// * The public share `y` is `sk + Hash(sk, epoch) * x`.
+/// * The public epoch nullifier is `Hash(Hash(sk, epoch))`.
+///
+/// Needs to implement `ConstraintSynthesizer` and a
+/// constructor to generate proper constraints
+#[derive(Clone)]
+pub struct RlnCircuit<F: PrimeField, HG: FieldHasherGadget<F>, const N: usize> {
+	// Hash of the message, the x coordinate of the share
+	x: F,
+	// The y coordinate of the share
+	y: F,
+	// Epoch in which a member can send a single message
+	epoch: F,
+	// Nullifier shared by all messages of a member in the epoch
+	nullifier: F,
+	// Secret key of the identity
+	secret: F,
+	// Merkle path to the identity commitment
+	path: Path<F, HG::Native, N>,
+	// Merkle root of the group
+	root: F,
+	// 1 input hasher
+	hasher2: HG::Native,
+	// 2 input hasher, also used by the tree
+	hasher3: HG::Native,
+}
+
+/// Constructor for RlnCircuit
+impl<F, HG, const N: usize> RlnCircuit<F, HG, N>
+where
+	F: PrimeField,
+	HG: FieldHasherGadget<F>,
+{
+	#[allow(clippy::too_many_arguments)]
+	pub fn new(
+		x: F,
+		y: F,
+		epoch: F,
+		nullifier: F,
+		secret: F,
+		path: Path<F, HG::Native, N>,
+		root: F,
+		hasher2: HG::Native,
+		hasher3: HG::Native,
+	) -> Self {
+		Self {
+			x,
+			y,
+			epoch,
+			nullifier,
+			secret,
+			path,
+			root,
+			hasher2,
+			hasher3,
+		}
+	}
+}
+
+impl<F, HG, const N: usize> ConstraintSynthesizer<F> for RlnCircuit<F, HG, N>
+where
+	F: PrimeField,
+	HG: FieldHasherGadget<F>,
+{
+	fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+		let x = self.x;
+		let y = self.y;
+		let epoch = self.epoch;
+		let nullifier = self.nullifier;
+		let secret = self.secret;
+		let path = self.path;
+		let root = self.root;
+
+		// Generating vars
+		// Public inputs
+		let root_var = FpVar::<F>::new_input(cs.clone(), || Ok(root))?;
+		let epoch_var = FpVar::<F>::new_input(cs.clone(), || Ok(epoch))?;
+		let x_var = FpVar::<F>::new_input(cs.clone(), || Ok(x))?;
+		let y_var = FpVar::<F>::new_input(cs.clone(), || Ok(y))?;
+		let nullifier_var = FpVar::<F>::new_input(cs.clone(), || Ok(nullifier))?;
+
+		// Hashers
+		let hasher2: HG = FieldHasherGadget::<F>::from_native(&mut cs.clone(), self.hasher2)?;
+		let hasher3: HG = FieldHasherGadget::<F>::from_native(&mut cs.clone(), self.hasher3)?;
+
+		// Private inputs
+		let secret_var = FpVar::<F>::new_witness(cs.clone(), || Ok(secret))?;
+		let path_var = PathVar::<F, HG, N>::new_witness(cs, || Ok(path))?;
+
+		// Creating the identity commitment and checking the membership inside the
+		// tree
+		let identity_commitment = hasher2.hash(&[secret_var.clone()])?;
+		let is_member = path_var.check_membership(&root_var, &identity_commitment, &hasher3)?;
+
+		// Evaluating the line of the epoch at x
+		let a1 = hasher3.hash_two(&secret_var, &epoch_var)?;
+		let computed_y = &secret_var + &a1 * &x_var;
+		let computed_nullifier = hasher2.hash(&[a1])?;
+
+		// Enforcing constraints
+		is_member.enforce_equal(&Boolean::TRUE)?;
+		computed_y.enforce_equal(&y_var)?;
+		computed_nullifier.enforce_equal(&nullifier_var)?;
+
+		Ok(())
+	}
+}
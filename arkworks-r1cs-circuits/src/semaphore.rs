@@ -0,0 +1,142 @@
+// This file is part of Webb.
+
+// Copyright (C) 2021 Webb Technologies Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Semaphore-style anonymous signalling.
+//!
+//! Members of a group register an identity commitment
+//! `Hash(identity_nullifier, identity_trapdoor)` as a leaf of a merkle tree.
+//! A member can then broadcast a signal, proving that it belongs to the group
+//! without revealing which member it is.
+//!
+//! The nullifier hash `Hash(external_nullifier, identity_nullifier)` is
+//! unique per identity and external nullifier, so an application can allow a
+//! single signal per member in every scope (e.g. a poll) by picking one
+//! external nullifier per scope and rejecting repeated nullifier hashes.
+use ark_ff::fields::PrimeField;
+use ark_r1cs_std::{eq::EqGadget, fields::fp::FpVar, prelude::*};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+use arkworks_native_gadgets::merkle_tree::Path;
+use arkworks_r1cs_gadgets::{merkle_tree::PathVar, poseidon::FieldHasherGadget};
+
+/// Defines a `SemaphoreCircuit` struct that hold all the information thats
+/// needed to verify the following statements:
+/// * Alice knows a witness tuple `(identity_nullifier, identity_trapdoor,
+///   merklePath)` and an identity commitment `Hash(identity_nullifier,
+///   identity_trapdoor)` that is stored in the merkle tree.
+/// * The public nullifier hash is `Hash(external_nullifier,
+///   identity_nullifier)`.
+///
+/// Needs to implement `ConstraintSynthesizer` and a
+/// constructor to generate proper constraints
+#[derive(Clone)]
+pub struct SemaphoreCircuit<F: PrimeField, HG: FieldHasherGadget<F>, const N: usize> {
+	// Hash of the signal broadcast by the member
+	signal_hash: F,
+	// Scope in which a member can signal once
+	external_nullifier: F,
+	// Secrets of the identity
+	identity_nullifier: F,
+	identity_trapdoor: F,
+	// Merkle path to the identity commitment
+	path: Path<F, HG::Native, N>,
+	// Merkle root of the group
+	root: F,
+	// Nullifier hash to prevent signalling twice in the same scope
+	nullifier_hash: F,
+	// Hasher to be used inside the circuit
+	hasher: HG::Native,
+}
+
+/// Constructor for SemaphoreCircuit
+impl<F, HG, const N: usize> SemaphoreCircuit<F, HG, N>
+where
+	F: PrimeField,
+	HG: FieldHasherGadget<F>,
+{
+	#[allow(clippy::too_many_arguments)]
+	pub fn new(
+		signal_hash: F,
+		external_nullifier: F,
+		identity_nullifier: F,
+		identity_trapdoor: F,
+		path: Path<F, HG::Native, N>,
+		root: F,
+		nullifier_hash: F,
+		hasher: HG::Native,
+	) -> Self {
+		Self {
+			signal_hash,
+			external_nullifier,
+			identity_nullifier,
+			identity_trapdoor,
+			path,
+			root,
+			nullifier_hash,
+			hasher,
+		}
+	}
+}
+
+impl<F, HG, const N: usize> ConstraintSynthesizer<F> for SemaphoreCircuit<F, HG, N>
+where
+	F: PrimeField,
+	HG: FieldHasherGadget<F>,
+{
+	fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+		let signal_hash = self.signal_hash;
+		let external_nullifier = self.external_nullifier;
+		let identity_nullifier = self.identity_nullifier;
+		let identity_trapdoor = self.identity_trapdoor;
+		let path = self.path;
+		let root = self.root;
+		let nullifier_hash = self.nullifier_hash;
+
+		// Generating vars
+		// Public inputs
+		let root_var = FpVar::<F>::new_input(cs.clone(), || Ok(root))?;
+		let nullifier_hash_var = FpVar::<F>::new_input(cs.clone(), || Ok(nullifier_hash))?;
+		let signal_hash_var = FpVar::<F>::new_input(cs.clone(), || Ok(signal_hash))?;
+		let external_nullifier_var = FpVar::<F>::new_input(cs.clone(), || Ok(external_nullifier))?;
+
+		// Hashers
+		let hasher: HG = FieldHasherGadget::<F>::from_native(&mut cs.clone(), self.hasher)?;
+
+		// Private inputs
+		let identity_nullifier_var =
+			FpVar::<F>::new_witness(cs.clone(), || Ok(identity_nullifier))?;
+		let identity_trapdoor_var = FpVar::<F>::new_witness(cs.clone(), || Ok(identity_trapdoor))?;
+		let path_var = PathVar::<F, HG, N>::new_witness(cs, || Ok(path))?;
+
+		// Creating the identity commitment and checking the membership inside the
+		// tree
+		let identity_commitment =
+			hasher.hash_two(&identity_nullifier_var, &identity_trapdoor_var)?;
+		let computed_nullifier_hash =
+			hasher.hash_two(&external_nullifier_var, &identity_nullifier_var)?;
+
+		let is_member = path_var.check_membership(&root_var, &identity_commitment, &hasher)?;
+		// Constraining the signal hash, so the proof can't be reused for another
+		// signal
+		let _ = &signal_hash_var * &signal_hash_var;
+
+		// Enforcing constraints
+		is_member.enforce_equal(&Boolean::TRUE)?;
+		computed_nullifier_hash.enforce_equal(&nullifier_hash_var)?;
+
+		Ok(())
+	}
+}
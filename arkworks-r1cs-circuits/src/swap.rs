@@ -0,0 +1,398 @@
+// This file is part of Webb.
+
+// Copyright (C) 2021 Webb Technologies Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Atomic swap of two assets between two parties of the multi-asset VAnchor.
+//!
+//! A swap is settled by two half proofs, one per party, submitted together.
+//! Each half works like a transaction of the `MaspVAnchorCircuit` without a
+//! public amount: its party spends its own Utxos of the asset it gives and
+//! creates outputs of the same asset, the first of which pays the
+//! counterparty. A party never learns the keys or the notes of the other one.
+//!
+//! The order of a party is `Hash(give_asset_id, give_amount, want_asset_id,
+//! want_amount, pub_key)`, where `pub_key` owns the spent Utxos and receives
+//! the payment of the counterparty. Each half exposes the hash of the order of
+//! its party and the hash of the order of the counterparty, with the assets
+//! and the amounts swapped, so the verifier settles the two halves together
+//! only if each one's counterparty order is the other one's order. Either both
+//! payments happen or none does, and the notes never leave the pool.
+//!
+//! The amounts also follow the exchange rate `rate_num / rate_den` of the
+//! public commitment `Hash(rate_num, rate_den, rate_blinding)`, i.e. the party
+//! of side one gives `rate_num / rate_den` of its asset per unit of the asset
+//! of the party of side zero. The public `side` tells which party a half is
+//! for. The amounts and the terms of the rate fit in `amount_bits` bits, and
+//! `2 * amount_bits` must be below the capacity of the field so the products
+//! of the rate check can't wrap around the modulus.
+use ark_ff::{fields::PrimeField, FpParameters};
+use ark_r1cs_std::{eq::EqGadget, fields::fp::FpVar, prelude::*};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+use ark_std::vec::Vec;
+use arkworks_native_gadgets::merkle_tree::Path;
+use arkworks_r1cs_gadgets::{poseidon::FieldHasherGadget, range::RangeGadget, set::SetGadget};
+
+use crate::vanchor::{
+	enforce_unique_nullifiers, sum_amounts, verify_input_note, verify_output_note,
+	VAnchorInputVars, VAnchorOutputVars,
+};
+
+/// Whether `amount_bits` leaves room for the products of the rate check, i.e.
+/// `2 * amount_bits` is below the capacity of the field
+pub fn is_valid_amount_bits<F: PrimeField>(amount_bits: usize) -> bool {
+	2 * amount_bits < <F::Params as FpParameters>::CAPACITY as usize
+}
+
+/// The terms of the order of a party: it gives `give_amount` of
+/// `give_asset_id` for `want_amount` of `want_asset_id`, paid to its own
+/// public key. `counterparty_key` is the public key of the other party, which
+/// receives the first output.
+#[derive(Clone, Copy)]
+pub struct SwapTerms<F: PrimeField> {
+	pub give_asset_id: F,
+	pub give_amount: F,
+	pub want_asset_id: F,
+	pub want_amount: F,
+	pub counterparty_key: F,
+}
+
+/// Defines a `SwapCircuit` struct that hold all the information thats needed
+/// to verify the following statements, for the half of one party:
+/// * The party knows a private key and, for each input, a witness tuple
+///   `(amount, blinding, merklePath)` such that the commitment `Hash(chain_id,
+///   give_asset_id, amount, Hash(private_key), blinding)` is stored in one of
+///   the VAnchor merkle trees, as in the `MaspVAnchorCircuit`. Inputs with a
+///   zero amount are padding and aren't checked to be in the trees.
+/// * Every output commitment is for `give_asset_id`, and the first one pays
+///   `give_amount` to the public key of the counterparty.
+/// * The inputs and the outputs are balanced, and every amount fits in
+///   `amount_bits` bits.
+/// * The order hash is `Hash(give_asset_id, give_amount, want_asset_id,
+///   want_amount, Hash(private_key))` and the counterparty order hash is
+///   `Hash(want_asset_id, want_amount, give_asset_id, give_amount,
+///   counterparty_key)`.
+/// * The amounts of side zero and one satisfy `amount_1 * rate_den = amount_0
+///   * rate_num` for the rate of the public commitment `Hash(rate_num,
+///   rate_den, rate_blinding)`, whose terms fit in `amount_bits` bits.
+///
+/// Needs to implement ConstraintSynthesizer and a
+/// constructor to generate proper constraints
+#[derive(Clone)]
+pub struct SwapCircuit<
+	F: PrimeField,
+	HG: FieldHasherGadget<F>,
+	const HEIGHT: usize,
+	const N_INS: usize,
+	const N_OUTS: usize,
+	const ANCHOR_CT: usize,
+> {
+	ext_data_hash: F,
+	rate_commitment: F,
+	rate_num: F,
+	rate_den: F,
+	rate_blinding: F,
+
+	// Whether the party is on side one of the rate
+	side: bool,
+	terms: SwapTerms<F>,
+	order_hash: F,
+	counter_order_hash: F,
+
+	in_amounts: Vec<F>,
+	in_blindings: Vec<F>,
+	in_private_key: F,
+	in_chain_id: F,
+	root_set: [F; ANCHOR_CT],
+
+	paths: Vec<Path<F, HG::Native, HEIGHT>>,
+	indices: Vec<F>,
+	nullifier_hash: Vec<F>,
+
+	out_commitment: Vec<F>,
+	out_amounts: Vec<F>,
+	out_blindings: Vec<F>,
+	out_chain_ids: Vec<F>,
+	out_pubkey: Vec<F>,
+
+	tree_hasher: HG::Native,
+	keypair_hasher: HG::Native,
+	// 5 input hasher, of the commitments and of the orders
+	leaf_hasher: HG::Native,
+	// 3 input hasher, of the nullifiers and of the rate commitment
+	nullifier_hasher: HG::Native,
+
+	// Bit width of the amounts and of the rate, checked to prevent overflows
+	// in the field
+	amount_bits: usize,
+}
+
+/// Constructor for SwapCircuit
+impl<
+		F,
+		HG,
+		const HEIGHT: usize,
+		const N_INS: usize,
+		const N_OUTS: usize,
+		const ANCHOR_CT: usize,
+	> SwapCircuit<F, HG, HEIGHT, N_INS, N_OUTS, ANCHOR_CT>
+where
+	F: PrimeField,
+	HG: FieldHasherGadget<F>,
+{
+	#[allow(clippy::too_many_arguments)]
+	pub fn new(
+		ext_data_hash: F,
+		rate_commitment: F,
+		rate_num: F,
+		rate_den: F,
+		rate_blinding: F,
+		side: bool,
+		terms: SwapTerms<F>,
+		order_hash: F,
+		counter_order_hash: F,
+		in_amounts: Vec<F>,
+		in_blindings: Vec<F>,
+		in_private_key: F,
+		in_chain_id: F,
+		root_set: [F; ANCHOR_CT],
+		paths: Vec<Path<F, HG::Native, HEIGHT>>,
+		indices: Vec<F>,
+		nullifier_hash: Vec<F>,
+		out_commitment: Vec<F>,
+		out_amounts: Vec<F>,
+		out_blindings: Vec<F>,
+		out_chain_ids: Vec<F>,
+		out_pubkey: Vec<F>,
+		tree_hasher: HG::Native,
+		keypair_hasher: HG::Native,
+		leaf_hasher: HG::Native,
+		nullifier_hasher: HG::Native,
+		amount_bits: usize,
+	) -> Self {
+		Self {
+			ext_data_hash,
+			rate_commitment,
+			rate_num,
+			rate_den,
+			rate_blinding,
+			side,
+			terms,
+			order_hash,
+			counter_order_hash,
+			in_amounts,
+			in_blindings,
+			in_private_key,
+			in_chain_id,
+			root_set,
+			paths,
+			indices,
+			nullifier_hash,
+			out_commitment,
+			out_amounts,
+			out_blindings,
+			out_chain_ids,
+			out_pubkey,
+			tree_hasher,
+			keypair_hasher,
+			leaf_hasher,
+			nullifier_hasher,
+			amount_bits,
+		}
+	}
+}
+
+impl<
+		F,
+		HG,
+		const HEIGHT: usize,
+		const N_INS: usize,
+		const N_OUTS: usize,
+		const ANCHOR_CT: usize,
+	> ConstraintSynthesizer<F> for SwapCircuit<F, HG, HEIGHT, N_INS, N_OUTS, ANCHOR_CT>
+where
+	F: PrimeField,
+	HG: FieldHasherGadget<F>,
+{
+	fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+		// The products of the rate check must not wrap around the modulus
+		if !is_valid_amount_bits::<F>(self.amount_bits) {
+			return Err(SynthesisError::Unsatisfiable);
+		}
+
+		let ext_data_hash = self.ext_data_hash;
+		let rate_commitment = self.rate_commitment;
+		let rate_num = self.rate_num;
+		let rate_den = self.rate_den;
+		let rate_blinding = self.rate_blinding;
+		let side = self.side;
+		let terms = self.terms;
+		let order_hash = self.order_hash;
+		let counter_order_hash = self.counter_order_hash;
+		let in_chain_id = self.in_chain_id;
+		let in_private_key = self.in_private_key;
+		let root_set = self.root_set;
+		let nullifier_hash = self.nullifier_hash;
+		let out_commitment = self.out_commitment;
+
+		// Generating vars
+		// Public inputs
+		let arbitrary_input_var = FpVar::<F>::new_input(cs.clone(), || Ok(ext_data_hash))?;
+		let rate_commitment_var = FpVar::<F>::new_input(cs.clone(), || Ok(rate_commitment))?;
+		let side_var = Boolean::<F>::new_input(cs.clone(), || Ok(side))?;
+		let order_hash_var = FpVar::<F>::new_input(cs.clone(), || Ok(order_hash))?;
+		let counter_order_hash_var = FpVar::<F>::new_input(cs.clone(), || Ok(counter_order_hash))?;
+		let in_nullifier_var = Vec::<FpVar<F>>::new_input(cs.clone(), || Ok(nullifier_hash))?;
+		let out_commitment_var = Vec::<FpVar<F>>::new_input(cs.clone(), || Ok(out_commitment))?;
+		let in_chain_id_var = FpVar::<F>::new_input(cs.clone(), || Ok(in_chain_id))?;
+		let root_set_var = Vec::<FpVar<F>>::new_input(cs.clone(), || Ok(root_set))?;
+
+		// Hashers
+		let tree_hasher = HG::from_native(&mut cs.clone(), self.tree_hasher)?;
+		let keypair_hasher = HG::from_native(&mut cs.clone(), self.keypair_hasher)?;
+		let leaf_hasher = HG::from_native(&mut cs.clone(), self.leaf_hasher)?;
+		let nullifier_hasher = HG::from_native(&mut cs.clone(), self.nullifier_hasher)?;
+
+		// Private inputs of the order and of the rate
+		let give_asset_id_var = FpVar::<F>::new_witness(cs.clone(), || Ok(terms.give_asset_id))?;
+		let give_amount_var = FpVar::<F>::new_witness(cs.clone(), || Ok(terms.give_amount))?;
+		let want_asset_id_var = FpVar::<F>::new_witness(cs.clone(), || Ok(terms.want_asset_id))?;
+		let want_amount_var = FpVar::<F>::new_witness(cs.clone(), || Ok(terms.want_amount))?;
+		let counterparty_key_var =
+			FpVar::<F>::new_witness(cs.clone(), || Ok(terms.counterparty_key))?;
+		let rate_num_var = FpVar::<F>::new_witness(cs.clone(), || Ok(rate_num))?;
+		let rate_den_var = FpVar::<F>::new_witness(cs.clone(), || Ok(rate_den))?;
+		let rate_blinding_var = FpVar::<F>::new_witness(cs.clone(), || Ok(rate_blinding))?;
+
+		// Private inputs
+		let ins = VAnchorInputVars::<F, HG, HEIGHT>::new_witness(
+			cs.clone(),
+			self.in_amounts,
+			self.in_blindings,
+			self.paths,
+			self.indices,
+		)?;
+		let in_private_key_var = FpVar::<F>::new_witness(cs.clone(), || Ok(in_private_key))?;
+
+		// Outputs
+		let outs = VAnchorOutputVars::new_witness(
+			cs,
+			self.out_chain_ids,
+			self.out_amounts,
+			self.out_pubkey,
+			self.out_blindings,
+		)?;
+
+		// Unused (zero) slots of the root set are ignored
+		let set_gadget = SetGadget::new_with_zero_slots_disabled(root_set_var)?;
+		let range_gadget = RangeGadget::new(self.amount_bits);
+
+		// Computing the public key of the party
+		let pub_key = keypair_hasher.hash(&[in_private_key_var.clone()])?;
+
+		// verify correctness of transaction inputs, all of the given asset
+		for (tx, nullifier) in in_nullifier_var.iter().enumerate() {
+			let roothash = verify_input_note(
+				&leaf_hasher,
+				&nullifier_hasher,
+				&tree_hasher,
+				&[
+					in_chain_id_var.clone(),
+					give_asset_id_var.clone(),
+					ins.amounts[tx].clone(),
+					pub_key.clone(),
+					ins.blindings[tx].clone(),
+				],
+				&in_private_key_var,
+				&ins.indices[tx],
+				&ins.paths[tx],
+				nullifier,
+			)?;
+
+			// Check membership if in_amount is non zero
+			let check = set_gadget.check_membership_enabled(&roothash, &ins.amounts[tx])?;
+			check.enforce_equal(&Boolean::TRUE)?;
+		}
+		let sum_ins_var = sum_amounts(&range_gadget, &ins.amounts)?;
+
+		// verify correctness of transaction outputs, all of the given asset
+		for (tx, commitment) in out_commitment_var.iter().enumerate() {
+			verify_output_note(
+				&leaf_hasher,
+				&[
+					outs.chain_ids[tx].clone(),
+					give_asset_id_var.clone(),
+					outs.amounts[tx].clone(),
+					outs.pubkeys[tx].clone(),
+					outs.blindings[tx].clone(),
+				],
+				commitment,
+			)?;
+		}
+		let sum_outs_var = sum_amounts(&range_gadget, &outs.amounts)?;
+
+		// The first output pays the counterparty
+		outs.amounts[0].enforce_equal(&give_amount_var)?;
+		outs.pubkeys[0].enforce_equal(&counterparty_key_var)?;
+
+		// check that there are no same nullifiers among all inputs
+		enforce_unique_nullifiers(&in_nullifier_var)?;
+
+		// verify amount invariant of the given asset
+		sum_ins_var.enforce_equal(&sum_outs_var)?;
+
+		// The order of the party, and the one of the counterparty it matches
+		let computed_order_hash = leaf_hasher.hash(&[
+			give_asset_id_var.clone(),
+			give_amount_var.clone(),
+			want_asset_id_var.clone(),
+			want_amount_var.clone(),
+			pub_key,
+		])?;
+		computed_order_hash.enforce_equal(&order_hash_var)?;
+		let computed_counter_order_hash = leaf_hasher.hash(&[
+			want_asset_id_var,
+			want_amount_var.clone(),
+			give_asset_id_var,
+			give_amount_var.clone(),
+			counterparty_key_var,
+		])?;
+		computed_counter_order_hash.enforce_equal(&counter_order_hash_var)?;
+
+		// verify the exchange rate. The given amount is range checked as the
+		// first output, and the wanted amount and the terms of the rate are range
+		// checked here, so the products can't wrap around the modulus.
+		range_gadget.enforce_in_range(&want_amount_var)?;
+		range_gadget.enforce_in_range(&rate_num_var)?;
+		range_gadget.enforce_in_range(&rate_den_var)?;
+		rate_den_var.enforce_not_equal(&FpVar::<F>::zero())?;
+		let computed_rate_commitment = nullifier_hasher.hash(&[
+			rate_num_var.clone(),
+			rate_den_var.clone(),
+			rate_blinding_var,
+		])?;
+		computed_rate_commitment.enforce_equal(&rate_commitment_var)?;
+		let amount_0 = side_var.select(&want_amount_var, &give_amount_var)?;
+		let amount_1 = side_var.select(&give_amount_var, &want_amount_var)?;
+		let amount_0_scaled = &amount_0 * &rate_num_var;
+		let amount_1_scaled = &amount_1 * &rate_den_var;
+		amount_1_scaled.enforce_equal(&amount_0_scaled)?;
+
+		// optional safety constraint to make sure extDataHash cannot be changed
+		let _ = &arbitrary_input_var * &arbitrary_input_var;
+
+		Ok(())
+	}
+}
@@ -20,8 +20,155 @@ use ark_r1cs_std::{eq::EqGadget, fields::fp::FpVar, prelude::*};
 use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
 use ark_std::vec::Vec;
 use arkworks_native_gadgets::merkle_tree::Path;
-use arkworks_r1cs_gadgets::{merkle_tree::PathVar, poseidon::FieldHasherGadget, set::SetGadget};
-use core::cmp::Ordering::Less;
+use arkworks_r1cs_gadgets::{
+	merkle_tree::PathVar,
+	poseidon::FieldHasherGadget,
+	range::{RangeGadget, SignedAmountVar},
+	set::SetGadget,
+	uniqueness::UniquenessGadget,
+};
+
+/// Above this number of inputs the nullifiers are checked to be unique by
+/// sorting them rather than with a check per pair. The sorted check costs
+/// about `2 * F::size_in_bits()` constraints per input, so it only needs fewer
+/// constraints than the pairwise check for about 1150 inputs or more.
+pub const NULLIFIER_SORT_THRESHOLD: usize = 1150;
+
+/// Variables of the input notes of a VAnchor transaction, except for the keys
+/// that own them
+pub struct VAnchorInputVars<F: PrimeField, HG: FieldHasherGadget<F>, const HEIGHT: usize> {
+	pub amounts: Vec<FpVar<F>>,
+	pub blindings: Vec<FpVar<F>>,
+	pub paths: Vec<PathVar<F, HG, HEIGHT>>,
+	pub indices: Vec<FpVar<F>>,
+}
+
+impl<F: PrimeField, HG: FieldHasherGadget<F>, const HEIGHT: usize> VAnchorInputVars<F, HG, HEIGHT> {
+	pub fn new_witness(
+		cs: ConstraintSystemRef<F>,
+		amounts: Vec<F>,
+		blindings: Vec<F>,
+		paths: Vec<Path<F, HG::Native, HEIGHT>>,
+		indices: Vec<F>,
+	) -> Result<Self, SynthesisError> {
+		Ok(Self {
+			amounts: Vec::<FpVar<F>>::new_witness(cs.clone(), || Ok(amounts))?,
+			blindings: Vec::<FpVar<F>>::new_witness(cs.clone(), || Ok(blindings))?,
+			paths: Vec::<PathVar<F, HG, HEIGHT>>::new_witness(cs.clone(), || Ok(paths))?,
+			indices: Vec::<FpVar<F>>::new_witness(cs, || Ok(indices))?,
+		})
+	}
+}
+
+/// Variables of the output notes of a VAnchor transaction, as constrained by
+/// the output commitments
+pub struct VAnchorOutputVars<F: PrimeField> {
+	pub chain_ids: Vec<FpVar<F>>,
+	pub amounts: Vec<FpVar<F>>,
+	pub pubkeys: Vec<FpVar<F>>,
+	pub blindings: Vec<FpVar<F>>,
+}
+
+impl<F: PrimeField> VAnchorOutputVars<F> {
+	pub fn new_witness(
+		cs: ConstraintSystemRef<F>,
+		chain_ids: Vec<F>,
+		amounts: Vec<F>,
+		pubkeys: Vec<F>,
+		blindings: Vec<F>,
+	) -> Result<Self, SynthesisError> {
+		Ok(Self {
+			chain_ids: Vec::<FpVar<F>>::new_witness(cs.clone(), || Ok(chain_ids))?,
+			amounts: Vec::<FpVar<F>>::new_witness(cs.clone(), || Ok(amounts))?,
+			pubkeys: Vec::<FpVar<F>>::new_witness(cs.clone(), || Ok(pubkeys))?,
+			blindings: Vec::<FpVar<F>>::new_witness(cs, || Ok(blindings))?,
+		})
+	}
+}
+
+/// Checks an input note of a VAnchor transaction: computes its commitment
+/// `Hash(leaf_inputs)`, enforces that its nullifier `Hash(commitment, index,
+/// Hash(key, commitment, index))` is the public `nullifier_hash`, and returns
+/// the root of the tree the `path` leads to. `key` is the private key of the
+/// owner of the note, or whatever replaces it in the nullifier.
+///
+/// The VAnchor circuits only differ in the preimage of their commitments and
+/// in how they check the root, which is left to the caller.
+#[allow(clippy::too_many_arguments)]
+pub fn verify_input_note<F: PrimeField, HG: FieldHasherGadget<F>, const HEIGHT: usize>(
+	leaf_hasher: &HG,
+	nullifier_hasher: &HG,
+	tree_hasher: &HG,
+	leaf_inputs: &[FpVar<F>],
+	key: &FpVar<F>,
+	index: &FpVar<F>,
+	path: &PathVar<F, HG, HEIGHT>,
+	nullifier_hash: &FpVar<F>,
+) -> Result<FpVar<F>, SynthesisError> {
+	let in_leaf = leaf_hasher.hash(leaf_inputs)?;
+
+	let signature = nullifier_hasher.hash(&[key.clone(), in_leaf.clone(), index.clone()])?;
+	// Nullifier
+	let nullifier = nullifier_hasher.hash(&[in_leaf.clone(), index.clone(), signature])?;
+	nullifier.enforce_equal(nullifier_hash)?;
+
+	path.root_hash(&in_leaf, tree_hasher)
+}
+
+/// Checks an output note of a VAnchor transaction: enforces that its
+/// commitment `Hash(leaf_inputs)` is the public `commitment`.
+pub fn verify_output_note<F: PrimeField, HG: FieldHasherGadget<F>>(
+	leaf_hasher: &HG,
+	leaf_inputs: &[FpVar<F>],
+	commitment: &FpVar<F>,
+) -> Result<(), SynthesisError> {
+	leaf_hasher.hash(leaf_inputs)?.enforce_equal(commitment)
+}
+
+/// Checks that every amount is within the amount range (to prevent overflow)
+/// and returns their sum
+pub fn sum_amounts<F: PrimeField>(
+	range_gadget: &RangeGadget<F>,
+	amounts: &[FpVar<F>],
+) -> Result<FpVar<F>, SynthesisError> {
+	let mut sum = FpVar::<F>::zero();
+	for amount in amounts {
+		range_gadget.enforce_in_range(amount)?;
+		sum += amount;
+	}
+
+	Ok(sum)
+}
+
+/// Checks that there are no same nullifiers among all inputs
+pub fn enforce_unique_nullifiers<F: PrimeField>(
+	in_nullifier_var: &[FpVar<F>],
+) -> Result<(), SynthesisError> {
+	let uniqueness_gadget = UniquenessGadget::new(in_nullifier_var.to_vec());
+	if in_nullifier_var.len() > NULLIFIER_SORT_THRESHOLD {
+		uniqueness_gadget.enforce_unique_sorted()
+	} else {
+		uniqueness_gadget.enforce_unique_pairwise()
+	}
+}
+
+/// Verifies the amount invariant: a positive public amount is a deposit added
+/// to the inputs, a negative one is a withdrawal taken from them
+pub fn enforce_balance<F: PrimeField>(
+	public_amount: &SignedAmountVar<F>,
+	sum_ins_var: &FpVar<F>,
+	sum_outs_var: &FpVar<F>,
+) -> Result<(), SynthesisError> {
+	let zero = FpVar::<F>::zero();
+	let deposit = public_amount
+		.is_negative
+		.select(&zero, &public_amount.magnitude)?;
+	let withdrawal = public_amount
+		.is_negative
+		.select(&public_amount.magnitude, &zero)?;
+	let res = sum_ins_var + deposit;
+	res.enforce_equal(&(sum_outs_var + withdrawal))
+}
 
 /// Defines a VAnchorCircuit struct that hold all the information thats needed
 /// to verify the following statements:
@@ -30,6 +177,8 @@ use core::cmp::Ordering::Less;
 ///   amount, pub_key, blinding)` stored in one of the valid VAnchor merkle
 ///   trees
 /// * The VAnchor contract hasn't seen this nullifier_hash before.
+/// * Every input and output amount fits in `amount_bits` bits and the absolute
+///   value of the public amount is at most `2^amount_bits`.
 ///
 /// Needs to implement ConstraintSynthesizer and a
 /// constructor to generate proper constraints
@@ -65,6 +214,9 @@ pub struct VAnchorCircuit<
 	keypair_hasher: HG::Native,
 	leaf_hasher: HG::Native,
 	nullifier_hasher: HG::Native,
+
+	// Bit width of the amounts, checked to prevent overflows in the field
+	amount_bits: usize,
 }
 
 /// Constructor for VAnchorCircuit
@@ -101,6 +253,7 @@ where
 		keypair_hasher: HG::Native,
 		leaf_hasher: HG::Native,
 		nullifier_hasher: HG::Native,
+		amount_bits: usize,
 	) -> Self {
 		Self {
 			public_amount,
@@ -122,69 +275,41 @@ where
 			keypair_hasher,
 			leaf_hasher,
 			nullifier_hasher,
+			amount_bits,
 		}
 	}
 
 	// Check that there are no same nullifiers among all inputs
 	pub fn verify_no_same_nul(in_nullifier_var: &[FpVar<F>]) -> Result<(), SynthesisError> {
-		for i in 0..N_INS - 1 {
-			for j in (i + 1)..N_INS {
-				in_nullifier_var[i].enforce_not_equal(&in_nullifier_var[j])?;
-			}
-		}
-
-		Ok(())
+		enforce_unique_nullifiers(&in_nullifier_var[..N_INS])
 	}
 
-	// Verify amount invariant
+	// Verify amount invariant: a positive public amount is a deposit added to
+	// the inputs, a negative one is a withdrawal taken from them
 	pub fn verify_input_invariant(
-		public_amount_var: &FpVar<F>,
+		public_amount: &SignedAmountVar<F>,
 		sum_ins_var: &FpVar<F>,
 		sum_outs_var: &FpVar<F>,
 	) -> Result<(), SynthesisError> {
-		let res = sum_ins_var + public_amount_var;
-		res.enforce_equal(sum_outs_var)?;
-		Ok(())
+		enforce_balance(public_amount, sum_ins_var, sum_outs_var)
 	}
-}
 
-impl<
-		F,
-		HG,
-		const HEIGHT: usize,
-		const N_INS: usize,
-		const N_OUTS: usize,
-		const ANCHOR_CT: usize,
-	> ConstraintSynthesizer<F> for VAnchorCircuit<F, HG, HEIGHT, N_INS, N_OUTS, ANCHOR_CT>
-where
-	F: PrimeField,
-	HG: FieldHasherGadget<F>,
-{
-	fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+	/// Generates the constraints of the circuit and returns the variables of
+	/// the output notes, so wrapping circuits can add constraints on them.
+	/// Public inputs allocated by the caller afterwards come after the ones of
+	/// this circuit.
+	pub fn synthesize(
+		self,
+		cs: ConstraintSystemRef<F>,
+	) -> Result<VAnchorOutputVars<F>, SynthesisError> {
 		let public_amount = self.public_amount;
 		let ext_data_hash = self.ext_data_hash;
-		let in_amounts = self.in_amounts;
-		let in_blindings = self.in_blindings;
 		let in_chain_id = self.in_chain_id;
 		let in_private_keys = self.in_private_keys;
-		let out_chain_ids = self.out_chain_ids;
 		let root_set = self.root_set;
-		let paths = self.paths;
-		let indices = self.indices;
 		let nullifier_hash = self.nullifier_hash;
-
 		let out_commitment = self.out_commitment;
-		let out_amounts = self.out_amounts;
-		let out_blindings = self.out_blindings;
-		let out_pubkey = self.out_pubkey;
-
-		// 2^248
-		let limit: F = F::from_str(
-			"452312848583266388373324160190187140051835877600158453279131187530910662656",
-		)
-		.unwrap_or_default();
-		// check the previous conversion is done correctly
-		assert_ne!(limit, F::default());
+
 		// Generating vars
 		// Public inputs
 		let public_amount_var = FpVar::<F>::new_input(cs.clone(), || Ok(public_amount))?;
@@ -194,9 +319,6 @@ where
 		let in_chain_id_var = FpVar::<F>::new_input(cs.clone(), || Ok(in_chain_id))?;
 		let root_set_var = Vec::<FpVar<F>>::new_input(cs.clone(), || Ok(root_set))?;
 
-		// Constants
-		let limit_var: FpVar<F> = FpVar::<F>::new_constant(cs.clone(), limit)?;
-
 		// Hashers
 		let tree_hasher = HG::from_native(&mut cs.clone(), self.tree_hasher)?;
 		let keypair_hasher = HG::from_native(&mut cs.clone(), self.keypair_hasher)?;
@@ -204,88 +326,100 @@ where
 		let nullifier_hasher = HG::from_native(&mut cs.clone(), self.nullifier_hasher)?;
 
 		// Private inputs
-		let in_amounts_var = Vec::<FpVar<F>>::new_witness(cs.clone(), || Ok(in_amounts))?;
-		let in_blindings_var = Vec::<FpVar<F>>::new_witness(cs.clone(), || Ok(in_blindings))?;
+		let ins = VAnchorInputVars::<F, HG, HEIGHT>::new_witness(
+			cs.clone(),
+			self.in_amounts,
+			self.in_blindings,
+			self.paths,
+			self.indices,
+		)?;
 		let in_private_keys_var = Vec::<FpVar<F>>::new_witness(cs.clone(), || Ok(in_private_keys))?;
-		let in_path_elements_var =
-			Vec::<PathVar<F, HG, HEIGHT>>::new_witness(cs.clone(), || Ok(paths))?;
-		let in_path_indices_var = Vec::<FpVar<F>>::new_witness(cs.clone(), || Ok(indices))?;
 
 		// Outputs
-		let out_amounts_var = Vec::<FpVar<F>>::new_witness(cs.clone(), || Ok(out_amounts))?;
-		let out_blindings_var = Vec::<FpVar<F>>::new_witness(cs.clone(), || Ok(out_blindings))?;
-		let out_chain_ids_var = Vec::<FpVar<F>>::new_witness(cs.clone(), || Ok(out_chain_ids))?;
-		let out_pubkey_var = Vec::<FpVar<F>>::new_witness(cs, || Ok(out_pubkey))?;
-
-		let set_gadget = SetGadget::new(root_set_var);
+		let outs = VAnchorOutputVars::new_witness(
+			cs,
+			self.out_chain_ids,
+			self.out_amounts,
+			self.out_pubkey,
+			self.out_blindings,
+		)?;
+
+		// Unused (zero) slots of the root set are ignored
+		let set_gadget = SetGadget::new_with_zero_slots_disabled(root_set_var)?;
+		let range_gadget = RangeGadget::new(self.amount_bits);
+
+		// Split the public amount into a sign and a magnitude, which also checks
+		// that it is within the amount range (to prevent overflow)
+		let public_amount = range_gadget.to_signed_amount(&public_amount_var)?;
 
 		// verify correctness of transaction inputs
-		let mut sum_ins_var = FpVar::<F>::zero();
 		for tx in 0..N_INS {
 			// Computing the public key
 			let pub_key = keypair_hasher.hash(&[in_private_keys_var[tx].clone()])?;
-			// Computing the hash
-			let in_leaf = leaf_hasher.hash(&[
-				in_chain_id_var.clone(),
-				in_amounts_var[tx].clone(),
-				pub_key,
-				in_blindings_var[tx].clone(),
-			])?;
-			// End of computing the hash
-
-			let signature = nullifier_hasher.hash(&[
-				in_private_keys_var[tx].clone(),
-				in_leaf.clone(),
-				in_path_indices_var[tx].clone(),
-			])?;
-			// Nullifier
-			let nullifier_hash = nullifier_hasher.hash(&[
-				in_leaf.clone(),
-				in_path_indices_var[tx].clone(),
-				signature,
-			])?;
-
-			nullifier_hash.enforce_equal(&in_nullifier_var[tx])?;
-
-			// Add the roots and diffs signals to the vanchor circuit
-			let roothash = &in_path_elements_var[tx].root_hash(&in_leaf, &tree_hasher)?;
-			let in_amount_tx = &in_amounts_var[tx];
+			let roothash = verify_input_note(
+				&leaf_hasher,
+				&nullifier_hasher,
+				&tree_hasher,
+				&[
+					in_chain_id_var.clone(),
+					ins.amounts[tx].clone(),
+					pub_key,
+					ins.blindings[tx].clone(),
+				],
+				&in_private_keys_var[tx],
+				&ins.indices[tx],
+				&ins.paths[tx],
+				&in_nullifier_var[tx],
+			)?;
 
 			// Check membership if in_amount is non zero
-			let check = set_gadget.check_membership_enabled(&roothash, in_amount_tx)?;
+			let check = set_gadget.check_membership_enabled(&roothash, &ins.amounts[tx])?;
 			check.enforce_equal(&Boolean::TRUE)?;
-
-			sum_ins_var += in_amount_tx;
 		}
+		let sum_ins_var = sum_amounts(&range_gadget, &ins.amounts)?;
+
 		// verify correctness of transaction outputs
-		let mut sum_outs_var = FpVar::<F>::zero();
-		for tx in 0..N_OUTS {
-			// Computing the hash
-			let leaf = leaf_hasher.hash(&[
-				out_chain_ids_var[tx].clone(),
-				out_amounts_var[tx].clone(),
-				out_pubkey_var[tx].clone(),
-				out_blindings_var[tx].clone(),
-			])?;
-			// End of computing the hash
-			let out_amount_var = &out_amounts_var[tx];
-			leaf.enforce_equal(&out_commitment_var[tx])?;
-
-			// Check that amount is less than 2^248 in the field (to prevent overflow)
-			out_amount_var.enforce_cmp_unchecked(&limit_var, Less, false)?;
-
-			sum_outs_var += out_amount_var;
+		for (tx, commitment) in out_commitment_var.iter().enumerate() {
+			verify_output_note(
+				&leaf_hasher,
+				&[
+					outs.chain_ids[tx].clone(),
+					outs.amounts[tx].clone(),
+					outs.pubkeys[tx].clone(),
+					outs.blindings[tx].clone(),
+				],
+				commitment,
+			)?;
 		}
+		let sum_outs_var = sum_amounts(&range_gadget, &outs.amounts)?;
 
 		// check that there are no same nullifiers among all inputs
 		Self::verify_no_same_nul(&in_nullifier_var)?;
 
 		// verify amount invariant
-		Self::verify_input_invariant(&public_amount_var, &sum_ins_var, &sum_outs_var)?;
+		Self::verify_input_invariant(&public_amount, &sum_ins_var, &sum_outs_var)?;
 
 		// optional safety constraint to make sure extDataHash cannot be changed
 		let _ = &arbitrary_input_var * &arbitrary_input_var;
 
+		Ok(outs)
+	}
+}
+
+impl<
+		F,
+		HG,
+		const HEIGHT: usize,
+		const N_INS: usize,
+		const N_OUTS: usize,
+		const ANCHOR_CT: usize,
+	> ConstraintSynthesizer<F> for VAnchorCircuit<F, HG, HEIGHT, N_INS, N_OUTS, ANCHOR_CT>
+where
+	F: PrimeField,
+	HG: FieldHasherGadget<F>,
+{
+	fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+		self.synthesize(cs)?;
 		Ok(())
 	}
 }
@@ -0,0 +1,165 @@
+// This file is part of Webb.
+
+// Copyright (C) 2021 Webb Technologies Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The VAnchor with verifiable encryption of its output notes.
+//!
+//! Works like the `VAnchorCircuit` over the scalar field of BN254, which is
+//! the base field of Baby Jubjub, and additionally proves that every output
+//! note is encrypted to its recipient. For each output, the sender picks an
+//! ephemeral Baby Jubjub key, derives a shared key with the recipient's
+//! encryption key by ECDH, and encrypts `[chain_id, amount, pubkey,
+//! blinding]` with Poseidon encryption. The ephemeral public key and the
+//! ciphertext are public inputs, so a recipient that decrypts them gets the
+//! values bound by the output commitment.
+//!
+//! The recipient's encryption key is a private input and is not related to
+//! the `pubkey` of the note, which is the hash of a different key. Agreeing
+//! on the encryption key with the recipient is left to the application. Every
+//! output uses a fresh ephemeral key, so the nonce is always zero.
+use crate::vanchor::VAnchorCircuit;
+use ark_ed_on_bn254::{Fq, Fr};
+use ark_r1cs_std::{eq::EqGadget, fields::fp::FpVar, prelude::*};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+use ark_std::vec::Vec;
+use arkworks_native_gadgets::{
+	eddsa::{to_base_field, PublicKey},
+	poseidon::Poseidon,
+};
+use arkworks_r1cs_gadgets::{
+	eddsa::{PublicKeyVar, SUBGROUP_ORDER_BITS},
+	poseidon::{encryption, FieldHasherGadget, PoseidonGadget},
+	range::RangeGadget,
+};
+
+/// Defines a VAnchorEncryptedCircuit struct that hold all the information
+/// thats needed to verify the statements of the `VAnchorCircuit` and the
+/// following ones:
+/// * For every output, Alice knows an ephemeral scalar whose public key is the
+///   public `ephemeral_public_key`
+/// * The public `ciphertext` is the Poseidon encryption of the output note
+///   under the ECDH shared key of the ephemeral scalar and the recipient's
+///   encryption key
+///
+/// Needs to implement ConstraintSynthesizer and a
+/// constructor to generate proper constraints
+#[derive(Clone)]
+pub struct VAnchorEncryptedCircuit<
+	HG: FieldHasherGadget<Fq>,
+	const HEIGHT: usize,
+	const N_INS: usize,
+	const N_OUTS: usize,
+	const ANCHOR_CT: usize,
+> {
+	vanchor: VAnchorCircuit<Fq, HG, HEIGHT, N_INS, N_OUTS, ANCHOR_CT>,
+
+	recipient_keys: Vec<PublicKey>,
+	ephemeral_scalars: Vec<Fr>,
+	ephemeral_public_keys: Vec<PublicKey>,
+	ciphertexts: Vec<Vec<Fq>>,
+
+	// Poseidon hash of width 4
+	encryption_hasher: Poseidon<Fq>,
+}
+
+/// Constructor for VAnchorEncryptedCircuit
+impl<HG, const HEIGHT: usize, const N_INS: usize, const N_OUTS: usize, const ANCHOR_CT: usize>
+	VAnchorEncryptedCircuit<HG, HEIGHT, N_INS, N_OUTS, ANCHOR_CT>
+where
+	HG: FieldHasherGadget<Fq>,
+{
+	pub fn new(
+		vanchor: VAnchorCircuit<Fq, HG, HEIGHT, N_INS, N_OUTS, ANCHOR_CT>,
+		recipient_keys: Vec<PublicKey>,
+		ephemeral_scalars: Vec<Fr>,
+		ephemeral_public_keys: Vec<PublicKey>,
+		ciphertexts: Vec<Vec<Fq>>,
+		encryption_hasher: Poseidon<Fq>,
+	) -> Self {
+		Self {
+			vanchor,
+			recipient_keys,
+			ephemeral_scalars,
+			ephemeral_public_keys,
+			ciphertexts,
+			encryption_hasher,
+		}
+	}
+}
+
+impl<HG, const HEIGHT: usize, const N_INS: usize, const N_OUTS: usize, const ANCHOR_CT: usize>
+	ConstraintSynthesizer<Fq> for VAnchorEncryptedCircuit<HG, HEIGHT, N_INS, N_OUTS, ANCHOR_CT>
+where
+	HG: FieldHasherGadget<Fq>,
+{
+	fn generate_constraints(self, cs: ConstraintSystemRef<Fq>) -> Result<(), SynthesisError> {
+		let outputs = self.vanchor.synthesize(cs.clone())?;
+
+		// Public inputs, after the ones of the VAnchor
+		let mut ephemeral_public_keys_var = Vec::with_capacity(N_OUTS);
+		let mut ciphertexts_var = Vec::with_capacity(N_OUTS);
+		for tx in 0..N_OUTS {
+			let ephemeral_public_key = self.ephemeral_public_keys[tx];
+			ephemeral_public_keys_var.push(PublicKeyVar::new_input(cs.clone(), || {
+				Ok(ephemeral_public_key)
+			})?);
+			let ciphertext = self.ciphertexts[tx].clone();
+			ciphertexts_var.push(Vec::<FpVar<Fq>>::new_input(cs.clone(), || Ok(ciphertext))?);
+		}
+
+		// Hashers
+		let encryption_hasher =
+			PoseidonGadget::from_native(&mut cs.clone(), self.encryption_hasher)?;
+
+		// Private inputs
+		let recipient_keys = self.recipient_keys;
+		let ephemeral_scalars = self
+			.ephemeral_scalars
+			.iter()
+			.map(to_base_field)
+			.collect::<Vec<_>>();
+		let recipient_keys_var =
+			Vec::<PublicKeyVar>::new_witness(cs.clone(), || Ok(recipient_keys))?;
+		let ephemeral_scalars_var = Vec::<FpVar<Fq>>::new_witness(cs, || Ok(ephemeral_scalars))?;
+
+		let scalar_range = RangeGadget::new(SUBGROUP_ORDER_BITS);
+		for tx in 0..N_OUTS {
+			// Check that the ephemeral public key matches the ephemeral scalar
+			let ephemeral_bits = scalar_range.to_bits_le(&ephemeral_scalars_var[tx])?;
+			let ephemeral_public_key = PublicKeyVar::from_scalar_bits(&ephemeral_bits)?;
+			ephemeral_public_key
+				.x
+				.enforce_equal(&ephemeral_public_keys_var[tx].x)?;
+			ephemeral_public_key
+				.y
+				.enforce_equal(&ephemeral_public_keys_var[tx].y)?;
+
+			// Encrypt the output note under the shared key
+			let shared_key = recipient_keys_var[tx].shared_key(&ephemeral_bits)?;
+			let note = [
+				outputs.chain_ids[tx].clone(),
+				outputs.amounts[tx].clone(),
+				outputs.pubkeys[tx].clone(),
+				outputs.blindings[tx].clone(),
+			];
+			let ciphertext =
+				encryption::encrypt(&encryption_hasher, &note, &shared_key, &FpVar::zero())?;
+			ciphertext.enforce_equal(&ciphertexts_var[tx])?;
+		}
+
+		Ok(())
+	}
+}
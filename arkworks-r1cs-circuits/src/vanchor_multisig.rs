@@ -0,0 +1,397 @@
+// This file is part of Webb.
+
+// Copyright (C) 2021 Webb Technologies Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The VAnchor with multi-signature spend authority.
+//!
+//! Works like the `VAnchorCircuit` over the scalar field of BN254, which is
+//! the base field of Baby Jubjub, except that the inputs are not owned by the
+//! key whose hash is their `pub_key`. Instead, every input has the public key
+//! `Hash(keys_hash, threshold, nullifier_key)`, where `keys_hash` commits to
+//! `N_SIGNERS` EdDSA public keys, and the spend is authorized by EdDSA
+//! signatures from at least `threshold` of them.
+//!
+//! The signed message is built in the circuit from the public inputs of the
+//! transaction, folding `acc = Hash(acc, a, b)` from `acc = 0` over
+//! `(ext_data_hash, nullifiers, commitments, public_amount)`, padded with a
+//! zero to an even length. A signature thus only authorizes the exact inputs,
+//! outputs and public amount the signers agreed to, besides the external data.
+//!
+//! The nullifier key replaces the private key in the nullifiers, so it must be
+//! known to the prover, but it can't authorize a spend on its own. The signers
+//! only share signatures, never their EdDSA private keys.
+//!
+//! All the inputs of a transaction are controlled by the same signers. The
+//! public inputs are the ones of the `VAnchorCircuit`, so proofs of both
+//! circuits look alike on chain.
+use ark_ed_on_bn254::Fq;
+use ark_r1cs_std::{eq::EqGadget, fields::fp::FpVar, prelude::*};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+use ark_std::vec::Vec;
+use arkworks_native_gadgets::{
+	eddsa::{PublicKey, Signature},
+	merkle_tree::Path,
+};
+use arkworks_r1cs_gadgets::{
+	eddsa::{EdDSAVerifyGadget, PublicKeyVar, SignatureVar},
+	poseidon::FieldHasherGadget,
+	range::RangeGadget,
+	set::SetGadget,
+};
+
+use crate::vanchor::{
+	enforce_balance, enforce_unique_nullifiers, sum_amounts, verify_input_note, verify_output_note,
+	VAnchorInputVars, VAnchorOutputVars,
+};
+
+/// Bit width of the threshold, which is compared with the number of signatures
+pub const THRESHOLD_BITS: usize = 64;
+
+/// Defines a VAnchorMultisigCircuit struct that hold all the information
+/// thats needed to verify the following statements:
+/// * Alice knows a witness tuple `(in_amounts, in_blindings, nullifier_key,
+///   signers, threshold, in_path_elements, in_path_indices)` and a
+///   commitment_hash `Hash(chain_id, amount, pub_key, blinding)` stored in one
+///   of the valid VAnchor merkle trees, with `pub_key = Hash(keys_hash,
+///   threshold, nullifier_key)`
+/// * At least `threshold` of the signers signed the message of the transaction,
+///   which commits to all its public inputs but the roots
+/// * The VAnchor contract hasn't seen this nullifier_hash before.
+/// * Every input and output amount fits in `amount_bits` bits and the absolute
+///   value of the public amount is at most `2^amount_bits`.
+///
+/// Needs to implement ConstraintSynthesizer and a
+/// constructor to generate proper constraints
+#[derive(Clone)]
+pub struct VAnchorMultisigCircuit<
+	HG: FieldHasherGadget<Fq>,
+	const HEIGHT: usize,
+	const N_INS: usize,
+	const N_OUTS: usize,
+	const ANCHOR_CT: usize,
+	const N_SIGNERS: usize,
+> {
+	public_amount: Fq,
+	ext_data_hash: Fq,
+
+	in_amounts: Vec<Fq>,
+	in_blindings: Vec<Fq>,
+	nullifier_key: Fq,
+	in_chain_id: Fq,
+	root_set: [Fq; ANCHOR_CT],
+
+	// Signers of the inputs, and a signature for every signer, which is only
+	// checked if the signer signed
+	signers: Vec<PublicKey>,
+	threshold: Fq,
+	signed: Vec<bool>,
+	signatures: Vec<Signature>,
+
+	paths: Vec<Path<Fq, HG::Native, HEIGHT>>,
+	indices: Vec<Fq>,
+	nullifier_hash: Vec<Fq>,
+
+	out_commitment: Vec<Fq>,
+	out_amounts: Vec<Fq>,
+	out_blindings: Vec<Fq>,
+	out_chain_ids: Vec<Fq>,
+	out_pubkey: Vec<Fq>,
+
+	tree_hasher: HG::Native,
+	leaf_hasher: HG::Native,
+	nullifier_hasher: HG::Native,
+	// Poseidon hash of width 6
+	signature_hasher: HG::Native,
+
+	// Bit width of the amounts, checked to prevent overflows in the field
+	amount_bits: usize,
+}
+
+/// Constructor for VAnchorMultisigCircuit
+impl<
+		HG,
+		const HEIGHT: usize,
+		const N_INS: usize,
+		const N_OUTS: usize,
+		const ANCHOR_CT: usize,
+		const N_SIGNERS: usize,
+	> VAnchorMultisigCircuit<HG, HEIGHT, N_INS, N_OUTS, ANCHOR_CT, N_SIGNERS>
+where
+	HG: FieldHasherGadget<Fq>,
+{
+	#[allow(clippy::too_many_arguments)]
+	pub fn new(
+		public_amount: Fq,
+		ext_data_hash: Fq,
+		in_amounts: Vec<Fq>,
+		in_blindings: Vec<Fq>,
+		nullifier_key: Fq,
+		in_chain_id: Fq,
+		root_set: [Fq; ANCHOR_CT],
+		signers: Vec<PublicKey>,
+		threshold: Fq,
+		signed: Vec<bool>,
+		signatures: Vec<Signature>,
+		paths: Vec<Path<Fq, HG::Native, HEIGHT>>,
+		indices: Vec<Fq>,
+		nullifier_hash: Vec<Fq>,
+		out_commitment: Vec<Fq>,
+		out_amounts: Vec<Fq>,
+		out_blindings: Vec<Fq>,
+		out_chain_ids: Vec<Fq>,
+		out_pubkey: Vec<Fq>,
+		tree_hasher: HG::Native,
+		leaf_hasher: HG::Native,
+		nullifier_hasher: HG::Native,
+		signature_hasher: HG::Native,
+		amount_bits: usize,
+	) -> Self {
+		Self {
+			public_amount,
+			ext_data_hash,
+			in_amounts,
+			in_blindings,
+			nullifier_key,
+			in_chain_id,
+			root_set,
+			signers,
+			threshold,
+			signed,
+			signatures,
+			paths,
+			indices,
+			nullifier_hash,
+			out_commitment,
+			out_amounts,
+			out_blindings,
+			out_chain_ids,
+			out_pubkey,
+			tree_hasher,
+			leaf_hasher,
+			nullifier_hasher,
+			signature_hasher,
+			amount_bits,
+		}
+	}
+
+	// Computes the message the signers sign, folding `acc = Hash(acc, a, b)`
+	// over the public inputs of the transaction, padded with a zero to an even
+	// length
+	pub fn transaction_message(
+		nullifier_hasher: &HG,
+		ext_data_hash: &FpVar<Fq>,
+		nullifiers: &[FpVar<Fq>],
+		commitments: &[FpVar<Fq>],
+		public_amount: &FpVar<Fq>,
+	) -> Result<FpVar<Fq>, SynthesisError> {
+		let mut elements = Vec::with_capacity(nullifiers.len() + commitments.len() + 3);
+		elements.push(ext_data_hash.clone());
+		elements.extend_from_slice(nullifiers);
+		elements.extend_from_slice(commitments);
+		elements.push(public_amount.clone());
+		if elements.len() % 2 == 1 {
+			elements.push(FpVar::<Fq>::zero());
+		}
+
+		let mut acc = FpVar::<Fq>::zero();
+		for pair in elements.chunks(2) {
+			acc = nullifier_hasher.hash(&[acc, pair[0].clone(), pair[1].clone()])?;
+		}
+		Ok(acc)
+	}
+
+	// Verify that at least `threshold` of the signers signed the message, and
+	// returns the public key the signers and the nullifier key commit to
+	#[allow(clippy::too_many_arguments)]
+	pub fn verify_signatures(
+		nullifier_hasher: &HG,
+		signature_hasher: &HG,
+		signers: &[PublicKeyVar],
+		threshold: &FpVar<Fq>,
+		nullifier_key: &FpVar<Fq>,
+		signed: &[Boolean<Fq>],
+		signatures: &[SignatureVar],
+		msg: &FpVar<Fq>,
+	) -> Result<FpVar<Fq>, SynthesisError> {
+		let mut keys_hash = FpVar::<Fq>::zero();
+		let mut num_signatures = FpVar::<Fq>::zero();
+		for i in 0..N_SIGNERS {
+			keys_hash =
+				nullifier_hasher.hash(&[keys_hash, signers[i].x.clone(), signers[i].y.clone()])?;
+
+			let is_valid =
+				EdDSAVerifyGadget::verify(signature_hasher, &signers[i], msg, &signatures[i])?;
+			is_valid
+				.or(&signed[i].not())?
+				.enforce_equal(&Boolean::TRUE)?;
+			num_signatures += FpVar::from(signed[i].clone());
+		}
+
+		// The threshold is never zero, so signatures are always needed
+		let range_gadget = RangeGadget::new(THRESHOLD_BITS);
+		range_gadget.enforce_in_range(threshold)?;
+		threshold.enforce_not_equal(&FpVar::zero())?;
+		range_gadget.enforce_less_or_equal(threshold, &num_signatures)?;
+
+		nullifier_hasher.hash(&[keys_hash, threshold.clone(), nullifier_key.clone()])
+	}
+}
+
+impl<
+		HG,
+		const HEIGHT: usize,
+		const N_INS: usize,
+		const N_OUTS: usize,
+		const ANCHOR_CT: usize,
+		const N_SIGNERS: usize,
+	> ConstraintSynthesizer<Fq>
+	for VAnchorMultisigCircuit<HG, HEIGHT, N_INS, N_OUTS, ANCHOR_CT, N_SIGNERS>
+where
+	HG: FieldHasherGadget<Fq>,
+{
+	fn generate_constraints(self, cs: ConstraintSystemRef<Fq>) -> Result<(), SynthesisError> {
+		let public_amount = self.public_amount;
+		let ext_data_hash = self.ext_data_hash;
+		let in_chain_id = self.in_chain_id;
+		let nullifier_key = self.nullifier_key;
+		let signers = self.signers;
+		let threshold = self.threshold;
+		let signed = self.signed;
+		let signatures = self.signatures;
+		let root_set = self.root_set;
+		let nullifier_hash = self.nullifier_hash;
+		let out_commitment = self.out_commitment;
+
+		// Generating vars
+		// Public inputs
+		let public_amount_var = FpVar::<Fq>::new_input(cs.clone(), || Ok(public_amount))?;
+		let arbitrary_input_var = FpVar::<Fq>::new_input(cs.clone(), || Ok(ext_data_hash))?;
+		let in_nullifier_var = Vec::<FpVar<Fq>>::new_input(cs.clone(), || Ok(nullifier_hash))?;
+		let out_commitment_var = Vec::<FpVar<Fq>>::new_input(cs.clone(), || Ok(out_commitment))?;
+		let in_chain_id_var = FpVar::<Fq>::new_input(cs.clone(), || Ok(in_chain_id))?;
+		let root_set_var = Vec::<FpVar<Fq>>::new_input(cs.clone(), || Ok(root_set))?;
+
+		// Hashers
+		let tree_hasher = HG::from_native(&mut cs.clone(), self.tree_hasher)?;
+		let leaf_hasher = HG::from_native(&mut cs.clone(), self.leaf_hasher)?;
+		let nullifier_hasher = HG::from_native(&mut cs.clone(), self.nullifier_hasher)?;
+		let signature_hasher = HG::from_native(&mut cs.clone(), self.signature_hasher)?;
+
+		// Private inputs
+		let ins = VAnchorInputVars::<Fq, HG, HEIGHT>::new_witness(
+			cs.clone(),
+			self.in_amounts,
+			self.in_blindings,
+			self.paths,
+			self.indices,
+		)?;
+		let nullifier_key_var = FpVar::<Fq>::new_witness(cs.clone(), || Ok(nullifier_key))?;
+		let signers_var = Vec::<PublicKeyVar>::new_witness(cs.clone(), || Ok(signers))?;
+		let threshold_var = FpVar::<Fq>::new_witness(cs.clone(), || Ok(threshold))?;
+		let signed_var = Vec::<Boolean<Fq>>::new_witness(cs.clone(), || Ok(signed))?;
+		let signatures_var = Vec::<SignatureVar>::new_witness(cs.clone(), || Ok(signatures))?;
+
+		// Outputs
+		let outs = VAnchorOutputVars::new_witness(
+			cs,
+			self.out_chain_ids,
+			self.out_amounts,
+			self.out_pubkey,
+			self.out_blindings,
+		)?;
+
+		// Unused (zero) slots of the root set are ignored
+		let set_gadget = SetGadget::new_with_zero_slots_disabled(root_set_var)?;
+		let range_gadget = RangeGadget::new(self.amount_bits);
+
+		// Split the public amount into a sign and a magnitude, which also checks
+		// that it is within the amount range (to prevent overflow)
+		let public_amount = range_gadget.to_signed_amount(&public_amount_var)?;
+
+		// The signers sign the public inputs of the transaction, so the proof
+		// can't be reused for other nullifiers, commitments or amounts
+		let msg = Self::transaction_message(
+			&nullifier_hasher,
+			&arbitrary_input_var,
+			&in_nullifier_var,
+			&out_commitment_var,
+			&public_amount_var,
+		)?;
+
+		// Computing the public key shared by all inputs
+		let pub_key = Self::verify_signatures(
+			&nullifier_hasher,
+			&signature_hasher,
+			&signers_var,
+			&threshold_var,
+			&nullifier_key_var,
+			&signed_var,
+			&signatures_var,
+			&msg,
+		)?;
+
+		// verify correctness of transaction inputs, with the nullifier key in
+		// place of the private key
+		for (tx, nullifier) in in_nullifier_var.iter().enumerate() {
+			let roothash = verify_input_note(
+				&leaf_hasher,
+				&nullifier_hasher,
+				&tree_hasher,
+				&[
+					in_chain_id_var.clone(),
+					ins.amounts[tx].clone(),
+					pub_key.clone(),
+					ins.blindings[tx].clone(),
+				],
+				&nullifier_key_var,
+				&ins.indices[tx],
+				&ins.paths[tx],
+				nullifier,
+			)?;
+
+			// Check membership if in_amount is non zero
+			let check = set_gadget.check_membership_enabled(&roothash, &ins.amounts[tx])?;
+			check.enforce_equal(&Boolean::TRUE)?;
+		}
+		let sum_ins_var = sum_amounts(&range_gadget, &ins.amounts)?;
+
+		// verify correctness of transaction outputs
+		for (tx, commitment) in out_commitment_var.iter().enumerate() {
+			verify_output_note(
+				&leaf_hasher,
+				&[
+					outs.chain_ids[tx].clone(),
+					outs.amounts[tx].clone(),
+					outs.pubkeys[tx].clone(),
+					outs.blindings[tx].clone(),
+				],
+				commitment,
+			)?;
+		}
+		let sum_outs_var = sum_amounts(&range_gadget, &outs.amounts)?;
+
+		// check that there are no same nullifiers among all inputs
+		enforce_unique_nullifiers(&in_nullifier_var)?;
+
+		// verify amount invariant
+		enforce_balance(&public_amount, &sum_ins_var, &sum_outs_var)?;
+
+		// optional safety constraint to make sure extDataHash cannot be changed
+		let _ = &arbitrary_input_var * &arbitrary_input_var;
+
+		Ok(())
+	}
+}
@@ -0,0 +1,299 @@
+// This file is part of Webb.
+
+// Copyright (C) 2021 Webb Technologies Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The VAnchor with time-locked notes.
+//!
+//! Works like the `VAnchorCircuit`, except that the commitments also include
+//! an unlock time, which may be a timestamp or a block number. A note can only
+//! be spent once the public `current_time` of the transaction has reached its
+//! unlock time, so notes with an unlock time of zero are spendable right away.
+//! The verifier is expected to check `current_time` against its own clock.
+//!
+//! The unlock times and the current time are checked to fit in `TIME_BITS`
+//! bits, so the comparison can't wrap around the modulus.
+//!
+//! The leaf hasher takes five inputs, i.e. it is the Poseidon hash of width 6.
+use ark_ff::fields::PrimeField;
+use ark_r1cs_std::{eq::EqGadget, fields::fp::FpVar, prelude::*};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+use ark_std::vec::Vec;
+use arkworks_native_gadgets::merkle_tree::Path;
+use arkworks_r1cs_gadgets::{poseidon::FieldHasherGadget, range::RangeGadget, set::SetGadget};
+
+use crate::vanchor::{
+	enforce_balance, enforce_unique_nullifiers, sum_amounts, verify_input_note, verify_output_note,
+	VAnchorInputVars, VAnchorOutputVars,
+};
+
+/// Bit width of the unlock times and of the current time
+pub const TIME_BITS: usize = 64;
+
+/// Defines a VAnchorTimeLockCircuit struct that hold all the information thats
+/// needed to verify the following statements:
+/// * Alice knows a witness tuple `(in_amounts, in_blindings, in_private_keys,
+///   in_unlock_times, in_path_elements, in_path_indices)` and a commitment_hash
+///   `Hash(chain_id, amount, pub_key, blinding, unlock_time)` stored in one of
+///   the valid VAnchor merkle trees
+/// * The unlock time of every input is at most the public `current_time`
+/// * The VAnchor contract hasn't seen this nullifier_hash before.
+/// * Every input and output amount fits in `amount_bits` bits and the absolute
+///   value of the public amount is at most `2^amount_bits`.
+///
+/// Needs to implement ConstraintSynthesizer and a
+/// constructor to generate proper constraints
+#[derive(Clone)]
+pub struct VAnchorTimeLockCircuit<
+	F: PrimeField,
+	HG: FieldHasherGadget<F>,
+	const HEIGHT: usize,
+	const N_INS: usize,
+	const N_OUTS: usize,
+	const ANCHOR_CT: usize,
+> {
+	public_amount: F,
+	ext_data_hash: F,
+	current_time: F,
+
+	in_amounts: Vec<F>,
+	in_blindings: Vec<F>,
+	in_private_keys: Vec<F>,
+	in_unlock_times: Vec<F>,
+	in_chain_id: F,
+	root_set: [F; ANCHOR_CT],
+
+	paths: Vec<Path<F, HG::Native, HEIGHT>>,
+	indices: Vec<F>,
+	nullifier_hash: Vec<F>,
+
+	out_commitment: Vec<F>,
+	out_amounts: Vec<F>,
+	out_blindings: Vec<F>,
+	out_chain_ids: Vec<F>,
+	out_pubkey: Vec<F>,
+	out_unlock_times: Vec<F>,
+
+	tree_hasher: HG::Native,
+	keypair_hasher: HG::Native,
+	leaf_hasher: HG::Native,
+	nullifier_hasher: HG::Native,
+
+	// Bit width of the amounts, checked to prevent overflows in the field
+	amount_bits: usize,
+}
+
+/// Constructor for VAnchorTimeLockCircuit
+impl<
+		F,
+		HG,
+		const HEIGHT: usize,
+		const N_INS: usize,
+		const N_OUTS: usize,
+		const ANCHOR_CT: usize,
+	> VAnchorTimeLockCircuit<F, HG, HEIGHT, N_INS, N_OUTS, ANCHOR_CT>
+where
+	F: PrimeField,
+	HG: FieldHasherGadget<F>,
+{
+	#[allow(clippy::too_many_arguments)]
+	pub fn new(
+		public_amount: F,
+		ext_data_hash: F,
+		current_time: F,
+		in_amounts: Vec<F>,
+		in_blindings: Vec<F>,
+		in_private_keys: Vec<F>,
+		in_unlock_times: Vec<F>,
+		in_chain_id: F,
+		root_set: [F; ANCHOR_CT],
+		paths: Vec<Path<F, HG::Native, HEIGHT>>,
+		indices: Vec<F>,
+		nullifier_hash: Vec<F>,
+		out_commitment: Vec<F>,
+		out_amounts: Vec<F>,
+		out_blindings: Vec<F>,
+		out_chain_ids: Vec<F>,
+		out_pubkey: Vec<F>,
+		out_unlock_times: Vec<F>,
+		tree_hasher: HG::Native,
+		keypair_hasher: HG::Native,
+		leaf_hasher: HG::Native,
+		nullifier_hasher: HG::Native,
+		amount_bits: usize,
+	) -> Self {
+		Self {
+			public_amount,
+			ext_data_hash,
+			current_time,
+			in_amounts,
+			in_blindings,
+			in_private_keys,
+			in_unlock_times,
+			in_chain_id,
+			root_set,
+			paths,
+			indices,
+			nullifier_hash,
+			out_commitment,
+			out_amounts,
+			out_blindings,
+			out_chain_ids,
+			out_pubkey,
+			out_unlock_times,
+			tree_hasher,
+			keypair_hasher,
+			leaf_hasher,
+			nullifier_hasher,
+			amount_bits,
+		}
+	}
+}
+
+impl<
+		F,
+		HG,
+		const HEIGHT: usize,
+		const N_INS: usize,
+		const N_OUTS: usize,
+		const ANCHOR_CT: usize,
+	> ConstraintSynthesizer<F> for VAnchorTimeLockCircuit<F, HG, HEIGHT, N_INS, N_OUTS, ANCHOR_CT>
+where
+	F: PrimeField,
+	HG: FieldHasherGadget<F>,
+{
+	fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+		let public_amount = self.public_amount;
+		let ext_data_hash = self.ext_data_hash;
+		let current_time = self.current_time;
+		let in_chain_id = self.in_chain_id;
+		let in_private_keys = self.in_private_keys;
+		let in_unlock_times = self.in_unlock_times;
+		let root_set = self.root_set;
+		let nullifier_hash = self.nullifier_hash;
+		let out_commitment = self.out_commitment;
+		let out_unlock_times = self.out_unlock_times;
+
+		// Generating vars
+		// Public inputs
+		let public_amount_var = FpVar::<F>::new_input(cs.clone(), || Ok(public_amount))?;
+		let arbitrary_input_var = FpVar::<F>::new_input(cs.clone(), || Ok(ext_data_hash))?;
+		let current_time_var = FpVar::<F>::new_input(cs.clone(), || Ok(current_time))?;
+		let in_nullifier_var = Vec::<FpVar<F>>::new_input(cs.clone(), || Ok(nullifier_hash))?;
+		let out_commitment_var = Vec::<FpVar<F>>::new_input(cs.clone(), || Ok(out_commitment))?;
+		let in_chain_id_var = FpVar::<F>::new_input(cs.clone(), || Ok(in_chain_id))?;
+		let root_set_var = Vec::<FpVar<F>>::new_input(cs.clone(), || Ok(root_set))?;
+
+		// Hashers
+		let tree_hasher = HG::from_native(&mut cs.clone(), self.tree_hasher)?;
+		let keypair_hasher = HG::from_native(&mut cs.clone(), self.keypair_hasher)?;
+		let leaf_hasher = HG::from_native(&mut cs.clone(), self.leaf_hasher)?;
+		let nullifier_hasher = HG::from_native(&mut cs.clone(), self.nullifier_hasher)?;
+
+		// Private inputs
+		let ins = VAnchorInputVars::<F, HG, HEIGHT>::new_witness(
+			cs.clone(),
+			self.in_amounts,
+			self.in_blindings,
+			self.paths,
+			self.indices,
+		)?;
+		let in_private_keys_var = Vec::<FpVar<F>>::new_witness(cs.clone(), || Ok(in_private_keys))?;
+		let in_unlock_times_var = Vec::<FpVar<F>>::new_witness(cs.clone(), || Ok(in_unlock_times))?;
+
+		// Outputs
+		let outs = VAnchorOutputVars::new_witness(
+			cs.clone(),
+			self.out_chain_ids,
+			self.out_amounts,
+			self.out_pubkey,
+			self.out_blindings,
+		)?;
+		let out_unlock_times_var = Vec::<FpVar<F>>::new_witness(cs, || Ok(out_unlock_times))?;
+
+		// Unused (zero) slots of the root set are ignored
+		let set_gadget = SetGadget::new_with_zero_slots_disabled(root_set_var)?;
+		let range_gadget = RangeGadget::new(self.amount_bits);
+		let time_gadget = RangeGadget::new(TIME_BITS);
+
+		// Split the public amount into a sign and a magnitude, which also checks
+		// that it is within the amount range (to prevent overflow)
+		let public_amount = range_gadget.to_signed_amount(&public_amount_var)?;
+
+		// The current time is compared with the unlock times of the inputs
+		time_gadget.enforce_in_range(&current_time_var)?;
+
+		// verify correctness of transaction inputs
+		for tx in 0..N_INS {
+			// Computing the public key
+			let pub_key = keypair_hasher.hash(&[in_private_keys_var[tx].clone()])?;
+			let roothash = verify_input_note(
+				&leaf_hasher,
+				&nullifier_hasher,
+				&tree_hasher,
+				&[
+					in_chain_id_var.clone(),
+					ins.amounts[tx].clone(),
+					pub_key,
+					ins.blindings[tx].clone(),
+					in_unlock_times_var[tx].clone(),
+				],
+				&in_private_keys_var[tx],
+				&ins.indices[tx],
+				&ins.paths[tx],
+				&in_nullifier_var[tx],
+			)?;
+
+			// Check membership if in_amount is non zero
+			let check = set_gadget.check_membership_enabled(&roothash, &ins.amounts[tx])?;
+			check.enforce_equal(&Boolean::TRUE)?;
+
+			// Check that the note is unlocked
+			time_gadget.enforce_in_range(&in_unlock_times_var[tx])?;
+			time_gadget.enforce_less_or_equal(&in_unlock_times_var[tx], &current_time_var)?;
+		}
+		let sum_ins_var = sum_amounts(&range_gadget, &ins.amounts)?;
+
+		// verify correctness of transaction outputs
+		for (tx, commitment) in out_commitment_var.iter().enumerate() {
+			verify_output_note(
+				&leaf_hasher,
+				&[
+					outs.chain_ids[tx].clone(),
+					outs.amounts[tx].clone(),
+					outs.pubkeys[tx].clone(),
+					outs.blindings[tx].clone(),
+					out_unlock_times_var[tx].clone(),
+				],
+				commitment,
+			)?;
+
+			// Check that the output can be unlocked at some point
+			time_gadget.enforce_in_range(&out_unlock_times_var[tx])?;
+		}
+		let sum_outs_var = sum_amounts(&range_gadget, &outs.amounts)?;
+
+		// check that there are no same nullifiers among all inputs
+		enforce_unique_nullifiers(&in_nullifier_var)?;
+
+		// verify amount invariant
+		enforce_balance(&public_amount, &sum_ins_var, &sum_outs_var)?;
+
+		// optional safety constraint to make sure extDataHash cannot be changed
+		let _ = &arbitrary_input_var * &arbitrary_input_var;
+
+		Ok(())
+	}
+}
@@ -0,0 +1,220 @@
+// This file is part of Webb.
+
+// Copyright (C) 2021 Webb Technologies Inc.
+// SPDX-License-Identifier: Apache-2.0
+
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Anonymous voting on DAO polls with the notes of an Anchor or a VAnchor.
+//!
+//! The eligible voters are the owners of the leaves in a snapshot of a
+//! commitment tree, e.g. the root of the Anchor or VAnchor tree when the poll
+//! is created. A voter proves that it owns one of the leaves and publishes a
+//! vote for one of the public number of options of the poll, together with a
+//! nullifier bound to the poll, so every note votes at most once per poll and
+//! the vote can't be linked to the note or to a later spend.
+//!
+//! The vote of an Anchor note has a weight of one, the vote of a VAnchor note
+//! is weighted by the amount of the note.
+use ark_ff::fields::PrimeField;
+use ark_r1cs_std::{eq::EqGadget, fields::fp::FpVar, prelude::*};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+use arkworks_native_gadgets::merkle_tree::Path;
+use arkworks_r1cs_gadgets::{
+	merkle_tree::PathVar, poseidon::FieldHasherGadget, range::RangeGadget,
+};
+
+/// Number of bits of the number of options of a poll
+pub const OPTION_BITS: usize = 16;
+
+/// The note a voter owns in the snapshot tree
+#[derive(Clone)]
+pub enum VoterNote<F: PrimeField> {
+	/// An Anchor note, with the leaf `Hash(chain_id, nullifier, secret)`
+	Anchor {
+		chain_id: F,
+		nullifier: F,
+		secret: F,
+	},
+	/// A VAnchor note, with the leaf `Hash(chain_id, amount,
+	/// Hash(private_key), blinding)`
+	VAnchor {
+		chain_id: F,
+		amount: F,
+		private_key: F,
+		blinding: F,
+	},
+}
+
+/// Defines a `VotingCircuit` struct that hold all the information thats
+/// needed to verify the following statements:
+/// * Alice knows a note whose leaf is stored in the merkle tree with the public
+///   snapshot root,
+/// * The poll nullifier is `Hash(note_secret, poll_id, leaf)`, where the note
+///   secret is the nullifier of an Anchor note or the private key of a VAnchor
+///   note,
+/// * The vote is smaller than the number of options, which fits in
+///   `OPTION_BITS` bits,
+/// * The weight is one for an Anchor note, and the amount of a VAnchor note.
+///
+/// Needs to implement `ConstraintSynthesizer` and a
+/// constructor to generate proper constraints
+#[derive(Clone)]
+pub struct VotingCircuit<F: PrimeField, HG: FieldHasherGadget<F>, const N: usize> {
+	// Poll the vote is cast in
+	poll_id: F,
+	num_options: F,
+	vote: F,
+	weight: F,
+	// Snapshot root of the eligible notes
+	root: F,
+	// Nullifier to prevent voting twice in the poll
+	poll_nullifier: F,
+	// Note of the voter, and its merkle path
+	note: VoterNote<F>,
+	path: Path<F, HG::Native, N>,
+	// 3 input hasher, of the tree
+	tree_hasher: HG::Native,
+	// 2 input hasher, of the VAnchor keypairs
+	keypair_hasher: HG::Native,
+	// Hasher of the leaves, of width 4 for Anchor notes and of width 5 for
+	// VAnchor notes
+	leaf_hasher: HG::Native,
+	// 4 input hasher, of the poll nullifiers
+	nullifier_hasher: HG::Native,
+}
+
+/// Constructor for VotingCircuit
+impl<F, HG, const N: usize> VotingCircuit<F, HG, N>
+where
+	F: PrimeField,
+	HG: FieldHasherGadget<F>,
+{
+	#[allow(clippy::too_many_arguments)]
+	pub fn new(
+		poll_id: F,
+		num_options: F,
+		vote: F,
+		weight: F,
+		root: F,
+		poll_nullifier: F,
+		note: VoterNote<F>,
+		path: Path<F, HG::Native, N>,
+		tree_hasher: HG::Native,
+		keypair_hasher: HG::Native,
+		leaf_hasher: HG::Native,
+		nullifier_hasher: HG::Native,
+	) -> Self {
+		Self {
+			poll_id,
+			num_options,
+			vote,
+			weight,
+			root,
+			poll_nullifier,
+			note,
+			path,
+			tree_hasher,
+			keypair_hasher,
+			leaf_hasher,
+			nullifier_hasher,
+		}
+	}
+}
+
+impl<F, HG, const N: usize> ConstraintSynthesizer<F> for VotingCircuit<F, HG, N>
+where
+	F: PrimeField,
+	HG: FieldHasherGadget<F>,
+{
+	fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+		let poll_id = self.poll_id;
+		let num_options = self.num_options;
+		let vote = self.vote;
+		let weight = self.weight;
+		let root = self.root;
+		let poll_nullifier = self.poll_nullifier;
+		let path = self.path;
+
+		// Generating vars
+		// Public inputs
+		let poll_id_var = FpVar::<F>::new_input(cs.clone(), || Ok(poll_id))?;
+		let root_var = FpVar::<F>::new_input(cs.clone(), || Ok(root))?;
+		let poll_nullifier_var = FpVar::<F>::new_input(cs.clone(), || Ok(poll_nullifier))?;
+		let num_options_var = FpVar::<F>::new_input(cs.clone(), || Ok(num_options))?;
+		let vote_var = FpVar::<F>::new_input(cs.clone(), || Ok(vote))?;
+		let weight_var = FpVar::<F>::new_input(cs.clone(), || Ok(weight))?;
+
+		// Hashers
+		let tree_hasher: HG =
+			FieldHasherGadget::<F>::from_native(&mut cs.clone(), self.tree_hasher)?;
+		let keypair_hasher: HG =
+			FieldHasherGadget::<F>::from_native(&mut cs.clone(), self.keypair_hasher)?;
+		let leaf_hasher: HG =
+			FieldHasherGadget::<F>::from_native(&mut cs.clone(), self.leaf_hasher)?;
+		let nullifier_hasher: HG =
+			FieldHasherGadget::<F>::from_native(&mut cs.clone(), self.nullifier_hasher)?;
+
+		// Private inputs
+		let path_var = PathVar::<F, HG, N>::new_witness(cs.clone(), || Ok(path))?;
+
+		// Creating the leaf of the note, and the secret its poll nullifier is
+		// derived from
+		let (leaf, note_secret) = match self.note {
+			VoterNote::Anchor {
+				chain_id,
+				nullifier,
+				secret,
+			} => {
+				let chain_id_var = FpVar::<F>::new_witness(cs.clone(), || Ok(chain_id))?;
+				let nullifier_var = FpVar::<F>::new_witness(cs.clone(), || Ok(nullifier))?;
+				let secret_var = FpVar::<F>::new_witness(cs, || Ok(secret))?;
+				// Every Anchor note has the same weight
+				weight_var.enforce_equal(&FpVar::<F>::one())?;
+				let leaf = leaf_hasher.hash(&[chain_id_var, nullifier_var.clone(), secret_var])?;
+				(leaf, nullifier_var)
+			}
+			VoterNote::VAnchor {
+				chain_id,
+				amount,
+				private_key,
+				blinding,
+			} => {
+				let chain_id_var = FpVar::<F>::new_witness(cs.clone(), || Ok(chain_id))?;
+				let amount_var = FpVar::<F>::new_witness(cs.clone(), || Ok(amount))?;
+				let private_key_var = FpVar::<F>::new_witness(cs.clone(), || Ok(private_key))?;
+				let blinding_var = FpVar::<F>::new_witness(cs, || Ok(blinding))?;
+				let pub_key = keypair_hasher.hash(&[private_key_var.clone()])?;
+				// The vote is weighted by the amount of the note
+				weight_var.enforce_equal(&amount_var)?;
+				let leaf = leaf_hasher.hash(&[chain_id_var, amount_var, pub_key, blinding_var])?;
+				(leaf, private_key_var)
+			}
+		};
+		let computed_poll_nullifier =
+			nullifier_hasher.hash(&[note_secret, poll_id_var, leaf.clone()])?;
+		let is_member = path_var.check_membership(&root_var, &leaf, &tree_hasher)?;
+
+		// Checking that the vote is one of the options
+		let range_gadget = RangeGadget::<F>::new(OPTION_BITS);
+		range_gadget.enforce_in_range(&num_options_var)?;
+		range_gadget.enforce_in_range(&vote_var)?;
+		range_gadget.enforce_less_than(&vote_var, &num_options_var)?;
+
+		// Enforcing constraints
+		is_member.enforce_equal(&Boolean::TRUE)?;
+		computed_poll_nullifier.enforce_equal(&poll_nullifier_var)?;
+
+		Ok(())
+	}
+}
@@ -0,0 +1,381 @@
+// This file is part of Webb.
+//
+// Copyright (C) 2021 Webb Technologies Inc.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A R1CS gadget to verify EdDSA-Poseidon signatures over Baby Jubjub, as
+//! produced by `arkworks_native_gadgets::eddsa` and circomlib.
+//!
+//! Public keys and signatures are allocated in circomlib coordinates, which
+//! is what the message hash commits to, and are mapped to arkworks
+//! coordinates for the group arithmetic. The mapping only scales the x
+//! coordinate by a constant, so it is free.
+//!
+//! Like circomlib's `EdDSAPoseidonVerifier`, the gadget checks:
+//! * `S < L`, so a signature cannot be made malleable by adding `L` to `S`,
+//! * the public key and `R8` lie on the curve,
+//! * the public key times the cofactor is not the identity, which rejects every
+//!   public key of low order,
+//! * `S * B8 == R8 + (8 * hm) * A`, where `hm = Poseidon(R8, A, msg)`.
+//!
+//! The public key is multiplied by the cofactor, so it does not need to be
+//! checked to lie in the prime order subgroup.
+use crate::{poseidon::FieldHasherGadget, range::RangeGadget};
+use ark_ed_on_bn254::{constraints::EdwardsVar, Fq, Fr};
+use ark_ff::{BigInteger, Field, FpParameters, PrimeField};
+use ark_r1cs_std::{fields::fp::FpVar, groups::CurveVar, prelude::*};
+use ark_relations::r1cs::{Namespace, SynthesisError};
+use arkworks_native_gadgets::eddsa::{base8, to_base_field, PublicKey, Signature, SQRT_A};
+use core::borrow::Borrow;
+
+/// Number of bits of the order `L` of the prime order subgroup
+pub const SUBGROUP_ORDER_BITS: usize = 251;
+
+/// A public key, in circomlib coordinates
+#[derive(Clone)]
+pub struct PublicKeyVar {
+	pub x: FpVar<Fq>,
+	pub y: FpVar<Fq>,
+}
+
+impl AllocVar<PublicKey, Fq> for PublicKeyVar {
+	fn new_variable<T: Borrow<PublicKey>>(
+		cs: impl Into<Namespace<Fq>>,
+		f: impl FnOnce() -> Result<T, SynthesisError>,
+		mode: AllocationMode,
+	) -> Result<Self, SynthesisError> {
+		let ns = cs.into();
+		let cs = ns.cs();
+
+		let public_key = *f()?.borrow();
+		let x = FpVar::new_variable(cs.clone(), || Ok(public_key.x), mode)?;
+		let y = FpVar::new_variable(cs, || Ok(public_key.y), mode)?;
+		Ok(Self { x, y })
+	}
+}
+
+impl PublicKeyVar {
+	/// Computes the public key `scalar * B8` of a scalar given by its
+	/// little-endian bits
+	pub fn from_scalar_bits(bits: &[Boolean<Fq>]) -> Result<Self, SynthesisError> {
+		let b8 = base8();
+		let b8 = EdwardsVar::new(FpVar::constant(b8.x), FpVar::constant(b8.y));
+		Ok(Self::from_edwards(&b8.scalar_mul_le(bits.iter())?))
+	}
+
+	/// Computes the ECDH shared point `scalar * self`, in circomlib
+	/// coordinates, for a scalar given by its little-endian bits
+	pub fn shared_key(
+		&self,
+		bits: &[Boolean<Fq>],
+	) -> Result<(FpVar<Fq>, FpVar<Fq>), SynthesisError> {
+		let shared = Self::from_edwards(&self.to_edwards().scalar_mul_le(bits.iter())?);
+		Ok((shared.x, shared.y))
+	}
+
+	fn to_edwards(&self) -> EdwardsVar {
+		EdwardsVar::new(&self.x * SQRT_A, self.y.clone())
+	}
+
+	fn from_edwards(point: &EdwardsVar) -> Self {
+		// SQRT_A is a non-zero constant, so the inverse always exists
+		let sqrt_a_inv = SQRT_A.inverse().unwrap();
+		Self {
+			x: &point.x * sqrt_a_inv,
+			y: point.y.clone(),
+		}
+	}
+}
+
+/// A signature, with `R8` in circomlib coordinates and `S` embedded in the
+/// base field
+#[derive(Clone)]
+pub struct SignatureVar {
+	pub r8_x: FpVar<Fq>,
+	pub r8_y: FpVar<Fq>,
+	pub s: FpVar<Fq>,
+}
+
+impl AllocVar<Signature, Fq> for SignatureVar {
+	fn new_variable<T: Borrow<Signature>>(
+		cs: impl Into<Namespace<Fq>>,
+		f: impl FnOnce() -> Result<T, SynthesisError>,
+		mode: AllocationMode,
+	) -> Result<Self, SynthesisError> {
+		let ns = cs.into();
+		let cs = ns.cs();
+
+		let signature = *f()?.borrow();
+		let s = to_base_field(&signature.s);
+		let r8_x = FpVar::new_variable(cs.clone(), || Ok(signature.r8_x), mode)?;
+		let r8_y = FpVar::new_variable(cs.clone(), || Ok(signature.r8_y), mode)?;
+		let s = FpVar::new_variable(cs, || Ok(s), mode)?;
+		Ok(Self { r8_x, r8_y, s })
+	}
+}
+
+pub struct EdDSAVerifyGadget;
+
+impl EdDSAVerifyGadget {
+	/// Outputs whether `signature` is a valid signature of `msg` under
+	/// `public_key`. `hasher` must be the Poseidon hash of width 6.
+	///
+	/// `S` is decomposed into 251 bits, so the constraints are unsatisfiable
+	/// if it does not fit in 251 bits.
+	pub fn verify<HG: FieldHasherGadget<Fq>>(
+		hasher: &HG,
+		public_key: &PublicKeyVar,
+		msg: &FpVar<Fq>,
+		signature: &SignatureVar,
+	) -> Result<Boolean<Fq>, SynthesisError> {
+		let range = RangeGadget::<Fq>::new(SUBGROUP_ORDER_BITS);
+		let s_bits = range.to_bits_le(&signature.s)?;
+		let order = Fq::from_le_bytes_mod_order(&<Fr as PrimeField>::Params::MODULUS.to_bytes_le());
+		let s_is_canonical = range.less_than(&signature.s, &FpVar::constant(order))?;
+
+		let public_key_on_curve = Self::is_on_curve(&public_key.x, &public_key.y)?;
+		let r8_on_curve = Self::is_on_curve(&signature.r8_x, &signature.r8_y)?;
+
+		let hm = hasher.hash(&[
+			signature.r8_x.clone(),
+			signature.r8_y.clone(),
+			public_key.x.clone(),
+			public_key.y.clone(),
+			msg.clone(),
+		])?;
+		let hm_bits = hm.to_bits_le()?;
+
+		let a8 = public_key.to_edwards().double()?.double()?.double()?;
+		// Under a key of low order `8 * A` is the identity, and any `S` with
+		// `R8 = S * B8` would pass the group equation
+		let public_key_not_low_order = a8.x.is_neq(&FpVar::zero())?;
+		let r8 = EdwardsVar::new(&signature.r8_x * SQRT_A, signature.r8_y.clone());
+		let rhs = PublicKeyVar::from_edwards(&(r8 + a8.scalar_mul_le(hm_bits.iter())?));
+		let lhs = PublicKeyVar::from_scalar_bits(&s_bits)?;
+
+		Boolean::kary_and(&[
+			s_is_canonical,
+			public_key_on_curve,
+			r8_on_curve,
+			public_key_not_low_order,
+			lhs.x.is_eq(&rhs.x)?,
+			lhs.y.is_eq(&rhs.y)?,
+		])
+	}
+
+	/// Outputs whether `(x, y)` satisfies circomlib's curve equation
+	/// `168700 x^2 + y^2 = 1 + 168696 x^2 y^2`
+	fn is_on_curve(x: &FpVar<Fq>, y: &FpVar<Fq>) -> Result<Boolean<Fq>, SynthesisError> {
+		let x2 = x.square()?;
+		let y2 = y.square()?;
+		let lhs = &x2 * Fq::from(168700u64) + &y2;
+		let rhs = FpVar::one() + (&x2 * &y2) * Fq::from(168696u64);
+		lhs.is_eq(&rhs)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::poseidon::PoseidonGadget;
+	use ark_ec::{AffineCurve, ProjectiveCurve};
+	use ark_ff::{One, Zero};
+	use ark_relations::r1cs::ConstraintSystem;
+	use ark_std::test_rng;
+	use arkworks_native_gadgets::{
+		eddsa::{to_circom_coordinates, SecretKey},
+		poseidon::{sbox::PoseidonSbox, Poseidon, PoseidonParameters},
+	};
+	use arkworks_utils::{
+		bytes_matrix_to_f, bytes_vec_to_f, poseidon_params::setup_poseidon_params, Curve,
+	};
+
+	fn setup_hasher() -> Poseidon<Fq> {
+		let pos_data = setup_poseidon_params(Curve::Bn254, 5, 6).unwrap();
+		Poseidon::new(PoseidonParameters {
+			mds_matrix: bytes_matrix_to_f(&pos_data.mds),
+			round_keys: bytes_vec_to_f(&pos_data.rounds),
+			full_rounds: pos_data.full_rounds,
+			partial_rounds: pos_data.partial_rounds,
+			sbox: PoseidonSbox(pos_data.exp),
+			width: pos_data.width,
+		})
+	}
+
+	/// Verifies `(r8_x, r8_y, s)` in a fresh constraint system and returns the
+	/// output of the gadget and whether the constraints are satisfied
+	fn verify_in_circuit(public_key: PublicKey, msg: Fq, signature: (Fq, Fq, Fq)) -> (bool, bool) {
+		let mut cs = ConstraintSystem::<Fq>::new_ref();
+		let hasher = PoseidonGadget::from_native(&mut cs, setup_hasher()).unwrap();
+
+		let public_key_var = PublicKeyVar::new_input(cs.clone(), || Ok(public_key)).unwrap();
+		let msg_var = FpVar::new_input(cs.clone(), || Ok(msg)).unwrap();
+		let signature_var = SignatureVar {
+			r8_x: FpVar::new_witness(cs.clone(), || Ok(signature.0)).unwrap(),
+			r8_y: FpVar::new_witness(cs.clone(), || Ok(signature.1)).unwrap(),
+			s: FpVar::new_witness(cs.clone(), || Ok(signature.2)).unwrap(),
+		};
+
+		let is_valid =
+			EdDSAVerifyGadget::verify(&hasher, &public_key_var, &msg_var, &signature_var).unwrap();
+		is_valid.enforce_equal(&Boolean::TRUE).unwrap();
+		(is_valid.value().unwrap(), cs.is_satisfied().unwrap())
+	}
+
+	fn to_inputs(signature: &Signature) -> (Fq, Fq, Fq) {
+		(signature.r8_x, signature.r8_y, to_base_field(&signature.s))
+	}
+
+	#[test]
+	fn should_verify_signature() {
+		let rng = &mut test_rng();
+		let hasher = setup_hasher();
+		let secret_key = SecretKey::rand(rng);
+		let msg = Fq::from(42u64);
+		let signature = secret_key.sign(msg, &hasher).unwrap();
+
+		let (is_valid, is_satisfied) =
+			verify_in_circuit(secret_key.public_key(), msg, to_inputs(&signature));
+		assert!(is_valid);
+		assert!(is_satisfied);
+	}
+
+	#[test]
+	fn should_allocate_signature() {
+		let rng = &mut test_rng();
+		let hasher = setup_hasher();
+		let secret_key = SecretKey::rand(rng);
+		let msg = Fq::from(42u64);
+		let signature = secret_key.sign(msg, &hasher).unwrap();
+
+		let cs = ConstraintSystem::<Fq>::new_ref();
+		let signature_var = SignatureVar::new_witness(cs, || Ok(signature)).unwrap();
+		let (r8_x, r8_y, s) = to_inputs(&signature);
+		assert_eq!(signature_var.r8_x.value().unwrap(), r8_x);
+		assert_eq!(signature_var.r8_y.value().unwrap(), r8_y);
+		assert_eq!(signature_var.s.value().unwrap(), s);
+	}
+
+	#[test]
+	fn should_not_verify_tampered_message() {
+		let rng = &mut test_rng();
+		let hasher = setup_hasher();
+		let secret_key = SecretKey::rand(rng);
+		let signature = secret_key.sign(Fq::from(42u64), &hasher).unwrap();
+
+		let (is_valid, is_satisfied) = verify_in_circuit(
+			secret_key.public_key(),
+			Fq::from(43u64),
+			to_inputs(&signature),
+		);
+		assert!(!is_valid);
+		assert!(!is_satisfied);
+	}
+
+	#[test]
+	fn should_not_verify_other_signer() {
+		let rng = &mut test_rng();
+		let hasher = setup_hasher();
+		let secret_key = SecretKey::rand(rng);
+		let msg = Fq::from(42u64);
+		let signature = secret_key.sign(msg, &hasher).unwrap();
+
+		let other_key = SecretKey::rand(rng).public_key();
+		let (is_valid, is_satisfied) = verify_in_circuit(other_key, msg, to_inputs(&signature));
+		assert!(!is_valid);
+		assert!(!is_satisfied);
+	}
+
+	#[test]
+	fn should_not_verify_non_canonical_scalar() {
+		let rng = &mut test_rng();
+		let hasher = setup_hasher();
+		let secret_key = SecretKey::rand(rng);
+		let msg = Fq::from(42u64);
+		let signature = secret_key.sign(msg, &hasher).unwrap();
+
+		// (S + L) * B8 == S * B8, but S + L must be rejected
+		let order = Fq::from_le_bytes_mod_order(&<Fr as PrimeField>::Params::MODULUS.to_bytes_le());
+		let (r8_x, r8_y, s) = to_inputs(&signature);
+		let (is_valid, is_satisfied) =
+			verify_in_circuit(secret_key.public_key(), msg, (r8_x, r8_y, s + order));
+		assert!(!is_valid);
+		assert!(!is_satisfied);
+	}
+
+	#[test]
+	fn should_not_verify_identity_public_key() {
+		let hasher = setup_hasher();
+		let msg = Fq::from(42u64);
+
+		// Under the identity, any S with R8 = S * B8 passes the group equation
+		let s = Fr::from(7u64);
+		let (r8_x, r8_y) = to_circom_coordinates(&base8().mul(s.into_repr()).into_affine());
+		let forged = Signature { r8_x, r8_y, s };
+		let identity = PublicKey {
+			x: Fq::zero(),
+			y: Fq::one(),
+		};
+		assert!(!identity.verify(msg, &forged, &hasher).unwrap());
+
+		let (is_valid, is_satisfied) = verify_in_circuit(identity, msg, to_inputs(&forged));
+		assert!(!is_valid);
+		assert!(!is_satisfied);
+	}
+
+	#[test]
+	fn should_not_verify_low_order_public_key() {
+		let hasher = setup_hasher();
+		let msg = Fq::from(42u64);
+
+		// (1 / sqrt(168700), 0) has order 4, so 8 * A is the identity even
+		// though A is not
+		let s = Fr::from(7u64);
+		let (r8_x, r8_y) = to_circom_coordinates(&base8().mul(s.into_repr()).into_affine());
+		let forged = Signature { r8_x, r8_y, s };
+		let low_order = PublicKey {
+			x: SQRT_A.inverse().unwrap(),
+			y: Fq::zero(),
+		};
+		assert!(!low_order.verify(msg, &forged, &hasher).unwrap());
+
+		let (is_valid, is_satisfied) = verify_in_circuit(low_order, msg, to_inputs(&forged));
+		assert!(!is_valid);
+		assert!(!is_satisfied);
+	}
+
+	#[test]
+	fn should_compute_public_and_shared_keys() {
+		let rng = &mut test_rng();
+		let alice = SecretKey::rand(rng);
+		let bob = SecretKey::rand(rng);
+
+		let cs = ConstraintSystem::<Fq>::new_ref();
+		let scalar = FpVar::new_witness(cs.clone(), || Ok(to_base_field(&alice.scalar()))).unwrap();
+		let bits = RangeGadget::new(SUBGROUP_ORDER_BITS)
+			.to_bits_le(&scalar)
+			.unwrap();
+
+		let public_key = PublicKeyVar::from_scalar_bits(&bits).unwrap();
+		assert_eq!(public_key.x.value().unwrap(), alice.public_key().x);
+		assert_eq!(public_key.y.value().unwrap(), alice.public_key().y);
+
+		let bob_var = PublicKeyVar::new_witness(cs.clone(), || Ok(bob.public_key())).unwrap();
+		let (x, y) = bob_var.shared_key(&bits).unwrap();
+		let expected = bob.shared_key(&alice.public_key()).unwrap();
+		assert_eq!((x.value().unwrap(), y.value().unwrap()), expected);
+		assert!(cs.is_satisfied().unwrap());
+	}
+}
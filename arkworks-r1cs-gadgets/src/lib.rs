@@ -5,6 +5,13 @@ pub extern crate ark_std;
 
 pub(crate) use ark_std::vec::Vec;
 
+#[cfg(feature = "babyjubjub")]
+pub mod eddsa;
 pub mod merkle_tree;
+pub mod mimc;
+#[cfg(feature = "babyjubjub")]
+pub mod pedersen;
 pub mod poseidon;
+pub mod range;
 pub mod set;
+pub mod uniqueness;
@@ -96,7 +96,7 @@ use ark_std::{borrow::Borrow, marker::PhantomData, vec::Vec};
 use arkworks_native_gadgets::{merkle_tree::Path, poseidon::FieldHasher};
 use core::convert::TryInto;
 
-use crate::poseidon::FieldHasherGadget;
+use crate::{poseidon::FieldHasherGadget, range::RangeGadget};
 
 /// Gadgets for one Merkle tree path
 #[derive(Debug, Clone)]
@@ -159,6 +159,62 @@ where
 		Ok(previous_hash)
 	}
 
+	/// check whether path belongs to a merkle tree of height `depth` (does not
+	/// check if indexes match)
+	pub fn check_membership_at_depth(
+		&self,
+		root: &FpVar<F>,
+		leaf: &FpVar<F>,
+		depth: &FpVar<F>,
+		hasher: &HG,
+	) -> Result<Boolean<F>, SynthesisError> {
+		let computed_root = self.root_hash_at_depth(leaf, depth, hasher)?;
+
+		root.is_eq(&computed_root)
+	}
+
+	/// Creates circuit to calculate the merkle root of a tree of height
+	/// `depth`, where `depth` is a public input or a witness between 1 and
+	/// `N`. Only the first `depth` levels of the path are checked, the levels
+	/// above are ignored and the root is selected among the intermediate
+	/// hashes.
+	pub fn root_hash_at_depth(
+		&self,
+		leaf: &FpVar<F>,
+		depth: &FpVar<F>,
+		hasher: &HG,
+	) -> Result<FpVar<F>, SynthesisError> {
+		assert_eq!(self.path.len(), N);
+		let mut root = FpVar::<F>::zero();
+		// Whether the current level is below `depth`
+		let mut is_active = Boolean::TRUE;
+
+		// Check levels between leaf level and `depth`.
+		let mut previous_hash = leaf.clone();
+		for (level, &(ref left_hash, ref right_hash)) in self.path.iter().enumerate() {
+			// Check if the previous_hash matches the correct current hash.
+			let previous_is_left = previous_hash.is_eq(left_hash)?;
+
+			previous_hash.conditional_enforce_equal(
+				&FpVar::<F>::conditionally_select(&previous_is_left, left_hash, right_hash)?,
+				&is_active,
+			)?;
+
+			previous_hash = hasher.hash_two(left_hash, right_hash)?;
+
+			// Select the hash of this level as the root if it is at `depth`
+			let level_var = FpVar::<F>::constant(F::from((level + 1) as u64));
+			let is_depth = depth.is_eq(&level_var)?;
+			root = FpVar::<F>::conditionally_select(&is_depth, &previous_hash, &root)?;
+			is_active = is_active.and(&is_depth.not())?;
+		}
+
+		// Deny a depth that wasn't reached, i.e. outside of 1..=N
+		is_active.enforce_equal(&Boolean::FALSE)?;
+
+		Ok(root)
+	}
+
 	/// Creates circuit to get index of a leaf hash
 	pub fn get_index(
 		&self,
@@ -189,6 +245,115 @@ where
 
 		Ok(index)
 	}
+
+	/// Creates circuit to calculate the merkle roots before and after
+	/// replacing `old_leaf` with `new_leaf`. The position of the leaf is given
+	/// by `index_bits`, one little-endian bit per level, and only the siblings
+	/// along the path are used, so one path serves both roots.
+	pub fn update_root(
+		&self,
+		old_leaf: &FpVar<F>,
+		new_leaf: &FpVar<F>,
+		index_bits: &[Boolean<F>],
+		hasher: &HG,
+	) -> Result<(FpVar<F>, FpVar<F>), SynthesisError> {
+		assert_eq!(index_bits.len(), N);
+		let mut old_hash = old_leaf.clone();
+		let mut new_hash = new_leaf.clone();
+		for (&(ref left_hash, ref right_hash), is_right) in self.path.iter().zip(index_bits) {
+			// The sibling is on the opposite side of the updated node
+			let sibling = FpVar::<F>::conditionally_select(is_right, left_hash, right_hash)?;
+
+			let old_left = FpVar::<F>::conditionally_select(is_right, &sibling, &old_hash)?;
+			let old_right = FpVar::<F>::conditionally_select(is_right, &old_hash, &sibling)?;
+			old_hash = hasher.hash_two(&old_left, &old_right)?;
+
+			let new_left = FpVar::<F>::conditionally_select(is_right, &sibling, &new_hash)?;
+			let new_right = FpVar::<F>::conditionally_select(is_right, &new_hash, &sibling)?;
+			new_hash = hasher.hash_two(&new_left, &new_right)?;
+		}
+
+		Ok((old_hash, new_hash))
+	}
+}
+
+/// Gadget for appending a batch of leaves to a Merkle tree at consecutive
+/// indices. Every leaf comes with the path of its slot in the tree holding
+/// the leaves inserted before it.
+pub struct BatchInsertGadget<F, HG, const N: usize>
+where
+	F: PrimeField,
+	HG: FieldHasherGadget<F>,
+{
+	paths: Vec<PathVar<F, HG, N>>,
+}
+
+impl<F, HG, const N: usize> BatchInsertGadget<F, HG, N>
+where
+	F: PrimeField,
+	HG: FieldHasherGadget<F>,
+{
+	pub fn new(paths: Vec<PathVar<F, HG, N>>) -> Self {
+		Self { paths }
+	}
+
+	/// Creates circuit to calculate the root of the tree after inserting
+	/// `leaves` at indices `start_index..start_index + leaves.len()` of the
+	/// tree with root `old_root`. Every slot is checked to hold `empty_leaf`
+	/// before the insertion, and every index to fit in the tree.
+	pub fn root_after_insert(
+		&self,
+		old_root: &FpVar<F>,
+		start_index: &FpVar<F>,
+		leaves: &[FpVar<F>],
+		empty_leaf: &FpVar<F>,
+		hasher: &HG,
+	) -> Result<FpVar<F>, SynthesisError> {
+		if self.paths.len() != leaves.len() {
+			return Err(SynthesisError::Unsatisfiable);
+		}
+		let range_gadget = RangeGadget::<F>::new(N);
+
+		let mut root = old_root.clone();
+		for (i, (path, leaf)) in self.paths.iter().zip(leaves).enumerate() {
+			let index = start_index + FpVar::<F>::constant(F::from(i as u64));
+			let index_bits = range_gadget.to_bits_le(&index)?;
+
+			let (root_before, root_after) =
+				path.update_root(empty_leaf, leaf, &index_bits, hasher)?;
+			root_before.enforce_equal(&root)?;
+			root = root_after;
+		}
+
+		Ok(root)
+	}
+
+	/// Checks that inserting `leaves` from `start_index` turns the tree with
+	/// root `old_root` into the tree with root `new_root`
+	pub fn check_batch_insert(
+		&self,
+		old_root: &FpVar<F>,
+		new_root: &FpVar<F>,
+		start_index: &FpVar<F>,
+		leaves: &[FpVar<F>],
+		empty_leaf: &FpVar<F>,
+		hasher: &HG,
+	) -> Result<Boolean<F>, SynthesisError> {
+		let root = self.root_after_insert(old_root, start_index, leaves, empty_leaf, hasher)?;
+
+		root.is_eq(new_root)
+	}
+
+	/// Creates circuit to hash the batch of leaves into a single value, the
+	/// same way as `batch_leaves_hash` from the native merkle tree
+	pub fn hash_leaves(leaves: &[FpVar<F>], hasher: &HG) -> Result<FpVar<F>, SynthesisError> {
+		let mut hash = FpVar::<F>::zero();
+		for leaf in leaves {
+			hash = hasher.hash_two(&hash, leaf)?;
+		}
+
+		Ok(hash)
+	}
 }
 
 impl<F, H, HG, const N: usize> AllocVar<Path<F, H, N>, F> for PathVar<F, HG, N>
@@ -235,10 +400,10 @@ where
 
 #[cfg(test)]
 mod test {
-	use super::PathVar;
+	use super::{BatchInsertGadget, PathVar};
 	use crate::poseidon::{FieldHasherGadget, PoseidonGadget};
 	use arkworks_native_gadgets::{
-		merkle_tree::SparseMerkleTree,
+		merkle_tree::{batch_leaves_hash, Path, SparseMerkleTree},
 		poseidon::{sbox::PoseidonSbox, Poseidon, PoseidonParameters},
 	};
 
@@ -246,7 +411,7 @@ mod test {
 	use ark_ff::PrimeField;
 	use ark_r1cs_std::{alloc::AllocVar, fields::fp::FpVar, R1CSVar};
 	use ark_relations::r1cs::ConstraintSystem;
-	use ark_std::{test_rng, UniformRand};
+	use ark_std::{collections::BTreeMap, test_rng, vec::Vec, UniformRand};
 	use arkworks_utils::{
 		bytes_matrix_to_f, bytes_vec_to_f, poseidon_params::setup_poseidon_params, Curve,
 	};
@@ -277,6 +442,26 @@ mod test {
 	const DEFAULT_LEAF: [u8; 32] = [0; 32];
 	type SMT = SparseMerkleTree<Fq, Poseidon<Fq>, HEIGHT>;
 
+	// Inserts the leaves one by one, collecting the path of each slot before
+	// its leaf is inserted
+	fn insert_and_create_paths(
+		smt: &mut SMT,
+		start_index: u64,
+		leaves: &[Fq],
+		hasher: &Poseidon<Fq>,
+	) -> Vec<Path<Fq, Poseidon<Fq>, HEIGHT>> {
+		let mut paths = Vec::new();
+		for (i, leaf) in leaves.iter().enumerate() {
+			let index = start_index + i as u64;
+			paths.push(smt.generate_membership_proof(index));
+
+			let mut pairs = BTreeMap::new();
+			pairs.insert(index as u32, *leaf);
+			smt.insert_batch(&pairs, hasher).unwrap();
+		}
+		paths
+	}
+
 	#[test]
 	fn should_verify_path() {
 		let rng = &mut test_rng();
@@ -305,6 +490,119 @@ mod test {
 		assert!(res.value().unwrap());
 	}
 
+	#[test]
+	fn should_verify_path_at_depth() {
+		let rng = &mut test_rng();
+		let curve = Curve::Bn254;
+
+		let params3 = setup_params(curve, 5, 3);
+		let hasher = Poseidon::<Fq> { params: params3 };
+
+		let leaves = vec![Fq::rand(rng), Fq::rand(rng), Fq::rand(rng)];
+		// The path of the largest tree serves the smaller trees too
+		let smt = SMT::new_sequential(&leaves, &hasher, &DEFAULT_LEAF).unwrap();
+		let path = smt.generate_membership_proof(1);
+
+		const SMALL_HEIGHT: usize = 4;
+		let small_smt = SparseMerkleTree::<Fq, Poseidon<Fq>, SMALL_HEIGHT>::new_sequential(
+			&leaves,
+			&hasher,
+			&DEFAULT_LEAF,
+		)
+		.unwrap();
+
+		for (depth, root) in [(SMALL_HEIGHT, small_smt.root()), (HEIGHT, smt.root())] {
+			let mut cs = ConstraintSystem::<Fq>::new_ref();
+			let hasher_gadget = PoseidonGadget::from_native(&mut cs, hasher.clone()).unwrap();
+
+			let path_var =
+				PathVar::<_, SMTCRHGadget, HEIGHT>::new_witness(cs.clone(), || Ok(path.clone()))
+					.unwrap();
+			let root_var = FieldVar::new_input(cs.clone(), || Ok(root)).unwrap();
+			let leaf_var = FieldVar::new_witness(cs.clone(), || Ok(leaves[1])).unwrap();
+			let depth_var = FieldVar::new_input(cs.clone(), || Ok(Fq::from(depth as u64))).unwrap();
+
+			let res = path_var
+				.check_membership_at_depth(&root_var, &leaf_var, &depth_var, &hasher_gadget)
+				.unwrap();
+			assert!(res.cs().is_satisfied().unwrap());
+			assert!(res.value().unwrap());
+		}
+	}
+
+	#[test]
+	fn should_ignore_levels_above_depth() {
+		let rng = &mut test_rng();
+		let curve = Curve::Bn254;
+
+		let params3 = setup_params(curve, 5, 3);
+		let hasher = Poseidon::<Fq> { params: params3 };
+
+		let mut cs = ConstraintSystem::<Fq>::new_ref();
+		let hasher_gadget = PoseidonGadget::from_native(&mut cs, hasher.clone()).unwrap();
+
+		const SMALL_HEIGHT: usize = 4;
+		let leaves = vec![Fq::rand(rng), Fq::rand(rng)];
+		let small_smt = SparseMerkleTree::<Fq, Poseidon<Fq>, SMALL_HEIGHT>::new_sequential(
+			&leaves,
+			&hasher,
+			&DEFAULT_LEAF,
+		)
+		.unwrap();
+		let small_path = small_smt.generate_membership_proof(0);
+
+		// Pad the path with garbage above the depth
+		let mut path = SMT::new_sequential(&leaves, &hasher, &DEFAULT_LEAF)
+			.unwrap()
+			.generate_membership_proof(0);
+		path.path[..SMALL_HEIGHT].copy_from_slice(&small_path.path);
+		for level in SMALL_HEIGHT..HEIGHT {
+			path.path[level] = (Fq::rand(rng), Fq::rand(rng));
+		}
+
+		let path_var =
+			PathVar::<_, SMTCRHGadget, HEIGHT>::new_witness(cs.clone(), || Ok(path)).unwrap();
+		let leaf_var = FieldVar::new_witness(cs.clone(), || Ok(leaves[0])).unwrap();
+		let depth_var =
+			FieldVar::new_witness(cs.clone(), || Ok(Fq::from(SMALL_HEIGHT as u64))).unwrap();
+
+		let root_var = path_var
+			.root_hash_at_depth(&leaf_var, &depth_var, &hasher_gadget)
+			.unwrap();
+		assert!(root_var.cs().is_satisfied().unwrap());
+		assert_eq!(root_var.value().unwrap(), small_smt.root());
+	}
+
+	#[test]
+	fn should_not_verify_depth_out_of_bounds() {
+		let rng = &mut test_rng();
+		let curve = Curve::Bn254;
+
+		let params3 = setup_params(curve, 5, 3);
+		let hasher = Poseidon::<Fq> { params: params3 };
+
+		let leaves = vec![Fq::rand(rng), Fq::rand(rng), Fq::rand(rng)];
+		let smt = SMT::new_sequential(&leaves, &hasher, &DEFAULT_LEAF).unwrap();
+		let path = smt.generate_membership_proof(0);
+
+		for depth in [0, HEIGHT + 1] {
+			let mut cs = ConstraintSystem::<Fq>::new_ref();
+			let hasher_gadget = PoseidonGadget::from_native(&mut cs, hasher.clone()).unwrap();
+
+			let path_var =
+				PathVar::<_, SMTCRHGadget, HEIGHT>::new_witness(cs.clone(), || Ok(path.clone()))
+					.unwrap();
+			let leaf_var = FieldVar::new_witness(cs.clone(), || Ok(leaves[0])).unwrap();
+			let depth_var =
+				FieldVar::new_witness(cs.clone(), || Ok(Fq::from(depth as u64))).unwrap();
+
+			let _ = path_var
+				.root_hash_at_depth(&leaf_var, &depth_var, &hasher_gadget)
+				.unwrap();
+			assert!(!cs.is_satisfied().unwrap());
+		}
+	}
+
 	#[test]
 	fn should_verify_index() {
 		let rng = &mut test_rng();
@@ -374,4 +672,111 @@ mod test {
 		assert!(res.cs().is_satisfied().unwrap());
 		assert_eq!(res.value().unwrap(), desired_res);
 	}
+
+	#[test]
+	fn should_verify_batch_insert() {
+		let rng = &mut test_rng();
+		let curve = Curve::Bn254;
+
+		let params3 = setup_params(curve, 5, 3);
+		let hasher = Poseidon::<Fq> { params: params3 };
+
+		let mut cs = ConstraintSystem::<Fq>::new_ref();
+		let hasher_gadget = PoseidonGadget::from_native(&mut cs, hasher.clone()).unwrap();
+
+		let leaves = vec![Fq::rand(rng), Fq::rand(rng), Fq::rand(rng)];
+		let mut smt = SMT::new_sequential(&leaves, &hasher, &DEFAULT_LEAF).unwrap();
+		let old_root = smt.root();
+
+		let start_index = leaves.len() as u64;
+		let new_leaves = vec![Fq::rand(rng), Fq::rand(rng), Fq::rand(rng)];
+		let paths = insert_and_create_paths(&mut smt, start_index, &new_leaves, &hasher);
+		let new_root = smt.root();
+
+		let old_root_var = FieldVar::new_input(cs.clone(), || Ok(old_root)).unwrap();
+		let new_root_var = FieldVar::new_input(cs.clone(), || Ok(new_root)).unwrap();
+		let start_index_var =
+			FieldVar::new_input(cs.clone(), || Ok(Fq::from(start_index))).unwrap();
+		let leaves_var =
+			Vec::<FieldVar>::new_witness(cs.clone(), || Ok(new_leaves.clone())).unwrap();
+		let paths_var = paths
+			.into_iter()
+			.map(|path| {
+				PathVar::<_, SMTCRHGadget, HEIGHT>::new_witness(cs.clone(), || Ok(path)).unwrap()
+			})
+			.collect();
+		let empty_leaf_var =
+			FieldVar::new_constant(cs.clone(), Fq::from_be_bytes_mod_order(&DEFAULT_LEAF)).unwrap();
+
+		let gadget = BatchInsertGadget::new(paths_var);
+		let res = gadget
+			.check_batch_insert(
+				&old_root_var,
+				&new_root_var,
+				&start_index_var,
+				&leaves_var,
+				&empty_leaf_var,
+				&hasher_gadget,
+			)
+			.unwrap();
+		assert!(res.cs().is_satisfied().unwrap());
+		assert!(res.value().unwrap());
+
+		let leaves_hash =
+			BatchInsertGadget::<_, SMTCRHGadget, HEIGHT>::hash_leaves(&leaves_var, &hasher_gadget)
+				.unwrap();
+		assert_eq!(
+			leaves_hash.value().unwrap(),
+			batch_leaves_hash(&new_leaves, &hasher).unwrap()
+		);
+	}
+
+	#[test]
+	fn should_not_verify_batch_insert_over_existing_leaves() {
+		let rng = &mut test_rng();
+		let curve = Curve::Bn254;
+
+		let params3 = setup_params(curve, 5, 3);
+		let hasher = Poseidon::<Fq> { params: params3 };
+
+		let mut cs = ConstraintSystem::<Fq>::new_ref();
+		let hasher_gadget = PoseidonGadget::from_native(&mut cs, hasher.clone()).unwrap();
+
+		let leaves = vec![Fq::rand(rng), Fq::rand(rng), Fq::rand(rng)];
+		let mut smt = SMT::new_sequential(&leaves, &hasher, &DEFAULT_LEAF).unwrap();
+		let old_root = smt.root();
+
+		// The slot at index 2 already holds a leaf
+		let start_index = 2;
+		let new_leaves = vec![Fq::rand(rng), Fq::rand(rng)];
+		let paths = insert_and_create_paths(&mut smt, start_index, &new_leaves, &hasher);
+		let new_root = smt.root();
+
+		let old_root_var = FieldVar::new_input(cs.clone(), || Ok(old_root)).unwrap();
+		let new_root_var = FieldVar::new_input(cs.clone(), || Ok(new_root)).unwrap();
+		let start_index_var =
+			FieldVar::new_input(cs.clone(), || Ok(Fq::from(start_index))).unwrap();
+		let leaves_var = Vec::<FieldVar>::new_witness(cs.clone(), || Ok(new_leaves)).unwrap();
+		let paths_var = paths
+			.into_iter()
+			.map(|path| {
+				PathVar::<_, SMTCRHGadget, HEIGHT>::new_witness(cs.clone(), || Ok(path)).unwrap()
+			})
+			.collect();
+		let empty_leaf_var =
+			FieldVar::new_constant(cs.clone(), Fq::from_be_bytes_mod_order(&DEFAULT_LEAF)).unwrap();
+
+		let gadget = BatchInsertGadget::new(paths_var);
+		let _ = gadget
+			.check_batch_insert(
+				&old_root_var,
+				&new_root_var,
+				&start_index_var,
+				&leaves_var,
+				&empty_leaf_var,
+				&hasher_gadget,
+			)
+			.unwrap();
+		assert!(!cs.is_satisfied().unwrap());
+	}
 }
@@ -0,0 +1,152 @@
+// This file is part of Webb.
+//
+// Copyright (C) 2021 Webb Technologies Inc.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A R1CS gadget of circomlib's MiMCSponge hash, as computed by
+//! `arkworks_native_gadgets::mimc::MiMCSponge`.
+//!
+//! It implements `FieldHasherGadget`, so it can be used with `PathVar` to
+//! check membership in Tornado Cash's Merkle trees. Every round costs 3
+//! constraints.
+use crate::poseidon::FieldHasherGadget;
+use ark_ff::PrimeField;
+use ark_r1cs_std::{fields::fp::FpVar, prelude::*};
+use ark_relations::r1cs::{ConstraintSystemRef, SynthesisError};
+use ark_std::vec::Vec;
+use arkworks_native_gadgets::mimc::MiMCSponge;
+
+#[derive(Clone)]
+pub struct MiMCSpongeGadget<F: PrimeField> {
+	/// The key of the Feistel network
+	pub k: FpVar<F>,
+	/// The round constants, one per round
+	pub round_keys: Vec<FpVar<F>>,
+}
+
+impl<F: PrimeField> MiMCSpongeGadget<F> {
+	/// Applies the Feistel permutation to the state `(x_l, x_r)`
+	pub fn feistel(
+		&self,
+		left: &FpVar<F>,
+		right: &FpVar<F>,
+	) -> Result<(FpVar<F>, FpVar<F>), SynthesisError> {
+		let rounds = self.round_keys.len();
+		let mut x_l = left.clone();
+		let mut x_r = right.clone();
+		for (i, c) in self.round_keys.iter().enumerate() {
+			let t = if i == 0 {
+				&self.k + &x_l
+			} else {
+				&self.k + &x_l + c
+			};
+			let t2 = t.square()?;
+			let t5 = t2.square()? * &t;
+
+			if i < rounds - 1 {
+				let temp_x_l = x_l;
+				x_l = x_r + t5;
+				x_r = temp_x_l;
+			} else {
+				x_r += t5;
+			}
+		}
+
+		Ok((x_l, x_r))
+	}
+}
+
+impl<F: PrimeField> FieldHasherGadget<F> for MiMCSpongeGadget<F> {
+	type Native = MiMCSponge<F>;
+
+	fn from_native(
+		cs: &mut ConstraintSystemRef<F>,
+		native: Self::Native,
+	) -> Result<Self, SynthesisError> {
+		let params = native.params;
+		assert!(params.rounds > 0 && params.round_keys.len() == params.rounds);
+
+		let k = FpVar::new_constant(cs.clone(), params.k)?;
+		let round_keys = params
+			.round_keys
+			.iter()
+			.map(|c| FpVar::new_constant(cs.clone(), c))
+			.collect::<Result<Vec<_>, _>>()?;
+		Ok(Self { k, round_keys })
+	}
+
+	fn hash(&self, inputs: &[FpVar<F>]) -> Result<FpVar<F>, SynthesisError> {
+		let mut x_l = FpVar::<F>::zero();
+		let mut x_r = FpVar::<F>::zero();
+		for input in inputs {
+			let (l, r) = self.feistel(&(x_l + input), &x_r)?;
+			x_l = l;
+			x_r = r;
+		}
+
+		Ok(x_l)
+	}
+
+	fn hash_two(&self, left: &FpVar<F>, right: &FpVar<F>) -> Result<FpVar<F>, SynthesisError> {
+		self.hash(&[left.clone(), right.clone()])
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use ark_ed_on_bn254::Fq;
+	use ark_ff::Zero;
+	use ark_relations::r1cs::ConstraintSystem;
+	use ark_std::{test_rng, UniformRand};
+	use arkworks_native_gadgets::{mimc::MiMCParameters, poseidon::FieldHasher};
+	use arkworks_utils::{bytes_vec_to_f, mimc_params::setup_mimc_params, Curve};
+
+	fn setup_mimc_sponge() -> MiMCSponge<Fq> {
+		let mimc_data = setup_mimc_params(Curve::Bn254, 220, 3).unwrap();
+		let round_keys = bytes_vec_to_f(&mimc_data.constants);
+		MiMCSponge::new(MiMCParameters::new(
+			Fq::zero(),
+			mimc_data.rounds as usize,
+			2,
+			1,
+			round_keys,
+		))
+	}
+
+	#[test]
+	fn should_match_native_mimc_sponge() {
+		let rng = &mut test_rng();
+		let mut cs = ConstraintSystem::<Fq>::new_ref();
+		let sponge = setup_mimc_sponge();
+		let sponge_gadget = MiMCSpongeGadget::from_native(&mut cs, sponge.clone()).unwrap();
+
+		let left = Fq::rand(rng);
+		let right = Fq::rand(rng);
+		let left_var = FpVar::new_witness(cs.clone(), || Ok(left)).unwrap();
+		let right_var = FpVar::new_witness(cs.clone(), || Ok(right)).unwrap();
+
+		let res = sponge.hash_two(&left, &right).unwrap();
+		let res_var = sponge_gadget.hash_two(&left_var, &right_var).unwrap();
+		assert_eq!(res, res_var.value().unwrap());
+
+		let inputs = [left, right, Fq::rand(rng)];
+		let inputs_var = Vec::<FpVar<Fq>>::new_witness(cs.clone(), || Ok(inputs)).unwrap();
+		let res = sponge.hash(&inputs).unwrap();
+		let res_var = sponge_gadget.hash(&inputs_var).unwrap();
+		assert_eq!(res, res_var.value().unwrap());
+		assert!(cs.is_satisfied().unwrap());
+	}
+}
@@ -0,0 +1,126 @@
+// This file is part of Webb.
+//
+// Copyright (C) 2021 Webb Technologies Inc.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A R1CS gadget of circomlib's Pedersen hash over Baby Jubjub, as computed by
+//! `arkworks_native_gadgets::pedersen`.
+//!
+//! The point `32^j * B_s` of every window is a constant, so the gadget looks
+//! up the multiple `1..8` of it selected by the first 3 bits of the window,
+//! negates it if the last bit is set, and adds up the points of all the
+//! windows.
+use ark_ed_on_bn254::{constraints::EdwardsVar, EdwardsAffine, Fq};
+use ark_ff::Field;
+use ark_r1cs_std::{fields::fp::FpVar, groups::CurveVar, prelude::*};
+use ark_relations::r1cs::SynthesisError;
+use ark_std::vec::Vec;
+use arkworks_native_gadgets::{
+	eddsa::SQRT_A,
+	pedersen::{window_multiples, BITS_PER_SEGMENT, MAX_BITS, WINDOW_SIZE},
+};
+
+pub struct PedersenHashGadget;
+
+impl PedersenHashGadget {
+	/// Computes the Pedersen hash of `bits`, in arkworks coordinates. Fails
+	/// with `SynthesisError::Unsatisfiable` unless there are between 1 and
+	/// `MAX_BITS` bits.
+	pub fn hash_point(bits: &[Boolean<Fq>]) -> Result<EdwardsVar, SynthesisError> {
+		if bits.is_empty() || bits.len() > MAX_BITS {
+			return Err(SynthesisError::Unsatisfiable);
+		}
+
+		let mut hash = EdwardsVar::zero();
+		for (segment, segment_bits) in bits.chunks(BITS_PER_SEGMENT).enumerate() {
+			for (window, window_bits) in segment_bits.chunks(WINDOW_SIZE).enumerate() {
+				// The missing bits of the last window are zeros
+				let bit = |i: usize| window_bits.get(i).cloned().unwrap_or(Boolean::FALSE);
+
+				let multiples = window_multiples(segment, window);
+				let (x, y) = Self::lookup(&[bit(0), bit(1), bit(2)], &multiples)?;
+				let x = bit(3).select(&x.negate()?, &x)?;
+				hash += EdwardsVar::new(x, y);
+			}
+		}
+
+		Ok(hash)
+	}
+
+	/// Computes the Pedersen hash of `bits`, and returns its x coordinate in
+	/// circomlib coordinates, like circomlib's `Pedersen`
+	pub fn hash(bits: &[Boolean<Fq>]) -> Result<FpVar<Fq>, SynthesisError> {
+		let hash = Self::hash_point(bits)?;
+		// SQRT_A is a non-zero constant, so the inverse always exists
+		let sqrt_a_inv = SQRT_A.inverse().unwrap();
+		Ok(hash.x * sqrt_a_inv)
+	}
+
+	/// Selects the point of `points` at the index given by the little-endian
+	/// `bits`, the points being constants
+	fn lookup(
+		bits: &[Boolean<Fq>],
+		points: &[EdwardsAffine],
+	) -> Result<(FpVar<Fq>, FpVar<Fq>), SynthesisError> {
+		let mut xs: Vec<FpVar<Fq>> = points.iter().map(|p| FpVar::constant(p.x)).collect();
+		let mut ys: Vec<FpVar<Fq>> = points.iter().map(|p| FpVar::constant(p.y)).collect();
+		for bit in bits {
+			xs = xs
+				.chunks(2)
+				.map(|pair| bit.select(&pair[1], &pair[0]))
+				.collect::<Result<_, _>>()?;
+			ys = ys
+				.chunks(2)
+				.map(|pair| bit.select(&pair[1], &pair[0]))
+				.collect::<Result<_, _>>()?;
+		}
+
+		Ok((xs[0].clone(), ys[0].clone()))
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use ark_relations::r1cs::ConstraintSystem;
+	use ark_std::{test_rng, UniformRand};
+	use arkworks_native_gadgets::pedersen::{bytes_to_bits_le, pedersen_hash_bytes};
+
+	#[test]
+	fn should_match_native_pedersen_hash() {
+		let rng = &mut test_rng();
+		// Lengths of the Tornado Cash nullifier and commitment preimages
+		for len in [31, 62].iter() {
+			let cs = ConstraintSystem::<Fq>::new_ref();
+			let bytes: Vec<u8> = (0..*len).map(|_| u8::rand(rng)).collect();
+			let bits = Vec::<Boolean<Fq>>::new_witness(cs.clone(), || Ok(bytes_to_bits_le(&bytes)))
+				.unwrap();
+
+			let hash = pedersen_hash_bytes(&bytes).unwrap();
+			let hash_var = PedersenHashGadget::hash(&bits).unwrap();
+			assert_eq!(hash, hash_var.value().unwrap());
+			assert!(cs.is_satisfied().unwrap());
+		}
+	}
+
+	#[test]
+	fn should_fail_with_invalid_input_length() {
+		let cs = ConstraintSystem::<Fq>::new_ref();
+		let bits = Vec::<Boolean<Fq>>::new_witness(cs, || Ok(vec![true; MAX_BITS + 1])).unwrap();
+
+		assert!(PedersenHashGadget::hash(&[]).is_err());
+		assert!(PedersenHashGadget::hash(&bits).is_err());
+	}
+}
@@ -0,0 +1,140 @@
+//! A R1CS gadget for Poseidon encryption.
+//!
+//! The gadget recomputes the ciphertext of a message, so a circuit can expose
+//! the ciphertext as a public input and prove that it encrypts values the
+//! circuit already constrains. For a description of the construction refer
+//! to [arkworks_native_gadgets::poseidon::encryption]
+
+use super::PoseidonGadget;
+use crate::{range::RangeGadget, Vec};
+use ark_ff::PrimeField;
+use ark_r1cs_std::{fields::fp::FpVar, prelude::*};
+use ark_relations::r1cs::SynthesisError;
+use arkworks_native_gadgets::poseidon::encryption::{
+	ciphertext_len, length_shift, NONCE_BITS, RATE, WIDTH,
+};
+
+/// Computes the ciphertext of `msg` under `key` and `nonce`, including the
+/// authentication tag. `hasher` must be the Poseidon hash of width 4, and the
+/// nonce is constrained to fit in 128 bits.
+pub fn encrypt<F: PrimeField>(
+	hasher: &PoseidonGadget<F>,
+	msg: &[FpVar<F>],
+	key: &(FpVar<F>, FpVar<F>),
+	nonce: &FpVar<F>,
+) -> Result<Vec<FpVar<F>>, SynthesisError> {
+	if hasher.params.width as usize != WIDTH {
+		return Err(SynthesisError::Unsatisfiable);
+	}
+	RangeGadget::new(NONCE_BITS).enforce_in_range(nonce)?;
+
+	let domain = nonce + FpVar::constant(F::from(msg.len() as u64) * length_shift::<F>());
+	let mut state = vec![FpVar::zero(), key.0.clone(), key.1.clone(), domain];
+
+	let mut padded = msg.to_vec();
+	padded.resize(ciphertext_len(msg.len()) - 1, FpVar::zero());
+
+	let mut ciphertext = Vec::with_capacity(padded.len() + 1);
+	for chunk in padded.chunks(RATE) {
+		state = hasher.permute(state)?;
+		for (i, m) in chunk.iter().enumerate() {
+			state[i + 1] += m;
+			ciphertext.push(state[i + 1].clone());
+		}
+	}
+
+	state = hasher.permute(state)?;
+	ciphertext.push(state[1].clone());
+	Ok(ciphertext)
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::poseidon::FieldHasherGadget;
+	use ark_ed_on_bn254::Fq;
+	use ark_relations::r1cs::ConstraintSystem;
+	use ark_std::{test_rng, UniformRand};
+	use arkworks_native_gadgets::poseidon::{
+		encryption, sbox::PoseidonSbox, Poseidon, PoseidonParameters,
+	};
+	use arkworks_utils::{
+		bytes_matrix_to_f, bytes_vec_to_f, poseidon_params::setup_poseidon_params, Curve,
+	};
+
+	fn setup_hasher(width: u8) -> Poseidon<Fq> {
+		let pos_data = setup_poseidon_params(Curve::Bn254, 5, width).unwrap();
+		Poseidon::new(PoseidonParameters {
+			mds_matrix: bytes_matrix_to_f(&pos_data.mds),
+			round_keys: bytes_vec_to_f(&pos_data.rounds),
+			full_rounds: pos_data.full_rounds,
+			partial_rounds: pos_data.partial_rounds,
+			sbox: PoseidonSbox(pos_data.exp),
+			width: pos_data.width,
+		})
+	}
+
+	#[test]
+	fn should_match_native_encryption() {
+		let rng = &mut test_rng();
+		let hasher = setup_hasher(4);
+		let key = (Fq::rand(rng), Fq::rand(rng));
+		let nonce = Fq::from(7u64);
+
+		for msg_len in 1..5 {
+			let msg = (0..msg_len).map(|_| Fq::rand(rng)).collect::<Vec<_>>();
+			let expected = encryption::encrypt(&hasher, &msg, &key, nonce).unwrap();
+
+			let mut cs = ConstraintSystem::<Fq>::new_ref();
+			let hasher_var = PoseidonGadget::from_native(&mut cs, hasher.clone()).unwrap();
+			let msg_var = Vec::<FpVar<Fq>>::new_witness(cs.clone(), || Ok(msg)).unwrap();
+			let key_var = (
+				FpVar::new_witness(cs.clone(), || Ok(key.0)).unwrap(),
+				FpVar::new_witness(cs.clone(), || Ok(key.1)).unwrap(),
+			);
+			let nonce_var = FpVar::new_input(cs.clone(), || Ok(nonce)).unwrap();
+			let expected_var = Vec::<FpVar<Fq>>::new_input(cs.clone(), || Ok(expected)).unwrap();
+
+			let ciphertext = encrypt(&hasher_var, &msg_var, &key_var, &nonce_var).unwrap();
+			ciphertext.enforce_equal(&expected_var).unwrap();
+			assert!(cs.is_satisfied().unwrap());
+		}
+	}
+
+	#[test]
+	fn should_not_match_other_message() {
+		let rng = &mut test_rng();
+		let hasher = setup_hasher(4);
+		let key = (Fq::rand(rng), Fq::rand(rng));
+		let nonce = Fq::from(7u64);
+		let msg = vec![Fq::from(1u64), Fq::from(2u64)];
+		let expected = encryption::encrypt(&hasher, &msg, &key, nonce).unwrap();
+
+		let mut cs = ConstraintSystem::<Fq>::new_ref();
+		let hasher_var = PoseidonGadget::from_native(&mut cs, hasher).unwrap();
+		let other_msg = vec![Fq::from(1u64), Fq::from(3u64)];
+		let msg_var = Vec::<FpVar<Fq>>::new_witness(cs.clone(), || Ok(other_msg)).unwrap();
+		let key_var = (
+			FpVar::new_witness(cs.clone(), || Ok(key.0)).unwrap(),
+			FpVar::new_witness(cs.clone(), || Ok(key.1)).unwrap(),
+		);
+		let nonce_var = FpVar::new_input(cs.clone(), || Ok(nonce)).unwrap();
+		let expected_var = Vec::<FpVar<Fq>>::new_input(cs.clone(), || Ok(expected)).unwrap();
+
+		let ciphertext = encrypt(&hasher_var, &msg_var, &key_var, &nonce_var).unwrap();
+		ciphertext.enforce_equal(&expected_var).unwrap();
+		assert!(!cs.is_satisfied().unwrap());
+	}
+
+	#[test]
+	fn should_reject_out_of_range_nonce() {
+		let mut cs = ConstraintSystem::<Fq>::new_ref();
+		let hasher_var = PoseidonGadget::from_native(&mut cs, setup_hasher(4)).unwrap();
+		let msg_var = vec![FpVar::new_witness(cs.clone(), || Ok(Fq::from(1u64))).unwrap()];
+		let key_var = (FpVar::one(), FpVar::one());
+		let nonce_var = FpVar::new_input(cs.clone(), || Ok(length_shift::<Fq>())).unwrap();
+
+		encrypt(&hasher_var, &msg_var, &key_var, &nonce_var).unwrap();
+		assert!(!cs.is_satisfied().unwrap());
+	}
+}
@@ -21,6 +21,7 @@ use core::{
 	ops::{Add, AddAssign, Mul},
 };
 
+pub mod encryption;
 pub mod sbox;
 use sbox::SboxConstraints;
 
@@ -0,0 +1,373 @@
+// This file is part of Webb.
+//
+// Copyright (C) 2021 Webb Technologies Inc.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A R1CS gadget to check that a field element lies in a bounded range.
+//!
+//! The value is decomposed into `num_bits` boolean witnesses and the
+//! recomposition of those bits is constrained to be equal to the value.
+//! Since `num_bits` is below the capacity of the field, the recomposition
+//! cannot wrap around the modulus, which proves `0 <= value < 2^num_bits`.
+//!
+//! A range check of `n` bits costs `n + 1` constraints: one booleanity
+//! constraint per bit and one equality constraint for the recomposition.
+//!
+//! On top of the range checks the gadget provides:
+//! * comparisons (`less_than`, `less_or_equal`) between values that are known
+//!   to lie below `2^num_bits`, built from a range check of the shifted
+//!   difference,
+//! * a signed amount decomposition, turning a field element in `[-2^num_bits,
+//!   2^num_bits)` into a sign and a magnitude.
+use crate::Vec;
+use ark_ff::{BigInteger, FpParameters, PrimeField};
+use ark_r1cs_std::{fields::fp::FpVar, prelude::*};
+use ark_relations::r1cs::SynthesisError;
+use ark_std::marker::PhantomData;
+
+/// A field element split into a sign and a magnitude, as returned by
+/// `RangeGadget::to_signed_amount`. The magnitude of a negative value is at
+/// least one, so zero has a single representation.
+#[derive(Debug, Clone)]
+pub struct SignedAmountVar<F: PrimeField> {
+	pub is_negative: Boolean<F>,
+	pub magnitude: FpVar<F>,
+}
+
+pub struct RangeGadget<F: PrimeField> {
+	num_bits: usize,
+	_field: PhantomData<F>,
+}
+
+impl<F: PrimeField> RangeGadget<F> {
+	/// Creates a range gadget for values of at most `num_bits` bits.
+	///
+	/// Panics if `num_bits` is not strictly smaller than the capacity of the
+	/// field, since the signed check needs one extra bit of headroom.
+	pub fn new(num_bits: usize) -> Self {
+		let capacity = <F::Params as FpParameters>::CAPACITY as usize;
+		assert!(
+			num_bits > 0 && num_bits < capacity,
+			"range of {} bits is not supported by a field of capacity {}",
+			num_bits,
+			capacity
+		);
+		Self {
+			num_bits,
+			_field: PhantomData,
+		}
+	}
+
+	pub fn num_bits(&self) -> usize {
+		self.num_bits
+	}
+
+	/// Decomposes `value` into `num_bits` little-endian bits and enforces
+	/// that the bits recompose to `value`.
+	pub fn to_bits_le(&self, value: &FpVar<F>) -> Result<Vec<Boolean<F>>, SynthesisError> {
+		Self::decompose(value, self.num_bits)
+	}
+
+	/// Enforces `0 <= value < 2^num_bits`
+	pub fn enforce_in_range(&self, value: &FpVar<F>) -> Result<(), SynthesisError> {
+		self.to_bits_le(value)?;
+		Ok(())
+	}
+
+	/// Enforces `-2^num_bits <= value < 2^num_bits`, where negative numbers
+	/// are represented by their additive inverse in the field.
+	///
+	/// The value is shifted by `2^num_bits` and the result is checked to fit
+	/// in `num_bits + 1` bits.
+	pub fn enforce_signed_in_range(&self, value: &FpVar<F>) -> Result<(), SynthesisError> {
+		let shift = F::from(2u64).pow(&[self.num_bits as u64]);
+		let shifted = value + FpVar::<F>::constant(shift);
+		Self::decompose(&shifted, self.num_bits + 1)?;
+		Ok(())
+	}
+
+	/// Outputs whether `a < b`. Both values must already be known to lie
+	/// below `2^num_bits`, otherwise the result is meaningless.
+	///
+	/// The difference `a - b` is shifted by `2^num_bits` and decomposed into
+	/// `num_bits + 1` bits, the top bit is zero iff `a < b`.
+	pub fn less_than(&self, a: &FpVar<F>, b: &FpVar<F>) -> Result<Boolean<F>, SynthesisError> {
+		let shift = F::from(2u64).pow(&[self.num_bits as u64]);
+		let shifted = a - b + FpVar::<F>::constant(shift);
+		let bits = Self::decompose(&shifted, self.num_bits + 1)?;
+		Ok(bits[self.num_bits].not())
+	}
+
+	/// Outputs whether `a <= b`, with the same requirements as `less_than`
+	pub fn less_or_equal(&self, a: &FpVar<F>, b: &FpVar<F>) -> Result<Boolean<F>, SynthesisError> {
+		Ok(self.less_than(b, a)?.not())
+	}
+
+	/// Enforces `a < b`, for values known to lie below `2^num_bits`
+	pub fn enforce_less_than(&self, a: &FpVar<F>, b: &FpVar<F>) -> Result<(), SynthesisError> {
+		let diff = b - a - FpVar::<F>::one();
+		Self::decompose(&diff, self.num_bits)?;
+		Ok(())
+	}
+
+	/// Enforces `a <= b`, for values known to lie below `2^num_bits`
+	pub fn enforce_less_or_equal(&self, a: &FpVar<F>, b: &FpVar<F>) -> Result<(), SynthesisError> {
+		Self::decompose(&(b - a), self.num_bits)?;
+		Ok(())
+	}
+
+	/// Splits `value` into a sign and a magnitude, and enforces
+	/// `-2^num_bits <= value < 2^num_bits`. Negative numbers are represented
+	/// by their additive inverse in the field.
+	///
+	/// Non-negative values have a magnitude in `[0, 2^num_bits)` and negative
+	/// values a magnitude in `[1, 2^num_bits]`, which is checked by a range
+	/// check of `magnitude - is_negative`.
+	pub fn to_signed_amount(&self, value: &FpVar<F>) -> Result<SignedAmountVar<F>, SynthesisError> {
+		let is_negative = if value.is_constant() {
+			Boolean::constant(Self::is_negative_native(value.value()?, self.num_bits))
+		} else {
+			let num_bits = self.num_bits;
+			Boolean::new_witness(ark_relations::ns!(value.cs(), "is_negative"), || {
+				value.value().map(|v| Self::is_negative_native(v, num_bits))
+			})?
+		};
+		let magnitude = is_negative.select(&value.negate()?, value)?;
+		Self::decompose(
+			&(&magnitude - &FpVar::<F>::from(is_negative.clone())),
+			self.num_bits,
+		)?;
+
+		Ok(SignedAmountVar {
+			is_negative,
+			magnitude,
+		})
+	}
+
+	// A value is negative when it doesn't fit in `num_bits` bits
+	fn is_negative_native(value: F, num_bits: usize) -> bool {
+		value.into_repr().num_bits() as usize > num_bits
+	}
+
+	fn decompose(value: &FpVar<F>, num_bits: usize) -> Result<Vec<Boolean<F>>, SynthesisError> {
+		// Constants are checked natively, there is nothing to allocate for them
+		if value.is_constant() {
+			let repr = value.value()?.into_repr();
+			if repr.num_bits() as usize > num_bits {
+				return Err(SynthesisError::Unsatisfiable);
+			}
+			return Ok((0..num_bits)
+				.map(|i| Boolean::constant(repr.get_bit(i)))
+				.collect());
+		}
+
+		let cs = value.cs();
+		let repr = value.value().map(|v| v.into_repr());
+		let mut bits = Vec::with_capacity(num_bits);
+		for i in 0..num_bits {
+			let bit =
+				Boolean::new_witness(ark_relations::ns!(cs, "bit"), || repr.map(|r| r.get_bit(i)))?;
+			bits.push(bit);
+		}
+
+		// The recomposition is a linear combination of the bits, so this is
+		// a single constraint
+		Boolean::le_bits_to_fp_var(&bits)?.enforce_equal(value)?;
+
+		Ok(bits)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use ark_bls12_381::Fr;
+	use ark_relations::r1cs::ConstraintSystem;
+
+	#[test]
+	fn should_verify_value_in_range() {
+		let cs = ConstraintSystem::<Fr>::new_ref();
+
+		let value_var = FpVar::<Fr>::new_witness(cs.clone(), || Ok(Fr::from(255u64))).unwrap();
+
+		let range_gadget = RangeGadget::new(8);
+		range_gadget.enforce_in_range(&value_var).unwrap();
+
+		assert!(cs.is_satisfied().unwrap());
+		assert_eq!(cs.num_constraints(), 8 + 1);
+	}
+
+	#[test]
+	fn should_not_verify_value_out_of_range() {
+		let cs = ConstraintSystem::<Fr>::new_ref();
+
+		let value_var = FpVar::<Fr>::new_witness(cs.clone(), || Ok(Fr::from(256u64))).unwrap();
+
+		let range_gadget = RangeGadget::new(8);
+		range_gadget.enforce_in_range(&value_var).unwrap();
+
+		assert!(!cs.is_satisfied().unwrap());
+	}
+
+	#[test]
+	fn should_not_verify_negative_value() {
+		let cs = ConstraintSystem::<Fr>::new_ref();
+
+		let value_var = FpVar::<Fr>::new_witness(cs.clone(), || Ok(-Fr::from(1u64))).unwrap();
+
+		let range_gadget = RangeGadget::new(64);
+		range_gadget.enforce_in_range(&value_var).unwrap();
+
+		assert!(!cs.is_satisfied().unwrap());
+	}
+
+	#[test]
+	fn should_verify_signed_value_in_range() {
+		let range_gadget = RangeGadget::new(8);
+
+		for value in [
+			-Fr::from(256u64),
+			-Fr::from(1u64),
+			Fr::from(0u64),
+			Fr::from(255u64),
+		] {
+			let cs = ConstraintSystem::<Fr>::new_ref();
+			let value_var = FpVar::<Fr>::new_witness(cs.clone(), || Ok(value)).unwrap();
+			range_gadget.enforce_signed_in_range(&value_var).unwrap();
+
+			assert!(cs.is_satisfied().unwrap());
+		}
+	}
+
+	#[test]
+	fn should_not_verify_signed_value_out_of_range() {
+		let range_gadget = RangeGadget::new(8);
+
+		for value in [-Fr::from(257u64), Fr::from(256u64)] {
+			let cs = ConstraintSystem::<Fr>::new_ref();
+			let value_var = FpVar::<Fr>::new_witness(cs.clone(), || Ok(value)).unwrap();
+			range_gadget.enforce_signed_in_range(&value_var).unwrap();
+
+			assert!(!cs.is_satisfied().unwrap());
+		}
+	}
+
+	#[test]
+	fn should_check_constants_natively() {
+		let range_gadget = RangeGadget::<Fr>::new(8);
+
+		let in_range = FpVar::<Fr>::constant(Fr::from(255u64));
+		assert!(range_gadget.enforce_in_range(&in_range).is_ok());
+
+		let out_of_range = FpVar::<Fr>::constant(Fr::from(256u64));
+		assert_eq!(
+			range_gadget.enforce_in_range(&out_of_range),
+			Err(SynthesisError::Unsatisfiable)
+		);
+	}
+
+	// Turns a small signed integer into a field element
+	fn signed(value: i64) -> Fr {
+		if value < 0 {
+			-Fr::from(value.unsigned_abs())
+		} else {
+			Fr::from(value as u64)
+		}
+	}
+
+	#[test]
+	fn should_check_every_value_in_range_exhaustively() {
+		let num_bits = 4;
+		let range_gadget = RangeGadget::<Fr>::new(num_bits);
+
+		for value in 0..(1u64 << (num_bits + 1)) {
+			let cs = ConstraintSystem::<Fr>::new_ref();
+			let value_var = FpVar::<Fr>::new_witness(cs.clone(), || Ok(Fr::from(value))).unwrap();
+			range_gadget.enforce_in_range(&value_var).unwrap();
+
+			assert_eq!(cs.is_satisfied().unwrap(), value < (1 << num_bits));
+		}
+	}
+
+	#[test]
+	fn should_compare_every_pair_exhaustively() {
+		let num_bits = 3;
+		let range_gadget = RangeGadget::<Fr>::new(num_bits);
+
+		for a in 0..(1u64 << num_bits) {
+			for b in 0..(1u64 << num_bits) {
+				let cs = ConstraintSystem::<Fr>::new_ref();
+				let a_var = FpVar::<Fr>::new_witness(cs.clone(), || Ok(Fr::from(a))).unwrap();
+				let b_var = FpVar::<Fr>::new_witness(cs.clone(), || Ok(Fr::from(b))).unwrap();
+
+				let lt = range_gadget.less_than(&a_var, &b_var).unwrap();
+				let le = range_gadget.less_or_equal(&a_var, &b_var).unwrap();
+				assert_eq!(lt.value().unwrap(), a < b);
+				assert_eq!(le.value().unwrap(), a <= b);
+				assert!(cs.is_satisfied().unwrap());
+
+				let cs = ConstraintSystem::<Fr>::new_ref();
+				let a_var = FpVar::<Fr>::new_witness(cs.clone(), || Ok(Fr::from(a))).unwrap();
+				let b_var = FpVar::<Fr>::new_witness(cs.clone(), || Ok(Fr::from(b))).unwrap();
+				range_gadget.enforce_less_than(&a_var, &b_var).unwrap();
+				assert_eq!(cs.is_satisfied().unwrap(), a < b);
+
+				let cs = ConstraintSystem::<Fr>::new_ref();
+				let a_var = FpVar::<Fr>::new_witness(cs.clone(), || Ok(Fr::from(a))).unwrap();
+				let b_var = FpVar::<Fr>::new_witness(cs.clone(), || Ok(Fr::from(b))).unwrap();
+				range_gadget.enforce_less_or_equal(&a_var, &b_var).unwrap();
+				assert_eq!(cs.is_satisfied().unwrap(), a <= b);
+			}
+		}
+	}
+
+	#[test]
+	fn should_split_every_signed_amount_exhaustively() {
+		let num_bits = 4;
+		let range_gadget = RangeGadget::<Fr>::new(num_bits);
+		let bound = 1i64 << num_bits;
+
+		for value in (-bound - 2)..(bound + 2) {
+			let cs = ConstraintSystem::<Fr>::new_ref();
+			let value_var = FpVar::<Fr>::new_witness(cs.clone(), || Ok(signed(value))).unwrap();
+			let amount = range_gadget.to_signed_amount(&value_var).unwrap();
+
+			let in_range = value >= -bound && value < bound;
+			assert_eq!(cs.is_satisfied().unwrap(), in_range);
+			if in_range {
+				assert_eq!(amount.is_negative.value().unwrap(), value < 0);
+				assert_eq!(
+					amount.magnitude.value().unwrap(),
+					Fr::from(value.unsigned_abs())
+				);
+			}
+		}
+	}
+
+	#[test]
+	fn should_split_constant_signed_amounts_natively() {
+		let range_gadget = RangeGadget::<Fr>::new(8);
+
+		let amount = range_gadget
+			.to_signed_amount(&FpVar::<Fr>::constant(-Fr::from(5u64)))
+			.unwrap();
+		assert!(amount.is_negative.is_constant());
+		assert!(amount.is_negative.value().unwrap());
+		assert_eq!(amount.magnitude.value().unwrap(), Fr::from(5u64));
+
+		let out_of_range = FpVar::<Fr>::constant(-Fr::from(257u64));
+		assert!(range_gadget.to_signed_amount(&out_of_range).is_err());
+	}
+}
@@ -6,29 +6,59 @@ use ark_r1cs_std::{
 	prelude::*,
 };
 use ark_relations::r1cs::SynthesisError;
+use ark_std::vec;
 
 pub struct SetGadget<F: PrimeField> {
 	set: Vec<FpVar<F>>,
+	enabled: Vec<Boolean<F>>,
 }
 
 impl<F: PrimeField> SetGadget<F> {
+	/// Creates a set where every slot takes part in the membership check
 	pub fn new(set: Vec<FpVar<F>>) -> Self {
-		Self { set }
+		let enabled = vec![Boolean::TRUE; set.len()];
+		Self { set, enabled }
+	}
+
+	/// Creates a set where a slot only takes part in the membership check
+	/// when the matching flag is true. Disabled slots contribute a factor of
+	/// one to the product, so no target can match them.
+	pub fn new_with_flags(
+		set: Vec<FpVar<F>>,
+		enabled: Vec<Boolean<F>>,
+	) -> Result<Self, SynthesisError> {
+		if set.len() != enabled.len() {
+			return Err(SynthesisError::Unsatisfiable);
+		}
+		Ok(Self { set, enabled })
+	}
+
+	/// Creates a set where slots holding zero are disabled. This lets a
+	/// fixed-size set of public inputs carry fewer items than it has slots,
+	/// by padding the unused slots with zeros.
+	pub fn new_with_zero_slots_disabled(set: Vec<FpVar<F>>) -> Result<Self, SynthesisError> {
+		let enabled = set
+			.iter()
+			.map(|item| item.is_neq(&FpVar::<F>::zero()))
+			.collect::<Result<Vec<Boolean<F>>, SynthesisError>>()?;
+		Self::new_with_flags(set, enabled)
 	}
 
 	/// Calculates product between (set_element - target) for every set_element.
 	/// Outputs 0 iff `set_element == target` for at least one element of the
 	/// set. This is an utility function to prove membership of a merkle_root in
-	/// a set of merkle_roots
+	/// a set of merkle_roots. Disabled slots are replaced by a factor of one.
 	pub fn calculate_product<T: ToBytesGadget<F>>(
 		&self,
 		target: &T,
 	) -> Result<FpVar<F>, SynthesisError> {
 		let target = Boolean::le_bits_to_fp_var(&target.to_bytes()?.to_bits_le()?)?;
+		let one = FpVar::<F>::one();
 		// Calculating the diffs inside the circuit
 		let mut diffs = Vec::new();
-		for item in &self.set {
-			diffs.push(item - &target);
+		for (item, enabled) in self.set.iter().zip(self.enabled.iter()) {
+			let diff = item - &target;
+			diffs.push(enabled.select(&diff, &one)?);
 		}
 
 		// Checking the membership
@@ -174,4 +204,65 @@ mod test {
 
 		assert!(cs.is_satisfied().unwrap());
 	}
+
+	#[test]
+	fn should_verify_set_membership_with_zero_slots_disabled() {
+		let cs = ConstraintSystem::<Fr>::new_ref();
+
+		// The last two slots are unused
+		let set = vec![
+			Fr::from(1u32),
+			Fr::from(2u32),
+			Fr::from(0u32),
+			Fr::from(0u32),
+		];
+		let target = Fr::from(2u32);
+		let target_var = FpVar::<Fr>::new_input(cs.clone(), || Ok(target)).unwrap();
+		let set_var = Vec::<FpVar<Fr>>::new_input(cs.clone(), || Ok(set)).unwrap();
+
+		let set_gadget = SetGadget::new_with_zero_slots_disabled(set_var).unwrap();
+		let is_member = set_gadget.check_membership(&target_var).unwrap();
+
+		is_member.enforce_equal(&Boolean::TRUE).unwrap();
+
+		assert!(cs.is_satisfied().unwrap());
+	}
+
+	#[test]
+	fn should_not_match_disabled_zero_slots() {
+		let cs = ConstraintSystem::<Fr>::new_ref();
+
+		// A zero target must not match the padding slots
+		let set = vec![Fr::from(1u32), Fr::from(2u32), Fr::from(0u32)];
+		let target = Fr::from(0u32);
+		let target_var = FpVar::<Fr>::new_input(cs.clone(), || Ok(target)).unwrap();
+		let set_var = Vec::<FpVar<Fr>>::new_input(cs.clone(), || Ok(set)).unwrap();
+
+		let set_gadget = SetGadget::new_with_zero_slots_disabled(set_var).unwrap();
+		let is_member = set_gadget.check_membership(&target_var).unwrap();
+
+		is_member.enforce_equal(&Boolean::TRUE).unwrap();
+
+		assert!(!cs.is_satisfied().unwrap());
+	}
+
+	#[test]
+	fn should_not_verify_membership_in_fully_disabled_set() {
+		let cs = ConstraintSystem::<Fr>::new_ref();
+
+		let set = vec![Fr::from(1u32), Fr::from(2u32)];
+		let target = Fr::from(1u32);
+		let target_var = FpVar::<Fr>::new_input(cs.clone(), || Ok(target)).unwrap();
+		let set_var = Vec::<FpVar<Fr>>::new_input(cs.clone(), || Ok(set)).unwrap();
+
+		let enabled_var =
+			Vec::<Boolean<Fr>>::new_witness(cs.clone(), || Ok(vec![false; 2])).unwrap();
+
+		let set_gadget = SetGadget::new_with_flags(set_var, enabled_var).unwrap();
+		let is_member = set_gadget.check_membership(&target_var).unwrap();
+
+		is_member.enforce_equal(&Boolean::TRUE).unwrap();
+
+		assert!(!cs.is_satisfied().unwrap());
+	}
 }
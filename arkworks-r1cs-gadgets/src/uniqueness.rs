@@ -0,0 +1,432 @@
+// This file is part of Webb.
+//
+// Copyright (C) 2021 Webb Technologies Inc.
+// SPDX-License-Identifier: Apache-2.0
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A R1CS gadget to check that all elements of a list are distinct.
+//!
+//! The pairwise check enforces `a_i != a_j` for every pair with an inverse
+//! witness, which costs `n * (n - 1) / 2` constraints.
+//!
+//! The sorted check takes the items in increasing order as a witness. It
+//! proves that the witness is a permutation of the items by routing the items
+//! through a Beneš network, where every switch is a boolean witness, and then
+//! checks that every item of the witness is strictly smaller than the next
+//! one. The network costs `O(n log n)` constraints, but every comparison needs
+//! a bit decomposition of the items, so the sorted check costs about
+//! `2 * F::size_in_bits()` constraints per item. It only pays off for lists
+//! of about a thousand items or more.
+//!
+//! Both checks leave the constraint system unsatisfied when two items are
+//! equal, rather than failing witness generation.
+use crate::Vec;
+use ark_ff::{BigInteger, PrimeField};
+use ark_r1cs_std::{fields::fp::FpVar, prelude::*};
+use ark_relations::r1cs::{ConstraintSystemRef, SynthesisError};
+
+/// A wire of the permutation network: an item and whether it is an actual
+/// item rather than padding
+type Wire<F> = (FpVar<F>, FpVar<F>);
+
+pub struct UniquenessGadget<F: PrimeField> {
+	items: Vec<FpVar<F>>,
+}
+
+impl<F: PrimeField> UniquenessGadget<F> {
+	pub fn new(items: Vec<FpVar<F>>) -> Self {
+		Self { items }
+	}
+
+	/// Enforces that all items are distinct by checking every pair separately.
+	pub fn enforce_unique_pairwise(&self) -> Result<(), SynthesisError> {
+		for i in 0..self.items.len() {
+			for j in (i + 1)..self.items.len() {
+				enforce_not_zero(&(&self.items[i] - &self.items[j]))?;
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Enforces that all items are distinct by checking that a sorted
+	/// permutation of them is strictly increasing.
+	pub fn enforce_unique_sorted(&self) -> Result<(), SynthesisError> {
+		let cs = self.items.cs();
+		// Constant items are checked natively
+		if cs.is_none() {
+			return self.enforce_unique_pairwise();
+		}
+		if self.items.len() < 2 {
+			return Ok(());
+		}
+
+		// The network permutes a power of two wires, the extra wires are
+		// padding and are routed after the items
+		let size = self.items.len().next_power_of_two();
+		let mut wires: Vec<Wire<F>> = self
+			.items
+			.iter()
+			.map(|item| (item.clone(), FpVar::one()))
+			.collect();
+		wires.resize(size, (FpVar::zero(), FpVar::zero()));
+
+		// The position of every wire after sorting, unknown when setting up keys
+		let perm = self.items.value().ok().map(|values| {
+			let mut order: Vec<usize> = (0..values.len()).collect();
+			order.sort_by_key(|&i| values[i].into_repr());
+			let mut perm: Vec<usize> = (0..size).collect();
+			for (position, &i) in order.iter().enumerate() {
+				perm[i] = position;
+			}
+			perm
+		});
+		let sorted = permute(&cs, &wires, perm.as_deref())?;
+		let (sorted, padding) = sorted.split_at(self.items.len());
+
+		// The padding holds no item, so the first wires hold all the items
+		if !padding.is_empty() {
+			let num_items = sorted
+				.iter()
+				.fold(FpVar::zero(), |sum, (_, is_item)| sum + is_item);
+			num_items.enforce_equal(&FpVar::Constant(F::from(sorted.len() as u64)))?;
+		}
+
+		// The items are decomposed into bits that may not be the canonical ones.
+		// Numbers that are strictly increasing and below the modulus are
+		// distinct field elements, so only the largest one is checked to be
+		// below the modulus.
+		let bits = sorted
+			.iter()
+			.map(|(item, _)| item.to_non_unique_bits_le())
+			.collect::<Result<Vec<_>, _>>()?;
+		if let Some(largest) = bits.last() {
+			Boolean::enforce_in_field_le(largest)?;
+		}
+
+		// The items are compared by their high and low halves
+		let num_low_bits = F::size_in_bits() / 2;
+		let num_high_bits = F::size_in_bits() - num_low_bits;
+		let halves = bits
+			.iter()
+			.map(|bits| {
+				let (low, high) = bits.split_at(num_low_bits);
+				Ok((
+					Boolean::le_bits_to_fp_var(high)?,
+					Boolean::le_bits_to_fp_var(low)?,
+				))
+			})
+			.collect::<Result<Vec<_>, SynthesisError>>()?;
+		for pair in halves.windows(2) {
+			let ((high_a, low_a), (high_b, low_b)) = (&pair[0], &pair[1]);
+			let is_high_smaller = is_smaller(high_a, high_b, num_high_bits)?;
+			let is_low_smaller = is_smaller(low_a, low_b, num_high_bits)?;
+			let is_smaller = is_high_smaller.or(&high_a.is_eq(high_b)?.and(&is_low_smaller)?)?;
+			is_smaller.enforce_equal(&Boolean::TRUE)?;
+		}
+
+		Ok(())
+	}
+}
+
+// Enforces that `value` is not zero with an inverse witness. A zero value gets
+// a zero inverse, which leaves the constraint system unsatisfied.
+fn enforce_not_zero<F: PrimeField>(value: &FpVar<F>) -> Result<(), SynthesisError> {
+	if let FpVar::Constant(value) = value {
+		return if value.is_zero() {
+			Err(SynthesisError::Unsatisfiable)
+		} else {
+			Ok(())
+		};
+	}
+
+	let inverse = FpVar::<F>::new_witness(value.cs(), || {
+		Ok(value.value()?.inverse().unwrap_or_else(F::zero))
+	})?;
+	value.mul_equals(&inverse, &FpVar::one())
+}
+
+// Whether `a` is smaller than `b`, both below `2^num_bits`. The number
+// `b - a - 1 + 2^num_bits` is below `2^(num_bits + 1)`, and its top bit is set
+// if and only if `a < b`.
+fn is_smaller<F: PrimeField>(
+	a: &FpVar<F>,
+	b: &FpVar<F>,
+	num_bits: usize,
+) -> Result<Boolean<F>, SynthesisError> {
+	let offset = F::from(2u64).pow([num_bits as u64]) - F::one();
+	let diff = b - a + offset;
+	if let FpVar::Constant(diff) = diff {
+		return Ok(Boolean::constant(diff.into_repr().get_bit(num_bits)));
+	}
+
+	let diff_bits = diff.value().ok().map(|diff| diff.into_repr().to_bits_le());
+	let bits = (0..=num_bits)
+		.map(|i| {
+			Boolean::new_witness(diff.cs(), || {
+				diff_bits
+					.as_ref()
+					.map(|bits| bits[i])
+					.ok_or(SynthesisError::AssignmentMissing)
+			})
+		})
+		.collect::<Result<Vec<_>, _>>()?;
+	Boolean::le_bits_to_fp_var(&bits)?.enforce_equal(&diff)?;
+
+	Ok(bits[num_bits].clone())
+}
+
+// Swaps the two wires if the `swap` witness is set
+fn switch<F: PrimeField>(
+	cs: &ConstraintSystemRef<F>,
+	first: &Wire<F>,
+	second: &Wire<F>,
+	swap: Option<bool>,
+) -> Result<(Wire<F>, Wire<F>), SynthesisError> {
+	let swap = Boolean::new_witness(cs.clone(), || swap.ok_or(SynthesisError::AssignmentMissing))?;
+	let item = swap.select(&second.0, &first.0)?;
+	let is_item = swap.select(&second.1, &first.1)?;
+	let other_item = &first.0 + &second.0 - &item;
+	let other_is_item = &first.1 + &second.1 - &is_item;
+
+	Ok(((item, is_item), (other_item, other_is_item)))
+}
+
+// Routes the wires through a Beneš network, so that wire `i` ends up at
+// position `perm[i]`. The number of wires must be a power of two.
+fn permute<F: PrimeField>(
+	cs: &ConstraintSystemRef<F>,
+	wires: &[Wire<F>],
+	perm: Option<&[usize]>,
+) -> Result<Vec<Wire<F>>, SynthesisError> {
+	if wires.len() == 1 {
+		return Ok(wires.to_vec());
+	}
+	if wires.len() == 2 {
+		let (first, second) = switch(cs, &wires[0], &wires[1], perm.map(|p| p[0] == 1))?;
+		return Ok(vec![first, second]);
+	}
+
+	let half = wires.len() / 2;
+	let routing = perm.map(route);
+
+	// The first column sends one wire of every switch to each subnetwork
+	let mut upper = Vec::with_capacity(half);
+	let mut lower = Vec::with_capacity(half);
+	for i in 0..half {
+		let swap = routing.as_ref().map(|r| r.is_lower[2 * i]);
+		let (first, second) = switch(cs, &wires[2 * i], &wires[2 * i + 1], swap)?;
+		upper.push(first);
+		lower.push(second);
+	}
+
+	let upper = permute(cs, &upper, routing.as_ref().map(|r| &r.upper_perm[..]))?;
+	let lower = permute(cs, &lower, routing.as_ref().map(|r| &r.lower_perm[..]))?;
+
+	// The last column takes one wire of every switch from each subnetwork
+	let mut outputs = Vec::with_capacity(wires.len());
+	for i in 0..half {
+		let swap = routing.as_ref().map(|r| r.is_lower[r.inverse[2 * i]]);
+		let (first, second) = switch(cs, &upper[i], &lower[i], swap)?;
+		outputs.push(first);
+		outputs.push(second);
+	}
+
+	Ok(outputs)
+}
+
+// The routing of a Beneš network between its two subnetworks
+struct Routing {
+	// Whether each wire goes through the lower subnetwork
+	is_lower: Vec<bool>,
+	// The wire that ends up at each position
+	inverse: Vec<usize>,
+	upper_perm: Vec<usize>,
+	lower_perm: Vec<usize>,
+}
+
+// Splits the wires between the subnetworks with the looping algorithm. The
+// two wires of a switch of the first column take different subnetworks, and
+// so do the two wires ending up at a switch of the last column.
+fn route(perm: &[usize]) -> Routing {
+	let mut inverse = vec![0; perm.len()];
+	for (i, &position) in perm.iter().enumerate() {
+		inverse[position] = i;
+	}
+
+	let mut is_lower: Vec<Option<bool>> = vec![None; perm.len()];
+	for start in (0..perm.len()).step_by(2) {
+		let mut i = start;
+		while is_lower[i].is_none() {
+			is_lower[i] = Some(false);
+			is_lower[i ^ 1] = Some(true);
+			// The wire sharing a last column switch with the one sent to the
+			// lower subnetwork goes through the upper one
+			i = inverse[perm[i ^ 1] ^ 1];
+		}
+	}
+	let is_lower: Vec<bool> = is_lower
+		.into_iter()
+		.map(|x| x.unwrap_or_default())
+		.collect();
+
+	let half = perm.len() / 2;
+	let mut upper_perm = vec![0; half];
+	let mut lower_perm = vec![0; half];
+	for (i, &position) in perm.iter().enumerate() {
+		if is_lower[i] {
+			lower_perm[i / 2] = position / 2;
+		} else {
+			upper_perm[i / 2] = position / 2;
+		}
+	}
+
+	Routing {
+		is_lower,
+		inverse,
+		upper_perm,
+		lower_perm,
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use ark_bls12_381::Fr;
+	use ark_relations::r1cs::{ConstraintSystem, SynthesisMode};
+	use ark_std::{test_rng, UniformRand};
+
+	fn alloc_items(cs: ConstraintSystemRef<Fr>, items: &[Fr]) -> Vec<FpVar<Fr>> {
+		Vec::<FpVar<Fr>>::new_input(cs, || Ok(items.to_vec())).unwrap()
+	}
+
+	#[test]
+	fn should_verify_unique_items() {
+		let rng = &mut test_rng();
+		for n in [1, 2, 3, 5, 8, 13] {
+			let items = (0..n).map(|_| Fr::rand(rng)).collect::<Vec<_>>();
+
+			let cs = ConstraintSystem::<Fr>::new_ref();
+			UniquenessGadget::new(alloc_items(cs.clone(), &items))
+				.enforce_unique_pairwise()
+				.unwrap();
+			assert!(cs.is_satisfied().unwrap());
+
+			let cs = ConstraintSystem::<Fr>::new_ref();
+			UniquenessGadget::new(alloc_items(cs.clone(), &items))
+				.enforce_unique_sorted()
+				.unwrap();
+			assert!(cs.is_satisfied().unwrap());
+		}
+	}
+
+	#[test]
+	fn should_not_verify_duplicate_items() {
+		let rng = &mut test_rng();
+		for n in [2, 3, 5, 8, 13] {
+			let mut items = (0..n).map(|_| Fr::rand(rng)).collect::<Vec<_>>();
+			items[n - 1] = items[(n - 1) / 2];
+
+			let cs = ConstraintSystem::<Fr>::new_ref();
+			UniquenessGadget::new(alloc_items(cs.clone(), &items))
+				.enforce_unique_pairwise()
+				.unwrap();
+			assert!(!cs.is_satisfied().unwrap());
+
+			let cs = ConstraintSystem::<Fr>::new_ref();
+			UniquenessGadget::new(alloc_items(cs.clone(), &items))
+				.enforce_unique_sorted()
+				.unwrap();
+			assert!(!cs.is_satisfied().unwrap());
+		}
+	}
+
+	#[test]
+	fn should_route_every_permutation() {
+		// All the orders of 4 items, and reversed orders of up to 16
+		let mut orders = Vec::new();
+		for a in 0..4u64 {
+			for b in 0..4u64 {
+				for c in 0..4u64 {
+					for d in 0..4u64 {
+						let order = [a, b, c, d];
+						if (0..4u64).all(|x| order.contains(&x)) {
+							orders.push(order.to_vec());
+						}
+					}
+				}
+			}
+		}
+		for n in [6u64, 7, 16] {
+			orders.push((0..n).rev().collect());
+		}
+
+		for order in orders {
+			let items = order.iter().map(|x| Fr::from(*x)).collect::<Vec<_>>();
+			let cs = ConstraintSystem::<Fr>::new_ref();
+			UniquenessGadget::new(alloc_items(cs.clone(), &items))
+				.enforce_unique_sorted()
+				.unwrap();
+			assert!(cs.is_satisfied().unwrap());
+		}
+	}
+
+	#[test]
+	fn should_not_depend_on_the_items_for_setup() {
+		let rng = &mut test_rng();
+		let items = (0..5).map(|_| Fr::rand(rng)).collect::<Vec<_>>();
+
+		let cs = ConstraintSystem::<Fr>::new_ref();
+		UniquenessGadget::new(alloc_items(cs.clone(), &items))
+			.enforce_unique_sorted()
+			.unwrap();
+
+		let setup_cs = ConstraintSystem::<Fr>::new_ref();
+		setup_cs.set_mode(SynthesisMode::Setup);
+		let items_var = (0..items.len())
+			.map(|_| {
+				FpVar::<Fr>::new_input(setup_cs.clone(), || {
+					Err::<Fr, _>(SynthesisError::AssignmentMissing)
+				})
+				.unwrap()
+			})
+			.collect();
+		UniquenessGadget::new(items_var)
+			.enforce_unique_sorted()
+			.unwrap();
+
+		assert_eq!(setup_cs.num_constraints(), cs.num_constraints());
+	}
+
+	#[test]
+	fn should_scale_sorted_check_linearithmically() {
+		let rng = &mut test_rng();
+		let mut counts = Vec::new();
+		for n in [64, 128, 256] {
+			let items = (0..n).map(|_| Fr::rand(rng)).collect::<Vec<_>>();
+			let cs = ConstraintSystem::<Fr>::new_ref();
+			UniquenessGadget::new(alloc_items(cs.clone(), &items))
+				.enforce_unique_sorted()
+				.unwrap();
+			assert!(cs.is_satisfied().unwrap());
+			counts.push(cs.num_constraints());
+		}
+
+		// Doubling the items doubles the comparisons and adds a network layer,
+		// while the pairwise check would quadruple
+		for (n, pair) in [64, 128].iter().zip(counts.windows(2)) {
+			assert!(pair[1] < 2 * pair[0] + 8 * n);
+		}
+	}
+}
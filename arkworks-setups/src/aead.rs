@@ -1,6 +1,6 @@
 use crate::{
 	keypair::{Keypair, KeypairError},
-	utxo::{Utxo, UtxoError},
+	utxo::{MaspUtxo, Utxo, UtxoError},
 };
 use ark_crypto_primitives::Error;
 use ark_ff::{to_bytes, PrimeField};
@@ -173,6 +173,32 @@ impl<F: PrimeField> Utxo<F> {
 	}
 }
 
+impl<F: PrimeField> MaspUtxo<F> {
+	pub fn encrypt<R: RngCore + CryptoRng>(&self, rng: &mut R) -> Result<Vec<u8>, Error> {
+		// We are encrypting the asset id along with the amount and the blinding
+		let msg = to_bytes![self.chain_id, self.asset_id, self.amount, self.blinding]?;
+		// Encrypting the message
+		let enc_data = self.keypair.encrypt(&msg, rng)?;
+
+		Ok(enc_data.encode())
+	}
+
+	pub fn decrypt(&self, data: &[u8]) -> Result<(Vec<u8>, Vec<u8>, Vec<u8>, Vec<u8>), Error> {
+		let decoded_ed = EncryptedData::decode(&mut &data[..])
+			.map_err(|_| UtxoError::EncryptedDataDecodeError)?;
+		// Decrypting the message
+		let plaintext = self.keypair.decrypt(&decoded_ed)?;
+
+		// Chain id, asset id, amount and blinding, 32 bytes each
+		let chain_id = plaintext[..32].to_vec();
+		let asset_id = plaintext[32..64].to_vec();
+		let amount = plaintext[64..96].to_vec();
+		let blinding = plaintext[96..128].to_vec();
+
+		Ok((chain_id, asset_id, amount, blinding))
+	}
+}
+
 #[cfg(test)]
 mod test {
 	use crate::common::setup_params;
@@ -184,7 +210,10 @@ mod test {
 	use codec::{Decode, Encode};
 
 	use super::EncryptedData;
-	use crate::{keypair::Keypair, utxo::Utxo};
+	use crate::{
+		keypair::Keypair,
+		utxo::{MaspUtxo, Utxo},
+	};
 
 	#[test]
 	fn should_encrypt_decrypt() {
@@ -261,4 +290,43 @@ mod test {
 		assert_eq!(amount_bytes, amount.into_repr().to_bytes_be());
 		assert_eq!(blinding_bytes, blinding.into_repr().to_bytes_be());
 	}
+
+	#[test]
+	fn test_masp_utxo_encrypt() {
+		let curve = Curve::Bn254;
+		let params2 = setup_params::<Fr>(curve, 5, 2);
+		let params6 = setup_params::<Fr>(curve, 5, 6);
+		let poseidon2 = Poseidon::new(params2);
+		let poseidon6 = Poseidon::new(params6);
+
+		let rng = &mut test_rng();
+
+		let chain_id_raw = 0u64;
+		let asset_id_raw = 7u64;
+		let amount = Fr::rand(rng);
+		let blinding = Fr::rand(rng);
+		let utxo = MaspUtxo::new(
+			chain_id_raw,
+			asset_id_raw,
+			amount,
+			None,
+			None,
+			Some(blinding),
+			&poseidon2,
+			&poseidon6,
+			rng,
+		)
+		.unwrap();
+
+		let encrypted_data = utxo.encrypt(rng).unwrap();
+		let (chain_id_bytes, asset_id_bytes, amount_bytes, blinding_bytes) =
+			utxo.decrypt(&encrypted_data).unwrap();
+
+		// The fields are encrypted as little-endian bytes
+
+		assert_eq!(chain_id_bytes, utxo.chain_id.into_repr().to_bytes_le());
+		assert_eq!(asset_id_bytes, utxo.asset_id.into_repr().to_bytes_le());
+		assert_eq!(amount_bytes, amount.into_repr().to_bytes_le());
+		assert_eq!(blinding_bytes, blinding.into_repr().to_bytes_le());
+	}
 }
@@ -10,14 +10,18 @@ use ark_std::{
 	vec::Vec,
 };
 use arkworks_native_gadgets::{
-	merkle_tree::{Path, SparseMerkleTree},
+	merkle_tree::{MerkleError, Path, SparseMerkleTree},
+	mimc::MiMCParameters,
 	poseidon::{sbox::PoseidonSbox, FieldHasher, PoseidonParameters},
 };
 use arkworks_utils::{
-	bytes_matrix_to_f, bytes_vec_to_f, poseidon_params::setup_poseidon_params, Curve,
+	bytes_matrix_to_f, bytes_vec_to_f, mimc_params::setup_mimc_params,
+	poseidon_params::setup_poseidon_params, Curve,
 };
 use tiny_keccak::{Hasher, Keccak};
 
+use crate::public_inputs::PublicInputs;
+
 pub struct VAnchorLeaf {
 	pub chain_id_bytes: Vec<u8>,
 	pub amount: u128,
@@ -43,6 +47,18 @@ pub struct VAnchorProof {
 	pub public_inputs_raw: Vec<Vec<u8>>,
 }
 
+pub struct SwapProof {
+	pub proof: Vec<u8>,
+	pub order_hashes_raw: Vec<Vec<u8>>,
+	pub public_inputs_raw: Vec<Vec<u8>>,
+}
+
+pub struct BalanceThresholdProof {
+	pub proof: Vec<u8>,
+	pub scoped_nullifiers_raw: Vec<Vec<u8>>,
+	pub public_inputs_raw: Vec<Vec<u8>>,
+}
+
 pub struct AnchorProof {
 	pub proof: Vec<u8>,
 	pub leaf_raw: Vec<u8>,
@@ -51,6 +67,44 @@ pub struct AnchorProof {
 	pub public_inputs_raw: Vec<Vec<u8>>,
 }
 
+pub struct AnchorMigrationProof {
+	pub proof: Vec<u8>,
+	pub nullifier_hash_raw: Vec<u8>,
+	pub commitment_raw: Vec<u8>,
+	pub roots_raw: Vec<Vec<u8>>,
+	pub public_inputs_raw: Vec<Vec<u8>>,
+}
+
+pub struct CredentialProof {
+	pub proof: Vec<u8>,
+	pub root_raw: Vec<u8>,
+	pub public_inputs_raw: Vec<Vec<u8>>,
+}
+
+pub struct LegacyImportProof {
+	pub proof: Vec<u8>,
+	pub nullifier_hash_raw: Vec<u8>,
+	pub commitment_raw: Vec<u8>,
+	pub root_raw: Vec<u8>,
+	pub public_inputs_raw: Vec<Vec<u8>>,
+}
+
+#[derive(Clone)]
+pub struct VotingProof {
+	pub proof: Vec<u8>,
+	pub poll_nullifier_raw: Vec<u8>,
+	pub root_raw: Vec<u8>,
+	pub public_inputs_raw: Vec<Vec<u8>>,
+}
+
+pub struct OwnershipProof {
+	pub proof: Vec<u8>,
+	pub leaf_raw: Vec<u8>,
+	pub scoped_nullifier_raw: Vec<u8>,
+	pub roots_raw: Vec<Vec<u8>>,
+	pub public_inputs_raw: Vec<Vec<u8>>,
+}
+
 pub struct MixerProof {
 	pub proof: Vec<u8>,
 	pub leaf_raw: Vec<u8>,
@@ -59,11 +113,66 @@ pub struct MixerProof {
 	pub public_inputs_raw: Vec<Vec<u8>>,
 }
 
+pub struct BatchInsertProof {
+	pub proof: Vec<u8>,
+	pub old_root_raw: Vec<u8>,
+	pub new_root_raw: Vec<u8>,
+	pub leaves_hash_raw: Vec<u8>,
+	pub public_inputs_raw: Vec<Vec<u8>>,
+}
+
+pub struct Identity {
+	pub identity_nullifier_bytes: Vec<u8>,
+	pub identity_trapdoor_bytes: Vec<u8>,
+	pub commitment_bytes: Vec<u8>,
+}
+
+pub struct SemaphoreProof {
+	pub proof: Vec<u8>,
+	pub nullifier_hash_raw: Vec<u8>,
+	pub root_raw: Vec<u8>,
+	pub signal_hash_raw: Vec<u8>,
+	pub public_inputs_raw: Vec<Vec<u8>>,
+}
+
+pub struct RlnIdentity {
+	pub secret_bytes: Vec<u8>,
+	pub commitment_bytes: Vec<u8>,
+}
+
+pub struct RlnProof {
+	pub proof: Vec<u8>,
+	pub root_raw: Vec<u8>,
+	pub epoch_raw: Vec<u8>,
+	pub x_raw: Vec<u8>,
+	pub y_raw: Vec<u8>,
+	pub nullifier_raw: Vec<u8>,
+	pub public_inputs_raw: Vec<Vec<u8>>,
+}
+
 pub struct Keys {
 	pub pk: Vec<u8>,
 	pub vk: Vec<u8>,
 }
 
+#[derive(Debug)]
+pub enum RootSetError {
+	EmptyRootSet,
+	TooManyRoots,
+}
+
+impl core::fmt::Display for RootSetError {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		let msg = match self {
+			Self::EmptyRootSet => ark_std::format!("Root set is empty"),
+			Self::TooManyRoots => ark_std::format!("Root set has more roots than the circuit"),
+		};
+		write!(f, "{}", msg)
+	}
+}
+
+impl ark_std::error::Error for RootSetError {}
+
 pub fn setup_keys<E: PairingEngine, R: RngCore + CryptoRng, C: ConstraintSynthesizer<E::Fr>>(
 	circuit: C,
 	rng: &mut R,
@@ -158,6 +267,15 @@ pub fn verify_unchecked_raw<E: PairingEngine>(
 	verify_groth16(&vk, &pub_ins, &proof)
 }
 
+/// Same as `verify_unchecked`, with the public inputs typed by their circuit
+pub fn verify_unchecked_typed<E: PairingEngine, P: PublicInputs<E::Fr>>(
+	public_inputs: &P,
+	vk_unchecked_bytes: &[u8],
+	proof: &[u8],
+) -> Result<bool, Error> {
+	verify_unchecked::<E>(&public_inputs.to_field_vec(), vk_unchecked_bytes, proof)
+}
+
 pub fn verify_groth16<E: PairingEngine>(
 	vk: &VerifyingKey<E>,
 	public_inputs: &[E::Fr],
@@ -206,6 +324,53 @@ pub fn setup_tree_and_create_path<F: PrimeField, H: FieldHasher<F>, const HEIGHT
 	Ok((smt, path))
 }
 
+/// Creates the path of the leaf at `index` in a tree of the largest height
+/// `HEIGHT`, along with the root of the tree of height `depth` holding the
+/// same leaves. Used by the dynamic depth circuits, which ignore the levels
+/// of the path above `depth`.
+pub fn setup_tree_and_create_path_at_depth<
+	F: PrimeField,
+	H: FieldHasher<F>,
+	const HEIGHT: usize,
+>(
+	hasher: &H,
+	leaves: &[F],
+	index: u64,
+	depth: usize,
+	default_leaf: &[u8],
+) -> Result<(F, Path<F, H, HEIGHT>), Error> {
+	// The tree of height `depth` must be able to hold all the leaves
+	let capacity = 1u64.checked_shl(depth as u32).unwrap_or(u64::MAX);
+	if depth == 0 || depth > HEIGHT || leaves.len() as u64 > capacity {
+		return Err(MerkleError::InvalidDepth.into());
+	}
+	let leaf = leaves.get(index as usize).ok_or(MerkleError::InvalidLeaf)?;
+
+	let (_, path) =
+		setup_tree_and_create_path::<F, H, HEIGHT>(hasher, leaves, index, default_leaf)?;
+	let root = path.calculate_root_at_depth(leaf, depth, hasher)?;
+	Ok((root, path))
+}
+
+/// Converts a root set of up to `ANCHOR_CT` roots into the fixed-size set of
+/// the circuits. The unused slots are filled with zeros, which the circuits
+/// treat as disabled.
+pub fn pad_root_set<F: PrimeField, const ANCHOR_CT: usize>(
+	roots: &[Vec<u8>],
+) -> Result<[F; ANCHOR_CT], Error> {
+	if roots.is_empty() {
+		return Err(RootSetError::EmptyRootSet.into());
+	}
+	if roots.len() > ANCHOR_CT {
+		return Err(RootSetError::TooManyRoots.into());
+	}
+	let mut root_set = [F::zero(); ANCHOR_CT];
+	for (slot, root) in root_set.iter_mut().zip(roots) {
+		*slot = F::from_be_bytes_mod_order(root);
+	}
+	Ok(root_set)
+}
+
 pub fn setup_params<F: PrimeField>(curve: Curve, exp: i8, width: u8) -> PoseidonParameters<F> {
 	let pos_data = setup_poseidon_params(curve, exp, width).unwrap();
 
@@ -223,3 +388,12 @@ pub fn setup_params<F: PrimeField>(curve: Curve, exp: i8, width: u8) -> Poseidon
 
 	pos
 }
+
+/// Parameters of circomlib's MiMCSponge with 220 rounds, the hash of the
+/// Tornado Cash Merkle trees
+pub fn setup_mimc_sponge_params<F: PrimeField>(curve: Curve) -> MiMCParameters<F> {
+	let mimc_data = setup_mimc_params(curve, 220, 3).unwrap();
+	let round_keys = bytes_vec_to_f(&mimc_data.constants);
+
+	MiMCParameters::new(F::zero(), mimc_data.rounds as usize, 2, 1, round_keys)
+}
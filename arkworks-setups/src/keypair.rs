@@ -1,7 +1,8 @@
 use ark_crypto_primitives::Error;
-use ark_ff::PrimeField;
-use ark_std::{error::Error as ArkError, marker::PhantomData, string::ToString};
-use arkworks_native_gadgets::poseidon::FieldHasher;
+use ark_ed_on_bn254::Fq;
+use ark_ff::{PrimeField, Zero};
+use ark_std::{error::Error as ArkError, marker::PhantomData, string::ToString, vec::Vec};
+use arkworks_native_gadgets::{eddsa::PublicKey, poseidon::FieldHasher};
 
 #[derive(Debug)]
 pub enum KeypairError {
@@ -63,6 +64,12 @@ impl<F: PrimeField, H: FieldHasher<F>> Keypair<F, H> {
 
 	// Computes the signature = hash(secret_key, commitment, pathIndices)
 	// If the secret_key is not configured on this Keypair, return an error
+	//
+	// Despite its name, this value is not a signature anyone can check: it
+	// is the keyed hash used to derive VAnchor nullifiers, and computing it
+	// requires the secret key. Spend authorization that can be produced by an
+	// external signer uses the EdDSA-Poseidon signatures of
+	// `arkworks_native_gadgets::eddsa` instead.
 	pub fn signature(&self, commitment: &F, index: &F, hasher4: &H) -> Result<F, Error> {
 		let res = hasher4.hash(&[self.secret_key.unwrap(), commitment.clone(), index.clone()])?;
 		Ok(res)
@@ -78,6 +85,106 @@ impl<F: PrimeField, H: FieldHasher<F>> Clone for Keypair<F, H> {
 	}
 }
 
+#[derive(Debug)]
+pub enum MultisigError {
+	InvalidThreshold,
+	DuplicateSigner,
+	MissingNullifierKey,
+}
+
+impl core::fmt::Display for MultisigError {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		let msg = match self {
+			MultisigError::InvalidThreshold => {
+				"Threshold must be between one and the number of signers".to_string()
+			}
+			MultisigError::DuplicateSigner => "Signer appears more than once".to_string(),
+			MultisigError::MissingNullifierKey => "Nullifier key not set".to_string(),
+		};
+		write!(f, "{}", msg)
+	}
+}
+
+impl ArkError for MultisigError {}
+
+/// The spend authority of a multi-signature Utxo: any `threshold` of the
+/// EdDSA `signers` can authorize a spend by signing the message of the
+/// transaction, see `VAnchorMultisigR1CSProver::create_message`. The public key
+/// is `Hash(keys_hash, threshold, nullifier_key)`, see `MultisigKeypair::
+/// keys_hash`.
+///
+/// The nullifier key takes the place of the secret key of a `Keypair` in the
+/// nullifiers. It is shared with whoever builds the proofs, but can't move the
+/// funds without the signatures, so the EdDSA keys never leave the signers.
+#[derive(Clone, Debug)]
+pub struct MultisigKeypair {
+	pub signers: Vec<PublicKey>,
+	pub threshold: u64,
+	pub nullifier_key: Option<Fq>,
+	pub public_key: Fq,
+}
+
+impl MultisigKeypair {
+	/// Creates the keypair of `threshold`-of-`signers.len()` signatures.
+	/// `hasher4` must be the Poseidon hash of width 4.
+	pub fn new<H: FieldHasher<Fq>>(
+		signers: Vec<PublicKey>,
+		threshold: u64,
+		nullifier_key: Fq,
+		hasher4: &H,
+	) -> Result<Self, Error> {
+		if threshold == 0 || threshold > signers.len() as u64 {
+			return Err(MultisigError::InvalidThreshold.into());
+		}
+		let is_duplicate = signers
+			.iter()
+			.enumerate()
+			.any(|(i, x)| signers[..i].contains(x));
+		if is_duplicate {
+			return Err(MultisigError::DuplicateSigner.into());
+		}
+
+		let keys_hash = Self::keys_hash(&signers, hasher4)?;
+		let public_key = hasher4.hash(&[keys_hash, Fq::from(threshold), nullifier_key])?;
+		Ok(Self {
+			signers,
+			threshold,
+			nullifier_key: Some(nullifier_key),
+			public_key,
+		})
+	}
+
+	/// Commits to the ordered signers, folding `acc = Hash(acc, x, y)` over
+	/// their public keys from `acc = 0`
+	pub fn keys_hash<H: FieldHasher<Fq>>(signers: &[PublicKey], hasher4: &H) -> Result<Fq, Error> {
+		let mut acc = Fq::zero();
+		for signer in signers {
+			acc = hasher4.hash(&[acc, signer.x, signer.y])?;
+		}
+		Ok(acc)
+	}
+
+	/// Position of `signer` in the signers, which is the slot of its signature
+	pub fn signer_index(&self, signer: &PublicKey) -> Option<usize> {
+		self.signers.iter().position(|x| x == signer)
+	}
+
+	// Computes the keyed hash of the nullifier, like `Keypair::signature` with
+	// the nullifier key in place of the secret key
+	pub fn signature<H: FieldHasher<Fq>>(
+		&self,
+		commitment: &Fq,
+		index: &Fq,
+		hasher4: &H,
+	) -> Result<Fq, Error> {
+		let nullifier_key = self
+			.nullifier_key
+			.ok_or(MultisigError::MissingNullifierKey)?;
+		let res = hasher4.hash(&[nullifier_key, *commitment, *index])?;
+		Ok(res)
+	}
+}
+
 #[cfg(test)]
 mod test {
 	use crate::common::setup_params;
@@ -18,6 +18,7 @@ pub mod aead;
 
 pub mod common;
 pub mod keypair;
+pub mod public_inputs;
 pub mod utxo;
 
 #[cfg(feature = "r1cs")]
@@ -69,7 +70,7 @@ pub trait AnchorProver<E: PairingEngine, const HEIGHT: usize, const ANCHOR_CT: u
 		chain_id: u64,
 		rng: &mut R,
 	) -> Result<Leaf, Error>;
-	// For making proofs
+	// For making proofs, the root set may hold up to ANCHOR_CT roots
 	fn create_proof<R: RngCore + CryptoRng>(
 		curve: Curve,
 		chain_id: u64,
@@ -77,7 +78,7 @@ pub trait AnchorProver<E: PairingEngine, const HEIGHT: usize, const ANCHOR_CT: u
 		nullifier: Vec<u8>,
 		leaves: Vec<Vec<u8>>,
 		index: u64,
-		root_set: [Vec<u8>; ANCHOR_CT],
+		root_set: Vec<Vec<u8>>,
 		recipient: Vec<u8>,
 		relayer: Vec<u8>,
 		fee: u128,
@@ -144,20 +145,23 @@ pub trait VAnchorProver<
 		public_key: Vec<u8>,
 		index: Option<u64>,
 	) -> Result<Utxo<E::Fr>, Error>;
-	/// For making proofs
+	/// For making proofs, the root set may hold up to ANCHOR_CT roots
+	#[allow(clippy::too_many_arguments)]
 	fn create_proof<R: RngCore + CryptoRng>(
 		curve: Curve,
 		chain_id: u64,
 		// External data
 		public_amount: i128,
 		ext_data_hash: Vec<u8>,
-		in_root_set: [Vec<u8>; ANCHOR_CT],
+		in_root_set: Vec<Vec<u8>>,
 		in_indices: [u64; INS],
 		in_leaves: BTreeMap<u64, Vec<Vec<u8>>>,
 		// Input transactions
 		in_utxos: [Utxo<E::Fr>; INS],
 		// Output transactions
 		out_utxos: [Utxo<E::Fr>; OUTS],
+		// Bit width of the amounts the circuit was set up with
+		amount_bits: usize,
 		pk: Vec<u8>,
 		default_leaf: [u8; 32],
 		rng: &mut R,
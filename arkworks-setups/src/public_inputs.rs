@@ -0,0 +1,771 @@
+//! Typed public inputs of the circuits with a prover in this crate, so that
+//! their order lives in one place instead of in every prover and verifier.
+use ark_crypto_primitives::Error;
+use ark_ff::{BigInteger, PrimeField};
+use ark_std::{error::Error as ArkError, string::String, vec, vec::Vec};
+use arkworks_utils::{decode_hex, encode_hex};
+
+#[derive(Debug)]
+pub enum PublicInputsError {
+	InvalidLength { expected: usize, actual: usize },
+	InvalidHex,
+}
+
+impl core::fmt::Display for PublicInputsError {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		let msg = match self {
+			PublicInputsError::InvalidLength { expected, actual } => {
+				ark_std::format!("Expected {} public inputs, found {}", expected, actual)
+			}
+			PublicInputsError::InvalidHex => ark_std::format!("Invalid hex public input"),
+		};
+		write!(f, "{}", msg)
+	}
+}
+
+impl ArkError for PublicInputsError {}
+
+/// The public inputs of a circuit, in the order the circuit allocates them
+pub trait PublicInputs<F: PrimeField>: Sized {
+	/// Number of public inputs of the circuit
+	const LEN: usize;
+
+	fn to_field_vec(&self) -> Vec<F>;
+
+	/// Builds the inputs from a slice already known to hold `LEN` elements
+	fn from_field_slice_unchecked(inputs: &[F]) -> Self;
+
+	fn from_field_slice(inputs: &[F]) -> Result<Self, Error> {
+		if inputs.len() != Self::LEN {
+			return Err(PublicInputsError::InvalidLength {
+				expected: Self::LEN,
+				actual: inputs.len(),
+			}
+			.into());
+		}
+		Ok(Self::from_field_slice_unchecked(inputs))
+	}
+
+	/// Big endian encoding of every input, the format of `public_inputs_raw`
+	fn to_bytes_vec(&self) -> Vec<Vec<u8>> {
+		self.to_field_vec()
+			.iter()
+			.map(|x| x.into_repr().to_bytes_be())
+			.collect()
+	}
+
+	fn from_bytes_slice(inputs: &[Vec<u8>]) -> Result<Self, Error> {
+		let inputs_f: Vec<F> = inputs
+			.iter()
+			.map(|x| F::from_be_bytes_mod_order(x))
+			.collect();
+		Self::from_field_slice(&inputs_f)
+	}
+
+	/// `0x`-prefixed hex encoding of every input
+	fn to_hex_vec(&self) -> Vec<String> {
+		self.to_bytes_vec().iter().map(|x| encode_hex(x)).collect()
+	}
+
+	fn from_hex_slice<S: AsRef<str>>(inputs: &[S]) -> Result<Self, Error> {
+		let inputs_bytes = inputs
+			.iter()
+			.map(|x| {
+				let x = x.as_ref();
+				if !x.starts_with("0x") {
+					return Err(PublicInputsError::InvalidHex);
+				}
+				decode_hex(x).map_err(|_| PublicInputsError::InvalidHex)
+			})
+			.collect::<Result<Vec<Vec<u8>>, PublicInputsError>>()?;
+		Self::from_bytes_slice(&inputs_bytes)
+	}
+}
+
+/// Public inputs of the Mixer circuit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MixerPublicInputs<F: PrimeField> {
+	pub nullifier_hash: F,
+	pub root: F,
+	pub arbitrary_input: F,
+}
+
+impl<F: PrimeField> PublicInputs<F> for MixerPublicInputs<F> {
+	const LEN: usize = 3;
+
+	fn to_field_vec(&self) -> Vec<F> {
+		vec![self.nullifier_hash, self.root, self.arbitrary_input]
+	}
+
+	fn from_field_slice_unchecked(inputs: &[F]) -> Self {
+		Self {
+			nullifier_hash: inputs[0],
+			root: inputs[1],
+			arbitrary_input: inputs[2],
+		}
+	}
+}
+
+/// Public inputs of the Anchor circuit, with a root set of `M` roots
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AnchorPublicInputs<F: PrimeField, const M: usize> {
+	pub nullifier_hash: F,
+	pub arbitrary_input: F,
+	pub chain_id: F,
+	pub roots: [F; M],
+}
+
+impl<F: PrimeField, const M: usize> PublicInputs<F> for AnchorPublicInputs<F, M> {
+	const LEN: usize = 3 + M;
+
+	fn to_field_vec(&self) -> Vec<F> {
+		let mut inputs = vec![self.nullifier_hash, self.arbitrary_input, self.chain_id];
+		inputs.extend(self.roots);
+		inputs
+	}
+
+	fn from_field_slice_unchecked(inputs: &[F]) -> Self {
+		let mut roots = [F::zero(); M];
+		roots.copy_from_slice(&inputs[3..]);
+		Self {
+			nullifier_hash: inputs[0],
+			arbitrary_input: inputs[1],
+			chain_id: inputs[2],
+			roots,
+		}
+	}
+}
+
+/// Public inputs of the VAnchor circuit with `INS` inputs, `OUTS` outputs and
+/// a root set of `M` roots
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VAnchorPublicInputs<F: PrimeField, const M: usize, const INS: usize, const OUTS: usize> {
+	pub public_amount: F,
+	pub ext_data_hash: F,
+	pub nullifiers: [F; INS],
+	pub commitments: [F; OUTS],
+	pub chain_id: F,
+	pub roots: [F; M],
+}
+
+impl<F: PrimeField, const M: usize, const INS: usize, const OUTS: usize> PublicInputs<F>
+	for VAnchorPublicInputs<F, M, INS, OUTS>
+{
+	const LEN: usize = 2 + INS + OUTS + 1 + M;
+
+	fn to_field_vec(&self) -> Vec<F> {
+		let mut inputs = vec![self.public_amount, self.ext_data_hash];
+		inputs.extend(self.nullifiers);
+		inputs.extend(self.commitments);
+		inputs.push(self.chain_id);
+		inputs.extend(self.roots);
+		inputs
+	}
+
+	fn from_field_slice_unchecked(inputs: &[F]) -> Self {
+		let mut nullifiers = [F::zero(); INS];
+		let mut commitments = [F::zero(); OUTS];
+		let mut roots = [F::zero(); M];
+		nullifiers.copy_from_slice(&inputs[2..2 + INS]);
+		commitments.copy_from_slice(&inputs[2 + INS..2 + INS + OUTS]);
+		roots.copy_from_slice(&inputs[3 + INS + OUTS..]);
+		Self {
+			public_amount: inputs[0],
+			ext_data_hash: inputs[1],
+			nullifiers,
+			commitments,
+			chain_id: inputs[2 + INS + OUTS],
+			roots,
+		}
+	}
+}
+
+/// Public inputs of the half of a party in the Swap circuit, with `INS`
+/// inputs, `OUTS` outputs and a root set of `M` roots. `side` is zero or one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SwapPublicInputs<F: PrimeField, const M: usize, const INS: usize, const OUTS: usize> {
+	pub ext_data_hash: F,
+	pub rate_commitment: F,
+	pub side: F,
+	pub order_hash: F,
+	pub counter_order_hash: F,
+	pub nullifiers: [F; INS],
+	pub commitments: [F; OUTS],
+	pub chain_id: F,
+	pub roots: [F; M],
+}
+
+impl<F: PrimeField, const M: usize, const INS: usize, const OUTS: usize> PublicInputs<F>
+	for SwapPublicInputs<F, M, INS, OUTS>
+{
+	const LEN: usize = 5 + INS + OUTS + 1 + M;
+
+	fn to_field_vec(&self) -> Vec<F> {
+		let mut inputs = vec![
+			self.ext_data_hash,
+			self.rate_commitment,
+			self.side,
+			self.order_hash,
+			self.counter_order_hash,
+		];
+		inputs.extend(self.nullifiers);
+		inputs.extend(self.commitments);
+		inputs.push(self.chain_id);
+		inputs.extend(self.roots);
+		inputs
+	}
+
+	fn from_field_slice_unchecked(inputs: &[F]) -> Self {
+		let mut nullifiers = [F::zero(); INS];
+		let mut commitments = [F::zero(); OUTS];
+		let mut roots = [F::zero(); M];
+		nullifiers.copy_from_slice(&inputs[5..5 + INS]);
+		commitments.copy_from_slice(&inputs[5 + INS..5 + INS + OUTS]);
+		roots.copy_from_slice(&inputs[6 + INS + OUTS..]);
+		Self {
+			ext_data_hash: inputs[0],
+			rate_commitment: inputs[1],
+			side: inputs[2],
+			order_hash: inputs[3],
+			counter_order_hash: inputs[4],
+			nullifiers,
+			commitments,
+			chain_id: inputs[5 + INS + OUTS],
+			roots,
+		}
+	}
+}
+
+/// Public inputs of the Voting circuit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VotingPublicInputs<F: PrimeField> {
+	pub poll_id: F,
+	pub root: F,
+	pub poll_nullifier: F,
+	pub num_options: F,
+	pub vote: F,
+	pub weight: F,
+}
+
+impl<F: PrimeField> PublicInputs<F> for VotingPublicInputs<F> {
+	const LEN: usize = 6;
+
+	fn to_field_vec(&self) -> Vec<F> {
+		vec![
+			self.poll_id,
+			self.root,
+			self.poll_nullifier,
+			self.num_options,
+			self.vote,
+			self.weight,
+		]
+	}
+
+	fn from_field_slice_unchecked(inputs: &[F]) -> Self {
+		Self {
+			poll_id: inputs[0],
+			root: inputs[1],
+			poll_nullifier: inputs[2],
+			num_options: inputs[3],
+			vote: inputs[4],
+			weight: inputs[5],
+		}
+	}
+}
+
+/// Public inputs of the dynamic depth Mixer circuit, the Mixer ones
+/// followed by the depth of the tree
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MixerDynamicDepthPublicInputs<F: PrimeField> {
+	pub nullifier_hash: F,
+	pub root: F,
+	pub arbitrary_input: F,
+	pub depth: F,
+}
+
+impl<F: PrimeField> PublicInputs<F> for MixerDynamicDepthPublicInputs<F> {
+	const LEN: usize = 4;
+
+	fn to_field_vec(&self) -> Vec<F> {
+		vec![
+			self.nullifier_hash,
+			self.root,
+			self.arbitrary_input,
+			self.depth,
+		]
+	}
+
+	fn from_field_slice_unchecked(inputs: &[F]) -> Self {
+		Self {
+			nullifier_hash: inputs[0],
+			root: inputs[1],
+			arbitrary_input: inputs[2],
+			depth: inputs[3],
+		}
+	}
+}
+
+/// Public inputs of the association set Anchor circuit, the Anchor ones
+/// followed by the association root and the association mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AnchorAssociationPublicInputs<F: PrimeField, const M: usize> {
+	pub nullifier_hash: F,
+	pub arbitrary_input: F,
+	pub chain_id: F,
+	pub roots: [F; M],
+	pub association_root: F,
+	pub association_enabled: F,
+}
+
+impl<F: PrimeField, const M: usize> PublicInputs<F> for AnchorAssociationPublicInputs<F, M> {
+	const LEN: usize = 5 + M;
+
+	fn to_field_vec(&self) -> Vec<F> {
+		let mut inputs = vec![self.nullifier_hash, self.arbitrary_input, self.chain_id];
+		inputs.extend(self.roots);
+		inputs.push(self.association_root);
+		inputs.push(self.association_enabled);
+		inputs
+	}
+
+	fn from_field_slice_unchecked(inputs: &[F]) -> Self {
+		let mut roots = [F::zero(); M];
+		roots.copy_from_slice(&inputs[3..3 + M]);
+		Self {
+			nullifier_hash: inputs[0],
+			arbitrary_input: inputs[1],
+			chain_id: inputs[2],
+			roots,
+			association_root: inputs[3 + M],
+			association_enabled: inputs[4 + M],
+		}
+	}
+}
+
+/// Public inputs of the Anchor migration circuit, with a root set of `M` roots
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AnchorMigrationPublicInputs<F: PrimeField, const M: usize> {
+	pub nullifier_hash: F,
+	pub denomination: F,
+	pub chain_id: F,
+	pub roots: [F; M],
+	pub out_commitment: F,
+}
+
+impl<F: PrimeField, const M: usize> PublicInputs<F> for AnchorMigrationPublicInputs<F, M> {
+	const LEN: usize = 4 + M;
+
+	fn to_field_vec(&self) -> Vec<F> {
+		let mut inputs = vec![self.nullifier_hash, self.denomination, self.chain_id];
+		inputs.extend(self.roots);
+		inputs.push(self.out_commitment);
+		inputs
+	}
+
+	fn from_field_slice_unchecked(inputs: &[F]) -> Self {
+		let mut roots = [F::zero(); M];
+		roots.copy_from_slice(&inputs[3..3 + M]);
+		Self {
+			nullifier_hash: inputs[0],
+			denomination: inputs[1],
+			chain_id: inputs[2],
+			roots,
+			out_commitment: inputs[3 + M],
+		}
+	}
+}
+
+/// Public inputs of the Ownership circuit, with a root set of `M` roots
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OwnershipPublicInputs<F: PrimeField, const M: usize> {
+	pub scoped_nullifier: F,
+	pub scope: F,
+	pub chain_id: F,
+	pub roots: [F; M],
+}
+
+impl<F: PrimeField, const M: usize> PublicInputs<F> for OwnershipPublicInputs<F, M> {
+	const LEN: usize = 3 + M;
+
+	fn to_field_vec(&self) -> Vec<F> {
+		let mut inputs = vec![self.scoped_nullifier, self.scope, self.chain_id];
+		inputs.extend(self.roots);
+		inputs
+	}
+
+	fn from_field_slice_unchecked(inputs: &[F]) -> Self {
+		let mut roots = [F::zero(); M];
+		roots.copy_from_slice(&inputs[3..]);
+		Self {
+			scoped_nullifier: inputs[0],
+			scope: inputs[1],
+			chain_id: inputs[2],
+			roots,
+		}
+	}
+}
+
+/// Public inputs of the VAnchor circuit with time-locked notes, with `INS`
+/// inputs, `OUTS` outputs and a root set of `M` roots
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeLockPublicInputs<F: PrimeField, const M: usize, const INS: usize, const OUTS: usize>
+{
+	pub public_amount: F,
+	pub ext_data_hash: F,
+	pub current_time: F,
+	pub nullifiers: [F; INS],
+	pub commitments: [F; OUTS],
+	pub chain_id: F,
+	pub roots: [F; M],
+}
+
+impl<F: PrimeField, const M: usize, const INS: usize, const OUTS: usize> PublicInputs<F>
+	for TimeLockPublicInputs<F, M, INS, OUTS>
+{
+	const LEN: usize = 3 + INS + OUTS + 1 + M;
+
+	fn to_field_vec(&self) -> Vec<F> {
+		let mut inputs = vec![self.public_amount, self.ext_data_hash, self.current_time];
+		inputs.extend(self.nullifiers);
+		inputs.extend(self.commitments);
+		inputs.push(self.chain_id);
+		inputs.extend(self.roots);
+		inputs
+	}
+
+	fn from_field_slice_unchecked(inputs: &[F]) -> Self {
+		let mut nullifiers = [F::zero(); INS];
+		let mut commitments = [F::zero(); OUTS];
+		let mut roots = [F::zero(); M];
+		nullifiers.copy_from_slice(&inputs[3..3 + INS]);
+		commitments.copy_from_slice(&inputs[3 + INS..3 + INS + OUTS]);
+		roots.copy_from_slice(&inputs[4 + INS + OUTS..]);
+		Self {
+			public_amount: inputs[0],
+			ext_data_hash: inputs[1],
+			current_time: inputs[2],
+			nullifiers,
+			commitments,
+			chain_id: inputs[3 + INS + OUTS],
+			roots,
+		}
+	}
+}
+
+/// Public inputs of the multi-asset VAnchor circuit, with `INS` inputs,
+/// `OUTS` outputs and a root set of `M` roots
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MaspPublicInputs<F: PrimeField, const M: usize, const INS: usize, const OUTS: usize> {
+	pub public_amount: F,
+	pub ext_data_hash: F,
+	pub asset_id: F,
+	pub nullifiers: [F; INS],
+	pub commitments: [F; OUTS],
+	pub chain_id: F,
+	pub roots: [F; M],
+}
+
+impl<F: PrimeField, const M: usize, const INS: usize, const OUTS: usize> PublicInputs<F>
+	for MaspPublicInputs<F, M, INS, OUTS>
+{
+	const LEN: usize = 3 + INS + OUTS + 1 + M;
+
+	fn to_field_vec(&self) -> Vec<F> {
+		let mut inputs = vec![self.public_amount, self.ext_data_hash, self.asset_id];
+		inputs.extend(self.nullifiers);
+		inputs.extend(self.commitments);
+		inputs.push(self.chain_id);
+		inputs.extend(self.roots);
+		inputs
+	}
+
+	fn from_field_slice_unchecked(inputs: &[F]) -> Self {
+		let mut nullifiers = [F::zero(); INS];
+		let mut commitments = [F::zero(); OUTS];
+		let mut roots = [F::zero(); M];
+		nullifiers.copy_from_slice(&inputs[3..3 + INS]);
+		commitments.copy_from_slice(&inputs[3 + INS..3 + INS + OUTS]);
+		roots.copy_from_slice(&inputs[4 + INS + OUTS..]);
+		Self {
+			public_amount: inputs[0],
+			ext_data_hash: inputs[1],
+			asset_id: inputs[2],
+			nullifiers,
+			commitments,
+			chain_id: inputs[3 + INS + OUTS],
+			roots,
+		}
+	}
+}
+
+/// Public inputs of the balance threshold circuit, with one scoped nullifier
+/// for each of the `INS` Utxos and a root set of `M` roots
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BalanceThresholdPublicInputs<F: PrimeField, const M: usize, const INS: usize> {
+	pub threshold: F,
+	pub scope: F,
+	pub scoped_nullifiers: [F; INS],
+	pub chain_id: F,
+	pub roots: [F; M],
+}
+
+impl<F: PrimeField, const M: usize, const INS: usize> PublicInputs<F>
+	for BalanceThresholdPublicInputs<F, M, INS>
+{
+	const LEN: usize = 2 + INS + 1 + M;
+
+	fn to_field_vec(&self) -> Vec<F> {
+		let mut inputs = vec![self.threshold, self.scope];
+		inputs.extend(self.scoped_nullifiers);
+		inputs.push(self.chain_id);
+		inputs.extend(self.roots);
+		inputs
+	}
+
+	fn from_field_slice_unchecked(inputs: &[F]) -> Self {
+		let mut scoped_nullifiers = [F::zero(); INS];
+		let mut roots = [F::zero(); M];
+		scoped_nullifiers.copy_from_slice(&inputs[2..2 + INS]);
+		roots.copy_from_slice(&inputs[3 + INS..]);
+		Self {
+			threshold: inputs[0],
+			scope: inputs[1],
+			scoped_nullifiers,
+			chain_id: inputs[2 + INS],
+			roots,
+		}
+	}
+}
+
+/// Public inputs of the batch insertion circuit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatchInsertPublicInputs<F: PrimeField> {
+	pub old_root: F,
+	pub new_root: F,
+	pub start_index: F,
+	pub leaves_hash: F,
+}
+
+impl<F: PrimeField> PublicInputs<F> for BatchInsertPublicInputs<F> {
+	const LEN: usize = 4;
+
+	fn to_field_vec(&self) -> Vec<F> {
+		vec![
+			self.old_root,
+			self.new_root,
+			self.start_index,
+			self.leaves_hash,
+		]
+	}
+
+	fn from_field_slice_unchecked(inputs: &[F]) -> Self {
+		Self {
+			old_root: inputs[0],
+			new_root: inputs[1],
+			start_index: inputs[2],
+			leaves_hash: inputs[3],
+		}
+	}
+}
+
+/// Public inputs of the Rate-Limiting Nullifier circuit, where `(x, y)` is
+/// the share of the identity secret
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RlnPublicInputs<F: PrimeField> {
+	pub root: F,
+	pub epoch: F,
+	pub x: F,
+	pub y: F,
+	pub nullifier: F,
+}
+
+impl<F: PrimeField> PublicInputs<F> for RlnPublicInputs<F> {
+	const LEN: usize = 5;
+
+	fn to_field_vec(&self) -> Vec<F> {
+		vec![self.root, self.epoch, self.x, self.y, self.nullifier]
+	}
+
+	fn from_field_slice_unchecked(inputs: &[F]) -> Self {
+		Self {
+			root: inputs[0],
+			epoch: inputs[1],
+			x: inputs[2],
+			y: inputs[3],
+			nullifier: inputs[4],
+		}
+	}
+}
+
+/// Public inputs of the Semaphore circuit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SemaphorePublicInputs<F: PrimeField> {
+	pub root: F,
+	pub nullifier_hash: F,
+	pub signal_hash: F,
+	pub external_nullifier: F,
+}
+
+impl<F: PrimeField> PublicInputs<F> for SemaphorePublicInputs<F> {
+	const LEN: usize = 4;
+
+	fn to_field_vec(&self) -> Vec<F> {
+		vec![
+			self.root,
+			self.nullifier_hash,
+			self.signal_hash,
+			self.external_nullifier,
+		]
+	}
+
+	fn from_field_slice_unchecked(inputs: &[F]) -> Self {
+		Self {
+			root: inputs[0],
+			nullifier_hash: inputs[1],
+			signal_hash: inputs[2],
+			external_nullifier: inputs[3],
+		}
+	}
+}
+
+/// Public inputs of the Tornado Cash legacy import circuit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LegacyImportPublicInputs<F: PrimeField> {
+	pub nullifier_hash: F,
+	pub root: F,
+	pub arbitrary_input: F,
+	pub denomination: F,
+	pub out_commitment: F,
+	pub out_chain_id: F,
+}
+
+impl<F: PrimeField> PublicInputs<F> for LegacyImportPublicInputs<F> {
+	const LEN: usize = 6;
+
+	fn to_field_vec(&self) -> Vec<F> {
+		vec![
+			self.nullifier_hash,
+			self.root,
+			self.arbitrary_input,
+			self.denomination,
+			self.out_commitment,
+			self.out_chain_id,
+		]
+	}
+
+	fn from_field_slice_unchecked(inputs: &[F]) -> Self {
+		Self {
+			nullifier_hash: inputs[0],
+			root: inputs[1],
+			arbitrary_input: inputs[2],
+			denomination: inputs[3],
+			out_commitment: inputs[4],
+			out_chain_id: inputs[5],
+		}
+	}
+}
+
+/// Public inputs of the credential circuit with `N_ATTRS` attributes. Every
+/// attribute has `SET_SIZE` operands, the ones its predicate doesn't use are
+/// zero.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CredentialPublicInputs<F: PrimeField, const N_ATTRS: usize, const SET_SIZE: usize> {
+	pub root: F,
+	pub operands: [[F; SET_SIZE]; N_ATTRS],
+}
+
+impl<F: PrimeField, const N_ATTRS: usize, const SET_SIZE: usize> PublicInputs<F>
+	for CredentialPublicInputs<F, N_ATTRS, SET_SIZE>
+{
+	const LEN: usize = 1 + N_ATTRS * SET_SIZE;
+
+	fn to_field_vec(&self) -> Vec<F> {
+		let mut inputs = vec![self.root];
+		for values in self.operands.iter() {
+			inputs.extend_from_slice(values);
+		}
+		inputs
+	}
+
+	fn from_field_slice_unchecked(inputs: &[F]) -> Self {
+		let mut operands = [[F::zero(); SET_SIZE]; N_ATTRS];
+		for (values, chunk) in operands.iter_mut().zip(inputs[1..].chunks(SET_SIZE)) {
+			values.copy_from_slice(chunk);
+		}
+		Self {
+			root: inputs[0],
+			operands,
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use ark_bn254::Fr;
+	use ark_std::{test_rng, vec, UniformRand};
+
+	use super::{AnchorPublicInputs, MixerPublicInputs, PublicInputs, VAnchorPublicInputs};
+
+	#[test]
+	fn should_round_trip_vanchor_public_inputs() {
+		let rng = &mut test_rng();
+		let inputs = VAnchorPublicInputs::<Fr, 2, 16, 2> {
+			public_amount: Fr::rand(rng),
+			ext_data_hash: Fr::rand(rng),
+			nullifiers: [(); 16].map(|_| Fr::rand(rng)),
+			commitments: [Fr::rand(rng), Fr::rand(rng)],
+			chain_id: Fr::from(1u64),
+			roots: [Fr::rand(rng), Fr::rand(rng)],
+		};
+
+		let inputs_f = inputs.to_field_vec();
+		assert_eq!(inputs_f.len(), 2 + 16 + 2 + 1 + 2);
+		assert_eq!(inputs_f[18], inputs.commitments[0]);
+		assert_eq!(inputs_f[20], inputs.chain_id);
+
+		let from_fields = VAnchorPublicInputs::from_field_slice(&inputs_f).unwrap();
+		let from_bytes = VAnchorPublicInputs::from_bytes_slice(&inputs.to_bytes_vec()).unwrap();
+		let from_hex = VAnchorPublicInputs::from_hex_slice(&inputs.to_hex_vec()).unwrap();
+		assert_eq!(from_fields, inputs);
+		assert_eq!(from_bytes, inputs);
+		assert_eq!(from_hex, inputs);
+	}
+
+	#[test]
+	fn should_round_trip_mixer_and_anchor_public_inputs() {
+		let rng = &mut test_rng();
+		let mixer = MixerPublicInputs {
+			nullifier_hash: Fr::rand(rng),
+			root: Fr::rand(rng),
+			arbitrary_input: Fr::rand(rng),
+		};
+		let anchor = AnchorPublicInputs::<Fr, 3> {
+			nullifier_hash: Fr::rand(rng),
+			arbitrary_input: Fr::rand(rng),
+			chain_id: Fr::from(5u64),
+			roots: [Fr::rand(rng), Fr::rand(rng), Fr::rand(rng)],
+		};
+
+		let hex = mixer.to_hex_vec();
+		assert!(hex.iter().all(|x| x.starts_with("0x") && x.len() == 66));
+		assert_eq!(MixerPublicInputs::from_hex_slice(&hex).unwrap(), mixer);
+		assert_eq!(
+			AnchorPublicInputs::from_bytes_slice(&anchor.to_bytes_vec()).unwrap(),
+			anchor
+		);
+	}
+
+	#[test]
+	fn should_fail_with_invalid_public_inputs() {
+		let rng = &mut test_rng();
+		let inputs = vec![Fr::rand(rng); 4];
+
+		assert!(MixerPublicInputs::from_field_slice(&inputs).is_err());
+		assert!(AnchorPublicInputs::<Fr, 2>::from_field_slice(&inputs).is_err());
+		assert!(AnchorPublicInputs::<Fr, 1>::from_field_slice(&inputs).is_ok());
+		assert!(VAnchorPublicInputs::<Fr, 2, 2, 2>::from_field_slice(&inputs).is_err());
+
+		assert!(MixerPublicInputs::<Fr>::from_hex_slice(&["12", "0x", "0x"]).is_err());
+		assert!(MixerPublicInputs::<Fr>::from_hex_slice(&["0xzz", "0x", "0x"]).is_err());
+	}
+}
@@ -1,4 +1,8 @@
-use crate::{common::*, AnchorProver};
+use crate::{
+	common::*,
+	public_inputs::{AnchorAssociationPublicInputs, AnchorPublicInputs, PublicInputs},
+	AnchorProver,
+};
 use ark_crypto_primitives::Error;
 use ark_ec::PairingEngine;
 use ark_ff::{BigInteger, PrimeField};
@@ -10,10 +14,13 @@ use ark_std::{
 	UniformRand, Zero,
 };
 use arkworks_native_gadgets::{
-	merkle_tree::Path,
+	merkle_tree::{association_path, build_association_tree, Path},
 	poseidon::{FieldHasher, Poseidon},
 };
-use arkworks_r1cs_circuits::anchor::AnchorCircuit;
+use arkworks_r1cs_circuits::{
+	anchor::AnchorCircuit, anchor_association::AnchorAssociationCircuit,
+	anchor_chain_bound::AnchorChainBoundCircuit, anchor_dynamic_depth::AnchorDynamicDepthCircuit,
+};
 use arkworks_r1cs_gadgets::poseidon::PoseidonGadget;
 use arkworks_utils::Curve;
 use codec::Encode;
@@ -24,6 +31,12 @@ mod tests;
 pub type PoseidonAnchorCircuit<F, const N: usize, const M: usize> =
 	AnchorCircuit<F, PoseidonGadget<F>, N, M>;
 
+pub type PoseidonAnchorAssociationCircuit<F, const N: usize, const M: usize, const A: usize> =
+	AnchorAssociationCircuit<F, PoseidonGadget<F>, N, M, A>;
+
+pub type PoseidonAnchorChainBoundCircuit<F, const N: usize, const M: usize> =
+	AnchorChainBoundCircuit<F, PoseidonGadget<F>, N, M>;
+
 pub struct AnchorR1CSProver<E: PairingEngine, const HEIGHT: usize, const ANCHOR_CT: usize> {
 	engine: PhantomData<E>,
 }
@@ -38,9 +51,13 @@ impl<E: PairingEngine, const HEIGHT: usize, const ANCHOR_CT: usize>
 		roots: [E::Fr; ANCHOR_CT],
 		arbitrary_input: E::Fr,
 	) -> Vec<E::Fr> {
-		let mut pub_ins = vec![nullifier_hash, arbitrary_input, chain_id];
-		pub_ins.extend(roots.to_vec());
-		pub_ins
+		AnchorPublicInputs {
+			nullifier_hash,
+			arbitrary_input,
+			chain_id,
+			roots,
+		}
+		.to_field_vec()
 	}
 
 	#[allow(dead_code)]
@@ -224,10 +241,9 @@ impl<E: PairingEngine, const HEIGHT: usize, const ANCHOR_CT: usize>
 		let leaf_raw = leaf.into_repr().to_bytes_be();
 		let nullifier_hash_raw = nullifier_hash.into_repr().to_bytes_be();
 		let roots_raw = roots.iter().map(|v| v.into_repr().to_bytes_be()).collect();
-		let public_inputs_raw: Vec<Vec<u8>> = public_inputs
-			.iter()
-			.map(|x| x.into_repr().to_bytes_be())
-			.collect();
+		let public_inputs_raw =
+			AnchorPublicInputs::<E::Fr, ANCHOR_CT>::from_field_slice(&public_inputs)?
+				.to_bytes_vec();
 
 		Ok((
 			mc,
@@ -268,6 +284,605 @@ impl<E: PairingEngine, const HEIGHT: usize, const ANCHOR_CT: usize>
 		);
 		mc
 	}
+
+	/// Sets up a dynamic depth circuit for a tree of the largest supported
+	/// height, e.g. for generating the keys
+	#[allow(dead_code)]
+	#[allow(clippy::type_complexity)]
+	pub fn setup_dynamic_depth_random_circuit<R: CryptoRng + RngCore>(
+		curve: Curve,
+		default_leaf: [u8; 32],
+		rng: &mut R,
+	) -> Result<
+		(
+			AnchorDynamicDepthCircuit<E::Fr, PoseidonGadget<E::Fr>, HEIGHT, ANCHOR_CT>,
+			E::Fr,
+			E::Fr,
+			Vec<E::Fr>,
+			Vec<E::Fr>,
+		),
+		Error,
+	> {
+		let params3 = setup_params::<E::Fr>(curve, 5, 3);
+		let tree_hasher = Poseidon::<E::Fr> { params: params3 };
+		let chain_id = 1u64;
+		let leaf = Self::create_random_leaf(curve, chain_id, rng)?;
+		let secret = E::Fr::from_be_bytes_mod_order(&leaf.secret_bytes);
+		let nullifier = E::Fr::from_be_bytes_mod_order(&leaf.nullifier_bytes);
+		let leaves = vec![E::Fr::from_be_bytes_mod_order(&leaf.leaf_bytes)];
+		let arbitrary_input = E::Fr::rand(rng);
+
+		let (tree, _) = setup_tree_and_create_path::<E::Fr, Poseidon<E::Fr>, HEIGHT>(
+			&tree_hasher,
+			&leaves,
+			0,
+			&default_leaf,
+		)?;
+		let mut root_set = [E::Fr::rand(rng); ANCHOR_CT];
+		root_set[0] = tree.root();
+
+		Self::setup_dynamic_depth_circuit_with_privates(
+			curve,
+			E::Fr::from(chain_id),
+			secret,
+			nullifier,
+			&leaves,
+			0,
+			HEIGHT,
+			root_set,
+			arbitrary_input,
+			default_leaf,
+		)
+	}
+
+	/// Sets up a dynamic depth circuit proving membership in the tree of
+	/// height `depth` holding `leaves`, whose root is in `roots`
+	#[allow(dead_code)]
+	#[allow(clippy::too_many_arguments)]
+	#[allow(clippy::type_complexity)]
+	pub fn setup_dynamic_depth_circuit_with_privates(
+		curve: Curve,
+		chain_id: E::Fr,
+		secret: E::Fr,
+		nullifier: E::Fr,
+		leaves: &[E::Fr],
+		index: u64,
+		depth: usize,
+		roots: [E::Fr; ANCHOR_CT],
+		arbitrary_input: E::Fr,
+		default_leaf: [u8; 32],
+	) -> Result<
+		(
+			AnchorDynamicDepthCircuit<E::Fr, PoseidonGadget<E::Fr>, HEIGHT, ANCHOR_CT>,
+			E::Fr,
+			E::Fr,
+			Vec<E::Fr>,
+			Vec<E::Fr>,
+		),
+		Error,
+	> {
+		let params3 = setup_params::<E::Fr>(curve, 5, 3);
+		let params4 = setup_params::<E::Fr>(curve, 5, 4);
+		let tree_hasher = Poseidon::<E::Fr> { params: params3 };
+		let leaf_hasher = Poseidon::<E::Fr> { params: params4 };
+		let leaf = leaf_hasher.hash(&[chain_id, secret, nullifier])?;
+		let nullifier_hash = tree_hasher.hash_two(&nullifier, &nullifier)?;
+		let (_, path) = setup_tree_and_create_path_at_depth::<E::Fr, Poseidon<E::Fr>, HEIGHT>(
+			&tree_hasher,
+			leaves,
+			index,
+			depth,
+			&default_leaf,
+		)?;
+
+		let mc = AnchorDynamicDepthCircuit::new(
+			arbitrary_input,
+			secret,
+			nullifier,
+			chain_id,
+			roots,
+			path,
+			E::Fr::from(depth as u64),
+			nullifier_hash,
+			tree_hasher,
+			leaf_hasher,
+		);
+		let public_inputs =
+			Self::construct_public_inputs(chain_id, nullifier_hash, roots, arbitrary_input);
+		Ok((mc, leaf, nullifier_hash, roots.to_vec(), public_inputs))
+	}
+
+	/// Creates a proof with the keys of a dynamic depth circuit, for the tree
+	/// of height `depth` holding `leaves`
+	#[allow(clippy::too_many_arguments)]
+	pub fn create_dynamic_depth_proof<R: RngCore + CryptoRng>(
+		curve: Curve,
+		chain_id: u64,
+		secret: Vec<u8>,
+		nullifier: Vec<u8>,
+		leaves: Vec<Vec<u8>>,
+		index: u64,
+		depth: usize,
+		root_set: Vec<Vec<u8>>,
+		recipient: Vec<u8>,
+		relayer: Vec<u8>,
+		fee: u128,
+		refund: u128,
+		commitment: Vec<u8>,
+		pk: Vec<u8>,
+		default_leaf: [u8; 32],
+		rng: &mut R,
+	) -> Result<AnchorProof, Error> {
+		// Get field element version of all the data
+		let secret_f = E::Fr::from_be_bytes_mod_order(&secret);
+		let nullifier_f = E::Fr::from_be_bytes_mod_order(&nullifier);
+		let leaves_f: Vec<E::Fr> = leaves
+			.iter()
+			.map(|x| E::Fr::from_be_bytes_mod_order(x))
+			.collect();
+		let root_set_f = pad_root_set::<E::Fr, ANCHOR_CT>(&root_set)?;
+		// Create the arbitrary input data
+		let mut arbitrary_data_bytes = Vec::new();
+		arbitrary_data_bytes.extend(&recipient);
+		arbitrary_data_bytes.extend(&relayer);
+		// Using encode to be compatible with on chain types
+		arbitrary_data_bytes.extend(fee.encode());
+		arbitrary_data_bytes.extend(refund.encode());
+		arbitrary_data_bytes.extend(&commitment);
+		let arbitrary_data = keccak_256(&arbitrary_data_bytes);
+		let arbitrary_input = E::Fr::from_be_bytes_mod_order(&arbitrary_data);
+
+		let (mc, leaf, nullifier_hash, roots, public_inputs) =
+			Self::setup_dynamic_depth_circuit_with_privates(
+				curve,
+				E::Fr::from(chain_id),
+				secret_f,
+				nullifier_f,
+				&leaves_f,
+				index,
+				depth,
+				root_set_f,
+				arbitrary_input,
+				default_leaf,
+			)?;
+
+		let leaf_raw = leaf.into_repr().to_bytes_be();
+		let nullifier_hash_raw = nullifier_hash.into_repr().to_bytes_be();
+		let roots_raw = roots.iter().map(|v| v.into_repr().to_bytes_be()).collect();
+		let public_inputs_raw =
+			AnchorPublicInputs::<E::Fr, ANCHOR_CT>::from_field_slice(&public_inputs)?
+				.to_bytes_vec();
+
+		let proof = prove_unchecked::<E, _, _>(mc, &pk, rng)?;
+
+		Ok(AnchorProof {
+			leaf_raw,
+			nullifier_hash_raw,
+			roots_raw,
+			public_inputs_raw,
+			proof,
+		})
+	}
+
+	/// Public inputs of the association set circuit: the Anchor ones followed
+	/// by the association root and the association mode
+	pub fn construct_association_public_inputs(
+		chain_id: E::Fr,
+		nullifier_hash: E::Fr,
+		roots: [E::Fr; ANCHOR_CT],
+		arbitrary_input: E::Fr,
+		association_root: E::Fr,
+		association_enabled: bool,
+	) -> Vec<E::Fr> {
+		AnchorAssociationPublicInputs {
+			nullifier_hash,
+			arbitrary_input,
+			chain_id,
+			roots,
+			association_root,
+			association_enabled: E::Fr::from(association_enabled),
+		}
+		.to_field_vec()
+	}
+
+	/// Sets up an association set circuit with a random deposit that is the
+	/// only member of the association set, e.g. for generating the keys
+	#[allow(dead_code)]
+	#[allow(clippy::type_complexity)]
+	pub fn setup_association_random_circuit<
+		R: CryptoRng + RngCore,
+		const ASSOCIATION_HEIGHT: usize,
+	>(
+		curve: Curve,
+		default_leaf: [u8; 32],
+		rng: &mut R,
+	) -> Result<
+		(
+			PoseidonAnchorAssociationCircuit<E::Fr, HEIGHT, ANCHOR_CT, ASSOCIATION_HEIGHT>,
+			E::Fr,
+			E::Fr,
+			E::Fr,
+			Vec<E::Fr>,
+		),
+		Error,
+	> {
+		let params3 = setup_params::<E::Fr>(curve, 5, 3);
+		let tree_hasher = Poseidon::<E::Fr> { params: params3 };
+		let chain_id = 1u64;
+		let leaf = Self::create_random_leaf(curve, chain_id, rng)?;
+		let secret = E::Fr::from_be_bytes_mod_order(&leaf.secret_bytes);
+		let nullifier = E::Fr::from_be_bytes_mod_order(&leaf.nullifier_bytes);
+		let leaves = vec![E::Fr::from_be_bytes_mod_order(&leaf.leaf_bytes)];
+		let arbitrary_input = E::Fr::rand(rng);
+
+		let (tree, _) = setup_tree_and_create_path::<E::Fr, Poseidon<E::Fr>, HEIGHT>(
+			&tree_hasher,
+			&leaves,
+			0,
+			&default_leaf,
+		)?;
+		let mut root_set = [E::Fr::rand(rng); ANCHOR_CT];
+		root_set[0] = tree.root();
+
+		Self::setup_association_circuit_with_privates(
+			curve,
+			E::Fr::from(chain_id),
+			secret,
+			nullifier,
+			&leaves,
+			0,
+			root_set,
+			arbitrary_input,
+			&leaves,
+			true,
+			default_leaf,
+		)
+	}
+
+	/// Sets up an association set circuit for the deposit at `index` in
+	/// `leaves`. The association set tree is built from `approved_leaves`.
+	///
+	/// When `association_enabled` is false the membership in the association
+	/// set isn't enforced, and the deposit doesn't need to be approved.
+	/// Returns the circuit, the leaf, the nullifier hash, the association root
+	/// and the public inputs.
+	#[allow(dead_code)]
+	#[allow(clippy::too_many_arguments)]
+	#[allow(clippy::type_complexity)]
+	pub fn setup_association_circuit_with_privates<const ASSOCIATION_HEIGHT: usize>(
+		curve: Curve,
+		chain_id: E::Fr,
+		secret: E::Fr,
+		nullifier: E::Fr,
+		leaves: &[E::Fr],
+		index: u64,
+		roots: [E::Fr; ANCHOR_CT],
+		arbitrary_input: E::Fr,
+		approved_leaves: &[E::Fr],
+		association_enabled: bool,
+		default_leaf: [u8; 32],
+	) -> Result<
+		(
+			PoseidonAnchorAssociationCircuit<E::Fr, HEIGHT, ANCHOR_CT, ASSOCIATION_HEIGHT>,
+			E::Fr,
+			E::Fr,
+			E::Fr,
+			Vec<E::Fr>,
+		),
+		Error,
+	> {
+		let params3 = setup_params::<E::Fr>(curve, 5, 3);
+		let params4 = setup_params::<E::Fr>(curve, 5, 4);
+		let tree_hasher = Poseidon::<E::Fr> { params: params3 };
+		let leaf_hasher = Poseidon::<E::Fr> { params: params4 };
+		let leaf = leaf_hasher.hash(&[chain_id, nullifier, secret])?;
+		let nullifier_hash = tree_hasher.hash_two(&nullifier, &nullifier)?;
+		let (_, path) = setup_tree_and_create_path::<E::Fr, Poseidon<E::Fr>, HEIGHT>(
+			&tree_hasher,
+			leaves,
+			index,
+			&default_leaf,
+		)?;
+
+		let association_tree = build_association_tree::<_, _, ASSOCIATION_HEIGHT>(
+			approved_leaves,
+			&tree_hasher,
+			&default_leaf,
+		)?;
+		let association_root = association_tree.root();
+		// When the mode is disabled the path doesn't need to lead to the
+		// association root, but it still has to be a valid path from the leaf
+		let association_path = match association_path(&association_tree, approved_leaves, &leaf) {
+			Ok(path) => path,
+			Err(e) if association_enabled => return Err(e),
+			Err(_) => build_association_tree::<_, _, ASSOCIATION_HEIGHT>(
+				&[leaf],
+				&tree_hasher,
+				&default_leaf,
+			)?
+			.generate_membership_proof(0),
+		};
+
+		let mc = AnchorAssociationCircuit::new(
+			arbitrary_input,
+			secret,
+			nullifier,
+			chain_id,
+			roots,
+			path,
+			nullifier_hash,
+			association_root,
+			association_enabled,
+			association_path,
+			tree_hasher,
+			leaf_hasher,
+		);
+		let public_inputs = Self::construct_association_public_inputs(
+			chain_id,
+			nullifier_hash,
+			roots,
+			arbitrary_input,
+			association_root,
+			association_enabled,
+		);
+		Ok((mc, leaf, nullifier_hash, association_root, public_inputs))
+	}
+
+	/// Creates a proof with the keys of an association set circuit. The
+	/// association set tree is built from `approved_leaves`, and its root is
+	/// the second to last public input.
+	#[allow(clippy::too_many_arguments)]
+	pub fn create_association_proof<R: RngCore + CryptoRng, const ASSOCIATION_HEIGHT: usize>(
+		curve: Curve,
+		chain_id: u64,
+		secret: Vec<u8>,
+		nullifier: Vec<u8>,
+		leaves: Vec<Vec<u8>>,
+		index: u64,
+		root_set: Vec<Vec<u8>>,
+		approved_leaves: Vec<Vec<u8>>,
+		association_enabled: bool,
+		recipient: Vec<u8>,
+		relayer: Vec<u8>,
+		fee: u128,
+		refund: u128,
+		commitment: Vec<u8>,
+		pk: Vec<u8>,
+		default_leaf: [u8; 32],
+		rng: &mut R,
+	) -> Result<AnchorProof, Error> {
+		// Get field element version of all the data
+		let secret_f = E::Fr::from_be_bytes_mod_order(&secret);
+		let nullifier_f = E::Fr::from_be_bytes_mod_order(&nullifier);
+		let leaves_f: Vec<E::Fr> = leaves
+			.iter()
+			.map(|x| E::Fr::from_be_bytes_mod_order(x))
+			.collect();
+		let approved_leaves_f: Vec<E::Fr> = approved_leaves
+			.iter()
+			.map(|x| E::Fr::from_be_bytes_mod_order(x))
+			.collect();
+		let root_set_f = pad_root_set::<E::Fr, ANCHOR_CT>(&root_set)?;
+		// Create the arbitrary input data
+		let mut arbitrary_data_bytes = Vec::new();
+		arbitrary_data_bytes.extend(&recipient);
+		arbitrary_data_bytes.extend(&relayer);
+		// Using encode to be compatible with on chain types
+		arbitrary_data_bytes.extend(fee.encode());
+		arbitrary_data_bytes.extend(refund.encode());
+		arbitrary_data_bytes.extend(&commitment);
+		let arbitrary_data = keccak_256(&arbitrary_data_bytes);
+		let arbitrary_input = E::Fr::from_be_bytes_mod_order(&arbitrary_data);
+
+		let (mc, leaf, nullifier_hash, _, public_inputs) =
+			Self::setup_association_circuit_with_privates::<ASSOCIATION_HEIGHT>(
+				curve,
+				E::Fr::from(chain_id),
+				secret_f,
+				nullifier_f,
+				&leaves_f,
+				index,
+				root_set_f,
+				arbitrary_input,
+				&approved_leaves_f,
+				association_enabled,
+				default_leaf,
+			)?;
+
+		let leaf_raw = leaf.into_repr().to_bytes_be();
+		let nullifier_hash_raw = nullifier_hash.into_repr().to_bytes_be();
+		let roots_raw = root_set_f
+			.iter()
+			.map(|v| v.into_repr().to_bytes_be())
+			.collect();
+		let public_inputs_raw =
+			AnchorAssociationPublicInputs::<E::Fr, ANCHOR_CT>::from_field_slice(&public_inputs)?
+				.to_bytes_vec();
+
+		let proof = prove_unchecked::<E, _, _>(mc, &pk, rng)?;
+
+		Ok(AnchorProof {
+			leaf_raw,
+			nullifier_hash_raw,
+			roots_raw,
+			public_inputs_raw,
+			proof,
+		})
+	}
+
+	/// Sets up a chain-bound circuit with a random deposit, e.g. for generating
+	/// the keys
+	#[allow(dead_code)]
+	#[allow(clippy::type_complexity)]
+	pub fn setup_chain_bound_random_circuit<R: CryptoRng + RngCore>(
+		curve: Curve,
+		default_leaf: [u8; 32],
+		rng: &mut R,
+	) -> Result<
+		(
+			PoseidonAnchorChainBoundCircuit<E::Fr, HEIGHT, ANCHOR_CT>,
+			E::Fr,
+			E::Fr,
+			Vec<E::Fr>,
+			Vec<E::Fr>,
+		),
+		Error,
+	> {
+		let params3 = setup_params::<E::Fr>(curve, 5, 3);
+		let tree_hasher = Poseidon::<E::Fr> { params: params3 };
+		let chain_id = 1u64;
+		let leaf = Self::create_random_leaf(curve, chain_id, rng)?;
+		let secret = E::Fr::from_be_bytes_mod_order(&leaf.secret_bytes);
+		let nullifier = E::Fr::from_be_bytes_mod_order(&leaf.nullifier_bytes);
+		let leaves = vec![E::Fr::from_be_bytes_mod_order(&leaf.leaf_bytes)];
+		let arbitrary_input = E::Fr::rand(rng);
+
+		let (tree, _) = setup_tree_and_create_path::<E::Fr, Poseidon<E::Fr>, HEIGHT>(
+			&tree_hasher,
+			&leaves,
+			0,
+			&default_leaf,
+		)?;
+		let mut root_set = [E::Fr::rand(rng); ANCHOR_CT];
+		root_set[0] = tree.root();
+
+		Self::setup_chain_bound_circuit_with_privates(
+			curve,
+			E::Fr::from(chain_id),
+			secret,
+			nullifier,
+			&leaves,
+			0,
+			root_set,
+			arbitrary_input,
+			default_leaf,
+		)
+	}
+
+	/// Sets up a chain-bound circuit for the deposit at `index` in `leaves`,
+	/// whose nullifier hash is `Hash(nullifier, chain_id)`
+	#[allow(dead_code)]
+	#[allow(clippy::too_many_arguments)]
+	#[allow(clippy::type_complexity)]
+	pub fn setup_chain_bound_circuit_with_privates(
+		curve: Curve,
+		chain_id: E::Fr,
+		secret: E::Fr,
+		nullifier: E::Fr,
+		leaves: &[E::Fr],
+		index: u64,
+		roots: [E::Fr; ANCHOR_CT],
+		arbitrary_input: E::Fr,
+		default_leaf: [u8; 32],
+	) -> Result<
+		(
+			PoseidonAnchorChainBoundCircuit<E::Fr, HEIGHT, ANCHOR_CT>,
+			E::Fr,
+			E::Fr,
+			Vec<E::Fr>,
+			Vec<E::Fr>,
+		),
+		Error,
+	> {
+		let params3 = setup_params::<E::Fr>(curve, 5, 3);
+		let params4 = setup_params::<E::Fr>(curve, 5, 4);
+		let tree_hasher = Poseidon::<E::Fr> { params: params3 };
+		let leaf_hasher = Poseidon::<E::Fr> { params: params4 };
+		let leaf = leaf_hasher.hash(&[chain_id, nullifier, secret])?;
+		let nullifier_hash = tree_hasher.hash_two(&nullifier, &chain_id)?;
+		let (_, path) = setup_tree_and_create_path::<E::Fr, Poseidon<E::Fr>, HEIGHT>(
+			&tree_hasher,
+			leaves,
+			index,
+			&default_leaf,
+		)?;
+
+		let mc = AnchorChainBoundCircuit::new(
+			arbitrary_input,
+			secret,
+			nullifier,
+			chain_id,
+			roots,
+			path,
+			nullifier_hash,
+			tree_hasher,
+			leaf_hasher,
+		);
+		let public_inputs =
+			Self::construct_public_inputs(chain_id, nullifier_hash, roots, arbitrary_input);
+		Ok((mc, leaf, nullifier_hash, roots.to_vec(), public_inputs))
+	}
+
+	/// Creates a proof whose nullifier hash `Hash(nullifier, chain_id)` is
+	/// bound to the chain of the withdrawal, so it can't be replayed on
+	/// another chain of the bridge
+	#[allow(clippy::too_many_arguments)]
+	pub fn create_chain_bound_proof<R: RngCore + CryptoRng>(
+		curve: Curve,
+		chain_id: u64,
+		secret: Vec<u8>,
+		nullifier: Vec<u8>,
+		leaves: Vec<Vec<u8>>,
+		index: u64,
+		root_set: Vec<Vec<u8>>,
+		recipient: Vec<u8>,
+		relayer: Vec<u8>,
+		fee: u128,
+		refund: u128,
+		commitment: Vec<u8>,
+		pk: Vec<u8>,
+		default_leaf: [u8; 32],
+		rng: &mut R,
+	) -> Result<AnchorProof, Error> {
+		// Get field element version of all the data
+		let secret_f = E::Fr::from_be_bytes_mod_order(&secret);
+		let nullifier_f = E::Fr::from_be_bytes_mod_order(&nullifier);
+		let leaves_f: Vec<E::Fr> = leaves
+			.iter()
+			.map(|x| E::Fr::from_be_bytes_mod_order(x))
+			.collect();
+		let root_set_f = pad_root_set::<E::Fr, ANCHOR_CT>(&root_set)?;
+		// Create the arbitrary input data
+		let mut arbitrary_data_bytes = Vec::new();
+		arbitrary_data_bytes.extend(&recipient);
+		arbitrary_data_bytes.extend(&relayer);
+		// Using encode to be compatible with on chain types
+		arbitrary_data_bytes.extend(fee.encode());
+		arbitrary_data_bytes.extend(refund.encode());
+		arbitrary_data_bytes.extend(&commitment);
+		let arbitrary_data = keccak_256(&arbitrary_data_bytes);
+		let arbitrary_input = E::Fr::from_be_bytes_mod_order(&arbitrary_data);
+
+		let (mc, leaf, nullifier_hash, roots, public_inputs) =
+			Self::setup_chain_bound_circuit_with_privates(
+				curve,
+				E::Fr::from(chain_id),
+				secret_f,
+				nullifier_f,
+				&leaves_f,
+				index,
+				root_set_f,
+				arbitrary_input,
+				default_leaf,
+			)?;
+
+		let leaf_raw = leaf.into_repr().to_bytes_be();
+		let nullifier_hash_raw = nullifier_hash.into_repr().to_bytes_be();
+		let roots_raw = roots.iter().map(|v| v.into_repr().to_bytes_be()).collect();
+		let public_inputs_raw =
+			AnchorPublicInputs::<E::Fr, ANCHOR_CT>::from_field_slice(&public_inputs)?
+				.to_bytes_vec();
+
+		let proof = prove_unchecked::<E, _, _>(mc, &pk, rng)?;
+
+		Ok(AnchorProof {
+			leaf_raw,
+			nullifier_hash_raw,
+			roots_raw,
+			public_inputs_raw,
+			proof,
+		})
+	}
 }
 
 impl<E: PairingEngine, const HEIGHT: usize, const ANCHOR_CT: usize>
@@ -308,7 +923,7 @@ impl<E: PairingEngine, const HEIGHT: usize, const ANCHOR_CT: usize>
 		nullifier: Vec<u8>,
 		leaves: Vec<Vec<u8>>,
 		index: u64,
-		root_set: [Vec<u8>; ANCHOR_CT],
+		root_set: Vec<Vec<u8>>,
 		recipient: Vec<u8>,
 		relayer: Vec<u8>,
 		fee: u128,
@@ -330,7 +945,7 @@ impl<E: PairingEngine, const HEIGHT: usize, const ANCHOR_CT: usize>
 			.iter()
 			.map(|x| E::Fr::from_be_bytes_mod_order(x))
 			.collect();
-		let root_set_f: [E::Fr; ANCHOR_CT] = root_set.map(|x| E::Fr::from_be_bytes_mod_order(&x));
+		let root_set_f = pad_root_set::<E::Fr, ANCHOR_CT>(&root_set)?;
 		// Create the arbitrary input data
 		let mut arbitrary_data_bytes = Vec::new();
 		arbitrary_data_bytes.extend(&recipient);
@@ -368,12 +983,12 @@ impl<E: PairingEngine, const HEIGHT: usize, const ANCHOR_CT: usize>
 			tree_hasher,
 			leaf_hasher,
 		);
-		let public_inputs = Self::construct_public_inputs(
-			chain_id_f,
-			nullifier_hash_f,
-			root_set_f,
+		let public_inputs = AnchorPublicInputs {
+			nullifier_hash: nullifier_hash_f,
 			arbitrary_input,
-		);
+			chain_id: chain_id_f,
+			roots: root_set_f,
+		};
 
 		let leaf_raw = leaf_bytes;
 		let nullifier_hash_raw = nullifier_hash_bytes;
@@ -381,10 +996,7 @@ impl<E: PairingEngine, const HEIGHT: usize, const ANCHOR_CT: usize>
 			.iter()
 			.map(|v| v.into_repr().to_bytes_be())
 			.collect();
-		let public_inputs_raw: Vec<Vec<u8>> = public_inputs
-			.iter()
-			.map(|x| x.into_repr().to_bytes_be())
-			.collect();
+		let public_inputs_raw = public_inputs.to_bytes_vec();
 
 		let proof = prove_unchecked::<E, _, _>(mc, &pk, rng)?;
 
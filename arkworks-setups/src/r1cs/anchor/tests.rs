@@ -1,11 +1,20 @@
-use crate::{common::*, AnchorProver};
+use crate::{
+	common::*,
+	public_inputs::{AnchorAssociationPublicInputs, AnchorPublicInputs, PublicInputs},
+	AnchorProver,
+};
 use ark_bn254::{Bn254, Fr as Bn254Fr};
-use ark_ff::{PrimeField, UniformRand};
+use ark_crypto_primitives::Error;
+use ark_ff::{BigInteger, PrimeField, UniformRand};
 use ark_groth16::Groth16;
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystem};
 use ark_snark::SNARK;
-use ark_std::test_rng;
-use arkworks_native_gadgets::poseidon::{FieldHasher, Poseidon};
-use arkworks_r1cs_circuits::anchor::AnchorCircuit;
+use ark_std::{rand::rngs::StdRng, test_rng, One, Zero};
+use arkworks_native_gadgets::{
+	merkle_tree::{build_association_tree, build_root_tree},
+	poseidon::{FieldHasher, Poseidon},
+};
+use arkworks_r1cs_circuits::{anchor::AnchorCircuit, anchor_root_tree::AnchorRootTreeCircuit};
 use arkworks_r1cs_gadgets::poseidon::PoseidonGadget;
 use arkworks_utils::Curve;
 use codec::Encode;
@@ -14,6 +23,8 @@ use super::{setup_params, AnchorR1CSProver};
 
 pub const HEIGHT: usize = 30;
 pub const ANCHOR_CT: usize = 2;
+pub const ROOT_HEIGHT: usize = 5;
+pub const ASSOCIATION_HEIGHT: usize = 4;
 
 #[allow(non_camel_case_types)]
 type AnchorR1CSProver_Bn254_Poseidon_30 = AnchorR1CSProver<Bn254, HEIGHT, ANCHOR_CT>;
@@ -437,3 +448,596 @@ fn setup_and_prove_2_anchors_using_zk_proof() {
 	let res = verify::<Bn254>(&public_inputs_second_anchor, &vk_second_anchor, &proof).unwrap();
 	assert_eq!(res, true);
 }
+
+#[allow(clippy::type_complexity)]
+fn setup_root_tree_circuit(
+	edge_roots_ct: usize,
+	include_source_root: bool,
+) -> (
+	AnchorRootTreeCircuit<Bn254Fr, PoseidonGadget<Bn254Fr>, HEIGHT, ROOT_HEIGHT>,
+	Vec<Bn254Fr>,
+) {
+	let rng = &mut test_rng();
+	let curve = Curve::Bn254;
+
+	let params3 = setup_params::<Bn254Fr>(curve, 5, 3);
+	let params4 = setup_params::<Bn254Fr>(curve, 5, 4);
+	let tree_hasher = Poseidon::<Bn254Fr> { params: params3 };
+	let leaf_hasher = Poseidon::<Bn254Fr> { params: params4 };
+
+	let chain_id_u64 = 1u64;
+	let chain_id = Bn254Fr::from(chain_id_u64);
+	let arbitrary_input = Bn254Fr::rand(rng);
+
+	let leaf =
+		AnchorR1CSProver_Bn254_Poseidon_30::create_random_leaf(curve, chain_id_u64, rng).unwrap();
+	let secret = Bn254Fr::from_be_bytes_mod_order(&leaf.secret_bytes);
+	let nullifier = Bn254Fr::from_be_bytes_mod_order(&leaf.nullifier_bytes);
+	let nullifier_hash = Bn254Fr::from_be_bytes_mod_order(&leaf.nullifier_hash_bytes);
+	let leaves = vec![Bn254Fr::from_be_bytes_mod_order(&leaf.leaf_bytes)];
+	let (tree, path) = setup_tree_and_create_path::<Bn254Fr, Poseidon<Bn254Fr>, HEIGHT>(
+		&tree_hasher,
+		&leaves,
+		0,
+		&DEFAULT_LEAF,
+	)
+	.unwrap();
+
+	// The source root sits among the roots of the other edges
+	let mut edge_roots = (0..edge_roots_ct)
+		.map(|_| Bn254Fr::rand(rng))
+		.collect::<Vec<_>>();
+	if include_source_root {
+		edge_roots[edge_roots_ct / 2] = tree.root();
+	}
+	let root_tree = build_root_tree::<Bn254Fr, Poseidon<Bn254Fr>, ROOT_HEIGHT>(
+		&edge_roots,
+		&tree_hasher,
+		&DEFAULT_LEAF,
+	)
+	.unwrap();
+	let root_of_roots = root_tree.root();
+	let root_path = root_tree.generate_membership_proof((edge_roots_ct / 2) as u64);
+
+	let circuit = AnchorRootTreeCircuit::new(
+		arbitrary_input,
+		secret,
+		nullifier,
+		chain_id,
+		root_of_roots,
+		path,
+		root_path,
+		nullifier_hash,
+		tree_hasher,
+		leaf_hasher,
+	);
+	// The root of roots takes the place of the root set
+	let public_inputs = AnchorPublicInputs::<Bn254Fr, 1> {
+		nullifier_hash,
+		arbitrary_input,
+		chain_id,
+		roots: [root_of_roots],
+	}
+	.to_field_vec();
+
+	(circuit, public_inputs)
+}
+
+#[test]
+fn setup_and_prove_anchor_with_root_tree() {
+	let rng = &mut test_rng();
+
+	// More edges than the root set of the regular Anchor, with the same
+	// number of public inputs
+	let (circuit, public_inputs) = setup_root_tree_circuit(12, true);
+	assert_eq!(public_inputs.len(), 4);
+
+	let (pk, vk) = setup_keys::<Bn254, _, _>(circuit.clone(), rng).unwrap();
+	let proof = prove::<Bn254, _, _>(circuit, &pk, rng).unwrap();
+	let res = verify::<Bn254>(&public_inputs, &vk, &proof).unwrap();
+	assert!(res);
+
+	// A different root of roots doesn't verify
+	let mut wrong_public_inputs =
+		AnchorPublicInputs::<Bn254Fr, 1>::from_field_slice(&public_inputs).unwrap();
+	wrong_public_inputs.roots[0] = Bn254Fr::rand(rng);
+	let res = verify::<Bn254>(&wrong_public_inputs.to_field_vec(), &vk, &proof).unwrap();
+	assert!(!res);
+}
+
+#[test]
+fn should_fail_with_root_outside_root_tree() {
+	// Root tree that doesn't contain the source root
+	let (circuit, _) = setup_root_tree_circuit(3, false);
+
+	let cs = ConstraintSystem::<Bn254Fr>::new_ref();
+	circuit.generate_constraints(cs.clone()).unwrap();
+	assert!(!cs.is_satisfied().unwrap());
+}
+
+#[test]
+fn setup_and_prove_dynamic_depth_anchor_with_edges_of_different_heights() {
+	let rng = &mut test_rng();
+	let curve = Curve::Bn254;
+
+	let params3 = setup_params::<Bn254Fr>(curve, 5, 3);
+	let tree_hasher = Poseidon::<Bn254Fr> { params: params3 };
+
+	// One set of keys for every tree height up to HEIGHT
+	let (circuit, ..) = AnchorR1CSProver_Bn254_Poseidon_30::setup_dynamic_depth_random_circuit(
+		curve,
+		DEFAULT_LEAF,
+		rng,
+	)
+	.unwrap();
+	let (pk, vk) = setup_keys_unchecked::<Bn254, _, _>(circuit, rng).unwrap();
+
+	// Two edges deployed with different heights
+	let chain_id = 1u64;
+	let depths = [8, 16];
+	let deposits = depths.map(|_| {
+		AnchorR1CSProver_Bn254_Poseidon_30::create_random_leaf(curve, chain_id, rng).unwrap()
+	});
+	let mut root_set = [Bn254Fr::from(0u64); ANCHOR_CT];
+	for (i, (depth, deposit)) in depths.iter().zip(deposits.iter()).enumerate() {
+		let leaves = vec![Bn254Fr::from_be_bytes_mod_order(&deposit.leaf_bytes)];
+		let (root, _) = setup_tree_and_create_path_at_depth::<Bn254Fr, Poseidon<Bn254Fr>, HEIGHT>(
+			&tree_hasher,
+			&leaves,
+			0,
+			*depth,
+			&DEFAULT_LEAF,
+		)
+		.unwrap();
+		root_set[i] = root;
+	}
+	let root_set_raw: Vec<Vec<u8>> = root_set
+		.iter()
+		.map(|x| x.into_repr().to_bytes_be())
+		.collect();
+
+	for (depth, deposit) in depths.into_iter().zip(deposits) {
+		let proof = AnchorR1CSProver_Bn254_Poseidon_30::create_dynamic_depth_proof(
+			curve,
+			chain_id,
+			deposit.secret_bytes,
+			deposit.nullifier_bytes,
+			vec![deposit.leaf_bytes],
+			0,
+			depth,
+			root_set_raw.clone(),
+			Bn254Fr::one().into_repr().to_bytes_be(),
+			Bn254Fr::zero().into_repr().to_bytes_be(),
+			0,
+			0,
+			Bn254Fr::zero().into_repr().to_bytes_be(),
+			pk.clone(),
+			DEFAULT_LEAF,
+			rng,
+		)
+		.unwrap();
+
+		let res =
+			verify_unchecked_raw::<Bn254>(&proof.public_inputs_raw, &vk, &proof.proof).unwrap();
+		assert!(res);
+	}
+}
+
+#[test]
+fn setup_and_prove_anchor_with_fewer_roots_than_slots() {
+	let rng = &mut test_rng();
+	let curve = Curve::Bn254;
+
+	let params3 = setup_params::<Bn254Fr>(curve, 5, 3);
+	let tree_hasher = Poseidon::<Bn254Fr> { params: params3 };
+
+	let (circuit, ..) =
+		AnchorR1CSProver_Bn254_Poseidon_30::setup_random_circuit(curve, DEFAULT_LEAF, rng).unwrap();
+	let (pk, vk) = setup_keys_unchecked::<Bn254, _, _>(circuit, rng).unwrap();
+
+	let chain_id = 1u64;
+	let leaf =
+		AnchorR1CSProver_Bn254_Poseidon_30::create_random_leaf(curve, chain_id, rng).unwrap();
+	let leaves = vec![Bn254Fr::from_be_bytes_mod_order(&leaf.leaf_bytes)];
+	let (tree, _) = setup_tree_and_create_path::<Bn254Fr, Poseidon<Bn254Fr>, HEIGHT>(
+		&tree_hasher,
+		&leaves,
+		0,
+		&DEFAULT_LEAF,
+	)
+	.unwrap();
+
+	// Only one of the ANCHOR_CT slots is used, the other one is padded
+	let proof = AnchorR1CSProver_Bn254_Poseidon_30::create_proof(
+		curve,
+		chain_id,
+		leaf.secret_bytes,
+		leaf.nullifier_bytes,
+		vec![leaf.leaf_bytes],
+		0,
+		vec![tree.root().into_repr().to_bytes_be()],
+		vec![0u8; 32],
+		vec![0u8; 32],
+		0,
+		0,
+		vec![0u8; 32],
+		pk,
+		DEFAULT_LEAF,
+		rng,
+	)
+	.unwrap();
+
+	assert_eq!(proof.roots_raw.len(), ANCHOR_CT);
+	assert_eq!(
+		Bn254Fr::from_be_bytes_mod_order(&proof.roots_raw[1]),
+		Bn254Fr::zero()
+	);
+	let res = verify_unchecked_raw::<Bn254>(&proof.public_inputs_raw, &vk, &proof.proof).unwrap();
+	assert!(res);
+}
+
+#[test]
+fn should_reject_invalid_root_set_sizes() {
+	let rng = &mut test_rng();
+	let curve = Curve::Bn254;
+
+	let (circuit, ..) =
+		AnchorR1CSProver_Bn254_Poseidon_30::setup_random_circuit(curve, DEFAULT_LEAF, rng).unwrap();
+	let (pk, _) = setup_keys_unchecked::<Bn254, _, _>(circuit, rng).unwrap();
+
+	let chain_id = 1u64;
+	let leaf =
+		AnchorR1CSProver_Bn254_Poseidon_30::create_random_leaf(curve, chain_id, rng).unwrap();
+	let root = Bn254Fr::rand(rng).into_repr().to_bytes_be();
+
+	for root_set in [vec![], vec![root; ANCHOR_CT + 1]] {
+		let res = AnchorR1CSProver_Bn254_Poseidon_30::create_proof(
+			curve,
+			chain_id,
+			leaf.secret_bytes.clone(),
+			leaf.nullifier_bytes.clone(),
+			vec![leaf.leaf_bytes.clone()],
+			0,
+			root_set,
+			vec![0u8; 32],
+			vec![0u8; 32],
+			0,
+			0,
+			vec![0u8; 32],
+			pk.clone(),
+			DEFAULT_LEAF,
+			rng,
+		);
+		assert!(res.is_err());
+	}
+}
+
+/// Creates `n` deposits in a single Anchor tree, returning the deposits and
+/// the root set holding the root of that tree
+fn setup_association_deposits(
+	curve: Curve,
+	chain_id: u64,
+	n: usize,
+	rng: &mut StdRng,
+) -> (Vec<Leaf>, Vec<Vec<u8>>) {
+	let params3 = setup_params::<Bn254Fr>(curve, 5, 3);
+	let tree_hasher = Poseidon::<Bn254Fr> { params: params3 };
+	let deposits: Vec<Leaf> = (0..n)
+		.map(|_| {
+			AnchorR1CSProver_Bn254_Poseidon_30::create_random_leaf(curve, chain_id, rng).unwrap()
+		})
+		.collect();
+	let leaves: Vec<Bn254Fr> = deposits
+		.iter()
+		.map(|x| Bn254Fr::from_be_bytes_mod_order(&x.leaf_bytes))
+		.collect();
+	let (tree, _) = setup_tree_and_create_path::<Bn254Fr, Poseidon<Bn254Fr>, HEIGHT>(
+		&tree_hasher,
+		&leaves,
+		0,
+		&DEFAULT_LEAF,
+	)
+	.unwrap();
+	(deposits, vec![tree.root().into_repr().to_bytes_be()])
+}
+
+#[allow(clippy::too_many_arguments)]
+fn create_association_proof(
+	curve: Curve,
+	chain_id: u64,
+	deposits: &[Leaf],
+	index: usize,
+	root_set: Vec<Vec<u8>>,
+	approved_leaves: Vec<Vec<u8>>,
+	association_enabled: bool,
+	pk: Vec<u8>,
+	rng: &mut StdRng,
+) -> Result<AnchorProof, Error> {
+	AnchorR1CSProver_Bn254_Poseidon_30::create_association_proof::<_, ASSOCIATION_HEIGHT>(
+		curve,
+		chain_id,
+		deposits[index].secret_bytes.clone(),
+		deposits[index].nullifier_bytes.clone(),
+		deposits.iter().map(|x| x.leaf_bytes.clone()).collect(),
+		index as u64,
+		root_set,
+		approved_leaves,
+		association_enabled,
+		vec![0u8; 32],
+		vec![0u8; 32],
+		0,
+		0,
+		vec![0u8; 32],
+		pk,
+		DEFAULT_LEAF,
+		rng,
+	)
+}
+
+#[test]
+fn setup_and_prove_anchor_in_association_set() {
+	let rng = &mut test_rng();
+	let curve = Curve::Bn254;
+
+	let params3 = setup_params::<Bn254Fr>(curve, 5, 3);
+	let tree_hasher = Poseidon::<Bn254Fr> { params: params3 };
+
+	let (circuit, ..) = AnchorR1CSProver_Bn254_Poseidon_30::setup_association_random_circuit::<
+		_,
+		ASSOCIATION_HEIGHT,
+	>(curve, DEFAULT_LEAF, rng)
+	.unwrap();
+	let (pk, vk) = setup_keys_unchecked::<Bn254, _, _>(circuit, rng).unwrap();
+
+	let chain_id = 1u64;
+	let (deposits, root_set) = setup_association_deposits(curve, chain_id, 3, rng);
+	// Only the first and the last deposits are in the association set
+	let approved_leaves = vec![
+		deposits[0].leaf_bytes.clone(),
+		deposits[2].leaf_bytes.clone(),
+	];
+
+	let proof = create_association_proof(
+		curve,
+		chain_id,
+		&deposits,
+		2,
+		root_set,
+		approved_leaves.clone(),
+		true,
+		pk,
+		rng,
+	)
+	.unwrap();
+
+	let approved_leaves_f: Vec<Bn254Fr> = approved_leaves
+		.iter()
+		.map(|x| Bn254Fr::from_be_bytes_mod_order(x))
+		.collect();
+	let association_tree = build_association_tree::<_, _, ASSOCIATION_HEIGHT>(
+		&approved_leaves_f,
+		&tree_hasher,
+		&DEFAULT_LEAF,
+	)
+	.unwrap();
+	let public_inputs = AnchorAssociationPublicInputs::<Bn254Fr, ANCHOR_CT>::from_bytes_slice(
+		&proof.public_inputs_raw,
+	)
+	.unwrap();
+	assert_eq!(public_inputs.association_root, association_tree.root());
+	let res = verify_unchecked_typed::<Bn254, _>(&public_inputs, &vk, &proof.proof).unwrap();
+	assert!(res);
+}
+
+#[test]
+fn should_fail_with_leaf_outside_association_set() {
+	let rng = &mut test_rng();
+	let curve = Curve::Bn254;
+
+	let (circuit, ..) = AnchorR1CSProver_Bn254_Poseidon_30::setup_association_random_circuit::<
+		_,
+		ASSOCIATION_HEIGHT,
+	>(curve, DEFAULT_LEAF, rng)
+	.unwrap();
+	let (pk, vk) = setup_keys_unchecked::<Bn254, _, _>(circuit, rng).unwrap();
+
+	let chain_id = 1u64;
+	let (deposits, root_set) = setup_association_deposits(curve, chain_id, 2, rng);
+	let approved_leaves = vec![deposits[0].leaf_bytes.clone()];
+
+	// The prover has no association path for a deposit that isn't approved
+	let res = create_association_proof(
+		curve,
+		chain_id,
+		&deposits,
+		1,
+		root_set.clone(),
+		approved_leaves.clone(),
+		true,
+		pk.clone(),
+		rng,
+	);
+	assert!(res.is_err());
+
+	// A proof made with the association mode disabled can't be passed off as
+	// an association proof by flipping the mode input
+	let proof = create_association_proof(
+		curve,
+		chain_id,
+		&deposits,
+		1,
+		root_set,
+		approved_leaves,
+		false,
+		pk,
+		rng,
+	)
+	.unwrap();
+	let mut public_inputs = AnchorAssociationPublicInputs::<Bn254Fr, ANCHOR_CT>::from_bytes_slice(
+		&proof.public_inputs_raw,
+	)
+	.unwrap();
+	public_inputs.association_enabled = Bn254Fr::one();
+	let res = verify_unchecked_typed::<Bn254, _>(&public_inputs, &vk, &proof.proof).unwrap();
+	assert!(!res);
+}
+
+#[test]
+fn setup_and_prove_anchor_with_association_disabled() {
+	let rng = &mut test_rng();
+	let curve = Curve::Bn254;
+
+	let (circuit, ..) = AnchorR1CSProver_Bn254_Poseidon_30::setup_association_random_circuit::<
+		_,
+		ASSOCIATION_HEIGHT,
+	>(curve, DEFAULT_LEAF, rng)
+	.unwrap();
+	let (pk, vk) = setup_keys_unchecked::<Bn254, _, _>(circuit, rng).unwrap();
+
+	let chain_id = 1u64;
+	let (deposits, root_set) = setup_association_deposits(curve, chain_id, 2, rng);
+
+	// Without the association mode, the deposit doesn't need to be approved
+	let proof = create_association_proof(
+		curve,
+		chain_id,
+		&deposits,
+		1,
+		root_set,
+		vec![deposits[0].leaf_bytes.clone()],
+		false,
+		pk,
+		rng,
+	)
+	.unwrap();
+
+	let public_inputs = AnchorAssociationPublicInputs::<Bn254Fr, ANCHOR_CT>::from_bytes_slice(
+		&proof.public_inputs_raw,
+	)
+	.unwrap();
+	assert_eq!(public_inputs.association_enabled, Bn254Fr::zero());
+	let res = verify_unchecked_typed::<Bn254, _>(&public_inputs, &vk, &proof.proof).unwrap();
+	assert!(res);
+}
+
+fn create_chain_bound_proof(
+	curve: Curve,
+	chain_id: u64,
+	deposits: &[Leaf],
+	index: usize,
+	root_set: Vec<Vec<u8>>,
+	pk: Vec<u8>,
+	rng: &mut StdRng,
+) -> Result<AnchorProof, Error> {
+	AnchorR1CSProver_Bn254_Poseidon_30::create_chain_bound_proof(
+		curve,
+		chain_id,
+		deposits[index].secret_bytes.clone(),
+		deposits[index].nullifier_bytes.clone(),
+		deposits.iter().map(|x| x.leaf_bytes.clone()).collect(),
+		index as u64,
+		root_set,
+		vec![0u8; 32],
+		vec![0u8; 32],
+		0,
+		0,
+		vec![0u8; 32],
+		pk,
+		DEFAULT_LEAF,
+		rng,
+	)
+}
+
+#[test]
+fn setup_and_prove_chain_bound_anchor() {
+	let rng = &mut test_rng();
+	let curve = Curve::Bn254;
+
+	let params3 = setup_params::<Bn254Fr>(curve, 5, 3);
+	let tree_hasher = Poseidon::<Bn254Fr> { params: params3 };
+
+	let (circuit, ..) = AnchorR1CSProver_Bn254_Poseidon_30::setup_chain_bound_random_circuit(
+		curve,
+		DEFAULT_LEAF,
+		rng,
+	)
+	.unwrap();
+	let (pk, vk) = setup_keys_unchecked::<Bn254, _, _>(circuit, rng).unwrap();
+
+	let chain_id = 1u64;
+	let (deposits, root_set) = setup_association_deposits(curve, chain_id, 2, rng);
+	let proof = create_chain_bound_proof(curve, chain_id, &deposits, 1, root_set, pk, rng).unwrap();
+
+	let nullifier = Bn254Fr::from_be_bytes_mod_order(&deposits[1].nullifier_bytes);
+	let nullifier_hash = Bn254Fr::from_be_bytes_mod_order(&proof.nullifier_hash_raw);
+	assert_eq!(
+		nullifier_hash,
+		tree_hasher
+			.hash_two(&nullifier, &Bn254Fr::from(chain_id))
+			.unwrap()
+	);
+	// The chain-bound nullifier hash differs from the one of the plain Anchor
+	assert_ne!(proof.nullifier_hash_raw, deposits[1].nullifier_hash_bytes);
+	// and from the one the same note would have on any other chain
+	assert_ne!(
+		nullifier_hash,
+		tree_hasher
+			.hash_two(&nullifier, &Bn254Fr::from(chain_id + 1))
+			.unwrap()
+	);
+
+	let res = verify_unchecked_raw::<Bn254>(&proof.public_inputs_raw, &vk, &proof.proof).unwrap();
+	assert!(res);
+}
+
+#[test]
+fn should_fail_to_replay_chain_bound_proof_on_another_chain() {
+	let rng = &mut test_rng();
+	let curve = Curve::Bn254;
+
+	let params3 = setup_params::<Bn254Fr>(curve, 5, 3);
+	let tree_hasher = Poseidon::<Bn254Fr> { params: params3 };
+
+	let (circuit, ..) = AnchorR1CSProver_Bn254_Poseidon_30::setup_chain_bound_random_circuit(
+		curve,
+		DEFAULT_LEAF,
+		rng,
+	)
+	.unwrap();
+	let (pk, vk) = setup_keys_unchecked::<Bn254, _, _>(circuit, rng).unwrap();
+
+	let chain_id = 1u64;
+	let other_chain_id = 2u64;
+	let (deposits, root_set) = setup_association_deposits(curve, chain_id, 1, rng);
+	let proof = create_chain_bound_proof(
+		curve,
+		chain_id,
+		&deposits,
+		0,
+		root_set.clone(),
+		pk.clone(),
+		rng,
+	)
+	.unwrap();
+
+	// Replaying the proof against the chain id and nullifier hash of another
+	// chain doesn't verify
+	let nullifier = Bn254Fr::from_be_bytes_mod_order(&deposits[0].nullifier_bytes);
+	let other_nullifier_hash = tree_hasher
+		.hash_two(&nullifier, &Bn254Fr::from(other_chain_id))
+		.unwrap();
+	let mut public_inputs =
+		AnchorPublicInputs::<Bn254Fr, ANCHOR_CT>::from_bytes_slice(&proof.public_inputs_raw)
+			.unwrap();
+	public_inputs.nullifier_hash = other_nullifier_hash;
+	public_inputs.chain_id = Bn254Fr::from(other_chain_id);
+	let res = verify_unchecked_typed::<Bn254, _>(&public_inputs, &vk, &proof.proof).unwrap();
+	assert!(!res);
+
+	// Neither does a fresh proof for the same note on another chain, since the
+	// leaf commits to the chain it was deposited on
+	let proof =
+		create_chain_bound_proof(curve, other_chain_id, &deposits, 0, root_set, pk, rng).unwrap();
+	let res = verify_unchecked_raw::<Bn254>(&proof.public_inputs_raw, &vk, &proof.proof).unwrap();
+	assert!(!res);
+}
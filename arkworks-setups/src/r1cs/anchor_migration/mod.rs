@@ -0,0 +1,260 @@
+use crate::{
+	common::*,
+	public_inputs::{AnchorMigrationPublicInputs, PublicInputs},
+	r1cs::anchor::AnchorR1CSProver,
+	utxo::Utxo,
+	AnchorProver,
+};
+use ark_crypto_primitives::Error;
+use ark_ec::PairingEngine;
+use ark_ff::{BigInteger, PrimeField};
+use ark_std::{
+	marker::PhantomData,
+	rand::{CryptoRng, RngCore},
+	vec,
+	vec::Vec,
+	UniformRand,
+};
+use arkworks_native_gadgets::poseidon::{FieldHasher, Poseidon};
+use arkworks_r1cs_circuits::anchor_migration::AnchorMigrationCircuit;
+use arkworks_r1cs_gadgets::poseidon::PoseidonGadget;
+use arkworks_utils::Curve;
+
+#[cfg(test)]
+mod tests;
+
+pub type PoseidonAnchorMigrationCircuit<F, const N: usize, const M: usize> =
+	AnchorMigrationCircuit<F, PoseidonGadget<F>, N, M>;
+
+/// Prover for the migration of the deposits of an Anchor with the given
+/// `HEIGHT` and `ANCHOR_CT` into VAnchor Utxos
+pub struct AnchorMigrationR1CSProver<E: PairingEngine, const HEIGHT: usize, const ANCHOR_CT: usize>
+{
+	engine: PhantomData<E>,
+}
+
+impl<E: PairingEngine, const HEIGHT: usize, const ANCHOR_CT: usize>
+	AnchorMigrationR1CSProver<E, HEIGHT, ANCHOR_CT>
+{
+	pub fn construct_public_inputs(
+		chain_id: E::Fr,
+		nullifier_hash: E::Fr,
+		roots: [E::Fr; ANCHOR_CT],
+		denomination: E::Fr,
+		out_commitment: E::Fr,
+	) -> Vec<E::Fr> {
+		AnchorMigrationPublicInputs {
+			nullifier_hash,
+			denomination,
+			chain_id,
+			roots,
+			out_commitment,
+		}
+		.to_field_vec()
+	}
+
+	#[allow(dead_code)]
+	#[allow(clippy::type_complexity)]
+	pub fn setup_random_circuit<R: CryptoRng + RngCore>(
+		curve: Curve,
+		default_leaf: [u8; 32],
+		rng: &mut R,
+	) -> Result<
+		(
+			PoseidonAnchorMigrationCircuit<E::Fr, HEIGHT, ANCHOR_CT>,
+			E::Fr,
+			Vec<E::Fr>,
+		),
+		Error,
+	> {
+		let params2 = setup_params::<E::Fr>(curve, 5, 2);
+		let params3 = setup_params::<E::Fr>(curve, 5, 3);
+		let params5 = setup_params::<E::Fr>(curve, 5, 5);
+		let keypair_hasher = Poseidon::<E::Fr> { params: params2 };
+		let tree_hasher = Poseidon::<E::Fr> { params: params3 };
+		let leaf_hasher = Poseidon::<E::Fr> { params: params5 };
+
+		let chain_id = 1u64;
+		let denomination = E::Fr::rand(rng);
+		let leaf =
+			AnchorR1CSProver::<E, HEIGHT, ANCHOR_CT>::create_random_leaf(curve, chain_id, rng)?;
+		let leaves = vec![E::Fr::from_be_bytes_mod_order(&leaf.leaf_bytes)];
+		let (tree, _) = setup_tree_and_create_path::<E::Fr, Poseidon<E::Fr>, HEIGHT>(
+			&tree_hasher,
+			&leaves,
+			0,
+			&default_leaf,
+		)?;
+		let mut root_set = [E::Fr::rand(rng); ANCHOR_CT];
+		root_set[0] = tree.root();
+		let out_utxo = Utxo::new(
+			chain_id,
+			denomination,
+			None,
+			None,
+			None,
+			&keypair_hasher,
+			&leaf_hasher,
+			rng,
+		)?;
+
+		Self::setup_circuit_with_privates(
+			curve,
+			E::Fr::from(chain_id),
+			E::Fr::from_be_bytes_mod_order(&leaf.secret_bytes),
+			E::Fr::from_be_bytes_mod_order(&leaf.nullifier_bytes),
+			&leaves,
+			0,
+			root_set,
+			denomination,
+			&out_utxo,
+			default_leaf,
+		)
+	}
+
+	/// Sets up a circuit spending the deposit at `index` in `leaves` into
+	/// `out_utxo`, whose amount must be the denomination. Returns the circuit,
+	/// the nullifier hash and the public inputs.
+	#[allow(dead_code)]
+	#[allow(clippy::too_many_arguments)]
+	#[allow(clippy::type_complexity)]
+	pub fn setup_circuit_with_privates(
+		curve: Curve,
+		chain_id: E::Fr,
+		secret: E::Fr,
+		nullifier: E::Fr,
+		leaves: &[E::Fr],
+		index: u64,
+		roots: [E::Fr; ANCHOR_CT],
+		denomination: E::Fr,
+		out_utxo: &Utxo<E::Fr>,
+		default_leaf: [u8; 32],
+	) -> Result<
+		(
+			PoseidonAnchorMigrationCircuit<E::Fr, HEIGHT, ANCHOR_CT>,
+			E::Fr,
+			Vec<E::Fr>,
+		),
+		Error,
+	> {
+		let params3 = setup_params::<E::Fr>(curve, 5, 3);
+		let params4 = setup_params::<E::Fr>(curve, 5, 4);
+		let params5 = setup_params::<E::Fr>(curve, 5, 5);
+		let tree_hasher = Poseidon::<E::Fr> { params: params3 };
+		let leaf_hasher = Poseidon::<E::Fr> { params: params4 };
+		let utxo_hasher = Poseidon::<E::Fr> { params: params5 };
+		let nullifier_hash = tree_hasher.hash_two(&nullifier, &nullifier)?;
+		let (_, path) = setup_tree_and_create_path::<E::Fr, Poseidon<E::Fr>, HEIGHT>(
+			&tree_hasher,
+			leaves,
+			index,
+			&default_leaf,
+		)?;
+
+		let mc = AnchorMigrationCircuit::new(
+			denomination,
+			secret,
+			nullifier,
+			chain_id,
+			roots,
+			path,
+			nullifier_hash,
+			out_utxo.commitment,
+			out_utxo.chain_id,
+			out_utxo.keypair.public_key,
+			out_utxo.blinding,
+			tree_hasher,
+			leaf_hasher,
+			utxo_hasher,
+		);
+		let public_inputs = Self::construct_public_inputs(
+			chain_id,
+			nullifier_hash,
+			roots,
+			denomination,
+			out_utxo.commitment,
+		);
+		Ok((mc, nullifier_hash, public_inputs))
+	}
+
+	/// Spends the Anchor deposit `leaf` at `index` in `leaves` and creates a
+	/// VAnchor Utxo of `denomination` on `out_chain_id`, owned by
+	/// `out_private_key`. Returns the proof and the new Utxo, whose index is
+	/// set once its commitment is inserted in the VAnchor tree.
+	#[allow(clippy::too_many_arguments)]
+	pub fn create_proof<R: RngCore + CryptoRng>(
+		curve: Curve,
+		chain_id: u64,
+		leaf: Leaf,
+		leaves: Vec<Vec<u8>>,
+		index: u64,
+		root_set: Vec<Vec<u8>>,
+		denomination: u128,
+		out_chain_id: u64,
+		out_private_key: Vec<u8>,
+		pk: Vec<u8>,
+		default_leaf: [u8; 32],
+		rng: &mut R,
+	) -> Result<(AnchorMigrationProof, Utxo<E::Fr>), Error> {
+		let params2 = setup_params::<E::Fr>(curve, 5, 2);
+		let params5 = setup_params::<E::Fr>(curve, 5, 5);
+		let keypair_hasher = Poseidon::<E::Fr> { params: params2 };
+		let utxo_hasher = Poseidon::<E::Fr> { params: params5 };
+		// Get field element version of all the data
+		let secret_f = E::Fr::from_be_bytes_mod_order(&leaf.secret_bytes);
+		let nullifier_f = E::Fr::from_be_bytes_mod_order(&leaf.nullifier_bytes);
+		let leaves_f: Vec<E::Fr> = leaves
+			.iter()
+			.map(|x| E::Fr::from_be_bytes_mod_order(x))
+			.collect();
+		let root_set_f = pad_root_set::<E::Fr, ANCHOR_CT>(&root_set)?;
+		let denomination_f = E::Fr::from(denomination);
+
+		// The Utxo receiving the funds of the deposit
+		let out_utxo = Utxo::new_with_privates(
+			out_chain_id,
+			denomination_f,
+			None,
+			E::Fr::from_be_bytes_mod_order(&out_private_key),
+			E::Fr::rand(rng),
+			&keypair_hasher,
+			&utxo_hasher,
+		)?;
+
+		let (mc, nullifier_hash, public_inputs) = Self::setup_circuit_with_privates(
+			curve,
+			E::Fr::from(chain_id),
+			secret_f,
+			nullifier_f,
+			&leaves_f,
+			index,
+			root_set_f,
+			denomination_f,
+			&out_utxo,
+			default_leaf,
+		)?;
+
+		let nullifier_hash_raw = nullifier_hash.into_repr().to_bytes_be();
+		let commitment_raw = out_utxo.commitment.into_repr().to_bytes_be();
+		let roots_raw = root_set_f
+			.iter()
+			.map(|v| v.into_repr().to_bytes_be())
+			.collect();
+		let public_inputs_raw =
+			AnchorMigrationPublicInputs::<E::Fr, ANCHOR_CT>::from_field_slice(&public_inputs)?
+				.to_bytes_vec();
+
+		let proof = prove_unchecked::<E, _, _>(mc, &pk, rng)?;
+
+		Ok((
+			AnchorMigrationProof {
+				proof,
+				nullifier_hash_raw,
+				commitment_raw,
+				roots_raw,
+				public_inputs_raw,
+			},
+			out_utxo,
+		))
+	}
+}
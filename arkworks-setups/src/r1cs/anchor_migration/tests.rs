@@ -0,0 +1,139 @@
+use super::{setup_params, AnchorMigrationR1CSProver};
+use crate::{
+	common::*,
+	public_inputs::{AnchorMigrationPublicInputs, PublicInputs},
+	r1cs::anchor::AnchorR1CSProver,
+	AnchorProver,
+};
+use ark_bn254::{Bn254, Fr as Bn254Fr};
+use ark_ff::{BigInteger, PrimeField, UniformRand};
+use ark_std::{rand::rngs::StdRng, test_rng, vec, vec::Vec};
+use arkworks_native_gadgets::poseidon::{FieldHasher, Poseidon};
+use arkworks_utils::Curve;
+
+pub const HEIGHT: usize = 30;
+pub const ANCHOR_CT: usize = 2;
+
+#[allow(non_camel_case_types)]
+type AnchorMigrationR1CSProver_Bn254_Poseidon_30 =
+	AnchorMigrationR1CSProver<Bn254, HEIGHT, ANCHOR_CT>;
+pub const DEFAULT_LEAF: [u8; 32] = [0u8; 32];
+
+// A deposit in an Anchor tree on chain 1, with the root set holding its root
+fn setup_deposit(rng: &mut StdRng) -> (Leaf, Vec<Vec<u8>>) {
+	let curve = Curve::Bn254;
+	let tree_hasher = Poseidon::<Bn254Fr>::new(setup_params(curve, 5, 3));
+	let leaf =
+		AnchorR1CSProver::<Bn254, HEIGHT, ANCHOR_CT>::create_random_leaf(curve, 1, rng).unwrap();
+	let (tree, _) = setup_tree_and_create_path::<Bn254Fr, Poseidon<Bn254Fr>, HEIGHT>(
+		&tree_hasher,
+		&[Bn254Fr::from_be_bytes_mod_order(&leaf.leaf_bytes)],
+		0,
+		&DEFAULT_LEAF,
+	)
+	.unwrap();
+	(leaf, vec![tree.root().into_repr().to_bytes_be()])
+}
+
+#[test]
+fn setup_random_anchor_migration() {
+	let rng = &mut test_rng();
+	let curve = Curve::Bn254;
+
+	let (circuit, _, public_inputs) =
+		AnchorMigrationR1CSProver_Bn254_Poseidon_30::setup_random_circuit(curve, DEFAULT_LEAF, rng)
+			.unwrap();
+	let (pk, vk) = setup_keys::<Bn254, _, _>(circuit.clone(), rng).unwrap();
+	let proof = prove::<Bn254, _, _>(circuit, &pk, rng).unwrap();
+	let res = verify::<Bn254>(&public_inputs, &vk, &proof).unwrap();
+	assert!(res);
+}
+
+#[test]
+fn should_migrate_anchor_deposit_into_utxo() {
+	let rng = &mut test_rng();
+	let curve = Curve::Bn254;
+
+	let (circuit, ..) =
+		AnchorMigrationR1CSProver_Bn254_Poseidon_30::setup_random_circuit(curve, DEFAULT_LEAF, rng)
+			.unwrap();
+	let (pk, vk) = setup_keys_unchecked::<Bn254, _, _>(circuit, rng).unwrap();
+
+	let (leaf, root_set) = setup_deposit(rng);
+	let leaf_bytes = leaf.leaf_bytes.clone();
+	let nullifier_hash_bytes = leaf.nullifier_hash_bytes.clone();
+	let private_key = Bn254Fr::rand(rng);
+	let (proof, utxo) = AnchorMigrationR1CSProver_Bn254_Poseidon_30::create_proof(
+		curve,
+		1,
+		leaf,
+		vec![leaf_bytes],
+		0,
+		root_set,
+		10,
+		2,
+		private_key.into_repr().to_bytes_be(),
+		pk,
+		DEFAULT_LEAF,
+		rng,
+	)
+	.unwrap();
+
+	let res = verify_unchecked_raw::<Bn254>(&proof.public_inputs_raw, &vk, &proof.proof).unwrap();
+	assert!(res);
+
+	// The deposit is spent with its Anchor nullifier hash
+	assert_eq!(proof.nullifier_hash_raw, nullifier_hash_bytes);
+	// The new Utxo holds the denomination and is owned by the given key
+	let keypair_hasher = Poseidon::<Bn254Fr>::new(setup_params(curve, 5, 2));
+	let leaf_hasher = Poseidon::<Bn254Fr>::new(setup_params(curve, 5, 5));
+	let public_key = keypair_hasher.hash(&[private_key]).unwrap();
+	let commitment = leaf_hasher
+		.hash(&[
+			Bn254Fr::from(2u64),
+			Bn254Fr::from(10u64),
+			public_key,
+			utxo.blinding,
+		])
+		.unwrap();
+	assert_eq!(utxo.commitment, commitment);
+	assert_eq!(proof.commitment_raw, commitment.into_repr().to_bytes_be());
+}
+
+#[test]
+fn should_fail_with_invalid_denomination() {
+	let rng = &mut test_rng();
+	let curve = Curve::Bn254;
+
+	let (circuit, ..) =
+		AnchorMigrationR1CSProver_Bn254_Poseidon_30::setup_random_circuit(curve, DEFAULT_LEAF, rng)
+			.unwrap();
+	let (pk, vk) = setup_keys_unchecked::<Bn254, _, _>(circuit, rng).unwrap();
+
+	let (leaf, root_set) = setup_deposit(rng);
+	let leaf_bytes = leaf.leaf_bytes.clone();
+	let (proof, _) = AnchorMigrationR1CSProver_Bn254_Poseidon_30::create_proof(
+		curve,
+		1,
+		leaf,
+		vec![leaf_bytes],
+		0,
+		root_set,
+		10,
+		2,
+		Bn254Fr::rand(rng).into_repr().to_bytes_be(),
+		pk,
+		DEFAULT_LEAF,
+		rng,
+	)
+	.unwrap();
+
+	// The Utxo can't hold more than the denomination of the Anchor
+	let mut public_inputs = AnchorMigrationPublicInputs::<Bn254Fr, ANCHOR_CT>::from_bytes_slice(
+		&proof.public_inputs_raw,
+	)
+	.unwrap();
+	public_inputs.denomination = Bn254Fr::from(11u64);
+	let res = verify_unchecked_typed::<Bn254, _>(&public_inputs, &vk, &proof.proof).unwrap();
+	assert!(!res);
+}
@@ -0,0 +1,368 @@
+use crate::{
+	common::*,
+	public_inputs::{BalanceThresholdPublicInputs, PublicInputs},
+	utxo::Utxo,
+};
+use ark_crypto_primitives::Error;
+use ark_ec::PairingEngine;
+use ark_ff::{BigInteger, PrimeField, Zero};
+use ark_std::{
+	collections::BTreeMap,
+	marker::PhantomData,
+	rand::{CryptoRng, Rng, RngCore},
+	vec::Vec,
+	UniformRand,
+};
+use arkworks_native_gadgets::{merkle_tree::Path, poseidon::Poseidon};
+use arkworks_r1cs_circuits::balance_threshold::BalanceThresholdCircuit;
+use arkworks_r1cs_gadgets::poseidon::PoseidonGadget;
+use arkworks_utils::Curve;
+
+#[cfg(test)]
+mod tests;
+
+#[derive(Debug)]
+pub enum BalanceThresholdError {
+	DuplicateUtxo,
+	InvalidInputChainId,
+	KeyMismatch,
+	MissingPrivateKey,
+	MissingLeaves,
+	TooManyUtxos,
+}
+
+impl core::fmt::Display for BalanceThresholdError {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		let msg = match self {
+			Self::DuplicateUtxo => ark_std::format!("Utxo is counted more than once"),
+			Self::InvalidInputChainId => ark_std::format!("Invalid input chain ID"),
+			Self::KeyMismatch => ark_std::format!("Utxos are owned by different keys"),
+			Self::MissingPrivateKey => ark_std::format!("Utxo has no private key"),
+			Self::MissingLeaves => ark_std::format!("No leaves for the chain of an input"),
+			Self::TooManyUtxos => ark_std::format!("More Utxos than circuit inputs"),
+		};
+		write!(f, "{}", msg)
+	}
+}
+
+impl ark_std::error::Error for BalanceThresholdError {}
+
+pub type PoseidonBalanceThresholdCircuit<
+	F,
+	const HEIGHT: usize,
+	const INS: usize,
+	const ANCHOR_CT: usize,
+> = BalanceThresholdCircuit<F, PoseidonGadget<F>, HEIGHT, INS, ANCHOR_CT>;
+
+/// Prover for balance thresholds over the Utxos of a VAnchor with the same
+/// `HEIGHT` and `ANCHOR_CT`, for up to `INS` Utxos
+pub struct BalanceThresholdR1CSProver<
+	E: PairingEngine,
+	const HEIGHT: usize,
+	const ANCHOR_CT: usize,
+	const INS: usize,
+> {
+	engine: PhantomData<E>,
+}
+
+impl<E: PairingEngine, const HEIGHT: usize, const ANCHOR_CT: usize, const INS: usize>
+	BalanceThresholdR1CSProver<E, HEIGHT, ANCHOR_CT, INS>
+{
+	/// Calculates the scoped nullifier of a Utxo, which must hold its private
+	/// key
+	pub fn create_scoped_nullifier(
+		curve: Curve,
+		utxo: &Utxo<E::Fr>,
+		scope: Vec<u8>,
+	) -> Result<Vec<u8>, Error> {
+		let params4 = setup_params::<E::Fr>(curve, 5, 4);
+		let nullifier_hasher = Poseidon::<E::Fr> { params: params4 };
+		let scope_f = E::Fr::from_be_bytes_mod_order(&scope);
+		let scoped_nullifier = utxo.calculate_scoped_nullifier(&scope_f, &nullifier_hasher)?;
+		Ok(scoped_nullifier.into_repr().to_bytes_be())
+	}
+
+	/// Sets up a circuit for Utxos with a zero amount and a zero threshold,
+	/// e.g. for generating the keys
+	#[allow(dead_code)]
+	pub fn setup_random_circuit<R: RngCore + CryptoRng>(
+		curve: Curve,
+		default_leaf: [u8; 32],
+		amount_bits: usize,
+		rng: &mut R,
+	) -> Result<PoseidonBalanceThresholdCircuit<E::Fr, HEIGHT, INS, ANCHOR_CT>, Error> {
+		// Initialize hashers
+		let params2 = setup_params::<E::Fr>(curve, 5, 2);
+		let params3 = setup_params::<E::Fr>(curve, 5, 3);
+		let params4 = setup_params::<E::Fr>(curve, 5, 4);
+		let params5 = setup_params::<E::Fr>(curve, 5, 5);
+		let keypair_hasher = Poseidon::<E::Fr> { params: params2 };
+		let tree_hasher = Poseidon::<E::Fr> { params: params3 };
+		let nullifier_hasher = Poseidon::<E::Fr> { params: params4 };
+		let leaf_hasher = Poseidon::<E::Fr> { params: params5 };
+
+		let chain_id: u64 = rng.gen();
+		let private_key = E::Fr::rand(rng);
+		let mut in_utxos = Vec::with_capacity(INS);
+		let mut in_paths = Vec::with_capacity(INS);
+		for _ in 0..INS {
+			let utxo = Utxo::new_with_privates(
+				chain_id,
+				E::Fr::zero(),
+				Some(0),
+				private_key,
+				E::Fr::rand(rng),
+				&keypair_hasher,
+				&leaf_hasher,
+			)?;
+			let (_, path) = setup_tree_and_create_path::<E::Fr, Poseidon<E::Fr>, HEIGHT>(
+				&tree_hasher,
+				&[utxo.commitment],
+				0,
+				&default_leaf,
+			)?;
+			in_utxos.push(utxo);
+			in_paths.push(path);
+		}
+		let in_utxos: [Utxo<E::Fr>; INS] = in_utxos
+			.try_into()
+			.unwrap_or_else(|_| unreachable!("one utxo per input"));
+
+		Self::setup_circuit(
+			E::Fr::from(chain_id),
+			E::Fr::zero(),
+			E::Fr::rand(rng),
+			in_utxos,
+			in_paths,
+			[E::Fr::rand(rng); ANCHOR_CT],
+			keypair_hasher,
+			tree_hasher,
+			nullifier_hasher,
+			leaf_hasher,
+			amount_bits,
+		)
+	}
+
+	/// Sets up the circuit, checking that every Utxo is owned by the key of
+	/// the first one
+	#[allow(clippy::too_many_arguments)]
+	pub fn setup_circuit(
+		chain_id: E::Fr,
+		threshold: E::Fr,
+		scope: E::Fr,
+		in_utxos: [Utxo<E::Fr>; INS],
+		in_paths: Vec<Path<E::Fr, Poseidon<E::Fr>, HEIGHT>>,
+		public_root_set: [E::Fr; ANCHOR_CT],
+		keypair_hasher: Poseidon<E::Fr>,
+		tree_hasher: Poseidon<E::Fr>,
+		nullifier_hasher: Poseidon<E::Fr>,
+		leaf_hasher: Poseidon<E::Fr>,
+		amount_bits: usize,
+	) -> Result<PoseidonBalanceThresholdCircuit<E::Fr, HEIGHT, INS, ANCHOR_CT>, Error> {
+		let private_key = in_utxos[0]
+			.keypair
+			.secret_key
+			.ok_or(BalanceThresholdError::MissingPrivateKey)?;
+		if in_utxos
+			.iter()
+			.any(|x| x.keypair.secret_key != Some(private_key))
+		{
+			return Err(BalanceThresholdError::KeyMismatch.into());
+		}
+
+		let scoped_nullifiers = in_utxos
+			.iter()
+			.map(|x| x.calculate_scoped_nullifier(&scope, &nullifier_hasher))
+			.collect::<Result<Vec<E::Fr>, Error>>()?;
+
+		let circuit = PoseidonBalanceThresholdCircuit::<E::Fr, HEIGHT, INS, ANCHOR_CT>::new(
+			threshold,
+			scope,
+			scoped_nullifiers,
+			in_utxos.iter().map(|x| x.amount).collect(),
+			in_utxos.iter().map(|x| x.blinding).collect(),
+			private_key,
+			chain_id,
+			public_root_set,
+			in_paths,
+			tree_hasher,
+			keypair_hasher,
+			leaf_hasher,
+			nullifier_hasher,
+			amount_bits,
+		);
+
+		Ok(circuit)
+	}
+
+	pub fn construct_public_inputs(
+		chain_id: E::Fr,
+		threshold: E::Fr,
+		scope: E::Fr,
+		roots: [E::Fr; ANCHOR_CT],
+		scoped_nullifiers: [E::Fr; INS],
+	) -> Vec<E::Fr> {
+		BalanceThresholdPublicInputs::<E::Fr, ANCHOR_CT, INS> {
+			threshold,
+			scope,
+			scoped_nullifiers,
+			chain_id,
+			roots,
+		}
+		.to_field_vec()
+	}
+
+	/// Proves that `in_utxos` hold at least `threshold` in total. There may be
+	/// fewer Utxos than `INS`, the remaining inputs are filled with Utxos of a
+	/// zero amount owned by the same key. The root set may hold up to
+	/// ANCHOR_CT roots.
+	#[allow(clippy::too_many_arguments)]
+	pub fn create_proof<R: RngCore + CryptoRng>(
+		curve: Curve,
+		chain_id: u64,
+		threshold: u128,
+		scope: Vec<u8>,
+		public_root_set: Vec<Vec<u8>>,
+		in_leaves: BTreeMap<u64, Vec<Vec<u8>>>,
+		in_utxos: Vec<Utxo<E::Fr>>,
+		amount_bits: usize,
+		pk: Vec<u8>,
+		default_leaf: [u8; 32],
+		rng: &mut R,
+	) -> Result<BalanceThresholdProof, Error> {
+		// Initialize hashers
+		let params2 = setup_params::<E::Fr>(curve, 5, 2);
+		let params3 = setup_params::<E::Fr>(curve, 5, 3);
+		let params4 = setup_params::<E::Fr>(curve, 5, 4);
+		let params5 = setup_params::<E::Fr>(curve, 5, 5);
+		let keypair_hasher = Poseidon::<E::Fr> { params: params2 };
+		let tree_hasher = Poseidon::<E::Fr> { params: params3 };
+		let nullifier_hasher = Poseidon::<E::Fr> { params: params4 };
+		let leaf_hasher = Poseidon::<E::Fr> { params: params5 };
+
+		// Cast as field elements
+		let chain_id_elt = E::Fr::from(chain_id);
+		let threshold_elt = E::Fr::from(threshold);
+		let scope_elt = E::Fr::from_be_bytes_mod_order(&scope);
+		let public_root_set_elt = pad_root_set::<E::Fr, ANCHOR_CT>(&public_root_set)?;
+
+		if in_utxos.len() > INS {
+			return Err(BalanceThresholdError::TooManyUtxos.into());
+		}
+		// A Utxo counted twice can't satisfy the circuit, so it is rejected
+		// before proving
+		for (i, utxo) in in_utxos.iter().enumerate() {
+			if in_utxos[..i]
+				.iter()
+				.any(|x| x.commitment == utxo.commitment)
+			{
+				return Err(BalanceThresholdError::DuplicateUtxo.into());
+			}
+		}
+		// Throw an error if chain IDs don't match the chain of the root set
+		if in_utxos.iter().any(|utxo| utxo.chain_id_raw != chain_id) {
+			return Err(BalanceThresholdError::InvalidInputChainId.into());
+		}
+		let private_key = in_utxos
+			.first()
+			.and_then(|x| x.keypair.secret_key)
+			.ok_or(BalanceThresholdError::MissingPrivateKey)?;
+
+		// Fill the unused inputs with Utxos of a zero amount
+		let mut in_utxos = in_utxos;
+		while in_utxos.len() < INS {
+			in_utxos.push(Utxo::new_with_privates(
+				chain_id,
+				E::Fr::zero(),
+				Some(0),
+				private_key,
+				E::Fr::rand(rng),
+				&keypair_hasher,
+				&leaf_hasher,
+			)?);
+		}
+
+		// Only the Utxos with a non zero amount need the tree of the chain
+		let tree = if in_utxos.iter().any(|utxo| utxo.amount != E::Fr::zero()) {
+			let leaves = in_leaves
+				.get(&chain_id)
+				.ok_or(BalanceThresholdError::MissingLeaves)?
+				.iter()
+				.map(|l| E::Fr::from_be_bytes_mod_order(l))
+				.collect::<Vec<E::Fr>>();
+			Some(create_merkle_tree::<E::Fr, Poseidon<E::Fr>, HEIGHT>(
+				&tree_hasher,
+				&leaves,
+				&default_leaf,
+			))
+		} else {
+			None
+		};
+
+		// Generate the paths for each UTXO
+		let mut in_paths = Vec::with_capacity(INS);
+		for utxo in in_utxos.iter() {
+			let path = match &tree {
+				Some(tree) if utxo.amount != E::Fr::zero() => {
+					tree.generate_membership_proof(utxo.index.unwrap_or_default())
+				}
+				// A Utxo with a zero amount isn't checked to be in the root set, so
+				// its path is taken from a tree holding only that Utxo
+				_ => {
+					let (_, path) = setup_tree_and_create_path::<E::Fr, Poseidon<E::Fr>, HEIGHT>(
+						&tree_hasher,
+						&[utxo.commitment],
+						0,
+						&default_leaf,
+					)?;
+					path
+				}
+			};
+			in_paths.push(path);
+		}
+
+		let mut scoped_nullifiers = [E::Fr::zero(); INS];
+		for (nullifier, utxo) in scoped_nullifiers.iter_mut().zip(in_utxos.iter()) {
+			*nullifier = utxo.calculate_scoped_nullifier(&scope_elt, &nullifier_hasher)?;
+		}
+		let in_utxos: [Utxo<E::Fr>; INS] = in_utxos
+			.try_into()
+			.unwrap_or_else(|_| unreachable!("one utxo per input"));
+
+		// Get the circuit
+		let circuit = Self::setup_circuit(
+			chain_id_elt,
+			threshold_elt,
+			scope_elt,
+			in_utxos,
+			in_paths,
+			public_root_set_elt,
+			keypair_hasher,
+			tree_hasher,
+			nullifier_hasher,
+			leaf_hasher,
+			amount_bits,
+		)?;
+
+		let proof = prove_unchecked::<E, _, _>(circuit, &pk, rng)?;
+
+		let public_inputs = BalanceThresholdPublicInputs::<E::Fr, ANCHOR_CT, INS> {
+			threshold: threshold_elt,
+			scope: scope_elt,
+			scoped_nullifiers,
+			chain_id: chain_id_elt,
+			roots: public_root_set_elt,
+		};
+		let scoped_nullifiers_raw = scoped_nullifiers
+			.iter()
+			.map(|x| x.into_repr().to_bytes_be())
+			.collect();
+		let public_inputs_raw = public_inputs.to_bytes_vec();
+
+		Ok(BalanceThresholdProof {
+			proof,
+			scoped_nullifiers_raw,
+			public_inputs_raw,
+		})
+	}
+}
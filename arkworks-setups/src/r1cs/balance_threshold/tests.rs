@@ -0,0 +1,258 @@
+use super::{setup_params, BalanceThresholdR1CSProver};
+use crate::{common::*, utxo::Utxo};
+use ark_bn254::{Bn254, Fr as BnFr};
+use ark_ff::{BigInteger, PrimeField, UniformRand};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystem};
+use ark_std::{collections::BTreeMap, rand::rngs::StdRng, test_rng, vec, vec::Vec};
+use arkworks_native_gadgets::poseidon::Poseidon;
+use arkworks_utils::Curve;
+
+const HEIGHT: usize = 30;
+const ANCHOR_CT: usize = 2;
+const INS: usize = 3;
+const AMOUNT_BITS: usize = 64;
+const DEFAULT_LEAF: [u8; 32] = [0u8; 32];
+
+#[allow(non_camel_case_types)]
+type BalanceThresholdR1CSProver_Bn254_Poseidon_30_3 =
+	BalanceThresholdR1CSProver<Bn254, HEIGHT, ANCHOR_CT, INS>;
+
+// Utxos of the given amounts on chain 0 owned by `private_key`, with the leaves
+// of their tree and its root
+#[allow(clippy::type_complexity)]
+fn setup_utxos(
+	private_key: BnFr,
+	amounts: &[u128],
+	rng: &mut StdRng,
+) -> (Vec<Utxo<BnFr>>, BTreeMap<u64, Vec<Vec<u8>>>, Vec<Vec<u8>>) {
+	let curve = Curve::Bn254;
+	let keypair_hasher = Poseidon::<BnFr>::new(setup_params(curve, 5, 2));
+	let tree_hasher = Poseidon::<BnFr>::new(setup_params(curve, 5, 3));
+	let leaf_hasher = Poseidon::<BnFr>::new(setup_params(curve, 5, 5));
+
+	let utxos = amounts
+		.iter()
+		.enumerate()
+		.map(|(index, amount)| {
+			Utxo::new_with_privates(
+				0,
+				BnFr::from(*amount),
+				Some(index as u64),
+				private_key,
+				BnFr::rand(rng),
+				&keypair_hasher,
+				&leaf_hasher,
+			)
+			.unwrap()
+		})
+		.collect::<Vec<_>>();
+	let leaves = utxos.iter().map(|x| x.commitment).collect::<Vec<_>>();
+	let tree =
+		create_merkle_tree::<BnFr, Poseidon<BnFr>, HEIGHT>(&tree_hasher, &leaves, &DEFAULT_LEAF);
+
+	let mut in_leaves = BTreeMap::new();
+	in_leaves.insert(
+		0,
+		leaves.iter().map(|x| x.into_repr().to_bytes_be()).collect(),
+	);
+	let root_set = vec![tree.root().into_repr().to_bytes_be()];
+	(utxos, in_leaves, root_set)
+}
+
+fn setup_keys_for_tests(rng: &mut StdRng) -> (Vec<u8>, Vec<u8>) {
+	let circuit = BalanceThresholdR1CSProver_Bn254_Poseidon_30_3::setup_random_circuit(
+		Curve::Bn254,
+		DEFAULT_LEAF,
+		AMOUNT_BITS,
+		rng,
+	)
+	.unwrap();
+	setup_keys_unchecked::<Bn254, _, _>(circuit, rng).unwrap()
+}
+
+#[test]
+fn should_satisfy_random_circuit() {
+	let rng = &mut test_rng();
+	let circuit = BalanceThresholdR1CSProver_Bn254_Poseidon_30_3::setup_random_circuit(
+		Curve::Bn254,
+		DEFAULT_LEAF,
+		AMOUNT_BITS,
+		rng,
+	)
+	.unwrap();
+
+	let cs = ConstraintSystem::<BnFr>::new_ref();
+	circuit.generate_constraints(cs.clone()).unwrap();
+	assert!(cs.is_satisfied().unwrap());
+}
+
+#[test]
+fn should_prove_balance_above_threshold() {
+	let rng = &mut test_rng();
+	let curve = Curve::Bn254;
+	let (pk, vk) = setup_keys_for_tests(rng);
+
+	// Two Utxos of the three inputs are used, the last one is padding
+	let (utxos, in_leaves, root_set) = setup_utxos(BnFr::rand(rng), &[5, 7], rng);
+	let proof = BalanceThresholdR1CSProver_Bn254_Poseidon_30_3::create_proof(
+		curve,
+		0,
+		12,
+		BnFr::from(1u64).into_repr().to_bytes_be(),
+		root_set,
+		in_leaves,
+		utxos,
+		AMOUNT_BITS,
+		pk,
+		DEFAULT_LEAF,
+		rng,
+	)
+	.unwrap();
+
+	assert_eq!(proof.scoped_nullifiers_raw.len(), INS);
+	let res = verify_unchecked_raw::<Bn254>(&proof.public_inputs_raw, &vk, &proof.proof).unwrap();
+	assert!(res);
+}
+
+#[test]
+fn should_fail_with_balance_below_threshold() {
+	let rng = &mut test_rng();
+	let curve = Curve::Bn254;
+	let (pk, vk) = setup_keys_for_tests(rng);
+
+	let (utxos, in_leaves, root_set) = setup_utxos(BnFr::rand(rng), &[5, 7], rng);
+	let proof = BalanceThresholdR1CSProver_Bn254_Poseidon_30_3::create_proof(
+		curve,
+		0,
+		13,
+		BnFr::from(1u64).into_repr().to_bytes_be(),
+		root_set,
+		in_leaves,
+		utxos,
+		AMOUNT_BITS,
+		pk,
+		DEFAULT_LEAF,
+		rng,
+	)
+	.unwrap();
+
+	let res = verify_unchecked_raw::<Bn254>(&proof.public_inputs_raw, &vk, &proof.proof).unwrap();
+	assert!(!res);
+}
+
+#[test]
+fn should_fail_when_counting_a_utxo_twice() {
+	let rng = &mut test_rng();
+	let curve = Curve::Bn254;
+	let (pk, _) = setup_keys_for_tests(rng);
+
+	let (utxos, in_leaves, root_set) = setup_utxos(BnFr::rand(rng), &[5], rng);
+	let res = BalanceThresholdR1CSProver_Bn254_Poseidon_30_3::create_proof(
+		curve,
+		0,
+		10,
+		BnFr::from(1u64).into_repr().to_bytes_be(),
+		root_set,
+		in_leaves,
+		vec![utxos[0].clone(), utxos[0].clone()],
+		AMOUNT_BITS,
+		pk,
+		DEFAULT_LEAF,
+		rng,
+	);
+	assert!(res.is_err());
+}
+
+#[test]
+fn should_not_satisfy_circuit_counting_a_utxo_twice() {
+	let rng = &mut test_rng();
+	let curve = Curve::Bn254;
+	let keypair_hasher = Poseidon::<BnFr>::new(setup_params(curve, 5, 2));
+	let tree_hasher = Poseidon::<BnFr>::new(setup_params(curve, 5, 3));
+	let nullifier_hasher = Poseidon::<BnFr>::new(setup_params(curve, 5, 4));
+	let leaf_hasher = Poseidon::<BnFr>::new(setup_params(curve, 5, 5));
+
+	// The first Utxo is counted twice, which would reach the threshold
+	let (utxos, in_leaves, root_set) = setup_utxos(BnFr::rand(rng), &[5, 7], rng);
+	let leaves = in_leaves[&0]
+		.iter()
+		.map(|x| BnFr::from_be_bytes_mod_order(x))
+		.collect::<Vec<_>>();
+	let tree =
+		create_merkle_tree::<BnFr, Poseidon<BnFr>, HEIGHT>(&tree_hasher, &leaves, &DEFAULT_LEAF);
+	let in_utxos = [utxos[0].clone(), utxos[1].clone(), utxos[0].clone()];
+	let in_paths = in_utxos
+		.iter()
+		.map(|x| tree.generate_membership_proof(x.index.unwrap()))
+		.collect();
+
+	let circuit = BalanceThresholdR1CSProver_Bn254_Poseidon_30_3::setup_circuit(
+		BnFr::from(0u64),
+		BnFr::from(17u64),
+		BnFr::from(1u64),
+		in_utxos,
+		in_paths,
+		pad_root_set(&root_set).unwrap(),
+		keypair_hasher,
+		tree_hasher,
+		nullifier_hasher,
+		leaf_hasher,
+		AMOUNT_BITS,
+	)
+	.unwrap();
+
+	let cs = ConstraintSystem::<BnFr>::new_ref();
+	circuit.generate_constraints(cs.clone()).unwrap();
+	assert!(!cs.is_satisfied().unwrap());
+}
+
+#[test]
+fn should_reject_utxos_of_different_keys() {
+	let rng = &mut test_rng();
+	let curve = Curve::Bn254;
+	let (pk, _) = setup_keys_for_tests(rng);
+
+	let (mut utxos, in_leaves, root_set) = setup_utxos(BnFr::rand(rng), &[5], rng);
+	let (other_utxos, ..) = setup_utxos(BnFr::rand(rng), &[7], rng);
+	utxos.extend(other_utxos);
+
+	let res = BalanceThresholdR1CSProver_Bn254_Poseidon_30_3::create_proof(
+		curve,
+		0,
+		12,
+		BnFr::from(1u64).into_repr().to_bytes_be(),
+		root_set,
+		in_leaves,
+		utxos,
+		AMOUNT_BITS,
+		pk,
+		DEFAULT_LEAF,
+		rng,
+	);
+	assert!(res.is_err());
+}
+
+#[test]
+fn scoped_nullifiers_should_differ_from_spend_nullifiers() {
+	let rng = &mut test_rng();
+	let curve = Curve::Bn254;
+	let nullifier_hasher = Poseidon::<BnFr>::new(setup_params(curve, 5, 4));
+
+	let (utxos, ..) = setup_utxos(BnFr::rand(rng), &[5], rng);
+	let spend_nullifier = utxos[0]
+		.calculate_nullifier(&nullifier_hasher)
+		.unwrap()
+		.into_repr()
+		.to_bytes_be();
+	let scoped_nullifiers = [1u64, 2].map(|scope| {
+		BalanceThresholdR1CSProver_Bn254_Poseidon_30_3::create_scoped_nullifier(
+			curve,
+			&utxos[0],
+			BnFr::from(scope).into_repr().to_bytes_be(),
+		)
+		.unwrap()
+	});
+
+	assert_ne!(scoped_nullifiers[0], scoped_nullifiers[1]);
+	assert!(!scoped_nullifiers.contains(&spend_nullifier));
+}
@@ -0,0 +1,219 @@
+use crate::{
+	common::*,
+	public_inputs::{BatchInsertPublicInputs, PublicInputs},
+};
+use ark_crypto_primitives::Error;
+use ark_ec::PairingEngine;
+use ark_ff::{BigInteger, PrimeField};
+use ark_std::{
+	collections::BTreeMap,
+	marker::PhantomData,
+	rand::{CryptoRng, RngCore},
+	vec::Vec,
+	UniformRand,
+};
+use arkworks_native_gadgets::{merkle_tree::batch_leaves_hash, poseidon::Poseidon};
+use arkworks_r1cs_circuits::batch_insert::BatchInsertCircuit;
+use arkworks_r1cs_gadgets::poseidon::PoseidonGadget;
+use arkworks_utils::Curve;
+
+#[cfg(test)]
+mod tests;
+
+#[derive(Debug)]
+pub enum BatchInsertError {
+	InvalidBatchSize,
+	IndexOutOfBounds,
+	SlotNotEmpty,
+}
+
+impl core::fmt::Display for BatchInsertError {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		let msg = match self {
+			Self::InvalidBatchSize => ark_std::format!("Invalid number of leaves in the batch"),
+			Self::IndexOutOfBounds => ark_std::format!("Batch does not fit in the tree"),
+			Self::SlotNotEmpty => ark_std::format!("Batch overwrites an existing leaf"),
+		};
+		write!(f, "{}", msg)
+	}
+}
+
+impl ark_std::error::Error for BatchInsertError {}
+
+pub fn construct_public_inputs<F: PrimeField>(
+	old_root: F,
+	new_root: F,
+	start_index: F,
+	leaves_hash: F,
+) -> Vec<F> {
+	BatchInsertPublicInputs {
+		old_root,
+		new_root,
+		start_index,
+		leaves_hash,
+	}
+	.to_field_vec()
+}
+
+pub type PoseidonBatchInsertCircuit<F, const N: usize, const K: usize> =
+	BatchInsertCircuit<F, PoseidonGadget<F>, N, K>;
+
+pub struct BatchInsertR1CSProver<E: PairingEngine, const HEIGHT: usize, const BATCH_SIZE: usize> {
+	engine: PhantomData<E>,
+}
+
+impl<E: PairingEngine, const HEIGHT: usize, const BATCH_SIZE: usize>
+	BatchInsertR1CSProver<E, HEIGHT, BATCH_SIZE>
+{
+	/// Sets up a circuit inserting a batch of random leaves into an empty tree
+	#[allow(dead_code)]
+	#[allow(clippy::type_complexity)]
+	pub fn setup_random_circuit<R: CryptoRng + RngCore>(
+		curve: Curve,
+		default_leaf: [u8; 32],
+		rng: &mut R,
+	) -> Result<
+		(
+			PoseidonBatchInsertCircuit<E::Fr, HEIGHT, BATCH_SIZE>,
+			Vec<E::Fr>,
+		),
+		Error,
+	> {
+		let params3 = setup_params(curve, 5, 3);
+		let poseidon = Poseidon::<E::Fr>::new(params3);
+
+		let mut tree =
+			create_merkle_tree::<E::Fr, Poseidon<E::Fr>, HEIGHT>(&poseidon, &[], &default_leaf);
+		let leaves = [(); BATCH_SIZE].map(|_| E::Fr::rand(rng));
+
+		let (circuit, .., public_inputs) =
+			Self::setup_circuit(curve, &mut tree, 0, leaves, default_leaf)?;
+		Ok((circuit, public_inputs))
+	}
+
+	/// Inserts `leaves` into `tree` at consecutive indices from
+	/// `start_index`, and sets up the circuit proving the insertion. The path
+	/// of every leaf is taken from the tree holding the leaves inserted before
+	/// it. The tree is left untouched if any of the slots is not empty.
+	#[allow(clippy::type_complexity)]
+	pub fn setup_circuit(
+		curve: Curve,
+		tree: &mut SMT<E::Fr, Poseidon<E::Fr>, HEIGHT>,
+		start_index: u64,
+		leaves: [E::Fr; BATCH_SIZE],
+		default_leaf: [u8; 32],
+	) -> Result<
+		(
+			PoseidonBatchInsertCircuit<E::Fr, HEIGHT, BATCH_SIZE>,
+			E::Fr,
+			E::Fr,
+			E::Fr,
+			Vec<E::Fr>,
+		),
+		Error,
+	> {
+		// Initialize hasher
+		let params3 = setup_params(curve, 5, 3);
+		let poseidon = Poseidon::<E::Fr>::new(params3);
+		let empty_leaf = E::Fr::from_be_bytes_mod_order(&default_leaf);
+
+		let end_index = start_index
+			.checked_add(BATCH_SIZE as u64)
+			.ok_or(BatchInsertError::IndexOutOfBounds)?;
+		if end_index > 1u64 << HEIGHT {
+			return Err(BatchInsertError::IndexOutOfBounds.into());
+		}
+
+		// Check that every slot is empty before touching the tree. The path
+		// holds both nodes of every level, so the slot is read from its first
+		// level rather than by recomputing the root.
+		let mut old_root = tree.root();
+		for index in start_index..end_index {
+			let path = tree.generate_membership_proof(index);
+			let (left, right) = path.path[0];
+			let slot = if index % 2 == 0 { left } else { right };
+			if slot != empty_leaf {
+				return Err(BatchInsertError::SlotNotEmpty.into());
+			}
+			// The root of an empty tree isn't stored, it is taken from the path
+			if tree.tree.is_empty() && index == start_index {
+				old_root = path.calculate_root(&empty_leaf, &poseidon)?;
+			}
+		}
+
+		// Insert the leaves one by one, collecting the paths
+		let mut paths = Vec::with_capacity(BATCH_SIZE);
+		for (index, leaf) in (start_index..end_index).zip(leaves.iter()) {
+			paths.push(tree.generate_membership_proof(index));
+
+			let mut pairs = BTreeMap::new();
+			pairs.insert(index as u32, *leaf);
+			tree.insert_batch(&pairs, &poseidon)?;
+		}
+		let new_root = tree.root();
+
+		let start_index_f = E::Fr::from(start_index);
+		let leaves_hash = batch_leaves_hash(&leaves, &poseidon)?;
+		let circuit = PoseidonBatchInsertCircuit::<E::Fr, HEIGHT, BATCH_SIZE>::new(
+			old_root,
+			new_root,
+			start_index_f,
+			leaves_hash,
+			leaves,
+			paths,
+			empty_leaf,
+			poseidon,
+		);
+		let public_inputs = construct_public_inputs(old_root, new_root, start_index_f, leaves_hash);
+
+		Ok((circuit, old_root, new_root, leaves_hash, public_inputs))
+	}
+
+	/// Creates a proof of appending `new_leaves` to the tree holding `leaves`
+	/// at its first indices
+	#[allow(clippy::too_many_arguments)]
+	pub fn create_proof<R: RngCore + CryptoRng>(
+		curve: Curve,
+		leaves: Vec<Vec<u8>>,
+		start_index: u64,
+		new_leaves: Vec<Vec<u8>>,
+		pk: Vec<u8>,
+		default_leaf: [u8; 32],
+		rng: &mut R,
+	) -> Result<BatchInsertProof, Error> {
+		let params3 = setup_params(curve, 5, 3);
+		let poseidon = Poseidon::<E::Fr>::new(params3);
+
+		let leaves_f: Vec<E::Fr> = leaves
+			.iter()
+			.map(|x| E::Fr::from_be_bytes_mod_order(x))
+			.collect();
+		let new_leaves_f: [E::Fr; BATCH_SIZE] = new_leaves
+			.iter()
+			.map(|x| E::Fr::from_be_bytes_mod_order(x))
+			.collect::<Vec<E::Fr>>()
+			.try_into()
+			.map_err(|_| BatchInsertError::InvalidBatchSize)?;
+
+		let mut tree = create_merkle_tree::<E::Fr, Poseidon<E::Fr>, HEIGHT>(
+			&poseidon,
+			&leaves_f,
+			&default_leaf,
+		);
+		let (circuit, old_root, new_root, leaves_hash, public_inputs) =
+			Self::setup_circuit(curve, &mut tree, start_index, new_leaves_f, default_leaf)?;
+
+		let public_inputs_raw =
+			BatchInsertPublicInputs::from_field_slice(&public_inputs)?.to_bytes_vec();
+
+		let proof = prove_unchecked::<E, _, _>(circuit, &pk, rng)?;
+
+		Ok(BatchInsertProof {
+			proof,
+			old_root_raw: old_root.into_repr().to_bytes_be(),
+			new_root_raw: new_root.into_repr().to_bytes_be(),
+			leaves_hash_raw: leaves_hash.into_repr().to_bytes_be(),
+			public_inputs_raw,
+		})
+	}
+}
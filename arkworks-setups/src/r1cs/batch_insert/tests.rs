@@ -0,0 +1,137 @@
+use super::{setup_params, BatchInsertR1CSProver};
+use crate::common::*;
+use ark_bn254::{Bn254, Fr as Bn254Fr};
+use ark_ff::{BigInteger, PrimeField, UniformRand};
+use ark_std::{test_rng, vec::Vec};
+use arkworks_native_gadgets::poseidon::Poseidon;
+use arkworks_utils::Curve;
+
+pub const HEIGHT: usize = 30;
+pub const BATCH_SIZE: usize = 4;
+
+#[allow(non_camel_case_types)]
+type BatchInsertR1CSProver_Bn254_Poseidon_30_4 = BatchInsertR1CSProver<Bn254, HEIGHT, BATCH_SIZE>;
+pub const DEFAULT_LEAF: [u8; 32] = [0u8; 32];
+
+#[test]
+fn setup_and_prove_random_batch_insert() {
+	let rng = &mut test_rng();
+	let curve = Curve::Bn254;
+
+	let (circuit, public_inputs) =
+		BatchInsertR1CSProver_Bn254_Poseidon_30_4::setup_random_circuit(curve, DEFAULT_LEAF, rng)
+			.unwrap();
+	let (pk, vk) = setup_keys::<Bn254, _, _>(circuit.clone(), rng).unwrap();
+	let proof = prove::<Bn254, _, _>(circuit, &pk, rng).unwrap();
+	let res = verify::<Bn254>(&public_inputs, &vk, &proof).unwrap();
+	assert!(res);
+}
+
+#[test]
+fn should_prove_consecutive_batches() {
+	let rng = &mut test_rng();
+	let curve = Curve::Bn254;
+
+	let params3 = setup_params::<Bn254Fr>(curve, 5, 3);
+	let tree_hasher = Poseidon::<Bn254Fr> { params: params3 };
+
+	let (circuit, _) =
+		BatchInsertR1CSProver_Bn254_Poseidon_30_4::setup_random_circuit(curve, DEFAULT_LEAF, rng)
+			.unwrap();
+	let (pk, vk) = setup_keys_unchecked::<Bn254, _, _>(circuit, rng).unwrap();
+
+	// A tree already holding a few leaves inserted on-chain
+	let mut leaves: Vec<Vec<u8>> = (0..3)
+		.map(|_| Bn254Fr::rand(rng).into_repr().to_bytes_be())
+		.collect();
+
+	for _ in 0..2 {
+		let new_leaves: Vec<Vec<u8>> = (0..BATCH_SIZE)
+			.map(|_| Bn254Fr::rand(rng).into_repr().to_bytes_be())
+			.collect();
+		let proof = BatchInsertR1CSProver_Bn254_Poseidon_30_4::create_proof(
+			curve,
+			leaves.clone(),
+			leaves.len() as u64,
+			new_leaves.clone(),
+			pk.clone(),
+			DEFAULT_LEAF,
+			rng,
+		)
+		.unwrap();
+
+		// The new root is the root of the tree holding all the leaves
+		leaves.extend(new_leaves);
+		let leaves_f: Vec<Bn254Fr> = leaves
+			.iter()
+			.map(|x| Bn254Fr::from_be_bytes_mod_order(x))
+			.collect();
+		let tree = create_merkle_tree::<Bn254Fr, Poseidon<Bn254Fr>, HEIGHT>(
+			&tree_hasher,
+			&leaves_f,
+			&DEFAULT_LEAF,
+		);
+		assert_eq!(
+			Bn254Fr::from_be_bytes_mod_order(&proof.new_root_raw),
+			tree.root()
+		);
+
+		let res =
+			verify_unchecked_raw::<Bn254>(&proof.public_inputs_raw, &vk, &proof.proof).unwrap();
+		assert!(res);
+	}
+}
+
+#[test]
+fn should_reject_invalid_batches() {
+	let rng = &mut test_rng();
+	let curve = Curve::Bn254;
+
+	let (circuit, _) =
+		BatchInsertR1CSProver_Bn254_Poseidon_30_4::setup_random_circuit(curve, DEFAULT_LEAF, rng)
+			.unwrap();
+	let (pk, _) = setup_keys_unchecked::<Bn254, _, _>(circuit, rng).unwrap();
+
+	let leaves: Vec<Vec<u8>> = (0..3)
+		.map(|_| Bn254Fr::rand(rng).into_repr().to_bytes_be())
+		.collect();
+	let new_leaves: Vec<Vec<u8>> = (0..BATCH_SIZE)
+		.map(|_| Bn254Fr::rand(rng).into_repr().to_bytes_be())
+		.collect();
+
+	// Overwriting the last existing leaf
+	let res = BatchInsertR1CSProver_Bn254_Poseidon_30_4::create_proof(
+		curve,
+		leaves.clone(),
+		2,
+		new_leaves.clone(),
+		pk.clone(),
+		DEFAULT_LEAF,
+		rng,
+	);
+	assert!(res.is_err());
+
+	// Batch of the wrong size
+	let res = BatchInsertR1CSProver_Bn254_Poseidon_30_4::create_proof(
+		curve,
+		leaves.clone(),
+		3,
+		new_leaves[1..].to_vec(),
+		pk.clone(),
+		DEFAULT_LEAF,
+		rng,
+	);
+	assert!(res.is_err());
+
+	// Batch past the last index of the tree
+	let res = BatchInsertR1CSProver_Bn254_Poseidon_30_4::create_proof(
+		curve,
+		leaves,
+		(1u64 << HEIGHT) - 2,
+		new_leaves,
+		pk,
+		DEFAULT_LEAF,
+		rng,
+	);
+	assert!(res.is_err());
+}
@@ -0,0 +1,326 @@
+use crate::{
+	common::*,
+	keypair::Keypair,
+	public_inputs::{CredentialPublicInputs, PublicInputs},
+};
+use ark_crypto_primitives::Error;
+use ark_ec::PairingEngine;
+use ark_ff::{BigInteger, PrimeField, Zero};
+use ark_std::{
+	marker::PhantomData,
+	rand::{CryptoRng, RngCore},
+	vec,
+	vec::Vec,
+	UniformRand,
+};
+use arkworks_native_gadgets::poseidon::{FieldHasher, Poseidon};
+use arkworks_r1cs_circuits::credential::{CredentialCircuit, Predicate, ATTRIBUTE_BITS};
+use arkworks_r1cs_gadgets::poseidon::PoseidonGadget;
+use arkworks_utils::Curve;
+
+#[cfg(test)]
+mod tests;
+
+/// Largest width of the Poseidon parameters, which bounds the number of
+/// attributes of a credential to `MAX_POSEIDON_WIDTH - 3`
+const MAX_POSEIDON_WIDTH: usize = 6;
+
+#[derive(Debug)]
+pub enum CredentialError {
+	TooManyAttributes,
+	NotHolder,
+	NotInRegistry,
+	PredicateNotSatisfied,
+}
+
+impl core::fmt::Display for CredentialError {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		let msg = match self {
+			Self::TooManyAttributes => ark_std::format!("Too many attributes for the leaf hasher"),
+			Self::NotHolder => ark_std::format!("Key is not the holder of the credential"),
+			Self::NotInRegistry => ark_std::format!("Credential is not in the registry"),
+			Self::PredicateNotSatisfied => {
+				ark_std::format!("Attributes don't satisfy the predicates")
+			}
+		};
+		write!(f, "{}", msg)
+	}
+}
+
+impl ark_std::error::Error for CredentialError {}
+
+/// A credential, the leaf `Hash(holder_pk, attributes, issuer_salt)` of the
+/// registry
+#[derive(Debug, Clone)]
+pub struct Credential<F: PrimeField, const N_ATTRS: usize> {
+	pub holder_pk: F,
+	pub attributes: [F; N_ATTRS],
+	pub issuer_salt: F,
+	pub leaf: F,
+}
+
+pub type PoseidonCredentialCircuit<F, const N: usize, const N_ATTRS: usize, const SET_SIZE: usize> =
+	CredentialCircuit<F, PoseidonGadget<F>, N, N_ATTRS, SET_SIZE>;
+
+/// Prover for credentials of `N_ATTRS` attributes in a registry of the given
+/// `HEIGHT`, whose set predicates take `SET_SIZE` values
+pub struct CredentialR1CSProver<
+	E: PairingEngine,
+	const HEIGHT: usize,
+	const N_ATTRS: usize,
+	const SET_SIZE: usize,
+> {
+	engine: PhantomData<E>,
+}
+
+impl<E: PairingEngine, const HEIGHT: usize, const N_ATTRS: usize, const SET_SIZE: usize>
+	CredentialR1CSProver<E, HEIGHT, N_ATTRS, SET_SIZE>
+{
+	/// The hasher of the credentials, taking `N_ATTRS + 2` inputs
+	fn setup_leaf_hasher(curve: Curve) -> Result<Poseidon<E::Fr>, Error> {
+		if N_ATTRS + 3 > MAX_POSEIDON_WIDTH {
+			return Err(CredentialError::TooManyAttributes.into());
+		}
+		Ok(Poseidon::new(setup_params(curve, 5, N_ATTRS as u8 + 3)))
+	}
+
+	/// The public key `Hash(holder_sk)` credentials are issued to
+	pub fn holder_public_key(curve: Curve, holder_sk: E::Fr) -> E::Fr {
+		let keypair_hasher = Poseidon::<E::Fr>::new(setup_params(curve, 5, 2));
+		Keypair::new(holder_sk, &keypair_hasher).public_key
+	}
+
+	/// Issues a credential with the given attributes to `holder_pk`, with a
+	/// random issuer salt
+	pub fn issue_credential<R: RngCore + CryptoRng>(
+		curve: Curve,
+		holder_pk: E::Fr,
+		attributes: [E::Fr; N_ATTRS],
+		rng: &mut R,
+	) -> Result<Credential<E::Fr, N_ATTRS>, Error> {
+		Self::issue_credential_with_salt(curve, holder_pk, attributes, E::Fr::rand(rng))
+	}
+
+	pub fn issue_credential_with_salt(
+		curve: Curve,
+		holder_pk: E::Fr,
+		attributes: [E::Fr; N_ATTRS],
+		issuer_salt: E::Fr,
+	) -> Result<Credential<E::Fr, N_ATTRS>, Error> {
+		let leaf_hasher = Self::setup_leaf_hasher(curve)?;
+		let mut inputs = vec![holder_pk];
+		inputs.extend_from_slice(&attributes);
+		inputs.push(issuer_salt);
+		let leaf = leaf_hasher.hash(&inputs)?;
+
+		Ok(Credential {
+			holder_pk,
+			attributes,
+			issuer_salt,
+			leaf,
+		})
+	}
+
+	/// Checks that `attributes` satisfy `predicates`, so no proof is created
+	/// for a statement the circuit would reject
+	pub fn check_predicates(
+		predicates: &[Predicate; N_ATTRS],
+		operands: &[[E::Fr; SET_SIZE]; N_ATTRS],
+		attributes: &[E::Fr; N_ATTRS],
+	) -> Result<(), Error> {
+		let fits = |value: &E::Fr| value.into_repr().num_bits() as usize <= ATTRIBUTE_BITS;
+		for ((predicate, values), attribute) in predicates.iter().zip(operands).zip(attributes) {
+			let is_satisfied = match predicate {
+				Predicate::None => true,
+				Predicate::Equal => *attribute == values[0],
+				Predicate::GreaterOrEqual => {
+					fits(attribute)
+						&& fits(&values[0]) && values[0].into_repr() <= attribute.into_repr()
+				}
+				Predicate::LessOrEqual => {
+					fits(attribute)
+						&& fits(&values[0]) && attribute.into_repr() <= values[0].into_repr()
+				}
+				Predicate::InSet => values.contains(attribute),
+			};
+			if !is_satisfied {
+				return Err(CredentialError::PredicateNotSatisfied.into());
+			}
+		}
+		Ok(())
+	}
+
+	/// Sets the operands that `predicates` don't use to zero, as the circuit
+	/// requires
+	pub fn clear_unused_operands(
+		predicates: &[Predicate; N_ATTRS],
+		mut operands: [[E::Fr; SET_SIZE]; N_ATTRS],
+	) -> [[E::Fr; SET_SIZE]; N_ATTRS] {
+		for (predicate, values) in predicates.iter().zip(operands.iter_mut()) {
+			for value in values[predicate.num_operands(SET_SIZE)..].iter_mut() {
+				*value = E::Fr::zero();
+			}
+		}
+		operands
+	}
+
+	pub fn construct_public_inputs(
+		root: E::Fr,
+		predicates: &[Predicate; N_ATTRS],
+		operands: &[[E::Fr; SET_SIZE]; N_ATTRS],
+	) -> Vec<E::Fr> {
+		CredentialPublicInputs::<E::Fr, N_ATTRS, SET_SIZE> {
+			root,
+			operands: Self::clear_unused_operands(predicates, *operands),
+		}
+		.to_field_vec()
+	}
+
+	/// Sets up a circuit for `predicates` with a random credential, and
+	/// operands that its attributes satisfy. Returns the circuit and the
+	/// public inputs.
+	#[allow(clippy::type_complexity)]
+	pub fn setup_random_circuit<R: CryptoRng + RngCore>(
+		curve: Curve,
+		predicates: [Predicate; N_ATTRS],
+		default_leaf: [u8; 32],
+		rng: &mut R,
+	) -> Result<
+		(
+			PoseidonCredentialCircuit<E::Fr, HEIGHT, N_ATTRS, SET_SIZE>,
+			Vec<E::Fr>,
+		),
+		Error,
+	> {
+		let holder_sk = E::Fr::rand(rng);
+		let holder_pk = Self::holder_public_key(curve, holder_sk);
+		let mut attributes = [E::Fr::from(0u64); N_ATTRS];
+		for attribute in attributes.iter_mut() {
+			*attribute = E::Fr::from(rng.next_u32());
+		}
+		let credential = Self::issue_credential(curve, holder_pk, attributes, rng)?;
+
+		// Every predicate holds with the attribute itself as operands
+		let mut operands = [[E::Fr::from(0u64); SET_SIZE]; N_ATTRS];
+		for (values, attribute) in operands.iter_mut().zip(attributes.iter()) {
+			*values = [*attribute; SET_SIZE];
+		}
+
+		Self::setup_circuit(
+			curve,
+			predicates,
+			operands,
+			holder_sk,
+			&credential,
+			&[credential.leaf],
+			0,
+			default_leaf,
+		)
+	}
+
+	/// Sets up a circuit proving that the holder of `holder_sk` owns
+	/// `credential`, the leaf at `index` in `leaves`, and that its attributes
+	/// satisfy `predicates`. Returns the circuit and the public inputs.
+	#[allow(clippy::too_many_arguments)]
+	#[allow(clippy::type_complexity)]
+	pub fn setup_circuit(
+		curve: Curve,
+		predicates: [Predicate; N_ATTRS],
+		operands: [[E::Fr; SET_SIZE]; N_ATTRS],
+		holder_sk: E::Fr,
+		credential: &Credential<E::Fr, N_ATTRS>,
+		leaves: &[E::Fr],
+		index: u64,
+		default_leaf: [u8; 32],
+	) -> Result<
+		(
+			PoseidonCredentialCircuit<E::Fr, HEIGHT, N_ATTRS, SET_SIZE>,
+			Vec<E::Fr>,
+		),
+		Error,
+	> {
+		let keypair_hasher = Poseidon::<E::Fr>::new(setup_params(curve, 5, 2));
+		let tree_hasher = Poseidon::<E::Fr>::new(setup_params(curve, 5, 3));
+		let leaf_hasher = Self::setup_leaf_hasher(curve)?;
+
+		if Self::holder_public_key(curve, holder_sk) != credential.holder_pk {
+			return Err(CredentialError::NotHolder.into());
+		}
+		if leaves.get(index as usize) != Some(&credential.leaf) {
+			return Err(CredentialError::NotInRegistry.into());
+		}
+		Self::check_predicates(&predicates, &operands, &credential.attributes)?;
+
+		let (tree, path) = setup_tree_and_create_path::<E::Fr, Poseidon<E::Fr>, HEIGHT>(
+			&tree_hasher,
+			leaves,
+			index,
+			&default_leaf,
+		)?;
+		let root = tree.root();
+		let operands = Self::clear_unused_operands(&predicates, operands);
+
+		let circuit = CredentialCircuit::new(
+			predicates,
+			operands,
+			root,
+			holder_sk,
+			credential.attributes,
+			credential.issuer_salt,
+			path,
+			keypair_hasher,
+			tree_hasher,
+			leaf_hasher,
+		);
+		let public_inputs = Self::construct_public_inputs(root, &predicates, &operands);
+		Ok((circuit, public_inputs))
+	}
+
+	/// Proves that the holder of `holder_sk` owns `credential`, the leaf at
+	/// `index` in the registry `leaves`, and that its attributes satisfy
+	/// `predicates` with the given operands
+	#[allow(clippy::too_many_arguments)]
+	pub fn create_proof<R: RngCore + CryptoRng>(
+		curve: Curve,
+		predicates: [Predicate; N_ATTRS],
+		operands: [[E::Fr; SET_SIZE]; N_ATTRS],
+		holder_sk: Vec<u8>,
+		credential: &Credential<E::Fr, N_ATTRS>,
+		leaves: Vec<Vec<u8>>,
+		index: u64,
+		pk: Vec<u8>,
+		default_leaf: [u8; 32],
+		rng: &mut R,
+	) -> Result<CredentialProof, Error> {
+		// Get field element version of all the data
+		let holder_sk_f = E::Fr::from_be_bytes_mod_order(&holder_sk);
+		let leaves_f: Vec<E::Fr> = leaves
+			.iter()
+			.map(|x| E::Fr::from_be_bytes_mod_order(x))
+			.collect();
+
+		let (circuit, public_inputs) = Self::setup_circuit(
+			curve,
+			predicates,
+			operands,
+			holder_sk_f,
+			credential,
+			&leaves_f,
+			index,
+			default_leaf,
+		)?;
+
+		let public_inputs =
+			CredentialPublicInputs::<E::Fr, N_ATTRS, SET_SIZE>::from_field_slice(&public_inputs)?;
+		let root_raw = public_inputs.root.into_repr().to_bytes_be();
+		let public_inputs_raw = public_inputs.to_bytes_vec();
+
+		let proof = prove_unchecked::<E, _, _>(circuit, &pk, rng)?;
+
+		Ok(CredentialProof {
+			proof,
+			root_raw,
+			public_inputs_raw,
+		})
+	}
+}
@@ -0,0 +1,232 @@
+use super::{CredentialR1CSProver, PoseidonCredentialCircuit};
+use crate::{
+	common::*,
+	public_inputs::{CredentialPublicInputs, PublicInputs},
+};
+use ark_bn254::{Bn254, Fr as Bn254Fr};
+use ark_ff::{BigInteger, PrimeField, UniformRand};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystem};
+use ark_std::{test_rng, vec, vec::Vec};
+use arkworks_native_gadgets::poseidon::Poseidon;
+use arkworks_r1cs_circuits::credential::Predicate;
+use arkworks_utils::Curve;
+
+pub const HEIGHT: usize = 30;
+pub const N_ATTRS: usize = 3;
+pub const SET_SIZE: usize = 4;
+
+#[allow(non_camel_case_types)]
+type CredentialR1CSProver_Bn254_Poseidon_30_3_4 =
+	CredentialR1CSProver<Bn254, HEIGHT, N_ATTRS, SET_SIZE>;
+pub const DEFAULT_LEAF: [u8; 32] = [0u8; 32];
+
+// Attributes are (age, country code, score), the proofs show that the age is
+// at least 18 and the country is one of a set
+const PREDICATES: [Predicate; N_ATTRS] =
+	[Predicate::GreaterOrEqual, Predicate::InSet, Predicate::None];
+
+fn operands(min_age: u64) -> [[Bn254Fr; SET_SIZE]; N_ATTRS] {
+	let zero = Bn254Fr::from(0u64);
+	[
+		[Bn254Fr::from(min_age), zero, zero, zero],
+		[
+			Bn254Fr::from(250u64),
+			Bn254Fr::from(276u64),
+			Bn254Fr::from(380u64),
+			Bn254Fr::from(380u64),
+		],
+		[zero; SET_SIZE],
+	]
+}
+
+#[test]
+fn setup_random_credential() {
+	let rng = &mut test_rng();
+	let curve = Curve::Bn254;
+
+	let predicates = [Predicate::Equal, Predicate::LessOrEqual, Predicate::InSet];
+	let (circuit, public_inputs) =
+		CredentialR1CSProver_Bn254_Poseidon_30_3_4::setup_random_circuit(
+			curve,
+			predicates,
+			DEFAULT_LEAF,
+			rng,
+		)
+		.unwrap();
+	// The root and `SET_SIZE` operands per attribute, of which the equality
+	// and the bound only use the first one
+	let typed_inputs =
+		CredentialPublicInputs::<Bn254Fr, N_ATTRS, SET_SIZE>::from_field_slice(&public_inputs)
+			.unwrap();
+	assert!(typed_inputs.operands[0][1..]
+		.iter()
+		.chain(&typed_inputs.operands[1][1..])
+		.all(|x| *x == Bn254Fr::from(0u64)));
+
+	let (pk, vk) = setup_keys::<Bn254, _, _>(circuit.clone(), rng).unwrap();
+	let proof = prove::<Bn254, _, _>(circuit, &pk, rng).unwrap();
+	let res = verify::<Bn254>(&public_inputs, &vk, &proof).unwrap();
+	assert!(res);
+}
+
+#[test]
+fn should_prove_credential_predicates() {
+	let rng = &mut test_rng();
+	let curve = Curve::Bn254;
+
+	let (circuit, _) = CredentialR1CSProver_Bn254_Poseidon_30_3_4::setup_random_circuit(
+		curve,
+		PREDICATES,
+		DEFAULT_LEAF,
+		rng,
+	)
+	.unwrap();
+	let (pk, vk) = setup_keys_unchecked::<Bn254, _, _>(circuit, rng).unwrap();
+
+	// The issuer registers two credentials
+	let holder_sk = Bn254Fr::rand(rng);
+	let holder_pk = CredentialR1CSProver_Bn254_Poseidon_30_3_4::holder_public_key(curve, holder_sk);
+	let attributes = [
+		Bn254Fr::from(30u64),
+		Bn254Fr::from(276u64),
+		Bn254Fr::from(7u64),
+	];
+	let credential = CredentialR1CSProver_Bn254_Poseidon_30_3_4::issue_credential(
+		curve, holder_pk, attributes, rng,
+	)
+	.unwrap();
+	let other = CredentialR1CSProver_Bn254_Poseidon_30_3_4::issue_credential(
+		curve,
+		Bn254Fr::rand(rng),
+		attributes,
+		rng,
+	)
+	.unwrap();
+	let leaves: Vec<Vec<u8>> = vec![other.leaf, credential.leaf]
+		.iter()
+		.map(|x| x.into_repr().to_bytes_be())
+		.collect();
+
+	let mut proof = CredentialR1CSProver_Bn254_Poseidon_30_3_4::create_proof(
+		curve,
+		PREDICATES,
+		operands(18),
+		holder_sk.into_repr().to_bytes_be(),
+		&credential,
+		leaves.clone(),
+		1,
+		pk.clone(),
+		DEFAULT_LEAF,
+		rng,
+	)
+	.unwrap();
+	let res = verify_unchecked_raw::<Bn254>(&proof.public_inputs_raw, &vk, &proof.proof).unwrap();
+	assert!(res);
+
+	// The proof doesn't hold for a higher minimum age, or with a value in an
+	// unused operand
+	let public_inputs = CredentialPublicInputs::<Bn254Fr, N_ATTRS, SET_SIZE>::from_bytes_slice(
+		&proof.public_inputs_raw,
+	)
+	.unwrap();
+	let mut wrong_inputs = public_inputs;
+	wrong_inputs.operands[0][0] = Bn254Fr::from(31u64);
+	proof.public_inputs_raw = wrong_inputs.to_bytes_vec();
+	let res = verify_unchecked_raw::<Bn254>(&proof.public_inputs_raw, &vk, &proof.proof).unwrap();
+	assert!(!res);
+	let mut wrong_inputs = public_inputs;
+	wrong_inputs.operands[2][0] = Bn254Fr::from(1u64);
+	proof.public_inputs_raw = wrong_inputs.to_bytes_vec();
+	let res = verify_unchecked_raw::<Bn254>(&proof.public_inputs_raw, &vk, &proof.proof).unwrap();
+	assert!(!res);
+
+	// No proof is created for predicates the attributes don't satisfy
+	let res = CredentialR1CSProver_Bn254_Poseidon_30_3_4::create_proof(
+		curve,
+		PREDICATES,
+		operands(31),
+		holder_sk.into_repr().to_bytes_be(),
+		&credential,
+		leaves.clone(),
+		1,
+		pk.clone(),
+		DEFAULT_LEAF,
+		rng,
+	);
+	assert!(res.is_err());
+
+	// Nor for a credential of another holder
+	let res = CredentialR1CSProver_Bn254_Poseidon_30_3_4::create_proof(
+		curve,
+		PREDICATES,
+		operands(18),
+		holder_sk.into_repr().to_bytes_be(),
+		&other,
+		leaves,
+		0,
+		pk,
+		DEFAULT_LEAF,
+		rng,
+	);
+	assert!(res.is_err());
+}
+
+#[test]
+fn should_fail_with_attribute_below_bound() {
+	let rng = &mut test_rng();
+	let curve = Curve::Bn254;
+	let keypair_hasher = Poseidon::<Bn254Fr>::new(setup_params(curve, 5, 2));
+	let tree_hasher = Poseidon::<Bn254Fr>::new(setup_params(curve, 5, 3));
+	let leaf_hasher = Poseidon::<Bn254Fr>::new(setup_params(curve, 5, 6));
+
+	let holder_sk = Bn254Fr::rand(rng);
+	let holder_pk = CredentialR1CSProver_Bn254_Poseidon_30_3_4::holder_public_key(curve, holder_sk);
+	let attributes = [
+		Bn254Fr::from(17u64),
+		Bn254Fr::from(276u64),
+		Bn254Fr::from(7u64),
+	];
+	let credential = CredentialR1CSProver_Bn254_Poseidon_30_3_4::issue_credential(
+		curve, holder_pk, attributes, rng,
+	)
+	.unwrap();
+	let (tree, path) = setup_tree_and_create_path::<Bn254Fr, Poseidon<Bn254Fr>, HEIGHT>(
+		&tree_hasher,
+		&[credential.leaf],
+		0,
+		&DEFAULT_LEAF,
+	)
+	.unwrap();
+
+	// The prover helpers refuse to set up this circuit, build it directly
+	let circuit = PoseidonCredentialCircuit::<Bn254Fr, HEIGHT, N_ATTRS, SET_SIZE>::new(
+		PREDICATES,
+		operands(18),
+		tree.root(),
+		holder_sk,
+		attributes,
+		credential.issuer_salt,
+		path,
+		keypair_hasher,
+		tree_hasher,
+		leaf_hasher,
+	);
+	let cs = ConstraintSystem::<Bn254Fr>::new_ref();
+	circuit.generate_constraints(cs.clone()).unwrap();
+	assert!(!cs.is_satisfied().unwrap());
+}
+
+#[test]
+fn should_fail_with_too_many_attributes() {
+	let rng = &mut test_rng();
+	let curve = Curve::Bn254;
+
+	// Credentials of 4 attributes need a Poseidon hash of width 7
+	let res = CredentialR1CSProver::<Bn254, HEIGHT, 4, SET_SIZE>::issue_credential(
+		curve,
+		Bn254Fr::rand(rng),
+		[Bn254Fr::from(1u64); 4],
+		rng,
+	);
+	assert!(res.is_err());
+}
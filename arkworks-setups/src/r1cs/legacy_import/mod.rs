@@ -0,0 +1,316 @@
+use crate::{
+	common::*,
+	public_inputs::{LegacyImportPublicInputs, PublicInputs},
+	utxo::Utxo,
+};
+use ark_crypto_primitives::Error;
+use ark_ec::PairingEngine;
+use ark_ed_on_bn254::Fq;
+use ark_ff::{BigInteger, PrimeField};
+use ark_std::{
+	marker::PhantomData,
+	rand::{CryptoRng, RngCore},
+	string::ToString,
+	vec,
+	vec::Vec,
+	UniformRand,
+};
+use arkworks_native_gadgets::{
+	mimc::MiMCSponge,
+	poseidon::{FieldHasher, Poseidon},
+	tornado::{deposit_path, TornadoNote},
+};
+use arkworks_r1cs_circuits::legacy_import::{LegacyImportCircuit, LegacyImportOutput};
+use arkworks_r1cs_gadgets::poseidon::PoseidonGadget;
+use arkworks_utils::Curve;
+use codec::Encode;
+
+#[cfg(test)]
+mod tests;
+
+pub type PoseidonLegacyImportCircuit = LegacyImportCircuit<PoseidonGadget<Fq>>;
+
+/// Prover for the import of Tornado Cash deposits into Mixer notes or VAnchor
+/// Utxos. The circuit works over the base field of Baby Jubjub, so the
+/// engine must be BN254.
+pub struct LegacyImportR1CSProver<E: PairingEngine<Fr = Fq>> {
+	engine: PhantomData<E>,
+}
+
+impl<E: PairingEngine<Fr = Fq>> LegacyImportR1CSProver<E> {
+	pub fn construct_public_inputs(
+		nullifier_hash: Fq,
+		root: Fq,
+		arbitrary_input: Fq,
+		denomination: Fq,
+		out_commitment: Fq,
+		out_chain_id: Fq,
+	) -> Vec<Fq> {
+		LegacyImportPublicInputs {
+			nullifier_hash,
+			root,
+			arbitrary_input,
+			denomination,
+			out_commitment,
+			out_chain_id,
+		}
+		.to_field_vec()
+	}
+
+	/// Sets up a circuit importing a random Tornado Cash deposit into a
+	/// random Mixer note. Returns the circuit and the public inputs.
+	#[allow(dead_code)]
+	pub fn setup_random_mixer_circuit<R: CryptoRng + RngCore>(
+		curve: Curve,
+		rng: &mut R,
+	) -> Result<(PoseidonLegacyImportCircuit, Vec<Fq>), Error> {
+		let output = LegacyImportOutput::Mixer {
+			secret: Fq::rand(rng),
+			nullifier: Fq::rand(rng),
+		};
+		Self::setup_random_circuit(curve, output, rng)
+	}
+
+	/// Sets up a circuit importing a random Tornado Cash deposit into a
+	/// random VAnchor note. Returns the circuit and the public inputs.
+	#[allow(dead_code)]
+	pub fn setup_random_vanchor_circuit<R: CryptoRng + RngCore>(
+		curve: Curve,
+		rng: &mut R,
+	) -> Result<(PoseidonLegacyImportCircuit, Vec<Fq>), Error> {
+		let output = LegacyImportOutput::VAnchor {
+			chain_id: Fq::from(1u64),
+			pubkey: Fq::rand(rng),
+			blinding: Fq::rand(rng),
+		};
+		Self::setup_random_circuit(curve, output, rng)
+	}
+
+	fn setup_random_circuit<R: CryptoRng + RngCore>(
+		curve: Curve,
+		output: LegacyImportOutput,
+		rng: &mut R,
+	) -> Result<(PoseidonLegacyImportCircuit, Vec<Fq>), Error> {
+		let note = TornadoNote::generate("eth".to_string(), "1".to_string(), 1, rng);
+		let commitments = vec![note.commitment()?];
+		let (circuit, _, public_inputs) = Self::setup_circuit(
+			curve,
+			&note,
+			&commitments,
+			Fq::rand(rng),
+			Fq::rand(rng),
+			output,
+		)?;
+		Ok((circuit, public_inputs))
+	}
+
+	/// Sets up a circuit importing the deposit of `note`, whose commitment
+	/// must be in `commitments`, the leaves of the Tornado Cash tree in
+	/// deposit order, into `output`. Returns the circuit, the output
+	/// commitment and the public inputs.
+	pub fn setup_circuit(
+		curve: Curve,
+		note: &TornadoNote,
+		commitments: &[Fq],
+		arbitrary_input: Fq,
+		denomination: Fq,
+		output: LegacyImportOutput,
+	) -> Result<(PoseidonLegacyImportCircuit, Fq, Vec<Fq>), Error> {
+		let mimc = MiMCSponge::new(setup_mimc_sponge_params::<Fq>(curve));
+		let (root, path) = deposit_path(commitments, note, &mimc)?;
+		let nullifier_hash = note.nullifier_hash()?;
+
+		let out_chain_id = output.chain_id();
+		// The hasher of the output commitment
+		let (hasher, out_commitment) = match &output {
+			LegacyImportOutput::Mixer { secret, nullifier } => {
+				let hasher = Poseidon::<Fq>::new(setup_params(curve, 5, 3));
+				let commitment = hasher.hash_two(secret, nullifier)?;
+				(hasher, commitment)
+			}
+			LegacyImportOutput::VAnchor {
+				chain_id,
+				pubkey,
+				blinding,
+			} => {
+				let hasher = Poseidon::<Fq>::new(setup_params(curve, 5, 5));
+				let commitment = hasher.hash(&[*chain_id, denomination, *pubkey, *blinding])?;
+				(hasher, commitment)
+			}
+		};
+
+		let circuit = LegacyImportCircuit::new(
+			arbitrary_input,
+			denomination,
+			note.secret(),
+			note.nullifier(),
+			root,
+			path,
+			nullifier_hash,
+			output,
+			out_commitment,
+			mimc,
+			hasher,
+		);
+		let public_inputs = Self::construct_public_inputs(
+			nullifier_hash,
+			root,
+			arbitrary_input,
+			denomination,
+			out_commitment,
+			out_chain_id,
+		);
+		Ok((circuit, out_commitment, public_inputs))
+	}
+
+	/// Imports the Tornado Cash deposit of the note string `note` into the
+	/// Mixer note `out_leaf`, created by the `MixerProver` of the Mixer.
+	/// `commitments` are the leaves of the Tornado Cash tree, in deposit
+	/// order.
+	#[allow(clippy::too_many_arguments)]
+	pub fn create_mixer_proof<R: RngCore + CryptoRng>(
+		curve: Curve,
+		note: &str,
+		commitments: Vec<Vec<u8>>,
+		denomination: u128,
+		out_leaf: &Leaf,
+		recipient: Vec<u8>,
+		relayer: Vec<u8>,
+		fee: u128,
+		refund: u128,
+		pk: Vec<u8>,
+		rng: &mut R,
+	) -> Result<LegacyImportProof, Error> {
+		let output = LegacyImportOutput::Mixer {
+			secret: Fq::from_be_bytes_mod_order(&out_leaf.secret_bytes),
+			nullifier: Fq::from_be_bytes_mod_order(&out_leaf.nullifier_bytes),
+		};
+		let arbitrary_input = Self::create_arbitrary_input(recipient, relayer, fee, refund);
+		Self::create_proof(
+			curve,
+			note,
+			commitments,
+			arbitrary_input,
+			denomination,
+			output,
+			pk,
+			rng,
+		)
+	}
+
+	/// Imports the Tornado Cash deposit of the note string `note` into a
+	/// VAnchor Utxo of `denomination` on `out_chain_id`, owned by
+	/// `out_private_key`. Returns the proof and the new Utxo, whose index is
+	/// set once its commitment is inserted in the VAnchor tree.
+	#[allow(clippy::too_many_arguments)]
+	pub fn create_vanchor_proof<R: RngCore + CryptoRng>(
+		curve: Curve,
+		note: &str,
+		commitments: Vec<Vec<u8>>,
+		denomination: u128,
+		out_chain_id: u64,
+		out_private_key: Vec<u8>,
+		recipient: Vec<u8>,
+		relayer: Vec<u8>,
+		fee: u128,
+		refund: u128,
+		pk: Vec<u8>,
+		rng: &mut R,
+	) -> Result<(LegacyImportProof, Utxo<Fq>), Error> {
+		let params2 = setup_params::<Fq>(curve, 5, 2);
+		let params5 = setup_params::<Fq>(curve, 5, 5);
+		let keypair_hasher = Poseidon::<Fq> { params: params2 };
+		let utxo_hasher = Poseidon::<Fq> { params: params5 };
+
+		// The Utxo receiving the funds of the deposit
+		let out_utxo = Utxo::new_with_privates(
+			out_chain_id,
+			Fq::from(denomination),
+			None,
+			Fq::from_be_bytes_mod_order(&out_private_key),
+			Fq::rand(rng),
+			&keypair_hasher,
+			&utxo_hasher,
+		)?;
+		let output = LegacyImportOutput::VAnchor {
+			chain_id: out_utxo.chain_id,
+			pubkey: out_utxo.keypair.public_key,
+			blinding: out_utxo.blinding,
+		};
+
+		let arbitrary_input = Self::create_arbitrary_input(recipient, relayer, fee, refund);
+		let proof = Self::create_proof(
+			curve,
+			note,
+			commitments,
+			arbitrary_input,
+			denomination,
+			output,
+			pk,
+			rng,
+		)?;
+		Ok((proof, out_utxo))
+	}
+
+	/// Hashes the data of the import the proof is bound to, like the Mixer
+	/// does for its withdrawals
+	pub fn create_arbitrary_input(
+		recipient: Vec<u8>,
+		relayer: Vec<u8>,
+		fee: u128,
+		refund: u128,
+	) -> Fq {
+		let mut arbitrary_data_bytes = Vec::new();
+		arbitrary_data_bytes.extend(&recipient);
+		arbitrary_data_bytes.extend(&relayer);
+		// Using encode to be compatible with on chain types
+		arbitrary_data_bytes.extend(fee.encode());
+		arbitrary_data_bytes.extend(refund.encode());
+		let arbitrary_data = keccak_256(&arbitrary_data_bytes);
+		Fq::from_be_bytes_mod_order(&arbitrary_data)
+	}
+
+	#[allow(clippy::too_many_arguments)]
+	fn create_proof<R: RngCore + CryptoRng>(
+		curve: Curve,
+		note: &str,
+		commitments: Vec<Vec<u8>>,
+		arbitrary_input: Fq,
+		denomination: u128,
+		output: LegacyImportOutput,
+		pk: Vec<u8>,
+		rng: &mut R,
+	) -> Result<LegacyImportProof, Error> {
+		let note = TornadoNote::parse(note)?;
+		// Get field element version of all the data
+		let commitments_f: Vec<Fq> = commitments
+			.iter()
+			.map(|x| Fq::from_be_bytes_mod_order(x))
+			.collect();
+
+		let (circuit, out_commitment, public_inputs) = Self::setup_circuit(
+			curve,
+			&note,
+			&commitments_f,
+			arbitrary_input,
+			Fq::from(denomination),
+			output,
+		)?;
+
+		let public_inputs = LegacyImportPublicInputs::from_field_slice(&public_inputs)?;
+		let nullifier_hash_raw = public_inputs.nullifier_hash.into_repr().to_bytes_be();
+		let root_raw = public_inputs.root.into_repr().to_bytes_be();
+		let commitment_raw = out_commitment.into_repr().to_bytes_be();
+		let public_inputs_raw = public_inputs.to_bytes_vec();
+
+		let proof = prove_unchecked::<E, _, _>(circuit, &pk, rng)?;
+
+		Ok(LegacyImportProof {
+			proof,
+			nullifier_hash_raw,
+			commitment_raw,
+			root_raw,
+			public_inputs_raw,
+		})
+	}
+}
@@ -0,0 +1,275 @@
+use super::{setup_params, LegacyImportR1CSProver, PoseidonLegacyImportCircuit};
+use crate::{
+	common::*,
+	public_inputs::{LegacyImportPublicInputs, PublicInputs},
+	r1cs::mixer::MixerR1CSProver,
+	MixerProver,
+};
+use ark_bn254::{Bn254, Fr as Bn254Fr};
+use ark_ff::{BigInteger, Field, PrimeField, UniformRand};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystem};
+use ark_std::{string::ToString, test_rng, vec, vec::Vec};
+use arkworks_native_gadgets::{
+	mimc::MiMCSponge,
+	poseidon::{FieldHasher, Poseidon},
+	tornado::{deposit_path, TornadoNote, ZERO_LEAF},
+};
+use arkworks_r1cs_circuits::legacy_import::LegacyImportOutput;
+use arkworks_utils::Curve;
+use hex_literal::hex;
+
+#[allow(non_camel_case_types)]
+type LegacyImportR1CSProver_Bn254 = LegacyImportR1CSProver<Bn254>;
+
+// The roots of the empty subtrees of every level, from the leaf to the root of
+// the empty tree of height 20, as returned by `zeros` in Tornado Cash's
+// MerkleTreeWithHistory.sol
+const TORNADO_ZEROS: [[u8; 32]; 21] = [
+	hex!("2fe54c60d3acabf3343a35b6eba15db4821b340f76e741e2249685ed4899af6c"),
+	hex!("256a6135777eee2fd26f54b8b7037a25439d5235caee224154186d2b8a52e31d"),
+	hex!("1151949895e82ab19924de92c40a3d6f7bcb60d92b00504b8199613683f0c200"),
+	hex!("20121ee811489ff8d61f09fb89e313f14959a0f28bb428a20dba6b0b068b3bdb"),
+	hex!("0a89ca6ffa14cc462cfedb842c30ed221a50a3d6bf022a6a57dc82ab24c157c9"),
+	hex!("24ca05c2b5cd42e890d6be94c68d0689f4f21c9cec9c0f13fe41d566dfb54959"),
+	hex!("1ccb97c932565a92c60156bdba2d08f3bf1377464e025cee765679e604a7315c"),
+	hex!("19156fbd7d1a8bf5cba8909367de1b624534ebab4f0f79e003bccdd1b182bdb4"),
+	hex!("261af8c1f0912e465744641409f622d466c3920ac6e5ff37e36604cb11dfff80"),
+	hex!("0058459724ff6ca5a1652fcbc3e82b93895cf08e975b19beab3f54c217d1c007"),
+	hex!("1f04ef20dee48d39984d8eabe768a70eafa6310ad20849d4573c3c40c2ad1e30"),
+	hex!("1bea3dec5dab51567ce7e200a30f7ba6d4276aeaa53e2686f962a46c66d511e5"),
+	hex!("0ee0f941e2da4b9e31c3ca97a40d8fa9ce68d97c084177071b3cb46cd3372f0f"),
+	hex!("1ca9503e8935884501bbaf20be14eb4c46b89772c97b96e3b2ebf3a36a948bbd"),
+	hex!("133a80e30697cd55d8f7d4b0965b7be24057ba5dc3da898ee2187232446cb108"),
+	hex!("13e6d8fc88839ed76e182c2a779af5b2c0da9dd18c90427a644f7e148a6253b6"),
+	hex!("1eb16b057a477f4bc8f572ea6bee39561098f78f15bfb3699dcbb7bd8db61854"),
+	hex!("0da2cb16a1ceaabf1c16b838f7a9e3f2a3a3088d9e0a6debaa748114620696ea"),
+	hex!("24a3b3d822420b14b5d8cb6c28a574f01e98ea9e940551d2ebd75cee12649f9d"),
+	hex!("198622acbd783d1b0d9064105b1fc8e4d8889de95c4c519b3f635809fe6afc05"),
+	hex!("29d7ed391256ccc3ea596c86e933b89ff339d25ea8ddced975ae2fe30b5296d4"),
+];
+
+const NOTE: &str = "tornado-eth-0.1-1-0x0102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f202122232425262728292a2b2c2d2e2f303132333435363738393a3b3c3d3e";
+
+// The commitments of a Tornado Cash tree holding the deposit of `NOTE`
+fn setup_commitments(note: &TornadoNote) -> Vec<Vec<u8>> {
+	let rng = &mut test_rng();
+	let other = TornadoNote::generate("eth".to_string(), "0.1".to_string(), 1, rng);
+	vec![
+		other.commitment().unwrap().into_repr().to_bytes_be(),
+		note.commitment().unwrap().into_repr().to_bytes_be(),
+	]
+}
+
+#[test]
+fn zero_leaf_should_match_tornado() {
+	let zero_leaf = Bn254Fr::from_be_bytes_mod_order(&keccak_256(b"tornado"));
+	assert_eq!(zero_leaf, ZERO_LEAF);
+}
+
+#[test]
+fn empty_tree_should_match_tornado() {
+	let mimc = MiMCSponge::new(setup_mimc_sponge_params::<Bn254Fr>(Curve::Bn254));
+	let zeros = TORNADO_ZEROS
+		.iter()
+		.map(|x| Bn254Fr::from_be_bytes_mod_order(x))
+		.collect::<Vec<_>>();
+
+	assert_eq!(zeros[0], ZERO_LEAF);
+	for level in zeros.windows(2) {
+		assert_eq!(mimc.hash_two(&level[0], &level[0]).unwrap(), level[1]);
+	}
+
+	// The first deposit of a pool only has empty subtrees as siblings
+	let note = TornadoNote::parse(NOTE).unwrap();
+	let (_, path) = deposit_path(&[note.commitment().unwrap()], &note, &mimc).unwrap();
+	for (level, (_, sibling)) in path.path.iter().enumerate() {
+		assert_eq!(*sibling, zeros[level]);
+	}
+}
+
+#[test]
+fn setup_random_legacy_import() {
+	let rng = &mut test_rng();
+	let curve = Curve::Bn254;
+
+	let (circuit, public_inputs) =
+		LegacyImportR1CSProver_Bn254::setup_random_vanchor_circuit(curve, rng).unwrap();
+	let (pk, vk) = setup_keys::<Bn254, _, _>(circuit.clone(), rng).unwrap();
+	let proof = prove::<Bn254, _, _>(circuit, &pk, rng).unwrap();
+	let res = verify::<Bn254>(&public_inputs, &vk, &proof).unwrap();
+	assert!(res);
+}
+
+#[test]
+fn should_import_tornado_note_into_mixer() {
+	let rng = &mut test_rng();
+	let curve = Curve::Bn254;
+
+	let (circuit, _) =
+		LegacyImportR1CSProver_Bn254::setup_random_mixer_circuit(curve, rng).unwrap();
+	let (pk, vk) = setup_keys_unchecked::<Bn254, _, _>(circuit, rng).unwrap();
+
+	let note = TornadoNote::parse(NOTE).unwrap();
+	let out_leaf = MixerR1CSProver::<Bn254, 30>::create_random_leaf(curve, rng).unwrap();
+	let recipient = vec![1u8; 32];
+	let relayer = vec![2u8; 32];
+	let (fee, refund) = (1u128, 0u128);
+	let proof = LegacyImportR1CSProver_Bn254::create_mixer_proof(
+		curve,
+		NOTE,
+		setup_commitments(&note),
+		10,
+		&out_leaf,
+		recipient.clone(),
+		relayer.clone(),
+		fee,
+		refund,
+		pk,
+		rng,
+	)
+	.unwrap();
+
+	let res = verify_unchecked_raw::<Bn254>(&proof.public_inputs_raw, &vk, &proof.proof).unwrap();
+	assert!(res);
+
+	// The proof is bound to its recipient, so it can't be front-run
+	let public_inputs =
+		LegacyImportPublicInputs::<Bn254Fr>::from_bytes_slice(&proof.public_inputs_raw).unwrap();
+	assert_eq!(
+		public_inputs.arbitrary_input,
+		LegacyImportR1CSProver_Bn254::create_arbitrary_input(
+			recipient,
+			relayer.clone(),
+			fee,
+			refund
+		)
+	);
+	let mut wrong_public_inputs = public_inputs;
+	wrong_public_inputs.arbitrary_input =
+		LegacyImportR1CSProver_Bn254::create_arbitrary_input(relayer.clone(), relayer, fee, refund);
+	let res = verify_unchecked_typed::<Bn254, _>(&wrong_public_inputs, &vk, &proof.proof).unwrap();
+	assert!(!res);
+
+	// The deposit is spent with its Tornado Cash nullifier hash
+	let nullifier_hash = note.nullifier_hash().unwrap();
+	assert_eq!(
+		proof.nullifier_hash_raw,
+		nullifier_hash.into_repr().to_bytes_be()
+	);
+	assert_eq!(proof.commitment_raw, out_leaf.leaf_bytes);
+}
+
+#[test]
+fn should_import_tornado_note_into_utxo() {
+	let rng = &mut test_rng();
+	let curve = Curve::Bn254;
+
+	let (circuit, _) =
+		LegacyImportR1CSProver_Bn254::setup_random_vanchor_circuit(curve, rng).unwrap();
+	let (pk, vk) = setup_keys_unchecked::<Bn254, _, _>(circuit, rng).unwrap();
+
+	let note = TornadoNote::parse(NOTE).unwrap();
+	let private_key = Bn254Fr::rand(rng);
+	let (proof, utxo) = LegacyImportR1CSProver_Bn254::create_vanchor_proof(
+		curve,
+		NOTE,
+		setup_commitments(&note),
+		10,
+		2,
+		private_key.into_repr().to_bytes_be(),
+		vec![1u8; 32],
+		vec![2u8; 32],
+		1,
+		0,
+		pk,
+		rng,
+	)
+	.unwrap();
+
+	let res = verify_unchecked_raw::<Bn254>(&proof.public_inputs_raw, &vk, &proof.proof).unwrap();
+	assert!(res);
+
+	// The new Utxo holds the denomination and is owned by the given key
+	let keypair_hasher = Poseidon::<Bn254Fr>::new(setup_params(curve, 5, 2));
+	let leaf_hasher = Poseidon::<Bn254Fr>::new(setup_params(curve, 5, 5));
+	let public_key = keypair_hasher.hash(&[private_key]).unwrap();
+	let commitment = leaf_hasher
+		.hash(&[
+			Bn254Fr::from(2u64),
+			Bn254Fr::from(10u64),
+			public_key,
+			utxo.blinding,
+		])
+		.unwrap();
+	assert_eq!(utxo.commitment, commitment);
+	assert_eq!(proof.commitment_raw, commitment.into_repr().to_bytes_be());
+
+	// The Utxo can't hold more than the denomination of the Tornado Cash pool
+	let public_inputs =
+		LegacyImportPublicInputs::<Bn254Fr>::from_bytes_slice(&proof.public_inputs_raw).unwrap();
+	let mut wrong_public_inputs = public_inputs;
+	wrong_public_inputs.denomination = Bn254Fr::from(11u64);
+	let res = verify_unchecked_typed::<Bn254, _>(&wrong_public_inputs, &vk, &proof.proof).unwrap();
+	assert!(!res);
+
+	// The pool checks the Utxo is created on its own chain
+	let mut wrong_public_inputs = public_inputs;
+	wrong_public_inputs.out_chain_id = Bn254Fr::from(3u64);
+	let res = verify_unchecked_typed::<Bn254, _>(&wrong_public_inputs, &vk, &proof.proof).unwrap();
+	assert!(!res);
+}
+
+#[test]
+fn should_fail_with_secret_out_of_range() {
+	let rng = &mut test_rng();
+	let curve = Curve::Bn254;
+	let mimc = MiMCSponge::new(setup_mimc_sponge_params::<Bn254Fr>(curve));
+	let hasher = Poseidon::<Bn254Fr>::new(setup_params(curve, 5, 3));
+
+	let note = TornadoNote::parse(NOTE).unwrap();
+	let (root, path) = deposit_path(&[note.commitment().unwrap()], &note, &mimc).unwrap();
+	let (secret, nullifier) = (Bn254Fr::rand(rng), Bn254Fr::rand(rng));
+	let out_commitment = hasher.hash_two(&secret, &nullifier).unwrap();
+
+	// Only the low 248 bits of the secret are committed to, so a secret out
+	// of range could open the commitment of another note
+	let out_of_range = note.secret() + Bn254Fr::from(2u64).pow([248u64]);
+	let circuit = PoseidonLegacyImportCircuit::new(
+		Bn254Fr::rand(rng),
+		Bn254Fr::from(10u64),
+		out_of_range,
+		note.nullifier(),
+		root,
+		path,
+		note.nullifier_hash().unwrap(),
+		LegacyImportOutput::Mixer { secret, nullifier },
+		out_commitment,
+		mimc,
+		hasher,
+	);
+	let cs = ConstraintSystem::<Bn254Fr>::new_ref();
+	circuit.generate_constraints(cs.clone()).unwrap();
+	assert!(!cs.is_satisfied().unwrap());
+}
+
+#[test]
+fn should_fail_with_deposit_not_in_tree() {
+	let rng = &mut test_rng();
+	let curve = Curve::Bn254;
+
+	let note = TornadoNote::parse(NOTE).unwrap();
+	let other = TornadoNote::generate("eth".to_string(), "0.1".to_string(), 1, rng);
+	let output = LegacyImportOutput::Mixer {
+		secret: Bn254Fr::rand(rng),
+		nullifier: Bn254Fr::rand(rng),
+	};
+	let res = LegacyImportR1CSProver_Bn254::setup_circuit(
+		curve,
+		&other,
+		&[note.commitment().unwrap()],
+		Bn254Fr::rand(rng),
+		Bn254Fr::from(10u64),
+		output,
+	);
+	assert!(res.is_err());
+}
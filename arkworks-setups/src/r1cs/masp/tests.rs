@@ -0,0 +1,233 @@
+use super::{setup_params, MaspVAnchorR1CSProver, PoseidonMaspVAnchorCircuit};
+use crate::{
+	common::*,
+	public_inputs::{MaspPublicInputs, PublicInputs},
+	utxo::MaspUtxo,
+};
+use ark_bn254::{Bn254, Fr as BnFr};
+use ark_ff::{BigInteger, PrimeField, UniformRand};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystem};
+use ark_std::{collections::BTreeMap, rand::rngs::StdRng, test_rng, vec, vec::Vec, One, Zero};
+use arkworks_native_gadgets::{eddsa::SecretKey, poseidon::Poseidon};
+use arkworks_utils::Curve;
+
+const HEIGHT: usize = 30;
+const ANCHOR_CT: usize = 2;
+const INS: usize = 2;
+const OUTS: usize = 2;
+const AMOUNT_BITS: usize = 248;
+const DEFAULT_LEAF: [u8; 32] = [0u8; 32];
+
+#[allow(non_camel_case_types)]
+type MaspVAnchorR1CSProver_Bn254_Poseidon_30_2_2 =
+	MaspVAnchorR1CSProver<Bn254, HEIGHT, ANCHOR_CT, INS, OUTS>;
+
+// Two inputs of 5 on chain 0, with the leaves of their tree and its root
+#[allow(clippy::type_complexity)]
+fn setup_inputs(
+	asset_id: u64,
+	rng: &mut StdRng,
+) -> (
+	[MaspUtxo<BnFr>; INS],
+	BTreeMap<u64, Vec<Vec<u8>>>,
+	Vec<Vec<u8>>,
+) {
+	let curve = Curve::Bn254;
+	let tree_hasher = Poseidon::<BnFr>::new(setup_params(curve, 5, 3));
+
+	let in_utxos = [0u64, 1].map(|index| {
+		MaspVAnchorR1CSProver_Bn254_Poseidon_30_2_2::create_random_utxo(
+			curve,
+			0,
+			asset_id,
+			5,
+			Some(index),
+			rng,
+		)
+		.unwrap()
+	});
+	let leaves = in_utxos.iter().map(|x| x.commitment).collect::<Vec<_>>();
+	let tree =
+		create_merkle_tree::<BnFr, Poseidon<BnFr>, HEIGHT>(&tree_hasher, &leaves, &DEFAULT_LEAF);
+
+	let mut in_leaves = BTreeMap::new();
+	in_leaves.insert(
+		0,
+		leaves.iter().map(|x| x.into_repr().to_bytes_be()).collect(),
+	);
+	let root_set = vec![tree.root().into_repr().to_bytes_be()];
+	(in_utxos, in_leaves, root_set)
+}
+
+#[test]
+fn should_create_proof_for_single_asset() {
+	let rng = &mut test_rng();
+	let curve = Curve::Bn254;
+	let asset_id = 3;
+
+	let random_circuit = MaspVAnchorR1CSProver_Bn254_Poseidon_30_2_2::setup_random_circuit(
+		curve,
+		DEFAULT_LEAF,
+		AMOUNT_BITS,
+		rng,
+	)
+	.unwrap();
+	let (pk, vk) = setup_keys_unchecked::<Bn254, _, _>(random_circuit, rng).unwrap();
+
+	let (in_utxos, in_leaves, root_set) = setup_inputs(asset_id, rng);
+	// Deposit 10 more of the asset
+	let out_utxos = [0; OUTS].map(|_| {
+		MaspVAnchorR1CSProver_Bn254_Poseidon_30_2_2::create_random_utxo(
+			curve, 0, asset_id, 10, None, rng,
+		)
+		.unwrap()
+	});
+
+	let proof = MaspVAnchorR1CSProver_Bn254_Poseidon_30_2_2::create_proof(
+		curve,
+		0,
+		asset_id,
+		10,
+		BnFr::rand(rng).into_repr().to_bytes_be(),
+		root_set,
+		[0, 1],
+		in_leaves,
+		in_utxos,
+		out_utxos,
+		AMOUNT_BITS,
+		pk,
+		DEFAULT_LEAF,
+		rng,
+	)
+	.unwrap();
+
+	let res = verify_unchecked_raw::<Bn254>(&proof.public_inputs_raw, &vk, &proof.proof).unwrap();
+	assert!(res);
+
+	// The asset id is a public input
+	let public_inputs =
+		MaspPublicInputs::<BnFr, ANCHOR_CT, INS, OUTS>::from_bytes_slice(&proof.public_inputs_raw)
+			.unwrap();
+	assert_eq!(public_inputs.asset_id, BnFr::from(asset_id));
+}
+
+#[test]
+fn should_reject_utxos_of_another_asset() {
+	let rng = &mut test_rng();
+	let curve = Curve::Bn254;
+
+	let (in_utxos, in_leaves, root_set) = setup_inputs(3, rng);
+	// The outputs are for another asset
+	let out_utxos = [0; OUTS].map(|_| {
+		MaspVAnchorR1CSProver_Bn254_Poseidon_30_2_2::create_random_utxo(curve, 0, 4, 5, None, rng)
+			.unwrap()
+	});
+
+	let res = MaspVAnchorR1CSProver_Bn254_Poseidon_30_2_2::create_proof(
+		curve,
+		0,
+		3,
+		0,
+		BnFr::rand(rng).into_repr().to_bytes_be(),
+		root_set,
+		[0, 1],
+		in_leaves,
+		in_utxos,
+		out_utxos,
+		AMOUNT_BITS,
+		vec![],
+		DEFAULT_LEAF,
+		rng,
+	);
+	assert!(res.is_err());
+}
+
+#[test]
+fn should_not_satisfy_circuit_for_another_asset() {
+	let rng = &mut test_rng();
+	let curve = Curve::Bn254;
+	let keypair_hasher = Poseidon::<BnFr>::new(setup_params(curve, 5, 2));
+	let tree_hasher = Poseidon::<BnFr>::new(setup_params(curve, 5, 3));
+	let nullifier_hasher = Poseidon::<BnFr>::new(setup_params(curve, 5, 4));
+	let leaf_hasher = Poseidon::<BnFr>::new(setup_params(curve, 5, 6));
+
+	let (in_utxos, ..) = setup_inputs(3, rng);
+	let out_utxos = [0; OUTS].map(|_| {
+		MaspVAnchorR1CSProver_Bn254_Poseidon_30_2_2::create_random_utxo(curve, 0, 3, 5, None, rng)
+			.unwrap()
+	});
+	let leaves = in_utxos.iter().map(|x| x.commitment).collect::<Vec<_>>();
+	let (tree, _) = setup_tree_and_create_path::<BnFr, Poseidon<BnFr>, HEIGHT>(
+		&tree_hasher,
+		&leaves,
+		0,
+		&DEFAULT_LEAF,
+	)
+	.unwrap();
+	let paths = (0..INS)
+		.map(|i| tree.generate_membership_proof(i as u64))
+		.collect::<Vec<_>>();
+
+	for (asset_id, is_satisfied) in [(3u64, true), (4, false)] {
+		// Bypass the asset check of the prover by building the circuit directly
+		let circuit = PoseidonMaspVAnchorCircuit::<BnFr, HEIGHT, INS, OUTS, ANCHOR_CT>::new(
+			BnFr::zero(),
+			BnFr::rand(rng),
+			BnFr::from(asset_id),
+			in_utxos.iter().map(|x| x.amount).collect(),
+			in_utxos.iter().map(|x| x.blinding).collect(),
+			in_utxos
+				.iter()
+				.map(|x| x.keypair.secret_key.unwrap())
+				.collect(),
+			BnFr::zero(),
+			[tree.root(), BnFr::zero()],
+			paths.clone(),
+			vec![BnFr::zero(), BnFr::one()],
+			in_utxos
+				.iter()
+				.map(|x| x.calculate_nullifier(&nullifier_hasher).unwrap())
+				.collect(),
+			out_utxos.iter().map(|x| x.commitment).collect(),
+			out_utxos.iter().map(|x| x.amount).collect(),
+			out_utxos.iter().map(|x| x.blinding).collect(),
+			out_utxos.iter().map(|x| x.chain_id).collect(),
+			out_utxos.iter().map(|x| x.keypair.public_key).collect(),
+			tree_hasher.clone(),
+			keypair_hasher.clone(),
+			leaf_hasher.clone(),
+			nullifier_hasher.clone(),
+			AMOUNT_BITS,
+		);
+
+		let cs = ConstraintSystem::<BnFr>::new_ref();
+		circuit.generate_constraints(cs.clone()).unwrap();
+		assert_eq!(cs.is_satisfied().unwrap(), is_satisfied);
+	}
+}
+
+#[test]
+fn should_encrypt_and_decrypt_masp_utxo() {
+	let rng = &mut test_rng();
+	let curve = Curve::Bn254;
+	let encryption_hasher = Poseidon::<BnFr>::new(setup_params(curve, 5, 4));
+	let leaf_hasher = Poseidon::<BnFr>::new(setup_params(curve, 5, 6));
+
+	let utxo =
+		MaspVAnchorR1CSProver_Bn254_Poseidon_30_2_2::create_random_utxo(curve, 1, 3, 5, None, rng)
+			.unwrap();
+	let recipient = SecretKey::rand(rng);
+	let ephemeral_key = SecretKey::rand(rng);
+	let note = utxo
+		.encrypt_poseidon(&recipient.public_key(), &ephemeral_key, &encryption_hasher)
+		.unwrap();
+
+	let decrypted =
+		MaspUtxo::decrypt_poseidon(&note, &recipient, &encryption_hasher, &leaf_hasher).unwrap();
+	assert_eq!(decrypted.asset_id_raw, 3);
+	assert_eq!(decrypted.commitment, utxo.commitment);
+
+	// Another key can't decrypt the note
+	let other = SecretKey::rand(rng);
+	assert!(MaspUtxo::decrypt_poseidon(&note, &other, &encryption_hasher, &leaf_hasher).is_err());
+}
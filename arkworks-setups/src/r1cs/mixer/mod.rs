@@ -13,12 +13,15 @@ use arkworks_native_gadgets::{
 	merkle_tree::Path,
 	poseidon::{FieldHasher, Poseidon},
 };
-use arkworks_r1cs_circuits::mixer::MixerCircuit;
+use arkworks_r1cs_circuits::{mixer::MixerCircuit, mixer_dynamic_depth::MixerDynamicDepthCircuit};
 use arkworks_r1cs_gadgets::poseidon::PoseidonGadget;
 use arkworks_utils::Curve;
 use codec::Encode;
 
-use crate::common::*;
+use crate::{
+	common::*,
+	public_inputs::{MixerDynamicDepthPublicInputs, MixerPublicInputs, PublicInputs},
+};
 
 #[cfg(test)]
 mod tests;
@@ -30,27 +33,33 @@ pub fn construct_public_inputs<F: PrimeField>(
 	root: F,
 	arbitrary_input: F,
 ) -> Vec<F> {
-	vec![nullifier_hash, root, arbitrary_input]
+	MixerPublicInputs {
+		nullifier_hash,
+		root,
+		arbitrary_input,
+	}
+	.to_field_vec()
+}
+
+pub fn construct_dynamic_depth_public_inputs<F: PrimeField>(
+	nullifier_hash: F,
+	root: F,
+	arbitrary_input: F,
+	depth: F,
+) -> Vec<F> {
+	MixerDynamicDepthPublicInputs {
+		nullifier_hash,
+		root,
+		arbitrary_input,
+		depth,
+	}
+	.to_field_vec()
 }
 
 pub fn deconstruct_public_inputs<F: PrimeField>(
 	public_inputs: &[F],
-) -> (
-	F, // nullifier hash
-	F, // root
-	F, // recipient
-	F, // relayer
-	F, // fee
-	F, // refund
-) {
-	(
-		public_inputs[0],
-		public_inputs[1],
-		public_inputs[2],
-		public_inputs[3],
-		public_inputs[4],
-		public_inputs[6],
-	)
+) -> Result<MixerPublicInputs<F>, Error> {
+	MixerPublicInputs::from_field_slice(public_inputs)
 }
 
 pub type PoseidonMixerCircuit<F, const N: usize> = MixerCircuit<F, PoseidonGadget<F>, N>;
@@ -208,10 +217,7 @@ impl<E: PairingEngine, const HEIGHT: usize> MixerR1CSProver<E, HEIGHT> {
 		let leaf_raw = leaf.into_repr().to_bytes_be();
 		let nullifier_hash_raw = nullifier_hash.into_repr().to_bytes_be();
 		let root_raw = root.into_repr().to_bytes_be();
-		let public_inputs_raw: Vec<Vec<u8>> = public_inputs
-			.iter()
-			.map(|x| x.into_repr().to_bytes_be())
-			.collect();
+		let public_inputs_raw = deconstruct_public_inputs(&public_inputs)?.to_bytes_vec();
 
 		Ok((
 			mc,
@@ -247,6 +253,162 @@ impl<E: PairingEngine, const HEIGHT: usize> MixerR1CSProver<E, HEIGHT> {
 		);
 		mc
 	}
+
+	/// Sets up a dynamic depth circuit for a tree of the largest supported
+	/// height, e.g. for generating the keys
+	#[allow(dead_code)]
+	#[allow(clippy::type_complexity)]
+	pub fn setup_dynamic_depth_random_circuit<R: CryptoRng + RngCore>(
+		curve: Curve,
+		default_leaf: [u8; 32],
+		rng: &mut R,
+	) -> Result<
+		(
+			MixerDynamicDepthCircuit<E::Fr, PoseidonGadget<E::Fr>, HEIGHT>,
+			E::Fr,
+			E::Fr,
+			E::Fr,
+			Vec<E::Fr>,
+		),
+		Error,
+	> {
+		let params3 = setup_params(curve, 5, 3);
+		let poseidon = Poseidon::<E::Fr>::new(params3);
+
+		let secret = E::Fr::rand(rng);
+		let nullifier = E::Fr::rand(rng);
+		let leaves = vec![poseidon.hash_two(&secret, &nullifier)?];
+		let arbitrary_input = E::Fr::rand(rng);
+
+		Self::setup_dynamic_depth_circuit_with_privates(
+			curve,
+			secret,
+			nullifier,
+			&leaves,
+			0,
+			HEIGHT,
+			arbitrary_input,
+			default_leaf,
+		)
+	}
+
+	/// Sets up a dynamic depth circuit proving membership in the tree of
+	/// height `depth` holding `leaves`
+	#[allow(dead_code)]
+	#[allow(clippy::too_many_arguments)]
+	#[allow(clippy::type_complexity)]
+	pub fn setup_dynamic_depth_circuit_with_privates(
+		curve: Curve,
+		secret: E::Fr,
+		nullifier: E::Fr,
+		leaves: &[E::Fr],
+		index: u64,
+		depth: usize,
+		arbitrary_input: E::Fr,
+		default_leaf: [u8; 32],
+	) -> Result<
+		(
+			MixerDynamicDepthCircuit<E::Fr, PoseidonGadget<E::Fr>, HEIGHT>,
+			E::Fr,
+			E::Fr,
+			E::Fr,
+			Vec<E::Fr>,
+		),
+		Error,
+	> {
+		// Initialize hasher
+		let params3 = setup_params(curve, 5, 3);
+		let poseidon = Poseidon::<E::Fr>::new(params3);
+		// Setup inputs
+		let leaf = poseidon.hash_two(&secret, &nullifier)?;
+		let nullifier_hash = poseidon.hash_two(&nullifier, &nullifier)?;
+		let (root, path) = setup_tree_and_create_path_at_depth::<E::Fr, Poseidon<E::Fr>, HEIGHT>(
+			&poseidon,
+			leaves,
+			index,
+			depth,
+			&default_leaf,
+		)?;
+		let depth_f = E::Fr::from(depth as u64);
+
+		let mc = MixerDynamicDepthCircuit::<E::Fr, PoseidonGadget<E::Fr>, HEIGHT>::new(
+			arbitrary_input,
+			secret,
+			nullifier,
+			path,
+			root,
+			depth_f,
+			nullifier_hash,
+			poseidon,
+		);
+		let public_inputs =
+			construct_dynamic_depth_public_inputs(nullifier_hash, root, arbitrary_input, depth_f);
+		Ok((mc, leaf, nullifier_hash, root, public_inputs))
+	}
+
+	/// Creates a proof with the keys of a dynamic depth circuit, for the tree
+	/// of height `depth` holding `leaves`
+	#[allow(clippy::too_many_arguments)]
+	pub fn create_dynamic_depth_proof<R: RngCore + CryptoRng>(
+		curve: Curve,
+		secret: Vec<u8>,
+		nullifier: Vec<u8>,
+		leaves: Vec<Vec<u8>>,
+		index: u64,
+		depth: usize,
+		recipient: Vec<u8>,
+		relayer: Vec<u8>,
+		fee: u128,
+		refund: u128,
+		pk: Vec<u8>,
+		default_leaf: [u8; 32],
+		rng: &mut R,
+	) -> Result<MixerProof, Error> {
+		// Get field element version of all the data
+		let secret_f = E::Fr::from_be_bytes_mod_order(&secret);
+		let nullifier_f = E::Fr::from_be_bytes_mod_order(&nullifier);
+		let leaves_f: Vec<E::Fr> = leaves
+			.iter()
+			.map(|x| E::Fr::from_be_bytes_mod_order(x))
+			.collect();
+
+		let mut arbitrary_data_bytes = Vec::new();
+		arbitrary_data_bytes.extend(&recipient);
+		arbitrary_data_bytes.extend(&relayer);
+		// Using encode to be compatible with on chain types
+		arbitrary_data_bytes.extend(fee.encode());
+		arbitrary_data_bytes.extend(refund.encode());
+		let arbitrary_data = keccak_256(&arbitrary_data_bytes);
+		let arbitrary_input = E::Fr::from_be_bytes_mod_order(&arbitrary_data);
+
+		let (mc, leaf, nullifier_hash, root, public_inputs) =
+			Self::setup_dynamic_depth_circuit_with_privates(
+				curve,
+				secret_f,
+				nullifier_f,
+				&leaves_f,
+				index,
+				depth,
+				arbitrary_input,
+				default_leaf,
+			)?;
+
+		let leaf_raw = leaf.into_repr().to_bytes_be();
+		let nullifier_hash_raw = nullifier_hash.into_repr().to_bytes_be();
+		let root_raw = root.into_repr().to_bytes_be();
+		let public_inputs_raw =
+			MixerDynamicDepthPublicInputs::from_field_slice(&public_inputs)?.to_bytes_vec();
+
+		let proof = prove_unchecked::<E, _, _>(mc, &pk, rng)?;
+
+		Ok(MixerProof {
+			leaf_raw,
+			nullifier_hash_raw,
+			root_raw,
+			public_inputs_raw,
+			proof,
+		})
+	}
 }
 
 impl<E: PairingEngine, const HEIGHT: usize> MixerProver<E, HEIGHT> for MixerR1CSProver<E, HEIGHT> {
@@ -329,15 +491,16 @@ impl<E: PairingEngine, const HEIGHT: usize> MixerProver<E, HEIGHT> for MixerR1CS
 			nullifier_hash_f,
 			poseidon,
 		);
-		let public_inputs = construct_public_inputs(nullifier_hash_f, root, arbitrary_input);
+		let public_inputs = MixerPublicInputs {
+			nullifier_hash: nullifier_hash_f,
+			root,
+			arbitrary_input,
+		};
 
 		let leaf_raw = leaf_bytes;
 		let nullifier_hash_raw = nullifier_hash_bytes;
 		let root_raw = root.into_repr().to_bytes_be();
-		let public_inputs_raw: Vec<Vec<u8>> = public_inputs
-			.iter()
-			.map(|x| x.into_repr().to_bytes_be())
-			.collect();
+		let public_inputs_raw = public_inputs.to_bytes_vec();
 
 		let proof = prove_unchecked::<E, _, _>(mc, &pk, rng)?;
 
@@ -15,12 +15,13 @@ pub const LEN: usize = 30;
 use crate::{
 	common::{
 		prove, prove_unchecked, setup_keys, setup_keys_unchecked, setup_tree_and_create_path,
-		verify, verify_unchecked_raw,
+		verify, verify_unchecked_raw, verify_unchecked_typed,
 	},
+	public_inputs::{MixerDynamicDepthPublicInputs, MixerPublicInputs, PublicInputs},
 	MixerProver,
 };
 
-use super::MixerR1CSProver;
+use super::{deconstruct_public_inputs, MixerR1CSProver};
 
 #[allow(non_camel_case_types)]
 type MixerR1CSProver_Bn254_Poseidon_30 = MixerR1CSProver<Bn254, LEN>;
@@ -328,10 +329,9 @@ fn setup_and_prove_mixer_raw_inputs() {
 		)
 		.unwrap();
 
-	let public_inputs: Vec<Bn254Fr> = public_inputs_raw
-		.iter()
-		.map(|x| Bn254Fr::from_be_bytes_mod_order(x))
-		.collect();
+	let public_inputs = MixerPublicInputs::<Bn254Fr>::from_bytes_slice(&public_inputs_raw)
+		.unwrap()
+		.to_field_vec();
 
 	let (pk, vk) = setup_keys::<Bn254, _, _>(circuit.clone(), rng).unwrap();
 	let proof = prove::<Bn254, _, _>(circuit, &pk, rng).unwrap();
@@ -398,3 +398,123 @@ fn setup_and_prove_mixer_raw_inputs_unchecked() {
 		recipient, relayer, fee, refund, public_inputs_raw, proof
 	);
 }
+
+#[test]
+fn setup_and_prove_dynamic_depth_mixer_for_several_heights() {
+	let rng = &mut test_rng();
+	let curve = Curve::Bn254;
+
+	// One set of keys for every tree height up to LEN
+	let (circuit, ..) = MixerR1CSProver_Bn254_Poseidon_30::setup_dynamic_depth_random_circuit(
+		curve,
+		DEFAULT_LEAF,
+		rng,
+	)
+	.unwrap();
+	let (pk, vk) = setup_keys_unchecked::<Bn254, _, _>(circuit, rng).unwrap();
+
+	let recipient_raw = Bn254Fr::one().into_repr().to_bytes_be();
+	let relayer_raw = Bn254Fr::zero().into_repr().to_bytes_be();
+
+	for depth in [4, 20, LEN] {
+		let leaf = MixerR1CSProver_Bn254_Poseidon_30::create_random_leaf(curve, rng).unwrap();
+		let other_leaf = MixerR1CSProver_Bn254_Poseidon_30::create_random_leaf(curve, rng).unwrap();
+		let leaves_raw = vec![other_leaf.leaf_bytes, leaf.leaf_bytes];
+
+		let proof = MixerR1CSProver_Bn254_Poseidon_30::create_dynamic_depth_proof(
+			curve,
+			leaf.secret_bytes,
+			leaf.nullifier_bytes,
+			leaves_raw,
+			1,
+			depth,
+			recipient_raw.clone(),
+			relayer_raw.clone(),
+			0,
+			0,
+			pk.clone(),
+			DEFAULT_LEAF,
+			rng,
+		)
+		.unwrap();
+
+		let res =
+			verify_unchecked_raw::<Bn254>(&proof.public_inputs_raw, &vk, &proof.proof).unwrap();
+		assert!(res);
+
+		// The proof is bound to the height of the tree
+		let mut public_inputs =
+			MixerDynamicDepthPublicInputs::from_bytes_slice(&proof.public_inputs_raw).unwrap();
+		public_inputs.depth = Bn254Fr::from(depth as u64 - 1);
+		let res = verify_unchecked_typed::<Bn254, _>(&public_inputs, &vk, &proof.proof).unwrap();
+		assert!(!res);
+	}
+}
+
+#[test]
+fn should_fail_with_depth_too_small_for_leaves() {
+	let rng = &mut test_rng();
+	let curve = Curve::Bn254;
+
+	let leaf = MixerR1CSProver_Bn254_Poseidon_30::create_random_leaf(curve, rng).unwrap();
+	let leaves = (0..5)
+		.map(|_| Bn254Fr::rand(rng))
+		.chain(vec![Bn254Fr::from_be_bytes_mod_order(&leaf.leaf_bytes)])
+		.collect::<Vec<_>>();
+
+	// A tree of height 2 can't hold 6 leaves
+	let res = MixerR1CSProver_Bn254_Poseidon_30::setup_dynamic_depth_circuit_with_privates(
+		curve,
+		Bn254Fr::from_be_bytes_mod_order(&leaf.secret_bytes),
+		Bn254Fr::from_be_bytes_mod_order(&leaf.nullifier_bytes),
+		&leaves,
+		5,
+		2,
+		Bn254Fr::rand(rng),
+		DEFAULT_LEAF,
+	);
+	assert!(res.is_err());
+}
+
+#[test]
+fn should_deconstruct_typed_public_inputs() {
+	let rng = &mut test_rng();
+	let curve = Curve::Bn254;
+
+	let leaf = MixerR1CSProver_Bn254_Poseidon_30::create_random_leaf(curve, rng).unwrap();
+	let (circuit, ..) =
+		MixerR1CSProver_Bn254_Poseidon_30::setup_random_circuit(curve, DEFAULT_LEAF, rng).unwrap();
+	let (pk, vk) = setup_keys_unchecked::<Bn254, _, _>(circuit, rng).unwrap();
+
+	let proof = MixerR1CSProver_Bn254_Poseidon_30::create_proof(
+		curve,
+		leaf.secret_bytes,
+		leaf.nullifier_bytes,
+		vec![leaf.leaf_bytes],
+		0,
+		Bn254Fr::one().into_repr().to_bytes_be(),
+		Bn254Fr::zero().into_repr().to_bytes_be(),
+		0,
+		0,
+		pk,
+		DEFAULT_LEAF,
+		rng,
+	)
+	.unwrap();
+
+	let public_inputs =
+		MixerPublicInputs::<Bn254Fr>::from_bytes_slice(&proof.public_inputs_raw).unwrap();
+	assert_eq!(
+		public_inputs.nullifier_hash.into_repr().to_bytes_be(),
+		leaf.nullifier_hash_bytes
+	);
+	assert_eq!(public_inputs.root.into_repr().to_bytes_be(), proof.root_raw);
+	assert!(verify_unchecked_typed::<Bn254, _>(&public_inputs, &vk, &proof.proof).unwrap());
+
+	let public_inputs_f = public_inputs.to_field_vec();
+	assert_eq!(
+		deconstruct_public_inputs(&public_inputs_f).unwrap(),
+		public_inputs
+	);
+	assert!(deconstruct_public_inputs(&public_inputs_f[..2]).is_err());
+}
@@ -1,3 +1,16 @@
 pub mod anchor;
+pub mod anchor_migration;
+pub mod balance_threshold;
+pub mod batch_insert;
+pub mod credential;
+pub mod legacy_import;
+pub mod masp;
 pub mod mixer;
+pub mod ownership;
+pub mod rln;
+pub mod semaphore;
+pub mod swap;
 pub mod vanchor;
+pub mod vanchor_multisig;
+pub mod vanchor_timelock;
+pub mod voting;
@@ -0,0 +1,245 @@
+use crate::{
+	common::*,
+	public_inputs::{OwnershipPublicInputs, PublicInputs},
+	r1cs::anchor::AnchorR1CSProver,
+	AnchorProver,
+};
+use ark_crypto_primitives::Error;
+use ark_ec::PairingEngine;
+use ark_ff::{BigInteger, PrimeField};
+use ark_std::{
+	marker::PhantomData,
+	rand::{CryptoRng, RngCore},
+	vec,
+	vec::Vec,
+	UniformRand,
+};
+use arkworks_native_gadgets::poseidon::{FieldHasher, Poseidon};
+use arkworks_r1cs_circuits::ownership::OwnershipCircuit;
+use arkworks_r1cs_gadgets::poseidon::PoseidonGadget;
+use arkworks_utils::Curve;
+
+#[cfg(test)]
+mod tests;
+
+pub type PoseidonOwnershipCircuit<F, const N: usize, const M: usize> =
+	OwnershipCircuit<F, PoseidonGadget<F>, N, M>;
+
+/// Prover for the ownership of Anchor deposits. The leaves are the ones of an
+/// Anchor with the same `HEIGHT` and `ANCHOR_CT`.
+pub struct OwnershipR1CSProver<E: PairingEngine, const HEIGHT: usize, const ANCHOR_CT: usize> {
+	engine: PhantomData<E>,
+}
+
+impl<E: PairingEngine, const HEIGHT: usize, const ANCHOR_CT: usize>
+	OwnershipR1CSProver<E, HEIGHT, ANCHOR_CT>
+{
+	pub fn construct_public_inputs(
+		chain_id: E::Fr,
+		scoped_nullifier: E::Fr,
+		roots: [E::Fr; ANCHOR_CT],
+		scope: E::Fr,
+	) -> Vec<E::Fr> {
+		OwnershipPublicInputs {
+			scoped_nullifier,
+			scope,
+			chain_id,
+			roots,
+		}
+		.to_field_vec()
+	}
+
+	#[allow(dead_code)]
+	#[allow(clippy::type_complexity)]
+	pub fn setup_random_circuit<R: CryptoRng + RngCore>(
+		curve: Curve,
+		default_leaf: [u8; 32],
+		rng: &mut R,
+	) -> Result<
+		(
+			PoseidonOwnershipCircuit<E::Fr, HEIGHT, ANCHOR_CT>,
+			E::Fr,
+			E::Fr,
+			Vec<E::Fr>,
+			Vec<E::Fr>,
+		),
+		Error,
+	> {
+		let params3 = setup_params::<E::Fr>(curve, 5, 3);
+		let tree_hasher = Poseidon::<E::Fr> { params: params3 };
+		let scope = E::Fr::rand(rng);
+		let chain_id = 1u64;
+		let leaf = Self::create_random_leaf(curve, chain_id, rng)?;
+		let secret = E::Fr::from_be_bytes_mod_order(&leaf.secret_bytes);
+		let nullifier = E::Fr::from_be_bytes_mod_order(&leaf.nullifier_bytes);
+		let leaves = vec![E::Fr::from_be_bytes_mod_order(&leaf.leaf_bytes)];
+
+		let (tree, _) = setup_tree_and_create_path::<E::Fr, Poseidon<E::Fr>, HEIGHT>(
+			&tree_hasher,
+			&leaves,
+			0,
+			&default_leaf,
+		)?;
+		let mut root_set = [E::Fr::rand(rng); ANCHOR_CT];
+		root_set[0] = tree.root();
+
+		Self::setup_circuit_with_privates(
+			curve,
+			E::Fr::from(chain_id),
+			secret,
+			nullifier,
+			&leaves,
+			0,
+			root_set,
+			scope,
+			default_leaf,
+		)
+	}
+
+	/// Sets up a circuit proving the ownership of the deposit at `index` in
+	/// `leaves`, returning the circuit, the leaf, the scoped nullifier, the
+	/// root set and the public inputs
+	#[allow(dead_code)]
+	#[allow(clippy::too_many_arguments)]
+	#[allow(clippy::type_complexity)]
+	pub fn setup_circuit_with_privates(
+		curve: Curve,
+		chain_id: E::Fr,
+		secret: E::Fr,
+		nullifier: E::Fr,
+		leaves: &[E::Fr],
+		index: u64,
+		roots: [E::Fr; ANCHOR_CT],
+		scope: E::Fr,
+		default_leaf: [u8; 32],
+	) -> Result<
+		(
+			PoseidonOwnershipCircuit<E::Fr, HEIGHT, ANCHOR_CT>,
+			E::Fr,
+			E::Fr,
+			Vec<E::Fr>,
+			Vec<E::Fr>,
+		),
+		Error,
+	> {
+		let params3 = setup_params::<E::Fr>(curve, 5, 3);
+		let params4 = setup_params::<E::Fr>(curve, 5, 4);
+		let tree_hasher = Poseidon::<E::Fr> { params: params3 };
+		let leaf_hasher = Poseidon::<E::Fr> { params: params4 };
+		let leaf = leaf_hasher.hash(&[chain_id, nullifier, secret])?;
+		let scoped_nullifier = tree_hasher.hash_two(&nullifier, &scope)?;
+		let (_, path) = setup_tree_and_create_path::<E::Fr, Poseidon<E::Fr>, HEIGHT>(
+			&tree_hasher,
+			leaves,
+			index,
+			&default_leaf,
+		)?;
+
+		let mc = OwnershipCircuit::new(
+			scope,
+			secret,
+			nullifier,
+			chain_id,
+			roots,
+			path,
+			scoped_nullifier,
+			tree_hasher,
+			leaf_hasher,
+		);
+		let public_inputs = Self::construct_public_inputs(chain_id, scoped_nullifier, roots, scope);
+		Ok((mc, leaf, scoped_nullifier, roots.to_vec(), public_inputs))
+	}
+
+	/// For creating Anchor leaves where we supply the chain_id, secret and the
+	/// nullifier
+	pub fn create_leaf_with_privates(
+		curve: Curve,
+		chain_id: u64,
+		secret: Vec<u8>,
+		nullifier: Vec<u8>,
+	) -> Result<Leaf, Error> {
+		// The deposits are regular Anchor deposits
+		AnchorR1CSProver::<E, HEIGHT, ANCHOR_CT>::create_leaf_with_privates(
+			curve, chain_id, secret, nullifier,
+		)
+	}
+
+	/// Create random leaf
+	pub fn create_random_leaf<R: RngCore + CryptoRng>(
+		curve: Curve,
+		chain_id: u64,
+		rng: &mut R,
+	) -> Result<Leaf, Error> {
+		AnchorR1CSProver::<E, HEIGHT, ANCHOR_CT>::create_random_leaf(curve, chain_id, rng)
+	}
+
+	/// Computes the nullifier of a deposit in the given application scope
+	pub fn create_scoped_nullifier(
+		curve: Curve,
+		nullifier: Vec<u8>,
+		scope: Vec<u8>,
+	) -> Result<Vec<u8>, Error> {
+		let params3 = setup_params::<E::Fr>(curve, 5, 3);
+		let tree_hasher = Poseidon::<E::Fr> { params: params3 };
+		let nullifier_f = E::Fr::from_be_bytes_mod_order(&nullifier);
+		let scope_f = E::Fr::from_be_bytes_mod_order(&scope);
+		let scoped_nullifier = tree_hasher.hash_two(&nullifier_f, &scope_f)?;
+		Ok(scoped_nullifier.into_repr().to_bytes_be())
+	}
+
+	/// For proving the ownership of a deposit in the given scope, the root set
+	/// may hold up to ANCHOR_CT roots
+	#[allow(clippy::too_many_arguments)]
+	pub fn create_proof<R: RngCore + CryptoRng>(
+		curve: Curve,
+		chain_id: u64,
+		secret: Vec<u8>,
+		nullifier: Vec<u8>,
+		leaves: Vec<Vec<u8>>,
+		index: u64,
+		root_set: Vec<Vec<u8>>,
+		scope: Vec<u8>,
+		pk: Vec<u8>,
+		default_leaf: [u8; 32],
+		rng: &mut R,
+	) -> Result<OwnershipProof, Error> {
+		// Get field element version of all the data
+		let secret_f = E::Fr::from_be_bytes_mod_order(&secret);
+		let nullifier_f = E::Fr::from_be_bytes_mod_order(&nullifier);
+		let scope_f = E::Fr::from_be_bytes_mod_order(&scope);
+		let leaves_f: Vec<E::Fr> = leaves
+			.iter()
+			.map(|x| E::Fr::from_be_bytes_mod_order(x))
+			.collect();
+		let root_set_f = pad_root_set::<E::Fr, ANCHOR_CT>(&root_set)?;
+
+		let (mc, leaf, scoped_nullifier, roots, public_inputs) = Self::setup_circuit_with_privates(
+			curve,
+			E::Fr::from(chain_id),
+			secret_f,
+			nullifier_f,
+			&leaves_f,
+			index,
+			root_set_f,
+			scope_f,
+			default_leaf,
+		)?;
+
+		let leaf_raw = leaf.into_repr().to_bytes_be();
+		let scoped_nullifier_raw = scoped_nullifier.into_repr().to_bytes_be();
+		let roots_raw = roots.iter().map(|v| v.into_repr().to_bytes_be()).collect();
+		let public_inputs_raw =
+			OwnershipPublicInputs::<E::Fr, ANCHOR_CT>::from_field_slice(&public_inputs)?
+				.to_bytes_vec();
+
+		let proof = prove_unchecked::<E, _, _>(mc, &pk, rng)?;
+
+		Ok(OwnershipProof {
+			proof,
+			leaf_raw,
+			scoped_nullifier_raw,
+			roots_raw,
+			public_inputs_raw,
+		})
+	}
+}
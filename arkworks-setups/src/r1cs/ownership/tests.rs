@@ -0,0 +1,202 @@
+use crate::{
+	common::*,
+	public_inputs::{OwnershipPublicInputs, PublicInputs},
+};
+use ark_bn254::{Bn254, Fr as Bn254Fr};
+use ark_ff::{BigInteger, PrimeField, UniformRand};
+use ark_std::{test_rng, vec, vec::Vec};
+use arkworks_native_gadgets::poseidon::Poseidon;
+use arkworks_utils::Curve;
+
+use super::{setup_params, OwnershipR1CSProver};
+
+pub const HEIGHT: usize = 30;
+pub const ANCHOR_CT: usize = 2;
+
+#[allow(non_camel_case_types)]
+type OwnershipR1CSProver_Bn254_Poseidon_30 = OwnershipR1CSProver<Bn254, HEIGHT, ANCHOR_CT>;
+pub const DEFAULT_LEAF: [u8; 32] = [0u8; 32];
+
+fn root_of(curve: Curve, leaves: &[Vec<u8>]) -> Vec<u8> {
+	let params3 = setup_params::<Bn254Fr>(curve, 5, 3);
+	let tree_hasher = Poseidon::<Bn254Fr> { params: params3 };
+	let leaves_f: Vec<Bn254Fr> = leaves
+		.iter()
+		.map(|x| Bn254Fr::from_be_bytes_mod_order(x))
+		.collect();
+	let (tree, _) = setup_tree_and_create_path::<Bn254Fr, Poseidon<Bn254Fr>, HEIGHT>(
+		&tree_hasher,
+		&leaves_f,
+		0,
+		&DEFAULT_LEAF,
+	)
+	.unwrap();
+	tree.root().into_repr().to_bytes_be()
+}
+
+#[test]
+fn setup_random_ownership() {
+	let rng = &mut test_rng();
+	let curve = Curve::Bn254;
+
+	let (circuit, .., public_inputs) =
+		OwnershipR1CSProver_Bn254_Poseidon_30::setup_random_circuit(curve, DEFAULT_LEAF, rng)
+			.unwrap();
+	let (pk, vk) = setup_keys::<Bn254, _, _>(circuit.clone(), rng).unwrap();
+	let proof = prove::<Bn254, _, _>(circuit, &pk, rng).unwrap();
+	let res = verify::<Bn254>(&public_inputs, &vk, &proof).unwrap();
+	assert!(res);
+}
+
+#[test]
+fn setup_and_prove_ownership_in_scope() {
+	let rng = &mut test_rng();
+	let curve = Curve::Bn254;
+
+	let (circuit, ..) =
+		OwnershipR1CSProver_Bn254_Poseidon_30::setup_random_circuit(curve, DEFAULT_LEAF, rng)
+			.unwrap();
+	let (pk, vk) = setup_keys_unchecked::<Bn254, _, _>(circuit, rng).unwrap();
+
+	let chain_id = 1u64;
+	let leaf =
+		OwnershipR1CSProver_Bn254_Poseidon_30::create_random_leaf(curve, chain_id, rng).unwrap();
+	let root_set = vec![root_of(curve, &[leaf.leaf_bytes.clone()])];
+	let scope = Bn254Fr::rand(rng).into_repr().to_bytes_be();
+
+	let proof = OwnershipR1CSProver_Bn254_Poseidon_30::create_proof(
+		curve,
+		chain_id,
+		leaf.secret_bytes.clone(),
+		leaf.nullifier_bytes.clone(),
+		vec![leaf.leaf_bytes.clone()],
+		0,
+		root_set,
+		scope.clone(),
+		pk,
+		DEFAULT_LEAF,
+		rng,
+	)
+	.unwrap();
+
+	let res = verify_unchecked_raw::<Bn254>(&proof.public_inputs_raw, &vk, &proof.proof).unwrap();
+	assert!(res);
+
+	// The scoped nullifier doesn't reveal the spend nullifier of the deposit
+	let scoped_nullifier = OwnershipR1CSProver_Bn254_Poseidon_30::create_scoped_nullifier(
+		curve,
+		leaf.nullifier_bytes,
+		scope,
+	)
+	.unwrap();
+	assert_eq!(proof.scoped_nullifier_raw, scoped_nullifier);
+	assert_ne!(proof.scoped_nullifier_raw, leaf.nullifier_hash_bytes);
+}
+
+#[test]
+fn scoped_nullifiers_are_unlinkable_across_scopes() {
+	let rng = &mut test_rng();
+	let curve = Curve::Bn254;
+
+	let leaf = OwnershipR1CSProver_Bn254_Poseidon_30::create_random_leaf(curve, 1, rng).unwrap();
+	let scope_a = Bn254Fr::from(1u64).into_repr().to_bytes_be();
+	let scope_b = Bn254Fr::from(2u64).into_repr().to_bytes_be();
+
+	let nullifier_a = OwnershipR1CSProver_Bn254_Poseidon_30::create_scoped_nullifier(
+		curve,
+		leaf.nullifier_bytes.clone(),
+		scope_a.clone(),
+	)
+	.unwrap();
+	let nullifier_a_again = OwnershipR1CSProver_Bn254_Poseidon_30::create_scoped_nullifier(
+		curve,
+		leaf.nullifier_bytes.clone(),
+		scope_a,
+	)
+	.unwrap();
+	let nullifier_b = OwnershipR1CSProver_Bn254_Poseidon_30::create_scoped_nullifier(
+		curve,
+		leaf.nullifier_bytes,
+		scope_b,
+	)
+	.unwrap();
+
+	assert_eq!(nullifier_a, nullifier_a_again);
+	assert_ne!(nullifier_a, nullifier_b);
+}
+
+#[test]
+fn should_fail_with_invalid_scope() {
+	let rng = &mut test_rng();
+	let curve = Curve::Bn254;
+
+	let (circuit, ..) =
+		OwnershipR1CSProver_Bn254_Poseidon_30::setup_random_circuit(curve, DEFAULT_LEAF, rng)
+			.unwrap();
+	let (pk, vk) = setup_keys_unchecked::<Bn254, _, _>(circuit, rng).unwrap();
+
+	let chain_id = 1u64;
+	let leaf =
+		OwnershipR1CSProver_Bn254_Poseidon_30::create_random_leaf(curve, chain_id, rng).unwrap();
+	let root_set = vec![root_of(curve, &[leaf.leaf_bytes.clone()])];
+
+	let proof = OwnershipR1CSProver_Bn254_Poseidon_30::create_proof(
+		curve,
+		chain_id,
+		leaf.secret_bytes,
+		leaf.nullifier_bytes,
+		vec![leaf.leaf_bytes],
+		0,
+		root_set,
+		Bn254Fr::from(1u64).into_repr().to_bytes_be(),
+		pk,
+		DEFAULT_LEAF,
+		rng,
+	)
+	.unwrap();
+
+	// The proof can't be replayed in another scope
+	let mut public_inputs =
+		OwnershipPublicInputs::<Bn254Fr, ANCHOR_CT>::from_bytes_slice(&proof.public_inputs_raw)
+			.unwrap();
+	public_inputs.scope = Bn254Fr::from(2u64);
+	let res = verify_unchecked_typed::<Bn254, _>(&public_inputs, &vk, &proof.proof).unwrap();
+	assert!(!res);
+}
+
+#[test]
+fn should_fail_with_leaf_outside_root_set() {
+	let rng = &mut test_rng();
+	let curve = Curve::Bn254;
+
+	let (circuit, ..) =
+		OwnershipR1CSProver_Bn254_Poseidon_30::setup_random_circuit(curve, DEFAULT_LEAF, rng)
+			.unwrap();
+	let (pk, vk) = setup_keys_unchecked::<Bn254, _, _>(circuit, rng).unwrap();
+
+	let chain_id = 1u64;
+	let leaf =
+		OwnershipR1CSProver_Bn254_Poseidon_30::create_random_leaf(curve, chain_id, rng).unwrap();
+	let other_leaf =
+		OwnershipR1CSProver_Bn254_Poseidon_30::create_random_leaf(curve, chain_id, rng).unwrap();
+	// The root set doesn't hold the tree of the deposit
+	let root_set = vec![root_of(curve, &[other_leaf.leaf_bytes])];
+
+	let proof = OwnershipR1CSProver_Bn254_Poseidon_30::create_proof(
+		curve,
+		chain_id,
+		leaf.secret_bytes,
+		leaf.nullifier_bytes,
+		vec![leaf.leaf_bytes],
+		0,
+		root_set,
+		Bn254Fr::from(1u64).into_repr().to_bytes_be(),
+		pk,
+		DEFAULT_LEAF,
+		rng,
+	)
+	.unwrap();
+
+	let res = verify_unchecked_raw::<Bn254>(&proof.public_inputs_raw, &vk, &proof.proof).unwrap();
+	assert!(!res);
+}
@@ -0,0 +1,247 @@
+use crate::{
+	common::*,
+	public_inputs::{PublicInputs, RlnPublicInputs},
+	r1cs::semaphore::signal_hash,
+};
+use ark_crypto_primitives::Error;
+use ark_ec::PairingEngine;
+use ark_ff::{BigInteger, PrimeField};
+use ark_std::{
+	marker::PhantomData,
+	rand::{CryptoRng, RngCore},
+	vec,
+	vec::Vec,
+	UniformRand,
+};
+use arkworks_native_gadgets::{
+	poseidon::Poseidon,
+	rln::{self, Share},
+};
+use arkworks_r1cs_circuits::rln::RlnCircuit;
+use arkworks_r1cs_gadgets::poseidon::PoseidonGadget;
+use arkworks_utils::Curve;
+
+#[cfg(test)]
+mod tests;
+
+#[derive(Debug)]
+pub enum RlnProverError {
+	NullifierMismatch,
+}
+
+impl core::fmt::Display for RlnProverError {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		let msg = match self {
+			Self::NullifierMismatch => ark_std::format!("Proofs are not from the same epoch"),
+		};
+		write!(f, "{}", msg)
+	}
+}
+
+impl ark_std::error::Error for RlnProverError {}
+
+pub fn construct_public_inputs<F: PrimeField>(
+	root: F,
+	epoch: F,
+	x: F,
+	y: F,
+	nullifier: F,
+) -> Vec<F> {
+	RlnPublicInputs {
+		root,
+		epoch,
+		x,
+		y,
+		nullifier,
+	}
+	.to_field_vec()
+}
+
+pub type PoseidonRlnCircuit<F, const N: usize> = RlnCircuit<F, PoseidonGadget<F>, N>;
+
+pub struct RlnR1CSProver<E: PairingEngine, const HEIGHT: usize> {
+	engine: PhantomData<E>,
+}
+
+impl<E: PairingEngine, const HEIGHT: usize> RlnR1CSProver<E, HEIGHT> {
+	/// Creates an identity from its secret key
+	pub fn create_identity_with_privates(
+		curve: Curve,
+		secret: Vec<u8>,
+	) -> Result<RlnIdentity, Error> {
+		let secret_f = E::Fr::from_be_bytes_mod_order(&secret);
+
+		let params2 = setup_params(curve, 5, 2);
+		let hasher2 = Poseidon::<E::Fr>::new(params2);
+		let commitment = rln::identity_commitment(&secret_f, &hasher2)?;
+		Ok(RlnIdentity {
+			secret_bytes: secret_f.into_repr().to_bytes_be(),
+			commitment_bytes: commitment.into_repr().to_bytes_be(),
+		})
+	}
+
+	/// Creates an identity with a random secret key
+	pub fn create_random_identity<R: RngCore + CryptoRng>(
+		curve: Curve,
+		rng: &mut R,
+	) -> Result<RlnIdentity, Error> {
+		let secret = E::Fr::rand(rng);
+		Self::create_identity_with_privates(curve, secret.into_repr().to_bytes_be())
+	}
+
+	/// Sets up a circuit for a group holding a single random identity, e.g.
+	/// for generating the keys
+	#[allow(dead_code)]
+	#[allow(clippy::type_complexity)]
+	pub fn setup_random_circuit<R: RngCore + CryptoRng>(
+		curve: Curve,
+		default_leaf: [u8; 32],
+		rng: &mut R,
+	) -> Result<(PoseidonRlnCircuit<E::Fr, HEIGHT>, Vec<E::Fr>), Error> {
+		let params2 = setup_params(curve, 5, 2);
+		let hasher2 = Poseidon::<E::Fr>::new(params2);
+
+		let secret = E::Fr::rand(rng);
+		let leaves = vec![rln::identity_commitment(&secret, &hasher2)?];
+
+		let (circuit, .., public_inputs) = Self::setup_circuit_with_privates(
+			curve,
+			secret,
+			&leaves,
+			0,
+			E::Fr::rand(rng),
+			E::Fr::rand(rng),
+			default_leaf,
+		)?;
+		Ok((circuit, public_inputs))
+	}
+
+	/// Sets up a circuit proving that the identity at `index` of the group
+	/// holding `leaves` sends the message hashed to `x` in `epoch`. Returns
+	/// the circuit, the share, the epoch nullifier, the root and the public
+	/// inputs.
+	#[allow(clippy::too_many_arguments)]
+	#[allow(clippy::type_complexity)]
+	pub fn setup_circuit_with_privates(
+		curve: Curve,
+		secret: E::Fr,
+		leaves: &[E::Fr],
+		index: u64,
+		epoch: E::Fr,
+		x: E::Fr,
+		default_leaf: [u8; 32],
+	) -> Result<
+		(
+			PoseidonRlnCircuit<E::Fr, HEIGHT>,
+			Share<E::Fr>,
+			E::Fr,
+			E::Fr,
+			Vec<E::Fr>,
+		),
+		Error,
+	> {
+		// Initialize hashers
+		let params2 = setup_params(curve, 5, 2);
+		let params3 = setup_params(curve, 5, 3);
+		let hasher2 = Poseidon::<E::Fr>::new(params2);
+		let hasher3 = Poseidon::<E::Fr>::new(params3);
+		// Setup inputs
+		let (share, nullifier) = rln::create_share(&secret, &epoch, &x, &hasher2, &hasher3)?;
+		let (tree, path) = setup_tree_and_create_path::<E::Fr, Poseidon<E::Fr>, HEIGHT>(
+			&hasher3,
+			leaves,
+			index,
+			&default_leaf,
+		)?;
+		let root = tree.root();
+
+		let circuit = PoseidonRlnCircuit::<E::Fr, HEIGHT>::new(
+			share.x, share.y, epoch, nullifier, secret, path, root, hasher2, hasher3,
+		);
+		let public_inputs = construct_public_inputs(root, epoch, share.x, share.y, nullifier);
+		Ok((circuit, share, nullifier, root, public_inputs))
+	}
+
+	/// Creates a proof that the identity at `index` of the group holding
+	/// `leaves` sends `signal` in `epoch`
+	#[allow(clippy::too_many_arguments)]
+	pub fn create_proof<R: RngCore + CryptoRng>(
+		curve: Curve,
+		secret: Vec<u8>,
+		leaves: Vec<Vec<u8>>,
+		index: u64,
+		epoch: Vec<u8>,
+		signal: Vec<u8>,
+		pk: Vec<u8>,
+		default_leaf: [u8; 32],
+		rng: &mut R,
+	) -> Result<RlnProof, Error> {
+		// Get field element version of all the data
+		let secret_f = E::Fr::from_be_bytes_mod_order(&secret);
+		let leaves_f: Vec<E::Fr> = leaves
+			.iter()
+			.map(|x| E::Fr::from_be_bytes_mod_order(x))
+			.collect();
+		let epoch_f = E::Fr::from_be_bytes_mod_order(&epoch);
+		let x_f = signal_hash::<E::Fr>(&signal);
+
+		let (circuit, share, nullifier, root, public_inputs) = Self::setup_circuit_with_privates(
+			curve,
+			secret_f,
+			&leaves_f,
+			index,
+			epoch_f,
+			x_f,
+			default_leaf,
+		)?;
+
+		let public_inputs_raw = RlnPublicInputs::from_field_slice(&public_inputs)?.to_bytes_vec();
+
+		let proof = prove_unchecked::<E, _, _>(circuit, &pk, rng)?;
+
+		Ok(RlnProof {
+			proof,
+			root_raw: root.into_repr().to_bytes_be(),
+			epoch_raw: epoch_f.into_repr().to_bytes_be(),
+			x_raw: share.x.into_repr().to_bytes_be(),
+			y_raw: share.y.into_repr().to_bytes_be(),
+			nullifier_raw: nullifier.into_repr().to_bytes_be(),
+			public_inputs_raw,
+		})
+	}
+
+	/// Verifies that `proof` sends `signal` in `epoch` for the group with
+	/// root `root`. The caller still has to check whether the epoch nullifier
+	/// of the proof was seen before in that epoch, and if so recover the
+	/// secret key with `recover_secret`.
+	pub fn verify_proof(
+		root: Vec<u8>,
+		epoch: Vec<u8>,
+		signal: Vec<u8>,
+		proof: &RlnProof,
+		vk: Vec<u8>,
+	) -> Result<bool, Error> {
+		let public_inputs = construct_public_inputs(
+			E::Fr::from_be_bytes_mod_order(&root),
+			E::Fr::from_be_bytes_mod_order(&epoch),
+			signal_hash::<E::Fr>(&signal),
+			E::Fr::from_be_bytes_mod_order(&proof.y_raw),
+			E::Fr::from_be_bytes_mod_order(&proof.nullifier_raw),
+		);
+		verify_unchecked::<E>(&public_inputs, &vk, &proof.proof)
+	}
+
+	/// Recovers the secret key of a member from two proofs of distinct
+	/// messages with the same epoch nullifier
+	pub fn recover_secret(a: &RlnProof, b: &RlnProof) -> Result<Vec<u8>, Error> {
+		if a.nullifier_raw != b.nullifier_raw {
+			return Err(RlnProverError::NullifierMismatch.into());
+		}
+		let share = |proof: &RlnProof| Share {
+			x: E::Fr::from_be_bytes_mod_order(&proof.x_raw),
+			y: E::Fr::from_be_bytes_mod_order(&proof.y_raw),
+		};
+		let secret = rln::recover_secret(&share(a), &share(b))?;
+		Ok(secret.into_repr().to_bytes_be())
+	}
+}
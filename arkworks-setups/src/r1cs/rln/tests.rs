@@ -0,0 +1,139 @@
+use super::{setup_params, RlnR1CSProver};
+use crate::common::*;
+use ark_bn254::{Bn254, Fr as Bn254Fr};
+use ark_ff::{BigInteger, PrimeField};
+use ark_std::{test_rng, vec, vec::Vec};
+use arkworks_native_gadgets::poseidon::Poseidon;
+use arkworks_utils::Curve;
+
+pub const HEIGHT: usize = 30;
+
+#[allow(non_camel_case_types)]
+type RlnR1CSProver_Bn254_Poseidon_30 = RlnR1CSProver<Bn254, HEIGHT>;
+pub const DEFAULT_LEAF: [u8; 32] = [0u8; 32];
+
+#[test]
+fn setup_and_prove_random_rln() {
+	let rng = &mut test_rng();
+	let curve = Curve::Bn254;
+
+	let (circuit, public_inputs) =
+		RlnR1CSProver_Bn254_Poseidon_30::setup_random_circuit(curve, DEFAULT_LEAF, rng).unwrap();
+	let (pk, vk) = setup_keys::<Bn254, _, _>(circuit.clone(), rng).unwrap();
+	let proof = prove::<Bn254, _, _>(circuit, &pk, rng).unwrap();
+	let res = verify::<Bn254>(&public_inputs, &vk, &proof).unwrap();
+	assert!(res);
+}
+
+#[test]
+fn should_reveal_secret_after_two_messages_in_an_epoch() {
+	let rng = &mut test_rng();
+	let curve = Curve::Bn254;
+
+	let (circuit, _) =
+		RlnR1CSProver_Bn254_Poseidon_30::setup_random_circuit(curve, DEFAULT_LEAF, rng).unwrap();
+	let (pk, vk) = setup_keys_unchecked::<Bn254, _, _>(circuit, rng).unwrap();
+
+	// A group of a few members
+	let identities = (0..4)
+		.map(|_| RlnR1CSProver_Bn254_Poseidon_30::create_random_identity(curve, rng).unwrap())
+		.collect::<Vec<_>>();
+	let leaves = identities
+		.iter()
+		.map(|x| x.commitment_bytes.clone())
+		.collect::<Vec<_>>();
+	let leaves_f: Vec<Bn254Fr> = leaves
+		.iter()
+		.map(|x| Bn254Fr::from_be_bytes_mod_order(x))
+		.collect();
+	let tree_hasher = Poseidon::<Bn254Fr>::new(setup_params(curve, 5, 3));
+	let tree = create_merkle_tree::<Bn254Fr, Poseidon<Bn254Fr>, HEIGHT>(
+		&tree_hasher,
+		&leaves_f,
+		&DEFAULT_LEAF,
+	);
+	let root = tree.root().into_repr().to_bytes_be();
+
+	let member = &identities[1];
+	let epoch = Bn254Fr::from(1u64).into_repr().to_bytes_be();
+	let create_proof = |epoch: &Vec<u8>, signal: &[u8], rng: &mut _| {
+		RlnR1CSProver_Bn254_Poseidon_30::create_proof(
+			curve,
+			member.secret_bytes.clone(),
+			leaves.clone(),
+			1,
+			epoch.clone(),
+			signal.to_vec(),
+			pk.clone(),
+			DEFAULT_LEAF,
+			rng,
+		)
+		.unwrap()
+	};
+
+	let first = create_proof(&epoch, b"hello", rng);
+	assert_eq!(first.root_raw, root);
+	let res = RlnR1CSProver_Bn254_Poseidon_30::verify_proof(
+		root.clone(),
+		epoch.clone(),
+		b"hello".to_vec(),
+		&first,
+		vk.clone(),
+	)
+	.unwrap();
+	assert!(res);
+
+	// A message in the next epoch can't be linked to the first one
+	let next_epoch = Bn254Fr::from(2u64).into_repr().to_bytes_be();
+	let other_epoch = create_proof(&next_epoch, b"world", rng);
+	assert_ne!(first.nullifier_raw, other_epoch.nullifier_raw);
+	assert!(RlnR1CSProver_Bn254_Poseidon_30::recover_secret(&first, &other_epoch).is_err());
+
+	// A second message in the same epoch is valid, but reveals the secret key
+	let second = create_proof(&epoch, b"world", rng);
+	let res =
+		RlnR1CSProver_Bn254_Poseidon_30::verify_proof(root, epoch, b"world".to_vec(), &second, vk)
+			.unwrap();
+	assert!(res);
+	assert_eq!(first.nullifier_raw, second.nullifier_raw);
+	let secret = RlnR1CSProver_Bn254_Poseidon_30::recover_secret(&first, &second).unwrap();
+	assert_eq!(secret, member.secret_bytes);
+}
+
+#[test]
+fn should_fail_with_invalid_share() {
+	let rng = &mut test_rng();
+	let curve = Curve::Bn254;
+
+	let (circuit, _) =
+		RlnR1CSProver_Bn254_Poseidon_30::setup_random_circuit(curve, DEFAULT_LEAF, rng).unwrap();
+	let (pk, vk) = setup_keys_unchecked::<Bn254, _, _>(circuit, rng).unwrap();
+
+	let identity = RlnR1CSProver_Bn254_Poseidon_30::create_random_identity(curve, rng).unwrap();
+	let epoch = Bn254Fr::from(1u64).into_repr().to_bytes_be();
+	let mut proof = RlnR1CSProver_Bn254_Poseidon_30::create_proof(
+		curve,
+		identity.secret_bytes,
+		vec![identity.commitment_bytes],
+		0,
+		epoch.clone(),
+		b"hello".to_vec(),
+		pk,
+		DEFAULT_LEAF,
+		rng,
+	)
+	.unwrap();
+
+	// A share that isn't on the line of the member is rejected
+	let y = Bn254Fr::from_be_bytes_mod_order(&proof.y_raw) + Bn254Fr::from(1u64);
+	proof.y_raw = y.into_repr().to_bytes_be();
+	let res = RlnR1CSProver_Bn254_Poseidon_30::verify_proof(
+		proof.root_raw.clone(),
+		epoch,
+		b"hello".to_vec(),
+		&proof,
+		vk,
+	)
+	.unwrap();
+	assert!(!res);
+}
@@ -0,0 +1,233 @@
+use crate::{
+	common::*,
+	public_inputs::{PublicInputs, SemaphorePublicInputs},
+};
+use ark_crypto_primitives::Error;
+use ark_ec::PairingEngine;
+use ark_ff::{BigInteger, PrimeField};
+use ark_std::{
+	marker::PhantomData,
+	rand::{CryptoRng, RngCore},
+	vec,
+	vec::Vec,
+	UniformRand,
+};
+use arkworks_native_gadgets::poseidon::{FieldHasher, Poseidon};
+use arkworks_r1cs_circuits::semaphore::SemaphoreCircuit;
+use arkworks_r1cs_gadgets::poseidon::PoseidonGadget;
+use arkworks_utils::Curve;
+
+#[cfg(test)]
+mod tests;
+
+pub fn construct_public_inputs<F: PrimeField>(
+	root: F,
+	nullifier_hash: F,
+	signal_hash: F,
+	external_nullifier: F,
+) -> Vec<F> {
+	SemaphorePublicInputs {
+		root,
+		nullifier_hash,
+		signal_hash,
+		external_nullifier,
+	}
+	.to_field_vec()
+}
+
+/// Hashes an arbitrary signal into a field element
+pub fn signal_hash<F: PrimeField>(signal: &[u8]) -> F {
+	F::from_be_bytes_mod_order(&keccak_256(signal))
+}
+
+pub type PoseidonSemaphoreCircuit<F, const N: usize> = SemaphoreCircuit<F, PoseidonGadget<F>, N>;
+
+pub struct SemaphoreR1CSProver<E: PairingEngine, const HEIGHT: usize> {
+	engine: PhantomData<E>,
+}
+
+impl<E: PairingEngine, const HEIGHT: usize> SemaphoreR1CSProver<E, HEIGHT> {
+	/// Creates an identity from its secrets
+	pub fn create_identity_with_privates(
+		curve: Curve,
+		identity_nullifier: Vec<u8>,
+		identity_trapdoor: Vec<u8>,
+	) -> Result<Identity, Error> {
+		let identity_nullifier_f = E::Fr::from_be_bytes_mod_order(&identity_nullifier);
+		let identity_trapdoor_f = E::Fr::from_be_bytes_mod_order(&identity_trapdoor);
+
+		let params3 = setup_params(curve, 5, 3);
+		let poseidon = Poseidon::<E::Fr>::new(params3);
+		let commitment = poseidon.hash_two(&identity_nullifier_f, &identity_trapdoor_f)?;
+		Ok(Identity {
+			identity_nullifier_bytes: identity_nullifier_f.into_repr().to_bytes_be(),
+			identity_trapdoor_bytes: identity_trapdoor_f.into_repr().to_bytes_be(),
+			commitment_bytes: commitment.into_repr().to_bytes_be(),
+		})
+	}
+
+	/// Creates an identity with random secrets
+	pub fn create_random_identity<R: RngCore + CryptoRng>(
+		curve: Curve,
+		rng: &mut R,
+	) -> Result<Identity, Error> {
+		let identity_nullifier = E::Fr::rand(rng);
+		let identity_trapdoor = E::Fr::rand(rng);
+		Self::create_identity_with_privates(
+			curve,
+			identity_nullifier.into_repr().to_bytes_be(),
+			identity_trapdoor.into_repr().to_bytes_be(),
+		)
+	}
+
+	/// Sets up a circuit for a group holding a single random identity, e.g.
+	/// for generating the keys
+	#[allow(dead_code)]
+	#[allow(clippy::type_complexity)]
+	pub fn setup_random_circuit<R: RngCore + CryptoRng>(
+		curve: Curve,
+		default_leaf: [u8; 32],
+		rng: &mut R,
+	) -> Result<(PoseidonSemaphoreCircuit<E::Fr, HEIGHT>, Vec<E::Fr>), Error> {
+		let params3 = setup_params(curve, 5, 3);
+		let poseidon = Poseidon::<E::Fr>::new(params3);
+
+		let identity_nullifier = E::Fr::rand(rng);
+		let identity_trapdoor = E::Fr::rand(rng);
+		let leaves = vec![poseidon.hash_two(&identity_nullifier, &identity_trapdoor)?];
+
+		let (circuit, .., public_inputs) = Self::setup_circuit_with_privates(
+			curve,
+			identity_nullifier,
+			identity_trapdoor,
+			&leaves,
+			0,
+			E::Fr::rand(rng),
+			E::Fr::rand(rng),
+			default_leaf,
+		)?;
+		Ok((circuit, public_inputs))
+	}
+
+	/// Sets up a circuit proving that the identity at `index` of the group
+	/// holding `leaves` broadcasts the signal hashed to `signal_hash`
+	#[allow(clippy::too_many_arguments)]
+	#[allow(clippy::type_complexity)]
+	pub fn setup_circuit_with_privates(
+		curve: Curve,
+		identity_nullifier: E::Fr,
+		identity_trapdoor: E::Fr,
+		leaves: &[E::Fr],
+		index: u64,
+		external_nullifier: E::Fr,
+		signal_hash: E::Fr,
+		default_leaf: [u8; 32],
+	) -> Result<
+		(
+			PoseidonSemaphoreCircuit<E::Fr, HEIGHT>,
+			E::Fr,
+			E::Fr,
+			E::Fr,
+			Vec<E::Fr>,
+		),
+		Error,
+	> {
+		// Initialize hasher
+		let params3 = setup_params(curve, 5, 3);
+		let poseidon = Poseidon::<E::Fr>::new(params3);
+		// Setup inputs
+		let commitment = poseidon.hash_two(&identity_nullifier, &identity_trapdoor)?;
+		let nullifier_hash = poseidon.hash_two(&external_nullifier, &identity_nullifier)?;
+		let (tree, path) = setup_tree_and_create_path::<E::Fr, Poseidon<E::Fr>, HEIGHT>(
+			&poseidon,
+			leaves,
+			index,
+			&default_leaf,
+		)?;
+		let root = tree.root();
+
+		let circuit = PoseidonSemaphoreCircuit::<E::Fr, HEIGHT>::new(
+			signal_hash,
+			external_nullifier,
+			identity_nullifier,
+			identity_trapdoor,
+			path,
+			root,
+			nullifier_hash,
+			poseidon,
+		);
+		let public_inputs =
+			construct_public_inputs(root, nullifier_hash, signal_hash, external_nullifier);
+		Ok((circuit, commitment, nullifier_hash, root, public_inputs))
+	}
+
+	/// Creates a proof that the identity at `index` of the group holding
+	/// `leaves` broadcasts `signal` in the scope of `external_nullifier`
+	#[allow(clippy::too_many_arguments)]
+	pub fn create_proof<R: RngCore + CryptoRng>(
+		curve: Curve,
+		identity_nullifier: Vec<u8>,
+		identity_trapdoor: Vec<u8>,
+		leaves: Vec<Vec<u8>>,
+		index: u64,
+		external_nullifier: Vec<u8>,
+		signal: Vec<u8>,
+		pk: Vec<u8>,
+		default_leaf: [u8; 32],
+		rng: &mut R,
+	) -> Result<SemaphoreProof, Error> {
+		// Get field element version of all the data
+		let identity_nullifier_f = E::Fr::from_be_bytes_mod_order(&identity_nullifier);
+		let identity_trapdoor_f = E::Fr::from_be_bytes_mod_order(&identity_trapdoor);
+		let leaves_f: Vec<E::Fr> = leaves
+			.iter()
+			.map(|x| E::Fr::from_be_bytes_mod_order(x))
+			.collect();
+		let external_nullifier_f = E::Fr::from_be_bytes_mod_order(&external_nullifier);
+		let signal_hash_f = signal_hash::<E::Fr>(&signal);
+
+		let (circuit, _, nullifier_hash, root, public_inputs) = Self::setup_circuit_with_privates(
+			curve,
+			identity_nullifier_f,
+			identity_trapdoor_f,
+			&leaves_f,
+			index,
+			external_nullifier_f,
+			signal_hash_f,
+			default_leaf,
+		)?;
+
+		let public_inputs_raw =
+			SemaphorePublicInputs::from_field_slice(&public_inputs)?.to_bytes_vec();
+
+		let proof = prove_unchecked::<E, _, _>(circuit, &pk, rng)?;
+
+		Ok(SemaphoreProof {
+			proof,
+			nullifier_hash_raw: nullifier_hash.into_repr().to_bytes_be(),
+			root_raw: root.into_repr().to_bytes_be(),
+			signal_hash_raw: signal_hash_f.into_repr().to_bytes_be(),
+			public_inputs_raw,
+		})
+	}
+
+	/// Verifies that `proof` signals `signal` in the scope of
+	/// `external_nullifier` for the group with root `root`. The caller still
+	/// has to check that the nullifier hash of the proof wasn't seen before in
+	/// that scope.
+	pub fn verify_proof(
+		root: Vec<u8>,
+		external_nullifier: Vec<u8>,
+		signal: Vec<u8>,
+		proof: &SemaphoreProof,
+		vk: Vec<u8>,
+	) -> Result<bool, Error> {
+		let public_inputs = construct_public_inputs(
+			E::Fr::from_be_bytes_mod_order(&root),
+			E::Fr::from_be_bytes_mod_order(&proof.nullifier_hash_raw),
+			signal_hash::<E::Fr>(&signal),
+			E::Fr::from_be_bytes_mod_order(&external_nullifier),
+		);
+		verify_unchecked::<E>(&public_inputs, &vk, &proof.proof)
+	}
+}
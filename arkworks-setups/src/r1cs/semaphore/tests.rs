@@ -0,0 +1,158 @@
+use super::{setup_params, SemaphoreR1CSProver};
+use crate::common::*;
+use ark_bn254::{Bn254, Fr as Bn254Fr};
+use ark_ff::{BigInteger, PrimeField, UniformRand};
+use ark_std::{test_rng, vec, vec::Vec};
+use arkworks_native_gadgets::poseidon::Poseidon;
+use arkworks_utils::Curve;
+
+pub const HEIGHT: usize = 30;
+
+#[allow(non_camel_case_types)]
+type SemaphoreR1CSProver_Bn254_Poseidon_30 = SemaphoreR1CSProver<Bn254, HEIGHT>;
+pub const DEFAULT_LEAF: [u8; 32] = [0u8; 32];
+
+#[test]
+fn setup_and_prove_random_semaphore() {
+	let rng = &mut test_rng();
+	let curve = Curve::Bn254;
+
+	let (circuit, public_inputs) =
+		SemaphoreR1CSProver_Bn254_Poseidon_30::setup_random_circuit(curve, DEFAULT_LEAF, rng)
+			.unwrap();
+	let (pk, vk) = setup_keys::<Bn254, _, _>(circuit.clone(), rng).unwrap();
+	let proof = prove::<Bn254, _, _>(circuit, &pk, rng).unwrap();
+	let res = verify::<Bn254>(&public_inputs, &vk, &proof).unwrap();
+	assert!(res);
+}
+
+#[test]
+fn should_signal_anonymously_once_per_scope() {
+	let rng = &mut test_rng();
+	let curve = Curve::Bn254;
+
+	let (circuit, _) =
+		SemaphoreR1CSProver_Bn254_Poseidon_30::setup_random_circuit(curve, DEFAULT_LEAF, rng)
+			.unwrap();
+	let (pk, vk) = setup_keys_unchecked::<Bn254, _, _>(circuit, rng).unwrap();
+
+	// A group of a few members
+	let identities = (0..4)
+		.map(|_| SemaphoreR1CSProver_Bn254_Poseidon_30::create_random_identity(curve, rng).unwrap())
+		.collect::<Vec<_>>();
+	let leaves = identities
+		.iter()
+		.map(|x| x.commitment_bytes.clone())
+		.collect::<Vec<_>>();
+	let leaves_f: Vec<Bn254Fr> = leaves
+		.iter()
+		.map(|x| Bn254Fr::from_be_bytes_mod_order(x))
+		.collect();
+	let tree_hasher = Poseidon::<Bn254Fr>::new(setup_params(curve, 5, 3));
+	let tree = create_merkle_tree::<Bn254Fr, Poseidon<Bn254Fr>, HEIGHT>(
+		&tree_hasher,
+		&leaves_f,
+		&DEFAULT_LEAF,
+	);
+	let root = tree.root().into_repr().to_bytes_be();
+
+	let member = &identities[2];
+	let external_nullifier = Bn254Fr::rand(rng).into_repr().to_bytes_be();
+	let signal = b"yes".to_vec();
+	let create_proof = |external_nullifier: &Vec<u8>, rng: &mut _| {
+		SemaphoreR1CSProver_Bn254_Poseidon_30::create_proof(
+			curve,
+			member.identity_nullifier_bytes.clone(),
+			member.identity_trapdoor_bytes.clone(),
+			leaves.clone(),
+			2,
+			external_nullifier.clone(),
+			signal.clone(),
+			pk.clone(),
+			DEFAULT_LEAF,
+			rng,
+		)
+		.unwrap()
+	};
+
+	let proof = create_proof(&external_nullifier, rng);
+	assert_eq!(proof.root_raw, root);
+	let res = SemaphoreR1CSProver_Bn254_Poseidon_30::verify_proof(
+		root.clone(),
+		external_nullifier.clone(),
+		signal.clone(),
+		&proof,
+		vk.clone(),
+	)
+	.unwrap();
+	assert!(res);
+
+	// The proof doesn't hold for another signal or another scope
+	let res = SemaphoreR1CSProver_Bn254_Poseidon_30::verify_proof(
+		root.clone(),
+		external_nullifier.clone(),
+		b"no".to_vec(),
+		&proof,
+		vk.clone(),
+	)
+	.unwrap();
+	assert!(!res);
+	let other_external_nullifier = Bn254Fr::rand(rng).into_repr().to_bytes_be();
+	let res = SemaphoreR1CSProver_Bn254_Poseidon_30::verify_proof(
+		root,
+		other_external_nullifier.clone(),
+		signal.clone(),
+		&proof,
+		vk,
+	)
+	.unwrap();
+	assert!(!res);
+
+	// Signalling again in the same scope reveals the same nullifier hash, while
+	// another scope gives an unlinkable one
+	let same_scope = create_proof(&external_nullifier, rng);
+	assert_eq!(same_scope.nullifier_hash_raw, proof.nullifier_hash_raw);
+	let other_scope = create_proof(&other_external_nullifier, rng);
+	assert_ne!(other_scope.nullifier_hash_raw, proof.nullifier_hash_raw);
+}
+
+#[test]
+fn should_fail_for_identity_outside_the_group() {
+	let rng = &mut test_rng();
+	let curve = Curve::Bn254;
+
+	let (circuit, _) =
+		SemaphoreR1CSProver_Bn254_Poseidon_30::setup_random_circuit(curve, DEFAULT_LEAF, rng)
+			.unwrap();
+	let (pk, vk) = setup_keys_unchecked::<Bn254, _, _>(circuit, rng).unwrap();
+
+	let member = SemaphoreR1CSProver_Bn254_Poseidon_30::create_random_identity(curve, rng).unwrap();
+	let outsider =
+		SemaphoreR1CSProver_Bn254_Poseidon_30::create_random_identity(curve, rng).unwrap();
+	let leaves = vec![member.commitment_bytes];
+	let external_nullifier = Bn254Fr::rand(rng).into_repr().to_bytes_be();
+
+	// The outsider claims to be the member at index 0
+	let proof = SemaphoreR1CSProver_Bn254_Poseidon_30::create_proof(
+		curve,
+		outsider.identity_nullifier_bytes,
+		outsider.identity_trapdoor_bytes,
+		leaves,
+		0,
+		external_nullifier.clone(),
+		b"yes".to_vec(),
+		pk,
+		DEFAULT_LEAF,
+		rng,
+	)
+	.unwrap();
+	let res = SemaphoreR1CSProver_Bn254_Poseidon_30::verify_proof(
+		proof.root_raw.clone(),
+		external_nullifier,
+		b"yes".to_vec(),
+		&proof,
+		vk,
+	)
+	.unwrap();
+	assert!(!res);
+}
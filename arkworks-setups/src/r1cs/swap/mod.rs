@@ -0,0 +1,571 @@
+use crate::{
+	common::*,
+	keypair::Keypair,
+	public_inputs::{PublicInputs, SwapPublicInputs},
+	utxo::MaspUtxo,
+};
+use ark_crypto_primitives::Error;
+use ark_ec::PairingEngine;
+use ark_ff::{BigInteger, PrimeField, Zero};
+use ark_std::{
+	collections::{btree_map::Entry, BTreeMap},
+	marker::PhantomData,
+	rand::{CryptoRng, Rng, RngCore},
+	vec::Vec,
+	UniformRand,
+};
+use arkworks_native_gadgets::{
+	merkle_tree::Path,
+	poseidon::{FieldHasher, Poseidon},
+};
+use arkworks_r1cs_circuits::swap::{is_valid_amount_bits, SwapCircuit, SwapTerms};
+use arkworks_r1cs_gadgets::poseidon::PoseidonGadget;
+use arkworks_utils::Curve;
+
+#[cfg(test)]
+mod tests;
+
+#[derive(Debug)]
+pub enum SwapError {
+	InvalidInputChainId,
+	InvalidAssetId,
+	KeyMismatch,
+	MissingPrivateKey,
+	MissingLeaves,
+	InvalidPayment,
+	OrderMismatch,
+	RateMismatch,
+	RateOutOfRange,
+	InvalidAmountBits,
+}
+
+impl core::fmt::Display for SwapError {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		let msg = match self {
+			Self::InvalidInputChainId => ark_std::format!("Invalid input chain ID"),
+			Self::InvalidAssetId => ark_std::format!("Utxo is for another asset than the order"),
+			Self::KeyMismatch => ark_std::format!("Inputs are owned by different keys"),
+			Self::MissingPrivateKey => ark_std::format!("Utxo has no private key"),
+			Self::MissingLeaves => ark_std::format!("No leaves for the chain of an input"),
+			Self::InvalidPayment => {
+				ark_std::format!("First output doesn't pay the counterparty")
+			}
+			Self::OrderMismatch => ark_std::format!("Half doesn't settle the orders"),
+			Self::RateMismatch => ark_std::format!("Amounts don't follow the exchange rate"),
+			Self::RateOutOfRange => ark_std::format!("Rate terms don't fit in the amount bits"),
+			Self::InvalidAmountBits => {
+				ark_std::format!("Products of amounts would overflow the field")
+			}
+		};
+		write!(f, "{}", msg)
+	}
+}
+
+impl ark_std::error::Error for SwapError {}
+
+/// The order of a party, giving `give_amount` of `give_asset_id` for
+/// `want_amount` of `want_asset_id`. The public key owns the Utxos the party
+/// spends and receives the payment of the counterparty.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SwapOrder<F: PrimeField> {
+	pub give_asset_id: F,
+	pub give_amount: F,
+	pub want_asset_id: F,
+	pub want_amount: F,
+	pub public_key: F,
+}
+
+impl<F: PrimeField> SwapOrder<F> {
+	/// The hash `Hash(give_asset_id, give_amount, want_asset_id, want_amount,
+	/// public_key)` the party agrees to, and the swap settles
+	pub fn hash(&self, hasher6: &Poseidon<F>) -> Result<F, Error> {
+		let hash = hasher6.hash(&[
+			self.give_asset_id,
+			self.give_amount,
+			self.want_asset_id,
+			self.want_amount,
+			self.public_key,
+		])?;
+		Ok(hash)
+	}
+
+	/// Whether `other` is the order of a counterparty of this order, giving
+	/// what this order wants for what it gives
+	pub fn is_counter_order(&self, other: &Self) -> bool {
+		self.give_asset_id == other.want_asset_id
+			&& self.give_amount == other.want_amount
+			&& self.want_asset_id == other.give_asset_id
+			&& self.want_amount == other.give_amount
+	}
+}
+
+/// The exchange rate `num / den` of a swap, with the blinding of its
+/// commitment
+#[derive(Debug, Clone, Copy)]
+pub struct SwapRate<F: PrimeField> {
+	pub num: F,
+	pub den: F,
+	pub blinding: F,
+}
+
+impl<F: PrimeField> SwapRate<F> {
+	/// The commitment `Hash(num, den, blinding)`
+	pub fn commitment(&self, hasher4: &Poseidon<F>) -> Result<F, Error> {
+		let commitment = hasher4.hash(&[self.num, self.den, self.blinding])?;
+		Ok(commitment)
+	}
+}
+
+pub type PoseidonSwapCircuit<
+	F,
+	const HEIGHT: usize,
+	const INS: usize,
+	const OUTS: usize,
+	const ANCHOR_CT: usize,
+> = SwapCircuit<F, PoseidonGadget<F>, HEIGHT, INS, OUTS, ANCHOR_CT>;
+
+/// Prover for the halves of swaps between two parties of a multi-asset
+/// VAnchor with the given `HEIGHT` and `ANCHOR_CT`, where each party spends
+/// `INS` Utxos and creates `OUTS` Utxos.
+///
+/// Swapping goes as follows:
+/// * the parties exchange their orders, created with `create_order`, and agree
+///   on the rate and the external data,
+/// * each party calls `create_proof` for its own half with its own Utxos,
+///   paying the counterparty in its first output,
+/// * the verifier settles both halves together with `verify_swap`.
+pub struct SwapR1CSProver<
+	E: PairingEngine,
+	const HEIGHT: usize,
+	const ANCHOR_CT: usize,
+	const INS: usize,
+	const OUTS: usize,
+> {
+	engine: PhantomData<E>,
+}
+
+impl<
+		E: PairingEngine,
+		const HEIGHT: usize,
+		const ANCHOR_CT: usize,
+		const INS: usize,
+		const OUTS: usize,
+	> SwapR1CSProver<E, HEIGHT, ANCHOR_CT, INS, OUTS>
+{
+	/// Creates the order of the owner of `private_key`
+	pub fn create_order(
+		curve: Curve,
+		give_asset_id: u64,
+		give_amount: u128,
+		want_asset_id: u64,
+		want_amount: u128,
+		private_key: Vec<u8>,
+	) -> Result<SwapOrder<E::Fr>, Error> {
+		let keypair_hasher = Poseidon::<E::Fr>::new(setup_params(curve, 5, 2));
+		let public_key = keypair_hasher.hash(&[E::Fr::from_be_bytes_mod_order(&private_key)])?;
+		Ok(SwapOrder {
+			give_asset_id: E::Fr::from(give_asset_id),
+			give_amount: E::Fr::from(give_amount),
+			want_asset_id: E::Fr::from(want_asset_id),
+			want_amount: E::Fr::from(want_amount),
+			public_key,
+		})
+	}
+
+	#[allow(dead_code)]
+	pub fn setup_random_circuit<R: RngCore + CryptoRng>(
+		curve: Curve,
+		default_leaf: [u8; 32],
+		amount_bits: usize,
+		rng: &mut R,
+	) -> Result<PoseidonSwapCircuit<E::Fr, HEIGHT, INS, OUTS, ANCHOR_CT>, Error> {
+		// Initialize hashers
+		let params2 = setup_params::<E::Fr>(curve, 5, 2);
+		let params3 = setup_params::<E::Fr>(curve, 5, 3);
+		let params4 = setup_params::<E::Fr>(curve, 5, 4);
+		let params6 = setup_params::<E::Fr>(curve, 5, 6);
+		let keypair_hasher = Poseidon::<E::Fr> { params: params2 };
+		let tree_hasher = Poseidon::<E::Fr> { params: params3 };
+		let nullifier_hasher = Poseidon::<E::Fr> { params: params4 };
+		let leaf_hasher = Poseidon::<E::Fr> { params: params6 };
+
+		let chain_id: u64 = rng.gen();
+		let asset_ids: [u64; 2] = [rng.gen(), rng.gen()];
+		let private_key = E::Fr::rand(rng);
+		let counterparty_key = Keypair::new(E::Fr::rand(rng), &keypair_hasher).public_key;
+		// Orders swapping nothing
+		let order = SwapOrder {
+			give_asset_id: E::Fr::from(asset_ids[0]),
+			give_amount: E::Fr::zero(),
+			want_asset_id: E::Fr::from(asset_ids[1]),
+			want_amount: E::Fr::zero(),
+			public_key: Keypair::new(private_key, &keypair_hasher).public_key,
+		};
+		let counter_order = SwapOrder {
+			give_asset_id: E::Fr::from(asset_ids[1]),
+			give_amount: E::Fr::zero(),
+			want_asset_id: E::Fr::from(asset_ids[0]),
+			want_amount: E::Fr::zero(),
+			public_key: counterparty_key,
+		};
+
+		// Utxos with a zero amount, which don't need to be in the root set
+		let mut in_root_set = [E::Fr::zero(); ANCHOR_CT];
+		let mut in_utxos = Vec::with_capacity(INS);
+		let mut in_paths = Vec::with_capacity(INS);
+		for _ in 0..INS {
+			let utxo = MaspUtxo::new_with_privates(
+				chain_id,
+				asset_ids[0],
+				E::Fr::zero(),
+				Some(0),
+				private_key,
+				E::Fr::rand(rng),
+				&keypair_hasher,
+				&leaf_hasher,
+			)?;
+			let (tree, path) = setup_tree_and_create_path::<E::Fr, Poseidon<E::Fr>, HEIGHT>(
+				&tree_hasher,
+				&[utxo.commitment],
+				0,
+				&default_leaf,
+			)?;
+			in_root_set[0] = tree.root();
+			in_utxos.push(utxo);
+			in_paths.push(path);
+		}
+		// The first output pays nothing to the counterparty
+		let mut out_utxos = Vec::with_capacity(OUTS);
+		for _ in 0..OUTS {
+			out_utxos.push(MaspUtxo::new_with_public(
+				chain_id,
+				asset_ids[0],
+				E::Fr::zero(),
+				None,
+				counterparty_key,
+				E::Fr::rand(rng),
+				&leaf_hasher,
+			)?);
+		}
+		let rate = SwapRate {
+			num: E::Fr::from(1u64),
+			den: E::Fr::from(1u64),
+			blinding: E::Fr::rand(rng),
+		};
+
+		Self::setup_circuit(
+			E::Fr::from(chain_id),
+			E::Fr::rand(rng),
+			rate,
+			false,
+			&order,
+			&counter_order,
+			Self::to_array(in_utxos),
+			in_paths,
+			in_root_set,
+			Self::to_array(out_utxos),
+			keypair_hasher,
+			tree_hasher,
+			nullifier_hasher,
+			leaf_hasher,
+			amount_bits,
+		)
+	}
+
+	fn to_array<T, const N: usize>(items: Vec<T>) -> [T; N] {
+		items
+			.try_into()
+			.unwrap_or_else(|_| unreachable!("one item per slot"))
+	}
+
+	/// Sets up the half of the owner of `in_utxos`, with `side` telling whether
+	/// it is on side one of the rate. Checks that `amount_bits` leaves room for
+	/// the rate check, that every Utxo is for the asset given by `order`, that
+	/// the inputs are owned by the key of `order`, that the first output pays
+	/// `counter_order`, and that the amounts of both orders follow the rate.
+	#[allow(clippy::too_many_arguments)]
+	pub fn setup_circuit(
+		chain_id: E::Fr,
+		ext_data_hash: E::Fr,
+		rate: SwapRate<E::Fr>,
+		side: bool,
+		order: &SwapOrder<E::Fr>,
+		counter_order: &SwapOrder<E::Fr>,
+		// Input transactions
+		in_utxos: [MaspUtxo<E::Fr>; INS],
+		// Data related to tree
+		in_paths: Vec<Path<E::Fr, Poseidon<E::Fr>, HEIGHT>>,
+		public_root_set: [E::Fr; ANCHOR_CT],
+		// Output transactions
+		out_utxos: [MaspUtxo<E::Fr>; OUTS],
+		keypair_hasher: Poseidon<E::Fr>,
+		tree_hasher: Poseidon<E::Fr>,
+		nullifier_hasher: Poseidon<E::Fr>,
+		leaf_hasher: Poseidon<E::Fr>,
+		amount_bits: usize,
+	) -> Result<PoseidonSwapCircuit<E::Fr, HEIGHT, INS, OUTS, ANCHOR_CT>, Error> {
+		if !is_valid_amount_bits::<E::Fr>(amount_bits) {
+			return Err(SwapError::InvalidAmountBits.into());
+		}
+		let same_asset = in_utxos
+			.iter()
+			.map(|x| x.asset_id)
+			.chain(out_utxos.iter().map(|x| x.asset_id))
+			.all(|x| x == order.give_asset_id);
+		if !same_asset {
+			return Err(SwapError::InvalidAssetId.into());
+		}
+		let private_key = in_utxos[0]
+			.keypair
+			.secret_key
+			.ok_or(SwapError::MissingPrivateKey)?;
+		if in_utxos
+			.iter()
+			.any(|x| x.keypair.secret_key != Some(private_key))
+			|| in_utxos[0].keypair.public_key != order.public_key
+		{
+			return Err(SwapError::KeyMismatch.into());
+		}
+		if !order.is_counter_order(counter_order) || out_utxos[0].amount != order.give_amount {
+			return Err(SwapError::OrderMismatch.into());
+		}
+		if out_utxos[0].keypair.public_key != counter_order.public_key {
+			return Err(SwapError::InvalidPayment.into());
+		}
+		let (amount_0, amount_1) = if side {
+			(order.want_amount, order.give_amount)
+		} else {
+			(order.give_amount, order.want_amount)
+		};
+		if amount_1 * rate.den != amount_0 * rate.num {
+			return Err(SwapError::RateMismatch.into());
+		}
+
+		let nullifier_hash = in_utxos
+			.iter()
+			.map(|x| x.calculate_nullifier(&nullifier_hasher))
+			.collect::<Result<Vec<E::Fr>, Error>>()?;
+		let terms = SwapTerms {
+			give_asset_id: order.give_asset_id,
+			give_amount: order.give_amount,
+			want_asset_id: order.want_asset_id,
+			want_amount: order.want_amount,
+			counterparty_key: counter_order.public_key,
+		};
+
+		let circuit = PoseidonSwapCircuit::<E::Fr, HEIGHT, INS, OUTS, ANCHOR_CT>::new(
+			ext_data_hash,
+			rate.commitment(&nullifier_hasher)?,
+			rate.num,
+			rate.den,
+			rate.blinding,
+			side,
+			terms,
+			order.hash(&leaf_hasher)?,
+			counter_order.hash(&leaf_hasher)?,
+			in_utxos.iter().map(|x| x.amount).collect(),
+			in_utxos.iter().map(|x| x.blinding).collect(),
+			private_key,
+			chain_id,
+			public_root_set,
+			in_paths,
+			in_utxos
+				.iter()
+				.map(|x| E::Fr::from(x.index.unwrap_or_default()))
+				.collect(),
+			nullifier_hash,
+			out_utxos.iter().map(|x| x.commitment).collect(),
+			out_utxos.iter().map(|x| x.amount).collect(),
+			out_utxos.iter().map(|x| x.blinding).collect(),
+			out_utxos.iter().map(|x| x.chain_id).collect(),
+			out_utxos.iter().map(|x| x.keypair.public_key).collect(),
+			tree_hasher,
+			keypair_hasher,
+			leaf_hasher,
+			nullifier_hasher,
+			amount_bits,
+		);
+
+		Ok(circuit)
+	}
+
+	/// Proves the half of the owner of `in_utxos`, which settles `order`, its
+	/// own order, against `counter_order`, the order of the counterparty, at
+	/// the exchange rate `rate_num / rate_den`. `side` tells whether the party
+	/// is on side one of the rate. The first output pays the counterparty, the
+	/// others hold the change. The root set may hold up to ANCHOR_CT roots.
+	#[allow(clippy::too_many_arguments)]
+	pub fn create_proof<R: RngCore + CryptoRng>(
+		curve: Curve,
+		chain_id: u64,
+		ext_data_hash: Vec<u8>,
+		side: bool,
+		order: SwapOrder<E::Fr>,
+		counter_order: SwapOrder<E::Fr>,
+		rate_num: u128,
+		rate_den: u128,
+		rate_blinding: Vec<u8>,
+		public_root_set: Vec<Vec<u8>>,
+		in_leaves: BTreeMap<u64, Vec<Vec<u8>>>,
+		// Input transactions
+		in_utxos: [MaspUtxo<E::Fr>; INS],
+		// Output transactions
+		out_utxos: [MaspUtxo<E::Fr>; OUTS],
+		amount_bits: usize,
+		pk: Vec<u8>,
+		default_leaf: [u8; 32],
+		rng: &mut R,
+	) -> Result<SwapProof, Error> {
+		// Initialize hashers
+		let params2 = setup_params::<E::Fr>(curve, 5, 2);
+		let params3 = setup_params::<E::Fr>(curve, 5, 3);
+		let params4 = setup_params::<E::Fr>(curve, 5, 4);
+		let params6 = setup_params::<E::Fr>(curve, 5, 6);
+		let keypair_hasher = Poseidon::<E::Fr> { params: params2 };
+		let tree_hasher = Poseidon::<E::Fr> { params: params3 };
+		let nullifier_hasher = Poseidon::<E::Fr> { params: params4 };
+		let leaf_hasher = Poseidon::<E::Fr> { params: params6 };
+
+		// The terms of the rate must fit in the amount bits, as the circuit
+		// checks, so that the rate can't be chosen to wrap around the modulus
+		if amount_bits < 128 && (rate_num >> amount_bits != 0 || rate_den >> amount_bits != 0) {
+			return Err(SwapError::RateOutOfRange.into());
+		}
+
+		// Cast as field elements
+		let chain_id_elt = E::Fr::from(chain_id);
+		let ext_data_hash_elt = E::Fr::from_be_bytes_mod_order(&ext_data_hash);
+		let public_root_set_elt = pad_root_set::<E::Fr, ANCHOR_CT>(&public_root_set)?;
+		let rate = SwapRate {
+			num: E::Fr::from(rate_num),
+			den: E::Fr::from(rate_den),
+			blinding: E::Fr::from_be_bytes_mod_order(&rate_blinding),
+		};
+
+		// Throw an error if chain IDs don't match intended spending chain.
+		if in_utxos.iter().any(|utxo| utxo.chain_id_raw != chain_id) {
+			return Err(SwapError::InvalidInputChainId.into());
+		}
+
+		// Generate the paths for each UTXO
+		let mut trees = BTreeMap::<u64, SMT<E::Fr, Poseidon<E::Fr>, HEIGHT>>::new();
+		let mut in_paths = Vec::with_capacity(INS);
+		for utxo in in_utxos.iter() {
+			let index = utxo.index.unwrap_or_default();
+			// A Utxo with a zero amount isn't checked to be in the root set, so its
+			// path is taken from a tree holding only that Utxo
+			let path = if utxo.amount == E::Fr::zero() {
+				let (_, path) = setup_tree_and_create_path::<E::Fr, Poseidon<E::Fr>, HEIGHT>(
+					&tree_hasher,
+					&[utxo.commitment],
+					index,
+					&default_leaf,
+				)?;
+				path
+			} else {
+				let tree = match trees.entry(utxo.chain_id_raw) {
+					Entry::Occupied(entry) => entry.into_mut(),
+					Entry::Vacant(entry) => {
+						let leaves = in_leaves
+							.get(&utxo.chain_id_raw)
+							.ok_or(SwapError::MissingLeaves)?
+							.iter()
+							.map(|l| E::Fr::from_be_bytes_mod_order(l))
+							.collect::<Vec<E::Fr>>();
+						entry.insert(create_merkle_tree::<E::Fr, Poseidon<E::Fr>, HEIGHT>(
+							&tree_hasher,
+							&leaves,
+							&default_leaf,
+						))
+					}
+				};
+				tree.generate_membership_proof(index)
+			};
+			in_paths.push(path);
+		}
+
+		let mut nullifiers = [E::Fr::zero(); INS];
+		for (nullifier, utxo) in nullifiers.iter_mut().zip(in_utxos.iter()) {
+			*nullifier = utxo.calculate_nullifier(&nullifier_hasher)?;
+		}
+		let public_inputs = SwapPublicInputs::<E::Fr, ANCHOR_CT, INS, OUTS> {
+			ext_data_hash: ext_data_hash_elt,
+			rate_commitment: rate.commitment(&nullifier_hasher)?,
+			side: E::Fr::from(side),
+			order_hash: order.hash(&leaf_hasher)?,
+			counter_order_hash: counter_order.hash(&leaf_hasher)?,
+			nullifiers,
+			commitments: out_utxos.clone().map(|utxo| utxo.commitment),
+			chain_id: chain_id_elt,
+			roots: public_root_set_elt,
+		};
+
+		// Get the circuit
+		let circuit = Self::setup_circuit(
+			chain_id_elt,
+			ext_data_hash_elt,
+			rate,
+			side,
+			&order,
+			&counter_order,
+			in_utxos,
+			in_paths,
+			public_root_set_elt,
+			out_utxos,
+			keypair_hasher,
+			tree_hasher,
+			nullifier_hasher,
+			leaf_hasher,
+			amount_bits,
+		)?;
+
+		let proof = prove_unchecked::<E, _, _>(circuit, &pk, rng)?;
+
+		let order_hashes_raw = [public_inputs.order_hash, public_inputs.counter_order_hash]
+			.iter()
+			.map(|x| x.into_repr().to_bytes_be())
+			.collect();
+
+		Ok(SwapProof {
+			proof,
+			order_hashes_raw,
+			public_inputs_raw: public_inputs.to_bytes_vec(),
+		})
+	}
+
+	/// Verifies the halves of a swap, which settle it only together: each one
+	/// must be valid, they must be on both sides of the same rate, for the same
+	/// external data and chain, and the counterparty order of each one must be
+	/// the order of the other one. `vk` is the unchecked serialization of the
+	/// verifying key.
+	pub fn verify_swap(halves: [&SwapProof; 2], vk: &[u8]) -> Result<bool, Error> {
+		let inputs = [
+			SwapPublicInputs::<E::Fr, ANCHOR_CT, INS, OUTS>::from_bytes_slice(
+				&halves[0].public_inputs_raw,
+			)?,
+			SwapPublicInputs::<E::Fr, ANCHOR_CT, INS, OUTS>::from_bytes_slice(
+				&halves[1].public_inputs_raw,
+			)?,
+		];
+		let is_matching = inputs[0].side != inputs[1].side
+			&& inputs[0].ext_data_hash == inputs[1].ext_data_hash
+			&& inputs[0].rate_commitment == inputs[1].rate_commitment
+			&& inputs[0].chain_id == inputs[1].chain_id
+			&& inputs[0].order_hash == inputs[1].counter_order_hash
+			&& inputs[1].order_hash == inputs[0].counter_order_hash
+			&& !inputs[0]
+				.nullifiers
+				.iter()
+				.any(|x| inputs[1].nullifiers.contains(x));
+		if !is_matching {
+			return Ok(false);
+		}
+
+		for (half, half_inputs) in halves.iter().zip(inputs.iter()) {
+			if !verify_unchecked_typed::<E, _>(half_inputs, vk, &half.proof)? {
+				return Ok(false);
+			}
+		}
+		Ok(true)
+	}
+}
@@ -0,0 +1,404 @@
+use super::{setup_params, PoseidonSwapCircuit, SwapOrder, SwapR1CSProver, SwapRate};
+use crate::{
+	common::*,
+	public_inputs::{PublicInputs, SwapPublicInputs},
+	r1cs::masp::MaspVAnchorR1CSProver,
+	utxo::MaspUtxo,
+};
+use ark_bn254::{Bn254, Fr as BnFr};
+use ark_crypto_primitives::Error;
+use ark_ff::{BigInteger, Field, PrimeField, UniformRand};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystem};
+use ark_std::{collections::BTreeMap, rand::rngs::StdRng, test_rng, vec, vec::Vec};
+use arkworks_native_gadgets::{
+	merkle_tree::Path,
+	poseidon::{FieldHasher, Poseidon},
+};
+use arkworks_utils::Curve;
+
+const HEIGHT: usize = 30;
+const ANCHOR_CT: usize = 2;
+const INS: usize = 2;
+const OUTS: usize = 2;
+const AMOUNT_BITS: usize = 64;
+const DEFAULT_LEAF: [u8; 32] = [0u8; 32];
+
+const ASSET_A: u64 = 1;
+const ASSET_B: u64 = 2;
+
+#[allow(non_camel_case_types)]
+type SwapR1CSProver_Bn254_Poseidon_30_2_2 = SwapR1CSProver<Bn254, HEIGHT, ANCHOR_CT, INS, OUTS>;
+#[allow(non_camel_case_types)]
+type MaspVAnchorR1CSProver_Bn254_Poseidon_30_2_2 =
+	MaspVAnchorR1CSProver<Bn254, HEIGHT, ANCHOR_CT, INS, OUTS>;
+
+struct Swap {
+	private_keys: [BnFr; 2],
+	in_utxos: [[MaspUtxo<BnFr>; INS]; 2],
+	out_utxos: [[MaspUtxo<BnFr>; OUTS]; 2],
+	in_leaves: BTreeMap<u64, Vec<Vec<u8>>>,
+	root_set: Vec<Vec<u8>>,
+}
+
+// The first party swaps 100 of asset A, out of inputs of 60 and 60, for 250
+// of asset B of the second party, out of inputs of 200 and 100. Both parties
+// get their change in the second output. The first output of the second party
+// pays `payment` instead of 250. Each party only uses its own side of the
+// Utxos.
+fn setup_swap(payment: u128, rng: &mut StdRng) -> Swap {
+	let curve = Curve::Bn254;
+	let tree_hasher = Poseidon::<BnFr>::new(setup_params(curve, 5, 3));
+	let keypair_hasher = Poseidon::<BnFr>::new(setup_params(curve, 5, 2));
+	let private_keys = [BnFr::rand(rng), BnFr::rand(rng)];
+	let public_keys = private_keys.map(|x| keypair_hasher.hash(&[x]).unwrap());
+
+	let create_utxo =
+		|asset_id: u64, amount: u128, index: u64, private_key: BnFr, rng: &mut StdRng| {
+			MaspVAnchorR1CSProver_Bn254_Poseidon_30_2_2::create_utxo(
+				curve,
+				0,
+				asset_id,
+				amount,
+				Some(index),
+				private_key.into_repr().to_bytes_be(),
+				BnFr::rand(rng).into_repr().to_bytes_be(),
+			)
+			.unwrap()
+		};
+	let in_utxos = [
+		[
+			create_utxo(ASSET_A, 60, 0, private_keys[0], rng),
+			create_utxo(ASSET_A, 60, 1, private_keys[0], rng),
+		],
+		[
+			create_utxo(ASSET_B, 200, 2, private_keys[1], rng),
+			create_utxo(ASSET_B, 100, 3, private_keys[1], rng),
+		],
+	];
+	let create_out = |asset_id: u64, amount: u128, public_key: BnFr, rng: &mut StdRng| {
+		MaspVAnchorR1CSProver_Bn254_Poseidon_30_2_2::create_public_utxo(
+			curve,
+			0,
+			asset_id,
+			amount,
+			BnFr::rand(rng).into_repr().to_bytes_be(),
+			public_key.into_repr().to_bytes_be(),
+			None,
+		)
+		.unwrap()
+	};
+	let out_utxos = [
+		[
+			create_out(ASSET_A, 100, public_keys[1], rng),
+			create_out(ASSET_A, 20, public_keys[0], rng),
+		],
+		[
+			create_out(ASSET_B, payment, public_keys[0], rng),
+			create_out(ASSET_B, 300 - payment, public_keys[1], rng),
+		],
+	];
+
+	let leaves = in_utxos
+		.iter()
+		.flatten()
+		.map(|x| x.commitment)
+		.collect::<Vec<_>>();
+	let tree =
+		create_merkle_tree::<BnFr, Poseidon<BnFr>, HEIGHT>(&tree_hasher, &leaves, &DEFAULT_LEAF);
+	let mut in_leaves = BTreeMap::new();
+	in_leaves.insert(
+		0,
+		leaves.iter().map(|x| x.into_repr().to_bytes_be()).collect(),
+	);
+	let root_set = vec![tree.root().into_repr().to_bytes_be()];
+
+	Swap {
+		private_keys,
+		in_utxos,
+		out_utxos,
+		in_leaves,
+		root_set,
+	}
+}
+
+// The orders of the swap, signed by the parties
+fn create_orders(private_keys: [BnFr; 2]) -> [SwapOrder<BnFr>; 2] {
+	[
+		SwapR1CSProver_Bn254_Poseidon_30_2_2::create_order(
+			Curve::Bn254,
+			ASSET_A,
+			100,
+			ASSET_B,
+			250,
+			private_keys[0].into_repr().to_bytes_be(),
+		)
+		.unwrap(),
+		SwapR1CSProver_Bn254_Poseidon_30_2_2::create_order(
+			Curve::Bn254,
+			ASSET_B,
+			250,
+			ASSET_A,
+			100,
+			private_keys[1].into_repr().to_bytes_be(),
+		)
+		.unwrap(),
+	]
+}
+
+fn setup_keys_for_tests(rng: &mut StdRng) -> (Vec<u8>, Vec<u8>) {
+	let circuit = SwapR1CSProver_Bn254_Poseidon_30_2_2::setup_random_circuit(
+		Curve::Bn254,
+		DEFAULT_LEAF,
+		AMOUNT_BITS,
+		rng,
+	)
+	.unwrap();
+	setup_keys_unchecked::<Bn254, _, _>(circuit, rng).unwrap()
+}
+
+// Proves the half of party `s` at the rate `rate_num / rate_den`
+fn prove_half(
+	swap: &Swap,
+	orders: &[SwapOrder<BnFr>; 2],
+	s: usize,
+	rate_num: u128,
+	rate_den: u128,
+	pk: &[u8],
+	rng: &mut StdRng,
+) -> Result<SwapProof, Error> {
+	SwapR1CSProver_Bn254_Poseidon_30_2_2::create_proof(
+		Curve::Bn254,
+		0,
+		vec![1u8; 32],
+		s == 1,
+		orders[s].clone(),
+		orders[1 - s].clone(),
+		rate_num,
+		rate_den,
+		vec![2u8; 32],
+		swap.root_set.clone(),
+		swap.in_leaves.clone(),
+		swap.in_utxos[s].clone(),
+		swap.out_utxos[s].clone(),
+		AMOUNT_BITS,
+		pk.to_vec(),
+		DEFAULT_LEAF,
+		rng,
+	)
+}
+
+// The paths of the inputs of party `s` in the tree of the swap, with its root
+fn paths_of(swap: &Swap, s: usize) -> (Vec<Path<BnFr, Poseidon<BnFr>, HEIGHT>>, BnFr) {
+	let tree_hasher = Poseidon::<BnFr>::new(setup_params(Curve::Bn254, 5, 3));
+	let leaves = swap.in_leaves[&0]
+		.iter()
+		.map(|x| BnFr::from_be_bytes_mod_order(x))
+		.collect::<Vec<_>>();
+	let tree =
+		create_merkle_tree::<BnFr, Poseidon<BnFr>, HEIGHT>(&tree_hasher, &leaves, &DEFAULT_LEAF);
+	let paths = (0..INS)
+		.map(|i| tree.generate_membership_proof((s * INS + i) as u64))
+		.collect();
+	(paths, tree.root())
+}
+
+// Sets up the circuit of the half of the first party at `rate`
+fn setup_first_half(
+	swap: &Swap,
+	orders: &[SwapOrder<BnFr>; 2],
+	rate: SwapRate<BnFr>,
+	amount_bits: usize,
+) -> Result<PoseidonSwapCircuit<BnFr, HEIGHT, INS, OUTS, ANCHOR_CT>, Error> {
+	let curve = Curve::Bn254;
+	let (paths, root) = paths_of(swap, 0);
+	SwapR1CSProver_Bn254_Poseidon_30_2_2::setup_circuit(
+		BnFr::from(0u64),
+		BnFr::from(1u64),
+		rate,
+		false,
+		&orders[0],
+		&orders[1],
+		swap.in_utxos[0].clone(),
+		paths,
+		[root, BnFr::from(0u64)],
+		swap.out_utxos[0].clone(),
+		Poseidon::new(setup_params(curve, 5, 2)),
+		Poseidon::new(setup_params(curve, 5, 3)),
+		Poseidon::new(setup_params(curve, 5, 4)),
+		Poseidon::new(setup_params(curve, 5, 6)),
+		amount_bits,
+	)
+}
+
+#[test]
+fn should_satisfy_random_circuit() {
+	let rng = &mut test_rng();
+	let circuit = SwapR1CSProver_Bn254_Poseidon_30_2_2::setup_random_circuit(
+		Curve::Bn254,
+		DEFAULT_LEAF,
+		AMOUNT_BITS,
+		rng,
+	)
+	.unwrap();
+	let cs = ConstraintSystem::<BnFr>::new_ref();
+	circuit.generate_constraints(cs.clone()).unwrap();
+	assert!(cs.is_satisfied().unwrap());
+}
+
+#[test]
+fn should_settle_swap() {
+	let rng = &mut test_rng();
+	let (pk, vk) = setup_keys_for_tests(rng);
+	let swap = setup_swap(250, rng);
+	let orders = create_orders(swap.private_keys);
+
+	// One unit of asset A is worth 5 / 2 of asset B. Each party proves its own
+	// half without the keys of the other one.
+	let halves = [
+		prove_half(&swap, &orders, 0, 5, 2, &pk, rng).unwrap(),
+		prove_half(&swap, &orders, 1, 5, 2, &pk, rng).unwrap(),
+	];
+	let res = SwapR1CSProver_Bn254_Poseidon_30_2_2::verify_swap([&halves[0], &halves[1]], &vk);
+	assert!(res.unwrap());
+
+	// Each half settles the order of its party against the other one
+	let leaf_hasher = Poseidon::<BnFr>::new(setup_params(Curve::Bn254, 5, 6));
+	for (s, half) in halves.iter().enumerate() {
+		let public_inputs = SwapPublicInputs::<BnFr, ANCHOR_CT, INS, OUTS>::from_bytes_slice(
+			&half.public_inputs_raw,
+		)
+		.unwrap();
+		assert_eq!(
+			public_inputs.order_hash,
+			orders[s].hash(&leaf_hasher).unwrap()
+		);
+		assert_eq!(
+			public_inputs.counter_order_hash,
+			orders[1 - s].hash(&leaf_hasher).unwrap()
+		);
+		assert_eq!(
+			half.order_hashes_raw[0],
+			public_inputs.order_hash.into_repr().to_bytes_be()
+		);
+	}
+
+	// A half alone, twice, or with another order doesn't settle the swap
+	let res = SwapR1CSProver_Bn254_Poseidon_30_2_2::verify_swap([&halves[0], &halves[0]], &vk);
+	assert!(!res.unwrap());
+	let mut public_inputs = SwapPublicInputs::<BnFr, ANCHOR_CT, INS, OUTS>::from_bytes_slice(
+		&halves[1].public_inputs_raw,
+	)
+	.unwrap();
+	public_inputs.order_hash = BnFr::rand(rng);
+	let other_order = SwapProof {
+		proof: halves[1].proof.clone(),
+		order_hashes_raw: halves[1].order_hashes_raw.clone(),
+		public_inputs_raw: public_inputs.to_bytes_vec(),
+	};
+	let res = SwapR1CSProver_Bn254_Poseidon_30_2_2::verify_swap([&halves[0], &other_order], &vk);
+	assert!(!res.unwrap());
+}
+
+#[test]
+fn should_fail_with_unsigned_order() {
+	let rng = &mut test_rng();
+	// The second party pays less than its order
+	let swap = setup_swap(240, rng);
+	let orders = create_orders(swap.private_keys);
+
+	let res = prove_half(&swap, &orders, 1, 5, 2, &[], rng);
+	assert!(res.is_err());
+}
+
+#[test]
+fn should_fail_with_wrong_rate() {
+	let rng = &mut test_rng();
+	let swap = setup_swap(250, rng);
+	let orders = create_orders(swap.private_keys);
+
+	let res = prove_half(&swap, &orders, 0, 3, 1, &[], rng);
+	assert!(res.is_err());
+}
+
+#[test]
+fn should_fail_with_unbalanced_side() {
+	let rng = &mut test_rng();
+	let curve = Curve::Bn254;
+	let mut swap = setup_swap(250, rng);
+	let orders = create_orders(swap.private_keys);
+
+	// The first party keeps more change than its inputs allow
+	swap.out_utxos[0][1] = MaspVAnchorR1CSProver_Bn254_Poseidon_30_2_2::create_public_utxo(
+		curve,
+		0,
+		ASSET_A,
+		50,
+		BnFr::rand(rng).into_repr().to_bytes_be(),
+		swap.out_utxos[0][1]
+			.keypair
+			.public_key
+			.into_repr()
+			.to_bytes_be(),
+		None,
+	)
+	.unwrap();
+
+	let rate = SwapRate {
+		num: BnFr::from(5u64),
+		den: BnFr::from(2u64),
+		blinding: BnFr::rand(rng),
+	};
+	let circuit = setup_first_half(&swap, &orders, rate, AMOUNT_BITS).unwrap();
+	let cs = ConstraintSystem::<BnFr>::new_ref();
+	circuit.generate_constraints(cs.clone()).unwrap();
+	assert!(!cs.is_satisfied().unwrap());
+}
+
+#[test]
+fn should_not_satisfy_circuit_with_wrapped_rate() {
+	let rng = &mut test_rng();
+	let swap = setup_swap(250, rng);
+	let orders = create_orders(swap.private_keys);
+
+	// The rate 5 / 2 written as a single field element, `100 * rate_num` wraps
+	// around the modulus to 250 and satisfies the rate equation
+	let num = BnFr::from(5u64) * BnFr::from(2u64).inverse().unwrap();
+	assert_eq!(BnFr::from(100u64) * num, BnFr::from(250u64));
+	let rate = SwapRate {
+		num,
+		den: BnFr::from(1u64),
+		blinding: BnFr::rand(rng),
+	};
+	let circuit = setup_first_half(&swap, &orders, rate, AMOUNT_BITS).unwrap();
+	let cs = ConstraintSystem::<BnFr>::new_ref();
+	circuit.generate_constraints(cs.clone()).unwrap();
+	assert!(!cs.is_satisfied().unwrap());
+
+	// The prover refuses rates that don't fit in the amount bits
+	let res = prove_half(
+		&swap,
+		&orders,
+		0,
+		5 << AMOUNT_BITS,
+		2 << AMOUNT_BITS,
+		&[],
+		rng,
+	);
+	assert!(res.is_err());
+}
+
+#[test]
+fn should_fail_with_invalid_amount_bits() {
+	let rng = &mut test_rng();
+	let swap = setup_swap(250, rng);
+	let orders = create_orders(swap.private_keys);
+	let rate = SwapRate {
+		num: BnFr::from(5u64),
+		den: BnFr::from(2u64),
+		blinding: BnFr::rand(rng),
+	};
+
+	// The products of amounts of 127 bits overflow the 253 bits of capacity
+	assert!(setup_first_half(&swap, &orders, rate, 127).is_err());
+	assert!(setup_first_half(&swap, &orders, rate, 126).is_ok());
+}
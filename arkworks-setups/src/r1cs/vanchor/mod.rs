@@ -1,12 +1,17 @@
-use crate::{common::*, r1cs::vanchor::utxo::Utxo, utxo, VAnchorProver};
+use crate::{
+	common::*,
+	public_inputs::{PublicInputs, VAnchorPublicInputs},
+	r1cs::vanchor::utxo::Utxo,
+	utxo, VAnchorProver,
+};
 use ark_crypto_primitives::Error;
 use ark_ec::PairingEngine;
 use ark_ff::{BigInteger, PrimeField, SquareRootField, Zero};
 use ark_std::{
 	boxed::Box,
-	collections::BTreeMap,
+	collections::{btree_map::Entry, BTreeMap},
 	marker::PhantomData,
-	rand::{CryptoRng, Rng, RngCore},
+	rand::{rngs::StdRng, CryptoRng, Rng, RngCore, SeedableRng},
 	vec,
 	vec::Vec,
 	UniformRand,
@@ -23,12 +28,16 @@ mod tests;
 #[derive(Debug)]
 pub enum VAnchorError {
 	InvalidInputChainId,
+	TooManyInputs,
+	MissingLeaves,
 }
 
 impl core::fmt::Display for VAnchorError {
 	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
 		let msg = match self {
 			Self::InvalidInputChainId => ark_std::format!("Invalid input chain ID"),
+			Self::TooManyInputs => ark_std::format!("Too many input UTXOs for the circuit"),
+			Self::MissingLeaves => ark_std::format!("Missing leaves for the input chain ID"),
 		};
 		write!(f, "{}", msg)
 	}
@@ -36,6 +45,91 @@ impl core::fmt::Display for VAnchorError {
 
 impl ark_std::error::Error for VAnchorError {}
 
+/// Number of inputs of the VAnchor circuit used for small transactions
+pub const SMALL_INS: usize = 2;
+/// Number of inputs of the VAnchor circuit used to consolidate many notes
+pub const LARGE_INS: usize = 16;
+/// Number of outputs of both the small and the large VAnchor circuits
+pub const TX_OUTS: usize = 2;
+
+/// Proving keys of the 2x2 and 16x2 VAnchor circuits
+pub struct VAnchorProvingKeys {
+	pub pk_2_2: Vec<u8>,
+	pub pk_16_2: Vec<u8>,
+}
+
+/// Returns the number of inputs of the circuit that spends `num_notes` notes.
+/// The verifier can tell the circuit of a proof the same way, since its public
+/// inputs hold one nullifier per input.
+pub fn select_ins(num_notes: usize) -> Result<usize, Error> {
+	if num_notes <= SMALL_INS {
+		Ok(SMALL_INS)
+	} else if num_notes <= LARGE_INS {
+		Ok(LARGE_INS)
+	} else {
+		Err(VAnchorError::TooManyInputs.into())
+	}
+}
+
+/// Creates a proof spending `in_utxos` with the 2x2 circuit when there are at
+/// most two of them and with the 16x2 circuit otherwise. The inputs are padded
+/// with zero-amount dummy UTXOs up to the number of inputs of the circuit.
+#[allow(clippy::too_many_arguments)]
+pub fn create_padded_proof<
+	E: PairingEngine,
+	R: RngCore + CryptoRng,
+	const HEIGHT: usize,
+	const ANCHOR_CT: usize,
+>(
+	curve: Curve,
+	chain_id: u64,
+	public_amount: i128,
+	ext_data_hash: Vec<u8>,
+	public_root_set: Vec<Vec<u8>>,
+	in_leaves: BTreeMap<u64, Vec<Vec<u8>>>,
+	in_utxos: Vec<Utxo<E::Fr>>,
+	out_utxos: [Utxo<E::Fr>; TX_OUTS],
+	amount_bits: usize,
+	keys: &VAnchorProvingKeys,
+	default_leaf: [u8; 32],
+	rng: &mut R,
+) -> Result<VAnchorProof, Error>
+where
+	<E as PairingEngine>::Fr: PrimeField + SquareRootField + From<i128>,
+{
+	if select_ins(in_utxos.len())? == SMALL_INS {
+		VAnchorR1CSProver::<E, HEIGHT, ANCHOR_CT, SMALL_INS, TX_OUTS>::create_padded_proof(
+			curve,
+			chain_id,
+			public_amount,
+			ext_data_hash,
+			public_root_set,
+			in_leaves,
+			in_utxos,
+			out_utxos,
+			amount_bits,
+			keys.pk_2_2.clone(),
+			default_leaf,
+			rng,
+		)
+	} else {
+		VAnchorR1CSProver::<E, HEIGHT, ANCHOR_CT, LARGE_INS, TX_OUTS>::create_padded_proof(
+			curve,
+			chain_id,
+			public_amount,
+			ext_data_hash,
+			public_root_set,
+			in_leaves,
+			in_utxos,
+			out_utxos,
+			amount_bits,
+			keys.pk_16_2.clone(),
+			default_leaf,
+			rng,
+		)
+	}
+}
+
 pub struct VAnchorR1CSProver<
 	E: PairingEngine,
 	const HEIGHT: usize,
@@ -88,6 +182,7 @@ where
 	pub fn setup_random_circuit<R: RngCore>(
 		curve: Curve,
 		default_leaf: [u8; 32],
+		amount_bits: usize,
 		rng: &mut R,
 	) -> Result<VAnchorCircuit<E::Fr, PoseidonGadget<E::Fr>, HEIGHT, INS, OUTS, ANCHOR_CT>, Error> {
 		// Initialize hashers
@@ -159,11 +254,13 @@ where
 			tree_hasher,
 			nullifier_hasher,
 			leaf_hasher,
+			amount_bits,
 		)?;
 
 		Ok(circuit)
 	}
 
+	#[allow(clippy::too_many_arguments)]
 	pub fn setup_circuit(
 		chain_id: E::Fr,
 		public_amount: E::Fr,
@@ -180,6 +277,7 @@ where
 		tree_hasher: Poseidon<E::Fr>,
 		nullifier_hasher: Poseidon<E::Fr>,
 		leaf_hasher: Poseidon<E::Fr>,
+		amount_bits: usize,
 	) -> Result<VAnchorCircuit<E::Fr, PoseidonGadget<E::Fr>, HEIGHT, INS, OUTS, ANCHOR_CT>, Error> {
 		let in_amounts = in_utxos
 			.iter()
@@ -240,6 +338,7 @@ where
 				keypair_hasher,
 				leaf_hasher,
 				nullifier_hasher,
+				amount_bits,
 			);
 
 		Ok(circuit)
@@ -248,18 +347,164 @@ where
 	pub fn construct_public_inputs(
 		chain_id: E::Fr,
 		public_amount: E::Fr,
-		roots: Vec<E::Fr>,
-		nullifiers: Vec<E::Fr>,
-		commitments: Vec<E::Fr>,
+		roots: [E::Fr; ANCHOR_CT],
+		nullifiers: [E::Fr; INS],
+		commitments: [E::Fr; OUTS],
 		ext_data_hash: E::Fr,
 	) -> Vec<E::Fr> {
-		let mut public_inputs = vec![public_amount, ext_data_hash];
-		public_inputs.extend(nullifiers);
-		public_inputs.extend(commitments);
-		public_inputs.push(chain_id);
-		public_inputs.extend(roots);
+		VAnchorPublicInputs::<E::Fr, ANCHOR_CT, INS, OUTS> {
+			public_amount,
+			ext_data_hash,
+			nullifiers,
+			commitments,
+			chain_id,
+			roots,
+		}
+		.to_field_vec()
+	}
+
+	/// Generates the keys of the circuit from `seed`, so that every party
+	/// derives the same keys. Anyone knowing the seed can forge proofs, so
+	/// these keys are only fit for tests and development networks.
+	pub fn setup_keys_from_seed(
+		curve: Curve,
+		default_leaf: [u8; 32],
+		amount_bits: usize,
+		seed: [u8; 32],
+	) -> Result<Keys, Error> {
+		let rng = &mut StdRng::from_seed(seed);
+		let circuit = Self::setup_random_circuit(curve, default_leaf, amount_bits, rng)?;
+		let (pk, vk) = setup_keys_unchecked::<E, _, _>(circuit, rng)?;
+		Ok(Keys { pk, vk })
+	}
+
+	/// Pads `in_utxos` up to `INS` inputs with zero-amount dummy UTXOs.
+	pub fn pad_in_utxos<R: RngCore + CryptoRng>(
+		curve: Curve,
+		chain_id: u64,
+		mut in_utxos: Vec<Utxo<E::Fr>>,
+		rng: &mut R,
+	) -> Result<[Utxo<E::Fr>; INS], Error> {
+		if in_utxos.len() > INS {
+			return Err(VAnchorError::TooManyInputs.into());
+		}
+		// Each dummy has its own keypair and blinding, so their nullifiers differ.
+		// `create_in_paths` puts the zero-amount inputs in one tree, in input
+		// order, so every dummy gets the index of its path in that tree. The
+		// circuit doesn't bind the index to the path yet, but padded proofs keep
+		// verifying once it does.
+		while in_utxos.len() < INS {
+			let index = in_utxos.iter().filter(|x| x.amount.is_zero()).count() as u64;
+			let dummy = Self::create_random_utxo(curve, chain_id, 0, Some(index), rng)?;
+			in_utxos.push(dummy);
+		}
+		in_utxos
+			.try_into()
+			.map_err(|_| VAnchorError::TooManyInputs.into())
+	}
+
+	/// Same as `create_proof`, but takes any number of input UTXOs up to `INS`
+	/// and pads them with zero-amount dummies. The indices of the inputs are
+	/// taken from the UTXOs themselves.
+	#[allow(clippy::too_many_arguments)]
+	pub fn create_padded_proof<R: RngCore + CryptoRng>(
+		curve: Curve,
+		chain_id: u64,
+		public_amount: i128,
+		ext_data_hash: Vec<u8>,
+		public_root_set: Vec<Vec<u8>>,
+		in_leaves: BTreeMap<u64, Vec<Vec<u8>>>,
+		in_utxos: Vec<Utxo<E::Fr>>,
+		out_utxos: [Utxo<E::Fr>; OUTS],
+		amount_bits: usize,
+		pk: Vec<u8>,
+		default_leaf: [u8; 32],
+		rng: &mut R,
+	) -> Result<VAnchorProof, Error> {
+		let in_utxos = Self::pad_in_utxos(curve, chain_id, in_utxos, rng)?;
+		let mut in_indices = [0u64; INS];
+		for (i, utxo) in in_utxos.iter().enumerate() {
+			in_indices[i] = utxo.get_index()?;
+		}
 
-		public_inputs
+		Self::create_proof(
+			curve,
+			chain_id,
+			public_amount,
+			ext_data_hash,
+			public_root_set,
+			in_indices,
+			in_leaves,
+			in_utxos,
+			out_utxos,
+			amount_bits,
+			pk,
+			default_leaf,
+			rng,
+		)
+	}
+
+	// Zero-amount inputs are not checked against the root set, but the path
+	// gadget still needs a valid path to them. All of them share one tree
+	// rather than building a tree per dummy, while the trees of the other
+	// inputs are built once per chain.
+	#[allow(clippy::type_complexity)]
+	fn create_in_paths(
+		tree_hasher: &Poseidon<E::Fr>,
+		in_utxos: &[Utxo<E::Fr>],
+		in_leaves: &BTreeMap<u64, Vec<Vec<u8>>>,
+		default_leaf: &[u8; 32],
+	) -> Result<Vec<Path<E::Fr, Poseidon<E::Fr>, HEIGHT>>, Error> {
+		let dummy_leaves: Vec<E::Fr> = in_utxos
+			.iter()
+			.filter(|utxo| utxo.amount.is_zero())
+			.map(|utxo| utxo.commitment)
+			.collect();
+		let dummy_tree = if dummy_leaves.is_empty() {
+			None
+		} else {
+			Some(SMT::<E::Fr, Poseidon<E::Fr>, HEIGHT>::new_sequential(
+				&dummy_leaves,
+				tree_hasher,
+				default_leaf,
+			)?)
+		};
+
+		let mut trees = BTreeMap::<u64, SMT<E::Fr, Poseidon<E::Fr>, HEIGHT>>::new();
+		let mut dummy_index = 0u64;
+		let mut in_paths = Vec::with_capacity(in_utxos.len());
+		for utxo in in_utxos {
+			let path = match &dummy_tree {
+				Some(tree) if utxo.amount.is_zero() => {
+					dummy_index += 1;
+					tree.generate_membership_proof(dummy_index - 1)
+				}
+				_ => {
+					let chain_id_of_utxo: u64 = utxo.chain_id_raw;
+					let tree = match trees.entry(chain_id_of_utxo) {
+						Entry::Occupied(entry) => entry.into_mut(),
+						Entry::Vacant(entry) => {
+							let leaves = in_leaves
+								.get(&chain_id_of_utxo)
+								.ok_or(VAnchorError::MissingLeaves)?;
+							let leaves_f = leaves
+								.iter()
+								.map(|l| E::Fr::from_be_bytes_mod_order(l))
+								.collect::<Vec<E::Fr>>();
+							entry.insert(SMT::<E::Fr, Poseidon<E::Fr>, HEIGHT>::new_sequential(
+								&leaves_f,
+								tree_hasher,
+								default_leaf,
+							)?)
+						}
+					};
+					tree.generate_membership_proof(utxo.index.unwrap_or_default())
+				}
+			};
+			in_paths.push(path);
+		}
+
+		Ok(in_paths)
 	}
 }
 
@@ -309,13 +554,14 @@ where
 		// External data
 		public_amount: i128,
 		ext_data_hash: Vec<u8>,
-		public_root_set: [Vec<u8>; ANCHOR_CT],
+		public_root_set: Vec<Vec<u8>>,
 		in_indices: [u64; INS],
 		in_leaves: BTreeMap<u64, Vec<Vec<u8>>>,
 		// Input transactions
 		in_utxos: [Utxo<E::Fr>; INS],
 		// Output transactions
 		out_utxos: [Utxo<E::Fr>; OUTS],
+		amount_bits: usize,
 		pk: Vec<u8>,
 		default_leaf: [u8; 32],
 		rng: &mut R,
@@ -334,9 +580,7 @@ where
 		let chain_id_elt = E::Fr::from(chain_id);
 		let public_amount_elt = E::Fr::from(public_amount);
 		let ext_data_hash_elt = E::Fr::from_be_bytes_mod_order(&ext_data_hash);
-		// Generate the paths for each UTXO
-		let mut trees = BTreeMap::<u64, SMT<E::Fr, Poseidon<E::Fr>, HEIGHT>>::new();
-
+		let public_root_set_elt = pad_root_set::<E::Fr, ANCHOR_CT>(&public_root_set)?;
 		// Throw an error if chain IDs don't match intended spending chain.
 		for utxo in in_utxos.clone() {
 			if utxo.chain_id_raw != chain_id {
@@ -349,54 +593,7 @@ where
 			.clone()
 			.map(|utxo| utxo.calculate_nullifier(&nullifier_hasher).unwrap());
 
-		let in_paths = in_utxos
-			.iter()
-			.map(|utxo| {
-				let chain_id_of_utxo: u64 = utxo.chain_id_raw;
-				// Handle the default utxo when the amount is 0.
-				if utxo.amount == E::Fr::zero() {
-					// If the amount is 0, we just need to create a dummy path for a tree
-					// that contains this dummy UTXO so that we can satisfy the merkle path
-					// constraints on the path gadget.
-					//
-					// The path gadget contains constraints that verify the path was correctly
-					// generated for a tree containing the UTXO in question. Therefore even for
-					// dummy UTXOs, we still need to *simulate* this by creating a valid path in an
-					// arbitrary tree. Since the amount is 0, this arbitrary tree has no effect on
-					// the set membership check.
-					match setup_tree_and_create_path::<E::Fr, Poseidon<E::Fr>, HEIGHT>(
-						&tree_hasher,
-						&[utxo.commitment],
-						utxo.index.unwrap_or_default(),
-						&default_leaf,
-					) {
-						Ok((_, path)) => path,
-						Err(err) => panic!("{}", err),
-					}
-				} else if trees.contains_key(&chain_id_of_utxo) {
-					let tree = trees.get(&chain_id_of_utxo).unwrap();
-					tree.generate_membership_proof(utxo.index.unwrap_or_default())
-				} else {
-					let leaves = in_leaves.get(&chain_id_of_utxo).unwrap();
-					let leaves_f = leaves
-						.iter()
-						.map(|l| E::Fr::from_be_bytes_mod_order(&l))
-						.collect::<Vec<E::Fr>>();
-					match setup_tree_and_create_path::<E::Fr, Poseidon<E::Fr>, HEIGHT>(
-						&tree_hasher,
-						&leaves_f,
-						utxo.index.unwrap_or_default(),
-						&default_leaf,
-					) {
-						Ok((tree, path)) => {
-							trees.insert(chain_id_of_utxo, tree);
-							path
-						}
-						Err(err) => panic!("{}", err),
-					}
-				}
-			})
-			.collect();
+		let in_paths = Self::create_in_paths(&tree_hasher, &in_utxos, &in_leaves, &default_leaf)?;
 
 		// Get the circuit
 		let circuit = Self::setup_circuit(
@@ -406,14 +603,13 @@ where
 			in_utxos.clone(),
 			in_indices.map(|elt| E::Fr::from(elt)),
 			in_paths,
-			public_root_set
-				.clone()
-				.map(|elt| E::Fr::from_be_bytes_mod_order(&elt)),
+			public_root_set_elt,
 			out_utxos.clone(),
 			keypair_hasher,
 			tree_hasher,
 			nullifier_hasher,
 			leaf_hasher,
+			amount_bits,
 		)?;
 
 		#[cfg(feature = "trace")]
@@ -439,21 +635,15 @@ where
 
 		let proof = prove_unchecked::<E, _, _>(circuit, &pk, rng)?;
 
-		let public_inputs = Self::construct_public_inputs(
-			chain_id_elt,
-			public_amount_elt,
-			public_root_set
-				.map(|elt| E::Fr::from_be_bytes_mod_order(&elt))
-				.to_vec(),
-			input_nullifiers.to_vec(),
-			out_utxos.map(|utxo| utxo.commitment).to_vec(),
-			ext_data_hash_elt,
-		);
-
-		let public_inputs_raw = public_inputs
-			.iter()
-			.map(|inp| inp.into_repr().to_bytes_be())
-			.collect();
+		let public_inputs = VAnchorPublicInputs::<E::Fr, ANCHOR_CT, INS, OUTS> {
+			public_amount: public_amount_elt,
+			ext_data_hash: ext_data_hash_elt,
+			nullifiers: input_nullifiers,
+			commitments: out_utxos.map(|utxo| utxo.commitment),
+			chain_id: chain_id_elt,
+			roots: public_root_set_elt,
+		};
+		let public_inputs_raw = public_inputs.to_bytes_vec();
 
 		Ok(VAnchorProof {
 			public_inputs_raw,
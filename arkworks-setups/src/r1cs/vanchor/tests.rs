@@ -1,25 +1,41 @@
 use ark_std::vec;
 
-use crate::{common::*, VAnchorProver};
+use crate::{
+	common::*,
+	public_inputs::{PublicInputs, VAnchorPublicInputs},
+	utxo::Utxo,
+	VAnchorProver,
+};
 use ark_serialize::CanonicalDeserialize;
 use ark_std::{collections::BTreeMap, One, Zero};
-use arkworks_native_gadgets::poseidon::Poseidon;
+use arkworks_native_gadgets::{
+	eddsa::SecretKey,
+	merkle_tree::build_root_tree,
+	poseidon::{encryption::ciphertext_len, Poseidon},
+};
+use arkworks_r1cs_circuits::{
+	vanchor_encrypted::VAnchorEncryptedCircuit, vanchor_root_tree::VAnchorRootTreeCircuit,
+};
+use arkworks_r1cs_gadgets::poseidon::PoseidonGadget;
 use arkworks_utils::Curve;
 
 use ark_bn254::{Bn254, Fr as BnFr};
 use ark_ff::{BigInteger, PrimeField, UniformRand};
 use ark_groth16::{Groth16, Proof, VerifyingKey};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystem, SynthesisMode};
 
 use ark_snark::SNARK;
 use ark_std::{str::FromStr, test_rng};
 
-use super::{setup_params, VAnchorR1CSProver};
+use super::{create_padded_proof, select_ins, setup_params, VAnchorProvingKeys, VAnchorR1CSProver};
 
 const HEIGHT: usize = 30;
 const ANCHOR_CT: usize = 2;
 const INS_2: usize = 2;
 const INS_16: usize = 16;
 const OUTS: usize = 2;
+const AMOUNT_BITS: usize = 248;
+const ROOT_HEIGHT: usize = 4;
 
 #[allow(non_camel_case_types)]
 type VAnchorR1CSProver_Bn254_Poseidon_30_2_2 =
@@ -37,9 +53,13 @@ fn should_create_proof_for_random_circuit() {
 	let tree_hasher = Poseidon::<BnFr> { params: params3 };
 
 	// Set up a random circuit and make pk/vk pair
-	let random_circuit =
-		VAnchorR1CSProver_Bn254_Poseidon_30_2_2::setup_random_circuit(curve, DEFAULT_LEAF, rng)
-			.unwrap();
+	let random_circuit = VAnchorR1CSProver_Bn254_Poseidon_30_2_2::setup_random_circuit(
+		curve,
+		DEFAULT_LEAF,
+		AMOUNT_BITS,
+		rng,
+	)
+	.unwrap();
 	let (proving_key, verifying_key) =
 		setup_keys_unchecked::<Bn254, _, _>(random_circuit, rng).unwrap();
 
@@ -107,7 +127,7 @@ fn should_create_proof_for_random_circuit() {
 		leaf1.into_repr().to_bytes_be(),
 	]);
 	let in_indices = [0, 1];
-	let in_root_set = [
+	let in_root_set = vec![
 		smt.root().into_repr().to_bytes_be(),
 		smt.root().into_repr().to_bytes_be(),
 	];
@@ -122,18 +142,130 @@ fn should_create_proof_for_random_circuit() {
 		in_leaves,
 		in_utxos,
 		out_utxos,
+		AMOUNT_BITS,
 		proving_key,
 		DEFAULT_LEAF,
 		rng,
 	)
 	.unwrap();
 
-	let pub_ins = proof
-		.public_inputs_raw
-		.iter()
-		.map(|inp| BnFr::from_be_bytes_mod_order(inp.as_slice()))
-		.collect::<Vec<_>>();
-	let res = verify_unchecked::<Bn254>(&pub_ins, &verifying_key, &proof.proof).unwrap();
+	let pub_ins = VAnchorPublicInputs::<BnFr, ANCHOR_CT, INS_2, OUTS>::from_bytes_slice(
+		&proof.public_inputs_raw,
+	)
+	.unwrap();
+	let res = verify_unchecked_typed::<Bn254, _>(&pub_ins, &verifying_key, &proof.proof).unwrap();
+
+	assert!(res);
+}
+
+#[test]
+fn should_create_proof_with_fewer_roots_than_slots() {
+	let rng = &mut test_rng();
+	let curve = Curve::Bn254;
+	let params3 = setup_params::<BnFr>(curve, 5, 3);
+	let tree_hasher = Poseidon::<BnFr> { params: params3 };
+
+	// Set up a random circuit and make pk/vk pair
+	let random_circuit = VAnchorR1CSProver_Bn254_Poseidon_30_2_2::setup_random_circuit(
+		curve,
+		DEFAULT_LEAF,
+		AMOUNT_BITS,
+		rng,
+	)
+	.unwrap();
+	let (proving_key, verifying_key) =
+		setup_keys_unchecked::<Bn254, _, _>(random_circuit, rng).unwrap();
+
+	// Make a proof now
+	let public_amount = 10;
+	let ext_data_hash = BnFr::rand(rng);
+
+	// Input Utxos
+	let in_chain_id = 0u64;
+	let in_amount = 5;
+	let index = 0u64;
+	let in_utxo1 = VAnchorR1CSProver_Bn254_Poseidon_30_2_2::create_random_utxo(
+		curve,
+		in_chain_id,
+		in_amount,
+		Some(index),
+		rng,
+	)
+	.unwrap();
+	let in_utxo2 = VAnchorR1CSProver_Bn254_Poseidon_30_2_2::create_random_utxo(
+		curve,
+		in_chain_id,
+		in_amount,
+		Some(1),
+		rng,
+	)
+	.unwrap();
+	let in_utxos = [in_utxo1.clone(), in_utxo2.clone()];
+
+	// Output Utxos
+	let out_chain_id = 0u64;
+	let out_amount = 10;
+	let out_utxo1 = VAnchorR1CSProver_Bn254_Poseidon_30_2_2::create_random_utxo(
+		curve,
+		out_chain_id,
+		out_amount,
+		None,
+		rng,
+	)
+	.unwrap();
+	let out_utxo2 = VAnchorR1CSProver_Bn254_Poseidon_30_2_2::create_random_utxo(
+		curve,
+		out_chain_id,
+		out_amount,
+		None,
+		rng,
+	)
+	.unwrap();
+	let out_utxos = [out_utxo1, out_utxo2];
+
+	let leaf0 = in_utxo1.commitment;
+	let leaf1 = in_utxo2.commitment;
+
+	let (smt, _) = setup_tree_and_create_path::<BnFr, Poseidon<BnFr>, HEIGHT>(
+		&tree_hasher,
+		&[leaf0, leaf1],
+		0,
+		&DEFAULT_LEAF,
+	)
+	.unwrap();
+
+	let mut in_leaves = BTreeMap::new();
+	in_leaves.insert(in_chain_id, vec![
+		leaf0.into_repr().to_bytes_be(),
+		leaf1.into_repr().to_bytes_be(),
+	]);
+	let in_indices = [0, 1];
+	// Only one of the ANCHOR_CT slots is used, the other one is padded
+	let in_root_set = vec![smt.root().into_repr().to_bytes_be()];
+
+	let proof = VAnchorR1CSProver_Bn254_Poseidon_30_2_2::create_proof(
+		curve,
+		in_chain_id,
+		public_amount,
+		ext_data_hash.into_repr().to_bytes_be(),
+		in_root_set,
+		in_indices,
+		in_leaves,
+		in_utxos,
+		out_utxos,
+		AMOUNT_BITS,
+		proving_key,
+		DEFAULT_LEAF,
+		rng,
+	)
+	.unwrap();
+
+	let pub_ins = VAnchorPublicInputs::<BnFr, ANCHOR_CT, INS_2, OUTS>::from_bytes_slice(
+		&proof.public_inputs_raw,
+	)
+	.unwrap();
+	assert_eq!(pub_ins.roots[ANCHOR_CT - 1], BnFr::zero());
+	let res = verify_unchecked_typed::<Bn254, _>(&pub_ins, &verifying_key, &proof.proof).unwrap();
 
 	assert!(res);
 }
@@ -146,9 +278,13 @@ fn should_create_circuit_and_prove_groth16_2_input_2_output() {
 	let tree_hasher = Poseidon::<BnFr> { params: params3 };
 
 	// Set up a random circuit and make pk/vk pair
-	let random_circuit =
-		VAnchorR1CSProver_Bn254_Poseidon_30_2_2::setup_random_circuit(curve, DEFAULT_LEAF, rng)
-			.unwrap();
+	let random_circuit = VAnchorR1CSProver_Bn254_Poseidon_30_2_2::setup_random_circuit(
+		curve,
+		DEFAULT_LEAF,
+		AMOUNT_BITS,
+		rng,
+	)
+	.unwrap();
 	let (proving_key, verifying_key) =
 		setup_keys_unchecked::<Bn254, _, _>(random_circuit, rng).unwrap();
 
@@ -220,7 +356,7 @@ fn should_create_circuit_and_prove_groth16_2_input_2_output() {
 		leaf1.into_repr().to_bytes_be(),
 	]);
 	let in_indices = [0, 1];
-	let in_root_set = [
+	let in_root_set = vec![
 		smt.root().into_repr().to_bytes_be(),
 		smt.root().into_repr().to_bytes_be(),
 	];
@@ -235,19 +371,19 @@ fn should_create_circuit_and_prove_groth16_2_input_2_output() {
 		in_leaves,
 		in_utxos,
 		out_utxos,
+		AMOUNT_BITS,
 		proving_key,
 		DEFAULT_LEAF,
 		rng,
 	)
 	.unwrap();
 
-	let pub_ins = proof
-		.public_inputs_raw
-		.iter()
-		.map(|inp| BnFr::from_be_bytes_mod_order(inp.as_slice()))
-		.collect::<Vec<_>>();
+	let pub_ins = VAnchorPublicInputs::<BnFr, ANCHOR_CT, INS_2, OUTS>::from_bytes_slice(
+		&proof.public_inputs_raw,
+	)
+	.unwrap();
 
-	let res = verify_unchecked::<Bn254>(&pub_ins, &verifying_key, &proof.proof).unwrap();
+	let res = verify_unchecked_typed::<Bn254, _>(&pub_ins, &verifying_key, &proof.proof).unwrap();
 
 	assert!(res);
 }
@@ -260,9 +396,13 @@ fn should_fail_with_invalid_root() {
 	let tree_hasher = Poseidon::<BnFr> { params: params3 };
 
 	// Set up a random circuit and make pk/vk pair
-	let random_circuit =
-		VAnchorR1CSProver_Bn254_Poseidon_30_2_2::setup_random_circuit(curve, DEFAULT_LEAF, rng)
-			.unwrap();
+	let random_circuit = VAnchorR1CSProver_Bn254_Poseidon_30_2_2::setup_random_circuit(
+		curve,
+		DEFAULT_LEAF,
+		AMOUNT_BITS,
+		rng,
+	)
+	.unwrap();
 	let (proving_key, verifying_key) =
 		setup_keys_unchecked::<Bn254, _, _>(random_circuit, rng).unwrap();
 
@@ -329,7 +469,7 @@ fn should_fail_with_invalid_root() {
 		leaf1.into_repr().to_bytes_be(),
 	]);
 	let in_indices = [0, 1];
-	let in_root_set = [
+	let in_root_set = vec![
 		smt.root().into_repr().to_bytes_be(),
 		smt.root().into_repr().to_bytes_be(),
 	];
@@ -344,19 +484,19 @@ fn should_fail_with_invalid_root() {
 		in_leaves,
 		in_utxos,
 		out_utxos,
+		AMOUNT_BITS,
 		proving_key,
 		DEFAULT_LEAF,
 		rng,
 	)
 	.unwrap();
 
-	let pub_ins = proof
-		.public_inputs_raw
-		.iter()
-		.map(|inp| BnFr::from_be_bytes_mod_order(inp.as_slice()))
-		.collect::<Vec<_>>();
+	let pub_ins = VAnchorPublicInputs::<BnFr, ANCHOR_CT, INS_2, OUTS>::from_bytes_slice(
+		&proof.public_inputs_raw,
+	)
+	.unwrap();
 
-	let res = verify_unchecked::<Bn254>(&pub_ins, &verifying_key, &proof.proof).unwrap();
+	let res = verify_unchecked_typed::<Bn254, _>(&pub_ins, &verifying_key, &proof.proof).unwrap();
 
 	assert!(!res);
 }
@@ -370,9 +510,13 @@ fn should_fail_with_same_nullifier() {
 	let tree_hasher = Poseidon::<BnFr> { params: params3 };
 
 	// Set up a random circuit and make pk/vk pair
-	let random_circuit =
-		VAnchorR1CSProver_Bn254_Poseidon_30_2_2::setup_random_circuit(curve, DEFAULT_LEAF, rng)
-			.unwrap();
+	let random_circuit = VAnchorR1CSProver_Bn254_Poseidon_30_2_2::setup_random_circuit(
+		curve,
+		DEFAULT_LEAF,
+		AMOUNT_BITS,
+		rng,
+	)
+	.unwrap();
 	let (proving_key, verifying_key) =
 		setup_keys_unchecked::<Bn254, _, _>(random_circuit, rng).unwrap();
 
@@ -432,7 +576,7 @@ fn should_fail_with_same_nullifier() {
 		leaf0.into_repr().to_bytes_be(),
 	]);
 	let in_indices = [0, 0];
-	let in_root_set = [
+	let in_root_set = vec![
 		smt.root().into_repr().to_bytes_be(),
 		smt.root().into_repr().to_bytes_be(),
 	];
@@ -447,18 +591,18 @@ fn should_fail_with_same_nullifier() {
 		in_leaves,
 		in_utxos,
 		out_utxos,
+		AMOUNT_BITS,
 		proving_key,
 		DEFAULT_LEAF,
 		rng,
 	)
 	.unwrap();
 
-	let pub_ins = proof
-		.public_inputs_raw
-		.iter()
-		.map(|inp| BnFr::from_be_bytes_mod_order(inp.as_slice()))
-		.collect::<Vec<_>>();
-	let res = verify_unchecked::<Bn254>(&pub_ins, &verifying_key, &proof.proof).unwrap();
+	let pub_ins = VAnchorPublicInputs::<BnFr, ANCHOR_CT, INS_2, OUTS>::from_bytes_slice(
+		&proof.public_inputs_raw,
+	)
+	.unwrap();
+	let res = verify_unchecked_typed::<Bn254, _>(&pub_ins, &verifying_key, &proof.proof).unwrap();
 
 	assert!(!res);
 }
@@ -471,9 +615,13 @@ fn should_fail_with_inconsistent_input_output_values() {
 	let tree_hasher = Poseidon::<BnFr> { params: params3 };
 
 	// Set up a random circuit and make pk/vk pair
-	let random_circuit =
-		VAnchorR1CSProver_Bn254_Poseidon_30_2_2::setup_random_circuit(curve, DEFAULT_LEAF, rng)
-			.unwrap();
+	let random_circuit = VAnchorR1CSProver_Bn254_Poseidon_30_2_2::setup_random_circuit(
+		curve,
+		DEFAULT_LEAF,
+		AMOUNT_BITS,
+		rng,
+	)
+	.unwrap();
 	let (proving_key, verifying_key) =
 		setup_keys_unchecked::<Bn254, _, _>(random_circuit, rng).unwrap();
 
@@ -541,7 +689,7 @@ fn should_fail_with_inconsistent_input_output_values() {
 		leaf1.into_repr().to_bytes_be(),
 	]);
 	let in_indices = [0, 1];
-	let in_root_set = [
+	let in_root_set = vec![
 		smt.root().into_repr().to_bytes_be(),
 		smt.root().into_repr().to_bytes_be(),
 	];
@@ -556,18 +704,18 @@ fn should_fail_with_inconsistent_input_output_values() {
 		in_leaves,
 		in_utxos,
 		out_utxos,
+		AMOUNT_BITS,
 		proving_key,
 		DEFAULT_LEAF,
 		rng,
 	)
 	.unwrap();
 
-	let pub_ins = proof
-		.public_inputs_raw
-		.iter()
-		.map(|inp| BnFr::from_be_bytes_mod_order(inp.as_slice()))
-		.collect::<Vec<_>>();
-	let res = verify_unchecked::<Bn254>(&pub_ins, &verifying_key, &proof.proof).unwrap();
+	let pub_ins = VAnchorPublicInputs::<BnFr, ANCHOR_CT, INS_2, OUTS>::from_bytes_slice(
+		&proof.public_inputs_raw,
+	)
+	.unwrap();
+	let res = verify_unchecked_typed::<Bn254, _>(&pub_ins, &verifying_key, &proof.proof).unwrap();
 
 	assert!(!res);
 }
@@ -580,9 +728,13 @@ fn should_fail_with_big_amount() {
 	let tree_hasher = Poseidon::<BnFr> { params: params3 };
 
 	// Set up a random circuit and make pk/vk pair
-	let random_circuit =
-		VAnchorR1CSProver_Bn254_Poseidon_30_2_2::setup_random_circuit(curve, DEFAULT_LEAF, rng)
-			.unwrap();
+	let random_circuit = VAnchorR1CSProver_Bn254_Poseidon_30_2_2::setup_random_circuit(
+		curve,
+		DEFAULT_LEAF,
+		AMOUNT_BITS,
+		rng,
+	)
+	.unwrap();
 	let (proving_key, verifying_key) =
 		setup_keys_unchecked::<Bn254, _, _>(random_circuit, rng).unwrap();
 
@@ -659,7 +811,7 @@ fn should_fail_with_big_amount() {
 		leaf1.into_repr().to_bytes_be(),
 	]);
 	let in_indices = [0, 1];
-	let in_root_set = [
+	let in_root_set = vec![
 		smt.root().into_repr().to_bytes_be(),
 		smt.root().into_repr().to_bytes_be(),
 	];
@@ -674,18 +826,18 @@ fn should_fail_with_big_amount() {
 		in_leaves,
 		in_utxos,
 		out_utxos,
+		AMOUNT_BITS,
 		proving_key,
 		DEFAULT_LEAF,
 		rng,
 	)
 	.unwrap();
 
-	let pub_ins = proof
-		.public_inputs_raw
-		.iter()
-		.map(|inp| BnFr::from_be_bytes_mod_order(inp.as_slice()))
-		.collect::<Vec<_>>();
-	let res = verify_unchecked::<Bn254>(&pub_ins, &verifying_key, &proof.proof).unwrap();
+	let pub_ins = VAnchorPublicInputs::<BnFr, ANCHOR_CT, INS_2, OUTS>::from_bytes_slice(
+		&proof.public_inputs_raw,
+	)
+	.unwrap();
+	let res = verify_unchecked_typed::<Bn254, _>(&pub_ins, &verifying_key, &proof.proof).unwrap();
 
 	assert!(!res);
 }
@@ -697,9 +849,13 @@ fn should_fail_with_invalid_public_input() {
 	let params3 = setup_params::<BnFr>(curve, 5, 3);
 	let tree_hasher = Poseidon::<BnFr> { params: params3 };
 	// Set up a random circuit and make pk/vk pair
-	let random_circuit =
-		VAnchorR1CSProver_Bn254_Poseidon_30_2_2::setup_random_circuit(curve, DEFAULT_LEAF, rng)
-			.unwrap();
+	let random_circuit = VAnchorR1CSProver_Bn254_Poseidon_30_2_2::setup_random_circuit(
+		curve,
+		DEFAULT_LEAF,
+		AMOUNT_BITS,
+		rng,
+	)
+	.unwrap();
 	let (proving_key, verifying_key) =
 		setup_keys_unchecked::<Bn254, _, _>(random_circuit, rng).unwrap();
 
@@ -772,7 +928,7 @@ fn should_fail_with_invalid_public_input() {
 		leaf1.into_repr().to_bytes_be(),
 	]);
 	let in_indices = [0, 1];
-	let in_root_set = [
+	let in_root_set = vec![
 		smt.root().into_repr().to_bytes_be(),
 		smt.root().into_repr().to_bytes_be(),
 	];
@@ -787,19 +943,19 @@ fn should_fail_with_invalid_public_input() {
 		in_leaves,
 		in_utxos,
 		out_utxos,
+		AMOUNT_BITS,
 		proving_key,
 		DEFAULT_LEAF,
 		rng,
 	)
 	.unwrap();
 
-	let pub_ins = proof
-		.public_inputs_raw
-		.iter()
-		.map(|inp| BnFr::from_be_bytes_mod_order(inp.as_slice()))
-		.collect::<Vec<_>>();
+	let pub_ins = VAnchorPublicInputs::<BnFr, ANCHOR_CT, INS_2, OUTS>::from_bytes_slice(
+		&proof.public_inputs_raw,
+	)
+	.unwrap();
 
-	let truncated_public_inputs = &pub_ins[2..];
+	let truncated_public_inputs = &pub_ins.to_field_vec()[2..];
 	let vk = VerifyingKey::<Bn254>::deserialize_unchecked(&verifying_key[..]).unwrap();
 	let proof = Proof::<Bn254>::deserialize(&proof.proof[..]).unwrap();
 	let res = Groth16::<Bn254>::verify(&vk, truncated_public_inputs, &proof);
@@ -815,9 +971,13 @@ fn should_create_circuit_and_prove_with_default_utxos() {
 	let tree_hasher = Poseidon::<BnFr> { params: params3 };
 
 	// Set up a random circuit and make pk/vk pair
-	let random_circuit =
-		VAnchorR1CSProver_Bn254_Poseidon_30_2_2::setup_random_circuit(curve, DEFAULT_LEAF, rng)
-			.unwrap();
+	let random_circuit = VAnchorR1CSProver_Bn254_Poseidon_30_2_2::setup_random_circuit(
+		curve,
+		DEFAULT_LEAF,
+		AMOUNT_BITS,
+		rng,
+	)
+	.unwrap();
 	let (proving_key, verifying_key) =
 		setup_keys_unchecked::<Bn254, _, _>(random_circuit, rng).unwrap();
 
@@ -879,7 +1039,7 @@ fn should_create_circuit_and_prove_with_default_utxos() {
 
 	let in_leaves = BTreeMap::new();
 	let in_indices = [0, 0];
-	let in_root_set = [
+	let in_root_set = vec![
 		smt.root().into_repr().to_bytes_be(),
 		smt.root().into_repr().to_bytes_be(),
 	];
@@ -894,18 +1054,18 @@ fn should_create_circuit_and_prove_with_default_utxos() {
 		in_leaves,
 		in_utxos,
 		out_utxos,
+		AMOUNT_BITS,
 		proving_key,
 		DEFAULT_LEAF,
 		rng,
 	)
 	.unwrap();
 
-	let pub_ins = proof
-		.public_inputs_raw
-		.iter()
-		.map(|inp| BnFr::from_be_bytes_mod_order(inp.as_slice()))
-		.collect::<Vec<_>>();
-	let res = verify_unchecked::<Bn254>(&pub_ins, &verifying_key, &proof.proof).unwrap();
+	let pub_ins = VAnchorPublicInputs::<BnFr, ANCHOR_CT, INS_2, OUTS>::from_bytes_slice(
+		&proof.public_inputs_raw,
+	)
+	.unwrap();
+	let res = verify_unchecked_typed::<Bn254, _>(&pub_ins, &verifying_key, &proof.proof).unwrap();
 
 	assert!(res);
 }
@@ -918,9 +1078,13 @@ fn should_create_circuit_and_prove_with_16_utxos() {
 	let tree_hasher = Poseidon::<BnFr> { params: params3 };
 
 	// Set up a random circuit and make pk/vk pair
-	let random_circuit =
-		VAnchorR1CSProver_Bn254_Poseidon_30_16_2::setup_random_circuit(curve, DEFAULT_LEAF, rng)
-			.unwrap();
+	let random_circuit = VAnchorR1CSProver_Bn254_Poseidon_30_16_2::setup_random_circuit(
+		curve,
+		DEFAULT_LEAF,
+		AMOUNT_BITS,
+		rng,
+	)
+	.unwrap();
 	let (proving_key, verifying_key) =
 		setup_keys_unchecked::<Bn254, _, _>(random_circuit, rng).unwrap();
 
@@ -998,7 +1162,7 @@ fn should_create_circuit_and_prove_with_16_utxos() {
 		.enumerate()
 		.map(|(index, _)| index as u64)
 		.collect::<Vec<u64>>();
-	let in_root_set = [
+	let in_root_set = vec![
 		smt.root().into_repr().to_bytes_be(),
 		smt.root().into_repr().to_bytes_be(),
 	];
@@ -1013,18 +1177,18 @@ fn should_create_circuit_and_prove_with_16_utxos() {
 		in_leaves,
 		in_utxos,
 		out_utxos,
+		AMOUNT_BITS,
 		proving_key,
 		DEFAULT_LEAF,
 		rng,
 	)
 	.unwrap();
 
-	let pub_ins = proof
-		.public_inputs_raw
-		.iter()
-		.map(|inp| BnFr::from_be_bytes_mod_order(inp.as_slice()))
-		.collect::<Vec<_>>();
-	let res = verify_unchecked::<Bn254>(&pub_ins, &verifying_key, &proof.proof).unwrap();
+	let pub_ins = VAnchorPublicInputs::<BnFr, ANCHOR_CT, INS_16, OUTS>::from_bytes_slice(
+		&proof.public_inputs_raw,
+	)
+	.unwrap();
+	let res = verify_unchecked_typed::<Bn254, _>(&pub_ins, &verifying_key, &proof.proof).unwrap();
 
 	assert!(res);
 }
@@ -1037,9 +1201,13 @@ fn should_create_circuit_and_prove_with_16_default_utxos() {
 	let tree_hasher = Poseidon::<BnFr> { params: params3 };
 
 	// Set up a random circuit and make pk/vk pair
-	let random_circuit =
-		VAnchorR1CSProver_Bn254_Poseidon_30_16_2::setup_random_circuit(curve, DEFAULT_LEAF, rng)
-			.unwrap();
+	let random_circuit = VAnchorR1CSProver_Bn254_Poseidon_30_16_2::setup_random_circuit(
+		curve,
+		DEFAULT_LEAF,
+		AMOUNT_BITS,
+		rng,
+	)
+	.unwrap();
 	let (proving_key, verifying_key) =
 		setup_keys_unchecked::<Bn254, _, _>(random_circuit, rng).unwrap();
 
@@ -1103,7 +1271,7 @@ fn should_create_circuit_and_prove_with_16_default_utxos() {
 
 	let in_leaves = BTreeMap::new();
 	let in_indices = [0; 16];
-	let in_root_set = [
+	let in_root_set = vec![
 		smt.root().into_repr().to_bytes_be(),
 		smt.root().into_repr().to_bytes_be(),
 	];
@@ -1118,18 +1286,18 @@ fn should_create_circuit_and_prove_with_16_default_utxos() {
 		in_leaves,
 		in_utxos,
 		out_utxos,
+		AMOUNT_BITS,
 		proving_key,
 		DEFAULT_LEAF,
 		rng,
 	)
 	.unwrap();
 
-	let pub_ins = proof
-		.public_inputs_raw
-		.iter()
-		.map(|inp| BnFr::from_be_bytes_mod_order(inp.as_slice()))
-		.collect::<Vec<_>>();
-	let res = verify_unchecked::<Bn254>(&pub_ins, &verifying_key, &proof.proof).unwrap();
+	let pub_ins = VAnchorPublicInputs::<BnFr, ANCHOR_CT, INS_16, OUTS>::from_bytes_slice(
+		&proof.public_inputs_raw,
+	)
+	.unwrap();
+	let res = verify_unchecked_typed::<Bn254, _>(&pub_ins, &verifying_key, &proof.proof).unwrap();
 
 	assert!(res);
 }
@@ -1146,9 +1314,13 @@ fn should_create_circuit_and_prove_with_16_mixed_utxos() {
 	let tree_hasher = Poseidon::<BnFr> { params: params3 };
 
 	// Set up a random circuit and make pk/vk pair
-	let random_circuit =
-		VAnchorR1CSProver_Bn254_Poseidon_30_16_2::setup_random_circuit(curve, DEFAULT_LEAF, rng)
-			.unwrap();
+	let random_circuit = VAnchorR1CSProver_Bn254_Poseidon_30_16_2::setup_random_circuit(
+		curve,
+		DEFAULT_LEAF,
+		AMOUNT_BITS,
+		rng,
+	)
+	.unwrap();
 	let (proving_key, verifying_key) =
 		setup_keys_unchecked::<Bn254, _, _>(random_circuit, rng).unwrap();
 
@@ -1240,7 +1412,7 @@ fn should_create_circuit_and_prove_with_16_mixed_utxos() {
 		.collect::<Vec<_>>()
 		.try_into()
 		.unwrap();
-	let in_root_set = [
+	let in_root_set = vec![
 		smt.root().into_repr().to_bytes_be(),
 		smt.root().into_repr().to_bytes_be(),
 	];
@@ -1255,18 +1427,18 @@ fn should_create_circuit_and_prove_with_16_mixed_utxos() {
 		in_leaves,
 		in_utxos,
 		out_utxos,
+		AMOUNT_BITS,
 		proving_key,
 		DEFAULT_LEAF,
 		rng,
 	)
 	.unwrap();
 
-	let pub_ins = proof
-		.public_inputs_raw
-		.iter()
-		.map(|inp| BnFr::from_be_bytes_mod_order(inp.as_slice()))
-		.collect::<Vec<_>>();
-	let res = verify_unchecked::<Bn254>(&pub_ins, &verifying_key, &proof.proof).unwrap();
+	let pub_ins = VAnchorPublicInputs::<BnFr, ANCHOR_CT, INS_16, OUTS>::from_bytes_slice(
+		&proof.public_inputs_raw,
+	)
+	.unwrap();
+	let res = verify_unchecked_typed::<Bn254, _>(&pub_ins, &verifying_key, &proof.proof).unwrap();
 
 	assert!(res);
 }
@@ -1299,3 +1471,534 @@ fn should_calculate_and_set_nullifier() {
 
 	assert_ne!(nullifier_after, nullifier_recalculated);
 }
+
+#[test]
+fn should_fail_with_amount_above_amount_bits() {
+	let rng = &mut test_rng();
+	let curve = Curve::Bn254;
+	let params3 = setup_params::<BnFr>(curve, 5, 3);
+	let tree_hasher = Poseidon::<BnFr> { params: params3 };
+	let amount_bits = 64;
+
+	// Set up a random circuit and make pk/vk pair
+	let random_circuit = VAnchorR1CSProver_Bn254_Poseidon_30_2_2::setup_random_circuit(
+		curve,
+		DEFAULT_LEAF,
+		amount_bits,
+		rng,
+	)
+	.unwrap();
+	let (proving_key, verifying_key) =
+		setup_keys_unchecked::<Bn254, _, _>(random_circuit, rng).unwrap();
+
+	let public_amount = 0;
+	let ext_data_hash = BnFr::rand(rng);
+
+	// Input and output amounts balance, but one of them needs 65 bits
+	let big_amount = 1u128 << amount_bits;
+	let chain_id = 0u64;
+	let in_utxo1 = VAnchorR1CSProver_Bn254_Poseidon_30_2_2::create_random_utxo(
+		curve,
+		chain_id,
+		big_amount,
+		Some(0),
+		rng,
+	)
+	.unwrap();
+	let in_utxo2 = VAnchorR1CSProver_Bn254_Poseidon_30_2_2::create_random_utxo(
+		curve,
+		chain_id,
+		0,
+		Some(1),
+		rng,
+	)
+	.unwrap();
+	let in_utxos = [in_utxo1.clone(), in_utxo2.clone()];
+
+	let out_utxo1 = VAnchorR1CSProver_Bn254_Poseidon_30_2_2::create_random_utxo(
+		curve, chain_id, big_amount, None, rng,
+	)
+	.unwrap();
+	let out_utxo2 =
+		VAnchorR1CSProver_Bn254_Poseidon_30_2_2::create_random_utxo(curve, chain_id, 0, None, rng)
+			.unwrap();
+	let out_utxos = [out_utxo1, out_utxo2];
+
+	let leaf0 = in_utxo1.commitment;
+	let leaf1 = in_utxo2.commitment;
+	let (smt, _) = setup_tree_and_create_path::<BnFr, Poseidon<BnFr>, HEIGHT>(
+		&tree_hasher,
+		&[leaf0, leaf1],
+		0,
+		&DEFAULT_LEAF,
+	)
+	.unwrap();
+
+	let mut in_leaves = BTreeMap::new();
+	in_leaves.insert(chain_id, vec![
+		leaf0.into_repr().to_bytes_be(),
+		leaf1.into_repr().to_bytes_be(),
+	]);
+	let in_indices = [0, 1];
+	let in_root_set = vec![
+		smt.root().into_repr().to_bytes_be(),
+		smt.root().into_repr().to_bytes_be(),
+	];
+
+	let proof = VAnchorR1CSProver_Bn254_Poseidon_30_2_2::create_proof(
+		curve,
+		chain_id,
+		public_amount,
+		ext_data_hash.into_repr().to_bytes_be(),
+		in_root_set,
+		in_indices,
+		in_leaves,
+		in_utxos,
+		out_utxos,
+		amount_bits,
+		proving_key,
+		DEFAULT_LEAF,
+		rng,
+	)
+	.unwrap();
+
+	let pub_ins = VAnchorPublicInputs::<BnFr, ANCHOR_CT, INS_2, OUTS>::from_bytes_slice(
+		&proof.public_inputs_raw,
+	)
+	.unwrap();
+	let res = verify_unchecked_typed::<Bn254, _>(&pub_ins, &verifying_key, &proof.proof).unwrap();
+
+	assert!(!res);
+}
+
+#[test]
+fn should_count_constraints_for_amount_bits() {
+	let rng = &mut test_rng();
+	let curve = Curve::Bn254;
+
+	// A range check of n bits costs n + 1 constraints, there is one for every
+	// input and output amount and one for the magnitude of the public amount.
+	for (amount_bits, expected) in [(64, 19864), (128, 20184), (248, 20784)] {
+		let circuit = VAnchorR1CSProver_Bn254_Poseidon_30_2_2::setup_random_circuit(
+			curve,
+			DEFAULT_LEAF,
+			amount_bits,
+			rng,
+		)
+		.unwrap();
+		// The random circuit isn't satisfiable, only its shape is synthesized
+		let cs = ConstraintSystem::<BnFr>::new_ref();
+		cs.set_mode(SynthesisMode::Setup);
+		circuit.generate_constraints(cs.clone()).unwrap();
+
+		assert_eq!(cs.num_constraints(), expected);
+	}
+}
+
+#[test]
+fn should_satisfy_root_tree_circuit() {
+	let rng = &mut test_rng();
+	let curve = Curve::Bn254;
+	let params2 = setup_params::<BnFr>(curve, 5, 2);
+	let params3 = setup_params::<BnFr>(curve, 5, 3);
+	let params4 = setup_params::<BnFr>(curve, 5, 4);
+	let params5 = setup_params::<BnFr>(curve, 5, 5);
+	let keypair_hasher = Poseidon::<BnFr> { params: params2 };
+	let tree_hasher = Poseidon::<BnFr> { params: params3 };
+	let nullifier_hasher = Poseidon::<BnFr> { params: params4 };
+	let leaf_hasher = Poseidon::<BnFr> { params: params5 };
+
+	// Input Utxos
+	let in_chain_id = 0u64;
+	let in_utxos = [0u64, 1].map(|index| {
+		VAnchorR1CSProver_Bn254_Poseidon_30_2_2::create_random_utxo(
+			curve,
+			in_chain_id,
+			5,
+			Some(index),
+			rng,
+		)
+		.unwrap()
+	});
+
+	// Output Utxos
+	let out_utxos = [0; OUTS].map(|_| {
+		VAnchorR1CSProver_Bn254_Poseidon_30_2_2::create_random_utxo(curve, 0, 5, None, rng).unwrap()
+	});
+
+	let leaves = in_utxos.iter().map(|x| x.commitment).collect::<Vec<_>>();
+	let (smt, _) = setup_tree_and_create_path::<BnFr, Poseidon<BnFr>, HEIGHT>(
+		&tree_hasher,
+		&leaves,
+		0,
+		&DEFAULT_LEAF,
+	)
+	.unwrap();
+	let paths = (0..INS_2)
+		.map(|i| smt.generate_membership_proof(i as u64))
+		.collect::<Vec<_>>();
+
+	// Commit to the roots of all the edges in the root tree
+	let edge_roots = vec![BnFr::rand(rng), smt.root(), BnFr::rand(rng)];
+	let root_tree = build_root_tree::<BnFr, Poseidon<BnFr>, ROOT_HEIGHT>(
+		&edge_roots,
+		&tree_hasher,
+		&DEFAULT_LEAF,
+	)
+	.unwrap();
+	let root_paths = vec![root_tree.generate_membership_proof(1); INS_2];
+
+	let circuit = VAnchorRootTreeCircuit::<
+		BnFr,
+		PoseidonGadget<BnFr>,
+		HEIGHT,
+		INS_2,
+		OUTS,
+		ROOT_HEIGHT,
+	>::new(
+		BnFr::zero(),
+		BnFr::rand(rng),
+		in_utxos.iter().map(|x| x.amount).collect(),
+		in_utxos.iter().map(|x| x.blinding).collect(),
+		in_utxos
+			.iter()
+			.map(|x| x.keypair.secret_key.unwrap())
+			.collect(),
+		BnFr::from(in_chain_id),
+		root_tree.root(),
+		paths,
+		root_paths,
+		vec![BnFr::zero(), BnFr::one()],
+		in_utxos
+			.iter()
+			.map(|x| x.calculate_nullifier(&nullifier_hasher).unwrap())
+			.collect(),
+		out_utxos.iter().map(|x| x.commitment).collect(),
+		out_utxos.iter().map(|x| x.amount).collect(),
+		out_utxos.iter().map(|x| x.blinding).collect(),
+		out_utxos.iter().map(|x| x.chain_id).collect(),
+		out_utxos.iter().map(|x| x.keypair.public_key).collect(),
+		tree_hasher,
+		keypair_hasher,
+		leaf_hasher,
+		nullifier_hasher,
+		AMOUNT_BITS,
+	);
+
+	let cs = ConstraintSystem::<BnFr>::new_ref();
+	circuit.generate_constraints(cs.clone()).unwrap();
+	assert!(cs.is_satisfied().unwrap());
+	// Public amount, ext data hash, nullifiers, commitments, chain id and the
+	// root of roots, after the constant one
+	assert_eq!(cs.num_instance_variables(), 1 + 2 + INS_2 + OUTS + 2);
+}
+
+#[test]
+fn should_satisfy_encrypted_circuit() {
+	let rng = &mut test_rng();
+	let curve = Curve::Bn254;
+	let params2 = setup_params::<BnFr>(curve, 5, 2);
+	let params3 = setup_params::<BnFr>(curve, 5, 3);
+	let params4 = setup_params::<BnFr>(curve, 5, 4);
+	let params5 = setup_params::<BnFr>(curve, 5, 5);
+	let keypair_hasher = Poseidon::<BnFr> { params: params2 };
+	let tree_hasher = Poseidon::<BnFr> { params: params3 };
+	let nullifier_hasher = Poseidon::<BnFr> {
+		params: params4.clone(),
+	};
+	let leaf_hasher = Poseidon::<BnFr> { params: params5 };
+	let encryption_hasher = Poseidon::<BnFr> { params: params4 };
+
+	// Input Utxos
+	let in_chain_id = 0u64;
+	let in_utxos = [0u64, 1].map(|index| {
+		VAnchorR1CSProver_Bn254_Poseidon_30_2_2::create_random_utxo(
+			curve,
+			in_chain_id,
+			5,
+			Some(index),
+			rng,
+		)
+		.unwrap()
+	});
+
+	// Output Utxos
+	let out_utxos = [0; OUTS].map(|_| {
+		VAnchorR1CSProver_Bn254_Poseidon_30_2_2::create_random_utxo(curve, 0, 5, None, rng).unwrap()
+	});
+
+	let leaves = in_utxos.iter().map(|x| x.commitment).collect::<Vec<_>>();
+	let (smt, _) = setup_tree_and_create_path::<BnFr, Poseidon<BnFr>, HEIGHT>(
+		&tree_hasher,
+		&leaves,
+		0,
+		&DEFAULT_LEAF,
+	)
+	.unwrap();
+	let paths = (0..INS_2)
+		.map(|i| smt.generate_membership_proof(i as u64))
+		.collect::<Vec<_>>();
+
+	let vanchor = VAnchorR1CSProver_Bn254_Poseidon_30_2_2::setup_circuit(
+		BnFr::from(in_chain_id),
+		BnFr::zero(),
+		BnFr::rand(rng),
+		in_utxos,
+		[BnFr::zero(), BnFr::one()],
+		paths,
+		[smt.root(), BnFr::rand(rng)],
+		out_utxos.clone(),
+		keypair_hasher,
+		tree_hasher,
+		nullifier_hasher,
+		leaf_hasher.clone(),
+		AMOUNT_BITS,
+	)
+	.unwrap();
+
+	// Encrypt every output to its recipient
+	let recipients = [0; OUTS].map(|_| SecretKey::rand(rng));
+	let ephemeral_keys = [0; OUTS].map(|_| SecretKey::rand(rng));
+	let notes = (0..OUTS)
+		.map(|i| {
+			out_utxos[i]
+				.encrypt_poseidon(
+					&recipients[i].public_key(),
+					&ephemeral_keys[i],
+					&encryption_hasher,
+				)
+				.unwrap()
+		})
+		.collect::<Vec<_>>();
+
+	// Recipients recover the committed Utxos
+	for i in 0..OUTS {
+		let decrypted =
+			Utxo::decrypt_poseidon(&notes[i], &recipients[i], &encryption_hasher, &leaf_hasher)
+				.unwrap();
+		assert_eq!(decrypted.commitment, out_utxos[i].commitment);
+	}
+
+	let circuit =
+		VAnchorEncryptedCircuit::<PoseidonGadget<BnFr>, HEIGHT, INS_2, OUTS, ANCHOR_CT>::new(
+			vanchor,
+			recipients.iter().map(|x| x.public_key()).collect(),
+			ephemeral_keys.iter().map(|x| x.scalar()).collect(),
+			notes.iter().map(|x| x.ephemeral_public_key).collect(),
+			notes.iter().map(|x| x.ciphertext.clone()).collect(),
+			encryption_hasher,
+		);
+
+	let cs = ConstraintSystem::<BnFr>::new_ref();
+	circuit.generate_constraints(cs.clone()).unwrap();
+	assert!(cs.is_satisfied().unwrap());
+	// The VAnchor public inputs after the constant one, then the ephemeral
+	// public key and the ciphertext of every output
+	assert_eq!(
+		cs.num_instance_variables(),
+		1 + 2 + INS_2 + OUTS + 1 + ANCHOR_CT + OUTS * (2 + ciphertext_len(4))
+	);
+}
+
+#[test]
+fn should_setup_same_keys_from_seed() {
+	let curve = Curve::Bn254;
+	let seed = [1u8; 32];
+
+	let keys1 = VAnchorR1CSProver_Bn254_Poseidon_30_2_2::setup_keys_from_seed(
+		curve,
+		DEFAULT_LEAF,
+		AMOUNT_BITS,
+		seed,
+	)
+	.unwrap();
+	let keys2 = VAnchorR1CSProver_Bn254_Poseidon_30_2_2::setup_keys_from_seed(
+		curve,
+		DEFAULT_LEAF,
+		AMOUNT_BITS,
+		seed,
+	)
+	.unwrap();
+	let other_keys = VAnchorR1CSProver_Bn254_Poseidon_30_2_2::setup_keys_from_seed(
+		curve,
+		DEFAULT_LEAF,
+		AMOUNT_BITS,
+		[2u8; 32],
+	)
+	.unwrap();
+
+	assert_eq!(keys1.pk, keys2.pk);
+	assert_eq!(keys1.vk, keys2.vk);
+	assert_ne!(keys1.vk, other_keys.vk);
+}
+
+#[test]
+fn should_create_padded_proofs_for_any_number_of_notes() {
+	let rng = &mut test_rng();
+	let curve = Curve::Bn254;
+	let params3 = setup_params::<BnFr>(curve, 5, 3);
+	let tree_hasher = Poseidon::<BnFr> { params: params3 };
+
+	let keys_2_2 = VAnchorR1CSProver_Bn254_Poseidon_30_2_2::setup_keys_from_seed(
+		curve,
+		DEFAULT_LEAF,
+		AMOUNT_BITS,
+		[1u8; 32],
+	)
+	.unwrap();
+	let keys_16_2 = VAnchorR1CSProver_Bn254_Poseidon_30_16_2::setup_keys_from_seed(
+		curve,
+		DEFAULT_LEAF,
+		AMOUNT_BITS,
+		[1u8; 32],
+	)
+	.unwrap();
+	let keys = VAnchorProvingKeys {
+		pk_2_2: keys_2_2.pk,
+		pk_16_2: keys_16_2.pk,
+	};
+
+	// Deposit 5 notes of 10, which fill positions 0 to 4 of the tree
+	let chain_id = 0u64;
+	let notes = (0..5)
+		.map(|i| {
+			VAnchorR1CSProver_Bn254_Poseidon_30_2_2::create_random_utxo(
+				curve,
+				chain_id,
+				10,
+				Some(i),
+				rng,
+			)
+			.unwrap()
+		})
+		.collect::<Vec<_>>();
+	let leaves = notes.iter().map(|x| x.commitment).collect::<Vec<_>>();
+	let (smt, _) = setup_tree_and_create_path::<BnFr, Poseidon<BnFr>, HEIGHT>(
+		&tree_hasher,
+		&leaves,
+		0,
+		&DEFAULT_LEAF,
+	)
+	.unwrap();
+	let mut in_leaves = BTreeMap::new();
+	in_leaves.insert(
+		chain_id,
+		leaves
+			.iter()
+			.map(|x| x.into_repr().to_bytes_be())
+			.collect::<Vec<_>>(),
+	);
+	let in_root_set = vec![smt.root().into_repr().to_bytes_be()];
+
+	// One note is spent with the 2x2 circuit and four with the 16x2 one
+	for (spent, ins, vk) in [
+		(vec![notes[1].clone()], INS_2, &keys_2_2.vk),
+		(notes[1..].to_vec(), INS_16, &keys_16_2.vk),
+	] {
+		assert_eq!(select_ins(spent.len()).unwrap(), ins);
+
+		let total = 10 * spent.len() as u128;
+		let out_utxos = [total / 2, total - total / 2].map(|amount| {
+			VAnchorR1CSProver_Bn254_Poseidon_30_2_2::create_random_utxo(
+				curve, chain_id, amount, None, rng,
+			)
+			.unwrap()
+		});
+
+		let proof = create_padded_proof::<Bn254, _, HEIGHT, ANCHOR_CT>(
+			curve,
+			chain_id,
+			0,
+			BnFr::rand(rng).into_repr().to_bytes_be(),
+			in_root_set.clone(),
+			in_leaves.clone(),
+			spent,
+			out_utxos,
+			AMOUNT_BITS,
+			&keys,
+			DEFAULT_LEAF,
+			rng,
+		)
+		.unwrap();
+
+		// One nullifier per input of the selected circuit
+		assert_eq!(
+			proof.public_inputs_raw.len(),
+			2 + ins + OUTS + 1 + ANCHOR_CT
+		);
+		assert!(verify_unchecked_raw::<Bn254>(&proof.public_inputs_raw, vk, &proof.proof).unwrap());
+	}
+}
+
+#[test]
+fn should_fail_to_pad_too_many_notes() {
+	let rng = &mut test_rng();
+	let curve = Curve::Bn254;
+
+	let notes = (0..3)
+		.map(|i| {
+			VAnchorR1CSProver_Bn254_Poseidon_30_2_2::create_random_utxo(curve, 0, 10, Some(i), rng)
+				.unwrap()
+		})
+		.collect::<Vec<_>>();
+
+	let padded =
+		VAnchorR1CSProver_Bn254_Poseidon_30_2_2::pad_in_utxos(curve, 0, notes.clone(), rng);
+	assert!(padded.is_err());
+
+	// Dummies have a zero amount and nullifiers distinct from each other
+	let padded =
+		VAnchorR1CSProver_Bn254_Poseidon_30_16_2::pad_in_utxos(curve, 0, notes, rng).unwrap();
+	assert!(padded[3..].iter().all(|x| x.amount.is_zero()));
+	assert_ne!(padded[3].commitment, padded[4].commitment);
+
+	assert!(select_ins(INS_16 + 1).is_err());
+}
+
+#[test]
+fn padded_dummies_should_have_the_index_of_their_path() {
+	let rng = &mut test_rng();
+	let curve = Curve::Bn254;
+	let tree_hasher = Poseidon::<BnFr>::new(setup_params(curve, 5, 3));
+
+	// A zero-amount note given by the caller shares the tree of the dummies
+	let notes = [10, 0, 10]
+		.iter()
+		.enumerate()
+		.map(|(i, amount)| {
+			VAnchorR1CSProver_Bn254_Poseidon_30_16_2::create_random_utxo(
+				curve,
+				0,
+				*amount,
+				Some(i as u64),
+				rng,
+			)
+			.unwrap()
+		})
+		.collect::<Vec<_>>();
+	let mut in_leaves = BTreeMap::new();
+	in_leaves.insert(
+		0,
+		notes
+			.iter()
+			.map(|x| x.commitment.into_repr().to_bytes_be())
+			.collect(),
+	);
+
+	let padded =
+		VAnchorR1CSProver_Bn254_Poseidon_30_16_2::pad_in_utxos(curve, 0, notes, rng).unwrap();
+	let paths = VAnchorR1CSProver_Bn254_Poseidon_30_16_2::create_in_paths(
+		&tree_hasher,
+		&padded,
+		&in_leaves,
+		&DEFAULT_LEAF,
+	)
+	.unwrap();
+	for (utxo, path) in padded[3..].iter().zip(&paths[3..]) {
+		let root = path.calculate_root(&utxo.commitment, &tree_hasher).unwrap();
+		let index = path
+			.get_index(&root, &utxo.commitment, &tree_hasher)
+			.unwrap();
+		assert_eq!(index, BnFr::from(utxo.get_index().unwrap()));
+	}
+}
@@ -0,0 +1,595 @@
+use crate::{
+	common::*,
+	keypair::MultisigKeypair,
+	public_inputs::{PublicInputs, VAnchorPublicInputs},
+	utxo::{MultisigUtxo, Utxo},
+};
+use ark_crypto_primitives::Error;
+use ark_ec::PairingEngine;
+use ark_ed_on_bn254::{Fq, Fr};
+use ark_ff::{BigInteger, PrimeField, Zero};
+use ark_std::{
+	collections::{btree_map::Entry, BTreeMap},
+	marker::PhantomData,
+	rand::{CryptoRng, Rng, RngCore},
+	vec::Vec,
+	UniformRand,
+};
+use arkworks_native_gadgets::{
+	eddsa::{base8, to_circom_coordinates, PublicKey, SecretKey, Signature},
+	merkle_tree::Path,
+	poseidon::{FieldHasher, Poseidon},
+};
+use arkworks_r1cs_circuits::vanchor_multisig::VAnchorMultisigCircuit;
+use arkworks_r1cs_gadgets::poseidon::PoseidonGadget;
+use arkworks_utils::Curve;
+
+#[cfg(test)]
+mod tests;
+
+#[derive(Debug)]
+pub enum VAnchorMultisigError {
+	InvalidInputChainId,
+	InvalidSignature,
+	KeyMismatch,
+	MissingLeaves,
+	NotEnoughSignatures,
+	SignerCountMismatch,
+	UnknownSigner,
+}
+
+impl core::fmt::Display for VAnchorMultisigError {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		let msg = match self {
+			Self::InvalidInputChainId => ark_std::format!("Invalid input chain ID"),
+			Self::InvalidSignature => ark_std::format!("Signature is not valid for the signer"),
+			Self::KeyMismatch => ark_std::format!("Utxos are owned by different keypairs"),
+			Self::MissingLeaves => ark_std::format!("No leaves for the chain of an input"),
+			Self::NotEnoughSignatures => ark_std::format!("Fewer signatures than the threshold"),
+			Self::SignerCountMismatch => {
+				ark_std::format!("Keypair has another number of signers than the circuit")
+			}
+			Self::UnknownSigner => ark_std::format!("Signer is not part of the keypair"),
+		};
+		write!(f, "{}", msg)
+	}
+}
+
+impl ark_std::error::Error for VAnchorMultisigError {}
+
+pub type PoseidonVAnchorMultisigCircuit<
+	const HEIGHT: usize,
+	const INS: usize,
+	const OUTS: usize,
+	const ANCHOR_CT: usize,
+	const SIGNERS: usize,
+> = VAnchorMultisigCircuit<PoseidonGadget<Fq>, HEIGHT, INS, OUTS, ANCHOR_CT, SIGNERS>;
+
+/// Prover for VAnchor transactions spending the Utxos of a `MultisigKeypair`
+/// with `SIGNERS` signers. The circuit is over the base field of Baby Jubjub,
+/// so the pairing engine must be BN254.
+///
+/// Spending goes as follows:
+/// * the co-signers agree on the transaction, compute its message with
+///   `create_message` and each one calls `sign` with their own EdDSA key,
+/// * the prover collects the signatures with `add_signature`, which checks
+///   them, starting from `SIGNERS` empty slots,
+/// * once `threshold` slots are filled, the prover calls `create_proof`.
+pub struct VAnchorMultisigR1CSProver<
+	E: PairingEngine<Fr = Fq>,
+	const HEIGHT: usize,
+	const ANCHOR_CT: usize,
+	const INS: usize,
+	const OUTS: usize,
+	const SIGNERS: usize,
+> {
+	engine: PhantomData<E>,
+}
+
+impl<
+		E: PairingEngine<Fr = Fq>,
+		const HEIGHT: usize,
+		const ANCHOR_CT: usize,
+		const INS: usize,
+		const OUTS: usize,
+		const SIGNERS: usize,
+	> VAnchorMultisigR1CSProver<E, HEIGHT, ANCHOR_CT, INS, OUTS, SIGNERS>
+{
+	/// For creating the keypair of `threshold`-of-`SIGNERS` signatures, with a
+	/// nullifier key already generated
+	pub fn create_keypair(
+		curve: Curve,
+		signers: Vec<PublicKey>,
+		threshold: u64,
+		nullifier_key: Vec<u8>,
+	) -> Result<MultisigKeypair, Error> {
+		if signers.len() != SIGNERS {
+			return Err(VAnchorMultisigError::SignerCountMismatch.into());
+		}
+		let params4 = setup_params::<Fq>(curve, 5, 4);
+		let nullifier_hasher = Poseidon::<Fq> { params: params4 };
+
+		MultisigKeypair::new(
+			signers,
+			threshold,
+			Fq::from_be_bytes_mod_order(&nullifier_key),
+			&nullifier_hasher,
+		)
+	}
+
+	/// For creating the keypair and generating the nullifier key
+	pub fn create_random_keypair<R: RngCore + CryptoRng>(
+		curve: Curve,
+		signers: Vec<PublicKey>,
+		threshold: u64,
+		rng: &mut R,
+	) -> Result<MultisigKeypair, Error> {
+		let nullifier_key = Fq::rand(rng).into_repr().to_bytes_be();
+		Self::create_keypair(curve, signers, threshold, nullifier_key)
+	}
+
+	/// For creating UTXO from with secrets already generated
+	pub fn create_utxo(
+		curve: Curve,
+		chain_id: u64,
+		amount: u128,
+		index: Option<u64>,
+		keypair: MultisigKeypair,
+		blinding: Vec<u8>,
+	) -> Result<MultisigUtxo, Error> {
+		let params5 = setup_params::<Fq>(curve, 5, 5);
+		let leaf_hasher = Poseidon::<Fq> { params: params5 };
+
+		MultisigUtxo::new(
+			chain_id,
+			Fq::from(amount),
+			index,
+			keypair,
+			Fq::from_be_bytes_mod_order(&blinding),
+			&leaf_hasher,
+		)
+	}
+
+	/// For creating UTXO and generating secrets
+	pub fn create_random_utxo<R: RngCore + CryptoRng>(
+		curve: Curve,
+		chain_id: u64,
+		amount: u128,
+		index: Option<u64>,
+		keypair: MultisigKeypair,
+		rng: &mut R,
+	) -> Result<MultisigUtxo, Error> {
+		let params5 = setup_params::<Fq>(curve, 5, 5);
+		let leaf_hasher = Poseidon::<Fq> { params: params5 };
+
+		MultisigUtxo::new(
+			chain_id,
+			Fq::from(amount),
+			index,
+			keypair,
+			Fq::rand(rng),
+			&leaf_hasher,
+		)
+	}
+
+	/// Computes the message the signers sign, which commits to the external
+	/// data, the input nullifiers, the output commitments and the public
+	/// amount of the transaction, as the circuit does
+	pub fn create_message(
+		curve: Curve,
+		public_amount: i128,
+		ext_data_hash: Vec<u8>,
+		in_utxos: &[MultisigUtxo; INS],
+		out_utxos: &[Utxo<Fq>; OUTS],
+	) -> Result<Vec<u8>, Error> {
+		let params4 = setup_params::<Fq>(curve, 5, 4);
+		let nullifier_hasher = Poseidon::<Fq> { params: params4 };
+
+		let in_nullifiers = in_utxos
+			.iter()
+			.map(|x| x.calculate_nullifier(&nullifier_hasher))
+			.collect::<Result<Vec<Fq>, Error>>()?;
+		let out_commitments = out_utxos.iter().map(|x| x.commitment).collect::<Vec<Fq>>();
+		let msg = Self::transaction_message(
+			Fq::from(public_amount),
+			Fq::from_be_bytes_mod_order(&ext_data_hash),
+			&in_nullifiers,
+			&out_commitments,
+			&nullifier_hasher,
+		)?;
+		Ok(msg.into_repr().to_bytes_be())
+	}
+
+	// Folds `acc = Hash(acc, a, b)` from `acc = 0` over the public inputs of the
+	// transaction, padded with a zero to an even length
+	fn transaction_message(
+		public_amount: Fq,
+		ext_data_hash: Fq,
+		in_nullifiers: &[Fq],
+		out_commitments: &[Fq],
+		nullifier_hasher: &Poseidon<Fq>,
+	) -> Result<Fq, Error> {
+		let mut elements = Vec::with_capacity(INS + OUTS + 3);
+		elements.push(ext_data_hash);
+		elements.extend_from_slice(in_nullifiers);
+		elements.extend_from_slice(out_commitments);
+		elements.push(public_amount);
+		if elements.len() % 2 == 1 {
+			elements.push(Fq::zero());
+		}
+
+		let mut acc = Fq::zero();
+		for pair in elements.chunks(2) {
+			acc = nullifier_hasher.hash(&[acc, pair[0], pair[1]])?;
+		}
+		Ok(acc)
+	}
+
+	/// Signs the message of a transaction, see `create_message`, run by each
+	/// co-signer with its own key
+	pub fn sign(
+		curve: Curve,
+		secret_key: &SecretKey,
+		message: Vec<u8>,
+	) -> Result<Signature, Error> {
+		let params6 = setup_params::<Fq>(curve, 5, 6);
+		let signature_hasher = Poseidon::<Fq> { params: params6 };
+		secret_key.sign(Fq::from_be_bytes_mod_order(&message), &signature_hasher)
+	}
+
+	/// Checks the signature of a co-signer over the `message` of the
+	/// transaction and puts it in the slot of the signer in `signatures`, which
+	/// holds one slot per signer of the keypair
+	pub fn add_signature(
+		curve: Curve,
+		keypair: &MultisigKeypair,
+		signatures: &mut [Option<Signature>],
+		signer: &PublicKey,
+		signature: Signature,
+		message: Vec<u8>,
+	) -> Result<(), Error> {
+		let params6 = setup_params::<Fq>(curve, 5, 6);
+		let signature_hasher = Poseidon::<Fq> { params: params6 };
+
+		let slot = keypair
+			.signer_index(signer)
+			.ok_or(VAnchorMultisigError::UnknownSigner)?;
+		let msg = Fq::from_be_bytes_mod_order(&message);
+		if !signer.verify(msg, &signature, &signature_hasher)? {
+			return Err(VAnchorMultisigError::InvalidSignature.into());
+		}
+		signatures[slot] = Some(signature);
+		Ok(())
+	}
+
+	#[allow(dead_code)]
+	pub fn setup_random_circuit<R: RngCore + CryptoRng>(
+		curve: Curve,
+		default_leaf: [u8; 32],
+		amount_bits: usize,
+		rng: &mut R,
+	) -> Result<PoseidonVAnchorMultisigCircuit<HEIGHT, INS, OUTS, ANCHOR_CT, SIGNERS>, Error> {
+		// Initialize hashers
+		let params2 = setup_params::<Fq>(curve, 5, 2);
+		let params3 = setup_params::<Fq>(curve, 5, 3);
+		let params4 = setup_params::<Fq>(curve, 5, 4);
+		let params5 = setup_params::<Fq>(curve, 5, 5);
+		let params6 = setup_params::<Fq>(curve, 5, 6);
+		let keypair_hasher = Poseidon::<Fq> { params: params2 };
+		let tree_hasher = Poseidon::<Fq> { params: params3 };
+		let nullifier_hasher = Poseidon::<Fq> { params: params4 };
+		let leaf_hasher = Poseidon::<Fq> { params: params5 };
+		let signature_hasher = Poseidon::<Fq> { params: params6 };
+
+		let chain_id: u64 = rng.gen();
+		let ext_data_hash = Fq::rand(rng);
+		// Every signer signs
+		let secret_keys: Vec<SecretKey> = (0..SIGNERS).map(|_| SecretKey::rand(rng)).collect();
+		let signers = secret_keys.iter().map(|x| x.public_key()).collect();
+		let keypair =
+			MultisigKeypair::new(signers, SIGNERS as u64, Fq::rand(rng), &nullifier_hasher)?;
+
+		// Utxos with a zero amount, which don't need to be in the root set
+		let mut in_utxos = Vec::with_capacity(INS);
+		for _ in 0..INS {
+			in_utxos.push(MultisigUtxo::new(
+				chain_id,
+				Fq::zero(),
+				Some(0),
+				keypair.clone(),
+				Fq::rand(rng),
+				&leaf_hasher,
+			)?);
+		}
+		let mut out_utxos = Vec::with_capacity(OUTS);
+		for _ in 0..OUTS {
+			out_utxos.push(Utxo::new(
+				chain_id,
+				Fq::zero(),
+				None,
+				None,
+				None,
+				&keypair_hasher,
+				&leaf_hasher,
+				rng,
+			)?);
+		}
+
+		let in_indices = [Fq::zero(); INS];
+		let mut in_paths = Vec::with_capacity(INS);
+		let mut in_root_set = [Fq::zero(); ANCHOR_CT];
+		for utxo in in_utxos.iter() {
+			let (tree, path) = setup_tree_and_create_path::<Fq, Poseidon<Fq>, HEIGHT>(
+				&tree_hasher,
+				&[utxo.commitment],
+				0,
+				&default_leaf,
+			)?;
+			in_root_set[0] = tree.root();
+			in_paths.push(path);
+		}
+		let in_utxos: [MultisigUtxo; INS] = in_utxos
+			.try_into()
+			.unwrap_or_else(|_| unreachable!("one utxo per input"));
+		let out_utxos: [Utxo<Fq>; OUTS] = out_utxos
+			.try_into()
+			.unwrap_or_else(|_| unreachable!("one utxo per output"));
+
+		let in_nullifiers = in_utxos
+			.iter()
+			.map(|x| x.calculate_nullifier(&nullifier_hasher))
+			.collect::<Result<Vec<Fq>, Error>>()?;
+		let out_commitments = out_utxos.iter().map(|x| x.commitment).collect::<Vec<Fq>>();
+		let msg = Self::transaction_message(
+			Fq::zero(),
+			ext_data_hash,
+			&in_nullifiers,
+			&out_commitments,
+			&nullifier_hasher,
+		)?;
+		let signatures = secret_keys
+			.iter()
+			.map(|x| x.sign(msg, &signature_hasher).map(Some))
+			.collect::<Result<Vec<_>, Error>>()?;
+
+		Self::setup_circuit(
+			Fq::from(chain_id),
+			Fq::zero(),
+			ext_data_hash,
+			in_utxos,
+			in_indices,
+			in_paths,
+			in_root_set,
+			out_utxos,
+			signatures,
+			tree_hasher,
+			leaf_hasher,
+			nullifier_hasher,
+			signature_hasher,
+			amount_bits,
+		)
+	}
+
+	/// Sets up the circuit, checking that every input is owned by the same
+	/// keypair and that enough of its signers signed. `signatures` holds one
+	/// slot per signer of the keypair.
+	#[allow(clippy::too_many_arguments)]
+	pub fn setup_circuit(
+		chain_id: Fq,
+		public_amount: Fq,
+		ext_data_hash: Fq,
+		// Input transactions
+		in_utxos: [MultisigUtxo; INS],
+		// Data related to tree
+		in_indices: [Fq; INS],
+		in_paths: Vec<Path<Fq, Poseidon<Fq>, HEIGHT>>,
+		public_root_set: [Fq; ANCHOR_CT],
+		// Output transactions
+		out_utxos: [Utxo<Fq>; OUTS],
+		signatures: Vec<Option<Signature>>,
+		tree_hasher: Poseidon<Fq>,
+		leaf_hasher: Poseidon<Fq>,
+		nullifier_hasher: Poseidon<Fq>,
+		signature_hasher: Poseidon<Fq>,
+		amount_bits: usize,
+	) -> Result<PoseidonVAnchorMultisigCircuit<HEIGHT, INS, OUTS, ANCHOR_CT, SIGNERS>, Error> {
+		let keypair = in_utxos[0].keypair.clone();
+		if in_utxos
+			.iter()
+			.any(|x| x.keypair.public_key != keypair.public_key)
+		{
+			return Err(VAnchorMultisigError::KeyMismatch.into());
+		}
+		if keypair.signers.len() != SIGNERS || signatures.len() != SIGNERS {
+			return Err(VAnchorMultisigError::SignerCountMismatch.into());
+		}
+		let num_signatures = signatures.iter().filter(|x| x.is_some()).count();
+		if (num_signatures as u64) < keypair.threshold {
+			return Err(VAnchorMultisigError::NotEnoughSignatures.into());
+		}
+
+		// The signature of a signer that didn't sign isn't checked, but still
+		// has to be a point of the curve
+		let (r8_x, r8_y) = to_circom_coordinates(&base8());
+		let unsigned = Signature {
+			r8_x,
+			r8_y,
+			s: Fr::zero(),
+		};
+
+		let in_nullifiers = in_utxos
+			.iter()
+			.map(|x| x.calculate_nullifier(&nullifier_hasher))
+			.collect::<Result<Vec<Fq>, Error>>()?;
+
+		let circuit = PoseidonVAnchorMultisigCircuit::<HEIGHT, INS, OUTS, ANCHOR_CT, SIGNERS>::new(
+			public_amount,
+			ext_data_hash,
+			in_utxos.iter().map(|x| x.amount).collect(),
+			in_utxos.iter().map(|x| x.blinding).collect(),
+			keypair.nullifier_key.unwrap_or_default(),
+			chain_id,
+			public_root_set,
+			keypair.signers,
+			Fq::from(keypair.threshold),
+			signatures.iter().map(|x| x.is_some()).collect(),
+			signatures.iter().map(|x| x.unwrap_or(unsigned)).collect(),
+			in_paths,
+			in_indices.to_vec(),
+			in_nullifiers,
+			out_utxos.iter().map(|x| x.commitment).collect(),
+			out_utxos.iter().map(|x| x.amount).collect(),
+			out_utxos.iter().map(|x| x.blinding).collect(),
+			out_utxos.iter().map(|x| x.chain_id).collect(),
+			out_utxos.iter().map(|x| x.keypair.public_key).collect(),
+			tree_hasher,
+			leaf_hasher,
+			nullifier_hasher,
+			signature_hasher,
+			amount_bits,
+		);
+
+		Ok(circuit)
+	}
+
+	pub fn construct_public_inputs(
+		chain_id: Fq,
+		public_amount: Fq,
+		roots: [Fq; ANCHOR_CT],
+		nullifiers: [Fq; INS],
+		commitments: [Fq; OUTS],
+		ext_data_hash: Fq,
+	) -> Vec<Fq> {
+		VAnchorPublicInputs::<Fq, ANCHOR_CT, INS, OUTS> {
+			public_amount,
+			ext_data_hash,
+			nullifiers,
+			commitments,
+			chain_id,
+			roots,
+		}
+		.to_field_vec()
+	}
+
+	/// For making proofs with the signatures collected by `add_signature`, the
+	/// root set may hold up to ANCHOR_CT roots
+	#[allow(clippy::too_many_arguments)]
+	pub fn create_proof<R: RngCore + CryptoRng>(
+		curve: Curve,
+		chain_id: u64,
+		// External data
+		public_amount: i128,
+		ext_data_hash: Vec<u8>,
+		public_root_set: Vec<Vec<u8>>,
+		in_indices: [u64; INS],
+		in_leaves: BTreeMap<u64, Vec<Vec<u8>>>,
+		// Input transactions
+		in_utxos: [MultisigUtxo; INS],
+		// Output transactions
+		out_utxos: [Utxo<Fq>; OUTS],
+		signatures: Vec<Option<Signature>>,
+		amount_bits: usize,
+		pk: Vec<u8>,
+		default_leaf: [u8; 32],
+		rng: &mut R,
+	) -> Result<VAnchorProof, Error> {
+		// Initialize hashers
+		let params3 = setup_params::<Fq>(curve, 5, 3);
+		let params4 = setup_params::<Fq>(curve, 5, 4);
+		let params5 = setup_params::<Fq>(curve, 5, 5);
+		let params6 = setup_params::<Fq>(curve, 5, 6);
+		let tree_hasher = Poseidon::<Fq> { params: params3 };
+		let nullifier_hasher = Poseidon::<Fq> { params: params4 };
+		let leaf_hasher = Poseidon::<Fq> { params: params5 };
+		let signature_hasher = Poseidon::<Fq> { params: params6 };
+
+		// Cast as field elements
+		let chain_id_elt = Fq::from(chain_id);
+		let public_amount_elt = Fq::from(public_amount);
+		let ext_data_hash_elt = Fq::from_be_bytes_mod_order(&ext_data_hash);
+		let public_root_set_elt = pad_root_set::<Fq, ANCHOR_CT>(&public_root_set)?;
+
+		// Throw an error if chain IDs don't match intended spending chain.
+		if in_utxos.iter().any(|utxo| utxo.chain_id_raw != chain_id) {
+			return Err(VAnchorMultisigError::InvalidInputChainId.into());
+		}
+
+		// Generate the paths for each UTXO
+		let mut trees = BTreeMap::<u64, SMT<Fq, Poseidon<Fq>, HEIGHT>>::new();
+		let mut in_paths = Vec::with_capacity(INS);
+		for utxo in in_utxos.iter() {
+			let index = utxo.index.unwrap_or_default();
+			// A Utxo with a zero amount isn't checked to be in the root set, so its
+			// path is taken from a tree holding only that Utxo
+			let path = if utxo.amount == Fq::zero() {
+				let (_, path) = setup_tree_and_create_path::<Fq, Poseidon<Fq>, HEIGHT>(
+					&tree_hasher,
+					&[utxo.commitment],
+					index,
+					&default_leaf,
+				)?;
+				path
+			} else {
+				let tree = match trees.entry(utxo.chain_id_raw) {
+					Entry::Occupied(entry) => entry.into_mut(),
+					Entry::Vacant(entry) => {
+						let leaves = in_leaves
+							.get(&utxo.chain_id_raw)
+							.ok_or(VAnchorMultisigError::MissingLeaves)?
+							.iter()
+							.map(|l| Fq::from_be_bytes_mod_order(l))
+							.collect::<Vec<Fq>>();
+						entry.insert(create_merkle_tree::<Fq, Poseidon<Fq>, HEIGHT>(
+							&tree_hasher,
+							&leaves,
+							&default_leaf,
+						))
+					}
+				};
+				tree.generate_membership_proof(index)
+			};
+			in_paths.push(path);
+		}
+
+		let mut input_nullifiers = [Fq::zero(); INS];
+		for (nullifier, utxo) in input_nullifiers.iter_mut().zip(in_utxos.iter()) {
+			*nullifier = utxo.calculate_nullifier(&nullifier_hasher)?;
+		}
+		let output_commitments = out_utxos.clone().map(|utxo| utxo.commitment);
+
+		// Get the circuit
+		let circuit = Self::setup_circuit(
+			chain_id_elt,
+			public_amount_elt,
+			ext_data_hash_elt,
+			in_utxos,
+			in_indices.map(Fq::from),
+			in_paths,
+			public_root_set_elt,
+			out_utxos,
+			signatures,
+			tree_hasher,
+			leaf_hasher,
+			nullifier_hasher,
+			signature_hasher,
+			amount_bits,
+		)?;
+
+		let proof = prove_unchecked::<E, _, _>(circuit, &pk, rng)?;
+
+		let public_inputs = VAnchorPublicInputs::<Fq, ANCHOR_CT, INS, OUTS> {
+			public_amount: public_amount_elt,
+			ext_data_hash: ext_data_hash_elt,
+			nullifiers: input_nullifiers,
+			commitments: output_commitments,
+			chain_id: chain_id_elt,
+			roots: public_root_set_elt,
+		};
+		let public_inputs_raw = public_inputs.to_bytes_vec();
+
+		Ok(VAnchorProof {
+			public_inputs_raw,
+			proof,
+		})
+	}
+}
@@ -0,0 +1,401 @@
+use super::{setup_params, PoseidonVAnchorMultisigCircuit, VAnchorMultisigR1CSProver};
+use crate::{
+	common::*,
+	keypair::MultisigKeypair,
+	utxo::{MultisigUtxo, Utxo},
+};
+use ark_bn254::{Bn254, Fr as BnFr};
+use ark_ff::{BigInteger, PrimeField, UniformRand};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystem};
+use ark_std::{collections::BTreeMap, rand::rngs::StdRng, test_rng, vec, vec::Vec, Zero};
+use arkworks_native_gadgets::{
+	eddsa::{SecretKey, Signature},
+	poseidon::Poseidon,
+};
+use arkworks_utils::Curve;
+
+const HEIGHT: usize = 30;
+const ANCHOR_CT: usize = 2;
+const INS: usize = 2;
+const OUTS: usize = 2;
+const SIGNERS: usize = 3;
+const THRESHOLD: u64 = 2;
+const AMOUNT_BITS: usize = 248;
+const DEFAULT_LEAF: [u8; 32] = [0u8; 32];
+
+#[allow(non_camel_case_types)]
+type VAnchorMultisigR1CSProver_Bn254_Poseidon_30_2_2_3 =
+	VAnchorMultisigR1CSProver<Bn254, HEIGHT, ANCHOR_CT, INS, OUTS, SIGNERS>;
+
+// A 2-of-3 keypair with the keys of its signers, and two of its inputs of 5
+// on chain 0 with the leaves of their tree and its root
+#[allow(clippy::type_complexity)]
+fn setup_inputs(
+	rng: &mut StdRng,
+) -> (
+	Vec<SecretKey>,
+	MultisigKeypair,
+	[MultisigUtxo; INS],
+	BTreeMap<u64, Vec<Vec<u8>>>,
+	Vec<Vec<u8>>,
+) {
+	let curve = Curve::Bn254;
+	let tree_hasher = Poseidon::<BnFr>::new(setup_params(curve, 5, 3));
+
+	let secret_keys: Vec<SecretKey> = (0..SIGNERS).map(|_| SecretKey::rand(rng)).collect();
+	let keypair = VAnchorMultisigR1CSProver_Bn254_Poseidon_30_2_2_3::create_random_keypair(
+		curve,
+		secret_keys.iter().map(|x| x.public_key()).collect(),
+		THRESHOLD,
+		rng,
+	)
+	.unwrap();
+
+	let in_utxos = [0u64, 1].map(|index| {
+		VAnchorMultisigR1CSProver_Bn254_Poseidon_30_2_2_3::create_random_utxo(
+			curve,
+			0,
+			5,
+			Some(index),
+			keypair.clone(),
+			rng,
+		)
+		.unwrap()
+	});
+	let leaves = in_utxos.iter().map(|x| x.commitment).collect::<Vec<_>>();
+	let tree =
+		create_merkle_tree::<BnFr, Poseidon<BnFr>, HEIGHT>(&tree_hasher, &leaves, &DEFAULT_LEAF);
+
+	let mut in_leaves = BTreeMap::new();
+	in_leaves.insert(
+		0,
+		leaves.iter().map(|x| x.into_repr().to_bytes_be()).collect(),
+	);
+	let root_set = vec![tree.root().into_repr().to_bytes_be()];
+	(secret_keys, keypair, in_utxos, in_leaves, root_set)
+}
+
+// Collects the signatures of the signers at `slots` over the message of a
+// transaction
+fn collect_signatures(
+	secret_keys: &[SecretKey],
+	keypair: &MultisigKeypair,
+	slots: &[usize],
+	message: &[u8],
+) -> Vec<Option<Signature>> {
+	let curve = Curve::Bn254;
+	let mut signatures = vec![None; SIGNERS];
+	for &slot in slots {
+		// Each co-signer signs on its own side and only shares the signature
+		let signature = VAnchorMultisigR1CSProver_Bn254_Poseidon_30_2_2_3::sign(
+			curve,
+			&secret_keys[slot],
+			message.to_vec(),
+		)
+		.unwrap();
+		VAnchorMultisigR1CSProver_Bn254_Poseidon_30_2_2_3::add_signature(
+			curve,
+			keypair,
+			&mut signatures,
+			&secret_keys[slot].public_key(),
+			signature,
+			message.to_vec(),
+		)
+		.unwrap();
+	}
+	signatures
+}
+
+fn setup_outputs(keypair: &MultisigKeypair, rng: &mut StdRng) -> [Utxo<BnFr>; OUTS] {
+	let curve = Curve::Bn254;
+	let keypair_hasher = Poseidon::<BnFr>::new(setup_params(curve, 5, 2));
+	let leaf_hasher = Poseidon::<BnFr>::new(setup_params(curve, 5, 5));
+	// Withdraw 4 and keep the change under the same keypair
+	let change = Utxo::new_with_public(
+		0,
+		BnFr::from(6u64),
+		None,
+		keypair.public_key,
+		BnFr::rand(rng),
+		&leaf_hasher,
+	)
+	.unwrap();
+	let empty = Utxo::new(
+		0,
+		BnFr::zero(),
+		None,
+		None,
+		None,
+		&keypair_hasher,
+		&leaf_hasher,
+		rng,
+	)
+	.unwrap();
+	[change, empty]
+}
+
+#[test]
+fn should_create_proof_with_threshold_signatures() {
+	let rng = &mut test_rng();
+	let curve = Curve::Bn254;
+	let leaf_hasher = Poseidon::<BnFr>::new(setup_params(curve, 5, 5));
+
+	let random_circuit = VAnchorMultisigR1CSProver_Bn254_Poseidon_30_2_2_3::setup_random_circuit(
+		curve,
+		DEFAULT_LEAF,
+		AMOUNT_BITS,
+		rng,
+	)
+	.unwrap();
+	let (pk, vk) = setup_keys_unchecked::<Bn254, _, _>(random_circuit, rng).unwrap();
+
+	let (secret_keys, keypair, in_utxos, in_leaves, root_set) = setup_inputs(rng);
+	let out_utxos = setup_outputs(&keypair, rng);
+	let ext_data_hash = BnFr::rand(rng).into_repr().to_bytes_be();
+	let message = VAnchorMultisigR1CSProver_Bn254_Poseidon_30_2_2_3::create_message(
+		curve,
+		-4,
+		ext_data_hash.clone(),
+		&in_utxos,
+		&out_utxos,
+	)
+	.unwrap();
+	// The first and the last signers sign
+	let signatures = collect_signatures(&secret_keys, &keypair, &[0, 2], &message);
+
+	let proof = VAnchorMultisigR1CSProver_Bn254_Poseidon_30_2_2_3::create_proof(
+		curve,
+		0,
+		-4,
+		ext_data_hash,
+		root_set,
+		[0, 1],
+		in_leaves,
+		in_utxos,
+		out_utxos.clone(),
+		signatures,
+		AMOUNT_BITS,
+		pk,
+		DEFAULT_LEAF,
+		rng,
+	)
+	.unwrap();
+
+	let res = verify_unchecked_raw::<Bn254>(&proof.public_inputs_raw, &vk, &proof.proof).unwrap();
+	assert!(res);
+
+	// The change can be spent again by the signers
+	let change = MultisigUtxo::new(
+		0,
+		out_utxos[0].amount,
+		None,
+		keypair,
+		out_utxos[0].blinding,
+		&leaf_hasher,
+	)
+	.unwrap();
+	assert_eq!(change.commitment, out_utxos[0].commitment);
+}
+
+#[test]
+fn should_reject_missing_or_invalid_signatures() {
+	let rng = &mut test_rng();
+	let curve = Curve::Bn254;
+
+	let (secret_keys, keypair, in_utxos, in_leaves, root_set) = setup_inputs(rng);
+	let out_utxos = setup_outputs(&keypair, rng);
+	let ext_data_hash = BnFr::rand(rng).into_repr().to_bytes_be();
+	let message = VAnchorMultisigR1CSProver_Bn254_Poseidon_30_2_2_3::create_message(
+		curve,
+		-4,
+		ext_data_hash.clone(),
+		&in_utxos,
+		&out_utxos,
+	)
+	.unwrap();
+
+	// A signature of another message, or from a key that isn't a signer, isn't
+	// collected
+	let mut signatures = vec![None; SIGNERS];
+	let other_message_signature = VAnchorMultisigR1CSProver_Bn254_Poseidon_30_2_2_3::sign(
+		curve,
+		&secret_keys[0],
+		BnFr::rand(rng).into_repr().to_bytes_be(),
+	)
+	.unwrap();
+	let res = VAnchorMultisigR1CSProver_Bn254_Poseidon_30_2_2_3::add_signature(
+		curve,
+		&keypair,
+		&mut signatures,
+		&secret_keys[0].public_key(),
+		other_message_signature,
+		message.clone(),
+	);
+	assert!(res.is_err());
+
+	let outsider = SecretKey::rand(rng);
+	let outsider_signature =
+		VAnchorMultisigR1CSProver_Bn254_Poseidon_30_2_2_3::sign(curve, &outsider, message.clone())
+			.unwrap();
+	let res = VAnchorMultisigR1CSProver_Bn254_Poseidon_30_2_2_3::add_signature(
+		curve,
+		&keypair,
+		&mut signatures,
+		&outsider.public_key(),
+		outsider_signature,
+		message.clone(),
+	);
+	assert!(res.is_err());
+
+	// A single signature is below the threshold
+	let signatures = collect_signatures(&secret_keys, &keypair, &[1], &message);
+	let res = VAnchorMultisigR1CSProver_Bn254_Poseidon_30_2_2_3::create_proof(
+		curve,
+		0,
+		-4,
+		ext_data_hash,
+		root_set,
+		[0, 1],
+		in_leaves,
+		in_utxos,
+		out_utxos,
+		signatures,
+		AMOUNT_BITS,
+		vec![],
+		DEFAULT_LEAF,
+		rng,
+	);
+	assert!(res.is_err());
+}
+
+// Sets up the circuit spending the inputs of `setup_inputs` into `out_utxos`,
+// with the signatures of the first `THRESHOLD` signers over `message`
+fn setup_signed_circuit(
+	secret_keys: &[SecretKey],
+	in_utxos: &[MultisigUtxo; INS],
+	out_utxos: &[Utxo<BnFr>; OUTS],
+	ext_data_hash: BnFr,
+	message: BnFr,
+) -> PoseidonVAnchorMultisigCircuit<HEIGHT, INS, OUTS, ANCHOR_CT, SIGNERS> {
+	let curve = Curve::Bn254;
+	let tree_hasher = Poseidon::<BnFr>::new(setup_params(curve, 5, 3));
+	let nullifier_hasher = Poseidon::<BnFr>::new(setup_params(curve, 5, 4));
+	let leaf_hasher = Poseidon::<BnFr>::new(setup_params(curve, 5, 5));
+	let signature_hasher = Poseidon::<BnFr>::new(setup_params(curve, 5, 6));
+
+	let leaves = in_utxos.iter().map(|x| x.commitment).collect::<Vec<_>>();
+	let (tree, _) = setup_tree_and_create_path::<BnFr, Poseidon<BnFr>, HEIGHT>(
+		&tree_hasher,
+		&leaves,
+		0,
+		&DEFAULT_LEAF,
+	)
+	.unwrap();
+	let paths = (0..INS)
+		.map(|i| tree.generate_membership_proof(i as u64))
+		.collect::<Vec<_>>();
+
+	// Bypass the signature checks of the prover by signing directly
+	let signatures = secret_keys
+		.iter()
+		.take(THRESHOLD as usize)
+		.map(|x| Some(x.sign(message, &signature_hasher).unwrap()))
+		.chain([None])
+		.collect();
+	VAnchorMultisigR1CSProver_Bn254_Poseidon_30_2_2_3::setup_circuit(
+		BnFr::zero(),
+		-BnFr::from(4u64),
+		ext_data_hash,
+		in_utxos.clone(),
+		[BnFr::from(0u64), BnFr::from(1u64)],
+		paths,
+		[tree.root(), BnFr::zero()],
+		out_utxos.clone(),
+		signatures,
+		tree_hasher,
+		leaf_hasher,
+		nullifier_hasher,
+		signature_hasher,
+		AMOUNT_BITS,
+	)
+	.unwrap()
+}
+
+#[test]
+fn should_not_satisfy_circuit_with_signatures_of_other_data() {
+	let rng = &mut test_rng();
+	let curve = Curve::Bn254;
+
+	let (secret_keys, keypair, in_utxos, ..) = setup_inputs(rng);
+	let out_utxos = setup_outputs(&keypair, rng);
+
+	let ext_data_hash = BnFr::rand(rng);
+	let message = VAnchorMultisigR1CSProver_Bn254_Poseidon_30_2_2_3::create_message(
+		curve,
+		-4,
+		ext_data_hash.into_repr().to_bytes_be(),
+		&in_utxos,
+		&out_utxos,
+	)
+	.unwrap();
+	let message = BnFr::from_be_bytes_mod_order(&message);
+	// Signing the external data alone doesn't authorize the transaction
+	for (signed_message, is_satisfied) in [(message, true), (ext_data_hash, false)] {
+		let circuit = setup_signed_circuit(
+			&secret_keys,
+			&in_utxos,
+			&out_utxos,
+			ext_data_hash,
+			signed_message,
+		);
+
+		let cs = ConstraintSystem::<BnFr>::new_ref();
+		circuit.generate_constraints(cs.clone()).unwrap();
+		assert_eq!(cs.is_satisfied().unwrap(), is_satisfied);
+	}
+}
+
+#[test]
+fn should_not_satisfy_circuit_with_other_outputs() {
+	let rng = &mut test_rng();
+	let curve = Curve::Bn254;
+	let leaf_hasher = Poseidon::<BnFr>::new(setup_params(curve, 5, 5));
+
+	let (secret_keys, keypair, in_utxos, ..) = setup_inputs(rng);
+	let out_utxos = setup_outputs(&keypair, rng);
+
+	let ext_data_hash = BnFr::rand(rng);
+	let message = VAnchorMultisigR1CSProver_Bn254_Poseidon_30_2_2_3::create_message(
+		curve,
+		-4,
+		ext_data_hash.into_repr().to_bytes_be(),
+		&in_utxos,
+		&out_utxos,
+	)
+	.unwrap();
+	let message = BnFr::from_be_bytes_mod_order(&message);
+
+	// The change is redirected to another key, with the same external data and
+	// the signatures of the original transaction
+	let mut other_out_utxos = out_utxos;
+	other_out_utxos[0] = Utxo::new_with_public(
+		0,
+		BnFr::from(6u64),
+		None,
+		BnFr::rand(rng),
+		BnFr::rand(rng),
+		&leaf_hasher,
+	)
+	.unwrap();
+	let circuit = setup_signed_circuit(
+		&secret_keys,
+		&in_utxos,
+		&other_out_utxos,
+		ext_data_hash,
+		message,
+	);
+
+	let cs = ConstraintSystem::<BnFr>::new_ref();
+	circuit.generate_constraints(cs.clone()).unwrap();
+	assert!(!cs.is_satisfied().unwrap());
+}
@@ -0,0 +1,437 @@
+use crate::{
+	common::*,
+	public_inputs::{PublicInputs, TimeLockPublicInputs},
+	utxo::TimeLockedUtxo,
+};
+use ark_crypto_primitives::Error;
+use ark_ec::PairingEngine;
+use ark_ff::{PrimeField, SquareRootField, Zero};
+use ark_std::{
+	collections::{btree_map::Entry, BTreeMap},
+	marker::PhantomData,
+	rand::{CryptoRng, Rng, RngCore},
+	vec::Vec,
+	UniformRand,
+};
+use arkworks_native_gadgets::{merkle_tree::Path, poseidon::Poseidon};
+use arkworks_r1cs_circuits::vanchor_timelock::VAnchorTimeLockCircuit;
+use arkworks_r1cs_gadgets::poseidon::PoseidonGadget;
+use arkworks_utils::Curve;
+
+#[cfg(test)]
+mod tests;
+
+#[derive(Debug)]
+pub enum VAnchorTimeLockError {
+	InvalidInputChainId,
+	MissingLeaves,
+	StillLocked,
+}
+
+impl core::fmt::Display for VAnchorTimeLockError {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		let msg = match self {
+			Self::InvalidInputChainId => ark_std::format!("Invalid input chain ID"),
+			Self::MissingLeaves => ark_std::format!("No leaves for the chain of an input"),
+			Self::StillLocked => ark_std::format!("Utxo is locked at the current time"),
+		};
+		write!(f, "{}", msg)
+	}
+}
+
+impl ark_std::error::Error for VAnchorTimeLockError {}
+
+pub type PoseidonVAnchorTimeLockCircuit<
+	F,
+	const HEIGHT: usize,
+	const INS: usize,
+	const OUTS: usize,
+	const ANCHOR_CT: usize,
+> = VAnchorTimeLockCircuit<F, PoseidonGadget<F>, HEIGHT, INS, OUTS, ANCHOR_CT>;
+
+pub struct VAnchorTimeLockR1CSProver<
+	E: PairingEngine,
+	const HEIGHT: usize,
+	const ANCHOR_CT: usize,
+	const INS: usize,
+	const OUTS: usize,
+> {
+	engine: PhantomData<E>,
+}
+
+impl<
+		E: PairingEngine,
+		const HEIGHT: usize,
+		const ANCHOR_CT: usize,
+		const INS: usize,
+		const OUTS: usize,
+	> VAnchorTimeLockR1CSProver<E, HEIGHT, ANCHOR_CT, INS, OUTS>
+where
+	<E as PairingEngine>::Fr: PrimeField + SquareRootField + From<i128>,
+{
+	/// For creating UTXO from with secrets already generated
+	#[allow(clippy::too_many_arguments)]
+	pub fn create_utxo(
+		curve: Curve,
+		chain_id: u64,
+		amount: u128,
+		unlock_time: u64,
+		index: Option<u64>,
+		private_key: Vec<u8>,
+		blinding: Vec<u8>,
+	) -> Result<TimeLockedUtxo<E::Fr>, Error> {
+		// Initialize hashers
+		let params2 = setup_params::<E::Fr>(curve, 5, 2);
+		let params6 = setup_params::<E::Fr>(curve, 5, 6);
+		let keypair_hasher = Poseidon::<E::Fr> { params: params2 };
+		let leaf_hasher = Poseidon::<E::Fr> { params: params6 };
+
+		TimeLockedUtxo::new_with_privates(
+			chain_id,
+			E::Fr::from(amount),
+			unlock_time,
+			index,
+			E::Fr::from_be_bytes_mod_order(&private_key),
+			E::Fr::from_be_bytes_mod_order(&blinding),
+			&keypair_hasher,
+			&leaf_hasher,
+		)
+	}
+
+	/// For creating UTXO and generating secrets
+	pub fn create_random_utxo<R: RngCore + CryptoRng>(
+		curve: Curve,
+		chain_id: u64,
+		amount: u128,
+		unlock_time: u64,
+		index: Option<u64>,
+		rng: &mut R,
+	) -> Result<TimeLockedUtxo<E::Fr>, Error> {
+		// Initialize hashers
+		let params2 = setup_params::<E::Fr>(curve, 5, 2);
+		let params6 = setup_params::<E::Fr>(curve, 5, 6);
+		let keypair_hasher = Poseidon::<E::Fr> { params: params2 };
+		let leaf_hasher = Poseidon::<E::Fr> { params: params6 };
+
+		TimeLockedUtxo::new(
+			chain_id,
+			E::Fr::from(amount),
+			unlock_time,
+			index,
+			None,
+			None,
+			&keypair_hasher,
+			&leaf_hasher,
+			rng,
+		)
+	}
+
+	/// For creating UTXO without private key
+	#[allow(clippy::too_many_arguments)]
+	pub fn create_public_utxo(
+		curve: Curve,
+		chain_id: u64,
+		amount: u128,
+		unlock_time: u64,
+		blinding: Vec<u8>,
+		public_key: Vec<u8>,
+		index: Option<u64>,
+	) -> Result<TimeLockedUtxo<E::Fr>, Error> {
+		// Initialize hashers
+		let params6 = setup_params::<E::Fr>(curve, 5, 6);
+		let leaf_hasher = Poseidon::<E::Fr> { params: params6 };
+
+		TimeLockedUtxo::new_with_public(
+			chain_id,
+			E::Fr::from(amount),
+			unlock_time,
+			index,
+			E::Fr::from_be_bytes_mod_order(&public_key),
+			E::Fr::from_be_bytes_mod_order(&blinding),
+			&leaf_hasher,
+		)
+	}
+
+	#[allow(dead_code)]
+	pub fn setup_random_circuit<R: RngCore + CryptoRng>(
+		curve: Curve,
+		default_leaf: [u8; 32],
+		amount_bits: usize,
+		rng: &mut R,
+	) -> Result<PoseidonVAnchorTimeLockCircuit<E::Fr, HEIGHT, INS, OUTS, ANCHOR_CT>, Error> {
+		// Initialize hashers
+		let params2 = setup_params::<E::Fr>(curve, 5, 2);
+		let params3 = setup_params::<E::Fr>(curve, 5, 3);
+		let params4 = setup_params::<E::Fr>(curve, 5, 4);
+		let params6 = setup_params::<E::Fr>(curve, 5, 6);
+		let keypair_hasher = Poseidon::<E::Fr> { params: params2 };
+		let tree_hasher = Poseidon::<E::Fr> { params: params3 };
+		let nullifier_hasher = Poseidon::<E::Fr> { params: params4 };
+		let leaf_hasher = Poseidon::<E::Fr> { params: params6 };
+
+		let chain_id: u64 = rng.gen();
+		// Unlocked Utxos with a zero amount, which don't need to be in the root set
+		let mut in_utxos = Vec::with_capacity(INS);
+		for _ in 0..INS {
+			in_utxos.push(Self::create_random_utxo(
+				curve,
+				chain_id,
+				0,
+				0,
+				Some(0),
+				rng,
+			)?);
+		}
+		let mut out_utxos = Vec::with_capacity(OUTS);
+		for _ in 0..OUTS {
+			out_utxos.push(Self::create_random_utxo(curve, chain_id, 0, 0, None, rng)?);
+		}
+
+		let in_indices = [E::Fr::zero(); INS];
+		let mut in_paths = Vec::with_capacity(INS);
+		let mut in_root_set = [E::Fr::zero(); ANCHOR_CT];
+		for utxo in in_utxos.iter() {
+			let (tree, path) = setup_tree_and_create_path::<E::Fr, Poseidon<E::Fr>, HEIGHT>(
+				&tree_hasher,
+				&[utxo.commitment],
+				0,
+				&default_leaf,
+			)?;
+			in_root_set[0] = tree.root();
+			in_paths.push(path);
+		}
+		let in_utxos: [TimeLockedUtxo<E::Fr>; INS] = in_utxos
+			.try_into()
+			.unwrap_or_else(|_| unreachable!("one utxo per input"));
+		let out_utxos: [TimeLockedUtxo<E::Fr>; OUTS] = out_utxos
+			.try_into()
+			.unwrap_or_else(|_| unreachable!("one utxo per output"));
+
+		Self::setup_circuit(
+			E::Fr::from(chain_id),
+			E::Fr::zero(),
+			E::Fr::rand(rng),
+			E::Fr::zero(),
+			in_utxos,
+			in_indices,
+			in_paths,
+			in_root_set,
+			out_utxos,
+			keypair_hasher,
+			tree_hasher,
+			nullifier_hasher,
+			leaf_hasher,
+			amount_bits,
+		)
+	}
+
+	#[allow(clippy::too_many_arguments)]
+	pub fn setup_circuit(
+		chain_id: E::Fr,
+		public_amount: E::Fr,
+		ext_data_hash: E::Fr,
+		current_time: E::Fr,
+		// Input transactions
+		in_utxos: [TimeLockedUtxo<E::Fr>; INS],
+		// Data related to tree
+		in_indices: [E::Fr; INS],
+		in_paths: Vec<Path<E::Fr, Poseidon<E::Fr>, HEIGHT>>,
+		public_root_set: [E::Fr; ANCHOR_CT],
+		// Output transactions
+		out_utxos: [TimeLockedUtxo<E::Fr>; OUTS],
+		keypair_hasher: Poseidon<E::Fr>,
+		tree_hasher: Poseidon<E::Fr>,
+		nullifier_hasher: Poseidon<E::Fr>,
+		leaf_hasher: Poseidon<E::Fr>,
+		amount_bits: usize,
+	) -> Result<PoseidonVAnchorTimeLockCircuit<E::Fr, HEIGHT, INS, OUTS, ANCHOR_CT>, Error> {
+		let in_nullifiers = in_utxos
+			.iter()
+			.map(|x| x.calculate_nullifier(&nullifier_hasher))
+			.collect::<Result<Vec<E::Fr>, Error>>()?;
+
+		let circuit = PoseidonVAnchorTimeLockCircuit::<E::Fr, HEIGHT, INS, OUTS, ANCHOR_CT>::new(
+			public_amount,
+			ext_data_hash,
+			current_time,
+			in_utxos.iter().map(|x| x.amount).collect(),
+			in_utxos.iter().map(|x| x.blinding).collect(),
+			in_utxos
+				.iter()
+				.map(|x| x.keypair.secret_key.unwrap_or_default())
+				.collect(),
+			in_utxos.iter().map(|x| x.unlock_time).collect(),
+			chain_id,
+			public_root_set,
+			in_paths,
+			in_indices.to_vec(),
+			in_nullifiers,
+			out_utxos.iter().map(|x| x.commitment).collect(),
+			out_utxos.iter().map(|x| x.amount).collect(),
+			out_utxos.iter().map(|x| x.blinding).collect(),
+			out_utxos.iter().map(|x| x.chain_id).collect(),
+			out_utxos.iter().map(|x| x.keypair.public_key).collect(),
+			out_utxos.iter().map(|x| x.unlock_time).collect(),
+			tree_hasher,
+			keypair_hasher,
+			leaf_hasher,
+			nullifier_hasher,
+			amount_bits,
+		);
+
+		Ok(circuit)
+	}
+
+	#[allow(clippy::too_many_arguments)]
+	pub fn construct_public_inputs(
+		chain_id: E::Fr,
+		public_amount: E::Fr,
+		current_time: E::Fr,
+		roots: [E::Fr; ANCHOR_CT],
+		nullifiers: [E::Fr; INS],
+		commitments: [E::Fr; OUTS],
+		ext_data_hash: E::Fr,
+	) -> Vec<E::Fr> {
+		TimeLockPublicInputs::<E::Fr, ANCHOR_CT, INS, OUTS> {
+			public_amount,
+			ext_data_hash,
+			current_time,
+			nullifiers,
+			commitments,
+			chain_id,
+			roots,
+		}
+		.to_field_vec()
+	}
+
+	/// For making proofs at `current_time`, the root set may hold up to
+	/// ANCHOR_CT roots. Every input must be unlocked at `current_time`.
+	#[allow(clippy::too_many_arguments)]
+	pub fn create_proof<R: RngCore + CryptoRng>(
+		curve: Curve,
+		chain_id: u64,
+		// External data
+		public_amount: i128,
+		ext_data_hash: Vec<u8>,
+		current_time: u64,
+		public_root_set: Vec<Vec<u8>>,
+		in_indices: [u64; INS],
+		in_leaves: BTreeMap<u64, Vec<Vec<u8>>>,
+		// Input transactions
+		in_utxos: [TimeLockedUtxo<E::Fr>; INS],
+		// Output transactions
+		out_utxos: [TimeLockedUtxo<E::Fr>; OUTS],
+		amount_bits: usize,
+		pk: Vec<u8>,
+		default_leaf: [u8; 32],
+		rng: &mut R,
+	) -> Result<VAnchorProof, Error> {
+		// Initialize hashers
+		let params2 = setup_params::<E::Fr>(curve, 5, 2);
+		let params3 = setup_params::<E::Fr>(curve, 5, 3);
+		let params4 = setup_params::<E::Fr>(curve, 5, 4);
+		let params6 = setup_params::<E::Fr>(curve, 5, 6);
+		let keypair_hasher = Poseidon::<E::Fr> { params: params2 };
+		let tree_hasher = Poseidon::<E::Fr> { params: params3 };
+		let nullifier_hasher = Poseidon::<E::Fr> { params: params4 };
+		let leaf_hasher = Poseidon::<E::Fr> { params: params6 };
+
+		// Cast as field elements
+		let chain_id_elt = E::Fr::from(chain_id);
+		let public_amount_elt = E::Fr::from(public_amount);
+		let ext_data_hash_elt = E::Fr::from_be_bytes_mod_order(&ext_data_hash);
+		let current_time_elt = E::Fr::from(current_time);
+		let public_root_set_elt = pad_root_set::<E::Fr, ANCHOR_CT>(&public_root_set)?;
+
+		// Throw an error if chain IDs don't match intended spending chain.
+		if in_utxos.iter().any(|utxo| utxo.chain_id_raw != chain_id) {
+			return Err(VAnchorTimeLockError::InvalidInputChainId.into());
+		}
+		// Throw an error if an input can't be spent yet
+		if in_utxos
+			.iter()
+			.any(|utxo| utxo.unlock_time_raw > current_time)
+		{
+			return Err(VAnchorTimeLockError::StillLocked.into());
+		}
+
+		// Generate the paths for each UTXO
+		let mut trees = BTreeMap::<u64, SMT<E::Fr, Poseidon<E::Fr>, HEIGHT>>::new();
+		let mut in_paths = Vec::with_capacity(INS);
+		for utxo in in_utxos.iter() {
+			let index = utxo.index.unwrap_or_default();
+			// A Utxo with a zero amount isn't checked to be in the root set, so its
+			// path is taken from a tree holding only that Utxo
+			let path = if utxo.amount == E::Fr::zero() {
+				let (_, path) = setup_tree_and_create_path::<E::Fr, Poseidon<E::Fr>, HEIGHT>(
+					&tree_hasher,
+					&[utxo.commitment],
+					index,
+					&default_leaf,
+				)?;
+				path
+			} else {
+				let tree = match trees.entry(utxo.chain_id_raw) {
+					Entry::Occupied(entry) => entry.into_mut(),
+					Entry::Vacant(entry) => {
+						let leaves = in_leaves
+							.get(&utxo.chain_id_raw)
+							.ok_or(VAnchorTimeLockError::MissingLeaves)?
+							.iter()
+							.map(|l| E::Fr::from_be_bytes_mod_order(l))
+							.collect::<Vec<E::Fr>>();
+						entry.insert(create_merkle_tree::<E::Fr, Poseidon<E::Fr>, HEIGHT>(
+							&tree_hasher,
+							&leaves,
+							&default_leaf,
+						))
+					}
+				};
+				tree.generate_membership_proof(index)
+			};
+			in_paths.push(path);
+		}
+
+		let mut input_nullifiers = [E::Fr::zero(); INS];
+		for (nullifier, utxo) in input_nullifiers.iter_mut().zip(in_utxos.iter()) {
+			*nullifier = utxo.calculate_nullifier(&nullifier_hasher)?;
+		}
+		let output_commitments = out_utxos.clone().map(|utxo| utxo.commitment);
+
+		// Get the circuit
+		let circuit = Self::setup_circuit(
+			chain_id_elt,
+			public_amount_elt,
+			ext_data_hash_elt,
+			current_time_elt,
+			in_utxos,
+			in_indices.map(E::Fr::from),
+			in_paths,
+			public_root_set_elt,
+			out_utxos,
+			keypair_hasher,
+			tree_hasher,
+			nullifier_hasher,
+			leaf_hasher,
+			amount_bits,
+		)?;
+
+		let proof = prove_unchecked::<E, _, _>(circuit, &pk, rng)?;
+
+		let public_inputs = TimeLockPublicInputs::<E::Fr, ANCHOR_CT, INS, OUTS> {
+			public_amount: public_amount_elt,
+			ext_data_hash: ext_data_hash_elt,
+			current_time: current_time_elt,
+			nullifiers: input_nullifiers,
+			commitments: output_commitments,
+			chain_id: chain_id_elt,
+			roots: public_root_set_elt,
+		};
+		let public_inputs_raw = public_inputs.to_bytes_vec();
+
+		Ok(VAnchorProof {
+			public_inputs_raw,
+			proof,
+		})
+	}
+}
@@ -0,0 +1,226 @@
+use super::{setup_params, PoseidonVAnchorTimeLockCircuit, VAnchorTimeLockR1CSProver};
+use crate::{
+	common::*,
+	public_inputs::{PublicInputs, TimeLockPublicInputs},
+	utxo::TimeLockedUtxo,
+};
+use ark_bn254::{Bn254, Fr as BnFr};
+use ark_ff::{BigInteger, PrimeField, UniformRand};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystem};
+use ark_std::{collections::BTreeMap, rand::rngs::StdRng, test_rng, vec, vec::Vec, One, Zero};
+use arkworks_native_gadgets::poseidon::Poseidon;
+use arkworks_utils::Curve;
+
+const HEIGHT: usize = 30;
+const ANCHOR_CT: usize = 2;
+const INS: usize = 2;
+const OUTS: usize = 2;
+const AMOUNT_BITS: usize = 248;
+const DEFAULT_LEAF: [u8; 32] = [0u8; 32];
+const UNLOCK_TIME: u64 = 1_700_000_000;
+
+#[allow(non_camel_case_types)]
+type VAnchorTimeLockR1CSProver_Bn254_Poseidon_30_2_2 =
+	VAnchorTimeLockR1CSProver<Bn254, HEIGHT, ANCHOR_CT, INS, OUTS>;
+
+// An unlocked input and an input locked until `UNLOCK_TIME`, both of 5 on
+// chain 0, with the leaves of their tree and its root
+#[allow(clippy::type_complexity)]
+fn setup_inputs(
+	rng: &mut StdRng,
+) -> (
+	[TimeLockedUtxo<BnFr>; INS],
+	BTreeMap<u64, Vec<Vec<u8>>>,
+	Vec<Vec<u8>>,
+) {
+	let curve = Curve::Bn254;
+	let tree_hasher = Poseidon::<BnFr>::new(setup_params(curve, 5, 3));
+
+	let in_utxos = [(0u64, 0u64), (1, UNLOCK_TIME)].map(|(index, unlock_time)| {
+		VAnchorTimeLockR1CSProver_Bn254_Poseidon_30_2_2::create_random_utxo(
+			curve,
+			0,
+			5,
+			unlock_time,
+			Some(index),
+			rng,
+		)
+		.unwrap()
+	});
+	let leaves = in_utxos.iter().map(|x| x.commitment).collect::<Vec<_>>();
+	let tree =
+		create_merkle_tree::<BnFr, Poseidon<BnFr>, HEIGHT>(&tree_hasher, &leaves, &DEFAULT_LEAF);
+
+	let mut in_leaves = BTreeMap::new();
+	in_leaves.insert(
+		0,
+		leaves.iter().map(|x| x.into_repr().to_bytes_be()).collect(),
+	);
+	let root_set = vec![tree.root().into_repr().to_bytes_be()];
+	(in_utxos, in_leaves, root_set)
+}
+
+#[test]
+fn should_create_proof_once_unlocked() {
+	let rng = &mut test_rng();
+	let curve = Curve::Bn254;
+
+	let random_circuit = VAnchorTimeLockR1CSProver_Bn254_Poseidon_30_2_2::setup_random_circuit(
+		curve,
+		DEFAULT_LEAF,
+		AMOUNT_BITS,
+		rng,
+	)
+	.unwrap();
+	let (pk, vk) = setup_keys_unchecked::<Bn254, _, _>(random_circuit, rng).unwrap();
+
+	let (in_utxos, in_leaves, root_set) = setup_inputs(rng);
+	// Lock the outputs again, e.g. for the next vesting period
+	let out_utxos = [0; OUTS].map(|_| {
+		VAnchorTimeLockR1CSProver_Bn254_Poseidon_30_2_2::create_random_utxo(
+			curve,
+			0,
+			5,
+			UNLOCK_TIME * 2,
+			None,
+			rng,
+		)
+		.unwrap()
+	});
+
+	let proof = VAnchorTimeLockR1CSProver_Bn254_Poseidon_30_2_2::create_proof(
+		curve,
+		0,
+		0,
+		BnFr::rand(rng).into_repr().to_bytes_be(),
+		UNLOCK_TIME,
+		root_set,
+		[0, 1],
+		in_leaves,
+		in_utxos,
+		out_utxos,
+		AMOUNT_BITS,
+		pk,
+		DEFAULT_LEAF,
+		rng,
+	)
+	.unwrap();
+
+	let res = verify_unchecked_raw::<Bn254>(&proof.public_inputs_raw, &vk, &proof.proof).unwrap();
+	assert!(res);
+
+	// The current time is a public input, and the proof doesn't verify for an
+	// earlier time
+	let mut public_inputs = TimeLockPublicInputs::<BnFr, ANCHOR_CT, INS, OUTS>::from_bytes_slice(
+		&proof.public_inputs_raw,
+	)
+	.unwrap();
+	assert_eq!(public_inputs.current_time, BnFr::from(UNLOCK_TIME));
+	public_inputs.current_time = BnFr::from(UNLOCK_TIME - 1);
+	let res = verify_unchecked_typed::<Bn254, _>(&public_inputs, &vk, &proof.proof).unwrap();
+	assert!(!res);
+}
+
+#[test]
+fn should_reject_locked_utxos() {
+	let rng = &mut test_rng();
+	let curve = Curve::Bn254;
+
+	let (in_utxos, in_leaves, root_set) = setup_inputs(rng);
+	let out_utxos = [0; OUTS].map(|_| {
+		VAnchorTimeLockR1CSProver_Bn254_Poseidon_30_2_2::create_random_utxo(
+			curve, 0, 5, 0, None, rng,
+		)
+		.unwrap()
+	});
+
+	let res = VAnchorTimeLockR1CSProver_Bn254_Poseidon_30_2_2::create_proof(
+		curve,
+		0,
+		0,
+		BnFr::rand(rng).into_repr().to_bytes_be(),
+		UNLOCK_TIME - 1,
+		root_set,
+		[0, 1],
+		in_leaves,
+		in_utxos,
+		out_utxos,
+		AMOUNT_BITS,
+		vec![],
+		DEFAULT_LEAF,
+		rng,
+	);
+	assert!(res.is_err());
+}
+
+#[test]
+fn should_not_satisfy_circuit_before_unlock_time() {
+	let rng = &mut test_rng();
+	let curve = Curve::Bn254;
+	let keypair_hasher = Poseidon::<BnFr>::new(setup_params(curve, 5, 2));
+	let tree_hasher = Poseidon::<BnFr>::new(setup_params(curve, 5, 3));
+	let nullifier_hasher = Poseidon::<BnFr>::new(setup_params(curve, 5, 4));
+	let leaf_hasher = Poseidon::<BnFr>::new(setup_params(curve, 5, 6));
+
+	let (in_utxos, ..) = setup_inputs(rng);
+	let out_utxos = [0; OUTS].map(|_| {
+		VAnchorTimeLockR1CSProver_Bn254_Poseidon_30_2_2::create_random_utxo(
+			curve, 0, 5, 0, None, rng,
+		)
+		.unwrap()
+	});
+	let leaves = in_utxos.iter().map(|x| x.commitment).collect::<Vec<_>>();
+	let (tree, _) = setup_tree_and_create_path::<BnFr, Poseidon<BnFr>, HEIGHT>(
+		&tree_hasher,
+		&leaves,
+		0,
+		&DEFAULT_LEAF,
+	)
+	.unwrap();
+	let paths = (0..INS)
+		.map(|i| tree.generate_membership_proof(i as u64))
+		.collect::<Vec<_>>();
+
+	for (current_time, is_satisfied) in [
+		(UNLOCK_TIME, true),
+		(UNLOCK_TIME + 1, true),
+		(UNLOCK_TIME - 1, false),
+	] {
+		// Bypass the lock check of the prover by building the circuit directly
+		let circuit = PoseidonVAnchorTimeLockCircuit::<BnFr, HEIGHT, INS, OUTS, ANCHOR_CT>::new(
+			BnFr::zero(),
+			BnFr::rand(rng),
+			BnFr::from(current_time),
+			in_utxos.iter().map(|x| x.amount).collect(),
+			in_utxos.iter().map(|x| x.blinding).collect(),
+			in_utxos
+				.iter()
+				.map(|x| x.keypair.secret_key.unwrap())
+				.collect(),
+			in_utxos.iter().map(|x| x.unlock_time).collect(),
+			BnFr::zero(),
+			[tree.root(), BnFr::zero()],
+			paths.clone(),
+			vec![BnFr::zero(), BnFr::one()],
+			in_utxos
+				.iter()
+				.map(|x| x.calculate_nullifier(&nullifier_hasher).unwrap())
+				.collect(),
+			out_utxos.iter().map(|x| x.commitment).collect(),
+			out_utxos.iter().map(|x| x.amount).collect(),
+			out_utxos.iter().map(|x| x.blinding).collect(),
+			out_utxos.iter().map(|x| x.chain_id).collect(),
+			out_utxos.iter().map(|x| x.keypair.public_key).collect(),
+			out_utxos.iter().map(|x| x.unlock_time).collect(),
+			tree_hasher.clone(),
+			keypair_hasher.clone(),
+			leaf_hasher.clone(),
+			nullifier_hasher.clone(),
+			AMOUNT_BITS,
+		);
+
+		let cs = ConstraintSystem::<BnFr>::new_ref();
+		circuit.generate_constraints(cs.clone()).unwrap();
+		assert_eq!(cs.is_satisfied().unwrap(), is_satisfied);
+	}
+}
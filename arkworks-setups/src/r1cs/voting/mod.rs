@@ -0,0 +1,396 @@
+use crate::{
+	common::*,
+	public_inputs::{PublicInputs, VotingPublicInputs},
+	utxo::Utxo,
+};
+use ark_crypto_primitives::Error;
+use ark_ec::PairingEngine;
+use ark_ff::{BigInteger, PrimeField, Zero};
+use ark_groth16::{Proof, VerifyingKey};
+use ark_serialize::CanonicalDeserialize;
+use ark_std::{
+	collections::BTreeSet,
+	marker::PhantomData,
+	rand::{CryptoRng, Rng, RngCore},
+	vec,
+	vec::Vec,
+	UniformRand,
+};
+use arkworks_native_gadgets::poseidon::{FieldHasher, Poseidon};
+use arkworks_r1cs_circuits::voting::{VoterNote, VotingCircuit, OPTION_BITS};
+use arkworks_r1cs_gadgets::poseidon::PoseidonGadget;
+use arkworks_utils::Curve;
+
+#[cfg(test)]
+mod tests;
+
+#[derive(Debug)]
+pub enum VotingError {
+	InvalidNumberOfOptions,
+	VoteOutOfRange,
+	MissingPrivateKey,
+	NotInSnapshot,
+	InvalidBallot,
+	InvalidProof,
+	DuplicateNullifier,
+}
+
+impl core::fmt::Display for VotingError {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		let msg = match self {
+			Self::InvalidNumberOfOptions => {
+				ark_std::format!("Number of options doesn't fit in the circuit")
+			}
+			Self::VoteOutOfRange => ark_std::format!("Vote is not one of the options"),
+			Self::MissingPrivateKey => ark_std::format!("Utxo has no private key"),
+			Self::NotInSnapshot => ark_std::format!("Note is not in the snapshot"),
+			Self::InvalidBallot => ark_std::format!("Ballot is for another poll"),
+			Self::InvalidProof => ark_std::format!("Ballot has an invalid proof"),
+			Self::DuplicateNullifier => ark_std::format!("Note already voted in the poll"),
+		};
+		write!(f, "{}", msg)
+	}
+}
+
+impl ark_std::error::Error for VotingError {}
+
+pub type PoseidonVotingCircuit<F, const N: usize> = VotingCircuit<F, PoseidonGadget<F>, N>;
+
+/// The total weight of every option, and the rejected ballots by position
+pub type Tally<F> = (Vec<F>, Vec<(usize, VotingError)>);
+
+/// Counts the votes of `ballots` in the poll `poll_id` with the snapshot
+/// `root`, returning the total weight of every option and the ballots that
+/// weren't counted, by position. A ballot is rejected if it has an invalid
+/// proof, is for another poll, or has a poll nullifier seen before, so a bad
+/// ballot can't block the others. Fails only on an invalid `vk`, which is the
+/// unchecked serialization of the verifying key.
+pub fn tally<E: PairingEngine>(
+	poll_id: E::Fr,
+	root: E::Fr,
+	num_options: u64,
+	ballots: &[VotingProof],
+	vk: &[u8],
+) -> Result<Tally<E::Fr>, Error> {
+	let vk = VerifyingKey::<E>::deserialize_unchecked(vk)?;
+
+	let mut totals = vec![E::Fr::zero(); num_options as usize];
+	let mut rejected = Vec::new();
+	let mut nullifiers = BTreeSet::new();
+	for (i, ballot) in ballots.iter().enumerate() {
+		match count_ballot::<E>(poll_id, root, num_options, ballot, &vk, &mut nullifiers) {
+			Ok((vote, weight)) => totals[vote] += weight,
+			Err(e) => rejected.push((i, e)),
+		}
+	}
+
+	Ok((totals, rejected))
+}
+
+// Checks a ballot of `tally`, returning its vote and weight. Its nullifier is
+// only recorded once the proof is known to be valid.
+fn count_ballot<E: PairingEngine>(
+	poll_id: E::Fr,
+	root: E::Fr,
+	num_options: u64,
+	ballot: &VotingProof,
+	vk: &VerifyingKey<E>,
+	nullifiers: &mut BTreeSet<E::Fr>,
+) -> Result<(usize, E::Fr), VotingError> {
+	let public_inputs = VotingPublicInputs::<E::Fr>::from_bytes_slice(&ballot.public_inputs_raw)
+		.map_err(|_| VotingError::InvalidBallot)?;
+	if public_inputs.poll_id != poll_id
+		|| public_inputs.root != root
+		|| public_inputs.num_options != E::Fr::from(num_options)
+	{
+		return Err(VotingError::InvalidBallot);
+	}
+	let proof =
+		Proof::<E>::deserialize(&ballot.proof[..]).map_err(|_| VotingError::InvalidProof)?;
+	if !verify_groth16(vk, &public_inputs.to_field_vec(), &proof).unwrap_or(false) {
+		return Err(VotingError::InvalidProof);
+	}
+	if nullifiers.contains(&public_inputs.poll_nullifier) {
+		return Err(VotingError::DuplicateNullifier);
+	}
+
+	// The proof ensures that the vote is one of the options
+	let vote = (0..num_options)
+		.position(|i| E::Fr::from(i) == public_inputs.vote)
+		.ok_or(VotingError::VoteOutOfRange)?;
+	nullifiers.insert(public_inputs.poll_nullifier);
+	Ok((vote, public_inputs.weight))
+}
+
+/// Prover for votes with the notes of a snapshot tree of the given `HEIGHT`
+pub struct VotingR1CSProver<E: PairingEngine, const HEIGHT: usize> {
+	engine: PhantomData<E>,
+}
+
+impl<E: PairingEngine, const HEIGHT: usize> VotingR1CSProver<E, HEIGHT> {
+	/// The note of an Anchor deposit on `chain_id`
+	pub fn anchor_note(chain_id: u64, leaf: &Leaf) -> VoterNote<E::Fr> {
+		VoterNote::Anchor {
+			chain_id: E::Fr::from(chain_id),
+			nullifier: E::Fr::from_be_bytes_mod_order(&leaf.nullifier_bytes),
+			secret: E::Fr::from_be_bytes_mod_order(&leaf.secret_bytes),
+		}
+	}
+
+	/// The note of a VAnchor Utxo, which must hold its private key
+	pub fn vanchor_note(utxo: &Utxo<E::Fr>) -> Result<VoterNote<E::Fr>, Error> {
+		let private_key = utxo
+			.keypair
+			.secret_key
+			.ok_or(VotingError::MissingPrivateKey)?;
+		Ok(VoterNote::VAnchor {
+			chain_id: utxo.chain_id,
+			amount: utxo.amount,
+			private_key,
+			blinding: utxo.blinding,
+		})
+	}
+
+	// The leaf hasher of the note, of width 4 for Anchor notes and of width 5
+	// for VAnchor notes
+	fn setup_leaf_hasher(curve: Curve, note: &VoterNote<E::Fr>) -> Poseidon<E::Fr> {
+		let width = match note {
+			VoterNote::Anchor { .. } => 4,
+			VoterNote::VAnchor { .. } => 5,
+		};
+		Poseidon::new(setup_params(curve, 5, width))
+	}
+
+	/// Calculates the leaf of a note in the snapshot tree
+	pub fn note_leaf(curve: Curve, note: &VoterNote<E::Fr>) -> Result<E::Fr, Error> {
+		let leaf_hasher = Self::setup_leaf_hasher(curve, note);
+		let leaf = match note {
+			VoterNote::Anchor {
+				chain_id,
+				nullifier,
+				secret,
+			} => leaf_hasher.hash(&[*chain_id, *nullifier, *secret])?,
+			VoterNote::VAnchor {
+				chain_id,
+				amount,
+				private_key,
+				blinding,
+			} => {
+				let keypair_hasher = Poseidon::<E::Fr>::new(setup_params(curve, 5, 2));
+				let pub_key = keypair_hasher.hash(&[*private_key])?;
+				leaf_hasher.hash(&[*chain_id, *amount, pub_key, *blinding])?
+			}
+		};
+		Ok(leaf)
+	}
+
+	/// The weight of the vote of a note, one for Anchor notes and the amount
+	/// for VAnchor notes
+	pub fn note_weight(note: &VoterNote<E::Fr>) -> E::Fr {
+		match note {
+			VoterNote::Anchor { .. } => E::Fr::from(1u64),
+			VoterNote::VAnchor { amount, .. } => *amount,
+		}
+	}
+
+	/// Calculates the nullifier of a note in the poll `poll_id`
+	pub fn create_poll_nullifier(
+		curve: Curve,
+		note: &VoterNote<E::Fr>,
+		poll_id: E::Fr,
+	) -> Result<E::Fr, Error> {
+		let nullifier_hasher = Poseidon::<E::Fr>::new(setup_params(curve, 5, 4));
+		let note_secret = match note {
+			VoterNote::Anchor { nullifier, .. } => *nullifier,
+			VoterNote::VAnchor { private_key, .. } => *private_key,
+		};
+		let leaf = Self::note_leaf(curve, note)?;
+		Ok(nullifier_hasher.hash(&[note_secret, poll_id, leaf])?)
+	}
+
+	pub fn construct_public_inputs(
+		poll_id: E::Fr,
+		root: E::Fr,
+		poll_nullifier: E::Fr,
+		num_options: E::Fr,
+		vote: E::Fr,
+		weight: E::Fr,
+	) -> Vec<E::Fr> {
+		VotingPublicInputs {
+			poll_id,
+			root,
+			poll_nullifier,
+			num_options,
+			vote,
+			weight,
+		}
+		.to_field_vec()
+	}
+
+	/// Sets up a circuit voting with a random Anchor note. Returns the circuit
+	/// and the public inputs.
+	#[allow(dead_code)]
+	#[allow(clippy::type_complexity)]
+	pub fn setup_random_anchor_circuit<R: CryptoRng + RngCore>(
+		curve: Curve,
+		num_options: u64,
+		default_leaf: [u8; 32],
+		rng: &mut R,
+	) -> Result<(PoseidonVotingCircuit<E::Fr, HEIGHT>, Vec<E::Fr>), Error> {
+		let note = VoterNote::Anchor {
+			chain_id: E::Fr::from(rng.gen::<u64>()),
+			nullifier: E::Fr::rand(rng),
+			secret: E::Fr::rand(rng),
+		};
+		Self::setup_random_circuit(curve, num_options, note, default_leaf, rng)
+	}
+
+	/// Sets up a circuit voting with a random VAnchor note. Returns the
+	/// circuit and the public inputs.
+	#[allow(dead_code)]
+	#[allow(clippy::type_complexity)]
+	pub fn setup_random_vanchor_circuit<R: CryptoRng + RngCore>(
+		curve: Curve,
+		num_options: u64,
+		default_leaf: [u8; 32],
+		rng: &mut R,
+	) -> Result<(PoseidonVotingCircuit<E::Fr, HEIGHT>, Vec<E::Fr>), Error> {
+		let note = VoterNote::VAnchor {
+			chain_id: E::Fr::from(rng.gen::<u64>()),
+			amount: E::Fr::from(rng.gen::<u64>()),
+			private_key: E::Fr::rand(rng),
+			blinding: E::Fr::rand(rng),
+		};
+		Self::setup_random_circuit(curve, num_options, note, default_leaf, rng)
+	}
+
+	#[allow(clippy::type_complexity)]
+	fn setup_random_circuit<R: CryptoRng + RngCore>(
+		curve: Curve,
+		num_options: u64,
+		note: VoterNote<E::Fr>,
+		default_leaf: [u8; 32],
+		rng: &mut R,
+	) -> Result<(PoseidonVotingCircuit<E::Fr, HEIGHT>, Vec<E::Fr>), Error> {
+		let leaf = Self::note_leaf(curve, &note)?;
+		Self::setup_circuit(
+			curve,
+			E::Fr::rand(rng),
+			num_options,
+			0,
+			note,
+			&[leaf],
+			0,
+			default_leaf,
+		)
+	}
+
+	/// Sets up a circuit voting for `vote` in the poll `poll_id` with `note`,
+	/// the leaf at `index` in the snapshot `leaves`. Returns the circuit and
+	/// the public inputs.
+	#[allow(clippy::too_many_arguments)]
+	#[allow(clippy::type_complexity)]
+	pub fn setup_circuit(
+		curve: Curve,
+		poll_id: E::Fr,
+		num_options: u64,
+		vote: u64,
+		note: VoterNote<E::Fr>,
+		leaves: &[E::Fr],
+		index: u64,
+		default_leaf: [u8; 32],
+	) -> Result<(PoseidonVotingCircuit<E::Fr, HEIGHT>, Vec<E::Fr>), Error> {
+		if num_options >= 1u64 << OPTION_BITS {
+			return Err(VotingError::InvalidNumberOfOptions.into());
+		}
+		if vote >= num_options {
+			return Err(VotingError::VoteOutOfRange.into());
+		}
+		let leaf = Self::note_leaf(curve, &note)?;
+		if leaves.get(index as usize) != Some(&leaf) {
+			return Err(VotingError::NotInSnapshot.into());
+		}
+
+		let tree_hasher = Poseidon::<E::Fr>::new(setup_params(curve, 5, 3));
+		let keypair_hasher = Poseidon::<E::Fr>::new(setup_params(curve, 5, 2));
+		let leaf_hasher = Self::setup_leaf_hasher(curve, &note);
+		let nullifier_hasher = Poseidon::<E::Fr>::new(setup_params(curve, 5, 4));
+		let (tree, path) = setup_tree_and_create_path::<E::Fr, Poseidon<E::Fr>, HEIGHT>(
+			&tree_hasher,
+			leaves,
+			index,
+			&default_leaf,
+		)?;
+		let root = tree.root();
+		let poll_nullifier = Self::create_poll_nullifier(curve, &note, poll_id)?;
+		let num_options = E::Fr::from(num_options);
+		let vote = E::Fr::from(vote);
+		let weight = Self::note_weight(&note);
+
+		let circuit = VotingCircuit::new(
+			poll_id,
+			num_options,
+			vote,
+			weight,
+			root,
+			poll_nullifier,
+			note,
+			path,
+			tree_hasher,
+			keypair_hasher,
+			leaf_hasher,
+			nullifier_hasher,
+		);
+		let public_inputs =
+			Self::construct_public_inputs(poll_id, root, poll_nullifier, num_options, vote, weight);
+		Ok((circuit, public_inputs))
+	}
+
+	/// Votes for `vote` in the poll `poll_id` with `note`, the leaf at `index`
+	/// in the snapshot `leaves`
+	#[allow(clippy::too_many_arguments)]
+	pub fn create_proof<R: RngCore + CryptoRng>(
+		curve: Curve,
+		poll_id: Vec<u8>,
+		num_options: u64,
+		vote: u64,
+		note: VoterNote<E::Fr>,
+		leaves: Vec<Vec<u8>>,
+		index: u64,
+		pk: Vec<u8>,
+		default_leaf: [u8; 32],
+		rng: &mut R,
+	) -> Result<VotingProof, Error> {
+		// Get field element version of all the data
+		let poll_id_f = E::Fr::from_be_bytes_mod_order(&poll_id);
+		let leaves_f: Vec<E::Fr> = leaves
+			.iter()
+			.map(|x| E::Fr::from_be_bytes_mod_order(x))
+			.collect();
+
+		let (circuit, public_inputs) = Self::setup_circuit(
+			curve,
+			poll_id_f,
+			num_options,
+			vote,
+			note,
+			&leaves_f,
+			index,
+			default_leaf,
+		)?;
+
+		let public_inputs = VotingPublicInputs::from_field_slice(&public_inputs)?;
+		let root_raw = public_inputs.root.into_repr().to_bytes_be();
+		let poll_nullifier_raw = public_inputs.poll_nullifier.into_repr().to_bytes_be();
+		let public_inputs_raw = public_inputs.to_bytes_vec();
+
+		let proof = prove_unchecked::<E, _, _>(circuit, &pk, rng)?;
+
+		Ok(VotingProof {
+			proof,
+			poll_nullifier_raw,
+			root_raw,
+			public_inputs_raw,
+		})
+	}
+}
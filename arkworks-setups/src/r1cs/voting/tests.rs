@@ -0,0 +1,285 @@
+use super::{setup_params, tally, PoseidonVotingCircuit, VotingError, VotingR1CSProver};
+use crate::{
+	common::*,
+	public_inputs::{PublicInputs, VotingPublicInputs},
+	utxo::Utxo,
+};
+use ark_bn254::{Bn254, Fr as BnFr};
+use ark_ff::{BigInteger, PrimeField, UniformRand, Zero};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystem};
+use ark_std::{rand::rngs::StdRng, test_rng, vec, vec::Vec};
+use arkworks_native_gadgets::poseidon::Poseidon;
+use arkworks_r1cs_circuits::voting::VoterNote;
+use arkworks_utils::Curve;
+
+const HEIGHT: usize = 30;
+const NUM_OPTIONS: u64 = 3;
+const DEFAULT_LEAF: [u8; 32] = [0u8; 32];
+
+#[allow(non_camel_case_types)]
+type VotingR1CSProver_Bn254_Poseidon_30 = VotingR1CSProver<Bn254, HEIGHT>;
+
+// VAnchor notes of the given amounts on chain 0, with the leaves of their
+// snapshot tree
+fn setup_vanchor_notes(amounts: &[u128], rng: &mut StdRng) -> (Vec<VoterNote<BnFr>>, Vec<Vec<u8>>) {
+	let curve = Curve::Bn254;
+	let keypair_hasher = Poseidon::<BnFr>::new(setup_params(curve, 5, 2));
+	let leaf_hasher = Poseidon::<BnFr>::new(setup_params(curve, 5, 5));
+
+	let utxos = amounts
+		.iter()
+		.enumerate()
+		.map(|(index, amount)| {
+			Utxo::new_with_privates(
+				0,
+				BnFr::from(*amount),
+				Some(index as u64),
+				BnFr::rand(rng),
+				BnFr::rand(rng),
+				&keypair_hasher,
+				&leaf_hasher,
+			)
+			.unwrap()
+		})
+		.collect::<Vec<_>>();
+	let notes = utxos
+		.iter()
+		.map(|x| VotingR1CSProver_Bn254_Poseidon_30::vanchor_note(x).unwrap())
+		.collect();
+	let leaves = utxos
+		.iter()
+		.map(|x| x.commitment.into_repr().to_bytes_be())
+		.collect();
+	(notes, leaves)
+}
+
+fn root_of(leaves: &[Vec<u8>]) -> BnFr {
+	let tree_hasher = Poseidon::<BnFr>::new(setup_params(Curve::Bn254, 5, 3));
+	let leaves_f: Vec<BnFr> = leaves
+		.iter()
+		.map(|x| BnFr::from_be_bytes_mod_order(x))
+		.collect();
+	create_merkle_tree::<BnFr, Poseidon<BnFr>, HEIGHT>(&tree_hasher, &leaves_f, &DEFAULT_LEAF)
+		.root()
+}
+
+#[test]
+fn should_satisfy_random_circuits() {
+	let rng = &mut test_rng();
+	let (circuit, _) = VotingR1CSProver_Bn254_Poseidon_30::setup_random_anchor_circuit(
+		Curve::Bn254,
+		NUM_OPTIONS,
+		DEFAULT_LEAF,
+		rng,
+	)
+	.unwrap();
+	let cs = ConstraintSystem::<BnFr>::new_ref();
+	circuit.generate_constraints(cs.clone()).unwrap();
+	assert!(cs.is_satisfied().unwrap());
+
+	let (circuit, public_inputs) =
+		VotingR1CSProver_Bn254_Poseidon_30::setup_random_vanchor_circuit(
+			Curve::Bn254,
+			NUM_OPTIONS,
+			DEFAULT_LEAF,
+			rng,
+		)
+		.unwrap();
+	let (pk, vk) = setup_keys::<Bn254, _, _>(circuit.clone(), rng).unwrap();
+	let proof = prove::<Bn254, _, _>(circuit, &pk, rng).unwrap();
+	let res = verify::<Bn254>(&public_inputs, &vk, &proof).unwrap();
+	assert!(res);
+}
+
+#[test]
+fn should_tally_weighted_vanchor_votes() {
+	let rng = &mut test_rng();
+	let curve = Curve::Bn254;
+	let (circuit, _) = VotingR1CSProver_Bn254_Poseidon_30::setup_random_vanchor_circuit(
+		curve,
+		NUM_OPTIONS,
+		DEFAULT_LEAF,
+		rng,
+	)
+	.unwrap();
+	let (pk, vk) = setup_keys_unchecked::<Bn254, _, _>(circuit, rng).unwrap();
+
+	let (notes, leaves) = setup_vanchor_notes(&[10, 20, 5], rng);
+	let poll_id = BnFr::rand(rng);
+	let votes = [2, 0, 2];
+	let ballots: Vec<VotingProof> = notes
+		.into_iter()
+		.zip(votes.iter())
+		.enumerate()
+		.map(|(index, (note, vote))| {
+			VotingR1CSProver_Bn254_Poseidon_30::create_proof(
+				curve,
+				poll_id.into_repr().to_bytes_be(),
+				NUM_OPTIONS,
+				*vote,
+				note,
+				leaves.clone(),
+				index as u64,
+				pk.clone(),
+				DEFAULT_LEAF,
+				rng,
+			)
+			.unwrap()
+		})
+		.collect();
+
+	let root = root_of(&leaves);
+	let (totals, rejected) = tally::<Bn254>(poll_id, root, NUM_OPTIONS, &ballots, &vk).unwrap();
+	assert_eq!(totals, vec![
+		BnFr::from(20u64),
+		BnFr::from(0u64),
+		BnFr::from(15u64)
+	]);
+	assert!(rejected.is_empty());
+
+	// The same note can't vote twice in the poll, nor can ballots claiming
+	// another weight or that are malformed be counted, but they don't stop the
+	// other ballots from being counted
+	let mut tampered = ballots[1].clone();
+	let mut public_inputs =
+		VotingPublicInputs::<BnFr>::from_bytes_slice(&tampered.public_inputs_raw).unwrap();
+	public_inputs.weight = BnFr::from(1000u64);
+	tampered.public_inputs_raw = public_inputs.to_bytes_vec();
+	let mut truncated = ballots[2].clone();
+	truncated.public_inputs_raw.pop();
+	let mut bad_ballots = ballots.clone();
+	bad_ballots.extend([ballots[0].clone(), tampered, truncated]);
+	let (totals, rejected) = tally::<Bn254>(poll_id, root, NUM_OPTIONS, &bad_ballots, &vk).unwrap();
+	assert_eq!(totals, vec![
+		BnFr::from(20u64),
+		BnFr::from(0u64),
+		BnFr::from(15u64)
+	]);
+	assert_eq!(rejected.len(), 3);
+	assert!(matches!(rejected[0], (3, VotingError::DuplicateNullifier)));
+	assert!(matches!(rejected[1], (4, VotingError::InvalidProof)));
+	assert!(matches!(rejected[2], (5, VotingError::InvalidBallot)));
+
+	// Ballots of another poll aren't counted
+	let (totals, rejected) =
+		tally::<Bn254>(BnFr::rand(rng), root, NUM_OPTIONS, &ballots, &vk).unwrap();
+	assert!(totals.iter().all(|x| x.is_zero()));
+	assert_eq!(rejected.len(), ballots.len());
+	assert!(rejected
+		.iter()
+		.all(|(_, e)| matches!(e, VotingError::InvalidBallot)));
+
+	// An invalid verifying key fails the whole tally
+	assert!(tally::<Bn254>(poll_id, root, NUM_OPTIONS, &ballots, &vk[1..]).is_err());
+}
+
+#[test]
+fn should_tally_anchor_votes() {
+	let rng = &mut test_rng();
+	let curve = Curve::Bn254;
+	let (circuit, _) = VotingR1CSProver_Bn254_Poseidon_30::setup_random_anchor_circuit(
+		curve,
+		NUM_OPTIONS,
+		DEFAULT_LEAF,
+		rng,
+	)
+	.unwrap();
+	let (pk, vk) = setup_keys_unchecked::<Bn254, _, _>(circuit, rng).unwrap();
+
+	let notes: Vec<VoterNote<BnFr>> = (0..2)
+		.map(|_| VoterNote::Anchor {
+			chain_id: BnFr::from(1u64),
+			nullifier: BnFr::rand(rng),
+			secret: BnFr::rand(rng),
+		})
+		.collect();
+	let leaves: Vec<Vec<u8>> = notes
+		.iter()
+		.map(|x| {
+			VotingR1CSProver_Bn254_Poseidon_30::note_leaf(curve, x)
+				.unwrap()
+				.into_repr()
+				.to_bytes_be()
+		})
+		.collect();
+	let poll_id = BnFr::rand(rng);
+	let ballots: Vec<VotingProof> = notes
+		.into_iter()
+		.enumerate()
+		.map(|(index, note)| {
+			VotingR1CSProver_Bn254_Poseidon_30::create_proof(
+				curve,
+				poll_id.into_repr().to_bytes_be(),
+				NUM_OPTIONS,
+				1,
+				note,
+				leaves.clone(),
+				index as u64,
+				pk.clone(),
+				DEFAULT_LEAF,
+				rng,
+			)
+			.unwrap()
+		})
+		.collect();
+
+	let (totals, rejected) =
+		tally::<Bn254>(poll_id, root_of(&leaves), NUM_OPTIONS, &ballots, &vk).unwrap();
+	assert!(rejected.is_empty());
+	assert_eq!(totals, vec![
+		BnFr::from(0u64),
+		BnFr::from(2u64),
+		BnFr::from(0u64)
+	]);
+}
+
+#[test]
+fn should_fail_with_vote_out_of_range() {
+	let rng = &mut test_rng();
+	let curve = Curve::Bn254;
+	let (notes, leaves) = setup_vanchor_notes(&[10], rng);
+	let leaves_f: Vec<BnFr> = leaves
+		.iter()
+		.map(|x| BnFr::from_be_bytes_mod_order(x))
+		.collect();
+	let poll_id = BnFr::rand(rng);
+
+	let res = VotingR1CSProver_Bn254_Poseidon_30::setup_circuit(
+		curve,
+		poll_id,
+		NUM_OPTIONS,
+		NUM_OPTIONS,
+		notes[0].clone(),
+		&leaves_f,
+		0,
+		DEFAULT_LEAF,
+	);
+	assert!(res.is_err());
+
+	// The prover helpers refuse to set up this circuit, build it directly
+	let (_, path) = setup_tree_and_create_path::<BnFr, Poseidon<BnFr>, HEIGHT>(
+		&Poseidon::new(setup_params(curve, 5, 3)),
+		&leaves_f,
+		0,
+		&DEFAULT_LEAF,
+	)
+	.unwrap();
+	let circuit = PoseidonVotingCircuit::<BnFr, HEIGHT>::new(
+		poll_id,
+		BnFr::from(NUM_OPTIONS),
+		BnFr::from(NUM_OPTIONS),
+		BnFr::from(10u64),
+		root_of(&leaves),
+		VotingR1CSProver_Bn254_Poseidon_30::create_poll_nullifier(curve, &notes[0], poll_id)
+			.unwrap(),
+		notes[0].clone(),
+		path,
+		Poseidon::new(setup_params(curve, 5, 3)),
+		Poseidon::new(setup_params(curve, 5, 2)),
+		Poseidon::new(setup_params(curve, 5, 5)),
+		Poseidon::new(setup_params(curve, 5, 4)),
+	);
+	let cs = ConstraintSystem::<BnFr>::new_ref();
+	circuit.generate_constraints(cs.clone()).unwrap();
+	assert!(!cs.is_satisfied().unwrap());
+}
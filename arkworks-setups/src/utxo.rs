@@ -1,14 +1,19 @@
-use crate::keypair::Keypair;
+use crate::keypair::{Keypair, MultisigKeypair};
 use ark_crypto_primitives::Error;
-use ark_ff::PrimeField;
-use ark_std::{error::Error as ArkError, rand::RngCore, string::ToString};
-use arkworks_native_gadgets::poseidon::{FieldHasher, Poseidon};
+use ark_ed_on_bn254::Fq;
+use ark_ff::{BigInteger, PrimeField};
+use ark_std::{error::Error as ArkError, rand::RngCore, string::ToString, vec::Vec};
+use arkworks_native_gadgets::{
+	eddsa::{PublicKey, SecretKey},
+	poseidon::{encryption, FieldHasher, Poseidon},
+};
 
 #[derive(Debug)]
 pub enum UtxoError {
 	NullifierNotCalculated,
 	EncryptedDataDecodeError,
 	IndexNotSet,
+	InvalidId,
 }
 
 impl core::fmt::Display for UtxoError {
@@ -17,6 +22,7 @@ impl core::fmt::Display for UtxoError {
 			UtxoError::NullifierNotCalculated => "Nullifier not calculated".to_string(),
 			UtxoError::EncryptedDataDecodeError => "Failed to decode encrypted data".to_string(),
 			&UtxoError::IndexNotSet => "Utxo index not set".to_string(),
+			UtxoError::InvalidId => "Id does not fit in 64 bits".to_string(),
 		};
 		write!(f, "{}", msg)
 	}
@@ -24,6 +30,14 @@ impl core::fmt::Display for UtxoError {
 
 impl ArkError for UtxoError {}
 
+/// A Utxo encrypted with Poseidon encryption, as checked by the
+/// `VAnchorEncryptedCircuit`
+#[derive(Clone)]
+pub struct PoseidonEncryptedNote {
+	pub ephemeral_public_key: PublicKey,
+	pub ciphertext: Vec<Fq>,
+}
+
 #[derive(Clone)]
 pub struct Utxo<F: PrimeField> {
 	pub chain_id_raw: u64,
@@ -135,7 +149,448 @@ impl<F: PrimeField> Utxo<F> {
 		}
 	}
 
+	/// Calculates the nullifier of the Utxo in an application scope, as
+	/// checked by the `BalanceThresholdCircuit`. It is unrelated to the spend
+	/// nullifier, so it can be revealed without linking a later spend.
+	pub fn calculate_scoped_nullifier(&self, scope: &F, hasher4: &Poseidon<F>) -> Result<F, Error> {
+		let private_key = self
+			.keypair
+			.secret_key
+			.ok_or(UtxoError::NullifierNotCalculated)?;
+		let nullifier = hasher4.hash(&[private_key, *scope, self.commitment]);
+		let nullifier = nullifier.map_err::<UtxoError, _>(|_| UtxoError::NullifierNotCalculated)?;
+		Ok(nullifier)
+	}
+
 	pub fn get_index(&self) -> Result<u64, Error> {
 		self.index.ok_or(UtxoError::IndexNotSet.into())
 	}
 }
+
+/// A Utxo of the multi-asset VAnchor, whose commitment is
+/// `Hash(chain_id, asset_id, amount, pub_key, blinding)`
+#[derive(Clone)]
+pub struct MaspUtxo<F: PrimeField> {
+	pub chain_id_raw: u64,
+	pub chain_id: F,
+	pub asset_id_raw: u64,
+	pub asset_id: F,
+	pub amount: F,
+	pub blinding: F,
+	pub keypair: Keypair<F, Poseidon<F>>,
+	pub index: Option<u64>,
+	pub commitment: F,
+}
+
+impl<F: PrimeField> MaspUtxo<F> {
+	#[allow(clippy::too_many_arguments)]
+	pub fn new<R: RngCore>(
+		chain_id_raw: u64,
+		asset_id_raw: u64,
+		amount: F,
+		index: Option<u64>,
+		private_key: Option<F>,
+		blinding: Option<F>,
+		hasher2: &Poseidon<F>,
+		hasher6: &Poseidon<F>,
+		rng: &mut R,
+	) -> Result<Self, Error> {
+		let blinding = blinding.unwrap_or_else(|| F::rand(rng));
+		let private_key = private_key.unwrap_or_else(|| F::rand(rng));
+		Self::new_with_privates(
+			chain_id_raw,
+			asset_id_raw,
+			amount,
+			index,
+			private_key,
+			blinding,
+			hasher2,
+			hasher6,
+		)
+	}
+
+	#[allow(clippy::too_many_arguments)]
+	pub fn new_with_privates(
+		chain_id_raw: u64,
+		asset_id_raw: u64,
+		amount: F,
+		index: Option<u64>,
+		private_key: F,
+		blinding: F,
+		hasher2: &Poseidon<F>,
+		hasher6: &Poseidon<F>,
+	) -> Result<Self, Error> {
+		let keypair = Keypair::new(private_key, hasher2);
+		Self::with_keypair(
+			chain_id_raw,
+			asset_id_raw,
+			amount,
+			index,
+			keypair,
+			blinding,
+			hasher6,
+		)
+	}
+
+	pub fn new_with_public(
+		chain_id_raw: u64,
+		asset_id_raw: u64,
+		amount: F,
+		index: Option<u64>,
+		public_key: F,
+		blinding: F,
+		hasher6: &Poseidon<F>,
+	) -> Result<Self, Error> {
+		let keypair = Keypair::new_from_public_key(public_key);
+		Self::with_keypair(
+			chain_id_raw,
+			asset_id_raw,
+			amount,
+			index,
+			keypair,
+			blinding,
+			hasher6,
+		)
+	}
+
+	fn with_keypair(
+		chain_id_raw: u64,
+		asset_id_raw: u64,
+		amount: F,
+		index: Option<u64>,
+		keypair: Keypair<F, Poseidon<F>>,
+		blinding: F,
+		hasher6: &Poseidon<F>,
+	) -> Result<Self, Error> {
+		let chain_id = F::from(chain_id_raw);
+		let asset_id = F::from(asset_id_raw);
+		let commitment =
+			hasher6.hash(&[chain_id, asset_id, amount, keypair.public_key, blinding])?;
+
+		Ok(Self {
+			chain_id_raw,
+			chain_id,
+			asset_id_raw,
+			asset_id,
+			amount,
+			blinding,
+			keypair,
+			index,
+			commitment,
+		})
+	}
+
+	pub fn set_index(&mut self, index: u64) {
+		self.index = Some(index);
+	}
+
+	pub fn calculate_nullifier(&self, hasher4: &Poseidon<F>) -> Result<F, Error> {
+		let index = self.index.ok_or(UtxoError::IndexNotSet)?;
+		let i = F::from(index);
+		let signature = self.keypair.signature(&self.commitment, &i, hasher4)?;
+		let nullifier = hasher4
+			.hash(&[self.commitment, i, signature])
+			.map_err::<UtxoError, _>(|_| UtxoError::NullifierNotCalculated)?;
+		Ok(nullifier)
+	}
+
+	pub fn get_index(&self) -> Result<u64, Error> {
+		self.index.ok_or_else(|| UtxoError::IndexNotSet.into())
+	}
+}
+
+/// A Utxo of the time-locked VAnchor, whose commitment is
+/// `Hash(chain_id, amount, pub_key, blinding, unlock_time)`. An unlock time of
+/// zero makes the Utxo spendable right away.
+#[derive(Clone)]
+pub struct TimeLockedUtxo<F: PrimeField> {
+	pub chain_id_raw: u64,
+	pub chain_id: F,
+	pub amount: F,
+	pub blinding: F,
+	pub unlock_time_raw: u64,
+	pub unlock_time: F,
+	pub keypair: Keypair<F, Poseidon<F>>,
+	pub index: Option<u64>,
+	pub commitment: F,
+}
+
+impl<F: PrimeField> TimeLockedUtxo<F> {
+	#[allow(clippy::too_many_arguments)]
+	pub fn new<R: RngCore>(
+		chain_id_raw: u64,
+		amount: F,
+		unlock_time_raw: u64,
+		index: Option<u64>,
+		private_key: Option<F>,
+		blinding: Option<F>,
+		hasher2: &Poseidon<F>,
+		hasher6: &Poseidon<F>,
+		rng: &mut R,
+	) -> Result<Self, Error> {
+		let blinding = blinding.unwrap_or_else(|| F::rand(rng));
+		let private_key = private_key.unwrap_or_else(|| F::rand(rng));
+		Self::new_with_privates(
+			chain_id_raw,
+			amount,
+			unlock_time_raw,
+			index,
+			private_key,
+			blinding,
+			hasher2,
+			hasher6,
+		)
+	}
+
+	#[allow(clippy::too_many_arguments)]
+	pub fn new_with_privates(
+		chain_id_raw: u64,
+		amount: F,
+		unlock_time_raw: u64,
+		index: Option<u64>,
+		private_key: F,
+		blinding: F,
+		hasher2: &Poseidon<F>,
+		hasher6: &Poseidon<F>,
+	) -> Result<Self, Error> {
+		let keypair = Keypair::new(private_key, hasher2);
+		Self::with_keypair(
+			chain_id_raw,
+			amount,
+			unlock_time_raw,
+			index,
+			keypair,
+			blinding,
+			hasher6,
+		)
+	}
+
+	pub fn new_with_public(
+		chain_id_raw: u64,
+		amount: F,
+		unlock_time_raw: u64,
+		index: Option<u64>,
+		public_key: F,
+		blinding: F,
+		hasher6: &Poseidon<F>,
+	) -> Result<Self, Error> {
+		let keypair = Keypair::new_from_public_key(public_key);
+		Self::with_keypair(
+			chain_id_raw,
+			amount,
+			unlock_time_raw,
+			index,
+			keypair,
+			blinding,
+			hasher6,
+		)
+	}
+
+	fn with_keypair(
+		chain_id_raw: u64,
+		amount: F,
+		unlock_time_raw: u64,
+		index: Option<u64>,
+		keypair: Keypair<F, Poseidon<F>>,
+		blinding: F,
+		hasher6: &Poseidon<F>,
+	) -> Result<Self, Error> {
+		let chain_id = F::from(chain_id_raw);
+		let unlock_time = F::from(unlock_time_raw);
+		let commitment =
+			hasher6.hash(&[chain_id, amount, keypair.public_key, blinding, unlock_time])?;
+
+		Ok(Self {
+			chain_id_raw,
+			chain_id,
+			amount,
+			blinding,
+			unlock_time_raw,
+			unlock_time,
+			keypair,
+			index,
+			commitment,
+		})
+	}
+
+	pub fn set_index(&mut self, index: u64) {
+		self.index = Some(index);
+	}
+
+	pub fn calculate_nullifier(&self, hasher4: &Poseidon<F>) -> Result<F, Error> {
+		let index = self.index.ok_or(UtxoError::IndexNotSet)?;
+		let i = F::from(index);
+		let signature = self.keypair.signature(&self.commitment, &i, hasher4)?;
+		let nullifier = hasher4
+			.hash(&[self.commitment, i, signature])
+			.map_err::<UtxoError, _>(|_| UtxoError::NullifierNotCalculated)?;
+		Ok(nullifier)
+	}
+
+	pub fn get_index(&self) -> Result<u64, Error> {
+		self.index.ok_or_else(|| UtxoError::IndexNotSet.into())
+	}
+}
+
+/// A Utxo spent with the signatures of a `MultisigKeypair`. Its commitment is
+/// `Hash(chain_id, amount, pub_key, blinding)` like the one of a `Utxo`, so it
+/// lives in the same trees and can be created as the output of any VAnchor
+/// transaction from the public key of the keypair.
+#[derive(Clone)]
+pub struct MultisigUtxo {
+	pub chain_id_raw: u64,
+	pub chain_id: Fq,
+	pub amount: Fq,
+	pub blinding: Fq,
+	pub keypair: MultisigKeypair,
+	pub index: Option<u64>,
+	pub commitment: Fq,
+}
+
+impl MultisigUtxo {
+	pub fn new(
+		chain_id_raw: u64,
+		amount: Fq,
+		index: Option<u64>,
+		keypair: MultisigKeypair,
+		blinding: Fq,
+		hasher5: &Poseidon<Fq>,
+	) -> Result<Self, Error> {
+		let chain_id = Fq::from(chain_id_raw);
+		let commitment = hasher5.hash(&[chain_id, amount, keypair.public_key, blinding])?;
+
+		Ok(Self {
+			chain_id_raw,
+			chain_id,
+			amount,
+			blinding,
+			keypair,
+			index,
+			commitment,
+		})
+	}
+
+	pub fn set_index(&mut self, index: u64) {
+		self.index = Some(index);
+	}
+
+	pub fn calculate_nullifier(&self, hasher4: &Poseidon<Fq>) -> Result<Fq, Error> {
+		let index = self.index.ok_or(UtxoError::IndexNotSet)?;
+		let i = Fq::from(index);
+		let signature = self.keypair.signature(&self.commitment, &i, hasher4)?;
+		let nullifier = hasher4
+			.hash(&[self.commitment, i, signature])
+			.map_err::<UtxoError, _>(|_| UtxoError::NullifierNotCalculated)?;
+		Ok(nullifier)
+	}
+
+	pub fn get_index(&self) -> Result<u64, Error> {
+		self.index.ok_or_else(|| UtxoError::IndexNotSet.into())
+	}
+}
+
+/// Reads back a 64 bit id, such as a chain id, from a decrypted field element
+fn to_raw_id(value: &Fq) -> Result<u64, Error> {
+	let value = value.into_repr();
+	if value.num_bits() > 64 {
+		return Err(UtxoError::InvalidId.into());
+	}
+	Ok(value.as_ref()[0])
+}
+
+impl Utxo<Fq> {
+	/// Encrypts `[chain_id, amount, pubkey, blinding]` to the Baby Jubjub key
+	/// of the recipient, using a key shared with `ephemeral_key`. The ephemeral
+	/// key must not be reused. `hasher4` must be the Poseidon hash of width 4.
+	pub fn encrypt_poseidon(
+		&self,
+		recipient: &PublicKey,
+		ephemeral_key: &SecretKey,
+		hasher4: &Poseidon<Fq>,
+	) -> Result<PoseidonEncryptedNote, Error> {
+		let shared_key = ephemeral_key.shared_key(recipient)?;
+		let note = [
+			self.chain_id,
+			self.amount,
+			self.keypair.public_key,
+			self.blinding,
+		];
+		let ciphertext = encryption::encrypt(hasher4, &note, &shared_key, Fq::from(0u64))?;
+
+		Ok(PoseidonEncryptedNote {
+			ephemeral_public_key: ephemeral_key.public_key(),
+			ciphertext,
+		})
+	}
+
+	/// Decrypts a note encrypted with `encrypt_poseidon`. The returned Utxo
+	/// only knows the public key of its keypair.
+	pub fn decrypt_poseidon(
+		note: &PoseidonEncryptedNote,
+		secret_key: &SecretKey,
+		hasher4: &Poseidon<Fq>,
+		hasher5: &Poseidon<Fq>,
+	) -> Result<Self, Error> {
+		let shared_key = secret_key.shared_key(&note.ephemeral_public_key)?;
+		let plaintext =
+			encryption::decrypt(hasher4, &note.ciphertext, &shared_key, Fq::from(0u64), 4)?;
+
+		Self::new_with_public(
+			to_raw_id(&plaintext[0])?,
+			plaintext[1],
+			None,
+			plaintext[2],
+			plaintext[3],
+			hasher5,
+		)
+	}
+}
+
+impl MaspUtxo<Fq> {
+	/// Encrypts `[chain_id, asset_id, amount, pubkey, blinding]` to the Baby
+	/// Jubjub key of the recipient, see `Utxo::encrypt_poseidon`
+	pub fn encrypt_poseidon(
+		&self,
+		recipient: &PublicKey,
+		ephemeral_key: &SecretKey,
+		hasher4: &Poseidon<Fq>,
+	) -> Result<PoseidonEncryptedNote, Error> {
+		let shared_key = ephemeral_key.shared_key(recipient)?;
+		let note = [
+			self.chain_id,
+			self.asset_id,
+			self.amount,
+			self.keypair.public_key,
+			self.blinding,
+		];
+		let ciphertext = encryption::encrypt(hasher4, &note, &shared_key, Fq::from(0u64))?;
+
+		Ok(PoseidonEncryptedNote {
+			ephemeral_public_key: ephemeral_key.public_key(),
+			ciphertext,
+		})
+	}
+
+	/// Decrypts a note encrypted with `encrypt_poseidon`. The returned Utxo
+	/// only knows the public key of its keypair.
+	pub fn decrypt_poseidon(
+		note: &PoseidonEncryptedNote,
+		secret_key: &SecretKey,
+		hasher4: &Poseidon<Fq>,
+		hasher6: &Poseidon<Fq>,
+	) -> Result<Self, Error> {
+		let shared_key = secret_key.shared_key(&note.ephemeral_public_key)?;
+		let plaintext =
+			encryption::decrypt(hasher4, &note.ciphertext, &shared_key, Fq::from(0u64), 5)?;
+
+		Self::new_with_public(
+			to_raw_id(&plaintext[0])?,
+			to_raw_id(&plaintext[1])?,
+			plaintext[2],
+			None,
+			plaintext[3],
+			plaintext[4],
+			hasher6,
+		)
+	}
+}
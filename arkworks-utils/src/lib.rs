@@ -4,6 +4,7 @@
 extern crate ark_std;
 
 use ark_ff::PrimeField;
+use ark_std::string::String;
 pub use ark_std::vec::Vec;
 pub use hex::FromHexError;
 
@@ -23,6 +24,12 @@ pub fn decode_hex(s: &str) -> Result<Bytes, FromHexError> {
 	hex::decode(s)
 }
 
+/// Encodes the bytes as a `0x`-prefixed hex string, the format `decode_hex`
+/// reads
+pub fn encode_hex(bytes: &[u8]) -> String {
+	format!("0x{}", hex::encode(bytes))
+}
+
 pub fn parse_vec(arr: Vec<&str>) -> Result<Vec<Bytes>, FromHexError> {
 	let mut res = Vec::new();
 	for r in arr.iter() {
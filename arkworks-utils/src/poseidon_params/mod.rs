@@ -198,6 +198,22 @@ pub fn setup_poseidon_params(
 				EXPONENTIATION,
 			)
 		}
+		#[cfg(feature = "poseidon_bn254_x5_6")]
+		(Curve::Bn254, 5, 6) => {
+			#[path = "./bn254_x5_6.rs"]
+			pub mod bn254_x5_6;
+			use bn254_x5_6::{
+				EXPONENTIATION, FULL_ROUNDS, MDS_ENTRIES, PARTIAL_ROUNDS, ROUND_CONSTS, WIDTH,
+			};
+			get_poseidon_data(
+				ROUND_CONSTS,
+				MDS_ENTRIES,
+				FULL_ROUNDS,
+				PARTIAL_ROUNDS,
+				WIDTH,
+				EXPONENTIATION,
+			)
+		}
 		#[cfg(feature = "poseidon_bls381_x17_3")]
 		(Curve::Bls381, 17, 3) => {
 			#[path = "./bls381_x17_3.rs"]